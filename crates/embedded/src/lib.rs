@@ -0,0 +1,54 @@
+// the protocol crate on a target with `alloc` but no `std`, e.g. a gateway
+// answering RESP over a UART; the firmware provides the global allocator
+#![no_std]
+
+use protocol::{io, lex_frame, resp, RedirsError, RedirsOutput, RedirsValue};
+
+// a sink over a fixed buffer, `write_all` fails with `WriteZero` once it is full
+pub struct SliceSink<'a> {
+    buf: &'a mut [u8],
+    len: usize,
+}
+
+impl<'a> SliceSink<'a> {
+    pub fn new(buf: &'a mut [u8]) -> Self {
+        Self { buf, len: 0 }
+    }
+    pub fn written(&self) -> &[u8] {
+        &self.buf[..self.len]
+    }
+}
+
+impl io::Write for SliceSink<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = buf.len().min(self.buf.len() - self.len);
+        self.buf[self.len..self.len + n].copy_from_slice(&buf[..n]);
+        self.len += n;
+        Ok(n)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+// the reply to the first frame of `input`, returns the bytes the frame spans
+pub fn answer(input: &[u8], out: &mut SliceSink<'_>) -> Result<usize, RedirsError> {
+    let (request, used) = lex_frame(input)?;
+    let name = match &request {
+        RedirsValue::Array(Some(args)) => args.first().and_then(RedirsValue::as_str),
+        _ => None,
+    };
+    let reply = match name {
+        Some("PING") => resp!(simple "PONG"),
+        _ => resp!(error "ERR unknown command"),
+    };
+    reply.write_resp_str(out)?;
+    Ok(used)
+}
+
+// the value in `buf`, `None` when it does not fit
+pub fn encode(value: &RedirsValue, buf: &mut [u8]) -> Option<usize> {
+    let mut sink = SliceSink::new(buf);
+    value.write_resp_str(&mut sink).ok()?;
+    Some(sink.written().len())
+}
@@ -0,0 +1,53 @@
+use criterion::{criterion_group, criterion_main, BatchSize, Criterion, Throughput};
+use protocol::{Cmd, Lexer};
+
+const COMMANDS: usize = 100_000;
+
+// a pipeline of GET commands, their keys are borrowed by `Cmd`, unlike SET
+// which copies the key and value into the command
+fn pipeline() -> Vec<u8> {
+    let mut pipeline = Vec::new();
+    for i in 0..COMMANDS {
+        let key = format!("key:{i}:{}", "x".repeat(32));
+        pipeline.extend_from_slice(
+            format!("*2\r\n$3\r\nGET\r\n${}\r\n{key}\r\n", key.len()).as_bytes(),
+        );
+    }
+    pipeline
+}
+
+// owned against borrowed parsing of the same pipeline into commands, e.g.
+// cargo bench -p protocol --bench parse
+fn parse(c: &mut Criterion) {
+    let pipeline = pipeline();
+    let mut group = c.benchmark_group("pipeline");
+    group.throughput(Throughput::Elements(COMMANDS as u64));
+    group.bench_function("owned", |b| {
+        b.iter_batched(
+            || Lexer::new(&pipeline),
+            |mut lexer| {
+                for _ in 0..COMMANDS {
+                    let value = lexer.lex().unwrap();
+                    Cmd::try_from(&value).expect("a valid command");
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.bench_function("borrowed", |b| {
+        b.iter_batched(
+            || Lexer::new(&pipeline),
+            |mut lexer| {
+                for _ in 0..COMMANDS {
+                    let value = lexer.lex_ref().unwrap();
+                    Cmd::try_from(&value).expect("a valid command");
+                }
+            },
+            BatchSize::SmallInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse);
+criterion_main!(benches);
@@ -0,0 +1,61 @@
+use bytes::BytesMut;
+use criterion::{
+    criterion_group, criterion_main, measurement::WallTime, BatchSize, BenchmarkGroup, BenchmarkId,
+    Criterion, Throughput,
+};
+use protocol::{lex_frame, RedirsValue};
+
+const COMMANDS: usize = 10_000;
+
+// a pipeline of SET commands with values of `len` bytes
+fn pipeline(len: usize) -> BytesMut {
+    let mut pipeline = BytesMut::new();
+    for i in 0..COMMANDS {
+        RedirsValue::from(vec![
+            RedirsValue::from("SET"),
+            RedirsValue::from(format!("key:{i}")),
+            RedirsValue::from("x".repeat(len)),
+        ])
+        .encode_into(&mut pipeline);
+    }
+    pipeline
+}
+
+// lexing the pipeline into copied against shared payloads, e.g.
+// cargo bench -p protocol --bench shared --features bytes. Sharing lexes every
+// frame twice, what it saves grows with the size of the payloads
+fn shared(c: &mut Criterion) {
+    let mut group = c.benchmark_group("pipelined SET");
+    group.throughput(Throughput::Elements(COMMANDS as u64));
+    for len in [64, 1024, 16 * 1024] {
+        bench_len(&mut group, len);
+    }
+    group.finish();
+}
+
+fn bench_len(group: &mut BenchmarkGroup<'_, WallTime>, len: usize) {
+    let pipeline = pipeline(len);
+    group.bench_function(BenchmarkId::new("copied", len), |b| {
+        b.iter(|| {
+            let mut rest = &pipeline[..];
+            while !rest.is_empty() {
+                let (_, used) = lex_frame(rest).unwrap();
+                rest = &rest[used..];
+            }
+        })
+    });
+    group.bench_function(BenchmarkId::new("shared", len), |b| {
+        b.iter_batched(
+            || pipeline.clone(),
+            |mut pipeline| {
+                while !pipeline.is_empty() {
+                    RedirsValue::parse_from(&mut pipeline).unwrap();
+                }
+            },
+            BatchSize::LargeInput,
+        )
+    });
+}
+
+criterion_group!(benches, shared);
+criterion_main!(benches);
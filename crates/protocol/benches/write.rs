@@ -0,0 +1,45 @@
+use std::{
+    io::{self, Read},
+    net::{TcpListener, TcpStream},
+    thread,
+};
+
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use protocol::{RedirsOutput, RedirsValue};
+
+const ELEMENTS: usize = 10_000;
+
+// a loopback connection whose peer drains everything sent, so the writer
+// never blocks on a full socket
+fn loopback() -> io::Result<TcpStream> {
+    let listener = TcpListener::bind("127.0.0.1:0")?;
+    let addr = listener.local_addr()?;
+    thread::spawn(move || {
+        let (mut stream, _) = listener.accept()?;
+        io::copy(&mut stream.by_ref(), &mut io::sink())
+    });
+    TcpStream::connect(addr)
+}
+
+// a big array written straight to a socket with one write per frame against
+// the buffered writer, e.g. cargo bench -p protocol --bench write
+fn write(c: &mut Criterion) {
+    let value = RedirsValue::Array(Some(
+        (0..ELEMENTS)
+            .map(|i| RedirsValue::from(format!("element:{i}")))
+            .collect(),
+    ));
+    let mut stream = loopback().unwrap();
+    let mut group = c.benchmark_group("array to a socket");
+    group.throughput(Throughput::Bytes(value.encoded_len() as u64));
+    group.bench_function("unbuffered", |b| {
+        b.iter(|| value.write_resp_str(&mut stream).unwrap())
+    });
+    group.bench_function("buffered", |b| {
+        b.iter(|| value.write_resp_buffered(&mut stream).unwrap())
+    });
+    group.finish();
+}
+
+criterion_group!(benches, write);
+criterion_main!(benches);
@@ -0,0 +1,17 @@
+use std::io;
+
+use protocol::{read_value, RedirsError};
+
+// reads RESP frames from stdin and prints them as redis-cli does, e.g.
+// printf '*2\r\n+OK\r\n:1\r\n' | cargo run --example pretty
+fn main() -> io::Result<()> {
+    let mut stdin = io::stdin().lock();
+    loop {
+        match read_value(&mut stdin) {
+            Ok(value) => println!("{value}"),
+            Err(RedirsError::Eof) => return Ok(()),
+            Err(RedirsError::Io(e)) => return Err(e),
+            Err(e) => return Err(io::Error::new(io::ErrorKind::InvalidData, e)),
+        }
+    }
+}
@@ -0,0 +1,31 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use protocol::{split_args, Cmd, Lexer, RedirsOutput};
+
+// arbitrary bytes through every entry point, run with `cargo +nightly fuzz run lex`
+// from crates/protocol, the seeds in corpus/lex cover the tricky framing cases
+fuzz_target!(|data: &[u8]| {
+    let mut lexer = Lexer::new(data);
+    loop {
+        match lexer.lex_request_ref() {
+            Ok(value) => {
+                let _ = Cmd::try_from(&value);
+                // whatever lexes must survive a round trip unchanged
+                let value = value.into_owned();
+                let mut out = Vec::new();
+                value.write_resp_str(&mut out).unwrap();
+                assert_eq!(Lexer::new(&out).lex().unwrap(), value);
+            }
+            Err(e) => {
+                let _ = (e.to_string(), e.to_client_error());
+                break;
+            }
+        }
+    }
+    // skipping a frame must consume exactly what lexing it does
+    let (mut lexed, mut skipped) = (Lexer::new(data), Lexer::new(data));
+    assert_eq!(lexed.lex().is_ok(), skipped.skip_value().is_ok());
+    assert_eq!(lexed.position(), skipped.position());
+    let _ = split_args(data);
+});
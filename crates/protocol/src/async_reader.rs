@@ -0,0 +1,73 @@
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use crate::{
+    lex_frame,
+    reader::{check_pending_line, unexpected_eof},
+    RedirsError, RedirsValue, MAX_RESERVE,
+};
+
+// the most bytes read at once while a line is pending
+const LINE_READ_LEN: u64 = 8 * 1024;
+
+// buffers bytes read from `inner` across `.await` points, so frames split over
+// any number of reads are lexed once complete
+pub struct RespReader<R> {
+    inner: R,
+    buffer: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> RespReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self {
+            inner,
+            buffer: Vec::new(),
+        }
+    }
+    pub fn get_ref(&self) -> &R {
+        &self.inner
+    }
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.inner
+    }
+    // bytes read from `inner` but not yet lexed into a value
+    pub fn buffered(&self) -> &[u8] {
+        &self.buffer
+    }
+    // cancel safe: bytes read before a cancelled call stay buffered. A line
+    // that does not end within the inline limit is an error rather than a
+    // buffer that keeps growing
+    pub async fn read_value(&mut self) -> Result<RedirsValue, RedirsError> {
+        loop {
+            // reads while waiting for the end of a line are kept small, so the
+            // buffer stops close to the limit whatever the peer sends at once
+            let mut limit = u64::MAX;
+            if !self.buffer.is_empty() {
+                match lex_frame(&self.buffer) {
+                    Ok((value, used)) => {
+                        self.buffer.drain(..used);
+                        return Ok(value);
+                    }
+                    Err(RedirsError::Incomplete(Some(missing))) => {
+                        self.buffer.reserve(missing.min(MAX_RESERVE))
+                    }
+                    Err(RedirsError::Incomplete(None)) => {
+                        check_pending_line(&self.buffer)?;
+                        limit = LINE_READ_LEN;
+                    }
+                    Err(e) => return Err(e),
+                }
+            }
+            let read = (&mut self.inner)
+                .take(limit)
+                .read_buf(&mut self.buffer)
+                .await
+                .map_err(RedirsError::Io)?;
+            if read == 0 {
+                return Err(match self.buffer.is_empty() {
+                    true => RedirsError::Eof,
+                    false => unexpected_eof(),
+                });
+            }
+        }
+    }
+}
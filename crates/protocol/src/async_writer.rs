@@ -0,0 +1,95 @@
+use std::io::{self, Write};
+
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::{ProcVersion, RedirsValue, SPACER};
+
+// frames are formatted into a buffer of this size that is written out once
+// full, bulk payloads at least as big skip it and are written from the value
+const BUFFER_LEN: usize = 8 * 1024;
+
+// the values of an aggregate that are still to be written
+type Elements<'a> = Box<dyn Iterator<Item = &'a RedirsValue> + Send + 'a>;
+
+impl RedirsValue {
+    // streams the value through a small buffer: the sync writer formats the
+    // scalar frames, partial writes are retried by `write_all` so a slow sink
+    // only delays the reply, not cancel safe: a dropped call may leave a
+    // partial frame on the sink
+    pub async fn write_resp_async<W: AsyncWrite + Unpin>(
+        &self,
+        out: &mut W,
+        version: ProcVersion,
+    ) -> io::Result<()> {
+        let mut buffer = Vec::with_capacity(self.encoded_len().min(BUFFER_LEN));
+        // an explicit stack instead of recursion, async fns cannot recurse
+        // without boxing every level
+        let mut stack: Vec<Elements<'_>> = vec![Box::new(std::iter::once(self))];
+        while let Some(elements) = stack.last_mut() {
+            let Some(value) = elements.next() else {
+                stack.pop();
+                continue;
+            };
+            match (aggregate(value, &mut buffer, version)?, value) {
+                (Some(elements), _) => stack.push(elements),
+                (None, RedirsValue::BulkString(Some(payload))) if payload.len() >= BUFFER_LEN => {
+                    write!(buffer, "${}{SPACER}", payload.len())?;
+                    out.write_all(&buffer).await?;
+                    buffer.clear();
+                    out.write_all(payload).await?;
+                    buffer.extend_from_slice(SPACER.as_bytes());
+                }
+                (None, value) => value.write_resp(&mut buffer, version)?,
+            }
+            if buffer.len() >= BUFFER_LEN {
+                out.write_all(&buffer).await?;
+                buffer.clear();
+            }
+        }
+        out.write_all(&buffer).await
+    }
+}
+
+// writes the header of an aggregate the way `write_resp` does and returns the
+// values that follow it, nothing is written for any other frame
+fn aggregate<'a>(
+    value: &'a RedirsValue,
+    out: &mut Vec<u8>,
+    version: ProcVersion,
+) -> io::Result<Option<Elements<'a>>> {
+    let v3 = version == ProcVersion::V3;
+    Ok(Some(match value {
+        RedirsValue::Array(Some(vals)) => {
+            write!(out, "*{}{SPACER}", vals.len())?;
+            Box::new(vals.iter())
+        }
+        RedirsValue::Push(vals) => {
+            write!(out, "{}{}{SPACER}", if v3 { '>' } else { '*' }, vals.len())?;
+            Box::new(vals.iter())
+        }
+        RedirsValue::Set(set) => {
+            write!(out, "{}{}{SPACER}", if v3 { '~' } else { '*' }, set.len())?;
+            Box::new(set.iter())
+        }
+        RedirsValue::Map(map) if v3 => {
+            write!(out, "%{}{SPACER}", map.len())?;
+            Box::new(map.iter().flat_map(|(k, v)| [k, v]))
+        }
+        RedirsValue::Map(map) => {
+            write!(out, "*{}{SPACER}", map.len() * 2)?;
+            Box::new(map.iter().flat_map(|(k, v)| [k, v]))
+        }
+        RedirsValue::Attribute(attrs, value) if v3 => {
+            write!(out, "|{}{SPACER}", attrs.len())?;
+            Box::new(
+                attrs
+                    .iter()
+                    .flat_map(|(k, v)| [k, v])
+                    .chain(std::iter::once(&**value)),
+            )
+        }
+        // there is no out of band data in RESP2
+        RedirsValue::Attribute(_, value) => Box::new(std::iter::once(&**value)),
+        _ => return Ok(None),
+    }))
+}
@@ -0,0 +1,65 @@
+use bytes::{BufMut, BytesMut};
+use tokio_util::codec::{Decoder, Encoder};
+
+#[cfg(not(feature = "bytes"))]
+use crate::lex_frame;
+use crate::{RedirsError, RedirsOutput, RedirsValue, MAX_RESERVE};
+
+#[derive(Debug, Default)]
+pub struct RespCodec {
+    // buffer length below which the pending frame is known to be incomplete,
+    // avoids re-lexing a big bulk string for every small chunk that arrives
+    wanted: usize,
+}
+
+impl RespCodec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+// with the `bytes` feature the payloads share the read buffer
+#[cfg(feature = "bytes")]
+fn split_frame(src: &mut BytesMut) -> Result<RedirsValue, RedirsError> {
+    RedirsValue::parse_from(src)
+}
+#[cfg(not(feature = "bytes"))]
+fn split_frame(src: &mut BytesMut) -> Result<RedirsValue, RedirsError> {
+    use bytes::Buf;
+
+    let (value, used) = lex_frame(src)?;
+    src.advance(used);
+    Ok(value)
+}
+
+impl Decoder for RespCodec {
+    type Item = RedirsValue;
+    type Error = RedirsError;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<RedirsValue>, RedirsError> {
+        if src.is_empty() || src.len() < self.wanted {
+            return Ok(None);
+        }
+        match split_frame(src) {
+            Ok(value) => {
+                self.wanted = 0;
+                Ok(Some(value))
+            }
+            Err(RedirsError::Incomplete(missing)) => {
+                let missing = missing.unwrap_or_default();
+                self.wanted = src.len().saturating_add(missing);
+                src.reserve(missing.min(MAX_RESERVE));
+                Ok(None)
+            }
+            Err(e) => Err(e),
+        }
+    }
+}
+
+impl Encoder<RedirsValue> for RespCodec {
+    type Error = RedirsError;
+
+    fn encode(&mut self, item: RedirsValue, dst: &mut BytesMut) -> Result<(), RedirsError> {
+        Ok(item.write_resp_str(&mut dst.writer())?)
+    }
+}
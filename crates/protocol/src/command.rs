@@ -0,0 +1,455 @@
+use std::{
+    borrow::Cow,
+    fmt::Display,
+    io::{self, Write},
+    time::{Duration, SystemTime},
+};
+
+use crate::{
+    Action, Cmd, Expiration, HelloCmd, HelloReply, KeyValue, ProcVersion, RedirsOutput,
+    RedirsValue, RedirsValueRef, SetCondition, SetOptions, System,
+};
+
+// limit on the argument text quoted back in an unknown command error, as redis does
+const UNKNOWN_ARGS_LEN: usize = 128;
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum CommandError {
+    // the request is not a non empty array of bulk strings
+    InvalidRequest,
+    UnknownCommand { name: String, args: Vec<String> },
+    // carries the lowercase command name
+    WrongArity(&'static str),
+    SyntaxError,
+    NotAnInteger,
+    // carries the lowercase command name
+    InvalidExpireTime(&'static str),
+    // HELLO with a protocol version that is not a number
+    InvalidProtocolVersion,
+    // HELLO with a protocol version other than 2 and 3
+    NoProto,
+    // a HELLO option that is unknown or misses its arguments
+    HelloSyntaxError(String),
+}
+
+impl Display for CommandError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CommandError::InvalidRequest => f.write_str("ERR Protocol error: expected '$'"),
+            CommandError::UnknownCommand { name, args } => {
+                write!(
+                    f,
+                    "ERR unknown command '{name}', with args beginning with: "
+                )?;
+                let mut len = 0;
+                for arg in args {
+                    if len >= UNKNOWN_ARGS_LEN {
+                        break;
+                    }
+                    let arg: String = arg.chars().take(UNKNOWN_ARGS_LEN - len).collect();
+                    // the quotes and the separator count towards the limit too
+                    len += arg.len() + 3;
+                    write!(f, "'{arg}' ")?;
+                }
+                Ok(())
+            }
+            CommandError::WrongArity(name) => {
+                write!(f, "ERR wrong number of arguments for '{name}' command")
+            }
+            CommandError::SyntaxError => f.write_str("ERR syntax error"),
+            CommandError::NotAnInteger => {
+                f.write_str("ERR value is not an integer or out of range")
+            }
+            CommandError::InvalidExpireTime(name) => {
+                write!(f, "ERR invalid expire time in '{name}' command")
+            }
+            CommandError::InvalidProtocolVersion => {
+                f.write_str("ERR Protocol version is not an integer or out of range")
+            }
+            CommandError::NoProto => f.write_str("NOPROTO unsupported protocol version"),
+            CommandError::HelloSyntaxError(opt) => {
+                write!(f, "ERR Syntax error in HELLO option '{opt}'")
+            }
+        }
+    }
+}
+
+impl CommandError {
+    // the error reply a server sends back for this error
+    pub fn to_client_error(&self) -> RedirsValue {
+        RedirsValue::SimpleError(self.to_string())
+    }
+}
+
+fn arity(name: &'static str, args: &[&[u8]], expected: usize) -> Result<(), CommandError> {
+    match args.len() == expected {
+        true => Ok(()),
+        false => Err(CommandError::WrongArity(name)),
+    }
+}
+
+// key [key ...]
+fn keys<'a>(name: &'static str, args: &[&'a [u8]]) -> Result<Vec<&'a [u8]>, CommandError> {
+    match args.is_empty() {
+        true => Err(CommandError::WrongArity(name)),
+        false => Ok(args.to_vec()),
+    }
+}
+
+// key value [key value ...]
+fn pairs<'a>(name: &'static str, args: &[&'a [u8]]) -> Result<Vec<KeyValue<'a>>, CommandError> {
+    match args.is_empty() || !args.len().is_multiple_of(2) {
+        true => Err(CommandError::WrongArity(name)),
+        false => Ok(args.chunks(2).map(|pair| (pair[0], pair[1])).collect()),
+    }
+}
+
+// HELLO [protover [AUTH username password] [SETNAME clientname]], the options
+// are accepted in any order
+fn parse_hello<'a>(args: &[&'a [u8]]) -> Result<HelloCmd<'a>, CommandError> {
+    let mut hello = HelloCmd {
+        version: None,
+        auth: None,
+        client_name: None,
+    };
+    let Some((protover, mut opts)) = args.split_first() else {
+        return Ok(hello);
+    };
+    hello.version = Some(match parse_int(protover) {
+        Some(2) => ProcVersion::V2,
+        Some(3) => ProcVersion::V3,
+        Some(_) => return Err(CommandError::NoProto),
+        None => return Err(CommandError::InvalidProtocolVersion),
+    });
+    loop {
+        opts = match opts {
+            [] => return Ok(hello),
+            [opt, user, pass, rest @ ..] if opt.eq_ignore_ascii_case(b"auth") => {
+                hello.auth = Some((Cow::Borrowed(*user), Cow::Borrowed(*pass)));
+                rest
+            }
+            [opt, name, rest @ ..] if opt.eq_ignore_ascii_case(b"setname") => {
+                hello.client_name = Some(Cow::Borrowed(*name));
+                rest
+            }
+            [opt, ..] => {
+                return Err(CommandError::HelloSyntaxError(
+                    String::from_utf8_lossy(opt).into_owned(),
+                ))
+            }
+        }
+    }
+}
+
+// an integer argument, `i64::from_str` accepts an optional sign and rejects overflow
+fn parse_int(arg: &[u8]) -> Option<i64> {
+    std::str::from_utf8(arg).ok()?.parse().ok()
+}
+
+// the time of an EX, PX, EXAT or PXAT option, which must be a positive integer
+fn parse_expiration(
+    name: &'static str,
+    opt: &[u8],
+    value: &[u8],
+) -> Result<Expiration, CommandError> {
+    let value = parse_int(value).ok_or(CommandError::NotAnInteger)?;
+    let invalid = CommandError::InvalidExpireTime(name);
+    if value <= 0 {
+        return Err(invalid);
+    }
+    let opt = opt.to_ascii_lowercase();
+    let millis = match opt.as_slice() {
+        b"ex" | b"exat" => value.checked_mul(1000).ok_or(invalid)?,
+        _ => value,
+    };
+    let time = Duration::from_millis(millis as u64);
+    Ok(match opt.as_slice() {
+        b"ex" | b"px" => Expiration::In(time),
+        _ => Expiration::At(SystemTime::UNIX_EPOCH + time),
+    })
+}
+
+// [NX | XX] [GET] [EX seconds | PX milliseconds | EXAT unix-time-seconds |
+// PXAT unix-time-milliseconds | KEEPTTL], in any order
+fn parse_set_options(args: &[&[u8]]) -> Result<SetOptions, CommandError> {
+    let mut options = SetOptions::default();
+    let mut args = args;
+    loop {
+        args = match args {
+            [] => return Ok(options),
+            [opt, rest @ ..]
+                if opt.eq_ignore_ascii_case(b"nx")
+                    && options.condition != Some(SetCondition::XX) =>
+            {
+                options.condition = Some(SetCondition::NX);
+                rest
+            }
+            [opt, rest @ ..]
+                if opt.eq_ignore_ascii_case(b"xx")
+                    && options.condition != Some(SetCondition::NX) =>
+            {
+                options.condition = Some(SetCondition::XX);
+                rest
+            }
+            [opt, rest @ ..] if opt.eq_ignore_ascii_case(b"get") => {
+                options.get = true;
+                rest
+            }
+            [opt, rest @ ..]
+                if opt.eq_ignore_ascii_case(b"keepttl")
+                    && !options.keep_ttl
+                    && options.expire.is_none() =>
+            {
+                options.keep_ttl = true;
+                rest
+            }
+            [opt, value, rest @ ..]
+                if [&b"ex"[..], b"px", b"exat", b"pxat"]
+                    .iter()
+                    .any(|o| opt.eq_ignore_ascii_case(o))
+                    && !options.keep_ttl
+                    && options.expire.is_none() =>
+            {
+                options.expire = Some(parse_expiration("set", opt, value)?);
+                rest
+            }
+            _ => return Err(CommandError::SyntaxError),
+        }
+    }
+}
+
+impl<'a> TryFrom<&'a RedirsValue> for Cmd<'a> {
+    type Error = CommandError;
+
+    // a client sends commands as an array of bulk strings, the command name
+    // first, matched case insensitively
+    fn try_from(value: &'a RedirsValue) -> Result<Self, CommandError> {
+        let RedirsValue::Array(Some(arr)) = value else {
+            return Err(CommandError::InvalidRequest);
+        };
+        let args = arr
+            .iter()
+            .map(|arg| match arg {
+                RedirsValue::BulkString(Some(arg)) => Ok(&arg[..]),
+                _ => Err(CommandError::InvalidRequest),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        parse_command(&args)
+    }
+}
+
+impl<'a> TryFrom<&'a RedirsValueRef<'_>> for Cmd<'a> {
+    type Error = CommandError;
+
+    fn try_from(value: &'a RedirsValueRef<'_>) -> Result<Self, CommandError> {
+        let RedirsValueRef::Array(Some(arr)) = value else {
+            return Err(CommandError::InvalidRequest);
+        };
+        let args = arr
+            .iter()
+            .map(|arg| arg.as_bulk().ok_or(CommandError::InvalidRequest))
+            .collect::<Result<Vec<_>, _>>()?;
+        parse_command(&args)
+    }
+}
+
+fn parse_command<'a>(args: &[&'a [u8]]) -> Result<Cmd<'a>, CommandError> {
+    let (name, args) = args.split_first().ok_or(CommandError::InvalidRequest)?;
+    match name.to_ascii_lowercase().as_slice() {
+        b"get" => {
+            arity("get", args, 1)?;
+            Ok(Cmd::Action(Action::GET(args[0])))
+        }
+        b"set" => match args {
+            [key, value, opts @ ..] => Ok(Cmd::Action(Action::SET((
+                key.to_vec(),
+                RedirsValue::from(*value),
+                parse_set_options(opts)?,
+            )))),
+            _ => Err(CommandError::WrongArity("set")),
+        },
+        b"del" => Ok(Cmd::Action(Action::DEL(keys("del", args)?))),
+        b"unlink" => Ok(Cmd::Action(Action::UNLINK(keys("unlink", args)?))),
+        b"exists" => Ok(Cmd::Action(Action::EXISTS(keys("exists", args)?))),
+        b"mget" => Ok(Cmd::Action(Action::MGET(keys("mget", args)?))),
+        b"mset" => Ok(Cmd::Action(Action::MSET(pairs("mset", args)?))),
+        b"msetnx" => Ok(Cmd::Action(Action::MSETNX(pairs("msetnx", args)?))),
+        b"ping" => match args {
+            [] => Ok(Cmd::System(System::PING(None))),
+            [message] => Ok(Cmd::System(System::PING(Some(message)))),
+            _ => Err(CommandError::WrongArity("ping")),
+        },
+        b"echo" => {
+            arity("echo", args, 1)?;
+            Ok(Cmd::System(System::ECHO(args[0])))
+        }
+        b"hello" => Ok(Cmd::System(System::HELLO(parse_hello(args)?))),
+        _ => Err(CommandError::UnknownCommand {
+            name: String::from_utf8_lossy(name).into_owned(),
+            args: args
+                .iter()
+                .map(|arg| String::from_utf8_lossy(arg).into_owned())
+                .collect(),
+        }),
+    }
+}
+
+// a command as a client sends it, an array of bulk strings
+fn write_command<T: Write>(out: &mut T, args: &[Cow<'_, [u8]>]) -> io::Result<()> {
+    write!(out, "*{}\r\n", args.len())?;
+    args.iter().try_for_each(|arg| {
+        write!(out, "${}\r\n", arg.len())?;
+        out.write_all(arg)?;
+        out.write_all(b"\r\n")
+    })
+}
+
+fn number(n: impl Display) -> Cow<'static, [u8]> {
+    Cow::Owned(n.to_string().into_bytes())
+}
+
+// EX and EXAT for whole seconds, PX and PXAT otherwise
+fn expiration_args(expire: &Expiration) -> [Cow<'static, [u8]>; 2] {
+    let (millis, relative) = match expire {
+        Expiration::In(time) => (time.as_millis(), true),
+        Expiration::At(time) => (
+            time.duration_since(SystemTime::UNIX_EPOCH)
+                .unwrap_or_default()
+                .as_millis(),
+            false,
+        ),
+    };
+    let opt: &'static [u8] = match (millis % 1000 == 0, relative) {
+        (true, true) => b"EX",
+        (true, false) => b"EXAT",
+        (false, true) => b"PX",
+        (false, false) => b"PXAT",
+    };
+    match millis % 1000 {
+        0 => [Cow::Borrowed(opt), number(millis / 1000)],
+        _ => [Cow::Borrowed(opt), number(millis)],
+    }
+}
+
+fn keys_args<'a>(name: &'static [u8], keys: &[&'a [u8]]) -> Vec<Cow<'a, [u8]>> {
+    let keys = keys.iter().map(|key| Cow::Borrowed(*key));
+    std::iter::once(Cow::Borrowed(name)).chain(keys).collect()
+}
+
+fn pairs_args<'a>(name: &'static [u8], pairs: &[KeyValue<'a>]) -> Vec<Cow<'a, [u8]>> {
+    let pairs = pairs
+        .iter()
+        .flat_map(|(key, value)| [Cow::Borrowed(*key), Cow::Borrowed(*value)]);
+    std::iter::once(Cow::Borrowed(name)).chain(pairs).collect()
+}
+
+impl RedirsOutput for Action<'_> {
+    fn write_resp_str<T: Write>(&self, out: &mut T) -> io::Result<()> {
+        let args = match self {
+            Action::GET(key) => vec![Cow::Borrowed(&b"GET"[..]), Cow::Borrowed(*key)],
+            Action::SET((key, value, options)) => {
+                let value = match value.as_bytes() {
+                    Some(value) => Cow::Borrowed(value),
+                    None => Cow::Owned(value.to_resp_bytes()),
+                };
+                let mut args = vec![Cow::Borrowed(&b"SET"[..]), Cow::Borrowed(&key[..]), value];
+                match options.condition {
+                    Some(SetCondition::NX) => args.push(Cow::Borrowed(b"NX")),
+                    Some(SetCondition::XX) => args.push(Cow::Borrowed(b"XX")),
+                    None => {}
+                }
+                if options.get {
+                    args.push(Cow::Borrowed(b"GET"));
+                }
+                if let Some(expire) = &options.expire {
+                    args.extend(expiration_args(expire));
+                }
+                if options.keep_ttl {
+                    args.push(Cow::Borrowed(b"KEEPTTL"));
+                }
+                args
+            }
+            Action::DEL(keys) => keys_args(b"DEL", keys),
+            Action::UNLINK(keys) => keys_args(b"UNLINK", keys),
+            Action::EXISTS(keys) => keys_args(b"EXISTS", keys),
+            Action::MGET(keys) => keys_args(b"MGET", keys),
+            Action::MSET(pairs) => pairs_args(b"MSET", pairs),
+            Action::MSETNX(pairs) => pairs_args(b"MSETNX", pairs),
+        };
+        write_command(out, &args)
+    }
+}
+
+impl RedirsOutput for System<'_> {
+    fn write_resp_str<T: Write>(&self, out: &mut T) -> io::Result<()> {
+        let args = match self {
+            System::PING(None) => vec![Cow::Borrowed(&b"PING"[..])],
+            System::PING(Some(message)) => {
+                vec![Cow::Borrowed(&b"PING"[..]), Cow::Borrowed(*message)]
+            }
+            System::ECHO(message) => vec![Cow::Borrowed(&b"ECHO"[..]), Cow::Borrowed(*message)],
+            System::HELLO(hello) => {
+                let mut args = vec![Cow::Borrowed(&b"HELLO"[..])];
+                match hello.version {
+                    Some(ProcVersion::V2) => args.push(Cow::Borrowed(b"2")),
+                    Some(ProcVersion::V3) => args.push(Cow::Borrowed(b"3")),
+                    None => {}
+                }
+                if let Some((user, pass)) = &hello.auth {
+                    args.extend([Cow::Borrowed(&b"AUTH"[..]), user.clone(), pass.clone()]);
+                }
+                if let Some(name) = &hello.client_name {
+                    args.extend([Cow::Borrowed(&b"SETNAME"[..]), name.clone()]);
+                }
+                args
+            }
+        };
+        write_command(out, &args)
+    }
+}
+
+impl RedirsOutput for Cmd<'_> {
+    // the canonical uppercase spelling, which the parser reads back as the same command
+    fn write_resp_str<T: Write>(&self, out: &mut T) -> io::Result<()> {
+        match self {
+            Cmd::System(system) => system.write_resp_str(out),
+            Cmd::Action(action) => action.write_resp_str(out),
+        }
+    }
+}
+
+impl HelloReply {
+    // a standalone master running this crate's version
+    pub fn new(proto: ProcVersion, id: i64) -> Self {
+        Self {
+            server: "redirs".to_owned(),
+            version: env!("CARGO_PKG_VERSION").to_owned(),
+            proto,
+            id,
+            mode: "standalone".to_owned(),
+            role: "master".to_owned(),
+        }
+    }
+    pub fn into_value(self) -> RedirsValue {
+        let proto = match self.proto {
+            ProcVersion::V2 => 2,
+            ProcVersion::V3 => 3,
+        };
+        let pairs = [
+            ("server", RedirsValue::from(self.server)),
+            ("version", RedirsValue::from(self.version)),
+            ("proto", RedirsValue::Integer(proto)),
+            ("id", RedirsValue::Integer(self.id)),
+            ("mode", RedirsValue::from(self.mode)),
+            ("role", RedirsValue::from(self.role)),
+            ("modules", RedirsValue::Array(Some(Vec::new()))),
+        ]
+        .map(|(key, value)| (RedirsValue::from(key), value));
+        match self.proto {
+            ProcVersion::V2 => {
+                RedirsValue::Array(Some(pairs.into_iter().flat_map(|(k, v)| [k, v]).collect()))
+            }
+            ProcVersion::V3 => RedirsValue::Map(pairs.into_iter().collect()),
+        }
+    }
+}
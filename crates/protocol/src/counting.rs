@@ -0,0 +1,69 @@
+use std::io::{self, Write};
+
+use crate::{ProcVersion, RedirsOutput, RedirsValue};
+
+// forwards writes to `inner`, counting the bytes it accepted and the frames
+// written through `write_frame`, e.g. for INFO's `total_net_output_bytes`.
+// Wrapped in a `BufWriter` it counts what reached the sink, wrapping one it
+// counts what was buffered
+pub struct CountingWriter<W> {
+    inner: W,
+    bytes: u64,
+    frames: u64,
+}
+
+impl<W: Write> CountingWriter<W> {
+    pub fn new(inner: W) -> Self {
+        Self {
+            inner,
+            bytes: 0,
+            frames: 0,
+        }
+    }
+    pub fn bytes_written(&self) -> u64 {
+        self.bytes
+    }
+    pub fn frames_written(&self) -> u64 {
+        self.frames
+    }
+    // the hook for serializers writing through `Write` directly, called once
+    // a whole top level value is written
+    pub fn count_frame(&mut self) {
+        self.frames += 1;
+    }
+    // writes a top level value, returns the bytes it took. A value that fails
+    // midway is not counted as a frame, its bytes that made it are
+    pub fn write_frame(&mut self, value: &impl RedirsOutput) -> io::Result<u64> {
+        let start = self.bytes;
+        value.write_resp_str(self)?;
+        self.count_frame();
+        Ok(self.bytes - start)
+    }
+    // `write_frame` for a client speaking `version`
+    pub fn write_value(&mut self, value: &RedirsValue, version: ProcVersion) -> io::Result<u64> {
+        let start = self.bytes;
+        value.write_resp(self, version)?;
+        self.count_frame();
+        Ok(self.bytes - start)
+    }
+    pub fn get_ref(&self) -> &W {
+        &self.inner
+    }
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.inner
+    }
+    pub fn into_inner(self) -> W {
+        self.inner
+    }
+}
+
+impl<W: Write> Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.bytes += n as u64;
+        Ok(n)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
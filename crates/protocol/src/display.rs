@@ -0,0 +1,126 @@
+use alloc::{
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::{Display, Formatter, Result, Write};
+
+use crate::{DoubleText, RedirsMap, RedirsValue, Sign};
+
+// payloads quoted and escaped the way redis-cli prints them, bytes that are
+// not printable ASCII as `\xHH`
+struct Quoted<'a>(&'a [u8]);
+
+impl Display for Quoted<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        f.write_char('"')?;
+        for &b in self.0 {
+            match b {
+                b'\\' => f.write_str("\\\\")?,
+                b'"' => f.write_str("\\\"")?,
+                b'\n' => f.write_str("\\n")?,
+                b'\r' => f.write_str("\\r")?,
+                b'\t' => f.write_str("\\t")?,
+                0x07 => f.write_str("\\a")?,
+                0x08 => f.write_str("\\b")?,
+                b' '..=b'~' => f.write_char(b as char)?,
+                b => write!(f, "\\x{b:02x}")?,
+            }
+        }
+        f.write_char('"')
+    }
+}
+
+// each element on a numbered line, the lines after the first indented by
+// `indent` since the first continues the line of the parent
+fn write_items(f: &mut Formatter<'_>, indent: usize, marker: char, items: Vec<String>) -> Result {
+    let width = items.len().to_string().len();
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            write!(f, "\n{:indent$}", "")?;
+        }
+        write!(f, "{:>width$}{marker} {item}", i + 1)?;
+    }
+    Ok(())
+}
+
+// where the elements of an aggregate with `len` elements start
+fn nested(indent: usize, len: usize) -> usize {
+    indent + len.to_string().len() + 2
+}
+
+fn pairs(map: &RedirsMap, indent: usize) -> Vec<String> {
+    let indent = nested(indent, map.len());
+    map.iter()
+        .map(|(k, v)| format!("{} => {}", k.pretty(indent), v.pretty(indent)))
+        .collect()
+}
+
+struct Pretty<'a>(&'a RedirsValue, usize);
+
+impl Display for Pretty<'_> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        let Pretty(value, indent) = *self;
+        match value {
+            // status replies are printed bare, as redis-cli does
+            RedirsValue::SimpleString(s) => f.write_str(s),
+            RedirsValue::SimpleError(s) => write!(f, "(error) {s}"),
+            RedirsValue::BulkError(s) => write!(f, "(error) {}", String::from_utf8_lossy(s)),
+            RedirsValue::Integer(i) => write!(f, "(integer) {i}"),
+            RedirsValue::BulkString(Some(s)) => Quoted(s).fmt(f),
+            RedirsValue::BulkString(None) | RedirsValue::Array(None) | RedirsValue::Null => {
+                f.write_str("(nil)")
+            }
+            RedirsValue::Bool(b) => write!(f, "({b})"),
+            RedirsValue::Double(d) => write!(f, "(double) {}", DoubleText(*d)),
+            // redis-cli leaves out the sign of positive numbers
+            RedirsValue::BigNumber(Sign::Positive, digits) => write!(f, "(big number) {digits}"),
+            RedirsValue::BigNumber(Sign::Negative, digits) => write!(f, "(big number) -{digits}"),
+            RedirsValue::VerbatimString(enc, s) => {
+                write!(f, "(verbatim {}) {}", enc.tag().escape_ascii(), Quoted(s))
+            }
+            RedirsValue::Array(Some(vals)) if vals.is_empty() => f.write_str("(empty array)"),
+            RedirsValue::Push(vals) if vals.is_empty() => f.write_str("(empty push)"),
+            RedirsValue::Array(Some(vals)) | RedirsValue::Push(vals) => {
+                let nested = nested(indent, vals.len());
+                write_items(
+                    f,
+                    indent,
+                    ')',
+                    vals.iter().map(|v| v.pretty(nested)).collect(),
+                )
+            }
+            RedirsValue::Map(map) if map.is_empty() => f.write_str("(empty hash)"),
+            RedirsValue::Map(map) => write_items(f, indent, '#', pairs(map, indent)),
+            RedirsValue::Set(set) if set.is_empty() => f.write_str("(empty set)"),
+            RedirsValue::Set(set) => {
+                // sorted, the iteration order of a `HashSet` changes from run to run
+                let nested = nested(indent, set.len());
+                let mut items: Vec<_> = set.iter().map(|v| v.pretty(nested)).collect();
+                items.sort();
+                write_items(f, indent, '~', items)
+            }
+            // the attributes on `|` lines above the value
+            RedirsValue::Attribute(attrs, value) => {
+                f.write_char('|')?;
+                write_items(f, indent + 1, '#', pairs(attrs, indent + 1))?;
+                write!(f, "\n{:indent$}{}", "", value.pretty(indent))
+            }
+        }
+    }
+}
+
+impl RedirsValue {
+    fn pretty(&self, indent: usize) -> String {
+        Pretty(self, indent).to_string()
+    }
+}
+
+// renders the value as redis-cli prints replies, e.g.
+// 1) "key"
+// 2) (integer) 42
+impl Display for RedirsValue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> Result {
+        Pretty(self, 0).fmt(f)
+    }
+}
@@ -0,0 +1,348 @@
+use alloc::{
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec::Vec,
+};
+use core::fmt::Display;
+#[cfg(feature = "std")]
+use std::{
+    collections::{HashMap, HashSet},
+    hash::Hash,
+};
+
+use crate::{from_payload, FrameKind, RedirsValue};
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum FromRespError {
+    // the server replied with an error, carries its message
+    Reply(String),
+    // a reply of the wrong type for the target
+    TypeMismatch {
+        expected: &'static str,
+        found: FrameKind,
+    },
+    // an aggregate with the wrong number of elements for a tuple
+    Length {
+        expected: usize,
+        found: usize,
+    },
+    // a reply of the right type that does not hold a valid target value,
+    // e.g. a bulk string that is not a number
+    Invalid {
+        expected: &'static str,
+        found: String,
+    },
+}
+
+impl Display for FromRespError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FromRespError::Reply(message) => write!(f, "error reply: {message}"),
+            FromRespError::TypeMismatch { expected, found } => {
+                write!(f, "expected {expected}, found {found:?}")
+            }
+            FromRespError::Length { expected, found } => {
+                write!(f, "expected {expected} elements, found {found}")
+            }
+            FromRespError::Invalid { expected, found } => {
+                write!(f, "expected {expected}, found \"{found}\"")
+            }
+        }
+    }
+}
+
+impl core::error::Error for FromRespError {}
+
+// converts a decoded reply into a rust type, error replies convert into
+// `FromRespError::Reply` whatever the target type, attributes are skipped
+pub trait FromResp: Sized {
+    fn from_resp(value: RedirsValue) -> Result<Self, FromRespError>;
+}
+
+impl RedirsValue {
+    pub fn convert<T: FromResp>(self) -> Result<T, FromRespError> {
+        T::from_resp(self)
+    }
+}
+
+// the reply with attributes removed, error replies turned into errors
+fn reply(value: RedirsValue) -> Result<RedirsValue, FromRespError> {
+    match value {
+        RedirsValue::Attribute(_, value) => reply(*value),
+        RedirsValue::SimpleError(message) => Err(FromRespError::Reply(message)),
+        RedirsValue::BulkError(message) => Err(FromRespError::Reply(
+            String::from_utf8_lossy(&message).into_owned(),
+        )),
+        value => Ok(value),
+    }
+}
+
+fn mismatch<T>(expected: &'static str, value: &RedirsValue) -> Result<T, FromRespError> {
+    Err(FromRespError::TypeMismatch {
+        expected,
+        found: value.kind(),
+    })
+}
+
+fn utf8(expected: &'static str, bytes: Vec<u8>) -> Result<String, FromRespError> {
+    String::from_utf8(bytes).map_err(|e| FromRespError::Invalid {
+        expected,
+        found: String::from_utf8_lossy(e.as_bytes()).into_owned(),
+    })
+}
+
+fn parse<T: core::str::FromStr>(expected: &'static str, s: String) -> Result<T, FromRespError> {
+    s.parse()
+        .map_err(|_| FromRespError::Invalid { expected, found: s })
+}
+
+// the payload of a string reply, or the text of a number as redis gives
+// `GET` of a key set with `INCR`
+fn bytes(value: RedirsValue) -> Result<Vec<u8>, FromRespError> {
+    match reply(value)? {
+        RedirsValue::SimpleString(s) => Ok(s.into_bytes()),
+        RedirsValue::BulkString(Some(s)) => Ok(from_payload(s)),
+        RedirsValue::VerbatimString(_, s) => Ok(s),
+        RedirsValue::Integer(i) => Ok(i.to_string().into_bytes()),
+        RedirsValue::BigNumber(sign, digits) => Ok(format!("{sign}{digits}").into_bytes()),
+        value => mismatch("a string", &value),
+    }
+}
+
+// the elements of any sequence reply
+fn elements(expected: &'static str, value: RedirsValue) -> Result<Vec<RedirsValue>, FromRespError> {
+    match reply(value)? {
+        RedirsValue::Array(Some(arr)) | RedirsValue::Push(arr) => Ok(arr),
+        RedirsValue::Set(set) => Ok(set.into_iter().collect()),
+        value => mismatch(expected, &value),
+    }
+}
+
+// the pairs of a map reply, or of a flat array of keys and values as RESP2
+// sends them (e.g. HGETALL)
+fn pairs(value: RedirsValue) -> Result<Vec<(RedirsValue, RedirsValue)>, FromRespError> {
+    match reply(value)? {
+        RedirsValue::Map(map) => Ok(map.into_pairs()),
+        RedirsValue::Array(Some(arr)) if arr.len() % 2 == 0 => {
+            let mut arr = arr.into_iter();
+            Ok(core::iter::from_fn(|| Some((arr.next()?, arr.next()?))).collect())
+        }
+        value => mismatch("a map", &value),
+    }
+}
+
+impl FromResp for RedirsValue {
+    fn from_resp(value: RedirsValue) -> Result<Self, FromRespError> {
+        reply(value)
+    }
+}
+
+// any reply that is not an error, e.g. the `+OK` of SET
+impl FromResp for () {
+    fn from_resp(value: RedirsValue) -> Result<Self, FromRespError> {
+        reply(value).map(drop)
+    }
+}
+
+impl FromResp for String {
+    fn from_resp(value: RedirsValue) -> Result<Self, FromRespError> {
+        match reply(value)? {
+            RedirsValue::SimpleString(s) => Ok(s),
+            value => utf8("a UTF-8 string", bytes(value)?),
+        }
+    }
+}
+
+impl FromResp for i64 {
+    fn from_resp(value: RedirsValue) -> Result<Self, FromRespError> {
+        match reply(value)? {
+            RedirsValue::Integer(i) => Ok(i),
+            // numbers stored as strings come back as bulk strings, e.g. GET
+            RedirsValue::SimpleString(s) => parse("an integer", s),
+            RedirsValue::BulkString(Some(s)) => {
+                parse("an integer", utf8("an integer", from_payload(s))?)
+            }
+            value => mismatch("an integer", &value),
+        }
+    }
+}
+
+macro_rules! from_resp_int {
+    ($($int:ty),*) => {$(
+        impl FromResp for $int {
+            fn from_resp(value: RedirsValue) -> Result<Self, FromRespError> {
+                let i = i64::from_resp(value)?;
+                <$int>::try_from(i).map_err(|_| FromRespError::Invalid {
+                    expected: concat!("an integer in the range of ", stringify!($int)),
+                    found: i.to_string(),
+                })
+            }
+        }
+    )*};
+}
+
+from_resp_int!(i8, i16, i32, isize, u8, u16, u32, u64, usize);
+
+impl FromResp for f64 {
+    fn from_resp(value: RedirsValue) -> Result<Self, FromRespError> {
+        match reply(value)? {
+            RedirsValue::Double(d) => Ok(d),
+            RedirsValue::Integer(i) => Ok(i as f64),
+            // RESP2 sends doubles as bulk strings, e.g. ZSCORE
+            RedirsValue::SimpleString(s) => parse("a double", s),
+            RedirsValue::BulkString(Some(s)) => {
+                parse("a double", utf8("a double", from_payload(s))?)
+            }
+            value => mismatch("a double", &value),
+        }
+    }
+}
+
+impl FromResp for bool {
+    fn from_resp(value: RedirsValue) -> Result<Self, FromRespError> {
+        match reply(value)? {
+            RedirsValue::Bool(b) => Ok(b),
+            // RESP2 booleans are the integers 0 and 1, e.g. EXISTS
+            RedirsValue::Integer(0) => Ok(false),
+            RedirsValue::Integer(1) => Ok(true),
+            RedirsValue::Integer(i) => Err(FromRespError::Invalid {
+                expected: "a boolean",
+                found: i.to_string(),
+            }),
+            value => mismatch("a boolean", &value),
+        }
+    }
+}
+
+impl<T: FromResp> FromResp for Option<T> {
+    fn from_resp(value: RedirsValue) -> Result<Self, FromRespError> {
+        match reply(value)? {
+            value if value.is_null() => Ok(None),
+            value => T::from_resp(value).map(Some),
+        }
+    }
+}
+
+impl<T: FromResp> FromResp for Vec<T> {
+    fn from_resp(value: RedirsValue) -> Result<Self, FromRespError> {
+        elements("an array", value)?
+            .into_iter()
+            .map(T::from_resp)
+            .collect()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<T: FromResp + Eq + Hash> FromResp for HashSet<T> {
+    fn from_resp(value: RedirsValue) -> Result<Self, FromRespError> {
+        elements("a set", value)?
+            .into_iter()
+            .map(T::from_resp)
+            .collect()
+    }
+}
+
+#[cfg(feature = "std")]
+impl<K: FromResp + Eq + Hash, V: FromResp> FromResp for HashMap<K, V> {
+    fn from_resp(value: RedirsValue) -> Result<Self, FromRespError> {
+        pairs(value)?
+            .into_iter()
+            .map(|(k, v)| Ok((K::from_resp(k)?, V::from_resp(v)?)))
+            .collect()
+    }
+}
+
+impl<K: FromResp + Ord, V: FromResp> FromResp for BTreeMap<K, V> {
+    fn from_resp(value: RedirsValue) -> Result<Self, FromRespError> {
+        pairs(value)?
+            .into_iter()
+            .map(|(k, v)| Ok((K::from_resp(k)?, V::from_resp(v)?)))
+            .collect()
+    }
+}
+
+macro_rules! from_resp_tuple {
+    ($len:literal: $($t:ident),+) => {
+        impl<$($t: FromResp),+> FromResp for ($($t,)+) {
+            fn from_resp(value: RedirsValue) -> Result<Self, FromRespError> {
+                let arr = elements("an array", value)?;
+                if arr.len() != $len {
+                    return Err(FromRespError::Length {
+                        expected: $len,
+                        found: arr.len(),
+                    });
+                }
+                let mut arr = arr.into_iter();
+                // the length is checked, every `next` yields an element
+                Ok(($($t::from_resp(arr.next().unwrap_or(RedirsValue::Null))?,)+))
+            }
+        }
+    };
+}
+
+from_resp_tuple!(1: A);
+from_resp_tuple!(2: A, B);
+from_resp_tuple!(3: A, B, C);
+from_resp_tuple!(4: A, B, C, D);
+from_resp_tuple!(5: A, B, C, D, E);
+from_resp_tuple!(6: A, B, C, D, E, F);
+
+// `Vec<u8>` is an array of bytes to `FromResp`, as a `TryFrom` target it is the
+// binary safe form of `String`
+impl TryFrom<RedirsValue> for Vec<u8> {
+    type Error = FromRespError;
+
+    fn try_from(value: RedirsValue) -> Result<Self, Self::Error> {
+        bytes(value)
+    }
+}
+
+impl TryFrom<&RedirsValue> for Vec<u8> {
+    type Error = FromRespError;
+
+    fn try_from(value: &RedirsValue) -> Result<Self, Self::Error> {
+        bytes(value.clone())
+    }
+}
+
+// `TryFrom` for the common targets of `FromResp`, generic `Option<T>` and
+// `Vec<T>` would overlap with the impls of the standard library
+macro_rules! try_from_resp {
+    ($([$($generics:tt)*] $t:ty;)*) => {$(
+        impl<$($generics)*> TryFrom<RedirsValue> for $t {
+            type Error = FromRespError;
+
+            fn try_from(value: RedirsValue) -> Result<Self, Self::Error> {
+                FromResp::from_resp(value)
+            }
+        }
+
+        // clones the value, convert owned values where possible
+        impl<$($generics)*> TryFrom<&RedirsValue> for $t {
+            type Error = FromRespError;
+
+            fn try_from(value: &RedirsValue) -> Result<Self, Self::Error> {
+                FromResp::from_resp(value.clone())
+            }
+        }
+    )*};
+}
+
+try_from_resp! {
+    [] i64;
+    [] f64;
+    [] bool;
+    [] String;
+    [] Option<String>;
+    [] Option<i64>;
+    [] Vec<RedirsValue>;
+    [] Vec<String>;
+    [K: FromResp + Ord, V: FromResp] BTreeMap<K, V>;
+}
+
+#[cfg(feature = "std")]
+try_from_resp! {
+    [K: FromResp + Eq + Hash, V: FromResp] HashMap<K, V>;
+    [T: FromResp + Eq + Hash] HashSet<T>;
+}
@@ -0,0 +1,127 @@
+use alloc::{borrow::Cow, vec::Vec};
+
+use crate::{FrameKind, Lexer, RedirsError, RedirsValue, RedirsValueRef};
+
+// longest inline request without a newline, same as the redis server
+pub(crate) const MAX_INLINE_LEN: usize = 64 * 1024;
+
+impl<'o> Lexer<'o> {
+    // lexes a client request, either a RESP frame or a telnet style inline
+    // command like `SET foo "bar baz"\r\n` turned into an array of bulk strings
+    pub fn lex_request(&mut self) -> Result<RedirsValue, RedirsError> {
+        self.lex_request_ref().map(RedirsValueRef::into_owned)
+    }
+    // like `lex_request`, inline arguments are owned since unquoting rewrites them
+    pub fn lex_request_ref(&mut self) -> Result<RedirsValueRef<'o>, RedirsError> {
+        match self.peek() {
+            Some(prefix) if FrameKind::from_prefix(prefix).is_some() => self.lex_ref(),
+            Some(_) => self.lex_inline(),
+            None => Err(RedirsError::Incomplete(None)),
+        }
+    }
+    fn lex_inline(&mut self) -> Result<RedirsValueRef<'o>, RedirsError> {
+        let rest = self.remaining();
+        let Some(end) = rest.iter().position(|&b| b == b'\n') else {
+            if rest.len() > MAX_INLINE_LEN {
+                return Err(RedirsError::LimitExceeded(crate::ParseLimit::InlineLen(
+                    MAX_INLINE_LEN,
+                )));
+            }
+            return Err(RedirsError::Incomplete(None));
+        };
+        let line = rest[..end].strip_suffix(b"\r").unwrap_or(&rest[..end]);
+        let args = split_args(line)?
+            .into_iter()
+            .map(|arg| RedirsValueRef::BulkString(Some(Cow::Owned(arg))))
+            .collect();
+        self.curr_pos += end + 1;
+        Ok(RedirsValueRef::Array(Some(args)))
+    }
+}
+
+// splits a line like redis `sdssplitargs`: whitespace separated arguments,
+// double quotes with backslash escapes (\n, \xHH, ...) and single quotes
+// where only \' is escaped, a closing quote must end the argument
+pub fn split_args(line: &[u8]) -> Result<Vec<Vec<u8>>, RedirsError> {
+    let mut args = Vec::new();
+    let mut i = 0;
+    loop {
+        while line.get(i).is_some_and(u8::is_ascii_whitespace) {
+            i += 1;
+        }
+        if i == line.len() {
+            return Ok(args);
+        }
+        let (mut in_double, mut in_single) = (false, false);
+        let mut arg = Vec::new();
+        loop {
+            let c = line.get(i).copied();
+            if in_double {
+                match c.ok_or(RedirsError::UnbalancedQuotes)? {
+                    b'\\'
+                        if line.get(i + 1) == Some(&b'x')
+                            && line.get(i + 2).is_some_and(u8::is_ascii_hexdigit)
+                            && line.get(i + 3).is_some_and(u8::is_ascii_hexdigit) =>
+                    {
+                        arg.push(hex_value(line[i + 2]) << 4 | hex_value(line[i + 3]));
+                        i += 3;
+                    }
+                    b'\\' if i + 1 < line.len() => {
+                        i += 1;
+                        arg.push(match line[i] {
+                            b'n' => b'\n',
+                            b'r' => b'\r',
+                            b't' => b'\t',
+                            b'b' => 0x08,
+                            b'a' => 0x07,
+                            c => c,
+                        });
+                    }
+                    b'"' => {
+                        in_double = false;
+                        end_quote(line, i + 1)?;
+                    }
+                    c => arg.push(c),
+                }
+            } else if in_single {
+                match c.ok_or(RedirsError::UnbalancedQuotes)? {
+                    b'\\' if line.get(i + 1) == Some(&b'\'') => {
+                        arg.push(b'\'');
+                        i += 1;
+                    }
+                    b'\'' => {
+                        in_single = false;
+                        end_quote(line, i + 1)?;
+                    }
+                    c => arg.push(c),
+                }
+            } else {
+                match c {
+                    None => break,
+                    Some(c) if c.is_ascii_whitespace() => break,
+                    Some(b'"') => in_double = true,
+                    Some(b'\'') => in_single = true,
+                    Some(c) => arg.push(c),
+                }
+            }
+            i += 1;
+        }
+        args.push(arg);
+    }
+}
+
+// `"foo"bar` is as unbalanced as `"foo` for redis
+fn end_quote(line: &[u8], next: usize) -> Result<(), RedirsError> {
+    match line.get(next) {
+        Some(c) if !c.is_ascii_whitespace() => Err(RedirsError::UnbalancedQuotes),
+        _ => Ok(()),
+    }
+}
+
+fn hex_value(c: u8) -> u8 {
+    match c {
+        b'0'..=b'9' => c - b'0',
+        b'a'..=b'f' => c - b'a' + 10,
+        _ => c - b'A' + 10,
+    }
+}
@@ -0,0 +1,124 @@
+// the byte sink the writers serialize into: `std::io::Write` itself with the
+// `std` feature, a lookalike of its core without it. The lookalike has the
+// same signatures, so a sink written against it builds with `std` as well
+#[cfg(feature = "std")]
+pub use std::io::{Error, ErrorKind, Result, Write};
+
+#[cfg(not(feature = "std"))]
+mod sink {
+    use core::fmt;
+
+    use alloc::vec::Vec;
+
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum ErrorKind {
+        // the sink is full, e.g. a fixed buffer
+        WriteZero,
+        // a `Display` impl failed while formatting
+        Other,
+    }
+
+    #[derive(Debug)]
+    pub struct Error(ErrorKind);
+
+    impl Error {
+        pub fn kind(&self) -> ErrorKind {
+            self.0
+        }
+    }
+
+    impl From<ErrorKind> for Error {
+        fn from(kind: ErrorKind) -> Self {
+            Error(kind)
+        }
+    }
+
+    impl fmt::Display for Error {
+        fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+            f.write_str(match self.0 {
+                ErrorKind::WriteZero => "failed to write whole buffer",
+                ErrorKind::Other => "formatter error",
+            })
+        }
+    }
+
+    impl core::error::Error for Error {}
+
+    pub type Result<T> = core::result::Result<T, Error>;
+
+    pub trait Write {
+        // writes a prefix of `buf`, returns how long it was
+        fn write(&mut self, buf: &[u8]) -> Result<usize>;
+        fn flush(&mut self) -> Result<()>;
+        fn write_all(&mut self, mut buf: &[u8]) -> Result<()> {
+            while !buf.is_empty() {
+                match self.write(buf)? {
+                    0 => return Err(ErrorKind::WriteZero.into()),
+                    n => buf = &buf[n..],
+                }
+            }
+            Ok(())
+        }
+        // the target of `write!`
+        fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> Result<()> {
+            struct Adapter<'a, W: ?Sized> {
+                inner: &'a mut W,
+                error: Result<()>,
+            }
+
+            impl<W: Write + ?Sized> fmt::Write for Adapter<'_, W> {
+                fn write_str(&mut self, s: &str) -> fmt::Result {
+                    self.inner.write_all(s.as_bytes()).map_err(|e| {
+                        self.error = Err(e);
+                        fmt::Error
+                    })
+                }
+            }
+
+            let mut adapter = Adapter {
+                inner: self,
+                error: Ok(()),
+            };
+            match fmt::write(&mut adapter, args) {
+                Ok(()) => Ok(()),
+                Err(_) => adapter.error.and(Err(ErrorKind::Other.into())),
+            }
+        }
+    }
+
+    impl Write for Vec<u8> {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            self.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    // fills the slice from the front and advances it past the written bytes
+    impl Write for &mut [u8] {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            let n = buf.len().min(self.len());
+            let (head, tail) = core::mem::take(self).split_at_mut(n);
+            head.copy_from_slice(&buf[..n]);
+            *self = tail;
+            Ok(n)
+        }
+        fn flush(&mut self) -> Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<W: Write + ?Sized> Write for &mut W {
+        fn write(&mut self, buf: &[u8]) -> Result<usize> {
+            (**self).write(buf)
+        }
+        fn flush(&mut self) -> Result<()> {
+            (**self).flush()
+        }
+    }
+}
+
+#[cfg(not(feature = "std"))]
+pub use sink::{Error, ErrorKind, Result, Write};
@@ -0,0 +1,196 @@
+use std::fmt::Display;
+
+use serde_json::{Map, Number, Value};
+
+use crate::{from_payload, RedirsMap, RedirsValue, Sign};
+
+// a value without a JSON form, `path` points at it from the root, e.g.
+// `[2].field`, and is empty for the root itself
+#[derive(Debug, PartialEq, Eq)]
+pub struct JsonError {
+    pub path: String,
+    pub kind: JsonErrorKind,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub enum JsonErrorKind {
+    // JSON strings are UTF-8, not binary safe
+    NotUtf8,
+    // JSON numbers cannot be infinite or NaN
+    NonFiniteDouble,
+    // JSON object keys are strings
+    NonStringKey,
+    // two keys of a map have the same text, a bulk and a simple string for
+    // one, and a JSON object would keep only the last
+    DuplicateKey,
+    // an error reply, carries its message
+    Reply(String),
+}
+
+impl Display for JsonErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            JsonErrorKind::NotUtf8 => f.write_str("string is not UTF-8"),
+            JsonErrorKind::NonFiniteDouble => f.write_str("double is not finite"),
+            JsonErrorKind::NonStringKey => f.write_str("map key is not a string"),
+            JsonErrorKind::DuplicateKey => f.write_str("map key is there twice"),
+            JsonErrorKind::Reply(message) => write!(f, "error reply: {message}"),
+        }
+    }
+}
+
+impl Display for JsonError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.path.as_str() {
+            "" => self.kind.fmt(f),
+            path => write!(f, "at {path}: {}", self.kind),
+        }
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+fn error<T>(path: &str, kind: JsonErrorKind) -> Result<T, JsonError> {
+    Err(JsonError {
+        path: path.to_owned(),
+        kind,
+    })
+}
+
+fn utf8(path: &str, bytes: Vec<u8>) -> Result<String, JsonError> {
+    String::from_utf8(bytes).or_else(|_| error(path, JsonErrorKind::NotUtf8))
+}
+
+// the elements with the index of each appended to `path`
+fn to_json_array(vals: Vec<RedirsValue>, path: &mut String) -> Result<Value, JsonError> {
+    let len = path.len();
+    let vals = vals
+        .into_iter()
+        .enumerate()
+        .map(|(i, value)| {
+            path.truncate(len);
+            path.push_str(&format!("[{i}]"));
+            to_json(value, path)
+        })
+        .collect::<Result<_, _>>()?;
+    path.truncate(len);
+    Ok(Value::Array(vals))
+}
+
+// `.key` for keys of letters, digits, `_` and `-` not starting with a digit,
+// `["key"]` with the key escaped as a JSON string for any other, so a path
+// cannot be read two ways
+fn push_key(path: &mut String, key: &str) {
+    let mut chars = key.chars();
+    let plain = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    match plain {
+        true => {
+            path.push('.');
+            path.push_str(key);
+        }
+        false => {
+            path.push('[');
+            path.push_str(&Value::from(key).to_string());
+            path.push(']');
+        }
+    }
+}
+
+// the entries in their order, the path of a duplicate key points at its
+// second entry
+fn to_json_object(map: RedirsMap, path: &mut String) -> Result<Value, JsonError> {
+    let len = path.len();
+    let mut object = Map::with_capacity(map.len());
+    for (key, value) in map {
+        let key = match key {
+            RedirsValue::SimpleString(s) => s,
+            RedirsValue::BulkString(Some(s)) => utf8(path, from_payload(s))?,
+            RedirsValue::VerbatimString(_, s) => utf8(path, s)?,
+            _ => return error(path, JsonErrorKind::NonStringKey),
+        };
+        push_key(path, &key);
+        if object.contains_key(&key) {
+            return error(path, JsonErrorKind::DuplicateKey);
+        }
+        let value = to_json(value, path)?;
+        path.truncate(len);
+        object.insert(key, value);
+    }
+    Ok(Value::Object(object))
+}
+
+fn to_json(value: RedirsValue, path: &mut String) -> Result<Value, JsonError> {
+    match value {
+        RedirsValue::SimpleString(s) => Ok(Value::String(s)),
+        RedirsValue::BulkString(Some(s)) => utf8(path, from_payload(s)).map(Value::String),
+        RedirsValue::VerbatimString(_, s) => utf8(path, s).map(Value::String),
+        RedirsValue::SimpleError(message) => error(path, JsonErrorKind::Reply(message)),
+        RedirsValue::BulkError(message) => error(
+            path,
+            JsonErrorKind::Reply(String::from_utf8_lossy(&message).into_owned()),
+        ),
+        RedirsValue::Integer(i) => Ok(Value::from(i)),
+        RedirsValue::Double(d) => Number::from_f64(d)
+            .map(Value::Number)
+            .map_or_else(|| error(path, JsonErrorKind::NonFiniteDouble), Ok),
+        // JSON numbers do not have the range, the digits are kept as text
+        RedirsValue::BigNumber(Sign::Positive, digits) => Ok(Value::String(digits)),
+        RedirsValue::BigNumber(Sign::Negative, digits) => Ok(Value::String(format!("-{digits}"))),
+        RedirsValue::Bool(b) => Ok(Value::Bool(b)),
+        RedirsValue::Null | RedirsValue::BulkString(None) | RedirsValue::Array(None) => {
+            Ok(Value::Null)
+        }
+        RedirsValue::Array(Some(vals)) | RedirsValue::Push(vals) => to_json_array(vals, path),
+        RedirsValue::Set(set) => {
+            // sorted so the array does not follow the order of the `HashSet`
+            let mut vals: Vec<_> = set.into_iter().collect();
+            vals.sort();
+            to_json_array(vals, path)
+        }
+        RedirsValue::Map(map) => to_json_object(map, path),
+        RedirsValue::Attribute(_, value) => to_json(*value, path),
+    }
+}
+
+// lossy: sets and pushes become arrays, the three nulls become `null`,
+// simple and verbatim strings become strings, big numbers become their digits
+// as a string and attributes are dropped; error replies, binary strings,
+// non string or duplicate map keys and non finite doubles have no JSON form
+impl TryFrom<RedirsValue> for Value {
+    type Error = JsonError;
+
+    fn try_from(value: RedirsValue) -> Result<Self, Self::Error> {
+        to_json(value, &mut String::new())
+    }
+}
+
+// strings become bulk strings and objects become maps keyed by bulk strings,
+// integers beyond the range of `i64` become big numbers; every JSON value has a
+// RESP form, so `TryFrom` comes through the blanket impl and never fails
+impl From<Value> for RedirsValue {
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => RedirsValue::Null,
+            Value::Bool(b) => RedirsValue::Bool(b),
+            Value::Number(n) => match (n.as_i64(), n.as_u64()) {
+                (Some(i), _) => RedirsValue::Integer(i),
+                (None, Some(u)) => RedirsValue::BigNumber(Sign::Positive, u.to_string()),
+                // numbers that are neither integer are doubles
+                (None, None) => RedirsValue::Double(n.as_f64().unwrap_or(f64::NAN)),
+            },
+            Value::String(s) => RedirsValue::from(s),
+            Value::Array(vals) => {
+                RedirsValue::Array(Some(vals.into_iter().map(RedirsValue::from).collect()))
+            }
+            Value::Object(object) => RedirsValue::Map(
+                object
+                    .into_iter()
+                    .map(|(k, v)| (RedirsValue::from(k), RedirsValue::from(v)))
+                    .collect(),
+            ),
+        }
+    }
+}
@@ -1,36 +1,409 @@
-use std::{
-    borrow::Cow,
-    collections::{BTreeMap, HashSet},
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(not(feature = "std"))]
+use alloc::collections::BTreeSet;
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    collections::BTreeMap,
+    format,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{
+    cmp::Ordering,
     fmt::Display,
-    io::{self, Write},
+    hash::{Hash, Hasher},
 };
+#[cfg(feature = "std")]
+use std::{
+    collections::{hash_map::DefaultHasher, HashSet},
+    io::Read,
+    time::{Duration, SystemTime},
+};
+
+use io::Write;
+
+#[cfg(feature = "async")]
+mod async_reader;
+#[cfg(feature = "async")]
+mod async_writer;
+#[cfg(feature = "codec")]
+pub mod codec;
+#[cfg(feature = "std")]
+mod command;
+#[cfg(feature = "std")]
+mod counting;
+mod display;
+mod from_resp;
+mod inline;
+pub mod io;
+#[cfg(feature = "json")]
+mod json;
+mod macros;
+#[cfg(feature = "std")]
+mod reader;
+#[cfg(feature = "serde")]
+mod serde_value;
+#[cfg(feature = "bytes")]
+mod shared;
+#[cfg(feature = "test-support")]
+pub mod test_support;
+mod value_ref;
+
+#[cfg(feature = "async")]
+pub use async_reader::RespReader;
+#[cfg(feature = "std")]
+pub use command::CommandError;
+#[cfg(feature = "std")]
+pub use counting::CountingWriter;
+pub use from_resp::{FromResp, FromRespError};
+pub use inline::split_args;
+#[cfg(feature = "json")]
+pub use json::{JsonError, JsonErrorKind};
+#[cfg(feature = "std")]
+pub use reader::{read_bulk, read_value, BulkReader};
+pub use value_ref::RedirsValueRef;
+
+// for the expansion of `resp!` in crates without `std`
+#[doc(hidden)]
+pub mod __private {
+    pub use alloc::{string::String, vec};
+}
 
 const SPACER: &str = "\r\n";
+// every frame spans at least three bytes, e.g. `_\r\n`
+const MIN_FRAME_LEN: usize = 3;
+// most buffer space reserved ahead for the missing bytes of a frame, the
+// count comes from untrusted length prefixes
+#[cfg(any(feature = "async", feature = "codec"))]
+const MAX_RESERVE: usize = 1024 * 1024;
 
 pub trait RedirsOutput {
     fn write_resp_str<T: Write>(&self, out: &mut T) -> io::Result<()>;
+    // `write_resp_str` for unbuffered sinks like a `TcpStream`, the small writes
+    // of the single frames are batched into a few big ones
+    #[cfg(feature = "std")]
+    fn write_resp_buffered<T: Write>(&self, out: &mut T) -> io::Result<()> {
+        let mut out = std::io::BufWriter::with_capacity(WRITE_BUFFER_LEN, out);
+        self.write_resp_str(&mut out)?;
+        out.flush()
+    }
+    // the entry point for sinks behind a trait object, the generic methods are
+    // the fast path for everything else
+    fn write_resp_dyn(&self, mut out: &mut dyn Write) -> io::Result<()> {
+        self.write_resp_str(&mut out)
+    }
+    // the frames as text for logs and snapshots, bytes that are not UTF-8 are
+    // escaped as `\xHH`
+    fn to_resp_string(&self) -> String {
+        let mut bytes = Vec::new();
+        self.write_resp_str(&mut bytes)
+            .expect("writing to a Vec cannot fail");
+        let mut out = String::with_capacity(bytes.len());
+        for chunk in bytes.utf8_chunks() {
+            out.push_str(chunk.valid());
+            chunk
+                .invalid()
+                .iter()
+                .for_each(|b| out.push_str(&format!("\\x{b:02x}")));
+        }
+        out
+    }
+    // `to_resp_string` into a `fmt::Write` sink, e.g. a `Formatter`
+    fn write_resp_fmt<F: core::fmt::Write>(&self, out: &mut F) -> core::fmt::Result {
+        out.write_str(&self.to_resp_string())
+    }
 }
 
+// payloads longer than this skip the buffer of `write_resp_buffered`
+#[cfg(feature = "std")]
+const WRITE_BUFFER_LEN: usize = 64 * 1024;
+
+#[derive(Debug)]
 pub enum RedirsError {
-    WhitespaceError,
-    StringError,
-    ParsingError,
+    // the input lacks the `\r\n` expected at the byte offset
+    WhitespaceError(usize),
+    // text that is not valid UTF-8 starting at the byte offset
+    StringError(usize),
+    ParsingError {
+        offset: usize,
+        expected: Expected,
+        found: Vec<u8>,
+    },
+    // a length that is negative, or -1 where null is not allowed, for the
+    // frame started by `prefix`
+    InvalidLength {
+        offset: usize,
+        prefix: u8,
+        len: i64,
+    },
+    // the input goes past one of the lexer `ParseLimits`
+    LimitExceeded(ParseLimit),
+    // an aggregate element failed to parse, carries (element index, cause)
+    ElementError(usize, Box<RedirsError>),
+    // the buffer ends mid frame, carries the number of missing bytes when known
+    // (a lower bound for aggregates), retry with more data appended
+    Incomplete(Option<usize>),
+    // an inline request with a quoted argument that is never closed
+    UnbalancedQuotes,
+    // the underlying reader failed, or ended in the middle of a frame
+    Io(io::Error),
+    // the underlying reader ended cleanly on a frame boundary
+    Eof,
 }
 
-#[derive(Debug)]
+// what the lexer was looking for when it found something else
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Expected {
+    // the type byte that starts every frame
+    Prefix,
+    Integer,
+    // the length of the frame started by the given prefix
+    Length(u8),
+    Null,
+    Bool,
+    Double,
+    BigNumber,
+    VerbatimEncoding,
+    // the `;` starting a chunk of a streamed string
+    Chunk,
+    // the `.` closing a streamed aggregate
+    StreamEnd,
+    // the text of a simple frame, which cannot hold CR or LF
+    Line,
+}
+
+impl Display for Expected {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            Expected::Prefix => f.write_str("a type prefix"),
+            Expected::Integer => f.write_str("an integer"),
+            Expected::Length(prefix) => write!(f, "the length of a '{}' frame", *prefix as char),
+            Expected::Null => f.write_str("a null"),
+            Expected::Bool => f.write_str("a boolean"),
+            Expected::Double => f.write_str("a double"),
+            Expected::BigNumber => f.write_str("a big number"),
+            Expected::VerbatimEncoding => f.write_str("a verbatim encoding"),
+            Expected::Chunk => f.write_str("a ';' chunk"),
+            Expected::StreamEnd => f.write_str("the '.' end of a streamed aggregate"),
+            Expected::Line => f.write_str("a single line"),
+        }
+    }
+}
+
+// the type of a frame, as told by its prefix byte
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameKind {
+    SimpleString,
+    SimpleError,
+    Integer,
+    BulkString,
+    Array,
+    Null,
+    Bool,
+    Double,
+    BigNumber,
+    BulkError,
+    VerbatimString,
+    Map,
+    Set,
+    Push,
+    Attribute,
+}
+
+impl FrameKind {
+    pub fn from_prefix(prefix: u8) -> Option<Self> {
+        Some(match prefix {
+            b'+' => FrameKind::SimpleString,
+            b'-' => FrameKind::SimpleError,
+            b':' => FrameKind::Integer,
+            b'$' => FrameKind::BulkString,
+            b'*' => FrameKind::Array,
+            b'_' => FrameKind::Null,
+            b'#' => FrameKind::Bool,
+            b',' => FrameKind::Double,
+            b'(' => FrameKind::BigNumber,
+            b'!' => FrameKind::BulkError,
+            b'=' => FrameKind::VerbatimString,
+            b'%' => FrameKind::Map,
+            b'~' => FrameKind::Set,
+            b'>' => FrameKind::Push,
+            b'|' => FrameKind::Attribute,
+            _ => return None,
+        })
+    }
+}
+
+// names the exceeded limit and carries its configured maximum
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseLimit {
+    Depth(usize),
+    Elements(usize),
+    BulkLen(usize),
+    InlineLen(usize),
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ParseLimits {
+    // how many aggregates can be nested into each other
+    pub max_depth: usize,
+    // the element count of a single aggregate (pairs for maps)
+    pub max_elements: usize,
+    pub max_bulk_len: usize,
+}
+
+impl Display for ParseLimit {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            ParseLimit::Depth(max) => write!(f, "nesting depth limit of {max} exceeded"),
+            ParseLimit::Elements(max) => write!(f, "aggregate length limit of {max} exceeded"),
+            ParseLimit::BulkLen(max) => write!(f, "bulk length limit of {max} exceeded"),
+            ParseLimit::InlineLen(max) => write!(f, "inline request limit of {max} exceeded"),
+        }
+    }
+}
+
+impl Default for ParseLimits {
+    // mirrors the redis server: 512 MB bulk strings and INT_MAX multibulk lengths
+    fn default() -> Self {
+        Self {
+            max_depth: 128,
+            max_elements: i32::MAX as usize,
+            max_bulk_len: 512 * 1024 * 1024,
+        }
+    }
+}
+
+impl Display for RedirsError {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self {
+            RedirsError::WhitespaceError(offset) => write!(f, "expected CRLF at byte {offset}"),
+            RedirsError::StringError(offset) => write!(f, "invalid UTF-8 at byte {offset}"),
+            RedirsError::ParsingError {
+                offset,
+                expected,
+                found,
+            } => write!(
+                f,
+                "expected {expected} at byte {offset}, found \"{}\"",
+                found.escape_ascii()
+            ),
+            RedirsError::InvalidLength {
+                offset,
+                prefix,
+                len,
+            } => write!(
+                f,
+                "invalid length {len} of a '{}' frame at byte {offset}",
+                *prefix as char
+            ),
+            RedirsError::LimitExceeded(limit) => limit.fmt(f),
+            RedirsError::ElementError(idx, e) => write!(f, "element {idx}: {e}"),
+            RedirsError::Incomplete(Some(missing)) => {
+                write!(f, "incomplete frame, at least {missing} more bytes needed")
+            }
+            RedirsError::Incomplete(None) => f.write_str("incomplete frame"),
+            RedirsError::UnbalancedQuotes => f.write_str("unbalanced quotes in inline request"),
+            RedirsError::Io(e) => e.fmt(f),
+            RedirsError::Eof => f.write_str("end of stream"),
+        }
+    }
+}
+
+impl RedirsError {
+    // the reply real Redis sends before closing a connection that sent this input
+    pub fn to_client_error(&self) -> RedirsValue {
+        let reason = match self {
+            RedirsError::ElementError(_, e) => return e.to_client_error(),
+            RedirsError::InvalidLength { prefix: b'*', .. }
+            | RedirsError::ParsingError {
+                expected: Expected::Length(b'*'),
+                ..
+            }
+            | RedirsError::LimitExceeded(ParseLimit::Elements(_)) => {
+                "invalid multibulk length".to_owned()
+            }
+            RedirsError::InvalidLength { prefix: b'$', .. }
+            | RedirsError::ParsingError {
+                expected: Expected::Length(b'$'),
+                ..
+            }
+            | RedirsError::LimitExceeded(ParseLimit::BulkLen(_)) => {
+                "invalid bulk length".to_owned()
+            }
+            RedirsError::ParsingError {
+                expected: Expected::Prefix,
+                found,
+                ..
+            } => format!("expected '$', got '{}'", found.escape_ascii()),
+            RedirsError::LimitExceeded(ParseLimit::InlineLen(_)) => {
+                "too big inline request".to_owned()
+            }
+            RedirsError::UnbalancedQuotes => "unbalanced quotes in request".to_owned(),
+            e => e.to_string(),
+        };
+        RedirsValue::SimpleError(format!("ERR Protocol error: {reason}"))
+    }
+}
+
+impl core::error::Error for RedirsError {
+    fn source(&self) -> Option<&(dyn core::error::Error + 'static)> {
+        match self {
+            RedirsError::ElementError(_, e) => Some(e.as_ref()),
+            RedirsError::Io(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for RedirsError {
+    fn from(e: io::Error) -> Self {
+        RedirsError::Io(e)
+    }
+}
+
+#[cfg(feature = "std")]
+impl From<RedirsError> for io::Error {
+    fn from(e: RedirsError) -> Self {
+        match e {
+            RedirsError::Io(e) => e,
+            RedirsError::Eof | RedirsError::Incomplete(_) => {
+                io::Error::new(std::io::ErrorKind::UnexpectedEof, e)
+            }
+            e => io::Error::new(std::io::ErrorKind::InvalidData, e),
+        }
+    }
+}
+
+impl RedirsError {
+    fn at_element(self, idx: usize) -> Self {
+        match self {
+            // a truncated element means a truncated frame, and limits apply
+            // to the whole frame, neither is about a single bad element
+            RedirsError::Incomplete(_) | RedirsError::LimitExceeded(_) => self,
+            e => RedirsError::ElementError(idx, Box::new(e)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum ProcVersion {
     V2,
     V3,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum Sign {
     Positive,
     Negative,
 }
 
 impl Display for Sign {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
         f.write_str(match self {
             Sign::Positive => "+",
             Sign::Negative => "-",
@@ -38,74 +411,910 @@ impl Display for Sign {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub enum VerbatimEncoding {
     Txt,
     Mrk,
+    // any other three byte tag, a `Tag` is only built by `new` and `from_tag` so
+    // known tags are never held here
+    Other(Tag),
+}
+
+// the tag of a verbatim encoding that is not one of the known ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Tag([u8; 3]);
+
+impl VerbatimEncoding {
+    // the encoding of a tag from the wire or a caller, tags are three printable
+    // ASCII characters
+    pub fn new(tag: &[u8]) -> Result<Self, RedirsError> {
+        match <[u8; 3]>::try_from(tag) {
+            Ok(tag) if tag.iter().all(u8::is_ascii_graphic) => Ok(Self::from_tag(tag)),
+            _ => Err(RedirsError::ParsingError {
+                offset: 0,
+                expected: Expected::VerbatimEncoding,
+                found: tag.to_vec(),
+            }),
+        }
+    }
+    pub fn from_tag(tag: [u8; 3]) -> Self {
+        match &tag {
+            b"txt" => VerbatimEncoding::Txt,
+            b"mrk" => VerbatimEncoding::Mrk,
+            _ => VerbatimEncoding::Other(Tag(tag)),
+        }
+    }
+    pub fn tag(&self) -> &[u8; 3] {
+        match self {
+            VerbatimEncoding::Txt => b"txt",
+            VerbatimEncoding::Mrk => b"mrk",
+            VerbatimEncoding::Other(Tag(tag)) => tag,
+        }
+    }
 }
 impl Display for VerbatimEncoding {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.write_str(match self {
-            VerbatimEncoding::Txt => "txt",
-            VerbatimEncoding::Mrk => "mrk",
-        })
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.write_str(&String::from_utf8_lossy(self.tag()))
     }
 }
 
+// the username and password of HELLO AUTH
+pub type Credentials<'a> = (Cow<'a, [u8]>, Cow<'a, [u8]>);
+
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub struct HelloCmd<'a> {
     pub version: Option<ProcVersion>,
-    pub auth: Option<(Cow<'a, str>, Cow<'a, str>)>,
-    pub client_name: Option<Cow<'a, str>>,
+    pub auth: Option<Credentials<'a>>,
+    pub client_name: Option<Cow<'a, [u8]>>,
+}
+
+#[cfg(feature = "std")]
+// the handshake reply to HELLO, `into_value` lays it out in the field order
+// of redis since RESP2 clients read the flat array positionally
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HelloReply {
+    pub server: String,
+    pub version: String,
+    // the negotiated protocol, it also picks the shape of the reply
+    pub proto: ProcVersion,
+    pub id: i64,
+    // standalone, sentinel or cluster
+    pub mode: String,
+    // master or replica
+    pub role: String,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum Expiration {
+    // relative to when the command runs (EX, PX)
+    In(Duration),
+    // an absolute unix time (EXAT, PXAT)
+    At(SystemTime),
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum SetCondition {
+    // only set keys that do not exist
+    NX,
+    // only set keys that already exist
+    XX,
+}
+
+#[cfg(feature = "std")]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct SetOptions {
+    pub expire: Option<Expiration>,
+    // retain the ttl of the key, never set together with `expire`
+    pub keep_ttl: bool,
+    pub condition: Option<SetCondition>,
+    // reply with the previous value
+    pub get: bool,
 }
 
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub enum Cmd<'a> {
     System(System<'a>),
     Action(Action<'a>),
 }
 
+// a key with the value to set it to
+#[cfg(feature = "std")]
+pub type KeyValue<'a> = (&'a [u8], &'a [u8]);
+
+#[cfg(feature = "std")]
 #[derive(Debug)]
+// keys and values are binary safe, as in redis
 pub enum Action<'a> {
-    GET(&'a str),
-    SET((String, RedirsValue)),
-    DEL(&'a str),
+    GET(&'a [u8]),
+    SET((Vec<u8>, RedirsValue, SetOptions)),
+    // the variadic commands carry one key or pair at least
+    DEL(Vec<&'a [u8]>),
+    UNLINK(Vec<&'a [u8]>),
+    EXISTS(Vec<&'a [u8]>),
+    MGET(Vec<&'a [u8]>),
+    MSET(Vec<KeyValue<'a>>),
+    MSETNX(Vec<KeyValue<'a>>),
 }
 
+#[cfg(feature = "std")]
 #[derive(Debug)]
 pub enum System<'a> {
-    PING(&'a str),
+    // none for a bare PING, which is told apart from `PING ""`
+    PING(Option<&'a [u8]>),
     HELLO(HelloCmd<'a>),
-    ECHO(&'a str),
+    ECHO(&'a [u8]),
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum RedirsValue {
     SimpleString(String),
     SimpleError(String),
     Integer(i64),
-    // this can be pretty huge (max 512 MB)
-    BulkString(Option<String>),
+    // binary safe and can be pretty huge (max 512 MB)
+    BulkString(Option<BulkPayload>),
     Array(Option<Vec<RedirsValue>>),
     Null,
     Bool(bool),
     Double(f64),
     BigNumber(Sign, String),
-    BulkError(String),
-    VerbatimString(VerbatimEncoding, String),
-    Map(BTreeMap<RedirsValue, RedirsValue>),
-    Set(HashSet<RedirsValue>),
+    BulkError(Vec<u8>),
+    VerbatimString(VerbatimEncoding, Vec<u8>),
+    Map(RedirsMap),
+    Set(RedirsSet),
     Push(Vec<RedirsValue>),
+    // out of band metadata (the map) attached to the reply that follows it
+    Attribute(RedirsMap, Box<RedirsValue>),
+}
+
+// the payload of a bulk string, `Bytes` with the `bytes` feature: code built
+// for both names the type through this alias and converts with `into`
+#[cfg(feature = "bytes")]
+pub type BulkPayload = bytes::Bytes;
+#[cfg(not(feature = "bytes"))]
+pub type BulkPayload = Vec<u8>;
+
+// moves bytes into and out of a `BulkPayload`, no-ops without `bytes`
+#[cfg(feature = "bytes")]
+fn to_payload(vec: Vec<u8>) -> BulkPayload {
+    bytes::Bytes::from(vec)
+}
+#[cfg(not(feature = "bytes"))]
+fn to_payload(vec: Vec<u8>) -> BulkPayload {
+    vec
+}
+#[cfg(feature = "bytes")]
+fn from_payload(payload: BulkPayload) -> Vec<u8> {
+    Vec::from(payload)
+}
+#[cfg(not(feature = "bytes"))]
+fn from_payload(payload: BulkPayload) -> Vec<u8> {
+    payload
+}
+
+// the elements of a set frame, a `BTreeSet` without `std`: code built for
+// both names the type through this alias
+#[cfg(feature = "std")]
+pub type RedirsSet = HashSet<RedirsValue>;
+#[cfg(not(feature = "std"))]
+pub type RedirsSet = BTreeSet<RedirsValue>;
+
+// the pairs of a map or attribute frame in wire order, duplicate keys included
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct RedirsMap(Vec<(RedirsValue, RedirsValue)>);
+
+impl RedirsMap {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // the value of the first pair with `key`, the linear scan is fine for
+    // the handful of entries replies carry
+    pub fn get(&self, key: &RedirsValue) -> Option<&RedirsValue> {
+        self.0.iter().find(|(k, _)| k == key).map(|(_, v)| v)
+    }
+    pub fn contains_key(&self, key: &RedirsValue) -> bool {
+        self.get(key).is_some()
+    }
+    // appends the pair, an existing pair with the same key is kept
+    pub fn push(&mut self, key: RedirsValue, value: RedirsValue) {
+        self.0.push((key, value));
+    }
+    pub fn len(&self) -> usize {
+        self.0.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (&RedirsValue, &RedirsValue)> {
+        self.0.iter().map(|(k, v)| (k, v))
+    }
+    pub fn into_pairs(self) -> Vec<(RedirsValue, RedirsValue)> {
+        self.0
+    }
+}
+
+impl From<Vec<(RedirsValue, RedirsValue)>> for RedirsMap {
+    fn from(pairs: Vec<(RedirsValue, RedirsValue)>) -> Self {
+        Self(pairs)
+    }
+}
+
+// for code written against the old sorted representation
+impl From<BTreeMap<RedirsValue, RedirsValue>> for RedirsMap {
+    fn from(map: BTreeMap<RedirsValue, RedirsValue>) -> Self {
+        map.into_iter().collect()
+    }
+}
+
+impl FromIterator<(RedirsValue, RedirsValue)> for RedirsMap {
+    fn from_iter<I: IntoIterator<Item = (RedirsValue, RedirsValue)>>(iter: I) -> Self {
+        Self(iter.into_iter().collect())
+    }
+}
+
+impl IntoIterator for RedirsMap {
+    type Item = (RedirsValue, RedirsValue);
+    type IntoIter = alloc::vec::IntoIter<(RedirsValue, RedirsValue)>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.into_iter()
+    }
+}
+
+// an optional sign and one or more ASCII digits, of any length
+fn split_big_number(s: &[u8]) -> Option<(Sign, &str)> {
+    let (sign, digits) = match s.split_first() {
+        Some((b'-', digits)) => (Sign::Negative, digits),
+        Some((b'+', digits)) => (Sign::Positive, digits),
+        _ => (Sign::Positive, s),
+    };
+    match !digits.is_empty() && digits.iter().all(u8::is_ascii_digit) {
+        // all ASCII, so always valid UTF-8
+        true => core::str::from_utf8(digits)
+            .ok()
+            .map(|digits| (sign, digits)),
+        false => None,
+    }
+}
+
+// text that fits a simple frame
+fn single_line(s: String) -> Result<String, RedirsError> {
+    match s.bytes().position(|b| matches!(b, b'\r' | b'\n')) {
+        Some(offset) => Err(RedirsError::ParsingError {
+            offset,
+            expected: Expected::Line,
+            found: s.into_bytes(),
+        }),
+        None => Ok(s),
+    }
+}
+
+// a CR or LF in the text of a simple frame would end it early and let the rest
+// be read as another frame
+fn spans_lines(s: &str) -> bool {
+    s.bytes().any(|b| matches!(b, b'\r' | b'\n'))
+}
+
+// plain text and bytes become bulk strings, the type of command arguments and
+// of most replies
+impl From<&str> for RedirsValue {
+    fn from(s: &str) -> Self {
+        RedirsValue::BulkString(Some(to_payload(s.as_bytes().to_vec())))
+    }
+}
+
+impl From<String> for RedirsValue {
+    fn from(s: String) -> Self {
+        RedirsValue::BulkString(Some(to_payload(s.into_bytes())))
+    }
+}
+
+impl From<&[u8]> for RedirsValue {
+    fn from(bytes: &[u8]) -> Self {
+        RedirsValue::BulkString(Some(to_payload(bytes.to_vec())))
+    }
+}
+
+impl From<Vec<u8>> for RedirsValue {
+    fn from(bytes: Vec<u8>) -> Self {
+        RedirsValue::BulkString(Some(to_payload(bytes)))
+    }
+}
+
+#[cfg(feature = "bytes")]
+impl From<bytes::Bytes> for RedirsValue {
+    fn from(bytes: bytes::Bytes) -> Self {
+        RedirsValue::BulkString(Some(bytes))
+    }
+}
+
+impl From<i64> for RedirsValue {
+    fn from(i: i64) -> Self {
+        RedirsValue::Integer(i)
+    }
+}
+
+impl From<f64> for RedirsValue {
+    fn from(d: f64) -> Self {
+        RedirsValue::Double(d)
+    }
+}
+
+impl From<bool> for RedirsValue {
+    fn from(b: bool) -> Self {
+        RedirsValue::Bool(b)
+    }
+}
+
+// `None` is the RESP3 null, `write_resp` spells it `$-1` for RESP2
+impl<T: Into<RedirsValue>> From<Option<T>> for RedirsValue {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(RedirsValue::Null, Into::into)
+    }
+}
+
+// an array, only `Vec<u8>` is a bulk string
+impl<T: Into<RedirsValue>> From<Vec<T>> for RedirsValue {
+    fn from(vals: Vec<T>) -> Self {
+        RedirsValue::Array(Some(vals.into_iter().map(Into::into).collect()))
+    }
+}
+
+impl<T: Into<RedirsValue>> FromIterator<T> for RedirsValue {
+    fn from_iter<I: IntoIterator<Item = T>>(iter: I) -> Self {
+        RedirsValue::Array(Some(iter.into_iter().map(Into::into).collect()))
+    }
+}
+
+impl<K: Into<RedirsValue>, V: Into<RedirsValue>> FromIterator<(K, V)> for RedirsValue {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        RedirsValue::Map(
+            iter.into_iter()
+                .map(|(k, v)| (k.into(), v.into()))
+                .collect(),
+        )
+    }
+}
+
+impl RedirsValue {
+    // the payload of a string or error value, whatever its frame type
+    pub fn as_bytes(&self) -> Option<&[u8]> {
+        match self {
+            RedirsValue::SimpleString(s) | RedirsValue::SimpleError(s) => Some(s.as_bytes()),
+            RedirsValue::BulkString(Some(b)) => Some(b),
+            RedirsValue::BulkError(b) | RedirsValue::VerbatimString(_, b) => Some(b),
+            _ => None,
+        }
+    }
+    // `as_bytes` for payloads that are valid UTF-8
+    pub fn as_str(&self) -> Option<&str> {
+        self.as_bytes()
+            .and_then(|bytes| core::str::from_utf8(bytes).ok())
+    }
+    // the only way to build a `BigNumber` that is sure to be a valid frame
+    pub fn big_number(s: &str) -> Result<RedirsValue, RedirsError> {
+        let (sign, digits) =
+            split_big_number(s.as_bytes()).ok_or_else(|| RedirsError::ParsingError {
+                offset: 0,
+                expected: Expected::BigNumber,
+                found: s.as_bytes().to_vec(),
+            })?;
+        Ok(RedirsValue::BigNumber(sign, digits.to_owned()))
+    }
+    // a `SimpleString` that is sure to be written as one, `write_resp_str` falls
+    // back to a bulk string for text spanning lines
+    pub fn simple_string(s: impl Into<String>) -> Result<RedirsValue, RedirsError> {
+        single_line(s.into()).map(RedirsValue::SimpleString)
+    }
+    // a `SimpleError` that is sure to be written as one, `write_resp_str` falls
+    // back to a bulk error for text spanning lines
+    pub fn simple_error(s: impl Into<String>) -> Result<RedirsValue, RedirsError> {
+        single_line(s.into()).map(RedirsValue::SimpleError)
+    }
+    // attaches the attribute to the value, attributes written in RESP3 ahead of
+    // the value frame and dropped for RESP2, e.g. the key popularity of a reply
+    pub fn with_attribute(
+        self,
+        key: impl Into<RedirsValue>,
+        value: impl Into<RedirsValue>,
+    ) -> RedirsValue {
+        match self {
+            RedirsValue::Attribute(mut attrs, inner) => {
+                attrs.push(key.into(), value.into());
+                RedirsValue::Attribute(attrs, inner)
+            }
+            inner => RedirsValue::Attribute(
+                RedirsMap::from(vec![(key.into(), value.into())]),
+                Box::new(inner),
+            ),
+        }
+    }
+    // the value under its attributes
+    fn unattributed(&self) -> &RedirsValue {
+        match self {
+            RedirsValue::Attribute(_, value) => value.unattributed(),
+            value => value,
+        }
+    }
+    // removes the attributes at any depth, for consumers that do not care about them
+    pub fn strip_attributes(self) -> RedirsValue {
+        match self {
+            RedirsValue::Attribute(_, value) => value.strip_attributes(),
+            RedirsValue::Array(Some(arr)) => RedirsValue::Array(Some(
+                arr.into_iter().map(RedirsValue::strip_attributes).collect(),
+            )),
+            RedirsValue::Map(map) => RedirsValue::Map(
+                map.into_iter()
+                    .map(|(k, v)| (k.strip_attributes(), v.strip_attributes()))
+                    .collect(),
+            ),
+            RedirsValue::Set(set) => {
+                RedirsValue::Set(set.into_iter().map(RedirsValue::strip_attributes).collect())
+            }
+            RedirsValue::Push(vals) => RedirsValue::Push(
+                vals.into_iter()
+                    .map(RedirsValue::strip_attributes)
+                    .collect(),
+            ),
+            value => value,
+        }
+    }
+    // any of the spellings of nothing, `_`, `$-1` or `*-1`
+    pub fn is_null(&self) -> bool {
+        matches!(
+            self,
+            RedirsValue::Null | RedirsValue::BulkString(None) | RedirsValue::Array(None)
+        )
+    }
+    // respells the nulls at any depth the way redis replies to `version`: RESP3
+    // has only `_`, RESP2 keeps null arrays apart and sends `$-1` for the rest
+    pub fn normalize_nulls(self, version: ProcVersion) -> RedirsValue {
+        let normalize = |value: RedirsValue| value.normalize_nulls(version);
+        let normalize_pairs = |map: RedirsMap| {
+            map.into_iter()
+                .map(|(k, v)| (normalize(k), normalize(v)))
+                .collect()
+        };
+        match (self, version) {
+            (RedirsValue::BulkString(None) | RedirsValue::Array(None), ProcVersion::V3) => {
+                RedirsValue::Null
+            }
+            (RedirsValue::Null, ProcVersion::V2) => RedirsValue::BulkString(None),
+            (RedirsValue::Array(Some(arr)), _) => {
+                RedirsValue::Array(Some(arr.into_iter().map(normalize).collect()))
+            }
+            (RedirsValue::Map(map), _) => RedirsValue::Map(normalize_pairs(map)),
+            (RedirsValue::Set(set), _) => {
+                RedirsValue::Set(set.into_iter().map(normalize).collect())
+            }
+            (RedirsValue::Push(vals), _) => {
+                RedirsValue::Push(vals.into_iter().map(normalize).collect())
+            }
+            (RedirsValue::Attribute(attrs, value), _) => {
+                RedirsValue::Attribute(normalize_pairs(attrs), Box::new(normalize(*value)))
+            }
+            (value, _) => value,
+        }
+    }
+    pub fn kind(&self) -> FrameKind {
+        match self {
+            RedirsValue::SimpleString(_) => FrameKind::SimpleString,
+            RedirsValue::SimpleError(_) => FrameKind::SimpleError,
+            RedirsValue::Integer(_) => FrameKind::Integer,
+            RedirsValue::BulkString(_) => FrameKind::BulkString,
+            RedirsValue::Array(_) => FrameKind::Array,
+            RedirsValue::Null => FrameKind::Null,
+            RedirsValue::Bool(_) => FrameKind::Bool,
+            RedirsValue::Double(_) => FrameKind::Double,
+            RedirsValue::BigNumber(_, _) => FrameKind::BigNumber,
+            RedirsValue::BulkError(_) => FrameKind::BulkError,
+            RedirsValue::VerbatimString(_, _) => FrameKind::VerbatimString,
+            RedirsValue::Map(_) => FrameKind::Map,
+            RedirsValue::Set(_) => FrameKind::Set,
+            RedirsValue::Push(_) => FrameKind::Push,
+            RedirsValue::Attribute(_, _) => FrameKind::Attribute,
+        }
+    }
+    // position of the variant in the total order used by `Ord`
+    fn variant_order(&self) -> u8 {
+        match self {
+            RedirsValue::SimpleString(_) => 0,
+            RedirsValue::SimpleError(_) => 1,
+            RedirsValue::Integer(_) => 2,
+            RedirsValue::BulkString(_) => 3,
+            RedirsValue::Array(_) => 4,
+            RedirsValue::Null => 5,
+            RedirsValue::Bool(_) => 6,
+            RedirsValue::Double(_) => 7,
+            RedirsValue::BigNumber(_, _) => 8,
+            RedirsValue::BulkError(_) => 9,
+            RedirsValue::VerbatimString(_, _) => 10,
+            RedirsValue::Map(_) => 11,
+            RedirsValue::Set(_) => 12,
+            RedirsValue::Push(_) => 13,
+            RedirsValue::Attribute(_, _) => 14,
+        }
+    }
+}
+
+impl PartialEq for RedirsValue {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RedirsValue {}
+
+impl PartialOrd for RedirsValue {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// a total order: values of different variants follow the declaration order of
+// the variants, values of the same variant compare their contents and
+// aggregates compare element by element. Doubles use the IEEE 754 total order,
+// so every NaN equals itself and -0.0 sorts before 0.0, which agrees with
+// hashing doubles by their bits. Attributes are metadata, a value carrying
+// them compares and hashes as the bare value
+impl Ord for RedirsValue {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (a, b) = (self.unattributed(), other.unattributed());
+        match (a, b) {
+            (RedirsValue::SimpleString(a), RedirsValue::SimpleString(b)) => a.cmp(b),
+            (RedirsValue::SimpleError(a), RedirsValue::SimpleError(b)) => a.cmp(b),
+            (RedirsValue::Integer(a), RedirsValue::Integer(b)) => a.cmp(b),
+            (RedirsValue::BulkString(a), RedirsValue::BulkString(b)) => a.cmp(b),
+            (RedirsValue::Array(a), RedirsValue::Array(b)) => a.cmp(b),
+            (RedirsValue::Null, RedirsValue::Null) => Ordering::Equal,
+            (RedirsValue::Bool(a), RedirsValue::Bool(b)) => a.cmp(b),
+            (RedirsValue::Double(a), RedirsValue::Double(b)) => a.total_cmp(b),
+            (RedirsValue::BigNumber(sa, a), RedirsValue::BigNumber(sb, b)) => {
+                sa.cmp(sb).then_with(|| a.cmp(b))
+            }
+            (RedirsValue::BulkError(a), RedirsValue::BulkError(b)) => a.cmp(b),
+            (RedirsValue::VerbatimString(ea, a), RedirsValue::VerbatimString(eb, b)) => {
+                ea.cmp(eb).then_with(|| a.cmp(b))
+            }
+            (RedirsValue::Map(a), RedirsValue::Map(b)) => a.cmp(b),
+            (RedirsValue::Set(a), RedirsValue::Set(b)) => {
+                // sets have no intrinsic order, compare their sorted elements
+                let mut a: Vec<_> = a.iter().collect();
+                let mut b: Vec<_> = b.iter().collect();
+                a.sort();
+                b.sort();
+                a.cmp(&b)
+            }
+            (RedirsValue::Push(a), RedirsValue::Push(b)) => a.cmp(b),
+            (a, b) => a.variant_order().cmp(&b.variant_order()),
+        }
+    }
+}
+
+impl Hash for RedirsValue {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        let value = self.unattributed();
+        value.variant_order().hash(state);
+        match value {
+            RedirsValue::SimpleString(s) => s.hash(state),
+            RedirsValue::SimpleError(s) => s.hash(state),
+            RedirsValue::Integer(i) => i.hash(state),
+            RedirsValue::BulkString(s) => s.hash(state),
+            RedirsValue::Array(arr) => arr.hash(state),
+            RedirsValue::Null => {}
+            RedirsValue::Bool(b) => b.hash(state),
+            RedirsValue::Double(d) => d.to_bits().hash(state),
+            RedirsValue::BigNumber(sign, value) => {
+                sign.hash(state);
+                value.hash(state);
+            }
+            RedirsValue::BulkError(err) => err.hash(state),
+            RedirsValue::VerbatimString(enc, s) => {
+                enc.hash(state);
+                s.hash(state);
+            }
+            RedirsValue::Map(map) => map.hash(state),
+            // a `BTreeSet` iterates in order already
+            #[cfg(not(feature = "std"))]
+            RedirsValue::Set(set) => {
+                set.len().hash(state);
+                set.iter().for_each(|v| v.hash(state));
+            }
+            #[cfg(feature = "std")]
+            RedirsValue::Set(set) => {
+                // order independent: combine the hashes of the single elements
+                let combined = set.iter().fold(0u64, |acc, v| {
+                    let mut hasher = DefaultHasher::new();
+                    v.hash(&mut hasher);
+                    acc.wrapping_add(hasher.finish())
+                });
+                set.len().hash(state);
+                combined.hash(state);
+            }
+            RedirsValue::Push(vals) => vals.hash(state),
+            // unreachable, `unattributed` never is an attribute
+            RedirsValue::Attribute(_, value) => value.hash(state),
+        }
+    }
+}
+
+// a double as RESP3 spells it, also the text of RESP2 double replies
+struct DoubleText(f64);
+
+impl Display for DoubleText {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        match self.0 {
+            // `Display` would print `NaN` where RESP3 wants `nan`
+            d if d.is_nan() => f.write_str("nan"),
+            // exponent notation where `%.17g` would switch to it, so `f64::MAX`
+            // and subnormals are not hundreds of digits long
+            d if d.is_finite() && d != 0.0 && !(1e-4..1e17).contains(&d.abs()) => {
+                write!(f, "{d:e}")
+            }
+            d => write!(f, "{d}"),
+        }
+    }
+}
+
+fn write_all<'v, T: Write>(
+    out: &mut T,
+    version: ProcVersion,
+    mut vals: impl Iterator<Item = &'v RedirsValue>,
+) -> io::Result<()> {
+    vals.try_for_each(|v| v.write_resp(out, version))
+}
+
+fn write_bulk<T: Write>(out: &mut T, payload: &[u8]) -> io::Result<()> {
+    write!(out, "${}{SPACER}", payload.len())?;
+    out.write_all(payload)?;
+    out.write_all(SPACER.as_bytes())
+}
+
+// a bulk string of `len` bytes pulled from `payload` through a small copy
+// buffer, for payloads too big to hold in memory. A payload of the wrong length
+// is an error, one too short leaves the frame truncated and the sink unusable
+#[cfg(feature = "std")]
+pub fn write_bulk_from<T: Write, R: Read>(out: &mut T, len: u64, mut payload: R) -> io::Result<()> {
+    write!(out, "${len}{SPACER}")?;
+    let copied = std::io::copy(&mut payload.by_ref().take(len), out)?;
+    if copied < len {
+        return Err(io::Error::new(
+            std::io::ErrorKind::UnexpectedEof,
+            format!("bulk payload ended after {copied} of {len} bytes"),
+        ));
+    }
+    out.write_all(SPACER.as_bytes())?;
+    match payload.read(&mut [0])? {
+        0 => Ok(()),
+        _ => Err(io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("bulk payload longer than {len} bytes"),
+        )),
+    }
+}
+
+// an error as a RESP2 simple error, line breaks turned into spaces
+fn write_error_line<T: Write>(out: &mut T, err: &[u8]) -> io::Result<()> {
+    out.write_all(b"-")?;
+    let line: Vec<u8> = err
+        .iter()
+        .map(|&b| match b {
+            b'\r' | b'\n' => b' ',
+            b => b,
+        })
+        .collect();
+    out.write_all(&line)?;
+    out.write_all(SPACER.as_bytes())
+}
+
+impl RedirsValue {
+    // writes the value for a client speaking `version`, RESP2 has no frames for
+    // the RESP3 types so they are downgraded the way redis does after HELLO 2
+    pub fn write_resp<T: Write>(&self, out: &mut T, version: ProcVersion) -> io::Result<()> {
+        if version == ProcVersion::V3 {
+            return self.write_resp_str(out);
+        }
+        match self {
+            RedirsValue::Null => write!(out, "$-1{SPACER}"),
+            RedirsValue::Bool(b) => write!(out, ":{}{SPACER}", *b as u8),
+            RedirsValue::Double(d) => write_bulk(out, DoubleText(*d).to_string().as_bytes()),
+            RedirsValue::BigNumber(Sign::Positive, digits) => write_bulk(out, digits.as_bytes()),
+            RedirsValue::BigNumber(Sign::Negative, digits) => {
+                write_bulk(out, format!("-{digits}").as_bytes())
+            }
+            // simple errors cannot span lines
+            RedirsValue::BulkError(err) => write_error_line(out, err),
+            RedirsValue::SimpleError(err) if spans_lines(err) => {
+                write_error_line(out, err.as_bytes())
+            }
+            RedirsValue::VerbatimString(_, s) => write_bulk(out, s),
+            RedirsValue::Map(map) => {
+                write!(out, "*{}{SPACER}", map.len() * 2)?;
+                write_all(out, version, map.iter().flat_map(|(k, v)| [k, v]))
+            }
+            RedirsValue::Set(set) => {
+                write!(out, "*{}{SPACER}", set.len())?;
+                write_all(out, version, set.iter())
+            }
+            RedirsValue::Array(Some(arr)) | RedirsValue::Push(arr) => {
+                write!(out, "*{}{SPACER}", arr.len())?;
+                write_all(out, version, arr.iter())
+            }
+            // there is no out of band data in RESP2
+            RedirsValue::Attribute(_, value) => value.write_resp(out, version),
+            value => value.write_resp_str(out),
+        }
+    }
+    // `write_resp` with the elements of sets at any depth sorted by their
+    // encoding, the same value is always the same bytes whatever the order
+    // of its `HashSet`s, e.g. for golden files or caching replies
+    pub fn write_resp_canonical<T: Write>(
+        &self,
+        out: &mut T,
+        version: ProcVersion,
+    ) -> io::Result<()> {
+        let v3 = version == ProcVersion::V3;
+        let write_pairs = |out: &mut T, map: &RedirsMap| {
+            map.iter().try_for_each(|(k, v)| {
+                k.write_resp_canonical(out, version)?;
+                v.write_resp_canonical(out, version)
+            })
+        };
+        match self {
+            RedirsValue::Set(set) => {
+                let mut vals = set
+                    .iter()
+                    .map(|v| {
+                        let mut buf = Vec::with_capacity(v.encoded_len());
+                        v.write_resp_canonical(&mut buf, version).map(|_| buf)
+                    })
+                    .collect::<io::Result<Vec<_>>>()?;
+                vals.sort_unstable();
+                write!(out, "{}{}{SPACER}", if v3 { '~' } else { '*' }, vals.len())?;
+                vals.iter().try_for_each(|v| out.write_all(v))
+            }
+            RedirsValue::Array(Some(vals)) => {
+                write!(out, "*{}{SPACER}", vals.len())?;
+                vals.iter()
+                    .try_for_each(|v| v.write_resp_canonical(out, version))
+            }
+            RedirsValue::Push(vals) => {
+                write!(out, "{}{}{SPACER}", if v3 { '>' } else { '*' }, vals.len())?;
+                vals.iter()
+                    .try_for_each(|v| v.write_resp_canonical(out, version))
+            }
+            RedirsValue::Map(map) if v3 => {
+                write!(out, "%{}{SPACER}", map.len())?;
+                write_pairs(out, map)
+            }
+            RedirsValue::Map(map) => {
+                write!(out, "*{}{SPACER}", map.len() * 2)?;
+                write_pairs(out, map)
+            }
+            RedirsValue::Attribute(attrs, value) if v3 => {
+                write!(out, "|{}{SPACER}", attrs.len())?;
+                write_pairs(out, attrs)?;
+                value.write_resp_canonical(out, version)
+            }
+            RedirsValue::Attribute(_, value) => value.write_resp_canonical(out, version),
+            value => value.write_resp(out, version),
+        }
+    }
+}
+
+// digits of `n` written in decimal
+fn decimal_len(n: u64) -> usize {
+    n.checked_ilog10().map_or(1, |digits| digits as usize + 1)
+}
+
+fn integer_len(i: i64) -> usize {
+    (i < 0) as usize + decimal_len(i.unsigned_abs())
+}
+
+// a `<prefix><len>\r\n` header
+fn header_len(len: usize) -> usize {
+    1 + decimal_len(len as u64) + SPACER.len()
+}
+
+fn pairs_len(map: &RedirsMap) -> usize {
+    map.iter()
+        .map(|(k, v)| k.encoded_len() + v.encoded_len())
+        .sum()
+}
+
+// counts the bytes formatted into it
+struct Counter(usize);
+
+impl core::fmt::Write for Counter {
+    fn write_str(&mut self, s: &str) -> core::fmt::Result {
+        self.0 += s.len();
+        Ok(())
+    }
+}
+
+// the tag, the colon and the payload
+fn verbatim_len(enc: &VerbatimEncoding, payload: &[u8]) -> usize {
+    enc.tag().len() + 1 + payload.len()
+}
+
+impl RedirsValue {
+    // the exact number of bytes `write_resp_str` produces for the value
+    pub fn encoded_len(&self) -> usize {
+        let line = |len: usize| 1 + len + SPACER.len();
+        let bulk = |len: usize| header_len(len) + len + SPACER.len();
+        match self {
+            RedirsValue::SimpleString(s) | RedirsValue::SimpleError(s) if spans_lines(s) => {
+                bulk(s.len())
+            }
+            RedirsValue::SimpleString(s) | RedirsValue::SimpleError(s) => line(s.len()),
+            RedirsValue::Integer(i) => line(integer_len(*i)),
+            RedirsValue::BulkString(Some(s)) => bulk(s.len()),
+            RedirsValue::BulkString(None) | RedirsValue::Array(None) => line(2),
+            RedirsValue::Array(Some(vals)) | RedirsValue::Push(vals) => {
+                header_len(vals.len()) + vals.iter().map(Self::encoded_len).sum::<usize>()
+            }
+            RedirsValue::Null => line(0),
+            RedirsValue::Bool(_) => line(1),
+            RedirsValue::Double(d) => {
+                let mut counter = Counter(0);
+                let _ =
+                    core::fmt::Write::write_fmt(&mut counter, format_args!("{}", DoubleText(*d)));
+                line(counter.0)
+            }
+            RedirsValue::BigNumber(_, digits) if spans_lines(digits) => bulk(1 + digits.len()),
+            RedirsValue::BigNumber(_, digits) => line(1 + digits.len()),
+            RedirsValue::BulkError(err) => bulk(err.len()),
+            RedirsValue::VerbatimString(enc, s) => bulk(verbatim_len(enc, s)),
+            RedirsValue::Map(map) => header_len(map.len()) + pairs_len(map),
+            RedirsValue::Set(set) => {
+                header_len(set.len()) + set.iter().map(Self::encoded_len).sum::<usize>()
+            }
+            RedirsValue::Attribute(attrs, value) => {
+                header_len(attrs.len()) + pairs_len(attrs) + value.encoded_len()
+            }
+        }
+    }
+    // the serialized value in a buffer allocated once, of the exact size
+    pub fn to_resp_bytes(&self) -> Vec<u8> {
+        let len = self.encoded_len();
+        let mut out = Vec::with_capacity(len);
+        self.write_resp_str(&mut out)
+            .expect("writing to a Vec cannot fail");
+        debug_assert_eq!(out.len(), len);
+        out
+    }
 }
 
 impl RedirsOutput for RedirsValue {
+    // sized to the value, so small replies do not allocate the whole buffer
+    #[cfg(feature = "std")]
+    fn write_resp_buffered<T: Write>(&self, out: &mut T) -> io::Result<()> {
+        let len = self.encoded_len().min(WRITE_BUFFER_LEN);
+        let mut out = std::io::BufWriter::with_capacity(len, out);
+        self.write_resp_str(&mut out)?;
+        out.flush()
+    }
     fn write_resp_str<T: Write>(&self, out: &mut T) -> io::Result<()> {
         match self {
+            // text spanning lines goes into the bulk frame of the same kind, every
+            // simple frame written is a single line whatever the value holds
+            RedirsValue::SimpleString(s) if spans_lines(s) => write_bulk(out, s.as_bytes()),
+            RedirsValue::SimpleError(s) if spans_lines(s) => {
+                write!(out, "!{}{SPACER}", s.len())?;
+                out.write_all(s.as_bytes())?;
+                out.write_all(SPACER.as_bytes())
+            }
+            RedirsValue::BigNumber(sign, value) if spans_lines(value) => {
+                write_bulk(out, format!("{sign}{value}").as_bytes())
+            }
             RedirsValue::SimpleString(s) => write!(out, "+{s}{SPACER}"),
             RedirsValue::SimpleError(s) => write!(out, "-{s}{SPACER}"),
             RedirsValue::Integer(i) => write!(out, ":{i}{SPACER}"),
             RedirsValue::BulkString(s) => match s {
-                Some(s) => write!(out, "${}{SPACER}{s}{SPACER}", s.len()),
+                Some(s) => write_bulk(out, s),
                 None => write!(out, "$-1{SPACER}"),
             },
             RedirsValue::Array(arr) => match arr {
@@ -125,11 +1334,20 @@ impl RedirsOutput for RedirsValue {
                 true => write!(out, "#t{SPACER}"),
                 false => write!(out, "#f{SPACER}"),
             },
-            RedirsValue::Double(d) => write!(out, ",{d}{SPACER}"),
+            RedirsValue::Double(d) => write!(out, ",{}{SPACER}", DoubleText(*d)),
             RedirsValue::BigNumber(sign, value) => write!(out, "({sign}{value}{SPACER}"),
-            RedirsValue::BulkError(err) => write!(out, "!{}{SPACER}{err}{SPACER}", err.len()),
+            RedirsValue::BulkError(err) => {
+                write!(out, "!{}{SPACER}", err.len())?;
+                out.write_all(err)?;
+                out.write_all(SPACER.as_bytes())
+            }
             RedirsValue::VerbatimString(enc, s) => {
-                write!(out, "={}{SPACER}{enc}:{s}{SPACER}", s.len() + 4)
+                // the tag is written raw, `Display` would mangle a non UTF-8 one
+                write!(out, "={}{SPACER}", verbatim_len(enc, s))?;
+                out.write_all(enc.tag())?;
+                out.write_all(b":")?;
+                out.write_all(s)?;
+                out.write_all(SPACER.as_bytes())
             }
             RedirsValue::Map(map) => {
                 write!(out, "%{}{SPACER}", map.len())?;
@@ -155,39 +1373,492 @@ impl RedirsOutput for RedirsValue {
                     .find(|x| x.is_err())
                     .unwrap_or(Ok(()))
             }
+            RedirsValue::Attribute(attrs, value) => {
+                write!(out, "|{}{SPACER}", attrs.len())?;
+                attrs
+                    .iter()
+                    .map(|(k, v)| {
+                        k.write_resp_str(out)?;
+                        v.write_resp_str(out)
+                    })
+                    .find(|x| x.is_err())
+                    .unwrap_or(Ok(()))?;
+                value.write_resp_str(out)
+            }
+        }
+    }
+}
+
+// writes a streamed string (`$?`) from chunks whose total length is not known
+// up front, empty chunks are skipped since a zero length chunk ends the string
+pub fn write_resp_streamed_bulk<T, C>(
+    out: &mut T,
+    chunks: impl IntoIterator<Item = C>,
+) -> io::Result<()>
+where
+    T: Write,
+    C: AsRef<[u8]>,
+{
+    write!(out, "$?{SPACER}")?;
+    for chunk in chunks {
+        let chunk = chunk.as_ref();
+        if !chunk.is_empty() {
+            write!(out, ";{}{SPACER}", chunk.len())?;
+            out.write_all(chunk)?;
+            out.write_all(SPACER.as_bytes())?;
         }
     }
+    write!(out, ";0{SPACER}")
 }
 
+// lexes the first frame of `buffer` and returns it with the number of bytes it
+// spans, so pipelined input can be drained frame by frame
+pub fn lex_frame(buffer: &[u8]) -> Result<(RedirsValue, usize), RedirsError> {
+    let mut lexer = Lexer::new(buffer);
+    let value = lexer.lex()?;
+    Ok((value, lexer.position()))
+}
+
+type Pair<'a> = (RedirsValueRef<'a>, RedirsValueRef<'a>);
+
 pub struct Lexer<'o> {
-    buffer: &'o str,
+    // never rebound while lexing, `curr_pos` is the only cursor state so the
+    // read_* helpers compose in any order
+    buffer: &'o [u8],
     curr_pos: usize,
+    depth: usize,
+    limits: ParseLimits,
 }
 
 impl<'o> Lexer<'o> {
-    pub fn pop(&mut self) -> &'o str {
+    pub fn new(buffer: &'o [u8]) -> Self {
+        Self::with_limits(buffer, ParseLimits::default())
+    }
+    pub fn with_limits(buffer: &'o [u8], limits: ParseLimits) -> Self {
+        Self {
+            buffer,
+            curr_pos: 0,
+            depth: 0,
+            limits,
+        }
+    }
+    pub fn pop(&mut self) -> Option<u8> {
+        let byte = self.peek()?;
         self.curr_pos += 1;
-        &self.buffer[self.curr_pos..self.curr_pos]
+        Some(byte)
     }
-    pub fn peek(&mut self) -> &'o str {
-        &self.buffer[self.curr_pos..self.curr_pos]
+    pub fn peek(&self) -> Option<u8> {
+        self.buffer.get(self.curr_pos).copied()
+    }
+    // offset of the next unread byte, i.e. the count of bytes consumed so far
+    pub fn position(&self) -> usize {
+        self.curr_pos
+    }
+    pub fn remaining(&self) -> &'o [u8] {
+        &self.buffer[self.curr_pos..]
+    }
+    // offset in the buffer of a slice handed out by the read_* helpers
+    fn offset_of(&self, slice: &'o [u8]) -> usize {
+        slice.as_ptr() as usize - self.buffer.as_ptr() as usize
+    }
+    fn unexpected(&self, expected: Expected, found: &'o [u8]) -> RedirsError {
+        RedirsError::ParsingError {
+            offset: self.offset_of(found),
+            expected,
+            found: found.to_vec(),
+        }
     }
     pub fn read_spacer(&mut self) -> Result<(), RedirsError> {
-        match &self.buffer[self.curr_pos..self.curr_pos + 2] {
-            SPACER => {
-                self.curr_pos += 2;
-                Ok(())
-            }
-            _ => Err(RedirsError::WhitespaceError),
+        let rest = self.remaining();
+        if rest.starts_with(SPACER.as_bytes()) {
+            self.curr_pos += SPACER.len();
+            Ok(())
+        } else if SPACER.as_bytes().starts_with(rest) {
+            Err(RedirsError::Incomplete(Some(SPACER.len() - rest.len())))
+        } else {
+            Err(RedirsError::WhitespaceError(self.curr_pos))
         }
     }
+    fn read_line(&mut self) -> Result<&'o [u8], RedirsError> {
+        let rest = self.remaining();
+        let end = rest
+            .windows(SPACER.len())
+            .position(|w| w == SPACER.as_bytes())
+            .ok_or(RedirsError::Incomplete(None))?;
+        self.curr_pos += end + SPACER.len();
+        Ok(&rest[..end])
+    }
+    // the text of a simple frame, a lone CR or LF in it is an error since
+    // writing such a value back would have to change its frame type
     pub fn read_str(&mut self) -> Result<&'o str, RedirsError> {
-        let splits = &mut self.buffer[self.curr_pos..].splitn(2, SPACER);
-        let out = splits.next().ok_or(RedirsError::StringError)?;
-        self.buffer = splits.next().ok_or(RedirsError::StringError)?;
+        let start = self.curr_pos;
+        let line = self.read_line()?;
+        if let Some(at) = line.iter().position(|b| matches!(b, b'\r' | b'\n')) {
+            self.curr_pos = start;
+            return Err(RedirsError::ParsingError {
+                offset: start + at,
+                expected: Expected::Line,
+                found: line.to_vec(),
+            });
+        }
+        core::str::from_utf8(line).map_err(|_| {
+            self.curr_pos = start;
+            RedirsError::StringError(start)
+        })
+    }
+    // an integer line, `i64::from_str` accepts an optional sign and rejects overflow
+    fn read_number(&mut self, expected: Expected) -> Result<i64, RedirsError> {
+        let line = self.read_line()?;
+        core::str::from_utf8(line)
+            .ok()
+            .and_then(|s| s.parse().ok())
+            .ok_or_else(|| self.unexpected(expected, line))
+    }
+    fn read_integer(&mut self) -> Result<i64, RedirsError> {
+        self.read_number(Expected::Integer)
+    }
+    // the length of the frame started by `prefix`, `None` for the -1 null marker
+    fn read_len(&mut self, prefix: u8) -> Result<Option<usize>, RedirsError> {
+        let offset = self.curr_pos;
+        match self.read_number(Expected::Length(prefix))? {
+            -1 => Ok(None),
+            len if len < 0 => Err(RedirsError::InvalidLength {
+                offset,
+                prefix,
+                len,
+            }),
+            len => Ok(Some(usize::try_from(len).unwrap_or(usize::MAX))),
+        }
+    }
+    fn read_non_null_len(&mut self, prefix: u8) -> Result<usize, RedirsError> {
+        let offset = self.curr_pos;
+        self.read_len(prefix)?.ok_or(RedirsError::InvalidLength {
+            offset,
+            prefix,
+            len: -1,
+        })
+    }
+    fn check_bulk_len(&self, len: usize) -> Result<(), RedirsError> {
+        match len > self.limits.max_bulk_len {
+            true => Err(RedirsError::LimitExceeded(ParseLimit::BulkLen(
+                self.limits.max_bulk_len,
+            ))),
+            false => Ok(()),
+        }
+    }
+    fn read_bulk_str(&mut self, prefix: u8) -> Result<Option<&'o [u8]>, RedirsError> {
+        let Some(len) = self.read_len(prefix)? else {
+            return Ok(None);
+        };
+        self.check_bulk_len(len)?;
+        self.read_payload(len).map(Some)
+    }
+    fn read_non_null_bulk_str(&mut self, prefix: u8) -> Result<&'o [u8], RedirsError> {
+        let len = self.read_non_null_len(prefix)?;
+        self.check_bulk_len(len)?;
+        self.read_payload(len)
+    }
+    fn read_payload(&mut self, len: usize) -> Result<&'o [u8], RedirsError> {
+        // the payload is binary safe, so it is read by length and never split on SPACER
+        // saturating, the limits can be raised up to `usize::MAX`
+        let end = self.curr_pos.saturating_add(len);
+        if end > self.buffer.len() {
+            return Err(RedirsError::Incomplete(Some(
+                end.saturating_add(SPACER.len()) - self.buffer.len(),
+            )));
+        }
+        let out = &self.buffer[self.curr_pos..end];
+        self.curr_pos = end;
+        self.read_spacer()?;
         Ok(out)
     }
+    // a `?` length opens a streamed string or aggregate
+    fn read_streamed_marker(&mut self) -> bool {
+        let streamed = self.remaining().starts_with(b"?\r\n");
+        if streamed {
+            self.curr_pos += 3;
+        }
+        streamed
+    }
+    // `.\r\n` closes a streamed aggregate
+    fn read_stream_end(&mut self) -> Result<bool, RedirsError> {
+        if self.peek() != Some(b'.') {
+            return Ok(false);
+        }
+        let start = self.remaining();
+        self.curr_pos += 1;
+        match self.read_line()? {
+            b"" => Ok(true),
+            line => Err(self.unexpected(Expected::StreamEnd, &start[..line.len() + 1])),
+        }
+    }
+    // the `;<len>` chunks of a streamed string, up to the zero length one
+    fn read_chunks(&mut self) -> Result<Vec<u8>, RedirsError> {
+        let mut out = Vec::new();
+        loop {
+            let rest = self.remaining();
+            match self.pop().ok_or(RedirsError::Incomplete(None))? {
+                b';' => {}
+                _ => return Err(self.unexpected(Expected::Chunk, &rest[..1])),
+            }
+            let len = self.read_non_null_len(b';')?;
+            if len == 0 {
+                return Ok(out);
+            }
+            self.check_bulk_len(out.len().saturating_add(len))?;
+            out.extend_from_slice(self.read_payload(len)?);
+        }
+    }
+    fn read_bulk_string(&mut self) -> Result<RedirsValueRef<'o>, RedirsError> {
+        if self.read_streamed_marker() {
+            // the chunks are not contiguous in the buffer, so only these are owned
+            return Ok(RedirsValueRef::BulkString(Some(Cow::Owned(
+                self.read_chunks()?,
+            ))));
+        }
+        Ok(RedirsValueRef::BulkString(
+            self.read_bulk_str(b'$')?.map(Cow::Borrowed),
+        ))
+    }
+    fn read_aggregate_len(&mut self, prefix: u8) -> Result<Option<usize>, RedirsError> {
+        match self.read_len(prefix)? {
+            Some(len) if len > self.limits.max_elements => Err(RedirsError::LimitExceeded(
+                ParseLimit::Elements(self.limits.max_elements),
+            )),
+            len => Ok(len),
+        }
+    }
+    // `None` stands for a streamed aggregate
+    fn read_streamable_len(&mut self, prefix: u8) -> Result<Option<usize>, RedirsError> {
+        if self.read_streamed_marker() {
+            return Ok(None);
+        }
+        let offset = self.curr_pos;
+        self.read_aggregate_len(prefix)?
+            .ok_or(RedirsError::InvalidLength {
+                offset,
+                prefix,
+                len: -1,
+            })
+            .map(Some)
+    }
+    // whether another element follows, `len` is `None` for streamed aggregates
+    fn has_next(&mut self, read: usize, len: Option<usize>) -> Result<bool, RedirsError> {
+        match len {
+            Some(len) => Ok(read < len),
+            None if self.read_stream_end()? => Ok(false),
+            None if read == self.limits.max_elements => Err(RedirsError::LimitExceeded(
+                ParseLimit::Elements(self.limits.max_elements),
+            )),
+            None => Ok(true),
+        }
+    }
+    fn enter_aggregate(&mut self) -> Result<(), RedirsError> {
+        if self.depth == self.limits.max_depth {
+            return Err(RedirsError::LimitExceeded(ParseLimit::Depth(
+                self.limits.max_depth,
+            )));
+        }
+        self.depth += 1;
+        Ok(())
+    }
+    // the length prefix is untrusted, never reserve more than the input could hold
+    fn capacity_for(&self, len: usize) -> usize {
+        len.min(self.remaining().len() / MIN_FRAME_LEN)
+    }
+    fn read_elements(
+        &mut self,
+        len: Option<usize>,
+    ) -> Result<Vec<RedirsValueRef<'o>>, RedirsError> {
+        self.enter_aggregate()?;
+        let mut arr = Vec::with_capacity(len.map_or(0, |len| self.capacity_for(len)));
+        while self.has_next(arr.len(), len)? {
+            let idx = arr.len();
+            arr.push(self.lex_value().map_err(|e| e.at_element(idx))?);
+        }
+        self.depth -= 1;
+        Ok(arr)
+    }
+    fn read_array(&mut self) -> Result<Option<Vec<RedirsValueRef<'o>>>, RedirsError> {
+        if self.read_streamed_marker() {
+            return self.read_elements(None).map(Some);
+        }
+        match self.read_aggregate_len(b'*')? {
+            Some(len) => self.read_elements(Some(len)).map(Some),
+            None => Ok(None),
+        }
+    }
+    fn read_null(&mut self) -> Result<RedirsValueRef<'o>, RedirsError> {
+        match self.read_line()? {
+            b"" => Ok(RedirsValueRef::Null),
+            line => Err(self.unexpected(Expected::Null, line)),
+        }
+    }
+    fn read_bool(&mut self) -> Result<RedirsValueRef<'o>, RedirsError> {
+        match self.read_line()? {
+            b"t" => Ok(RedirsValueRef::Bool(true)),
+            b"f" => Ok(RedirsValueRef::Bool(false)),
+            line => Err(self.unexpected(Expected::Bool, line)),
+        }
+    }
+    fn read_double(&mut self) -> Result<RedirsValueRef<'o>, RedirsError> {
+        let line = self.read_line()?;
+        let d = match line {
+            b"inf" | b"+inf" => Some(f64::INFINITY),
+            b"-inf" => Some(f64::NEG_INFINITY),
+            b"nan" => Some(f64::NAN),
+            // `f64::from_str` also takes `infinity` and any casing of `nan`
+            _ if line
+                .iter()
+                .all(|b| matches!(b, b'0'..=b'9' | b'+' | b'-' | b'.' | b'e' | b'E')) =>
+            {
+                core::str::from_utf8(line).ok().and_then(|s| s.parse().ok())
+            }
+            _ => None,
+        };
+        d.map(RedirsValueRef::Double)
+            .ok_or_else(|| self.unexpected(Expected::Double, line))
+    }
+    fn read_big_number(&mut self) -> Result<RedirsValueRef<'o>, RedirsError> {
+        let line = self.read_line()?;
+        let (sign, digits) =
+            split_big_number(line).ok_or_else(|| self.unexpected(Expected::BigNumber, line))?;
+        Ok(RedirsValueRef::BigNumber(sign, digits))
+    }
+    fn read_verbatim_str(&mut self) -> Result<RedirsValueRef<'o>, RedirsError> {
+        let s = self.read_non_null_bulk_str(b'=')?;
+        // the payload is prefixed by a three byte tag and a colon
+        let enc = match s {
+            [a, b, c, b':', ..] => VerbatimEncoding::new(&[*a, *b, *c]).ok(),
+            _ => None,
+        };
+        match enc {
+            Some(enc) => Ok(RedirsValueRef::VerbatimString(enc, &s[4..])),
+            None => Err(self.unexpected(Expected::VerbatimEncoding, &s[..s.len().min(4)])),
+        }
+    }
+    fn read_pairs(&mut self, prefix: u8) -> Result<Vec<Pair<'o>>, RedirsError> {
+        let len = self.read_streamable_len(prefix)?;
+        let mut map = Vec::with_capacity(len.map_or(0, |len| self.capacity_for(len)));
+        let mut idx = 0;
+        while self.has_next(idx, len)? {
+            let (k, v) = self
+                .lex_value()
+                .and_then(|k| Ok((k, self.lex_value()?)))
+                .map_err(|e| e.at_element(idx))?;
+            map.push((k, v));
+            idx += 1;
+        }
+        Ok(map)
+    }
+    fn read_map(&mut self) -> Result<RedirsValueRef<'o>, RedirsError> {
+        self.enter_aggregate()?;
+        let map = self.read_pairs(b'%')?;
+        self.depth -= 1;
+        Ok(RedirsValueRef::Map(map))
+    }
+    fn read_attribute(&mut self) -> Result<RedirsValueRef<'o>, RedirsError> {
+        // the attributed value counts as nested, so chained attributes hit the depth limit
+        self.enter_aggregate()?;
+        let attrs = self.read_pairs(b'|')?;
+        let value = self.lex_value()?;
+        self.depth -= 1;
+        Ok(RedirsValueRef::Attribute(attrs, Box::new(value)))
+    }
+    fn read_set(&mut self) -> Result<RedirsValueRef<'o>, RedirsError> {
+        let len = self.read_streamable_len(b'~')?;
+        Ok(RedirsValueRef::Set(self.read_elements(len)?))
+    }
+    fn read_push(&mut self) -> Result<RedirsValueRef<'o>, RedirsError> {
+        let len = self.read_streamable_len(b'>')?;
+        Ok(RedirsValueRef::Push(self.read_elements(len)?))
+    }
+    // the type of the next frame, without moving past it
+    pub fn peek_type(&self) -> Result<FrameKind, RedirsError> {
+        let rest = self.remaining();
+        let prefix = *rest.first().ok_or(RedirsError::Incomplete(None))?;
+        FrameKind::from_prefix(prefix).ok_or_else(|| self.unexpected(Expected::Prefix, &rest[..1]))
+    }
+    // moves past the next frame without building its value, on error the
+    // position is left untouched as for `lex`
+    pub fn skip_value(&mut self) -> Result<(), RedirsError> {
+        let start = self.curr_pos;
+        self.skip_frame().inspect_err(|_| {
+            self.curr_pos = start;
+            self.depth = 0;
+        })
+    }
+    fn skip_frame(&mut self) -> Result<(), RedirsError> {
+        let kind = self.peek_type()?;
+        // aggregates are walked, with maps and attributes made of pairs
+        let (prefix, per_element) = match kind {
+            FrameKind::Array => (b'*', 1),
+            FrameKind::Set => (b'~', 1),
+            FrameKind::Push => (b'>', 1),
+            FrameKind::Map => (b'%', 2),
+            FrameKind::Attribute => (b'|', 2),
+            // scalars hold at most one borrowed slice, lexing them costs nothing
+            _ => return self.lex_value().map(drop),
+        };
+        self.curr_pos += 1;
+        let len = match kind {
+            FrameKind::Array if self.read_streamed_marker() => None,
+            // only arrays have a null form
+            FrameKind::Array => match self.read_aggregate_len(prefix)? {
+                Some(len) => Some(len),
+                None => return Ok(()),
+            },
+            _ => self.read_streamable_len(prefix)?,
+        };
+        self.enter_aggregate()?;
+        let mut idx = 0;
+        while self.has_next(idx, len)? {
+            for _ in 0..per_element {
+                self.skip_frame().map_err(|e| e.at_element(idx))?;
+            }
+            idx += 1;
+        }
+        // the attributed value counts as nested, same as in `read_attribute`
+        if kind == FrameKind::Attribute {
+            self.skip_frame()?;
+        }
+        self.depth -= 1;
+        Ok(())
+    }
+    // on error the position is left untouched, so after an `Incomplete` the
+    // same input with more bytes appended can be lexed again
     pub fn lex(&mut self) -> Result<RedirsValue, RedirsError> {
-        Err(RedirsError::ParsingError)
+        self.lex_ref().map(RedirsValueRef::into_owned)
+    }
+    // like `lex`, without copying the text out of the buffer
+    pub fn lex_ref(&mut self) -> Result<RedirsValueRef<'o>, RedirsError> {
+        let start = self.curr_pos;
+        self.lex_value().inspect_err(|_| {
+            self.curr_pos = start;
+            self.depth = 0;
+        })
+    }
+    fn lex_value(&mut self) -> Result<RedirsValueRef<'o>, RedirsError> {
+        let rest = self.remaining();
+        match self.pop().ok_or(RedirsError::Incomplete(None))? {
+            b'+' => Ok(RedirsValueRef::SimpleString(self.read_str()?)),
+            b'-' => Ok(RedirsValueRef::SimpleError(self.read_str()?)),
+            b':' => Ok(RedirsValueRef::Integer(self.read_integer()?)),
+            b'$' => self.read_bulk_string(),
+            b'*' => Ok(RedirsValueRef::Array(self.read_array()?)),
+            b'_' => self.read_null(),
+            b'#' => self.read_bool(),
+            b',' => self.read_double(),
+            b'(' => self.read_big_number(),
+            b'!' => Ok(RedirsValueRef::BulkError(
+                self.read_non_null_bulk_str(b'!')?,
+            )),
+            b'=' => self.read_verbatim_str(),
+            b'%' => self.read_map(),
+            b'~' => self.read_set(),
+            b'>' => self.read_push(),
+            b'|' => self.read_attribute(),
+            _ => Err(self.unexpected(Expected::Prefix, &rest[..1])),
+        }
     }
 }
@@ -0,0 +1,78 @@
+// builds a `RedirsValue` out of a literal like notation:
+//
+//   resp!(42)                       Integer(42)
+//   resp!("GET")                    BulkString, strings are binary safe by default
+//   resp!(simple "OK")              SimpleString
+//   resp!(error "ERR unknown")      SimpleError
+//   resp!(nil)                      Null
+//   resp!(true)                     Bool(true)
+//   resp!(["GET", "foo", [1, 2]])   Array of the elements
+//   resp!({"a" => 1, "b" => nil})   Map in the order written
+//
+// anything else is an expression converted with `RedirsValue::from`, elements
+// and map entries take any of these forms
+#[macro_export]
+macro_rules! resp {
+    (nil) => {
+        $crate::RedirsValue::Null
+    };
+    (true) => {
+        $crate::RedirsValue::Bool(true)
+    };
+    (false) => {
+        $crate::RedirsValue::Bool(false)
+    };
+    (simple $s:expr) => {
+        $crate::RedirsValue::SimpleString($crate::__private::String::from($s))
+    };
+    (error $s:expr) => {
+        $crate::RedirsValue::SimpleError($crate::__private::String::from($s))
+    };
+    ([ $($elems:tt)* ]) => {
+        $crate::RedirsValue::Array(Some($crate::resp!(@elems [] [] $($elems)*)))
+    };
+    ({ $($pairs:tt)* }) => {
+        $crate::RedirsValue::Map($crate::RedirsMap::from($crate::resp!(@pairs [] [] $($pairs)*)))
+    };
+
+    // elements are munched a token at a time up to the next comma, so they can
+    // be any number of tokens like `-1` or `simple "OK"`
+    (@elems [$($done:expr,)*] []) => {
+        $crate::__private::vec![$($done,)*]
+    };
+    (@elems [$($done:expr,)*] [$($elem:tt)+]) => {
+        $crate::__private::vec![$($done,)* $crate::resp!($($elem)+)]
+    };
+    (@elems [$($done:expr,)*] [$($elem:tt)+] , $($rest:tt)*) => {
+        $crate::resp!(@elems [$($done,)* $crate::resp!($($elem)+),] [] $($rest)*)
+    };
+    (@elems [$($done:expr,)*] [$($elem:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::resp!(@elems [$($done,)*] [$($elem)* $next] $($rest)*)
+    };
+
+    // keys are munched up to `=>`, values up to the next comma
+    (@pairs [$($done:expr,)*] []) => {
+        $crate::__private::vec![$($done,)*]
+    };
+    (@pairs [$($done:expr,)*] [$($key:tt)+] => $($rest:tt)*) => {
+        $crate::resp!(@value [$($done,)*] [$($key)+] [] $($rest)*)
+    };
+    (@pairs [$($done:expr,)*] [$($key:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::resp!(@pairs [$($done,)*] [$($key)* $next] $($rest)*)
+    };
+    (@value [$($done:expr,)*] [$($key:tt)+] [$($value:tt)+]) => {
+        $crate::__private::vec![$($done,)* ($crate::resp!($($key)+), $crate::resp!($($value)+))]
+    };
+    (@value [$($done:expr,)*] [$($key:tt)+] [$($value:tt)+] , $($rest:tt)*) => {
+        $crate::resp!(
+            @pairs [$($done,)* ($crate::resp!($($key)+), $crate::resp!($($value)+)),] [] $($rest)*
+        )
+    };
+    (@value [$($done:expr,)*] [$($key:tt)+] [$($value:tt)*] $next:tt $($rest:tt)*) => {
+        $crate::resp!(@value [$($done,)*] [$($key)+] [$($value)* $next] $($rest)*)
+    };
+
+    ($value:expr) => {
+        $crate::RedirsValue::from($value)
+    };
+}
@@ -0,0 +1,165 @@
+use std::io::{self, BufRead, Read};
+
+use crate::{
+    inline::MAX_INLINE_LEN, Expected, Lexer, ParseLimit, RedirsError, RedirsValue, SPACER,
+};
+
+// reads a single frame from `reader`, pulling bytes on demand and consuming
+// exactly the bytes of the frame, the rest stays in the reader
+pub fn read_value<R: BufRead>(reader: &mut R) -> Result<RedirsValue, RedirsError> {
+    let mut frame = Vec::new();
+    loop {
+        let chunk = reader.fill_buf().map_err(RedirsError::Io)?;
+        if chunk.is_empty() {
+            return Err(match frame.is_empty() {
+                true => RedirsError::Eof,
+                false => unexpected_eof(),
+            });
+        }
+        let read = frame.len();
+        frame.extend_from_slice(chunk);
+        let mut lexer = Lexer::new(&frame);
+        let mut missing = match lexer.lex() {
+            Ok(value) => {
+                reader.consume(lexer.position() - read);
+                return Ok(value);
+            }
+            Err(RedirsError::Incomplete(missing)) => missing,
+            Err(e) => return Err(e),
+        };
+        reader.consume(frame.len() - read);
+        if missing.is_none() {
+            check_pending_line(&frame)?;
+        }
+        // bytes known to be missing (e.g. a big bulk payload) are pulled
+        // directly, so large frames are not re-lexed once per chunk
+        while let Some(len) = missing {
+            let start = frame.len();
+            reader
+                .by_ref()
+                .take(len as u64)
+                .read_to_end(&mut frame)
+                .map_err(RedirsError::Io)?;
+            if frame.len() - start < len {
+                return Err(unexpected_eof());
+            }
+            missing = match Lexer::new(&frame).lex() {
+                Ok(value) => return Ok(value),
+                Err(RedirsError::Incomplete(missing)) => missing,
+                Err(e) => return Err(e),
+            };
+        }
+    }
+}
+
+// `$`, a sign, the 20 digits of `u64::MAX` and the spacer
+const MAX_BULK_HEADER_LEN: u64 = 24;
+
+// reads the header of a bulk string frame and hands out its payload as a
+// reader instead of a buffer, `None` for the null bulk string. Payloads are
+// not held in memory so `max_bulk_len` does not apply to them
+pub fn read_bulk<R: BufRead>(reader: &mut R) -> Result<Option<BulkReader<'_, R>>, RedirsError> {
+    let mut header = Vec::new();
+    reader
+        .by_ref()
+        .take(MAX_BULK_HEADER_LEN)
+        .read_until(b'\n', &mut header)
+        .map_err(RedirsError::Io)?;
+    match header.last() {
+        Some(b'\n') => {}
+        None => return Err(RedirsError::Eof),
+        Some(_) if header.len() as u64 == MAX_BULK_HEADER_LEN => {
+            return Err(RedirsError::ParsingError {
+                offset: 1,
+                expected: Expected::Length(b'$'),
+                found: header,
+            })
+        }
+        Some(_) => return Err(unexpected_eof()),
+    }
+    let mut lexer = Lexer::new(&header);
+    if lexer.pop() != Some(b'$') {
+        return Err(lexer.unexpected(Expected::Length(b'$'), &header[..1]));
+    }
+    let len = match lexer.read_len(b'$') {
+        Ok(len) => len,
+        // a lone LF ends the header
+        Err(RedirsError::Incomplete(_)) => {
+            return Err(RedirsError::WhitespaceError(header.len() - 1))
+        }
+        Err(e) => return Err(e),
+    };
+    Ok(len.map(|len| BulkReader {
+        inner: reader,
+        remaining: len as u64,
+        terminated: false,
+    }))
+}
+
+// the payload of a bulk string read by `read_bulk`, the spacer after it is
+// checked and consumed along with the last payload byte. Read it to the end
+// before reading the next frame from the underlying reader
+pub struct BulkReader<'a, R> {
+    inner: &'a mut R,
+    remaining: u64,
+    terminated: bool,
+}
+
+impl<R: BufRead> BulkReader<'_, R> {
+    // payload bytes not read yet
+    pub fn remaining(&self) -> u64 {
+        self.remaining
+    }
+    fn terminate(&mut self) -> io::Result<()> {
+        if !self.terminated {
+            let mut spacer = [0; SPACER.len()];
+            self.inner.read_exact(&mut spacer)?;
+            if spacer != SPACER.as_bytes() {
+                return Err(RedirsError::WhitespaceError(0).into());
+            }
+            self.terminated = true;
+        }
+        Ok(())
+    }
+}
+
+impl<R: BufRead> Read for BulkReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if self.remaining == 0 {
+            self.terminate()?;
+            return Ok(0);
+        }
+        let max = buf
+            .len()
+            .min(usize::try_from(self.remaining).unwrap_or(usize::MAX));
+        let n = self.inner.read(&mut buf[..max])?;
+        if n == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        self.remaining -= n as u64;
+        if self.remaining == 0 {
+            self.terminate()?;
+        }
+        Ok(n)
+    }
+}
+
+// a frame waiting for the CRLF of a line only holds the bytes after the last
+// CRLF of it, that line can grow up to the inline request limit as in redis
+// before the frame is given up on instead of buffered without end
+pub(crate) fn check_pending_line(frame: &[u8]) -> Result<(), RedirsError> {
+    let start = frame
+        .windows(SPACER.len())
+        .rposition(|w| w == SPACER.as_bytes())
+        .map_or(0, |at| at + SPACER.len());
+    match frame.len() - start > MAX_INLINE_LEN {
+        true => Err(RedirsError::LimitExceeded(ParseLimit::InlineLen(
+            MAX_INLINE_LEN,
+        ))),
+        false => Ok(()),
+    }
+}
+
+pub(crate) fn unexpected_eof() -> RedirsError {
+    RedirsError::Io(io::ErrorKind::UnexpectedEof.into())
+}
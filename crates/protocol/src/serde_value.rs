@@ -0,0 +1,273 @@
+use std::fmt::Formatter;
+
+use serde::{
+    de::{self, Deserializer, MapAccess, SeqAccess, Visitor},
+    ser::{SerializeMap, SerializeSeq, Serializer},
+    Deserialize, Serialize,
+};
+
+use crate::{split_big_number, DoubleText, RedirsMap, RedirsValue, Sign, VerbatimEncoding};
+
+// strings, integers, finite doubles, booleans, arrays and null map to the
+// natural data of the format, every other value is a map holding one tag:
+//
+//   BulkString(Some(utf8))  "text"
+//   BulkString(Some(bytes)) {"bytes": [0, 255]}
+//   BulkString(None)        {"null_bulk_string": null}
+//   Array(None)             {"null_array": null}
+//   SimpleString            {"simple_string": "OK"}
+//   SimpleError             {"simple_error": "ERR ..."}
+//   Double(non finite)      {"double": "inf"}, "-inf" or "nan"
+//   BigNumber               {"big_number": "-123"}
+//   BulkError               {"bulk_error": payload}
+//   VerbatimString          {"verbatim_string": ["txt", payload]}
+//   Map                     {"map": [[key, value], ...]}
+//   Set                     {"set": [...]}
+//   Push                    {"push": [...]}
+//   Attribute               {"attribute": [[[key, value], ...], value]}
+//
+// payloads are a string when they are UTF-8 and an array of bytes otherwise
+
+// a binary safe payload
+struct Payload<'a>(&'a [u8]);
+
+impl Serialize for Payload<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match std::str::from_utf8(self.0) {
+            Ok(s) => serializer.serialize_str(s),
+            Err(_) => {
+                let mut seq = serializer.serialize_seq(Some(self.0.len()))?;
+                self.0.iter().try_for_each(|b| seq.serialize_element(b))?;
+                seq.end()
+            }
+        }
+    }
+}
+
+struct PayloadBuf(Vec<u8>);
+
+impl<'de> Deserialize<'de> for PayloadBuf {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct PayloadVisitor;
+
+        impl<'de> Visitor<'de> for PayloadVisitor {
+            type Value = PayloadBuf;
+
+            fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+                f.write_str("a string or an array of bytes")
+            }
+            fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+                Ok(PayloadBuf(s.as_bytes().to_vec()))
+            }
+            fn visit_bytes<E: de::Error>(self, b: &[u8]) -> Result<Self::Value, E> {
+                Ok(PayloadBuf(b.to_vec()))
+            }
+            fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+                let mut bytes = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+                while let Some(b) = seq.next_element()? {
+                    bytes.push(b);
+                }
+                Ok(PayloadBuf(bytes))
+            }
+        }
+
+        deserializer.deserialize_any(PayloadVisitor)
+    }
+}
+
+// the pairs of a map as a sequence, keys are not restricted to strings
+struct Pairs<'a>(&'a RedirsMap);
+
+impl Serialize for Pairs<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.0.iter())
+    }
+}
+
+fn tagged<S: Serializer, T: Serialize + ?Sized>(
+    serializer: S,
+    tag: &str,
+    value: &T,
+) -> Result<S::Ok, S::Error> {
+    let mut map = serializer.serialize_map(Some(1))?;
+    map.serialize_entry(tag, value)?;
+    map.end()
+}
+
+impl Serialize for RedirsValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            RedirsValue::BulkString(Some(s)) => match std::str::from_utf8(s) {
+                Ok(s) => serializer.serialize_str(s),
+                Err(_) => tagged(serializer, "bytes", &Payload(s)),
+            },
+            RedirsValue::BulkString(None) => tagged(serializer, "null_bulk_string", &()),
+            RedirsValue::Integer(i) => serializer.serialize_i64(*i),
+            RedirsValue::Array(Some(vals)) => serializer.collect_seq(vals),
+            RedirsValue::Array(None) => tagged(serializer, "null_array", &()),
+            RedirsValue::Null => serializer.serialize_unit(),
+            RedirsValue::Bool(b) => serializer.serialize_bool(*b),
+            RedirsValue::Double(d) if d.is_finite() => serializer.serialize_f64(*d),
+            // most formats have no infinities, JSON no NaN either
+            RedirsValue::Double(d) => tagged(serializer, "double", &DoubleText(*d).to_string()),
+            RedirsValue::SimpleString(s) => tagged(serializer, "simple_string", s),
+            RedirsValue::SimpleError(s) => tagged(serializer, "simple_error", s),
+            RedirsValue::BigNumber(sign, digits) => {
+                let text = match sign {
+                    Sign::Positive => digits.clone(),
+                    Sign::Negative => format!("-{digits}"),
+                };
+                tagged(serializer, "big_number", &text)
+            }
+            RedirsValue::BulkError(err) => tagged(serializer, "bulk_error", &Payload(err)),
+            RedirsValue::VerbatimString(enc, s) => tagged(
+                serializer,
+                "verbatim_string",
+                &(Payload(enc.tag()), Payload(s)),
+            ),
+            RedirsValue::Map(map) => tagged(serializer, "map", &Pairs(map)),
+            RedirsValue::Set(set) => tagged(serializer, "set", set),
+            RedirsValue::Push(vals) => tagged(serializer, "push", vals),
+            RedirsValue::Attribute(attrs, value) => {
+                tagged(serializer, "attribute", &(Pairs(attrs), value))
+            }
+        }
+    }
+}
+
+struct ValueVisitor;
+
+impl<'de> Visitor<'de> for ValueVisitor {
+    type Value = RedirsValue;
+
+    fn expecting(&self, f: &mut Formatter) -> std::fmt::Result {
+        f.write_str("a RESP value")
+    }
+    fn visit_bool<E: de::Error>(self, b: bool) -> Result<Self::Value, E> {
+        Ok(RedirsValue::Bool(b))
+    }
+    fn visit_i64<E: de::Error>(self, i: i64) -> Result<Self::Value, E> {
+        Ok(RedirsValue::Integer(i))
+    }
+    fn visit_u64<E: de::Error>(self, u: u64) -> Result<Self::Value, E> {
+        i64::try_from(u)
+            .map(RedirsValue::Integer)
+            .map_err(|_| E::invalid_value(de::Unexpected::Unsigned(u), &"a 64 bit signed integer"))
+    }
+    fn visit_f64<E: de::Error>(self, d: f64) -> Result<Self::Value, E> {
+        Ok(RedirsValue::Double(d))
+    }
+    fn visit_str<E: de::Error>(self, s: &str) -> Result<Self::Value, E> {
+        Ok(RedirsValue::from(s))
+    }
+    fn visit_string<E: de::Error>(self, s: String) -> Result<Self::Value, E> {
+        Ok(RedirsValue::from(s))
+    }
+    fn visit_bytes<E: de::Error>(self, b: &[u8]) -> Result<Self::Value, E> {
+        Ok(RedirsValue::from(b))
+    }
+    fn visit_byte_buf<E: de::Error>(self, b: Vec<u8>) -> Result<Self::Value, E> {
+        Ok(RedirsValue::from(b))
+    }
+    fn visit_unit<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(RedirsValue::Null)
+    }
+    fn visit_none<E: de::Error>(self) -> Result<Self::Value, E> {
+        Ok(RedirsValue::Null)
+    }
+    fn visit_some<D: Deserializer<'de>>(self, deserializer: D) -> Result<Self::Value, D::Error> {
+        RedirsValue::deserialize(deserializer)
+    }
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let mut vals = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(value) = seq.next_element()? {
+            vals.push(value);
+        }
+        Ok(RedirsValue::Array(Some(vals)))
+    }
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<Self::Value, A::Error> {
+        let tag: String = map
+            .next_key()?
+            .ok_or_else(|| de::Error::invalid_length(0, &"a map with a single tag"))?;
+        let value = match tag.as_str() {
+            "bytes" => RedirsValue::from(map.next_value::<PayloadBuf>()?.0),
+            "null_bulk_string" => {
+                map.next_value::<()>()?;
+                RedirsValue::BulkString(None)
+            }
+            "null_array" => {
+                map.next_value::<()>()?;
+                RedirsValue::Array(None)
+            }
+            "simple_string" => RedirsValue::SimpleString(map.next_value()?),
+            "simple_error" => RedirsValue::SimpleError(map.next_value()?),
+            "double" => {
+                let text: String = map.next_value()?;
+                let d = match text.as_str() {
+                    "inf" | "+inf" => f64::INFINITY,
+                    "-inf" => f64::NEG_INFINITY,
+                    "nan" => f64::NAN,
+                    text => text.parse().map_err(|_| {
+                        de::Error::invalid_value(de::Unexpected::Str(text), &"a double")
+                    })?,
+                };
+                RedirsValue::Double(d)
+            }
+            "big_number" => {
+                let text: String = map.next_value()?;
+                let (sign, digits) = split_big_number(text.as_bytes()).ok_or_else(|| {
+                    de::Error::invalid_value(de::Unexpected::Str(&text), &"a big number")
+                })?;
+                RedirsValue::BigNumber(sign, digits.to_owned())
+            }
+            "bulk_error" => RedirsValue::BulkError(map.next_value::<PayloadBuf>()?.0),
+            "verbatim_string" => {
+                let (tag, s) = map.next_value::<(PayloadBuf, PayloadBuf)>()?;
+                let enc = VerbatimEncoding::new(&tag.0).map_err(|_| {
+                    de::Error::invalid_value(
+                        de::Unexpected::Bytes(&tag.0),
+                        &"three printable ASCII characters",
+                    )
+                })?;
+                RedirsValue::VerbatimString(enc, s.0)
+            }
+            "map" => RedirsValue::Map(RedirsMap::from(
+                map.next_value::<Vec<(RedirsValue, RedirsValue)>>()?,
+            )),
+            "set" => RedirsValue::Set(map.next_value()?),
+            "push" => RedirsValue::Push(map.next_value()?),
+            "attribute" => {
+                let (attrs, value) =
+                    map.next_value::<(Vec<(RedirsValue, RedirsValue)>, RedirsValue)>()?;
+                RedirsValue::Attribute(RedirsMap::from(attrs), Box::new(value))
+            }
+            tag => return Err(de::Error::unknown_variant(tag, TAGS)),
+        };
+        match map.next_key::<de::IgnoredAny>()? {
+            None => Ok(value),
+            Some(_) => Err(de::Error::invalid_length(2, &"a map with a single tag")),
+        }
+    }
+}
+
+const TAGS: &[&str] = &[
+    "bytes",
+    "null_bulk_string",
+    "null_array",
+    "simple_string",
+    "simple_error",
+    "double",
+    "big_number",
+    "bulk_error",
+    "verbatim_string",
+    "map",
+    "set",
+    "push",
+    "attribute",
+];
+
+impl<'de> Deserialize<'de> for RedirsValue {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(ValueVisitor)
+    }
+}
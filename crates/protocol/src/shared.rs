@@ -0,0 +1,65 @@
+use alloc::{borrow::Cow, boxed::Box, vec::Vec};
+
+use bytes::{Bytes, BytesMut};
+
+use crate::{io, Lexer, Pair, RedirsError, RedirsMap, RedirsOutput, RedirsValue, RedirsValueRef};
+
+// appends to a `BytesMut`, the `Write` of `BufMut::writer` needs `std`
+struct Appender<'a>(&'a mut BytesMut);
+
+impl io::Write for Appender<'_> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.extend_from_slice(buf);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn share_pairs(pairs: Vec<Pair<'_>>, frame: &Bytes) -> RedirsMap {
+    pairs
+        .into_iter()
+        .map(|(k, v)| (share(k, frame), share(v, frame)))
+        .collect()
+}
+
+// `into_owned` with the borrowed bulk payloads as slices of `frame`, the
+// buffer `value` was lexed from
+fn share<'o>(value: RedirsValueRef<'o>, frame: &Bytes) -> RedirsValue {
+    let share_all = |vals: Vec<RedirsValueRef<'o>>| vals.into_iter().map(move |v| share(v, frame));
+    match value {
+        RedirsValueRef::BulkString(Some(Cow::Borrowed(s))) => {
+            RedirsValue::BulkString(Some(frame.slice_ref(s)))
+        }
+        RedirsValueRef::Array(Some(vals)) => RedirsValue::Array(Some(share_all(vals).collect())),
+        RedirsValueRef::Map(pairs) => RedirsValue::Map(share_pairs(pairs, frame)),
+        RedirsValueRef::Set(vals) => RedirsValue::Set(share_all(vals).collect()),
+        RedirsValueRef::Push(vals) => RedirsValue::Push(share_all(vals).collect()),
+        RedirsValueRef::Attribute(attrs, value) => {
+            RedirsValue::Attribute(share_pairs(attrs, frame), Box::new(share(*value, frame)))
+        }
+        value => value.into_owned(),
+    }
+}
+
+impl RedirsValue {
+    // lexes the first frame of `buf` and splits it off the front, bulk string
+    // payloads are slices of the split frame rather than copies. On error
+    // `buf` is left untouched, so an `Incomplete` can be retried once more
+    // bytes are appended
+    pub fn parse_from(buf: &mut BytesMut) -> Result<RedirsValue, RedirsError> {
+        // measured first, the slices of a lexed value would keep `buf` borrowed
+        let mut lexer = Lexer::new(buf);
+        lexer.skip_value()?;
+        let frame = buf.split_to(lexer.position()).freeze();
+        let value = Lexer::new(&frame).lex_ref()?;
+        Ok(share(value, &frame))
+    }
+    // appends the frames to `dst`, growing it once to the encoded length
+    pub fn encode_into(&self, dst: &mut BytesMut) {
+        dst.reserve(self.encoded_len());
+        self.write_resp_str(&mut Appender(dst))
+            .expect("appending to a BytesMut cannot fail");
+    }
+}
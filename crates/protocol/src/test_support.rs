@@ -0,0 +1,276 @@
+use std::collections::HashSet;
+
+#[cfg(feature = "proptest")]
+use proptest::prelude::*;
+
+use crate::{
+    Lexer, ProcVersion, RedirsError, RedirsMap, RedirsOutput, RedirsValue, Sign, VerbatimEncoding,
+};
+
+// writes the value and lexes it back, the whole frame must be consumed
+pub fn roundtrip(value: &RedirsValue) -> Result<RedirsValue, RedirsError> {
+    let mut out = Vec::new();
+    value.write_resp_str(&mut out)?;
+    let mut lexer = Lexer::new(&out);
+    let back = lexer.lex()?;
+    match lexer.remaining() {
+        [] => Ok(back),
+        rest => Err(RedirsError::ParsingError {
+            offset: lexer.position(),
+            expected: crate::Expected::Prefix,
+            found: rest.to_vec(),
+        }),
+    }
+}
+
+// seeded generator of values that survive `roundtrip`, it has no
+// dependencies so it also works for plain seed loops without proptest
+pub struct ValueGen {
+    state: u64,
+    version: ProcVersion,
+    // how many aggregates can be nested into each other
+    pub max_depth: usize,
+    // the element count of generated aggregates
+    pub max_elements: usize,
+    // the byte length of generated strings
+    pub max_len: usize,
+}
+
+impl ValueGen {
+    pub fn new(seed: u64, version: ProcVersion) -> Self {
+        Self {
+            // xorshift gets stuck on a zero state
+            state: seed | 1,
+            version,
+            max_depth: 4,
+            max_elements: 8,
+            max_len: 64,
+        }
+    }
+    fn next_u64(&mut self) -> u64 {
+        // xorshift64*
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+    fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n.max(1) as u64) as usize
+    }
+    fn bool(&mut self) -> bool {
+        self.next_u64() & 1 == 1
+    }
+    // any text up to `max_len` bytes, CR and LF only when `line_safe` is false
+    fn text(&mut self, line_safe: bool) -> String {
+        const SPECIAL: [char; 6] = ['\r', '\n', ' ', '"', 'é', '🦀'];
+        let len = self.below(self.max_len + 1);
+        let mut s = String::with_capacity(len);
+        while s.len() < len {
+            let c = match self.below(8) {
+                0 => SPECIAL[self.below(SPECIAL.len())],
+                _ => (b' ' + self.below(95) as u8) as char,
+            };
+            if !(line_safe && matches!(c, '\r' | '\n')) {
+                s.push(c);
+            }
+        }
+        s
+    }
+    // text, or now and then raw bytes that are not UTF-8
+    fn bytes(&mut self) -> Vec<u8> {
+        match self.below(4) {
+            0 => (0..self.below(self.max_len + 1))
+                .map(|_| self.next_u64() as u8)
+                .collect(),
+            _ => self.text(false).into_bytes(),
+        }
+    }
+    fn double(&mut self) -> f64 {
+        match self.below(8) {
+            0 => [
+                0.0,
+                -0.0,
+                f64::INFINITY,
+                f64::NEG_INFINITY,
+                f64::MAX,
+                f64::MIN_POSITIVE,
+                // the smallest subnormal
+                5e-324,
+                f64::NAN,
+            ][self.below(8)],
+            // `nan` on the wire always reads back as the canonical NaN
+            _ => match f64::from_bits(self.next_u64()) {
+                d if d.is_nan() => f64::NAN,
+                d => d,
+            },
+        }
+    }
+    fn elements(&mut self, depth: usize) -> Vec<RedirsValue> {
+        (0..self.below(self.max_elements + 1))
+            .map(|_| self.value_at(depth + 1))
+            .collect()
+    }
+    fn pairs(&mut self, depth: usize) -> RedirsMap {
+        (0..self.below(self.max_elements + 1))
+            .map(|_| (self.value_at(depth + 1), self.value_at(depth + 1)))
+            .collect()
+    }
+    pub fn value(&mut self) -> RedirsValue {
+        self.value_at(0)
+    }
+    fn value_at(&mut self, depth: usize) -> RedirsValue {
+        let kinds = match self.version {
+            ProcVersion::V2 => 5,
+            ProcVersion::V3 => 15,
+        };
+        // past the depth limit aggregates fall back to null
+        let nested = depth < self.max_depth;
+        match self.below(kinds) {
+            0 => RedirsValue::SimpleString(self.text(true)),
+            1 => RedirsValue::SimpleError(self.text(true)),
+            2 => RedirsValue::Integer(self.next_u64() as i64),
+            3 => match self.bool() {
+                true => RedirsValue::from(self.bytes()),
+                false => RedirsValue::BulkString(None),
+            },
+            4 if nested => RedirsValue::Array(self.bool().then(|| self.elements(depth))),
+            4 => RedirsValue::Array(None),
+            5 => RedirsValue::Null,
+            6 => RedirsValue::Bool(self.bool()),
+            7 => RedirsValue::Double(self.double()),
+            8 => {
+                let sign = match self.bool() {
+                    true => Sign::Positive,
+                    false => Sign::Negative,
+                };
+                let digits = (0..=self.below(self.max_len))
+                    .map(|_| (b'0' + self.below(10) as u8) as char)
+                    .collect();
+                RedirsValue::BigNumber(sign, digits)
+            }
+            9 => RedirsValue::BulkError(self.bytes()),
+            10 => {
+                let enc = match self.below(3) {
+                    0 => VerbatimEncoding::Txt,
+                    1 => VerbatimEncoding::Mrk,
+                    _ => VerbatimEncoding::from_tag([0; 3].map(|_| b'!' + self.below(94) as u8)),
+                };
+                RedirsValue::VerbatimString(enc, self.bytes())
+            }
+            11 if nested => RedirsValue::Map(self.pairs(depth)),
+            12 if nested => {
+                RedirsValue::Set(self.elements(depth).into_iter().collect::<HashSet<_>>())
+            }
+            13 if nested => RedirsValue::Push(self.elements(depth)),
+            14 if nested => {
+                let attrs = self.pairs(depth);
+                RedirsValue::Attribute(attrs, Box::new(self.value_at(depth + 1)))
+            }
+            _ => RedirsValue::Null,
+        }
+    }
+}
+
+impl Iterator for ValueGen {
+    type Item = RedirsValue;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some(self.value())
+    }
+}
+
+// proptest strategy of the same values, unlike the seed loops failing cases
+// shrink down to the smallest value that still fails
+#[cfg(feature = "proptest")]
+pub fn arb_value(version: ProcVersion) -> impl Strategy<Value = RedirsValue> {
+    // simple frames hold a single line, the writer moves anything else to bulk frames
+    let line = || "[^\r\n]{0,32}";
+    let bytes = || prop::collection::vec(any::<u8>(), 0..64);
+    let leaf = prop_oneof![
+        line().prop_map(RedirsValue::SimpleString),
+        line().prop_map(RedirsValue::SimpleError),
+        any::<i64>().prop_map(RedirsValue::Integer),
+        prop::option::of(bytes()).prop_map(|b| match b {
+            Some(b) => RedirsValue::from(b),
+            None => RedirsValue::BulkString(None),
+        }),
+    ];
+    let leaf = match version {
+        ProcVersion::V2 => leaf.boxed(),
+        ProcVersion::V3 => prop_oneof![
+            4 => leaf,
+            1 => Just(RedirsValue::Null),
+            1 => any::<bool>().prop_map(RedirsValue::Bool),
+            1 => arb_double().prop_map(RedirsValue::Double),
+            1 => (any::<bool>(), "[0-9]{1,64}").prop_map(|(positive, digits)| {
+                let sign = match positive {
+                    true => Sign::Positive,
+                    false => Sign::Negative,
+                };
+                RedirsValue::BigNumber(sign, digits)
+            }),
+            1 => bytes().prop_map(RedirsValue::BulkError),
+            1 => (arb_encoding(), bytes())
+                .prop_map(|(enc, b)| RedirsValue::VerbatimString(enc, b)),
+        ]
+        .boxed(),
+    };
+    leaf.prop_recursive(4, 64, 8, move |inner| {
+        let elements = || prop::collection::vec(inner.clone(), 0..8);
+        let pairs = || {
+            prop::collection::vec((inner.clone(), inner.clone()), 0..8).prop_map(RedirsMap::from)
+        };
+        let array = prop::option::of(elements()).prop_map(RedirsValue::Array);
+        match version {
+            ProcVersion::V2 => array.boxed(),
+            ProcVersion::V3 => prop_oneof![
+                array,
+                pairs().prop_map(RedirsValue::Map),
+                elements().prop_map(|e| RedirsValue::Set(e.into_iter().collect::<HashSet<_>>())),
+                elements().prop_map(RedirsValue::Push),
+                (pairs(), inner.clone())
+                    .prop_map(|(attrs, value)| RedirsValue::Attribute(attrs, Box::new(value))),
+            ]
+            .boxed(),
+        }
+    })
+}
+
+#[cfg(feature = "proptest")]
+fn arb_double() -> impl Strategy<Value = f64> {
+    prop_oneof![
+        Just(0.0),
+        Just(-0.0),
+        Just(f64::INFINITY),
+        Just(f64::NEG_INFINITY),
+        Just(f64::MIN_POSITIVE),
+        Just(5e-324),
+        Just(f64::NAN),
+        // `nan` on the wire always reads back as the canonical NaN
+        any::<u64>().prop_map(|bits| match f64::from_bits(bits) {
+            d if d.is_nan() => f64::NAN,
+            d => d,
+        }),
+    ]
+}
+
+#[cfg(feature = "proptest")]
+fn arb_encoding() -> impl Strategy<Value = VerbatimEncoding> {
+    prop_oneof![
+        Just(VerbatimEncoding::Txt),
+        Just(VerbatimEncoding::Mrk),
+        prop::array::uniform3(b'!'..=b'~').prop_map(VerbatimEncoding::from_tag),
+    ]
+}
+
+// values that can be written in RESP3, `arb_value(ProcVersion::V2)` for the
+// ones both versions can write
+#[cfg(feature = "proptest")]
+impl Arbitrary for RedirsValue {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<RedirsValue>;
+
+    fn arbitrary_with(_: ()) -> Self::Strategy {
+        arb_value(ProcVersion::V3).boxed()
+    }
+}
@@ -0,0 +1,128 @@
+use alloc::{
+    borrow::{Cow, ToOwned},
+    boxed::Box,
+    string::ToString,
+    vec::Vec,
+};
+
+use crate::{to_payload, RedirsMap, RedirsValue, Sign, VerbatimEncoding};
+
+// a `RedirsValue` borrowing its payloads from the lexed buffer, only streamed
+// strings and inline arguments have to be assembled into owned buffers
+#[derive(Debug, Clone, PartialEq)]
+pub enum RedirsValueRef<'a> {
+    SimpleString(&'a str),
+    SimpleError(&'a str),
+    Integer(i64),
+    BulkString(Option<Cow<'a, [u8]>>),
+    Array(Option<Vec<RedirsValueRef<'a>>>),
+    Null,
+    Bool(bool),
+    Double(f64),
+    BigNumber(Sign, &'a str),
+    BulkError(&'a [u8]),
+    VerbatimString(VerbatimEncoding, &'a [u8]),
+    Map(Vec<(RedirsValueRef<'a>, RedirsValueRef<'a>)>),
+    // the elements in wire order, duplicates are only dropped by `into_owned`
+    Set(Vec<RedirsValueRef<'a>>),
+    Push(Vec<RedirsValueRef<'a>>),
+    Attribute(
+        Vec<(RedirsValueRef<'a>, RedirsValueRef<'a>)>,
+        Box<RedirsValueRef<'a>>,
+    ),
+}
+
+fn pairs_into_owned(pairs: Vec<(RedirsValueRef<'_>, RedirsValueRef<'_>)>) -> RedirsMap {
+    pairs
+        .into_iter()
+        .map(|(k, v)| (k.into_owned(), v.into_owned()))
+        .collect()
+}
+
+fn pairs_to_owned(pairs: &[(RedirsValueRef<'_>, RedirsValueRef<'_>)]) -> RedirsMap {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_owned(), v.to_owned()))
+        .collect()
+}
+
+impl RedirsValueRef<'_> {
+    // the bulk string payload, the shape every command argument has
+    pub fn as_bulk(&self) -> Option<&[u8]> {
+        match self {
+            RedirsValueRef::BulkString(Some(s)) => Some(s),
+            _ => None,
+        }
+    }
+    // copies the borrowed text, `Cow::Owned` strings are moved instead
+    pub fn into_owned(self) -> RedirsValue {
+        match self {
+            RedirsValueRef::SimpleString(s) => RedirsValue::SimpleString(s.to_owned()),
+            RedirsValueRef::SimpleError(s) => RedirsValue::SimpleError(s.to_owned()),
+            RedirsValueRef::Integer(i) => RedirsValue::Integer(i),
+            RedirsValueRef::BulkString(s) => {
+                RedirsValue::BulkString(s.map(|s| to_payload(s.into_owned())))
+            }
+            RedirsValueRef::Array(arr) => {
+                RedirsValue::Array(arr.map(|arr| arr.into_iter().map(Self::into_owned).collect()))
+            }
+            RedirsValueRef::Null => RedirsValue::Null,
+            RedirsValueRef::Bool(b) => RedirsValue::Bool(b),
+            RedirsValueRef::Double(d) => RedirsValue::Double(d),
+            RedirsValueRef::BigNumber(sign, digits) => {
+                RedirsValue::BigNumber(sign, digits.to_owned())
+            }
+            RedirsValueRef::BulkError(s) => RedirsValue::BulkError(s.to_vec()),
+            RedirsValueRef::VerbatimString(enc, s) => RedirsValue::VerbatimString(enc, s.to_vec()),
+            RedirsValueRef::Map(pairs) => RedirsValue::Map(pairs_into_owned(pairs)),
+            RedirsValueRef::Set(vals) => {
+                RedirsValue::Set(vals.into_iter().map(Self::into_owned).collect())
+            }
+            RedirsValueRef::Push(vals) => {
+                RedirsValue::Push(vals.into_iter().map(Self::into_owned).collect())
+            }
+            RedirsValueRef::Attribute(attrs, value) => {
+                RedirsValue::Attribute(pairs_into_owned(attrs), Box::new(value.into_owned()))
+            }
+        }
+    }
+    pub fn to_owned(&self) -> RedirsValue {
+        match self {
+            RedirsValueRef::SimpleString(s) => RedirsValue::SimpleString(s.to_string()),
+            RedirsValueRef::SimpleError(s) => RedirsValue::SimpleError(s.to_string()),
+            RedirsValueRef::Integer(i) => RedirsValue::Integer(*i),
+            RedirsValueRef::BulkString(s) => {
+                RedirsValue::BulkString(s.as_ref().map(|s| to_payload(s.to_vec())))
+            }
+            RedirsValueRef::Array(arr) => RedirsValue::Array(
+                arr.as_ref()
+                    .map(|arr| arr.iter().map(Self::to_owned).collect()),
+            ),
+            RedirsValueRef::Null => RedirsValue::Null,
+            RedirsValueRef::Bool(b) => RedirsValue::Bool(*b),
+            RedirsValueRef::Double(d) => RedirsValue::Double(*d),
+            RedirsValueRef::BigNumber(sign, digits) => {
+                RedirsValue::BigNumber(*sign, digits.to_string())
+            }
+            RedirsValueRef::BulkError(s) => RedirsValue::BulkError(s.to_vec()),
+            RedirsValueRef::VerbatimString(enc, s) => RedirsValue::VerbatimString(*enc, s.to_vec()),
+            RedirsValueRef::Map(pairs) => RedirsValue::Map(pairs_to_owned(pairs)),
+            RedirsValueRef::Set(vals) => {
+                RedirsValue::Set(vals.iter().map(Self::to_owned).collect())
+            }
+            RedirsValueRef::Push(vals) => {
+                RedirsValue::Push(vals.iter().map(Self::to_owned).collect())
+            }
+            RedirsValueRef::Attribute(attrs, value) => RedirsValue::Attribute(
+                pairs_to_owned(attrs),
+                Box::new(RedirsValueRef::to_owned(value)),
+            ),
+        }
+    }
+}
+
+impl From<RedirsValueRef<'_>> for RedirsValue {
+    fn from(value: RedirsValueRef<'_>) -> Self {
+        value.into_owned()
+    }
+}
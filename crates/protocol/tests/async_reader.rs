@@ -0,0 +1,35 @@
+use protocol::{ParseLimit, RedirsError, RedirsValue, RespReader};
+use tokio::io::{self, AsyncReadExt, AsyncWriteExt};
+
+#[tokio::test]
+async fn endless_line_hits_the_inline_limit() {
+    let mut reader = RespReader::new(b"+".chain(io::repeat(b'a')));
+    assert!(matches!(
+        reader.read_value().await,
+        Err(RedirsError::LimitExceeded(ParseLimit::InlineLen(_)))
+    ));
+    // at most one small read past the limit is buffered
+    assert!(reader.buffered().len() < 128 * 1024);
+    let head = b"*3\r\n+ok\r\n:1\r\n$".chain(io::repeat(b'9'));
+    assert!(matches!(
+        RespReader::new(head).read_value().await,
+        Err(RedirsError::LimitExceeded(ParseLimit::InlineLen(_)))
+    ));
+}
+
+#[tokio::test]
+async fn frames_split_across_small_reads() {
+    let text = "a".repeat(60 * 1024);
+    let payload = RedirsValue::from(vec![b'x'; 256 * 1024]);
+    let mut input = format!("+{text}\r\n").into_bytes();
+    input.extend(payload.to_resp_bytes());
+    let (mut client, server) = io::duplex(512);
+    tokio::spawn(async move { client.write_all(&input).await });
+    let mut reader = RespReader::new(server);
+    assert_eq!(
+        reader.read_value().await.unwrap(),
+        RedirsValue::SimpleString(text)
+    );
+    assert_eq!(reader.read_value().await.unwrap(), payload);
+    assert!(matches!(reader.read_value().await, Err(RedirsError::Eof)));
+}
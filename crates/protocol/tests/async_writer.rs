@@ -0,0 +1,120 @@
+use std::{
+    io,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use protocol::{test_support::ValueGen, ProcVersion, RedirsValue};
+use tokio::io::AsyncWrite;
+
+// accepts at most `per_poll` bytes and is pending every other poll, the sizes
+// of the writes that got through are kept
+struct Throttled {
+    written: Vec<u8>,
+    writes: Vec<usize>,
+    per_poll: usize,
+    pending: bool,
+}
+
+impl Throttled {
+    fn new(per_poll: usize) -> Self {
+        Self {
+            written: Vec::new(),
+            writes: Vec::new(),
+            per_poll,
+            pending: false,
+        }
+    }
+}
+
+impl AsyncWrite for Throttled {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        self.pending = !self.pending;
+        if self.pending {
+            cx.waker().wake_by_ref();
+            return Poll::Pending;
+        }
+        let len = buf.len().min(self.per_poll);
+        self.written.extend_from_slice(&buf[..len]);
+        self.writes.push(len);
+        Poll::Ready(Ok(len))
+    }
+    fn poll_flush(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+    fn poll_shutdown(self: Pin<&mut Self>, _: &mut Context<'_>) -> Poll<io::Result<()>> {
+        Poll::Ready(Ok(()))
+    }
+}
+
+fn sync_written(value: &RedirsValue, version: ProcVersion) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.write_resp(&mut out, version).unwrap();
+    out
+}
+
+#[tokio::test]
+async fn partial_writes_give_the_sync_bytes() {
+    for version in [ProcVersion::V2, ProcVersion::V3] {
+        for (seed, value) in ValueGen::new(35, version).take(300).enumerate() {
+            let mut sink = Throttled::new(3);
+            value.write_resp_async(&mut sink, version).await.unwrap();
+            assert_eq!(
+                sink.written,
+                sync_written(&value, version),
+                "{version:?} seed {seed}"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn downgrades_match_the_sync_writer() {
+    let value = RedirsValue::Push(vec![
+        protocol::resp!({"k" => [true, nil]}),
+        RedirsValue::Integer(1).with_attribute("ttl", 2),
+        RedirsValue::Set([RedirsValue::Double(0.5)].into_iter().collect()),
+    ]);
+    for version in [ProcVersion::V2, ProcVersion::V3] {
+        let mut sink = Throttled::new(usize::MAX);
+        value.write_resp_async(&mut sink, version).await.unwrap();
+        assert_eq!(sink.written, sync_written(&value, version), "{version:?}");
+    }
+}
+
+#[tokio::test]
+async fn big_replies_stream_through_a_small_buffer() {
+    let payload = vec![b'x'; 1 << 20];
+    let many: Vec<_> = (0..10_000).map(RedirsValue::Integer).collect();
+    let value = RedirsValue::Array(Some(vec![
+        RedirsValue::from("small"),
+        RedirsValue::from(payload.clone()),
+        RedirsValue::Array(Some(many)),
+    ]));
+    let mut sink = Throttled::new(usize::MAX);
+    value
+        .write_resp_async(&mut sink, ProcVersion::V3)
+        .await
+        .unwrap();
+    assert_eq!(sink.written, value.to_resp_bytes());
+    // the payload goes out in one write straight from the value, everything
+    // else in writes of about the buffer size
+    assert_eq!(
+        sink.writes
+            .iter()
+            .filter(|&&len| len == payload.len())
+            .count(),
+        1
+    );
+    let rest: Vec<_> = sink
+        .writes
+        .iter()
+        .filter(|&&len| len != payload.len())
+        .collect();
+    assert!(rest.iter().all(|&&len| len < 16 * 1024), "{rest:?}");
+    assert!(rest.len() > 5, "{rest:?}");
+}
@@ -0,0 +1,66 @@
+use bytes::BytesMut;
+use protocol::{codec::RespCodec, RedirsValue};
+use tokio_util::codec::{Decoder, Encoder};
+
+#[test]
+fn frames_split_across_reads() {
+    let value = RedirsValue::from(vec!["SET", "key", "a value"]);
+    let frame = value.to_resp_bytes();
+    let mut codec = RespCodec::new();
+    let mut buf = BytesMut::new();
+    for (at, byte) in frame.iter().enumerate() {
+        buf.extend_from_slice(&[*byte]);
+        let decoded = codec.decode(&mut buf).unwrap();
+        match at + 1 == frame.len() {
+            true => assert_eq!(decoded, Some(value.clone())),
+            false => assert_eq!(decoded, None, "decoded after {} bytes", at + 1),
+        }
+    }
+    assert!(buf.is_empty());
+}
+
+#[test]
+fn big_bulk_in_small_chunks() {
+    let value = RedirsValue::from(vec![b'x'; 1024 * 1024]);
+    let frame = value.to_resp_bytes();
+    let mut codec = RespCodec::new();
+    let mut buf = BytesMut::new();
+    let mut chunks = frame.chunks(1000).peekable();
+    while let Some(chunk) = chunks.next() {
+        buf.extend_from_slice(chunk);
+        let decoded = codec.decode(&mut buf).unwrap();
+        assert_eq!(decoded.is_some(), chunks.peek().is_none());
+        if let Some(decoded) = decoded {
+            assert_eq!(decoded, value);
+        }
+    }
+}
+
+#[test]
+fn pipelined_frames_and_errors() {
+    let mut codec = RespCodec::new();
+    let mut buf = BytesMut::from(&b"+OK\r\n:1\r\n$3\r\nab"[..]);
+    assert_eq!(
+        codec.decode(&mut buf).unwrap(),
+        Some(RedirsValue::SimpleString("OK".to_owned()))
+    );
+    assert_eq!(
+        codec.decode(&mut buf).unwrap(),
+        Some(RedirsValue::Integer(1))
+    );
+    assert_eq!(codec.decode(&mut buf).unwrap(), None);
+    buf.extend_from_slice(b"c\r\n");
+    assert_eq!(
+        codec.decode(&mut buf).unwrap(),
+        Some(RedirsValue::from("abc"))
+    );
+    assert_eq!(codec.decode(&mut buf).unwrap(), None);
+    buf.extend_from_slice(b":x\r\n");
+    assert!(codec.decode(&mut buf).is_err());
+
+    let mut out = BytesMut::new();
+    codec
+        .encode(RedirsValue::from(vec!["PING"]), &mut out)
+        .unwrap();
+    assert_eq!(&out[..], b"*1\r\n$4\r\nPING\r\n");
+}
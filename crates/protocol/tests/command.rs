@@ -0,0 +1,319 @@
+use std::time::{Duration, UNIX_EPOCH};
+
+use protocol::{
+    Action, Cmd, CommandError, Expiration, HelloCmd, HelloReply, Lexer, ProcVersion, RedirsOutput,
+    RedirsValue, SetCondition, SetOptions, System,
+};
+
+fn request(args: &[&str]) -> RedirsValue {
+    RedirsValue::from(args.to_vec())
+}
+
+// the command named by `args`, or the reply a server would send for it
+fn parse(args: &[&str]) -> Result<String, String> {
+    let request = request(args);
+    Cmd::try_from(&request)
+        .map(|cmd| format!("{cmd:?}"))
+        .map_err(|e| e.to_string())
+}
+
+#[test]
+fn names_match_in_any_case() {
+    for name in ["get", "GET", "GeT"] {
+        let get = request(&[name, "foo"]);
+        assert!(matches!(
+            Cmd::try_from(&get),
+            Ok(Cmd::Action(Action::GET(b"foo")))
+        ));
+    }
+    let mset = request(&["MSET", "a", "1", "b", "2"]);
+    assert!(matches!(
+        Cmd::try_from(&mset),
+        Ok(Cmd::Action(Action::MSET(pairs))) if pairs == [(&b"a"[..], &b"1"[..]), (b"b", b"2")]
+    ));
+    let ping = request(&["ping"]);
+    assert!(matches!(
+        Cmd::try_from(&ping),
+        Ok(Cmd::System(System::PING(None)))
+    ));
+    let ping = request(&["ping", ""]);
+    assert!(matches!(
+        Cmd::try_from(&ping),
+        Ok(Cmd::System(System::PING(Some(b""))))
+    ));
+    for args in [
+        &["DEL", "a", "b"][..],
+        &["UNLINK", "a"],
+        &["EXISTS", "a"],
+        &["MGET", "a", "b"],
+        &["MSETNX", "a", "1"],
+        &["ECHO", "hi"],
+        &["PING", "hi"],
+    ] {
+        assert!(parse(args).is_ok(), "{args:?}");
+    }
+}
+
+#[test]
+fn arity_and_unknown_commands() {
+    for (args, reply) in [
+        (
+            &["GET"][..],
+            "ERR wrong number of arguments for 'get' command",
+        ),
+        (
+            &["get", "a", "b"],
+            "ERR wrong number of arguments for 'get' command",
+        ),
+        (
+            &["SET", "a"],
+            "ERR wrong number of arguments for 'set' command",
+        ),
+        (&["DEL"], "ERR wrong number of arguments for 'del' command"),
+        (
+            &["MSET", "a"],
+            "ERR wrong number of arguments for 'mset' command",
+        ),
+        (
+            &["PING", "a", "b"],
+            "ERR wrong number of arguments for 'ping' command",
+        ),
+        (
+            &["FOO", "bar", "baz"],
+            "ERR unknown command 'FOO', with args beginning with: 'bar' 'baz' ",
+        ),
+        (
+            &["FOO"],
+            "ERR unknown command 'FOO', with args beginning with: ",
+        ),
+    ] {
+        assert_eq!(parse(args).unwrap_err(), reply, "{args:?}");
+    }
+    // only arrays of bulk strings are requests
+    for request in [
+        RedirsValue::Array(Some(Vec::new())),
+        RedirsValue::Array(Some(vec![RedirsValue::Integer(1)])),
+        RedirsValue::SimpleString("GET".to_owned()),
+    ] {
+        assert_eq!(
+            Cmd::try_from(&request).unwrap_err(),
+            CommandError::InvalidRequest
+        );
+    }
+}
+
+// a HELLO from the raw frame a client sends
+fn hello(frame: &[u8]) -> Result<HelloCmd<'static>, CommandError> {
+    let request = Lexer::new(frame).lex().unwrap();
+    match Cmd::try_from(&request)? {
+        Cmd::System(System::HELLO(hello)) => Ok(HelloCmd {
+            version: hello.version,
+            auth: hello
+                .auth
+                .map(|(user, pass)| (user.into_owned().into(), pass.into_owned().into())),
+            client_name: hello.client_name.map(|name| name.into_owned().into()),
+        }),
+        cmd => panic!("not a HELLO: {cmd:?}"),
+    }
+}
+
+#[test]
+fn hello_options_in_any_order() {
+    let bare = hello(b"*1\r\n$5\r\nHELLO\r\n").unwrap();
+    assert!(bare.version.is_none() && bare.auth.is_none() && bare.client_name.is_none());
+    let frames: [&[u8]; 2] = [
+        b"*7\r\n$5\r\nhello\r\n$1\r\n3\r\n$4\r\nAUTH\r\n$4\r\nuser\r\n$4\r\npass\r\n$7\r\nSETNAME\r\n$3\r\ncli\r\n",
+        b"*7\r\n$5\r\nhello\r\n$1\r\n3\r\n$7\r\nsetname\r\n$3\r\ncli\r\n$4\r\nauth\r\n$4\r\nuser\r\n$4\r\npass\r\n",
+    ];
+    for frame in frames {
+        let full = hello(frame).unwrap();
+        assert_eq!(full.version, Some(ProcVersion::V3));
+        let (user, pass) = full.auth.unwrap();
+        assert_eq!((&user[..], &pass[..]), (&b"user"[..], &b"pass"[..]));
+        assert_eq!(full.client_name.as_deref(), Some(&b"cli"[..]));
+    }
+    assert_eq!(
+        hello(b"*2\r\n$5\r\nHELLO\r\n$1\r\n2\r\n").unwrap().version,
+        Some(ProcVersion::V2)
+    );
+}
+
+#[test]
+fn hello_errors() {
+    for (args, reply) in [
+        (&["HELLO", "4"][..], "NOPROTO unsupported protocol version"),
+        (&["HELLO", "1"], "NOPROTO unsupported protocol version"),
+        (
+            &["HELLO", "three"],
+            "ERR Protocol version is not an integer or out of range",
+        ),
+        // AUTH takes exactly a username and a password
+        (
+            &["HELLO", "3", "AUTH", "user"],
+            "ERR Syntax error in HELLO option 'AUTH'",
+        ),
+        (
+            &["HELLO", "3", "SETNAME"],
+            "ERR Syntax error in HELLO option 'SETNAME'",
+        ),
+        (
+            &["HELLO", "3", "FOO"],
+            "ERR Syntax error in HELLO option 'FOO'",
+        ),
+    ] {
+        assert_eq!(parse(args).unwrap_err(), reply, "{args:?}");
+    }
+}
+
+fn set_options(args: &[&str]) -> Result<SetOptions, String> {
+    let request = request(&[&["SET", "key", "value"][..], args].concat());
+    match Cmd::try_from(&request).map_err(|e| e.to_string())? {
+        Cmd::Action(Action::SET((_, _, options))) => Ok(options),
+        cmd => panic!("not a SET: {cmd:?}"),
+    }
+}
+
+#[test]
+fn set_options_alone() {
+    let at = |millis| Some(Expiration::At(UNIX_EPOCH + Duration::from_millis(millis)));
+    for (args, expire) in [
+        ("EX", Some(Expiration::In(Duration::from_secs(10)))),
+        ("px", Some(Expiration::In(Duration::from_millis(10)))),
+        ("EXAT", at(10_000)),
+        ("PXAT", at(10)),
+    ] {
+        assert_eq!(set_options(&[args, "10"]).unwrap().expire, expire);
+    }
+    assert_eq!(
+        set_options(&["NX"]).unwrap().condition,
+        Some(SetCondition::NX)
+    );
+    assert_eq!(
+        set_options(&["xx"]).unwrap().condition,
+        Some(SetCondition::XX)
+    );
+    assert!(set_options(&["KEEPTTL"]).unwrap().keep_ttl);
+    assert!(set_options(&["GET"]).unwrap().get);
+    assert_eq!(set_options(&[]).unwrap(), SetOptions::default());
+}
+
+#[test]
+fn set_options_combined() {
+    assert_eq!(
+        set_options(&["GET", "PX", "1500", "NX"]).unwrap(),
+        SetOptions {
+            expire: Some(Expiration::In(Duration::from_millis(1500))),
+            keep_ttl: false,
+            condition: Some(SetCondition::NX),
+            get: true,
+        }
+    );
+    assert_eq!(
+        set_options(&["XX", "KEEPTTL", "GET"]).unwrap(),
+        SetOptions {
+            expire: None,
+            keep_ttl: true,
+            condition: Some(SetCondition::XX),
+            get: true,
+        }
+    );
+    for args in [
+        &["NX", "XX"][..],
+        &["EX", "1", "PX", "1"],
+        &["EX", "1", "KEEPTTL"],
+        &["KEEPTTL", "EXAT", "1"],
+        &["EX"],
+        &["SOON"],
+    ] {
+        assert_eq!(
+            set_options(args).unwrap_err(),
+            "ERR syntax error",
+            "{args:?}"
+        );
+    }
+}
+
+#[test]
+fn set_expirations_are_positive_integers() {
+    for time in ["0", "-5"] {
+        assert_eq!(
+            set_options(&["EX", time]).unwrap_err(),
+            "ERR invalid expire time in 'set' command"
+        );
+    }
+    assert_eq!(
+        set_options(&["EX", "9223372036854775807"]).unwrap_err(),
+        "ERR invalid expire time in 'set' command"
+    );
+    assert_eq!(
+        set_options(&["PX", "1.5"]).unwrap_err(),
+        "ERR value is not an integer or out of range"
+    );
+}
+
+// the frame a command is written as, checked to parse back to the same command
+fn encoded(args: &[&str]) -> String {
+    let request = request(args);
+    let cmd = Cmd::try_from(&request).unwrap();
+    let text = cmd.to_resp_string();
+    let back = Lexer::new(text.as_bytes()).lex().unwrap();
+    assert_eq!(
+        format!("{:?}", Cmd::try_from(&back).unwrap()),
+        format!("{cmd:?}")
+    );
+    text
+}
+
+#[test]
+fn commands_encode_like_redis_cli() {
+    for (args, frame) in [
+        (&["get", "foo"][..], "*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n"),
+        (&["ping"], "*1\r\n$4\r\nPING\r\n"),
+        (&["Ping", "hi"], "*2\r\n$4\r\nPING\r\n$2\r\nhi\r\n"),
+        (&["ping", ""], "*2\r\n$4\r\nPING\r\n$0\r\n\r\n"),
+        (&["echo", ""], "*2\r\n$4\r\nECHO\r\n$0\r\n\r\n"),
+        (
+            &["del", "a", "b"],
+            "*3\r\n$3\r\nDEL\r\n$1\r\na\r\n$1\r\nb\r\n",
+        ),
+        (
+            &["mset", "a", "1"],
+            "*3\r\n$4\r\nMSET\r\n$1\r\na\r\n$1\r\n1\r\n",
+        ),
+        (
+            &["set", "k", "v", "px", "1500", "nx", "get"],
+            "*7\r\n$3\r\nSET\r\n$1\r\nk\r\n$1\r\nv\r\n$2\r\nNX\r\n$3\r\nGET\r\n$2\r\nPX\r\n$4\r\n1500\r\n",
+        ),
+        // whole seconds go out as EX
+        (
+            &["set", "k", "v", "px", "2000"],
+            "*5\r\n$3\r\nSET\r\n$1\r\nk\r\n$1\r\nv\r\n$2\r\nEX\r\n$1\r\n2\r\n",
+        ),
+        (
+            &["hello", "3", "setname", "cli", "auth", "u", "p"],
+            "*7\r\n$5\r\nHELLO\r\n$1\r\n3\r\n$4\r\nAUTH\r\n$1\r\nu\r\n$1\r\np\r\n$7\r\nSETNAME\r\n$3\r\ncli\r\n",
+        ),
+    ] {
+        assert_eq!(encoded(args), frame, "{args:?}");
+    }
+}
+
+fn hello_reply(proto: ProcVersion) -> Vec<u8> {
+    let reply = HelloReply {
+        server: "redis".to_owned(),
+        version: "7.2.4".to_owned(),
+        ..HelloReply::new(proto, 3)
+    };
+    let mut out = Vec::new();
+    reply.into_value().write_resp(&mut out, proto).unwrap();
+    out
+}
+
+// captured from redis 7.2.4 for `HELLO 3` and `HELLO 2` on client id 3
+#[test]
+fn hello_reply_matches_redis() {
+    let v3 = "%7\r\n$6\r\nserver\r\n$5\r\nredis\r\n$7\r\nversion\r\n$5\r\n7.2.4\r\n$5\r\nproto\r\n:3\r\n$2\r\nid\r\n:3\r\n$4\r\nmode\r\n$10\r\nstandalone\r\n$4\r\nrole\r\n$6\r\nmaster\r\n$7\r\nmodules\r\n*0\r\n";
+    let v2 = "*14\r\n$6\r\nserver\r\n$5\r\nredis\r\n$7\r\nversion\r\n$5\r\n7.2.4\r\n$5\r\nproto\r\n:2\r\n$2\r\nid\r\n:3\r\n$4\r\nmode\r\n$10\r\nstandalone\r\n$4\r\nrole\r\n$6\r\nmaster\r\n$7\r\nmodules\r\n*0\r\n";
+    assert_eq!(String::from_utf8(hello_reply(ProcVersion::V3)).unwrap(), v3);
+    assert_eq!(String::from_utf8(hello_reply(ProcVersion::V2)).unwrap(), v2);
+}
@@ -0,0 +1,69 @@
+use std::{fs, path::Path};
+
+use proptest::prelude::*;
+
+use protocol::{split_args, Cmd, Lexer, RedirsOutput, RedirsValue};
+
+// every frame of the input as a request, then the error that stopped the loop
+fn lex_all(data: &[u8]) -> (Vec<RedirsValue>, String) {
+    let mut lexer = Lexer::new(data);
+    let mut values = Vec::new();
+    loop {
+        match lexer.lex_request_ref() {
+            Ok(value) => {
+                let _ = Cmd::try_from(&value);
+                values.push(value.into_owned());
+            }
+            Err(e) => {
+                let _ = e.to_client_error();
+                return (values, e.to_string());
+            }
+        }
+    }
+}
+
+// the seeds of the fuzz target as regular tests, so the tricky framing cases
+// run without a nightly toolchain
+#[test]
+fn fuzz_seeds_lex_without_panicking() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("fuzz/corpus/lex");
+    let mut seeds = 0;
+    for entry in fs::read_dir(&dir).unwrap() {
+        let path = entry.unwrap().path();
+        let data = fs::read(&path).unwrap();
+        let name = path.file_name().unwrap().to_string_lossy();
+        let (values, error) = lex_all(&data);
+        assert_eq!(lex_all(&data), (values.clone(), error), "{name}");
+        for value in values {
+            let mut out = Vec::new();
+            value.write_resp_str(&mut out).unwrap();
+            assert_eq!(Lexer::new(&out).lex().unwrap(), value, "{name}");
+        }
+        let (mut lexed, mut skipped) = (Lexer::new(&data), Lexer::new(&data));
+        assert_eq!(lexed.lex().is_ok(), skipped.skip_value().is_ok(), "{name}");
+        assert_eq!(lexed.position(), skipped.position(), "{name}");
+        let _ = split_args(&data);
+        seeds += 1;
+    }
+    assert!(seeds >= 29, "only {seeds} seeds in {}", dir.display());
+}
+
+proptest! {
+    // a short fuzzing run on every test run, mostly RESP looking bytes
+    #[test]
+    fn arbitrary_bytes_lex_without_panicking(
+        data in prop::collection::vec(
+            prop_oneof![
+                any::<u8>(),
+                prop::sample::select(b"*$%~>|+-:_#,(=!\r\n0123456789".to_vec()),
+            ],
+            0..256,
+        )
+    ) {
+        let (values, error) = lex_all(&data);
+        prop_assert_eq!(lex_all(&data), (values, error));
+        let (mut lexed, mut skipped) = (Lexer::new(&data), Lexer::new(&data));
+        prop_assert_eq!(lexed.lex().is_ok(), skipped.skip_value().is_ok());
+        prop_assert_eq!(lexed.position(), skipped.position());
+    }
+}
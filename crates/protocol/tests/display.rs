@@ -0,0 +1,98 @@
+use protocol::{resp, RedirsMap, RedirsValue, Sign, VerbatimEncoding};
+
+#[test]
+fn scalars_print_as_redis_cli_prints_them() {
+    for (value, printed) in [
+        (resp!(simple "OK"), "OK"),
+        (resp!(error "ERR broken"), "(error) ERR broken"),
+        (
+            RedirsValue::BulkError(b"ERR bulk".to_vec()),
+            "(error) ERR bulk",
+        ),
+        (RedirsValue::Integer(-42), "(integer) -42"),
+        (resp!("text"), "\"text\""),
+        (resp!(""), "\"\""),
+        (RedirsValue::BulkString(None), "(nil)"),
+        (RedirsValue::Array(None), "(nil)"),
+        (RedirsValue::Null, "(nil)"),
+        (RedirsValue::Bool(true), "(true)"),
+        (RedirsValue::Bool(false), "(false)"),
+        (RedirsValue::Double(1.5), "(double) 1.5"),
+        (RedirsValue::Double(f64::NEG_INFINITY), "(double) -inf"),
+        (
+            RedirsValue::BigNumber(Sign::Positive, "12345678901234567890".to_owned()),
+            "(big number) 12345678901234567890",
+        ),
+        (
+            RedirsValue::BigNumber(Sign::Negative, "12345678901234567890".to_owned()),
+            "(big number) -12345678901234567890",
+        ),
+        (
+            RedirsValue::VerbatimString(VerbatimEncoding::Txt, b"some text".to_vec()),
+            "(verbatim txt) \"some text\"",
+        ),
+        (
+            RedirsValue::VerbatimString(VerbatimEncoding::Mrk, b"# title".to_vec()),
+            "(verbatim mrk) \"# title\"",
+        ),
+        (RedirsValue::Array(Some(vec![])), "(empty array)"),
+        (RedirsValue::Push(vec![]), "(empty push)"),
+        (RedirsValue::Map(RedirsMap::default()), "(empty hash)"),
+        (RedirsValue::Set(Default::default()), "(empty set)"),
+    ] {
+        assert_eq!(value.to_string(), printed, "{value:?}");
+    }
+}
+
+#[test]
+fn payloads_are_quoted_and_escaped() {
+    let value = RedirsValue::from(b"say \"hi\"\\\n\r\t\x07\x08\x00\xff".to_vec());
+    assert_eq!(value.to_string(), r#""say \"hi\"\\\n\r\t\a\b\x00\xff""#);
+}
+
+#[test]
+fn nested_arrays_are_indented() {
+    let value = resp!(["a", ["b", ["c", "d"]], 3]);
+    assert_eq!(
+        value.to_string(),
+        "1) \"a\"\n\
+         2) 1) \"b\"\n\
+         \x20  2) 1) \"c\"\n\
+         \x20     2) \"d\"\n\
+         3) (integer) 3"
+    );
+}
+
+#[test]
+fn indexes_are_aligned() {
+    let value = RedirsValue::Array(Some((1..=10).map(RedirsValue::Integer).collect()));
+    let printed = value.to_string();
+    let lines: Vec<_> = printed.lines().collect();
+    assert_eq!(lines[0], " 1) (integer) 1");
+    assert_eq!(lines[8], " 9) (integer) 9");
+    assert_eq!(lines[9], "10) (integer) 10");
+
+    // elements nested under a two digit index start after it
+    let mut vals: Vec<_> = (1..=9).map(RedirsValue::Integer).collect();
+    vals.push(resp!(["x", "y"]));
+    let printed = RedirsValue::Array(Some(vals)).to_string();
+    let lines: Vec<_> = printed.lines().skip(9).collect();
+    assert_eq!(lines, ["10) 1) \"x\"", "    2) \"y\""]);
+}
+
+#[test]
+fn maps_and_sets_have_their_own_markers() {
+    let value = resp!({"k" => "v", simple "n" => 1, "list" => ["a", "b"]});
+    assert_eq!(
+        value.to_string(),
+        "1# \"k\" => \"v\"\n\
+         2# n => (integer) 1\n\
+         3# \"list\" => 1) \"a\"\n\
+         \x20  2) \"b\""
+    );
+    // sets print sorted
+    let value = RedirsValue::Set(["b", "a"].into_iter().map(RedirsValue::from).collect());
+    assert_eq!(value.to_string(), "1~ \"a\"\n2~ \"b\"");
+    let value = RedirsValue::Push(vec![resp!("message"), resp!("chan")]);
+    assert_eq!(value.to_string(), "1) \"message\"\n2) \"chan\"");
+}
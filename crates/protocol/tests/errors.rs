@@ -0,0 +1,34 @@
+use protocol::{Lexer, RedirsValue};
+
+// the replies redis 7 sends for the same input, right before it hangs up
+#[test]
+fn protocol_errors_read_like_redis() {
+    let cases: [(&[u8], &str); 9] = [
+        (b"*-5\r\n", "invalid multibulk length"),
+        (b"*abc\r\n", "invalid multibulk length"),
+        (b"*3000000000\r\n", "invalid multibulk length"),
+        (b"*1\r\n$-5\r\n", "invalid bulk length"),
+        (b"*1\r\n$x\r\n", "invalid bulk length"),
+        (b"*1\r\n$600000000\r\n", "invalid bulk length"),
+        (b"*1\r\nx\r\n", "expected '$', got 'x'"),
+        (b"SET \"a\r\n", "unbalanced quotes in request"),
+        (b"SET 'a'b\r\n", "unbalanced quotes in request"),
+    ];
+    for (input, reason) in cases {
+        let err = Lexer::new(input).lex_request().unwrap_err();
+        assert_eq!(
+            err.to_client_error(),
+            RedirsValue::SimpleError(format!("ERR Protocol error: {reason}")),
+            "{}",
+            input.escape_ascii()
+        );
+    }
+    let inline = [b'a'; 70 * 1024];
+    assert_eq!(
+        Lexer::new(&inline)
+            .lex_request()
+            .unwrap_err()
+            .to_client_error(),
+        RedirsValue::SimpleError("ERR Protocol error: too big inline request".to_owned())
+    );
+}
@@ -0,0 +1,95 @@
+use protocol::{split_args, Lexer, RedirsError, RedirsValue};
+
+fn split(line: &str) -> Vec<Vec<u8>> {
+    split_args(line.as_bytes()).unwrap()
+}
+
+fn args(args: &[&[u8]]) -> Vec<Vec<u8>> {
+    args.iter().map(|arg| arg.to_vec()).collect()
+}
+
+fn request(args: &[&[u8]]) -> RedirsValue {
+    RedirsValue::Array(Some(
+        args.iter().map(|&arg| RedirsValue::from(arg)).collect(),
+    ))
+}
+
+#[test]
+fn arguments_are_split_on_whitespace() {
+    assert_eq!(split("SET k v"), args(&[b"SET", b"k", b"v"]));
+    assert_eq!(split("  GET \t k  "), args(&[b"GET", b"k"]));
+    assert_eq!(split(""), args(&[]));
+    assert_eq!(split(" \t "), args(&[]));
+}
+
+#[test]
+fn double_quotes_take_escapes() {
+    assert_eq!(split(r#"SET k "a b\nc""#), args(&[b"SET", b"k", b"a b\nc"]));
+    assert_eq!(split(r#""say \"hi\"""#), args(&[br#"say "hi""#]));
+    assert_eq!(split(r#""\x00\xff\x41z""#), args(&[b"\x00\xffAz"]));
+    assert_eq!(split(r#""\r\t\b\a\\""#), args(&[b"\r\t\x08\x07\\"]));
+    // a \x without two hex digits is an x, an unknown escape the character
+    assert_eq!(split(r#""\xg1" "\q""#), args(&[b"xg1", b"q"]));
+    assert_eq!(split(r#""""#), args(&[b""]));
+}
+
+#[test]
+fn single_quotes_only_escape_a_quote() {
+    assert_eq!(split(r"'it\'s'"), args(&[b"it's"]));
+    assert_eq!(split(r"'a\nb' 'c d'"), args(&[br"a\nb", b"c d"]));
+    assert_eq!(split(r#"'say "hi"'"#), args(&[br#"say "hi""#]));
+}
+
+#[test]
+fn quotes_must_be_closed_and_end_the_argument() {
+    for line in [r#""open"#, "'open", r#""a"b"#, "'a'b"] {
+        assert!(
+            matches!(
+                split_args(line.as_bytes()),
+                Err(RedirsError::UnbalancedQuotes)
+            ),
+            "{line}"
+        );
+    }
+}
+
+#[test]
+fn inline_requests_read_as_their_array_form() {
+    let inline = Lexer::new(b"SET k \"a b\"\r\n").lex_request().unwrap();
+    let array = Lexer::new(b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$3\r\na b\r\n")
+        .lex_request()
+        .unwrap();
+    assert_eq!(inline, array);
+    assert_eq!(inline, request(&[b"SET", b"k", b"a b"]));
+    let borrowed = Lexer::new(b"SET k \"a b\"\r\n")
+        .lex_request_ref()
+        .unwrap()
+        .into_owned();
+    assert_eq!(borrowed, array);
+}
+
+#[test]
+fn lines_end_in_a_bare_lf_too() {
+    let mut lexer = Lexer::new(b"PING\nECHO \"x\"\r\n*1\r\n$4\r\nPING\r\n\r\n");
+    assert_eq!(lexer.lex_request().unwrap(), request(&[b"PING"]));
+    assert_eq!(lexer.lex_request().unwrap(), request(&[b"ECHO", b"x"]));
+    assert_eq!(lexer.lex_request().unwrap(), request(&[b"PING"]));
+    // an empty line is a request without arguments
+    assert_eq!(lexer.lex_request().unwrap(), request(&[]));
+    assert!(matches!(
+        lexer.lex_request(),
+        Err(RedirsError::Incomplete(None))
+    ));
+}
+
+#[test]
+fn a_line_without_its_end_is_incomplete() {
+    assert!(matches!(
+        Lexer::new(b"SET k v").lex_request(),
+        Err(RedirsError::Incomplete(None))
+    ));
+    assert!(matches!(
+        Lexer::new(b"SET k \"v\r\n").lex_request(),
+        Err(RedirsError::UnbalancedQuotes)
+    ));
+}
@@ -0,0 +1,162 @@
+use protocol::{resp, JsonError, JsonErrorKind, RedirsMap, RedirsValue, Sign, VerbatimEncoding};
+use serde_json::{json, Value};
+
+fn to_json(value: RedirsValue) -> Result<Value, JsonError> {
+    Value::try_from(value)
+}
+
+fn at(path: &str, kind: JsonErrorKind) -> Result<Value, JsonError> {
+    Err(JsonError {
+        path: path.to_owned(),
+        kind,
+    })
+}
+
+#[test]
+fn a_stream_info_reply_round_trips() {
+    // the shape of XINFO STREAM FULL, keys in the order redis sends them
+    let info = resp!({
+        "length" => 2,
+        "radix-tree-keys" => 1,
+        "last-generated-id" => "1-1",
+        "max-deleted-entry-id" => "0-0",
+        "entries-added" => 2,
+        "recorded-first-entry-id" => "1-0",
+        "entries" => [["1-0", ["field", "a"]], ["1-1", ["field", "b"]]],
+        "groups" => [{
+            "name" => "group",
+            "last-delivered-id" => "1-0",
+            "entries-read" => 1,
+            "lag" => nil,
+            "pel-count" => 1,
+            "pending" => [["1-0", "alice", 1700000000000, 1]],
+            "consumers" => [{
+                "name" => "alice",
+                "seen-time" => 1700000000000,
+                "active-time" => 1700000000000,
+                "pel-count" => 1,
+                "pending" => [["1-0", 1700000000000, 1]],
+            }],
+        }],
+        "ratio" => 0.5,
+        "ok" => true,
+    });
+    let json = to_json(info.clone()).unwrap();
+    assert_eq!(json["groups"][0]["consumers"][0]["name"], json!("alice"));
+    assert_eq!(json["entries"][1], json!(["1-1", ["field", "b"]]));
+    assert_eq!(json["groups"][0]["lag"], Value::Null);
+    // key order is kept
+    let keys: Vec<_> = json.as_object().unwrap().keys().take(3).collect();
+    assert_eq!(keys, ["length", "radix-tree-keys", "last-generated-id"]);
+    assert_eq!(RedirsValue::from(json), info);
+}
+
+#[test]
+fn json_has_no_form_of_some_values() {
+    for (value, kind) in [
+        (
+            RedirsValue::from(b"\xff\xfe".to_vec()),
+            JsonErrorKind::NotUtf8,
+        ),
+        (
+            RedirsValue::VerbatimString(VerbatimEncoding::Txt, vec![0xc3]),
+            JsonErrorKind::NotUtf8,
+        ),
+        (
+            RedirsValue::Double(f64::NAN),
+            JsonErrorKind::NonFiniteDouble,
+        ),
+        (
+            RedirsValue::Double(f64::INFINITY),
+            JsonErrorKind::NonFiniteDouble,
+        ),
+        (
+            RedirsValue::Double(f64::NEG_INFINITY),
+            JsonErrorKind::NonFiniteDouble,
+        ),
+        (
+            resp!(error "ERR broken"),
+            JsonErrorKind::Reply("ERR broken".to_owned()),
+        ),
+        (
+            RedirsValue::BulkError(b"ERR \xff".to_vec()),
+            JsonErrorKind::Reply("ERR \u{fffd}".to_owned()),
+        ),
+    ] {
+        assert_eq!(to_json(value.clone()), at("", kind), "{value:?}");
+    }
+}
+
+#[test]
+fn errors_name_the_path_of_the_value() {
+    let value = resp!([1, 2, {"field" => [true, RedirsValue::Double(f64::NAN)]}]);
+    let e = to_json(value).unwrap_err();
+    assert_eq!(
+        e,
+        JsonError {
+            path: "[2].field[1]".to_owned(),
+            kind: JsonErrorKind::NonFiniteDouble,
+        }
+    );
+    assert_eq!(e.to_string(), "at [2].field[1]: double is not finite");
+
+    let value = resp!([nil, nil, {"field" => RedirsValue::from(b"\xff".to_vec())}]);
+    assert_eq!(to_json(value), at("[2].field", JsonErrorKind::NotUtf8));
+
+    // keys that are not plain words are quoted as JSON strings
+    let value = resp!({"a.b" => {"x[0]" => {"say \"hi\"" => resp!(error "ERR x")}}});
+    assert_eq!(
+        to_json(value),
+        at(
+            r#"["a.b"]["x[0]"]["say \"hi\""]"#,
+            JsonErrorKind::Reply("ERR x".to_owned())
+        )
+    );
+
+    // a bad key points at the map holding it
+    let value = resp!({"outer" => {1 => "v"}});
+    assert_eq!(to_json(value), at(".outer", JsonErrorKind::NonStringKey));
+    let value = resp!([{RedirsValue::from(b"\xff".to_vec()) => "v"}]);
+    assert_eq!(to_json(value), at("[0]", JsonErrorKind::NotUtf8));
+}
+
+#[test]
+fn duplicate_keys_are_errors() {
+    // a bulk and a simple string with the same text are different RESP keys
+    let map = RedirsMap::from(vec![
+        (RedirsValue::from("k"), RedirsValue::Integer(1)),
+        (resp!(simple "k"), RedirsValue::Integer(2)),
+    ]);
+    let value = resp!({"outer" => RedirsValue::Map(map)});
+    assert_eq!(to_json(value), at(".outer.k", JsonErrorKind::DuplicateKey));
+}
+
+#[test]
+fn lossy_conversions() {
+    let value = resp!([
+        simple "OK",
+        RedirsValue::BigNumber(Sign::Negative, "123456789012345678901234567890".to_owned()),
+        RedirsValue::BulkString(None),
+        RedirsValue::Array(None),
+        RedirsValue::Push(vec![resp!("message")]),
+        RedirsValue::Set(["b", "a"].into_iter().map(RedirsValue::from).collect()),
+        RedirsValue::Integer(7).with_attribute("ttl", 10),
+    ]);
+    assert_eq!(
+        to_json(value).unwrap(),
+        json!([
+            "OK",
+            "-123456789012345678901234567890",
+            null,
+            null,
+            ["message"],
+            ["a", "b"],
+            7
+        ])
+    );
+    // integers past `i64` become big numbers
+    assert_eq!(
+        RedirsValue::from(json!(u64::MAX)),
+        RedirsValue::BigNumber(Sign::Positive, u64::MAX.to_string())
+    );
+}
@@ -0,0 +1,464 @@
+use protocol::{
+    lex_frame, Expected, FrameKind, Lexer, ParseLimit, ParseLimits, RedirsError, RedirsOutput,
+    RedirsValue, Sign, VerbatimEncoding,
+};
+
+fn lex(input: &[u8]) -> Result<RedirsValue, RedirsError> {
+    Lexer::new(input).lex()
+}
+
+// the value written and lexed back, the whole frame must be consumed
+fn roundtrip(value: &RedirsValue) -> RedirsValue {
+    let mut out = Vec::new();
+    value.write_resp_str(&mut out).unwrap();
+    let mut lexer = Lexer::new(&out);
+    let back = lexer.lex().unwrap();
+    assert_eq!(lexer.remaining(), b"", "{value:?} left bytes behind");
+    back
+}
+
+#[test]
+fn simple_frames_roundtrip() {
+    for value in [
+        RedirsValue::SimpleString("OK".to_owned()),
+        RedirsValue::SimpleString(String::new()),
+        RedirsValue::SimpleError("ERR unknown command".to_owned()),
+        RedirsValue::Integer(0),
+        RedirsValue::Integer(i64::MIN),
+        RedirsValue::Integer(i64::MAX),
+    ] {
+        assert_eq!(roundtrip(&value), value);
+    }
+}
+
+#[test]
+fn integer_with_trailing_garbage() {
+    let err = lex(b":12a\r\n").unwrap_err();
+    assert!(
+        matches!(
+            err,
+            RedirsError::ParsingError {
+                offset: 1,
+                expected: Expected::Integer,
+                ref found,
+            } if found == b"12a"
+        ),
+        "{err:?}"
+    );
+    // and no overflow into a wrong value
+    assert!(lex(b":9223372036854775808\r\n").is_err());
+}
+
+#[test]
+fn missing_crlf_is_incomplete() {
+    for input in [&b"+OK"[..], b"+OK\r", b"-ERR", b":12"] {
+        assert!(
+            matches!(lex(input), Err(RedirsError::Incomplete(_))),
+            "{:?}",
+            input.escape_ascii().to_string()
+        );
+    }
+    let mut lexer = Lexer::new(b"+OK");
+    assert!(lexer.lex().is_err());
+    assert_eq!(lexer.position(), 0);
+}
+
+#[test]
+fn resp3_frames_from_the_wire() {
+    let cases: [(&[u8], RedirsValue); 9] = [
+        (b"_\r\n", RedirsValue::Null),
+        (b"#t\r\n", RedirsValue::Bool(true)),
+        (b",-1.5\r\n", RedirsValue::Double(-1.5)),
+        (
+            b"(-3492890328409238509324850943850943825024385\r\n",
+            RedirsValue::big_number("-3492890328409238509324850943850943825024385").unwrap(),
+        ),
+        (b"!3\r\nERR\r\n", RedirsValue::BulkError(b"ERR".to_vec())),
+        (
+            b"=8\r\ntxt:some\r\n",
+            RedirsValue::VerbatimString(VerbatimEncoding::Txt, b"some".to_vec()),
+        ),
+        (
+            b"%1\r\n+a\r\n:1\r\n",
+            RedirsValue::Map(vec![(RedirsValue::SimpleString("a".to_owned()), 1.into())].into()),
+        ),
+        (
+            b"~2\r\n:1\r\n:2\r\n",
+            RedirsValue::Set([1.into(), 2.into()].into_iter().collect()),
+        ),
+        (
+            b">2\r\n+message\r\n$2\r\nhi\r\n",
+            RedirsValue::Push(vec![
+                RedirsValue::SimpleString("message".to_owned()),
+                "hi".into(),
+            ]),
+        ),
+    ];
+    for (input, value) in cases {
+        assert_eq!(lex(input).unwrap(), value, "{}", input.escape_ascii());
+        assert_eq!(roundtrip(&value), value);
+    }
+}
+
+#[test]
+fn verbatim_needs_a_tag_and_a_colon() {
+    for input in [
+        &b"=3\r\ntxt\r\n"[..],
+        b"=7\r\ntxtsome\r\n",
+        b"=6\r\ntx:abc\r\n",
+    ] {
+        let err = lex(input).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                RedirsError::ParsingError {
+                    expected: Expected::VerbatimEncoding,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+    }
+    assert_eq!(
+        lex(b"=4\r\ntxt:\r\n").unwrap(),
+        RedirsValue::VerbatimString(VerbatimEncoding::Txt, Vec::new())
+    );
+}
+
+#[test]
+fn bulk_payloads_are_binary_safe() {
+    let value = lex(b"$6\r\n\xff\r\n\x00\xfe\xff\r\n").unwrap();
+    assert_eq!(value.as_bytes(), Some(&b"\xff\r\n\x00\xfe\xff"[..]));
+    assert_eq!(roundtrip(&value), value);
+    // text frames still have to be UTF-8
+    assert!(matches!(
+        lex(b"+\xff\xfe\r\n"),
+        Err(RedirsError::StringError(1))
+    ));
+}
+
+#[test]
+fn cursor_at_the_edges() {
+    let mut empty = Lexer::new(b"");
+    assert_eq!((empty.peek(), empty.pop()), (None, None));
+    assert_eq!((empty.position(), empty.remaining()), (0, &b""[..]));
+
+    let mut single = Lexer::new(b"+");
+    assert_eq!(single.peek(), Some(b'+'));
+    assert_eq!(single.pop(), Some(b'+'));
+    assert_eq!((single.position(), single.remaining()), (1, &b""[..]));
+    // reading past the end neither panics nor moves
+    assert_eq!((single.peek(), single.pop()), (None, None));
+    assert_eq!(single.position(), 1);
+    assert!(matches!(
+        single.read_spacer(),
+        Err(RedirsError::Incomplete(Some(2)))
+    ));
+}
+
+#[test]
+fn consecutive_lines_compose() {
+    let input = b"+OK\r\n:1\r\n+DONE\r\n";
+    let mut lexer = Lexer::new(input);
+    for (prefix, text) in [(b'+', "OK"), (b':', "1"), (b'+', "DONE")] {
+        assert_eq!(lexer.pop(), Some(prefix));
+        assert_eq!(lexer.read_str().unwrap(), text);
+    }
+    assert_eq!(lexer.position(), input.len());
+    let mut lexer = Lexer::new(input);
+    let values: Vec<_> = (0..3).map(|_| lexer.lex().unwrap()).collect();
+    assert_eq!(
+        values,
+        [
+            RedirsValue::SimpleString("OK".to_owned()),
+            RedirsValue::Integer(1),
+            RedirsValue::SimpleString("DONE".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn trailing_line_without_crlf() {
+    let mut lexer = Lexer::new(b"+OK\r\n+DONE");
+    assert_eq!(lexer.pop(), Some(b'+'));
+    assert_eq!(lexer.read_str().unwrap(), "OK");
+    assert_eq!(lexer.pop(), Some(b'+'));
+    assert!(lexer.read_str().is_err());
+    assert_eq!(lexer.remaining(), b"DONE");
+    assert!(lexer.read_spacer().is_err());
+}
+
+fn set(key: &str, value: &str) -> Vec<u8> {
+    RedirsValue::from(vec!["SET", key, value]).to_resp_bytes()
+}
+
+#[test]
+fn pipelined_frames_report_their_length() {
+    let frames = [set("a", "1"), set("b", "2"), set("c", "3")];
+    let input = frames.concat();
+    let mut rest = &input[..];
+    for frame in &frames {
+        let (value, used) = lex_frame(rest).unwrap();
+        assert_eq!(used, frame.len());
+        assert_eq!(value, lex(frame).unwrap());
+        rest = &rest[used..];
+    }
+    assert!(matches!(
+        lex_frame(rest),
+        Err(RedirsError::Incomplete(None))
+    ));
+}
+
+#[test]
+fn truncated_last_frame_is_incomplete() {
+    let frames = [set("a", "1"), set("b", "2"), set("c", "value")];
+    let input = frames.concat();
+    // cut in the middle of the payload "value", 3 bytes and the CRLF missing
+    let input = &input[..input.len() - 5];
+    let mut lexer = Lexer::new(input);
+    lexer.lex().unwrap();
+    lexer.lex().unwrap();
+    let consumed = lexer.position();
+    assert_eq!(consumed, frames[0].len() + frames[1].len());
+    assert!(matches!(lexer.lex(), Err(RedirsError::Incomplete(Some(5)))));
+    assert_eq!(lexer.position(), consumed);
+}
+
+#[test]
+fn depth_bomb_fails_fast() {
+    let bomb = b"*1\r\n".repeat(1_000_000);
+    assert!(matches!(
+        lex(&bomb),
+        Err(RedirsError::LimitExceeded(ParseLimit::Depth(128)))
+    ));
+    let attributes = b"|0\r\n".repeat(1_000_000);
+    assert!(matches!(
+        Lexer::new(&attributes).skip_value(),
+        Err(RedirsError::LimitExceeded(ParseLimit::Depth(128)))
+    ));
+    // a huge announced length reserves for what the input can hold, not more
+    assert!(matches!(
+        lex(b"*1000000000\r\n:1\r\n"),
+        Err(RedirsError::Incomplete(_))
+    ));
+}
+
+#[test]
+fn tightened_limits() {
+    let limits = ParseLimits {
+        max_depth: 1,
+        max_elements: 2,
+        max_bulk_len: 4,
+    };
+    let lex = |input: &[u8]| Lexer::with_limits(input, limits).lex();
+    assert!(lex(b"*2\r\n:1\r\n:2\r\n").is_ok());
+    assert!(matches!(
+        lex(b"*3\r\n:1\r\n:2\r\n:3\r\n"),
+        Err(RedirsError::LimitExceeded(ParseLimit::Elements(2)))
+    ));
+    assert!(matches!(
+        lex(b"*1\r\n*0\r\n"),
+        Err(RedirsError::LimitExceeded(ParseLimit::Depth(1)))
+    ));
+    assert!(matches!(
+        lex(b"$5\r\nhello\r\n"),
+        Err(RedirsError::LimitExceeded(ParseLimit::BulkLen(4)))
+    ));
+    // streamed frames count their elements and chunks all the same
+    assert!(matches!(
+        lex(b"*?\r\n:1\r\n:2\r\n:3\r\n.\r\n"),
+        Err(RedirsError::LimitExceeded(ParseLimit::Elements(2)))
+    ));
+    assert!(matches!(
+        lex(b"$?\r\n;3\r\nabc\r\n;2\r\nde\r\n;0\r\n"),
+        Err(RedirsError::LimitExceeded(ParseLimit::BulkLen(4)))
+    ));
+}
+
+#[test]
+fn doubles_roundtrip() {
+    for d in [
+        0.0,
+        -0.0,
+        1.5,
+        1e20,
+        -2.5e-7,
+        f64::MAX,
+        f64::MIN_POSITIVE,
+        // the smallest subnormal
+        5e-324,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+    ] {
+        let back = roundtrip(&RedirsValue::Double(d));
+        let RedirsValue::Double(back) = back else {
+            panic!("{back:?} is not a double");
+        };
+        assert_eq!(back.to_bits(), d.to_bits(), "{d}");
+    }
+    assert_eq!(RedirsValue::Double(f64::NAN).to_resp_bytes(), b",nan\r\n");
+    assert!(matches!(lex(b",nan\r\n"), Ok(RedirsValue::Double(d)) if d.is_nan()));
+}
+
+#[test]
+fn double_spellings() {
+    for (input, d) in [
+        (&b",inf\r\n"[..], f64::INFINITY),
+        (b",+inf\r\n", f64::INFINITY),
+        (b",-inf\r\n", f64::NEG_INFINITY),
+        (b",10\r\n", 10.0),
+        (b",1.23E+3\r\n", 1230.0),
+        (b",-4e-2\r\n", -0.04),
+    ] {
+        assert_eq!(lex(input).unwrap(), RedirsValue::Double(d));
+    }
+    for input in [
+        &b",infinity\r\n"[..],
+        b",NaN\r\n",
+        b",1.5x\r\n",
+        b",\r\n",
+        b", 1\r\n",
+    ] {
+        let err = lex(input).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                RedirsError::ParsingError {
+                    expected: Expected::Double,
+                    ..
+                }
+            ),
+            "{err:?}"
+        );
+    }
+}
+
+#[test]
+fn big_numbers_of_any_length() {
+    let digits = "1234567890".repeat(10);
+    for text in [digits.clone(), format!("-{digits}"), format!("+{digits}")] {
+        let value = RedirsValue::big_number(&text).unwrap();
+        assert_eq!(
+            lex(format!("({text}\r\n").as_bytes()).unwrap(),
+            value,
+            "{text}"
+        );
+        assert_eq!(roundtrip(&value), value);
+    }
+    assert_eq!(
+        RedirsValue::big_number(&format!("-{digits}")).unwrap(),
+        RedirsValue::BigNumber(Sign::Negative, digits)
+    );
+    for text in ["", "-", "+", "12abc", "1 2", " 12", "1.5"] {
+        assert!(RedirsValue::big_number(text).is_err(), "{text:?}");
+        let err = lex(format!("({text}\r\n").as_bytes()).unwrap_err();
+        assert!(
+            matches!(
+                err,
+                RedirsError::ParsingError {
+                    expected: Expected::BigNumber,
+                    ..
+                }
+            ),
+            "{text:?}: {err:?}"
+        );
+    }
+}
+
+#[test]
+fn verbatim_encodings_roundtrip() {
+    let other = VerbatimEncoding::new(b"rst").unwrap();
+    for (input, enc) in [
+        (&b"=9\r\ntxt:hello\r\n"[..], VerbatimEncoding::Txt),
+        (b"=9\r\nmrk:hello\r\n", VerbatimEncoding::Mrk),
+        (b"=9\r\nrst:hello\r\n", other),
+    ] {
+        let value = RedirsValue::VerbatimString(enc, b"hello".to_vec());
+        assert_eq!(lex(input).unwrap(), value);
+        assert_eq!(roundtrip(&value), value);
+    }
+    assert_eq!(other.tag(), b"rst");
+    // known tags are never held as unknown ones
+    assert_eq!(
+        VerbatimEncoding::new(b"txt").unwrap(),
+        VerbatimEncoding::Txt
+    );
+    for input in [&b"=0\r\n\r\n"[..], b"=3\r\nrst\r\n", b"=-1\r\n"] {
+        assert!(lex(input).is_err(), "{}", input.escape_ascii());
+    }
+    for tag in [&b"tx"[..], b"text", b"t t", b"t\x00t"] {
+        assert!(VerbatimEncoding::new(tag).is_err(), "{tag:?}");
+    }
+}
+
+#[test]
+fn peek_and_skip_between_full_parses() {
+    let input = b">2\r\n+message\r\n:1\r\n|1\r\n+ttl\r\n:9\r\n$3\r\nfoo\r\n*2\r\n%1\r\n+a\r\n~1\r\n:1\r\n_\r\n:7\r\n";
+    let mut lexer = Lexer::new(input);
+    assert_eq!(lexer.peek_type().unwrap(), FrameKind::Push);
+    assert_eq!(lexer.position(), 0);
+    assert!(matches!(lexer.lex().unwrap(), RedirsValue::Push(_)));
+    // the attribute goes along with the value it is attached to
+    assert_eq!(lexer.peek_type().unwrap(), FrameKind::Attribute);
+    lexer.skip_value().unwrap();
+    assert_eq!(lexer.peek_type().unwrap(), FrameKind::Array);
+    let before = lexer.position();
+    lexer.skip_value().unwrap();
+    let mut again = Lexer::new(&input[before..]);
+    again.lex().unwrap();
+    assert_eq!(lexer.position() - before, again.position());
+    assert_eq!(lexer.lex().unwrap(), RedirsValue::Integer(7));
+    assert!(matches!(
+        lexer.peek_type(),
+        Err(RedirsError::Incomplete(None))
+    ));
+    assert!(matches!(
+        lexer.skip_value(),
+        Err(RedirsError::Incomplete(None))
+    ));
+}
+
+#[test]
+fn failed_skip_leaves_the_position() {
+    let mut lexer = Lexer::new(b":1\r\n*2\r\n:1\r\n");
+    lexer.skip_value().unwrap();
+    assert!(matches!(
+        lexer.skip_value(),
+        Err(RedirsError::Incomplete(_))
+    ));
+    assert_eq!(lexer.position(), 4);
+    assert!(matches!(
+        Lexer::new(b"?").peek_type(),
+        Err(RedirsError::ParsingError {
+            expected: Expected::Prefix,
+            ..
+        })
+    ));
+}
+
+#[test]
+fn incomplete_frames_hint_the_missing_bytes() {
+    for (input, missing) in [
+        (&b"$5\r\nhel"[..], Some(4)),
+        (b"$5\r\nhello", Some(2)),
+        (b"$5\r\nhello\r", Some(1)),
+        (b"*2\r\n$3\r\nfoo\r\n$3\r\nb", Some(4)),
+        // nothing is known before the length line ends
+        (b"$5", None),
+        (b"*2\r\n", None),
+        (b"", None),
+    ] {
+        let mut lexer = Lexer::new(input);
+        match lexer.lex() {
+            Err(RedirsError::Incomplete(hint)) => {
+                assert_eq!(hint, missing, "{}", input.escape_ascii())
+            }
+            other => panic!("{}: {other:?}", input.escape_ascii()),
+        }
+        assert_eq!(lexer.position(), 0);
+    }
+    // appending the missing bytes completes the frame
+    let mut input = b"$5\r\nhel".to_vec();
+    input.extend_from_slice(b"lo\r\n");
+    assert_eq!(lex(&input).unwrap(), RedirsValue::from("hello"));
+}
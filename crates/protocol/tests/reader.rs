@@ -0,0 +1,35 @@
+use std::io::{self, BufReader, Read};
+
+use protocol::{read_value, ParseLimit, RedirsError, RedirsValue};
+
+#[test]
+fn endless_line_hits_the_inline_limit() {
+    let mut reader = BufReader::new(b"+".chain(io::repeat(b'a')));
+    assert!(matches!(
+        read_value(&mut reader),
+        Err(RedirsError::LimitExceeded(ParseLimit::InlineLen(_)))
+    ));
+    // the same within an aggregate, past frames that did end
+    let head = b"*3\r\n+ok\r\n:1\r\n$".chain(io::repeat(b'9'));
+    assert!(matches!(
+        read_value(&mut BufReader::new(head)),
+        Err(RedirsError::LimitExceeded(ParseLimit::InlineLen(_)))
+    ));
+}
+
+#[test]
+fn long_lines_and_payloads_under_the_limit() {
+    let text = "a".repeat(60 * 1024);
+    let input = format!("+{text}\r\n");
+    let mut reader = BufReader::with_capacity(512, input.as_bytes());
+    assert_eq!(
+        read_value(&mut reader).unwrap(),
+        RedirsValue::SimpleString(text)
+    );
+    // bulk payloads are read by length, CRLF or not
+    let payload = vec![b'x'; 256 * 1024];
+    let value = RedirsValue::from(payload.clone());
+    let bytes = value.to_resp_bytes();
+    let mut reader = BufReader::with_capacity(512, &bytes[..]);
+    assert_eq!(read_value(&mut reader).unwrap(), value);
+}
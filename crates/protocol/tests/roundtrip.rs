@@ -0,0 +1,32 @@
+use proptest::prelude::*;
+use protocol::{
+    test_support::{arb_value, roundtrip},
+    Lexer, ProcVersion, RedirsError, RedirsValue,
+};
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(2000))]
+
+    #[test]
+    fn lex_of_write_is_the_value(value in any::<RedirsValue>()) {
+        prop_assert_eq!(roundtrip(&value)?, value);
+    }
+
+    #[test]
+    fn resp2_values_roundtrip_in_resp2(value in arb_value(ProcVersion::V2)) {
+        let mut out = Vec::new();
+        value.write_resp(&mut out, ProcVersion::V2)?;
+        let mut lexer = Lexer::new(&out);
+        prop_assert_eq!(lexer.lex()?, value);
+        prop_assert_eq!(lexer.remaining(), b"");
+    }
+
+    // the length is known up front, a frame split at any byte is incomplete
+    #[test]
+    fn every_prefix_is_incomplete(value in any::<RedirsValue>(), cut in any::<prop::sample::Index>()) {
+        let bytes = value.to_resp_bytes();
+        let cut = cut.index(bytes.len());
+        let result = Lexer::new(&bytes[..cut]).lex();
+        prop_assert!(matches!(result, Err(RedirsError::Incomplete(_))), "{:?}", result);
+    }
+}
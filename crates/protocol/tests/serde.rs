@@ -0,0 +1,55 @@
+use protocol::{test_support::ValueGen, ProcVersion, RedirsValue, Sign, VerbatimEncoding};
+use serde_json::json;
+
+fn through_json(value: &RedirsValue) -> RedirsValue {
+    let text = serde_json::to_string(value).unwrap();
+    serde_json::from_str(&text).unwrap_or_else(|e| panic!("{e} reading back {text}"))
+}
+
+#[test]
+fn generated_values_roundtrip() {
+    for (seed, value) in ValueGen::new(41, ProcVersion::V3).take(2000).enumerate() {
+        assert_eq!(through_json(&value), value, "seed {seed}");
+    }
+}
+
+#[test]
+fn tagged_forms() {
+    let cases = [
+        (RedirsValue::from("text"), json!("text")),
+        (
+            RedirsValue::from(&b"\xff\x00"[..]),
+            json!({"bytes": [255, 0]}),
+        ),
+        (
+            RedirsValue::BulkString(None),
+            json!({"null_bulk_string": null}),
+        ),
+        (RedirsValue::Null, json!(null)),
+        (
+            RedirsValue::SimpleString("OK".to_owned()),
+            json!({"simple_string": "OK"}),
+        ),
+        (RedirsValue::Double(f64::NAN), json!({"double": "nan"})),
+        (
+            RedirsValue::BigNumber(Sign::Negative, "123".to_owned()),
+            json!({"big_number": "-123"}),
+        ),
+        (
+            RedirsValue::VerbatimString(VerbatimEncoding::Txt, b"hi".to_vec()),
+            json!({"verbatim_string": ["txt", "hi"]}),
+        ),
+        (
+            RedirsValue::Map(vec![(1.into(), "a".into())].into()),
+            json!({"map": [[1, "a"]]}),
+        ),
+        (
+            RedirsValue::Integer(1).with_attribute("a", true),
+            json!({"attribute": [[["a", true]], 1]}),
+        ),
+    ];
+    for (value, expected) in cases {
+        assert_eq!(serde_json::to_value(&value).unwrap(), expected);
+        assert_eq!(through_json(&value), value);
+    }
+}
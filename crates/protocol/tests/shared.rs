@@ -0,0 +1,69 @@
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    cell::Cell,
+};
+
+use bytes::BytesMut;
+use protocol::{lex_frame, RedirsValue};
+
+const COMMANDS: usize = 1000;
+
+// counts the allocations of the current thread, other tests of the binary
+// and the harness allocate on threads of their own
+struct Counting;
+
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.alloc(layout)
+    }
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.with(|count| count.set(count.get() + 1));
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn allocations(f: impl FnOnce()) -> usize {
+    let start = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - start
+}
+
+#[test]
+fn shared_payloads_are_not_copied() {
+    let mut pipeline = BytesMut::new();
+    for i in 0..COMMANDS {
+        RedirsValue::from(vec![
+            RedirsValue::from("SET"),
+            RedirsValue::from(format!("key:{i}")),
+            RedirsValue::from("x".repeat(64)),
+        ])
+        .encode_into(&mut pipeline);
+    }
+    let copied = allocations(|| {
+        let mut rest = &pipeline[..];
+        while !rest.is_empty() {
+            let (_, used) = lex_frame(rest).unwrap();
+            rest = &rest[used..];
+        }
+    });
+    let shared = allocations(|| {
+        while !pipeline.is_empty() {
+            RedirsValue::parse_from(&mut pipeline).unwrap();
+        }
+    });
+    // the elements of the borrowed and of the returned array, copies add one
+    // per argument, the buffer is shared once in all
+    assert_eq!(copied / COMMANDS, 5);
+    assert_eq!(shared / COMMANDS, 2);
+}
@@ -0,0 +1,259 @@
+use std::{
+    cmp::Ordering,
+    collections::{hash_map::DefaultHasher, BTreeMap, HashMap, HashSet},
+    hash::{Hash, Hasher},
+};
+
+use protocol::{resp, FrameKind, FromRespError, Lexer, RedirsMap, RedirsValue};
+
+#[test]
+fn resp_macro_builds_the_values() {
+    assert_eq!(resp!(42), RedirsValue::Integer(42));
+    assert_eq!(resp!(-1), RedirsValue::Integer(-1));
+    assert_eq!(resp!("hello"), RedirsValue::from("hello"));
+    assert_eq!(
+        resp!(simple "OK"),
+        RedirsValue::SimpleString("OK".to_owned())
+    );
+    assert_eq!(
+        resp!(error "ERR no"),
+        RedirsValue::SimpleError("ERR no".to_owned())
+    );
+    assert_eq!(resp!(nil), RedirsValue::Null);
+    assert_eq!(resp!(false), RedirsValue::Bool(false));
+    assert_eq!(
+        resp!([1, "two", [3], simple "OK"]),
+        RedirsValue::Array(Some(vec![
+            RedirsValue::Integer(1),
+            RedirsValue::from("two"),
+            RedirsValue::Array(Some(vec![RedirsValue::Integer(3)])),
+            RedirsValue::SimpleString("OK".to_owned()),
+        ]))
+    );
+    assert_eq!(resp!([]), RedirsValue::Array(Some(Vec::new())));
+    let key = "computed";
+    assert_eq!(
+        resp!({"a" => 1, key => nil, "nested" => {"b" => [true]}}),
+        RedirsValue::Map(RedirsMap::from(vec![
+            (RedirsValue::from("a"), RedirsValue::Integer(1)),
+            (RedirsValue::from("computed"), RedirsValue::Null),
+            (
+                RedirsValue::from("nested"),
+                RedirsValue::Map(RedirsMap::from(vec![(
+                    RedirsValue::from("b"),
+                    RedirsValue::Array(Some(vec![RedirsValue::Bool(true)])),
+                )])),
+            ),
+        ]))
+    );
+}
+
+#[test]
+fn resp_macro_bytes() {
+    assert_eq!(
+        resp!(["GET", "foo"]).to_resp_bytes(),
+        b"*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n"
+    );
+    // maps keep the order they are written in
+    assert_eq!(
+        resp!({"b" => simple "x", "a" => error "E"}).to_resp_bytes(),
+        b"%2\r\n$1\r\nb\r\n+x\r\n$1\r\na\r\n-E\r\n"
+    );
+}
+
+#[test]
+fn conversions_pick_the_variant() {
+    let cases = [
+        (RedirsValue::from(7i64), &b":7\r\n"[..]),
+        (RedirsValue::from(true), b"#t\r\n"),
+        (RedirsValue::from(0.5), b",0.5\r\n"),
+        // strings are binary safe bulk strings, never simple ones
+        (RedirsValue::from("a"), b"$1\r\na\r\n"),
+        (RedirsValue::from(String::from("a")), b"$1\r\na\r\n"),
+        (RedirsValue::from(&b"\xff"[..]), b"$1\r\n\xff\r\n"),
+        (RedirsValue::from(Some(1i64)), b":1\r\n"),
+        (RedirsValue::from(None::<i64>), b"_\r\n"),
+        (RedirsValue::from(vec![1i64, 2]), b"*2\r\n:1\r\n:2\r\n"),
+        (
+            (1..=2i64).map(|i| i * 10).collect::<RedirsValue>(),
+            b"*2\r\n:10\r\n:20\r\n",
+        ),
+        (
+            [("a", 1i64), ("b", 2)].into_iter().collect::<RedirsValue>(),
+            b"%2\r\n$1\r\na\r\n:1\r\n$1\r\nb\r\n:2\r\n",
+        ),
+    ];
+    for (value, bytes) in cases {
+        assert_eq!(value.to_resp_bytes(), bytes, "{value:?}");
+    }
+}
+
+// a value and what it converts to as an i64, f64, bool and String
+type Row = (
+    RedirsValue,
+    Option<i64>,
+    Option<f64>,
+    Option<bool>,
+    Option<&'static str>,
+);
+
+#[test]
+fn try_from_conversion_matrix() {
+    let simple = |s: &str| RedirsValue::SimpleString(s.to_owned());
+    let matrix: [Row; 9] = [
+        (
+            RedirsValue::Integer(1),
+            Some(1),
+            Some(1.0),
+            Some(true),
+            Some("1"),
+        ),
+        (RedirsValue::Integer(7), Some(7), Some(7.0), None, Some("7")),
+        // numbers stored as strings, as redis replies to GET
+        (
+            RedirsValue::from("42"),
+            Some(42),
+            Some(42.0),
+            None,
+            Some("42"),
+        ),
+        (RedirsValue::from("4.5"), None, Some(4.5), None, Some("4.5")),
+        (RedirsValue::from("abc"), None, None, None, Some("abc")),
+        (simple("OK"), None, None, None, Some("OK")),
+        (RedirsValue::Double(0.5), None, Some(0.5), None, None),
+        (RedirsValue::Bool(false), None, None, Some(false), None),
+        (RedirsValue::Null, None, None, None, None),
+    ];
+    for (value, int, double, boolean, string) in matrix {
+        assert_eq!(i64::try_from(&value).ok(), int, "{value:?} as i64");
+        assert_eq!(f64::try_from(&value).ok(), double, "{value:?} as f64");
+        assert_eq!(bool::try_from(&value).ok(), boolean, "{value:?} as bool");
+        assert_eq!(
+            String::try_from(&value).ok().as_deref(),
+            string,
+            "{value:?} as String"
+        );
+    }
+    assert_eq!(
+        i64::try_from(RedirsValue::Null),
+        Err(FromRespError::TypeMismatch {
+            expected: "an integer",
+            found: FrameKind::Null,
+        })
+    );
+    assert_eq!(
+        String::try_from(RedirsValue::SimpleError("ERR no".to_owned())),
+        Err(FromRespError::Reply("ERR no".to_owned()))
+    );
+}
+
+#[test]
+fn try_from_optional_and_aggregate_targets() {
+    let binary = RedirsValue::from(&b"\xff"[..]);
+    assert_eq!(Vec::<u8>::try_from(&binary).unwrap(), b"\xff");
+    assert!(String::try_from(binary).is_err());
+    assert_eq!(Option::<String>::try_from(RedirsValue::Null), Ok(None));
+    assert_eq!(
+        Option::<String>::try_from(RedirsValue::BulkString(None)),
+        Ok(None)
+    );
+    assert_eq!(
+        Option::<String>::try_from(RedirsValue::from("a")),
+        Ok(Some("a".to_owned()))
+    );
+    let array = RedirsValue::from(vec!["a", "b"]);
+    assert_eq!(
+        Vec::<RedirsValue>::try_from(&array).unwrap(),
+        [RedirsValue::from("a"), RedirsValue::from("b")]
+    );
+    assert_eq!(
+        HashSet::<String>::try_from(&array).unwrap(),
+        HashSet::from(["a".to_owned(), "b".to_owned()])
+    );
+    // RESP2 sends maps as flat arrays of keys and values
+    assert_eq!(
+        BTreeMap::<String, String>::try_from(&array).unwrap(),
+        BTreeMap::from([("a".to_owned(), "b".to_owned())])
+    );
+    let map = resp!({"a" => 1, "b" => 2});
+    assert_eq!(
+        HashMap::<String, i64>::try_from(map).unwrap(),
+        HashMap::from([("a".to_owned(), 1), ("b".to_owned(), 2)])
+    );
+    assert!(Vec::<RedirsValue>::try_from(RedirsValue::Integer(1)).is_err());
+    assert!(BTreeMap::<String, String>::try_from(resp!(["odd"])).is_err());
+}
+
+fn hash(value: &RedirsValue) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    value.hash(&mut hasher);
+    hasher.finish()
+}
+
+#[test]
+fn equal_values_hash_equal() {
+    let set = |vals: Vec<RedirsValue>| RedirsValue::Set(vals.into_iter().collect());
+    let pairs = [
+        (RedirsValue::Double(f64::NAN), RedirsValue::Double(f64::NAN)),
+        (
+            set(vec![1.into(), 2.into(), 3.into()]),
+            set(vec![3.into(), 1.into(), 2.into()]),
+        ),
+        // attributes are metadata, not part of the value
+        (RedirsValue::from("v").with_attribute("ttl", 1), "v".into()),
+    ];
+    for (a, b) in pairs {
+        assert_eq!(a, b);
+        assert_eq!(a.cmp(&b), Ordering::Equal);
+        assert_eq!(hash(&a), hash(&b), "{a:?}");
+    }
+    assert_ne!(RedirsValue::Double(0.0), RedirsValue::Double(-0.0));
+    assert!(RedirsValue::Double(-0.0) < RedirsValue::Double(0.0));
+}
+
+#[test]
+fn total_order_across_variants() {
+    let mut values = vec![
+        RedirsValue::Push(Vec::new()),
+        RedirsValue::Double(f64::NAN),
+        RedirsValue::Double(f64::NEG_INFINITY),
+        RedirsValue::Null,
+        RedirsValue::from("b"),
+        RedirsValue::from("a"),
+        RedirsValue::Integer(2),
+        RedirsValue::Integer(-2),
+        RedirsValue::SimpleString("z".to_owned()),
+    ];
+    values.sort();
+    assert_eq!(
+        values,
+        [
+            RedirsValue::SimpleString("z".to_owned()),
+            RedirsValue::Integer(-2),
+            RedirsValue::Integer(2),
+            RedirsValue::from("a"),
+            RedirsValue::from("b"),
+            RedirsValue::Null,
+            RedirsValue::Double(f64::NEG_INFINITY),
+            RedirsValue::Double(f64::NAN),
+            RedirsValue::Push(Vec::new()),
+        ]
+    );
+}
+
+#[test]
+fn mixed_keys_in_maps_and_sets() {
+    let mut map = BTreeMap::new();
+    map.insert(RedirsValue::Integer(1), "int");
+    map.insert(RedirsValue::from("1"), "bulk");
+    map.insert(RedirsValue::Double(f64::NAN), "nan");
+    map.insert(RedirsValue::Double(f64::NAN), "nan again");
+    map.insert(resp!([1, 2]), "array");
+    assert_eq!(map.len(), 4);
+    assert_eq!(map[&RedirsValue::Double(f64::NAN)], "nan again");
+    let set: HashSet<_> = [resp!(1), resp!("1"), resp!(1), resp!([nil]), resp!([nil])].into();
+    assert_eq!(set.len(), 3);
+    let value = RedirsValue::Set(set);
+    let back = Lexer::new(&value.to_resp_bytes()).lex().unwrap();
+    assert_eq!(back, value);
+}
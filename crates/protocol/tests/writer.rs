@@ -0,0 +1,371 @@
+use std::io::{self, BufReader, Read, Write};
+
+use protocol::{
+    read_bulk, test_support::ValueGen, write_bulk_from, CountingWriter, Lexer, ProcVersion,
+    RedirsMap, RedirsOutput, RedirsValue, Sign, VerbatimEncoding,
+};
+
+fn written(value: &RedirsValue, version: ProcVersion) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.write_resp(&mut out, version).unwrap();
+    out
+}
+
+fn map(pairs: Vec<(RedirsValue, RedirsValue)>) -> RedirsValue {
+    RedirsValue::Map(RedirsMap::from(pairs))
+}
+
+#[test]
+fn resp2_downgrades_resp3_frames() {
+    let cases: [(RedirsValue, &[u8], &[u8]); 11] = [
+        (RedirsValue::Null, b"_\r\n", b"$-1\r\n"),
+        (RedirsValue::Bool(true), b"#t\r\n", b":1\r\n"),
+        (RedirsValue::Bool(false), b"#f\r\n", b":0\r\n"),
+        (RedirsValue::Double(1.5), b",1.5\r\n", b"$3\r\n1.5\r\n"),
+        (
+            RedirsValue::Double(f64::NEG_INFINITY),
+            b",-inf\r\n",
+            b"$4\r\n-inf\r\n",
+        ),
+        (
+            RedirsValue::BigNumber(Sign::Negative, "12".to_owned()),
+            b"(-12\r\n",
+            b"$3\r\n-12\r\n",
+        ),
+        (
+            RedirsValue::BulkError(b"ERR a\nb".to_vec()),
+            b"!7\r\nERR a\nb\r\n",
+            b"-ERR a b\r\n",
+        ),
+        (
+            RedirsValue::VerbatimString(VerbatimEncoding::Txt, b"hi".to_vec()),
+            b"=6\r\ntxt:hi\r\n",
+            b"$2\r\nhi\r\n",
+        ),
+        (
+            map(vec![("a".into(), RedirsValue::Null)]),
+            b"%1\r\n$1\r\na\r\n_\r\n",
+            b"*2\r\n$1\r\na\r\n$-1\r\n",
+        ),
+        (
+            RedirsValue::Set([RedirsValue::Bool(true)].into_iter().collect()),
+            b"~1\r\n#t\r\n",
+            b"*1\r\n:1\r\n",
+        ),
+        (
+            RedirsValue::Push(vec!["message".into(), RedirsValue::Double(2.0)]),
+            b">2\r\n$7\r\nmessage\r\n,2\r\n",
+            b"*2\r\n$7\r\nmessage\r\n$1\r\n2\r\n",
+        ),
+    ];
+    for (value, v3, v2) in cases {
+        assert_eq!(
+            written(&value, ProcVersion::V3).escape_ascii().to_string(),
+            v3.escape_ascii().to_string()
+        );
+        assert_eq!(
+            written(&value, ProcVersion::V2).escape_ascii().to_string(),
+            v2.escape_ascii().to_string()
+        );
+    }
+}
+
+#[test]
+fn resp2_frames_stay_as_they_are() {
+    for value in [
+        RedirsValue::SimpleString("OK".to_owned()),
+        RedirsValue::SimpleError("ERR no".to_owned()),
+        RedirsValue::Integer(-3),
+        RedirsValue::BulkString(None),
+        RedirsValue::Array(None),
+        RedirsValue::from(vec!["a", "b"]),
+    ] {
+        assert_eq!(
+            written(&value, ProcVersion::V2),
+            written(&value, ProcVersion::V3)
+        );
+    }
+}
+
+#[test]
+fn binary_payloads_roundtrip() {
+    let payload = b"\x00\xff\xfe\r\n\x80".to_vec();
+    // lengths count bytes, not characters
+    for (value, header) in [
+        (RedirsValue::from(payload.clone()), &b"$6\r\n"[..]),
+        (RedirsValue::BulkError(payload.clone()), b"!6\r\n"),
+        (
+            RedirsValue::VerbatimString(VerbatimEncoding::Txt, payload.clone()),
+            b"=10\r\n",
+        ),
+    ] {
+        let bytes = value.to_resp_bytes();
+        assert!(bytes.starts_with(header), "{}", bytes.escape_ascii());
+        assert_eq!(Lexer::new(&bytes).lex().unwrap(), value);
+        assert_eq!(value.as_bytes(), Some(&payload[..]));
+        assert_eq!(value.as_str(), None);
+    }
+    assert_eq!(RedirsValue::from("héllo").as_str(), Some("héllo"));
+    assert_eq!(
+        RedirsValue::from(String::from("héllo")).to_resp_bytes(),
+        "$6\r\nhéllo\r\n".as_bytes()
+    );
+}
+
+#[test]
+fn encoded_len_is_the_written_length() {
+    for version in [ProcVersion::V2, ProcVersion::V3] {
+        for (seed, value) in ValueGen::new(33, version).take(2000).enumerate() {
+            let mut out = Vec::new();
+            value.write_resp_str(&mut out).unwrap();
+            assert_eq!(value.encoded_len(), out.len(), "seed {seed}: {value:?}");
+            assert_eq!(value.to_resp_bytes(), out);
+        }
+    }
+    // the text of simple frames spanning lines is written as a bulk frame
+    for value in [
+        RedirsValue::SimpleString("a\r\nb".to_owned()),
+        RedirsValue::SimpleError("a\nb".to_owned()),
+        RedirsValue::BigNumber(Sign::Positive, "1\r2".to_owned()),
+        RedirsValue::Double(f64::MIN_POSITIVE),
+        RedirsValue::Integer(i64::MIN),
+    ] {
+        assert_eq!(
+            value.encoded_len(),
+            value.to_resp_bytes().len(),
+            "{value:?}"
+        );
+    }
+}
+
+#[test]
+fn nulls_across_versions() {
+    let lex = |input: &[u8]| Lexer::new(input).lex().unwrap();
+    assert_eq!(lex(b"$-1\r\n"), RedirsValue::BulkString(None));
+    assert_eq!(lex(b"*-1\r\n"), RedirsValue::Array(None));
+    assert_eq!(lex(b"_\r\n"), RedirsValue::Null);
+    for input in [&b"$-1\r\n"[..], b"*-1\r\n", b"_\r\n"] {
+        assert!(lex(input).is_null());
+        // what a RESP3 client stored is always `_` for RESP3 clients
+        let v3 = lex(input).normalize_nulls(ProcVersion::V3);
+        assert_eq!(written(&v3, ProcVersion::V3), b"_\r\n");
+    }
+    // RESP2 keeps null arrays apart and spells the rest `$-1`
+    let stored = RedirsValue::from(vec![
+        RedirsValue::Null,
+        RedirsValue::Array(None),
+        RedirsValue::Push(vec![RedirsValue::Null]),
+    ]);
+    let v2 = stored.clone().normalize_nulls(ProcVersion::V2);
+    assert_eq!(
+        written(&v2, ProcVersion::V2),
+        b"*3\r\n$-1\r\n*-1\r\n*1\r\n$-1\r\n"
+    );
+    let v3 = v2.normalize_nulls(ProcVersion::V3);
+    assert_eq!(
+        written(&v3, ProcVersion::V3),
+        b"*3\r\n_\r\n_\r\n>1\r\n_\r\n"
+    );
+    assert!(!RedirsValue::from("").is_null());
+}
+
+fn canonical(value: &RedirsValue, version: ProcVersion) -> Vec<u8> {
+    let mut out = Vec::new();
+    value.write_resp_canonical(&mut out, version).unwrap();
+    out
+}
+
+#[test]
+fn canonical_sets_are_stable() {
+    // every `HashSet` is seeded anew, so the sets iterate in different orders
+    let sets: Vec<_> = (0..32)
+        .map(|i| {
+            let mut elements: Vec<RedirsValue> = (0..16).map(RedirsValue::from).collect();
+            elements.rotate_left(i % 16);
+            RedirsValue::from(vec![RedirsValue::Set(elements.into_iter().collect())])
+        })
+        .collect();
+    for version in [ProcVersion::V2, ProcVersion::V3] {
+        let first = canonical(&sets[0], version);
+        assert!(sets.iter().all(|set| canonical(set, version) == first));
+    }
+    let small = RedirsValue::Set(["b", "a", "c"].map(RedirsValue::from).into());
+    assert_eq!(
+        canonical(&small, ProcVersion::V3),
+        b"~3\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n"
+    );
+    assert_eq!(
+        canonical(&small, ProcVersion::V2),
+        b"*3\r\n$1\r\na\r\n$1\r\nb\r\n$1\r\nc\r\n"
+    );
+}
+
+// the example of the RESP3 spec, a reply with the popularity of its keys
+#[test]
+fn attributes_go_ahead_of_the_value() {
+    let popularity = RedirsValue::Map(RedirsMap::from(vec![
+        ("a".into(), RedirsValue::Double(0.1923)),
+        ("b".into(), RedirsValue::Double(0.0012)),
+    ]));
+    let reply = RedirsValue::from(vec![2039123i64, 9543892]).with_attribute(
+        RedirsValue::SimpleString("key-popularity".to_owned()),
+        popularity,
+    );
+    let v3: &[u8] = b"|1\r\n+key-popularity\r\n%2\r\n$1\r\na\r\n,0.1923\r\n$1\r\nb\r\n,0.0012\r\n*2\r\n:2039123\r\n:9543892\r\n";
+    assert_eq!(written(&reply, ProcVersion::V3), v3);
+    assert_eq!(Lexer::new(v3).lex().unwrap(), reply);
+    // RESP2 has no out of band data, the attribute is dropped
+    assert_eq!(
+        written(&reply, ProcVersion::V2),
+        b"*2\r\n:2039123\r\n:9543892\r\n"
+    );
+    // and values compare without it
+    assert_eq!(reply, RedirsValue::from(vec![2039123i64, 9543892]));
+    let twice = RedirsValue::Integer(1)
+        .with_attribute("a", 1)
+        .with_attribute("b", 2);
+    assert_eq!(
+        written(&twice, ProcVersion::V3),
+        b"|2\r\n$1\r\na\r\n:1\r\n$1\r\nb\r\n:2\r\n:1\r\n"
+    );
+    // the pretty form shows them on lines above the value, as redis-cli does
+    assert_eq!(
+        twice.to_string(),
+        "|1# \"a\" => (integer) 1\n 2# \"b\" => (integer) 2\n(integer) 1"
+    );
+}
+
+#[test]
+fn other_verbatim_tags() {
+    let ext = VerbatimEncoding::new(b"ext").unwrap();
+    assert_eq!(ext.to_string(), "ext");
+    let value = RedirsValue::VerbatimString(ext, b"body".to_vec());
+    // the length covers the tag and the colon
+    assert_eq!(value.to_resp_bytes(), b"=8\r\next:body\r\n");
+    assert_eq!(value.encoded_len(), 14);
+    assert_eq!(Lexer::new(&value.to_resp_bytes()).lex().unwrap(), value);
+    assert!(VerbatimEncoding::new(b"html").is_err());
+    assert_eq!(VerbatimEncoding::from_tag(*b"mrk"), VerbatimEncoding::Mrk);
+}
+
+// accepts `left` bytes, then fails every write
+struct Failing {
+    left: usize,
+}
+
+impl Write for Failing {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if self.left == 0 {
+            return Err(io::ErrorKind::BrokenPipe.into());
+        }
+        let n = buf.len().min(self.left);
+        self.left -= n;
+        Ok(n)
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn counting_writer_counts_frames_and_bytes() {
+    let nested = RedirsValue::from(vec![
+        RedirsValue::from(vec!["a", "b"]),
+        RedirsValue::Map(RedirsMap::from(vec![("k".into(), 1.into())])),
+    ]);
+    let mut out = CountingWriter::new(Vec::new());
+    assert_eq!(
+        out.write_frame(&nested).unwrap(),
+        nested.encoded_len() as u64
+    );
+    let n = out
+        .write_value(&RedirsValue::Null, ProcVersion::V2)
+        .unwrap();
+    assert_eq!(n, 5);
+    // nested values are one top level frame
+    assert_eq!(out.frames_written(), 2);
+    assert_eq!(out.bytes_written(), out.get_ref().len() as u64);
+
+    let mut out = CountingWriter::new(Failing { left: 7 });
+    assert!(out.write_frame(&nested).is_err());
+    // what made it to the sink counts, the frame does not
+    assert_eq!((out.bytes_written(), out.frames_written()), (7, 0));
+}
+
+// `len` bytes of a pattern made up on the fly, checking that no reader asks
+// for more than `max_read` bytes at once
+struct Synthetic {
+    len: u64,
+    max_read: usize,
+}
+
+impl Read for Synthetic {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        assert!(buf.len() <= self.max_read, "a {} byte read", buf.len());
+        let n = buf
+            .len()
+            .min(usize::try_from(self.len).unwrap_or(usize::MAX));
+        buf[..n].iter_mut().for_each(|b| *b = b'x');
+        self.len -= n as u64;
+        Ok(n)
+    }
+}
+
+const HUGE: u64 = 300 * 1024 * 1024;
+
+#[test]
+fn huge_bulk_streams_through_small_buffers() {
+    let source = Synthetic {
+        len: HUGE,
+        max_read: 64 * 1024,
+    };
+    let mut out = CountingWriter::new(io::sink());
+    write_bulk_from(&mut out, HUGE, source).unwrap();
+    let header = format!("${HUGE}\r\n").len() as u64;
+    assert_eq!(out.bytes_written(), header + HUGE + 2);
+
+    // and read back through a limited reader, the frame never held at once
+    let frame = io::Cursor::new(format!("${HUGE}\r\n"))
+        .chain(Synthetic {
+            len: HUGE,
+            max_read: 64 * 1024,
+        })
+        .chain(&b"\r\n"[..]);
+    let mut frame = BufReader::with_capacity(16 * 1024, frame);
+    let mut payload = read_bulk(&mut frame).unwrap().unwrap();
+    assert_eq!(payload.remaining(), HUGE);
+    assert_eq!(io::copy(&mut payload, &mut io::sink()).unwrap(), HUGE);
+    assert_eq!(payload.remaining(), 0);
+}
+
+#[test]
+fn streamed_payload_of_the_wrong_length() {
+    let mut out = Vec::new();
+    let err = write_bulk_from(&mut out, 5, &b"abc"[..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+    let err = write_bulk_from(&mut Vec::new(), 2, &b"abc"[..]).unwrap_err();
+    assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    let mut out = Vec::new();
+    write_bulk_from(&mut out, 3, &b"abc"[..]).unwrap();
+    assert_eq!(out, b"$3\r\nabc\r\n");
+}
+
+#[test]
+fn dyn_and_text_sinks() {
+    let value = RedirsValue::from(vec![
+        RedirsValue::from("ok"),
+        RedirsValue::from(&b"\xff"[..]),
+    ]);
+    let mut sinks: Vec<Box<dyn Write>> = vec![Box::new(Vec::new()), Box::new(io::sink())];
+    for sink in &mut sinks {
+        value.write_resp_dyn(sink.as_mut()).unwrap();
+    }
+    let mut bytes = Vec::new();
+    value.write_resp_dyn(&mut bytes).unwrap();
+    assert_eq!(bytes, value.to_resp_bytes());
+    // bytes that are not UTF-8 are escaped for the text forms
+    assert_eq!(value.to_resp_string(), "*2\r\n$2\r\nok\r\n$1\r\n\\xff\r\n");
+    let mut text = String::from("> ");
+    value.write_resp_fmt(&mut text).unwrap();
+    assert_eq!(text, format!("> {}", value.to_resp_string()));
+}
@@ -0,0 +1,478 @@
+// who may run what: the users connections authenticate as, each with its
+// passwords, the commands it can run and the keys it can touch. The default
+// user is the one connections start as, with no password and every permission
+// until requirepass gives it one
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    sync::{Mutex, MutexGuard},
+};
+
+use crate::{
+    commands::{self, Command},
+    glob,
+    scripting::hex,
+};
+
+pub(crate) const DEFAULT_USER: &[u8] = b"default";
+
+// in the order ACL CAT lists them
+pub(crate) const CATEGORIES: &[&str] = &[
+    "keyspace",
+    "read",
+    "write",
+    "set",
+    "sortedset",
+    "list",
+    "hash",
+    "string",
+    "bitmap",
+    "hyperloglog",
+    "geo",
+    "stream",
+    "pubsub",
+    "admin",
+    "fast",
+    "slow",
+    "blocking",
+    "dangerous",
+    "connection",
+    "transaction",
+    "scripting",
+];
+
+// whether `command` is in `category`, as its table entry says or as its
+// flags do: a command that is not fast is slow and an admin one dangerous
+pub(crate) fn in_category(command: &Command, category: &str) -> bool {
+    let flagged = |flag: &str| command.flags.contains(&flag);
+    command.categories.contains(&category)
+        || match category {
+            "read" => flagged("readonly"),
+            "write" | "pubsub" | "fast" | "blocking" | "admin" => flagged(category),
+            "slow" => !flagged("fast"),
+            "dangerous" => flagged("admin"),
+            _ => false,
+        }
+}
+
+// a +command, -command, +@category or -@category rule, applied in order on
+// top of +@all or -@all
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Rule {
+    allow: bool,
+    // a command name, with its subcommand after a | for one of them only
+    command: Option<String>,
+    category: Option<&'static str>,
+}
+
+impl Rule {
+    // whether the rule is about `command` run with `args`, the name first
+    fn covers(&self, command: &Command, args: &[&[u8]]) -> bool {
+        match (&self.command, self.category) {
+            (Some(name), _) => match name.split_once('|') {
+                Some((name, sub)) => {
+                    name == command.name
+                        && args
+                            .get(1)
+                            .is_some_and(|arg| arg.eq_ignore_ascii_case(sub.as_bytes()))
+                }
+                None => name == command.name,
+            },
+            (None, Some(category)) => in_category(command, category),
+            (None, None) => false,
+        }
+    }
+    fn describe(&self) -> String {
+        let sign = if self.allow { '+' } else { '-' };
+        match (&self.command, self.category) {
+            (Some(name), _) => format!("{sign}{name}"),
+            (_, category) => format!("{sign}@{}", category.unwrap_or_default()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct User {
+    pub enabled: bool,
+    // any password does
+    pub nopass: bool,
+    // the SHA256 of each
+    passwords: BTreeSet<[u8; 32]>,
+    // +@all or -@all, what the rules go from
+    all_commands: bool,
+    rules: Vec<Rule>,
+    // the glob patterns of the keys it can touch
+    keys: Vec<Vec<u8>>,
+}
+
+// why a user cannot run a command
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Denied {
+    // it was deleted since the connection authenticated as it
+    NoUser,
+    Command,
+    Key(Vec<u8>),
+}
+
+impl User {
+    // what ACL SETUSER creates: disabled, with no password and no
+    // permission
+    fn new() -> User {
+        User {
+            enabled: false,
+            nopass: false,
+            passwords: BTreeSet::new(),
+            all_commands: false,
+            rules: Vec::new(),
+            keys: Vec::new(),
+        }
+    }
+    fn default_user() -> User {
+        User {
+            enabled: true,
+            nopass: true,
+            all_commands: true,
+            keys: vec![b"*".to_vec()],
+            ..User::new()
+        }
+    }
+    // applies an ACL SETUSER rule, the reason it is wrong otherwise
+    pub fn apply(&mut self, rule: &[u8]) -> Result<(), &'static str> {
+        let text = String::from_utf8_lossy(rule);
+        let lower = text.to_ascii_lowercase();
+        match lower.as_str() {
+            "on" => self.enabled = true,
+            "off" => self.enabled = false,
+            "nopass" => {
+                self.nopass = true;
+                self.passwords.clear();
+            }
+            "resetpass" => {
+                self.nopass = false;
+                self.passwords.clear();
+            }
+            "allkeys" => self.keys = vec![b"*".to_vec()],
+            "resetkeys" => self.keys.clear(),
+            "allcommands" => self.set_all_commands(true),
+            "nocommands" => self.set_all_commands(false),
+            "reset" => *self = User::new(),
+            _ => match rule.split_first() {
+                Some((b'>', password)) => {
+                    self.passwords.insert(sha256(password));
+                    self.nopass = false;
+                }
+                Some((b'<', password)) => {
+                    if !self.passwords.remove(&sha256(password)) {
+                        return Err(
+                            "The password you are trying to remove from the user does not exist",
+                        );
+                    }
+                }
+                Some((b'#', hash)) => {
+                    self.passwords.insert(parse_hash(hash)?);
+                    self.nopass = false;
+                }
+                Some((b'!', hash)) => {
+                    if !self.passwords.remove(&parse_hash(hash)?) {
+                        return Err(
+                            "The password you are trying to remove from the user does not exist",
+                        );
+                    }
+                }
+                Some((b'~', pattern)) => {
+                    if !self.keys.iter().any(|key| key == pattern) {
+                        self.keys.push(pattern.to_vec());
+                    }
+                }
+                Some((sign @ (b'+' | b'-'), name)) => self.add_rule(*sign == b'+', name)?,
+                _ => return Err("Syntax error"),
+            },
+        }
+        Ok(())
+    }
+    fn set_all_commands(&mut self, allow: bool) {
+        self.all_commands = allow;
+        self.rules.clear();
+    }
+    fn add_rule(&mut self, allow: bool, name: &[u8]) -> Result<(), &'static str> {
+        const UNKNOWN: &str = "Unknown command or category name in ACL";
+        let name = String::from_utf8_lossy(name).to_ascii_lowercase();
+        let rule = match name.strip_prefix('@') {
+            Some("all") => {
+                self.set_all_commands(allow);
+                return Ok(());
+            }
+            Some(category) => Rule {
+                allow,
+                command: None,
+                category: Some(CATEGORIES.iter().find(|c| **c == category).ok_or(UNKNOWN)?),
+            },
+            None => {
+                let command = name.split_once('|').map_or(&name[..], |(name, _)| name);
+                commands::spec(command.as_bytes()).ok_or(UNKNOWN)?;
+                Rule {
+                    allow,
+                    command: Some(name),
+                    category: None,
+                }
+            }
+        };
+        self.rules.push(rule);
+        Ok(())
+    }
+    // on or off, and nopass
+    pub fn flags(&self) -> Vec<&'static str> {
+        let mut flags = vec![if self.enabled { "on" } else { "off" }];
+        if self.nopass {
+            flags.push("nopass");
+        }
+        flags
+    }
+    pub fn passwords(&self) -> Vec<String> {
+        self.passwords.iter().map(|hash| hex(hash)).collect()
+    }
+    pub fn commands(&self) -> String {
+        let base = if self.all_commands { "+@all" } else { "-@all" };
+        std::iter::once(base.to_owned())
+            .chain(self.rules.iter().map(Rule::describe))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+    pub fn keys(&self) -> String {
+        self.keys
+            .iter()
+            .map(|pattern| format!("~{}", String::from_utf8_lossy(pattern)))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+    // the whole of it as ACL LIST shows it
+    pub fn describe(&self) -> String {
+        let passwords = self.passwords().into_iter().map(|hash| format!("#{hash}"));
+        let keys = Some(self.keys()).filter(|keys| !keys.is_empty());
+        self.flags()
+            .into_iter()
+            .map(str::to_owned)
+            .chain(passwords)
+            .chain(keys)
+            .chain(Some(self.commands()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+    fn can_run(&self, command: &Command, args: &[&[u8]]) -> bool {
+        self.rules
+            .iter()
+            .rev()
+            .find(|rule| rule.covers(command, args))
+            .map_or(self.all_commands, |rule| rule.allow)
+    }
+    fn can_touch(&self, key: &[u8]) -> bool {
+        self.keys.iter().any(|pattern| glob::matches(pattern, key))
+    }
+}
+
+// a password hash given to #, 64 lowercase hex digits
+fn parse_hash(hash: &[u8]) -> Result<[u8; 32], &'static str> {
+    const WRONG: &str =
+        "The password hash must be exactly 64 characters and contain only lowercase hexadecimal characters";
+    let digit = |d: u8| match d {
+        b'0'..=b'9' => Some(d - b'0'),
+        b'a'..=b'f' => Some(d - b'a' + 10),
+        _ => None,
+    };
+    if hash.len() != 64 {
+        return Err(WRONG);
+    }
+    let mut parsed = [0; 32];
+    for (byte, pair) in parsed.iter_mut().zip(hash.chunks_exact(2)) {
+        *byte = digit(pair[0]).ok_or(WRONG)? << 4 | digit(pair[1]).ok_or(WRONG)?;
+    }
+    Ok(parsed)
+}
+
+#[derive(Debug)]
+pub(crate) struct Acl {
+    users: Mutex<BTreeMap<Vec<u8>, User>>,
+    // the last requirepass given, what CONFIG GET shows of it
+    requirepass: Mutex<Vec<u8>>,
+}
+
+impl Default for Acl {
+    fn default() -> Self {
+        Acl {
+            users: Mutex::new(BTreeMap::from([(
+                DEFAULT_USER.to_vec(),
+                User::default_user(),
+            )])),
+            requirepass: Mutex::default(),
+        }
+    }
+}
+
+impl Acl {
+    fn users(&self) -> MutexGuard<'_, BTreeMap<Vec<u8>, User>> {
+        self.users.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    // requirepass: the password of the default user, none for it to take
+    // any
+    pub fn set_requirepass(&self, password: Option<Vec<u8>>) {
+        let mut users = self.users();
+        let default = users
+            .entry(DEFAULT_USER.to_vec())
+            .or_insert_with(User::default_user);
+        default.passwords.clear();
+        default.nopass = password.is_none();
+        default.passwords.extend(password.as_deref().map(sha256));
+        *self.requirepass.lock().unwrap_or_else(|e| e.into_inner()) = password.unwrap_or_default();
+    }
+    // the last requirepass given, empty for none
+    pub fn requirepass(&self) -> Vec<u8> {
+        self.requirepass
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+    // whether the default user needs no password, connections then start
+    // authenticated as it
+    pub fn open(&self) -> bool {
+        self.users()
+            .get(DEFAULT_USER)
+            .is_some_and(|user| user.enabled && user.nopass)
+    }
+    // whether `user` goes by `password`. The hashes are compared in the same
+    // time wherever they differ
+    pub fn check(&self, user: &[u8], password: &[u8]) -> bool {
+        let hash = sha256(password);
+        self.users().get(user).is_some_and(|user| {
+            user.enabled
+                && (user.nopass
+                    || user
+                        .passwords
+                        .iter()
+                        .fold(false, |found, p| found | constant_time_eq(p, &hash)))
+        })
+    }
+    pub fn get(&self, name: &[u8]) -> Option<User> {
+        self.users().get(name).cloned()
+    }
+    // applies every rule or none of them, the user created when missing.
+    // The rule that is wrong and why otherwise
+    pub fn set_user<'a>(
+        &self,
+        name: &[u8],
+        rules: &[&'a [u8]],
+    ) -> Result<(), (&'a [u8], &'static str)> {
+        let mut users = self.users();
+        let mut user = users.get(name).cloned().unwrap_or_else(User::new);
+        for rule in rules {
+            user.apply(rule).map_err(|reason| (*rule, reason))?;
+        }
+        users.insert(name.to_vec(), user);
+        Ok(())
+    }
+    // how many of them there were
+    pub fn delete(&self, names: &[&[u8]]) -> usize {
+        let mut users = self.users();
+        names
+            .iter()
+            .filter(|name| users.remove(**name).is_some())
+            .count()
+    }
+    // in name order
+    pub fn list(&self) -> Vec<(Vec<u8>, User)> {
+        self.users()
+            .iter()
+            .map(|(name, user)| (name.clone(), user.clone()))
+            .collect()
+    }
+    // whether `user` can run `command` with `args`, the name first, on every
+    // key it is given
+    pub fn permitted(&self, user: &[u8], command: &Command, args: &[&[u8]]) -> Result<(), Denied> {
+        let users = self.users();
+        let user = users.get(user).ok_or(Denied::NoUser)?;
+        if !user.can_run(command, args) {
+            return Err(Denied::Command);
+        }
+        match command
+            .keys
+            .of(args)
+            .into_iter()
+            .find(|key| !user.can_touch(key))
+        {
+            Some(key) => Err(Denied::Key(key.to_vec())),
+            None => Ok(()),
+        }
+    }
+}
+
+// every byte of the longer of the two is looked at, only the length of a
+// wrong guess shows in the time taken
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    let diff = (0..a.len().max(b.len())).fold(a.len() ^ b.len(), |diff, i| {
+        let x = a.get(i).copied().unwrap_or(0);
+        let y = b.get(i).copied().unwrap_or(0);
+        diff | usize::from(x ^ y)
+    });
+    std::hint::black_box(diff) == 0
+}
+
+const K: [u32; 64] = [
+    0x428a2f98, 0x71374491, 0xb5c0fbcf, 0xe9b5dba5, 0x3956c25b, 0x59f111f1, 0x923f82a4, 0xab1c5ed5,
+    0xd807aa98, 0x12835b01, 0x243185be, 0x550c7dc3, 0x72be5d74, 0x80deb1fe, 0x9bdc06a7, 0xc19bf174,
+    0xe49b69c1, 0xefbe4786, 0x0fc19dc6, 0x240ca1cc, 0x2de92c6f, 0x4a7484aa, 0x5cb0a9dc, 0x76f988da,
+    0x983e5152, 0xa831c66d, 0xb00327c8, 0xbf597fc7, 0xc6e00bf3, 0xd5a79147, 0x06ca6351, 0x14292967,
+    0x27b70a85, 0x2e1b2138, 0x4d2c6dfc, 0x53380d13, 0x650a7354, 0x766a0abb, 0x81c2c92e, 0x92722c85,
+    0xa2bfe8a1, 0xa81a664b, 0xc24b8b70, 0xc76c51a3, 0xd192e819, 0xd6990624, 0xf40e3585, 0x106aa070,
+    0x19a4c116, 0x1e376c08, 0x2748774c, 0x34b0bcb5, 0x391c0cb3, 0x4ed8aa4a, 0x5b9cca4f, 0x682e6ff3,
+    0x748f82ee, 0x78a5636f, 0x84c87814, 0x8cc70208, 0x90befffa, 0xa4506ceb, 0xbef9a3f7, 0xc67178f2,
+];
+
+// SHA256 as FIPS 180-4 has it, what passwords are kept as
+pub(crate) fn sha256(message: &[u8]) -> [u8; 32] {
+    let mut h: [u32; 8] = [
+        0x6a09e667, 0xbb67ae85, 0x3c6ef372, 0xa54ff53a, 0x510e527f, 0x9b05688c, 0x1f83d9ab,
+        0x5be0cd19,
+    ];
+    // padded the way SHA1 is, see `scripting::sha1`
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&(message.len() as u64 * 8).to_be_bytes());
+    for block in padded.chunks_exact(64) {
+        let mut w = [0u32; 64];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().expect("four bytes"));
+        }
+        for i in 16..64 {
+            let s0 = w[i - 15].rotate_right(7) ^ w[i - 15].rotate_right(18) ^ (w[i - 15] >> 3);
+            let s1 = w[i - 2].rotate_right(17) ^ w[i - 2].rotate_right(19) ^ (w[i - 2] >> 10);
+            w[i] = w[i - 16]
+                .wrapping_add(s0)
+                .wrapping_add(w[i - 7])
+                .wrapping_add(s1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e, mut f, mut g, mut hh] = h;
+        for (k, word) in K.iter().zip(w) {
+            let s1 = e.rotate_right(6) ^ e.rotate_right(11) ^ e.rotate_right(25);
+            let ch = (e & f) ^ (!e & g);
+            let t1 = hh
+                .wrapping_add(s1)
+                .wrapping_add(ch)
+                .wrapping_add(*k)
+                .wrapping_add(word);
+            let s0 = a.rotate_right(2) ^ a.rotate_right(13) ^ a.rotate_right(22);
+            let maj = (a & b) ^ (a & c) ^ (b & c);
+            let t2 = s0.wrapping_add(maj);
+            (hh, g, f, e, d, c, b, a) = (g, f, e, d.wrapping_add(t1), c, b, a, t1.wrapping_add(t2));
+        }
+        for (h, x) in h.iter_mut().zip([a, b, c, d, e, f, g, hh]) {
+            *h = h.wrapping_add(x);
+        }
+    }
+    let mut digest = [0; 32];
+    for (bytes, h) in digest.chunks_exact_mut(4).zip(h) {
+        bytes.copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
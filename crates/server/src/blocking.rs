@@ -0,0 +1,191 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::{Arc, Mutex},
+};
+
+use protocol::RedirsValue;
+use tokio::{sync::Notify, time::Instant};
+
+use crate::{sorted_set::SortedSet, Db};
+
+// which end of a list a command works on, for a sorted set the left one holds
+// the lowest scores
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum End {
+    Left,
+    Right,
+}
+
+impl End {
+    pub fn push(self, list: &mut VecDeque<Vec<u8>>, element: Vec<u8>) {
+        match self {
+            End::Left => list.push_front(element),
+            End::Right => list.push_back(element),
+        }
+    }
+    pub fn pop(self, list: &mut VecDeque<Vec<u8>>) -> Option<Vec<u8>> {
+        match self {
+            End::Left => list.pop_front(),
+            End::Right => list.pop_back(),
+        }
+    }
+    pub fn pop_member(self, zset: &mut SortedSet) -> Option<(Vec<u8>, f64)> {
+        let rank = match self {
+            End::Left => 0,
+            End::Right => zset.len().checked_sub(1)?,
+        };
+        let (member, score) = zset.iter_from(rank).next()?;
+        let member = member.to_vec();
+        zset.remove(&member);
+        Some((member, score))
+    }
+}
+
+// what a blocked connection waits to pop
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Pop {
+    // BLPOP and BRPOP
+    List(End),
+    // BZPOPMIN and BZPOPMAX
+    SortedSet(End),
+}
+
+// what a blocked connection was handed
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Popped {
+    Element(Vec<u8>),
+    Member(Vec<u8>, f64),
+}
+
+// a connection blocked until one of `keys` gets an element. The element is
+// handed over with the keyspace locked, which also guards `delivered`
+#[derive(Debug)]
+pub(crate) struct Waiter {
+    keys: Vec<Vec<u8>>,
+    pop: Pop,
+    // the key served from and what was taken from it
+    delivered: Mutex<Option<(Vec<u8>, Popped)>>,
+    notify: Notify,
+}
+
+impl Waiter {
+    pub fn pop(&self) -> Pop {
+        self.pop
+    }
+    pub fn deliver(&self, key: Vec<u8>, popped: Popped) {
+        *self.delivered.lock().unwrap_or_else(|e| e.into_inner()) = Some((key, popped));
+        self.notify.notify_one();
+    }
+    fn take(&self) -> Option<(Vec<u8>, Popped)> {
+        self.delivered
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take()
+    }
+}
+
+// the waiters of every key in the order they blocked
+#[derive(Debug, Default)]
+pub(crate) struct Waiters {
+    by_key: HashMap<Vec<u8>, VecDeque<Arc<Waiter>>>,
+}
+
+impl Waiters {
+    pub fn add(&mut self, waiter: &Arc<Waiter>) {
+        for key in &waiter.keys {
+            self.by_key
+                .entry(key.clone())
+                .or_default()
+                .push_back(waiter.clone());
+        }
+    }
+    // the longest waiting on `key` to pop what `serves` accepts
+    pub fn first(&self, key: &[u8], serves: impl Fn(Pop) -> bool) -> Option<Arc<Waiter>> {
+        self.by_key
+            .get(key)?
+            .iter()
+            .find(|waiter| serves(waiter.pop))
+            .cloned()
+    }
+    // the keys connections wait on
+    pub fn keys(&self) -> Vec<Vec<u8>> {
+        self.by_key.keys().cloned().collect()
+    }
+    // off the queue of every key it waits on
+    pub fn remove(&mut self, waiter: &Arc<Waiter>) {
+        for key in &waiter.keys {
+            if let Some(queue) = self.by_key.get_mut(key) {
+                queue.retain(|other| !Arc::ptr_eq(other, waiter));
+                if queue.is_empty() {
+                    self.by_key.remove(key);
+                }
+            }
+        }
+    }
+}
+
+// what the blocking pops hand the connection when there is nothing to pop yet
+#[derive(Debug)]
+pub(crate) struct Block {
+    db: Db,
+    waiter: Arc<Waiter>,
+    // none waits forever
+    deadline: Option<Instant>,
+}
+
+impl Block {
+    // registers a waiter on `keys`, in the order of the keys given
+    pub fn new(db: &Db, keys: &[&[u8]], pop: Pop, deadline: Option<Instant>) -> Self {
+        let waiter = Arc::new(Waiter {
+            keys: keys.iter().map(|key| key.to_vec()).collect(),
+            pop,
+            delivered: Mutex::new(None),
+            notify: Notify::new(),
+        });
+        db.lock().waiters_mut().add(&waiter);
+        Self {
+            db: db.clone(),
+            waiter,
+            deadline,
+        }
+    }
+    // [key, element] or [key, member, score] once served, a nil array at the
+    // deadline
+    pub async fn wait(&self) -> RedirsValue {
+        let notified = self.waiter.notify.notified();
+        match self.deadline {
+            Some(deadline) => {
+                let _ = tokio::time::timeout_at(deadline, notified).await;
+            }
+            None => notified.await,
+        }
+        // an element may have come in right at the deadline, the lock settles
+        // whether it did
+        let mut keyspace = self.db.lock();
+        keyspace.waiters_mut().remove(&self.waiter);
+        match self.waiter.take() {
+            Some((key, Popped::Element(element))) => RedirsValue::Array(Some(vec![
+                RedirsValue::from(key),
+                RedirsValue::from(element),
+            ])),
+            Some((key, Popped::Member(member, score))) => RedirsValue::Array(Some(vec![
+                RedirsValue::from(key),
+                RedirsValue::from(member),
+                RedirsValue::Double(score),
+            ])),
+            None => RedirsValue::Array(None),
+        }
+    }
+}
+
+// a connection closed while blocked gives back what it was handed and not yet
+// replied with, to the next waiter or to the key
+impl Drop for Block {
+    fn drop(&mut self) {
+        let mut keyspace = self.db.lock();
+        keyspace.waiters_mut().remove(&self.waiter);
+        if let Some((key, popped)) = self.waiter.take() {
+            keyspace.give_back(&key, self.waiter.pop, popped);
+        }
+    }
+}
@@ -0,0 +1,175 @@
+// the live connections, for CLIENT LIST and CLIENT KILL. Each one keeps what
+// they show of it up to date after every command and listens for being killed
+// alongside its socket, which is also how those idle past the timeout are
+// closed
+
+use std::{
+    collections::BTreeMap,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+    time::{Duration, Instant},
+};
+
+use protocol::{ProcVersion, RedirsValue};
+use tokio::sync::Notify;
+
+use crate::{pubsub::Mailbox, Db};
+
+// how often connections are checked for being idle past the timeout
+const REAP_EVERY: Duration = Duration::from_millis(100);
+
+// what CLIENT LIST shows of a connection
+#[derive(Debug, Clone)]
+pub(crate) struct Info {
+    pub id: i64,
+    pub addr: SocketAddr,
+    pub laddr: SocketAddr,
+    pub proto: ProcVersion,
+    pub name: Option<Vec<u8>>,
+    pub connected: Instant,
+    // when its last command ran
+    pub last: Instant,
+    pub db: usize,
+    pub subscriptions: usize,
+    pub patterns: usize,
+    // the commands queued since MULTI, none outside of a transaction
+    pub multi: Option<usize>,
+    // waiting on a blocking command, which has a timeout of its own
+    pub blocked: bool,
+    // the name of the last command, lowercase
+    pub cmd: String,
+    pub user: Vec<u8>,
+}
+
+impl Info {
+    // a line of CLIENT LIST, without the newline ending it
+    pub fn line(&self) -> String {
+        let now = Instant::now();
+        format!(
+            "id={} addr={} laddr={} name={} age={} idle={} db={} sub={} psub={} multi={} cmd={} user={} resp={}",
+            self.id,
+            self.addr,
+            self.laddr,
+            String::from_utf8_lossy(self.name.as_deref().unwrap_or_default()),
+            now.duration_since(self.connected).as_secs(),
+            now.duration_since(self.last).as_secs(),
+            self.db,
+            self.subscriptions,
+            self.patterns,
+            self.multi.map_or(-1, |queued| queued as i64),
+            self.cmd,
+            String::from_utf8_lossy(&self.user),
+            match self.proto {
+                ProcVersion::V2 => 2,
+                ProcVersion::V3 => 3,
+            },
+        )
+    }
+}
+
+// the entry of a connection, the connection itself holding it too
+#[derive(Debug)]
+pub(crate) struct Registration {
+    info: Mutex<Info>,
+    killed: Notify,
+    // where what is sent to the connection outside of its replies goes
+    mailbox: Mailbox,
+}
+
+impl Registration {
+    // a connection that panicked must not take CLIENT LIST or INFO down with
+    // it, so poisoning is ignored as for the keyspace
+    fn locked(&self) -> MutexGuard<'_, Info> {
+        self.info.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    pub fn info(&self) -> Info {
+        self.locked().clone()
+    }
+    pub fn update(&self, update: impl FnOnce(&mut Info)) {
+        update(&mut self.locked());
+    }
+    // false when the connection fell too far behind, see `Mailbox::post`
+    pub fn post(&self, message: RedirsValue) -> bool {
+        self.mailbox.post(message)
+    }
+    // resolves once the connection is killed, the kill remembered until then
+    pub async fn killed(&self) {
+        self.killed.notified().await
+    }
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Clients {
+    live: Mutex<BTreeMap<i64, Arc<Registration>>>,
+    // the seconds a connection can go without a command, 0 for no limit
+    timeout: AtomicU64,
+}
+
+impl Clients {
+    fn live(&self) -> MutexGuard<'_, BTreeMap<i64, Arc<Registration>>> {
+        self.live.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    pub fn register(&self, info: Info, mailbox: Mailbox) -> Arc<Registration> {
+        let id = info.id;
+        let registration = Arc::new(Registration {
+            info: Mutex::new(info),
+            killed: Notify::new(),
+            mailbox,
+        });
+        self.live().insert(id, registration.clone());
+        registration
+    }
+    pub fn deregister(&self, id: i64) {
+        self.live().remove(&id);
+    }
+    pub fn get(&self, id: i64) -> Option<Arc<Registration>> {
+        self.live().get(&id).cloned()
+    }
+    // every connection, in id order
+    pub fn list(&self) -> Vec<Info> {
+        let live = self.live();
+        live.values()
+            .map(|registration| registration.locked().clone())
+            .collect()
+    }
+    // signals the connections `filter` picks to close, returning their ids.
+    // They go as soon as they notice, even when waiting on a read
+    pub fn kill(&self, filter: impl Fn(&Info) -> bool) -> Vec<i64> {
+        let live = self.live();
+        live.values()
+            .filter(|registration| filter(&registration.locked()))
+            .map(|registration| {
+                registration.killed.notify_one();
+                registration.locked().id
+            })
+            .collect()
+    }
+    pub fn timeout(&self) -> u64 {
+        self.timeout.load(Ordering::Relaxed)
+    }
+    pub fn set_timeout(&self, seconds: u64) {
+        self.timeout.store(seconds, Ordering::Relaxed);
+    }
+}
+
+// closes the connections that went without a command for longer than the
+// timeout, as redis does those neither subscribed nor blocked
+pub async fn reap_idle(db: Db) {
+    let mut ticks = tokio::time::interval(REAP_EVERY);
+    loop {
+        ticks.tick().await;
+        let timeout = db.clients().timeout();
+        if timeout == 0 {
+            continue;
+        }
+        let timeout = Duration::from_secs(timeout);
+        db.clients().kill(|info| {
+            info.subscriptions + info.patterns == 0
+                && !info.blocked
+                && info.last.elapsed() > timeout
+        });
+    }
+}
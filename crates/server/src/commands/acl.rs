@@ -0,0 +1,185 @@
+use protocol::{CommandError, RedirsValue};
+
+use super::{args, ok, spec, Context, Error, Reply};
+use crate::{
+    acl::{self, Denied, DEFAULT_USER},
+    connection::Client,
+    Db,
+};
+
+// the commands a connection can run before it authenticated, HELLO only with
+// its AUTH option
+const UNAUTHENTICATED: &[&str] = &["auth", "quit", "reset"];
+
+// the error for any other command until the connection authenticated
+pub(super) fn unauthenticated(request: &RedirsValue, client: &Client) -> Option<RedirsValue> {
+    if client.authenticated {
+        return None;
+    }
+    let args = args(request).unwrap_or_default();
+    let allowed = match args.split_first() {
+        Some((name, _))
+            if UNAUTHENTICATED
+                .iter()
+                .any(|a| name.eq_ignore_ascii_case(a.as_bytes())) =>
+        {
+            true
+        }
+        Some((name, opts)) if name.eq_ignore_ascii_case(b"hello") => opts
+            .iter()
+            .skip(1)
+            .any(|opt| opt.eq_ignore_ascii_case(b"auth")),
+        _ => false,
+    };
+    (!allowed).then(|| RedirsValue::SimpleError("NOAUTH Authentication required.".to_owned()))
+}
+
+// whether the user of the connection can run the request, on every key it
+// names. The commands needing no auth are always let through, as are unknown
+// ones for their own error to be the reply
+pub(super) fn permitted(request: &RedirsValue, client: &Client, db: &Db) -> Result<(), Error> {
+    let Some(args) = args(request) else {
+        return Ok(());
+    };
+    let Some(command) = args.first().and_then(|name| spec(name)) else {
+        return Ok(());
+    };
+    if command.flags.contains(&"no_auth") {
+        return Ok(());
+    }
+    let user = String::from_utf8_lossy(&client.user);
+    match db.acl().permitted(&client.user, command, &args) {
+        Ok(()) => Ok(()),
+        Err(Denied::NoUser) => Err(Error::Message("NOAUTH Authentication required.".to_owned())),
+        Err(Denied::Command) => Err(Error::Message(format!(
+            "NOPERM User {user} has no permissions to run the '{}' command",
+            command.name
+        ))),
+        Err(Denied::Key(key)) => Err(Error::Message(format!(
+            "NOPERM User {user} has no permissions to access the '{}' key",
+            String::from_utf8_lossy(&key)
+        ))),
+    }
+}
+
+// authenticates the connection as `user`, unless the password is not theirs
+// or they are disabled
+pub(super) fn login(
+    client: &mut Client,
+    db: &Db,
+    user: &[u8],
+    password: &[u8],
+) -> Result<(), Error> {
+    if !db.acl().check(user, password) {
+        return Err(Error::Message(
+            "WRONGPASS invalid username-password pair".to_owned(),
+        ));
+    }
+    client.authenticated = true;
+    client.user = user.to_vec();
+    Ok(())
+}
+
+// AUTH [username] password, the username being the default user's when left
+// out
+pub(crate) fn auth(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (user, password) = match args {
+        [_] if context.db.acl().open() => {
+            return Err(Error::Message(
+                "ERR Client sent AUTH, but no password is set".to_owned(),
+            ))
+        }
+        [password] => (DEFAULT_USER, *password),
+        [user, password] => (*user, *password),
+        _ => return Err(CommandError::SyntaxError.into()),
+    };
+    login(context.client, context.db, user, password)?;
+    Ok(ok())
+}
+
+fn strings<T: AsRef<str>>(items: impl IntoIterator<Item = T>) -> RedirsValue {
+    RedirsValue::Array(Some(
+        items
+            .into_iter()
+            .map(|item| RedirsValue::from(item.as_ref()))
+            .collect(),
+    ))
+}
+
+// ACL SETUSER, GETUSER, DELUSER, LIST, USERS, WHOAMI and CAT
+pub(crate) fn acl(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let acl = context.db.acl();
+    let subcommand = args[0].to_ascii_lowercase();
+    match (subcommand.as_slice(), &args[1..]) {
+        (b"setuser", [name, rules @ ..]) => {
+            acl.set_user(name, rules).map_err(|(rule, reason)| {
+                Error::Message(format!(
+                    "ERR Error in ACL SETUSER modifier '{}': {reason}",
+                    String::from_utf8_lossy(rule)
+                ))
+            })?;
+            Ok(ok())
+        }
+        (b"getuser", [name]) => {
+            let Some(user) = acl.get(name) else {
+                return Ok(RedirsValue::Null);
+            };
+            let fields = [
+                ("flags", strings(user.flags())),
+                ("passwords", strings(user.passwords())),
+                ("commands", RedirsValue::from(user.commands())),
+                ("keys", RedirsValue::from(user.keys())),
+            ];
+            Ok(RedirsValue::Map(
+                fields
+                    .into_iter()
+                    .map(|(field, value)| (RedirsValue::from(field), value))
+                    .collect(),
+            ))
+        }
+        (b"deluser", names @ [_, ..]) => {
+            if names.contains(&DEFAULT_USER) {
+                return Err(Error::Message(
+                    "ERR The 'default' user cannot be removed".to_owned(),
+                ));
+            }
+            Ok(RedirsValue::Integer(acl.delete(names) as i64))
+        }
+        (b"list", []) => Ok(strings(acl.list().into_iter().map(|(name, user)| {
+            format!(
+                "user {} {}",
+                String::from_utf8_lossy(&name),
+                user.describe()
+            )
+        }))),
+        (b"users", []) => Ok(RedirsValue::Array(Some(
+            acl.list()
+                .into_iter()
+                .map(|(name, _)| RedirsValue::from(name))
+                .collect(),
+        ))),
+        (b"whoami", []) => Ok(RedirsValue::from(context.client.user.clone())),
+        (b"cat", []) => Ok(strings(acl::CATEGORIES)),
+        (b"cat", [category]) => {
+            let category = String::from_utf8_lossy(category).to_ascii_lowercase();
+            if !acl::CATEGORIES.contains(&category.as_str()) {
+                return Err(Error::Message(format!("ERR Unknown category '{category}'")));
+            }
+            Ok(strings(
+                super::table()
+                    .filter(|command| acl::in_category(command, &category))
+                    .map(|command| command.name),
+            ))
+        }
+        (b"setuser" | b"getuser" | b"deluser" | b"list" | b"users" | b"whoami" | b"cat", _) => {
+            Err(Error::Message(format!(
+                "ERR wrong number of arguments for 'acl|{}' command",
+                String::from_utf8_lossy(&subcommand)
+            )))
+        }
+        _ => Err(Error::Message(format!(
+            "ERR unknown subcommand '{}'. Try ACL HELP.",
+            String::from_utf8_lossy(args[0])
+        ))),
+    }
+}
@@ -0,0 +1,254 @@
+use protocol::{CommandError, RedirsValue};
+
+use super::{parse_int, strings::MAX_STRING_LEN, Context, Error, Reply};
+use crate::db::Value;
+
+// bits are numbered from the most significant one of the first byte, so bit 0
+// is the 0x80 of byte 0
+fn bit_of(value: &[u8], offset: u64) -> u8 {
+    let byte = value.get((offset / 8) as usize).copied().unwrap_or(0);
+    (byte >> (7 - offset % 8)) & 1
+}
+
+// an offset into the longest string a key can hold
+fn parse_offset(arg: &[u8]) -> Result<u64, Error> {
+    match parse_int(arg) {
+        Ok(offset) if (0..MAX_STRING_LEN as i64 * 8).contains(&offset) => Ok(offset as u64),
+        _ => Err(Error::Message(
+            "ERR bit offset is not an integer or out of range".to_owned(),
+        )),
+    }
+}
+
+// SETBIT key offset value: the bit it replaced, growing the string with zero
+// bytes up to the one holding it
+pub(crate) fn setbit(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, offset) = (args[0], parse_offset(args[1])?);
+    let on = match args[2] {
+        b"0" => false,
+        b"1" => true,
+        _ => {
+            return Err(Error::Message(
+                "ERR bit is not an integer or out of range".to_owned(),
+            ))
+        }
+    };
+    let mut keyspace = context.db.lock();
+    let value = keyspace.get_or_create_string(key)?;
+    let byte = (offset / 8) as usize;
+    if value.len() <= byte {
+        value.resize(byte + 1, 0);
+    }
+    let old = bit_of(value, offset);
+    let mask = 0x80 >> (offset % 8);
+    match on {
+        true => value[byte] |= mask,
+        false => value[byte] &= !mask,
+    }
+    Ok(RedirsValue::Integer(old as i64))
+}
+
+// GETBIT key offset: 0 past the end of the string or for a missing key
+pub(crate) fn getbit(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let offset = parse_offset(args[1])?;
+    let mut keyspace = context.db.lock();
+    let value = keyspace.get_string(args[0])?.map_or(&[][..], Vec::as_slice);
+    Ok(RedirsValue::Integer(bit_of(value, offset) as i64))
+}
+
+// what the start and end of BITCOUNT and BITPOS count in
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Unit {
+    Byte,
+    Bit,
+}
+
+// the start of a range, its end when given and what they count in
+type Span = (i64, Option<i64>, Unit);
+
+// [start [end [BYTE|BIT]]], an end required when `end_required`
+fn parse_span(args: &[&[u8]], end_required: bool) -> Result<Option<Span>, Error> {
+    let unit = match args.get(2) {
+        None => Unit::Byte,
+        Some(unit) if unit.eq_ignore_ascii_case(b"byte") => Unit::Byte,
+        Some(unit) if unit.eq_ignore_ascii_case(b"bit") => Unit::Bit,
+        Some(_) => return Err(CommandError::SyntaxError.into()),
+    };
+    match args {
+        [] => Ok(None),
+        [_] if end_required => Err(CommandError::SyntaxError.into()),
+        [start] => Ok(Some((parse_int(start)?, None, unit))),
+        [start, end] | [start, end, _] => {
+            Ok(Some((parse_int(start)?, Some(parse_int(end)?), unit)))
+        }
+        _ => Err(CommandError::SyntaxError.into()),
+    }
+}
+
+// the first and last bit a span covers of a string of `len` bytes, negative
+// positions counting from the end, none when it covers nothing
+fn bits(span: Option<Span>, len: usize) -> Option<(u64, u64)> {
+    let (start, end, unit) = span.unwrap_or((0, None, Unit::Byte));
+    let total = match unit {
+        Unit::Byte => len as i64,
+        Unit::Bit => len as i64 * 8,
+    };
+    let end = end.unwrap_or(-1);
+    if start < 0 && end < 0 && start > end {
+        return None;
+    }
+    let start = match start < 0 {
+        true => (start + total).max(0),
+        false => start,
+    };
+    let end = match end < 0 {
+        true => (end + total).max(0),
+        false => end.min(total - 1),
+    };
+    if start > end || total == 0 {
+        return None;
+    }
+    Some(match unit {
+        Unit::Byte => (start as u64 * 8, end as u64 * 8 + 7),
+        Unit::Bit => (start as u64, end as u64),
+    })
+}
+
+// the set bits of `bytes`, eight bytes at a time
+fn popcount(bytes: &[u8]) -> u64 {
+    let mut words = bytes.chunks_exact(8);
+    let whole: u64 = words
+        .by_ref()
+        .map(|word| u64::from_ne_bytes(word.try_into().expect("eight bytes")).count_ones() as u64)
+        .sum();
+    whole
+        + words
+            .remainder()
+            .iter()
+            .map(|byte| byte.count_ones() as u64)
+            .sum::<u64>()
+}
+
+// the bits of `byte` a range from bit `first` to bit `last` covers of it, the
+// byte being at bit `at`
+fn mask(at: u64, first: u64, last: u64) -> u8 {
+    let skip = first.saturating_sub(at).min(8);
+    let keep = (last + 1).saturating_sub(at).min(8);
+    ((0xffu16 >> skip) & !(0xffu16 >> keep)) as u8
+}
+
+// BITCOUNT key [start end [BYTE|BIT]]: the set bits of the string, or of the
+// bytes or bits from start to end both included
+pub(crate) fn bitcount(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let span = parse_span(&args[1..], true)?;
+    let mut keyspace = context.db.lock();
+    let value = keyspace.get_string(args[0])?.map_or(&[][..], Vec::as_slice);
+    let Some((first, last)) = bits(span, value.len()) else {
+        return Ok(RedirsValue::Integer(0));
+    };
+    let (first_byte, last_byte) = ((first / 8) as usize, (last / 8) as usize);
+    // the ends may be partly covered, the bytes between them are whole
+    let end = |byte: usize| (value[byte] & mask(byte as u64 * 8, first, last)).count_ones() as u64;
+    let count = match first_byte == last_byte {
+        true => end(first_byte),
+        false => end(first_byte) + popcount(&value[first_byte + 1..last_byte]) + end(last_byte),
+    };
+    Ok(RedirsValue::Integer(count as i64))
+}
+
+// BITPOS key bit [start [end [BYTE|BIT]]]: the first bit set to bit in the
+// range, -1 when there is none. Looking for a 0 without an end given treats
+// the string as followed by zero bits, so a string of ones answers with the
+// bit right after it
+pub(crate) fn bitpos(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let wanted = match args[1] {
+        b"0" => 0,
+        b"1" => 1,
+        _ => {
+            return Err(Error::Message(
+                "ERR The bit argument must be 1 or 0.".to_owned(),
+            ))
+        }
+    };
+    let span = parse_span(&args[2..], false)?;
+    let end_given = span.is_some_and(|(_, end, _)| end.is_some());
+    let mut keyspace = context.db.lock();
+    let Some(value) = keyspace.get_string(args[0])? else {
+        return Ok(RedirsValue::Integer(match wanted {
+            1 => -1,
+            _ => 0,
+        }));
+    };
+    let Some((first, last)) = bits(span, value.len()) else {
+        return Ok(RedirsValue::Integer(-1));
+    };
+    let found = (first / 8..=last / 8).find_map(|byte| {
+        let at = byte * 8;
+        let bits = match wanted {
+            1 => value[byte as usize],
+            _ => !value[byte as usize],
+        } & mask(at, first, last);
+        (bits != 0).then(|| at + bits.leading_zeros() as u64)
+    });
+    Ok(RedirsValue::Integer(match found {
+        Some(position) => position as i64,
+        None if wanted == 0 && !end_given => last as i64 + 1,
+        None => -1,
+    }))
+}
+
+#[derive(Debug, Clone, Copy)]
+enum BitOp {
+    And,
+    Or,
+    Xor,
+    Not,
+}
+
+// BITOP AND|OR|XOR|NOT destkey key [key ...]: stores the sources combined
+// byte by byte, the shorter ones padded with zero bytes, and replies with the
+// length stored. An empty result removes the destination
+pub(crate) fn bitop(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let op = match args[0].to_ascii_lowercase().as_slice() {
+        b"and" => BitOp::And,
+        b"or" => BitOp::Or,
+        b"xor" => BitOp::Xor,
+        b"not" => BitOp::Not,
+        _ => return Err(CommandError::SyntaxError.into()),
+    };
+    let (dest, keys) = (args[1], &args[2..]);
+    if matches!(op, BitOp::Not) && keys.len() != 1 {
+        return Err(Error::Message(
+            "ERR BITOP NOT must be called with a single source key.".to_owned(),
+        ));
+    }
+    let mut keyspace = context.db.lock();
+    let sources: Vec<_> = keyspace
+        .get_strings(keys)?
+        .into_iter()
+        .map(Option::unwrap_or_default)
+        .collect();
+    let len = sources.iter().map(|source| source.len()).max().unwrap_or(0);
+    let byte = |source: &[u8], at: usize| source.get(at).copied().unwrap_or(0);
+    let result: Vec<u8> = (0..len)
+        .map(|at| {
+            let mut bytes = sources.iter().map(|source| byte(source, at));
+            let first = bytes.next().expect("at least one source");
+            match op {
+                BitOp::And => bytes.fold(first, |acc, b| acc & b),
+                BitOp::Or => bytes.fold(first, |acc, b| acc | b),
+                BitOp::Xor => bytes.fold(first, |acc, b| acc ^ b),
+                BitOp::Not => !first,
+            }
+        })
+        .collect();
+    match result.is_empty() {
+        true => {
+            keyspace.remove(dest);
+        }
+        false => {
+            keyspace.set(dest.to_vec(), Value::String(result));
+        }
+    }
+    Ok(RedirsValue::Integer(len as i64))
+}
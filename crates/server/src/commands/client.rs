@@ -0,0 +1,203 @@
+use protocol::{CommandError, RedirsValue, VerbatimEncoding};
+
+use super::{ok, parse_int, Context, Error, Reply};
+use crate::{clients::Info, tracking::Tracking};
+
+// a name CLIENT SETNAME and HELLO SETNAME take, no spaces, newlines or other
+// characters outside of printable ASCII
+pub(super) fn check_name(name: &[u8]) -> Result<(), Error> {
+    match name.iter().all(u8::is_ascii_graphic) {
+        true => Ok(()),
+        false => Err(Error::Message(
+            "ERR Client names cannot contain spaces, newlines or special characters.".to_owned(),
+        )),
+    }
+}
+
+// the connections CLIENT KILL picks with its filters, all of them applying
+#[derive(Default)]
+struct Filter<'a> {
+    id: Option<i64>,
+    addr: Option<&'a [u8]>,
+    laddr: Option<&'a [u8]>,
+    user: Option<&'a [u8]>,
+    // the calling connection is left alone unless SKIPME no
+    skip: Option<i64>,
+}
+
+impl Filter<'_> {
+    fn matches(&self, info: &Info) -> bool {
+        self.id.is_none_or(|id| info.id == id)
+            && self
+                .addr
+                .is_none_or(|addr| info.addr.to_string().as_bytes() == addr)
+            && self
+                .laddr
+                .is_none_or(|laddr| info.laddr.to_string().as_bytes() == laddr)
+            && self.user.is_none_or(|user| info.user == user)
+            && self.skip != Some(info.id)
+    }
+}
+
+fn filter<'a>(context: &Context<'_>, args: &[&'a [u8]]) -> Result<Filter<'a>, Error> {
+    let mut filter = Filter {
+        skip: Some(context.client.id),
+        ..Filter::default()
+    };
+    for pair in args.chunks(2) {
+        let [option, value] = pair else {
+            return Err(CommandError::SyntaxError.into());
+        };
+        match option.to_ascii_lowercase().as_slice() {
+            b"id" => match parse_int(value) {
+                Ok(id) if id > 0 => filter.id = Some(id),
+                _ => {
+                    return Err(Error::Message(
+                        "ERR client-id should be greater than 0".to_owned(),
+                    ))
+                }
+            },
+            b"addr" => filter.addr = Some(value),
+            b"laddr" => filter.laddr = Some(value),
+            b"user" => filter.user = Some(value),
+            b"skipme" => match value.to_ascii_lowercase().as_slice() {
+                b"yes" => filter.skip = Some(context.client.id),
+                b"no" => filter.skip = None,
+                _ => return Err(CommandError::SyntaxError.into()),
+            },
+            _ => return Err(CommandError::SyntaxError.into()),
+        }
+    }
+    Ok(filter)
+}
+
+// CLIENT TRACKING ON|OFF [REDIRECT id] [BCAST] [PREFIX prefix ...], see
+// `tracking`. Turning it on again changes where it is told and the prefixes,
+// not the mode
+fn tracking(context: &mut Context<'_>, switch: &[u8], options: &[&[u8]]) -> Reply {
+    let mut redirect = None;
+    let mut bcast = false;
+    let mut prefixes = Vec::new();
+    let mut options = options.iter();
+    while let Some(option) = options.next() {
+        match option.to_ascii_lowercase().as_slice() {
+            b"redirect" => {
+                let id = parse_int(options.next().ok_or(CommandError::SyntaxError)?)?;
+                if context.db.clients().get(id).is_none() {
+                    return Err(Error::Message(
+                        "ERR The client ID you want redirect to does not exist".to_owned(),
+                    ));
+                }
+                redirect = Some(id);
+            }
+            b"bcast" => bcast = true,
+            b"prefix" => prefixes.push(options.next().ok_or(CommandError::SyntaxError)?.to_vec()),
+            _ => return Err(CommandError::SyntaxError.into()),
+        }
+    }
+    let client = &mut *context.client;
+    match switch.to_ascii_lowercase().as_slice() {
+        b"on" => {
+            if !bcast && !prefixes.is_empty() {
+                return Err(Error::Message(
+                    "ERR PREFIX option requires BCAST mode to be enabled".to_owned(),
+                ));
+            }
+            if client
+                .tracking
+                .as_ref()
+                .is_some_and(|tracking| tracking.prefixes.is_some() != bcast)
+            {
+                return Err(Error::Message(
+                    "ERR You can't switch BCAST mode on/off before disabling tracking for this \
+                     client, and then re-enabling it with a different mode."
+                        .to_owned(),
+                ));
+            }
+            let tracking = Tracking {
+                redirect,
+                prefixes: bcast.then_some(prefixes),
+            };
+            context.db.tracker().start(client.id, tracking.clone());
+            client.tracking = Some(tracking);
+        }
+        b"off" => {
+            if client.tracking.take().is_some() {
+                context.db.tracker().stop(client.id);
+            }
+        }
+        _ => return Err(CommandError::SyntaxError.into()),
+    }
+    Ok(ok())
+}
+
+// CLIENT ID, GETNAME, SETNAME, LIST, KILL and TRACKING
+pub(crate) fn client(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let subcommand = args[0].to_ascii_lowercase();
+    match (subcommand.as_slice(), &args[1..]) {
+        (b"id", []) => Ok(RedirsValue::Integer(context.client.id)),
+        (b"getname", []) => Ok(context
+            .client
+            .name
+            .clone()
+            .map_or(RedirsValue::Null, RedirsValue::from)),
+        // an empty name takes the name away
+        (b"setname", [name]) => {
+            check_name(name)?;
+            context.client.name = (!name.is_empty()).then(|| name.to_vec());
+            Ok(ok())
+        }
+        (b"list", []) => {
+            let lines: String = context
+                .db
+                .clients()
+                .list()
+                .iter()
+                .map(|info| info.line() + "\n")
+                .collect();
+            Ok(RedirsValue::VerbatimString(
+                VerbatimEncoding::Txt,
+                lines.into_bytes(),
+            ))
+        }
+        // the old form, a single address, can kill the caller too
+        (b"kill", [addr]) => {
+            let filter = Filter {
+                addr: Some(addr),
+                ..Filter::default()
+            };
+            match kill(context, filter) {
+                0 => Err(Error::Message("ERR No such client".to_owned())),
+                _ => Ok(ok()),
+            }
+        }
+        (b"kill", filters @ [_, ..]) => {
+            let filter = filter(context, filters)?;
+            Ok(RedirsValue::Integer(kill(context, filter) as i64))
+        }
+        (b"tracking", [switch, options @ ..]) => tracking(context, switch, options),
+        (b"id" | b"getname" | b"setname" | b"list" | b"kill" | b"tracking", _) => {
+            Err(Error::Message(format!(
+                "ERR wrong number of arguments for 'client|{}' command",
+                String::from_utf8_lossy(&subcommand)
+            )))
+        }
+        _ => Err(Error::Message(format!(
+            "ERR unknown subcommand '{}'. Try CLIENT HELP.",
+            String::from_utf8_lossy(args[0])
+        ))),
+    }
+}
+
+// the other connections close right away, the calling one once its reply is
+// out
+fn kill(context: &mut Context<'_>, filter: Filter<'_>) -> usize {
+    let id = context.client.id;
+    let killed = context
+        .db
+        .clients()
+        .kill(|info| info.id != id && filter.matches(info));
+    let me = filter.matches(&context.client.registration.info());
+    context.client.quit |= me;
+    killed.len() + me as usize
+}
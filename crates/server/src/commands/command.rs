@@ -0,0 +1,84 @@
+use protocol::RedirsValue;
+
+use super::{check_arity, spec, table, Command, Context, Error, Keys, Reply};
+use crate::acl;
+
+// the table entry of a command the way COMMAND INFO describes it: name,
+// arity, flags, first key, last key, step, ACL categories, then the tips, key
+// specs and subcommands this server has none of. The keys a count or a
+// keyword finds are movablekeys with no range
+fn describe(command: &Command) -> RedirsValue {
+    let (first, last, step, movable) = match command.keys {
+        Keys::None => (0, 0, 0, false),
+        Keys::Range(first, last, step) => (first as i64, last as i64, step as i64, false),
+        Keys::Counted(_) | Keys::Streams => (0, 0, 0, true),
+    };
+    let flags = command
+        .flags
+        .iter()
+        .copied()
+        .chain(movable.then_some("movablekeys"))
+        .map(|flag| RedirsValue::SimpleString(flag.to_owned()))
+        .collect();
+    let categories = acl::CATEGORIES
+        .iter()
+        .filter(|category| acl::in_category(command, category))
+        .map(|category| RedirsValue::SimpleString(format!("@{category}")))
+        .collect();
+    let none = || RedirsValue::Array(Some(Vec::new()));
+    RedirsValue::Array(Some(vec![
+        RedirsValue::from(command.name),
+        RedirsValue::Integer(command.arity as i64),
+        RedirsValue::Array(Some(flags)),
+        RedirsValue::Integer(first),
+        RedirsValue::Integer(last),
+        RedirsValue::Integer(step),
+        RedirsValue::Array(Some(categories)),
+        none(),
+        none(),
+        none(),
+    ]))
+}
+
+// COMMAND [COUNT | INFO [name ...] | GETKEYS command [arg ...]], all of them
+// read from the command table
+pub(crate) fn command(_: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let Some((subcommand, args)) = args.split_first() else {
+        return Ok(RedirsValue::Array(Some(table().map(describe).collect())));
+    };
+    let subcommand = subcommand.to_ascii_lowercase();
+    match (subcommand.as_slice(), args) {
+        (b"count", []) => Ok(RedirsValue::Integer(table().count() as i64)),
+        (b"info", []) => Ok(RedirsValue::Array(Some(table().map(describe).collect()))),
+        (b"info", names) => Ok(RedirsValue::Array(Some(
+            names
+                .iter()
+                .map(|name| spec(name).map_or(RedirsValue::Null, describe))
+                .collect(),
+        ))),
+        (b"getkeys", invocation @ [name, ..]) => {
+            let command = spec(name)
+                .ok_or_else(|| Error::Message("ERR Invalid command specified".to_owned()))?;
+            check_arity(command, invocation).map_err(|_| {
+                Error::Message("ERR Invalid number of arguments specified for command".to_owned())
+            })?;
+            let keys = command.keys.of(invocation);
+            if keys.is_empty() {
+                return Err(Error::Message(
+                    "ERR The command has no key arguments".to_owned(),
+                ));
+            }
+            Ok(RedirsValue::Array(Some(
+                keys.into_iter().map(RedirsValue::from).collect(),
+            )))
+        }
+        (b"count" | b"getkeys", _) => Err(Error::Message(format!(
+            "ERR wrong number of arguments for 'command|{}' command",
+            String::from_utf8_lossy(&subcommand)
+        ))),
+        _ => Err(Error::Message(format!(
+            "ERR unknown subcommand '{}'. Try COMMAND HELP.",
+            String::from_utf8_lossy(&subcommand)
+        ))),
+    }
+}
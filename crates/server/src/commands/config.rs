@@ -0,0 +1,62 @@
+use protocol::RedirsValue;
+
+use super::{ok, Context, Error, Reply};
+use crate::{config, glob};
+
+// CONFIG GET pattern [pattern ...] and CONFIG SET parameter value [parameter
+// value ...]. GET shows every parameter a pattern matches once, SET checks
+// every value before applying any of them, so either all change or none
+pub(crate) fn config(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let subcommand = args[0].to_ascii_lowercase();
+    match (subcommand.as_slice(), &args[1..]) {
+        (b"get", patterns @ [_, ..]) => Ok(RedirsValue::Map(
+            config::PARAMETERS
+                .iter()
+                .filter(|parameter| {
+                    patterns
+                        .iter()
+                        .any(|pattern| glob::matches_nocase(pattern, parameter.name.as_bytes()))
+                })
+                .map(|parameter| {
+                    (
+                        RedirsValue::from(parameter.name),
+                        RedirsValue::from((parameter.get)(context.db).show()),
+                    )
+                })
+                .collect(),
+        )),
+        (b"set", pairs) if !pairs.is_empty() && pairs.len() % 2 == 0 => {
+            let mut changes: Vec<(&config::Parameter, config::Setting)> = Vec::new();
+            for pair in pairs.chunks_exact(2) {
+                let name = String::from_utf8_lossy(pair[0]);
+                let Some(parameter) = config::parameter(pair[0]) else {
+                    return Err(Error::Message(format!(
+                        "ERR Unknown option or number of arguments for CONFIG SET - '{name}'"
+                    )));
+                };
+                let failed = |reason: &str| {
+                    Error::Message(format!(
+                        "ERR CONFIG SET failed (possibly related to argument '{name}') - {reason}"
+                    ))
+                };
+                if changes.iter().any(|(p, _)| p.name == parameter.name) {
+                    return Err(failed("duplicate parameter"));
+                }
+                let setting = parameter.parse(pair[1]).map_err(|reason| failed(&reason))?;
+                changes.push((parameter, setting));
+            }
+            for (parameter, setting) in changes {
+                parameter.apply(context.db, &setting);
+            }
+            Ok(ok())
+        }
+        (b"get" | b"set", _) => Err(Error::Message(format!(
+            "ERR wrong number of arguments for 'config|{}' command",
+            String::from_utf8_lossy(&subcommand)
+        ))),
+        _ => Err(Error::Message(format!(
+            "ERR unknown subcommand '{}'. Try CONFIG HELP.",
+            String::from_utf8_lossy(args[0])
+        ))),
+    }
+}
@@ -0,0 +1,100 @@
+use protocol::{CommandError, RedirsValue};
+
+use super::{ok, parse_int, Context, Error, Reply};
+use crate::{db::Flushed, Db};
+
+fn out_of_range() -> Error {
+    Error::Message("ERR DB index is out of range".to_owned())
+}
+
+// the index of one of the databases, `invalid` when it is not a number
+pub(super) fn db_index(db: &Db, arg: &[u8], invalid: Error) -> Result<usize, Error> {
+    let index = parse_int(arg).map_err(|_| invalid)?;
+    match usize::try_from(index) {
+        Ok(index) if index < db.databases() => Ok(index),
+        _ => Err(out_of_range()),
+    }
+}
+
+// SELECT index: the database the commands of the connection run against
+pub(crate) fn select(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    context.client.db = db_index(context.db, args[0], CommandError::NotAnInteger.into())?;
+    Ok(ok())
+}
+
+// SWAPDB index1 index2: what the connections of one see is what the other
+// held, at once for all of them
+pub(crate) fn swapdb(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let invalid = |which| Error::Message(format!("ERR invalid {which} DB index"));
+    let first = db_index(context.db, args[0], invalid("first"))?;
+    let second = db_index(context.db, args[1], invalid("second"))?;
+    if let Some((mut first, mut second)) = context.db.select(first).lock_with(second) {
+        first.swap_keys(&mut second);
+    }
+    Ok(ok())
+}
+
+// MOVE key db: 1 once the key, its deadline along, is in the other database,
+// 0 when it is missing here or already there
+pub(crate) fn move_(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let key = args[0];
+    let target = db_index(context.db, args[1], CommandError::NotAnInteger.into())?;
+    let Some((mut here, mut there)) = context.db.lock_with(target) else {
+        return Err(Error::Message(
+            "ERR source and destination objects are the same".to_owned(),
+        ));
+    };
+    if !here.exists(key) || there.exists(key) {
+        return Ok(RedirsValue::Integer(0));
+    }
+    let (value, deadline) = here.take(key, "move_from").expect("the key exists");
+    there.place(key, value, deadline, "move_to");
+    // move_from is notified first
+    drop(here);
+    Ok(RedirsValue::Integer(1))
+}
+
+// whether FLUSHDB and FLUSHALL free what they flushed in the background,
+// with ASYNC, or before replying, with SYNC or neither
+fn asynchronous(args: &[&[u8]]) -> Result<bool, Error> {
+    match args {
+        [] => Ok(false),
+        [mode] if mode.eq_ignore_ascii_case(b"sync") => Ok(false),
+        [mode] if mode.eq_ignore_ascii_case(b"async") => Ok(true),
+        _ => Err(CommandError::SyntaxError.into()),
+    }
+}
+
+// on a blocking task, for the keys of a big database to be dropped while
+// other commands run
+fn free(flushed: Vec<Flushed>, asynchronous: bool) {
+    match asynchronous {
+        true => drop(tokio::task::spawn_blocking(move || drop(flushed))),
+        false => drop(flushed),
+    }
+}
+
+// FLUSHDB [ASYNC | SYNC]: every key of the selected database
+pub(crate) fn flushdb(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let asynchronous = asynchronous(args)?;
+    let flushed = context.db.lock().flush();
+    free(vec![flushed], asynchronous);
+    Ok(ok())
+}
+
+// FLUSHALL [ASYNC | SYNC]: every key of every database, one database after
+// the other
+pub(crate) fn flushall(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let asynchronous = asynchronous(args)?;
+    let flushed = (0..context.db.databases())
+        .map(|index| context.db.select(index).lock().flush())
+        .collect();
+    free(flushed, asynchronous);
+    Ok(ok())
+}
+
+// DBSIZE: the keys of the selected database, those expired but not yet
+// reclaimed along
+pub(crate) fn dbsize(context: &mut Context<'_>, _: &[&[u8]]) -> Reply {
+    Ok(RedirsValue::Integer(context.db.lock().len() as i64))
+}
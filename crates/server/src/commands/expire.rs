@@ -0,0 +1,120 @@
+use protocol::{CommandError, RedirsValue};
+
+use super::{parse_int, Context, Error, Reply};
+
+// what EXPIRE and friends measure their argument from
+#[derive(Clone, Copy)]
+enum Base {
+    Now,
+    UnixEpoch,
+}
+
+// KEY time [NX | XX | GT | LT], with time in `unit` milliseconds
+fn expire_generic(
+    context: &mut Context<'_>,
+    args: &[&[u8]],
+    name: &'static str,
+    base: Base,
+    unit: i64,
+) -> Reply {
+    let (key, time, opts) = (args[0], args[1], &args[2..]);
+    let time = parse_int(time)?;
+    let (mut nx, mut xx, mut gt, mut lt) = (false, false, false, false);
+    for opt in opts {
+        match opt.to_ascii_lowercase().as_slice() {
+            b"nx" => nx = true,
+            b"xx" => xx = true,
+            b"gt" => gt = true,
+            b"lt" => lt = true,
+            _ => {
+                return Err(Error::Message(format!(
+                    "ERR Unsupported option {}",
+                    String::from_utf8_lossy(opt)
+                )))
+            }
+        }
+    }
+    if nx && (xx || gt || lt) {
+        return Err(Error::Message(
+            "ERR NX and XX, GT or LT options at the same time are not compatible".to_owned(),
+        ));
+    }
+    if gt && lt {
+        return Err(Error::Message(
+            "ERR GT and LT options at the same time are not compatible".to_owned(),
+        ));
+    }
+    let mut keyspace = context.db.lock();
+    let base = match base {
+        Base::Now => keyspace.now() as i64,
+        Base::UnixEpoch => 0,
+    };
+    let invalid = CommandError::InvalidExpireTime(name);
+    let deadline = time
+        .checked_mul(unit)
+        .and_then(|millis| millis.checked_add(base))
+        .ok_or(invalid)?;
+    if !keyspace.exists(key) {
+        return Ok(RedirsValue::Integer(0));
+    }
+    // a key without a deadline counts as one that never expires
+    let current = keyspace.deadline(key);
+    let allowed = match current {
+        _ if nx => current.is_none(),
+        _ if xx && current.is_none() => false,
+        Some(current) if gt => deadline > current as i64,
+        None if gt => false,
+        Some(current) if lt => deadline < current as i64,
+        _ => true,
+    };
+    if !allowed {
+        return Ok(RedirsValue::Integer(0));
+    }
+    keyspace.expire_at(key, deadline.max(0) as u64);
+    Ok(RedirsValue::Integer(1))
+}
+
+pub(crate) fn expire(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    expire_generic(context, args, "expire", Base::Now, 1000)
+}
+
+pub(crate) fn pexpire(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    expire_generic(context, args, "pexpire", Base::Now, 1)
+}
+
+pub(crate) fn expireat(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    expire_generic(context, args, "expireat", Base::UnixEpoch, 1000)
+}
+
+pub(crate) fn pexpireat(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    expire_generic(context, args, "pexpireat", Base::UnixEpoch, 1)
+}
+
+pub(crate) fn persist(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    Ok(RedirsValue::Integer(
+        context.db.lock().persist(args[0]) as i64
+    ))
+}
+
+// -2 for a missing key, -1 for one without a deadline, the time left otherwise
+fn remaining(context: &mut Context<'_>, key: &[u8]) -> Result<i64, i64> {
+    let mut keyspace = context.db.lock();
+    if !keyspace.exists(key) {
+        return Err(-2);
+    }
+    match keyspace.deadline(key) {
+        Some(deadline) => Ok(deadline.saturating_sub(keyspace.now()) as i64),
+        None => Err(-1),
+    }
+}
+
+pub(crate) fn ttl(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    // rounded to the closest second
+    let ttl = remaining(context, args[0]).map_or_else(|code| code, |millis| (millis + 500) / 1000);
+    Ok(RedirsValue::Integer(ttl))
+}
+
+pub(crate) fn pttl(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let ttl = remaining(context, args[0]).unwrap_or_else(|code| code);
+    Ok(RedirsValue::Integer(ttl))
+}
@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use protocol::{CommandError, ProcVersion, RedirsMap, RedirsValue};
+use rand::seq::{index, IndexedRandom};
+
+use super::{
+    keys::{scan_options, scan_reply},
+    parse_float, parse_int,
+    strings::format_float,
+    Context, Error, Reply,
+};
+use crate::db::scan_members;
+
+// HSET key field value [field value ...], the number of fields that were not
+// there before
+pub(crate) fn hset(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, pairs) = (args[0], &args[1..]);
+    if !pairs.len().is_multiple_of(2) {
+        return Err(CommandError::WrongArity("hset").into());
+    }
+    let mut keyspace = context.db.lock();
+    let hash = keyspace.get_or_create_hash(key)?;
+    let created = pairs
+        .chunks(2)
+        .filter(|pair| hash.insert(pair[0].to_vec(), pair[1].to_vec()).is_none())
+        .count();
+    Ok(RedirsValue::Integer(created as i64))
+}
+
+pub(crate) fn hget(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let mut keyspace = context.db.lock();
+    let value = keyspace
+        .get_hash(args[0])?
+        .and_then(|hash| hash.get(args[1]))
+        .cloned();
+    Ok(RedirsValue::from(value))
+}
+
+// nil for every missing field, all of them for a missing key
+pub(crate) fn hmget(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let mut keyspace = context.db.lock();
+    let hash = keyspace.get_hash(args[0])?;
+    let values = args[1..]
+        .iter()
+        .map(|field| RedirsValue::from(hash.and_then(|hash| hash.get(*field)).cloned()))
+        .collect();
+    Ok(RedirsValue::Array(Some(values)))
+}
+
+// removing the last field removes the key
+pub(crate) fn hdel(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let key = args[0];
+    let mut keyspace = context.db.lock();
+    let Some(hash) = keyspace.get_hash_mut(key)? else {
+        return Ok(RedirsValue::Integer(0));
+    };
+    let removed = args[1..]
+        .iter()
+        .filter(|field| hash.remove(**field).is_some())
+        .count();
+    keyspace.remove_if_empty(key);
+    Ok(RedirsValue::Integer(removed as i64))
+}
+
+// a map, which RESP2 clients get as a flat array of fields and values
+pub(crate) fn hgetall(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let mut keyspace = context.db.lock();
+    let pairs = keyspace
+        .get_hash(args[0])?
+        .into_iter()
+        .flatten()
+        .map(|(field, value)| {
+            (
+                RedirsValue::from(field.clone()),
+                RedirsValue::from(value.clone()),
+            )
+        })
+        .collect::<RedirsMap>();
+    Ok(RedirsValue::Map(pairs))
+}
+
+pub(crate) fn hexists(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let mut keyspace = context.db.lock();
+    let found = keyspace
+        .get_hash(args[0])?
+        .is_some_and(|hash| hash.contains_key(args[1]));
+    Ok(RedirsValue::Integer(found as i64))
+}
+
+pub(crate) fn hlen(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let len = context.db.lock().get_hash(args[0])?.map_or(0, HashMap::len);
+    Ok(RedirsValue::Integer(len as i64))
+}
+
+// adds `by` to the integer a field holds, a missing field holding 0
+pub(crate) fn hincrby(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, field, by) = (args[0], args[1], parse_int(args[2])?);
+    let mut keyspace = context.db.lock();
+    let hash = keyspace.get_or_create_hash(key)?;
+    let old = match hash.get(field) {
+        Some(value) => parse_int(value)
+            .map_err(|_| Error::Message("ERR hash value is not an integer".to_owned()))?,
+        None => 0,
+    };
+    let new = old
+        .checked_add(by)
+        .ok_or_else(|| Error::Message("ERR increment or decrement would overflow".to_owned()))?;
+    hash.insert(field.to_vec(), new.to_string().into_bytes());
+    Ok(RedirsValue::Integer(new))
+}
+
+pub(crate) fn hincrbyfloat(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, field, by) = (args[0], args[1], parse_float(args[2])?);
+    let mut keyspace = context.db.lock();
+    let hash = keyspace.get_or_create_hash(key)?;
+    let old = match hash.get(field) {
+        Some(value) => parse_float(value)
+            .map_err(|_| Error::Message("ERR hash value is not a float".to_owned()))?,
+        None => 0.0,
+    };
+    let new = old + by;
+    if !new.is_finite() {
+        return Err(Error::Message(
+            "ERR increment would produce NaN or Infinity".to_owned(),
+        ));
+    }
+    let text = format_float(new);
+    hash.insert(field.to_vec(), text.clone());
+    Ok(RedirsValue::from(text))
+}
+
+// HRANDFIELD key [count [WITHVALUES]]: one random field or nil without a
+// count, up to count distinct fields with a positive one and exactly -count
+// fields, repeats allowed, with a negative one
+pub(crate) fn hrandfield(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (count, withvalues) = match args {
+        [_] => (None, false),
+        [_, count] => (Some(parse_int(count)?), false),
+        [_, count, opt] if opt.eq_ignore_ascii_case(b"withvalues") => {
+            (Some(parse_int(count)?), true)
+        }
+        _ => return Err(CommandError::SyntaxError.into()),
+    };
+    // -count pairs of replies must not overflow
+    if count.is_some_and(|count| count.unsigned_abs() > i64::MAX as u64 / 2) {
+        return Err(Error::Message("ERR value is out of range".to_owned()));
+    }
+    let proto = context.client.proto;
+    let mut keyspace = context.db.lock();
+    let fields: Vec<_> = keyspace.get_hash(args[0])?.into_iter().flatten().collect();
+    let mut rng = rand::rng();
+    let Some(count) = count else {
+        let field = fields.choose(&mut rng).map(|(field, _)| (*field).clone());
+        return Ok(RedirsValue::from(field));
+    };
+    let picked: Vec<_> = match count >= 0 {
+        true => index::sample(&mut rng, fields.len(), (count as usize).min(fields.len()))
+            .into_iter()
+            .map(|at| fields[at])
+            .collect(),
+        false if fields.is_empty() => Vec::new(),
+        false => (0..count.unsigned_abs())
+            .filter_map(|_| fields.choose(&mut rng).copied())
+            .collect(),
+    };
+    let field_value = |(field, value): (&Vec<u8>, &Vec<u8>)| {
+        [
+            RedirsValue::from(field.clone()),
+            RedirsValue::from(value.clone()),
+        ]
+    };
+    let items = match (withvalues, proto) {
+        (false, _) => picked
+            .into_iter()
+            .map(|(field, _)| RedirsValue::from(field.clone()))
+            .collect(),
+        // a pair per field over RESP3, one flat array over RESP2
+        (true, ProcVersion::V3) => picked
+            .into_iter()
+            .map(|pair| RedirsValue::Array(Some(field_value(pair).into())))
+            .collect(),
+        (true, _) => picked.into_iter().flat_map(field_value).collect(),
+    };
+    Ok(RedirsValue::Array(Some(items)))
+}
+
+// HSCAN key cursor [MATCH pattern] [COUNT count] [NOVALUES], the fields with
+// their values unless NOVALUES
+pub(crate) fn hscan(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let options = scan_options(&args[1..], "novalues")?;
+    let mut keyspace = context.db.lock();
+    let Some(hash) = keyspace.get_hash(args[0])? else {
+        return Ok(scan_reply(0, Vec::new()));
+    };
+    let fields = hash.keys().map(Vec::as_slice);
+    let (next, fields) = scan_members(fields, options.cursor, options.count);
+    let found = fields
+        .into_iter()
+        .filter(|field| options.matches(field))
+        .flat_map(|field| {
+            let value = (!options.novalues).then(|| RedirsValue::from(hash[field].clone()));
+            [Some(RedirsValue::from(field.to_vec())), value]
+        })
+        .flatten()
+        .collect();
+    Ok(scan_reply(next, found))
+}
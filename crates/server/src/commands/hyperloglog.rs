@@ -0,0 +1,74 @@
+use protocol::RedirsValue;
+
+use super::{ok, Context, Error, Reply};
+use crate::{db::Value, hyperloglog};
+
+fn not_a_hyperloglog() -> Error {
+    Error::Message("WRONGTYPE Key is not a valid HyperLogLog string value.".to_owned())
+}
+
+// PFADD key [element ...]: 1 when the key was created or a register changed
+pub(crate) fn pfadd(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let key = args[0];
+    let mut keyspace = context.db.lock();
+    let created = keyspace.get_string(key)?.is_none();
+    let value = keyspace.get_or_create_string(key)?;
+    if created {
+        *value = hyperloglog::new();
+    }
+    if !hyperloglog::is_valid(value) {
+        return Err(not_a_hyperloglog());
+    }
+    let changed = args[1..].iter().fold(false, |changed, element| {
+        hyperloglog::add(value, element) | changed
+    });
+    Ok(RedirsValue::Integer((created || changed) as i64))
+}
+
+// PFCOUNT key [key ...]: the estimated number of distinct elements added to
+// the key, or to any of the keys. A single key keeps the count it works out
+pub(crate) fn pfcount(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let mut keyspace = context.db.lock();
+    if let [key] = args {
+        let count = match keyspace.get_string_mut(key)? {
+            Some(value) if hyperloglog::is_valid(value) => hyperloglog::count(value),
+            Some(_) => return Err(not_a_hyperloglog()),
+            None => 0,
+        };
+        return Ok(RedirsValue::Integer(count as i64));
+    }
+    let mut registers = [0; hyperloglog::REGISTERS];
+    for value in keyspace.get_strings(args)?.into_iter().flatten() {
+        if !hyperloglog::is_valid(value) {
+            return Err(not_a_hyperloglog());
+        }
+        hyperloglog::merge(&mut registers, value);
+    }
+    Ok(RedirsValue::Integer(
+        hyperloglog::estimate(&registers) as i64
+    ))
+}
+
+// PFMERGE destkey [sourcekey ...]: stores the union of the destination and
+// the sources in the destination
+pub(crate) fn pfmerge(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let dest = args[0];
+    let mut keyspace = context.db.lock();
+    let mut registers = [0; hyperloglog::REGISTERS];
+    for value in keyspace.get_strings(args)?.into_iter().flatten() {
+        if !hyperloglog::is_valid(value) {
+            return Err(not_a_hyperloglog());
+        }
+        hyperloglog::merge(&mut registers, value);
+    }
+    // an existing destination changes in place, keeping its deadline
+    match keyspace.get_string_mut(dest)? {
+        Some(value) => hyperloglog::store(value, &registers),
+        None => {
+            let mut value = hyperloglog::new();
+            hyperloglog::store(&mut value, &registers);
+            keyspace.set(dest.to_vec(), Value::String(value));
+        }
+    }
+    Ok(ok())
+}
@@ -0,0 +1,144 @@
+use std::fmt::{Display, Write};
+
+use protocol::{RedirsValue, VerbatimEncoding};
+
+use super::{Context, Reply};
+use crate::Db;
+
+// adds the fields of a section to the payload
+type Section = fn(&Db, &mut String);
+
+// the sections in the order INFO lists them, all of them in the default set
+const SECTIONS: &[(&str, Section)] = &[
+    ("server", server),
+    ("clients", clients),
+    ("memory", memory),
+    ("stats", stats),
+    ("replication", replication),
+    ("keyspace", keyspace),
+];
+
+// INFO [section ...]: `# Section` headers each followed by its `field:value`
+// lines. Without a section, or with default, all or everything, every one of
+// them is there; an unknown section adds nothing
+pub(crate) fn info(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let every = args.is_empty()
+        || args.iter().any(|arg| {
+            [&b"default"[..], b"all", b"everything"]
+                .iter()
+                .any(|every| arg.eq_ignore_ascii_case(every))
+        });
+    let mut payload = String::new();
+    for (name, section) in SECTIONS {
+        if every
+            || args
+                .iter()
+                .any(|arg| arg.eq_ignore_ascii_case(name.as_bytes()))
+        {
+            if !payload.is_empty() {
+                payload.push_str("\r\n");
+            }
+            let mut title = name.to_string();
+            title[..1].make_ascii_uppercase();
+            let _ = write!(payload, "# {title}\r\n");
+            section(context.db, &mut payload);
+        }
+    }
+    Ok(RedirsValue::VerbatimString(
+        VerbatimEncoding::Txt,
+        payload.into_bytes(),
+    ))
+}
+
+fn field(payload: &mut String, name: &str, value: impl Display) {
+    let _ = write!(payload, "{name}:{value}\r\n");
+}
+
+fn server(db: &Db, payload: &mut String) {
+    let uptime = db.stats().uptime().as_secs();
+    field(payload, "redis_version", env!("CARGO_PKG_VERSION"));
+    field(payload, "redis_mode", "standalone");
+    field(payload, "process_id", std::process::id());
+    field(payload, "tcp_port", db.stats().port());
+    field(payload, "uptime_in_seconds", uptime);
+    field(payload, "uptime_in_days", uptime / 86_400);
+}
+
+fn clients(db: &Db, payload: &mut String) {
+    field(payload, "connected_clients", db.stats().connected_clients());
+    field(payload, "blocked_clients", db.stats().blocked_clients());
+}
+
+fn memory(db: &Db, payload: &mut String) {
+    let used = db.used_memory();
+    field(payload, "used_memory", used);
+    field(payload, "used_memory_human", human(used));
+    let maxmemory = db.eviction().maxmemory() as usize;
+    field(payload, "maxmemory", maxmemory);
+    field(payload, "maxmemory_human", human(maxmemory));
+    field(payload, "maxmemory_policy", db.eviction().policy().name());
+}
+
+// bytes the way redis shows them, e.g. 1.50K
+fn human(bytes: usize) -> String {
+    let units = ["K", "M", "G", "T", "P"];
+    let mut scaled = bytes as f64;
+    let mut unit = "B";
+    for next in units {
+        if scaled < 1024.0 {
+            break;
+        }
+        scaled /= 1024.0;
+        unit = next;
+    }
+    match unit {
+        "B" => format!("{bytes}B"),
+        unit => format!("{scaled:.2}{unit}"),
+    }
+}
+
+fn stats(db: &Db, payload: &mut String) {
+    let stats = db.stats();
+    let (mut expired, mut evicted, mut hits, mut misses) = (0, 0, 0, 0);
+    for index in 0..db.databases() {
+        let db = db.select(index);
+        let keyspace = db.lock();
+        let (database_hits, database_misses) = keyspace.keyspace_hits();
+        expired += keyspace.expired_keys();
+        evicted += keyspace.evicted_keys();
+        hits += database_hits;
+        misses += database_misses;
+    }
+    field(
+        payload,
+        "total_connections_received",
+        stats.connections_received(),
+    );
+    field(
+        payload,
+        "total_commands_processed",
+        stats.commands_processed(),
+    );
+    field(payload, "instantaneous_ops_per_sec", stats.ops_per_sec());
+    field(payload, "expired_keys", expired);
+    field(payload, "evicted_keys", evicted);
+    field(payload, "keyspace_hits", hits);
+    field(payload, "keyspace_misses", misses);
+}
+
+fn replication(_: &Db, payload: &mut String) {
+    field(payload, "role", "master");
+    field(payload, "connected_slaves", 0);
+}
+
+// a line for each database holding keys
+fn keyspace(db: &Db, payload: &mut String) {
+    for index in 0..db.databases() {
+        let db = db.select(index);
+        let keyspace = db.lock();
+        if !keyspace.is_empty() {
+            let value = format!("keys={},expires={}", keyspace.len(), keyspace.expiring());
+            field(payload, &format!("db{index}"), value);
+        }
+    }
+}
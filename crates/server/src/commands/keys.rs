@@ -0,0 +1,189 @@
+use protocol::{CommandError, RedirsValue};
+
+use super::{databases, ok, parse_int, Context, Error, Reply};
+use crate::glob;
+
+// every key matching a glob pattern, in no particular order
+pub(crate) fn keys(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let pattern = args[0];
+    let keys = context.db.lock().keys(|key| glob::matches(pattern, key));
+    Ok(RedirsValue::Array(Some(
+        keys.into_iter().map(RedirsValue::from).collect(),
+    )))
+}
+
+// the types TYPE can report, which SCAN TYPE filters by
+const TYPE_NAMES: [&str; 6] = ["string", "list", "hash", "set", "zset", "stream"];
+
+// what a SCAN, HSCAN, SSCAN or ZSCAN step was asked for
+pub(crate) struct ScanOptions<'a> {
+    pub cursor: u64,
+    pub pattern: Option<&'a [u8]>,
+    // how many names a step looks at, the pattern then filters what it found
+    pub count: usize,
+    pub type_name: Option<&'static str>,
+    pub novalues: bool,
+}
+
+impl ScanOptions<'_> {
+    pub fn matches(&self, name: &[u8]) -> bool {
+        self.pattern
+            .is_none_or(|pattern| glob::matches(pattern, name))
+    }
+}
+
+// the cursor then [MATCH pattern] [COUNT count] and `extra`, the one option
+// beyond them the command takes: TYPE for SCAN and NOVALUES for HSCAN
+pub(crate) fn scan_options<'a>(args: &[&'a [u8]], extra: &str) -> Result<ScanOptions<'a>, Error> {
+    let cursor = std::str::from_utf8(args[0])
+        .ok()
+        .and_then(|cursor| cursor.parse::<u64>().ok())
+        .ok_or_else(|| Error::Message("ERR invalid cursor".to_owned()))?;
+    let mut options = ScanOptions {
+        cursor,
+        pattern: None,
+        count: 10,
+        type_name: None,
+        novalues: false,
+    };
+    let mut opts = &args[1..];
+    while let [opt, rest @ ..] = opts {
+        let opt = opt.to_ascii_lowercase();
+        opts = match (opt.as_slice(), rest) {
+            (b"novalues", rest) if extra == "novalues" => {
+                options.novalues = true;
+                rest
+            }
+            (b"match", [pattern, rest @ ..]) => {
+                options.pattern = Some(*pattern);
+                rest
+            }
+            (b"count", [count, rest @ ..]) => {
+                let count = parse_int(count)?;
+                if count < 1 {
+                    return Err(CommandError::SyntaxError.into());
+                }
+                options.count = count as usize;
+                rest
+            }
+            (b"type", [name, rest @ ..]) if extra == "type" => {
+                let found = TYPE_NAMES
+                    .into_iter()
+                    .find(|type_name| name.eq_ignore_ascii_case(type_name.as_bytes()))
+                    .ok_or_else(|| {
+                        Error::Message(format!(
+                            "ERR unknown type name '{}'",
+                            String::from_utf8_lossy(name)
+                        ))
+                    })?;
+                options.type_name = Some(found);
+                rest
+            }
+            _ => return Err(CommandError::SyntaxError.into()),
+        };
+    }
+    Ok(options)
+}
+
+// the reply of every scan, the cursor to go on from and what the step found
+pub(crate) fn scan_reply(next: u64, found: Vec<RedirsValue>) -> RedirsValue {
+    RedirsValue::Array(Some(vec![
+        RedirsValue::from(next.to_string()),
+        RedirsValue::Array(Some(found)),
+    ]))
+}
+
+// SCAN cursor [MATCH pattern] [COUNT count] [TYPE type]
+pub(crate) fn scan(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let options = scan_options(args, "type")?;
+    let mut keyspace = context.db.lock();
+    let (next, keys) = keyspace.scan(options.cursor, options.count);
+    let keys = keys
+        .into_iter()
+        .filter(|key| options.matches(key))
+        .filter(|key| {
+            options
+                .type_name
+                .is_none_or(|name| keyspace.key_type(key) == Some(name))
+        })
+        .map(RedirsValue::from)
+        .collect();
+    Ok(scan_reply(next, keys))
+}
+
+pub(crate) fn type_(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let name = context.db.lock().key_type(args[0]).unwrap_or("none");
+    Ok(RedirsValue::SimpleString(name.to_owned()))
+}
+
+fn no_such_key() -> Error {
+    Error::Message("ERR no such key".to_owned())
+}
+
+// the value keeps its deadline under the new name
+pub(crate) fn rename(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    match context.db.lock().rename(args[0], args[1]) {
+        true => Ok(ok()),
+        false => Err(no_such_key()),
+    }
+}
+
+// 0 when the new name is taken, the old one included
+pub(crate) fn renamenx(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (from, to) = (args[0], args[1]);
+    let mut keyspace = context.db.lock();
+    if !keyspace.exists(from) {
+        return Err(no_such_key());
+    }
+    if keyspace.exists(to) {
+        return Ok(RedirsValue::Integer(0));
+    }
+    keyspace.rename(from, to);
+    Ok(RedirsValue::Integer(1))
+}
+
+// COPY source destination [DB destination-db] [REPLACE], to the same database
+// unless DB says otherwise
+pub(crate) fn copy(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (from, to) = (args[0], args[1]);
+    let mut replace = false;
+    let mut target = context.db.index();
+    let mut opts = &args[2..];
+    loop {
+        opts = match opts {
+            [] => break,
+            [opt, rest @ ..] if opt.eq_ignore_ascii_case(b"replace") => {
+                replace = true;
+                rest
+            }
+            [opt, db, rest @ ..] if opt.eq_ignore_ascii_case(b"db") => {
+                target = databases::db_index(context.db, db, CommandError::NotAnInteger.into())?;
+                rest
+            }
+            _ => return Err(CommandError::SyntaxError.into()),
+        }
+    }
+    let Some((mut here, mut there)) = context.db.lock_with(target) else {
+        if from == to {
+            return Err(Error::Message(
+                "ERR source and destination objects are the same".to_owned(),
+            ));
+        }
+        let copied = context.db.lock().copy(from, to, replace);
+        return Ok(RedirsValue::Integer(copied as i64));
+    };
+    let Some(value) = here.get(from).cloned() else {
+        return Ok(RedirsValue::Integer(0));
+    };
+    if !replace && there.exists(to) {
+        return Ok(RedirsValue::Integer(0));
+    }
+    let deadline = here.deadline(from);
+    there.place(to, value, deadline, "copy_to");
+    Ok(RedirsValue::Integer(1))
+}
+
+// nil when the database is empty
+pub(crate) fn randomkey(context: &mut Context<'_>, _: &[&[u8]]) -> Reply {
+    Ok(RedirsValue::from(context.db.lock().random_key()))
+}
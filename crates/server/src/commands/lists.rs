@@ -0,0 +1,301 @@
+use std::{collections::VecDeque, time::Duration};
+
+use protocol::{CommandError, RedirsValue};
+use tokio::time::Instant;
+
+use super::{ok, parse_float, parse_int, Context, Error, Reply};
+use crate::blocking::{Block, End, Pop};
+
+// the elements go in one at a time, so LPUSH a b c leaves c first
+fn push(context: &mut Context<'_>, args: &[&[u8]], end: End) -> Reply {
+    let mut keyspace = context.db.lock();
+    let list = keyspace.get_or_create_list(args[0])?;
+    for element in &args[1..] {
+        end.push(list, element.to_vec());
+    }
+    // the length includes what blocked connections are about to take
+    let len = list.len();
+    keyspace.serve_waiters(args[0]);
+    Ok(RedirsValue::Integer(len as i64))
+}
+
+pub(crate) fn lpush(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    push(context, args, End::Left)
+}
+
+pub(crate) fn rpush(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    push(context, args, End::Right)
+}
+
+// one element or nil without a count, an array of up to count of them or a
+// nil array for a missing key with one
+fn pop(context: &mut Context<'_>, args: &[&[u8]], end: End, name: &'static str) -> Reply {
+    let count = match args {
+        [_] => None,
+        [_, count] => match parse_int(count) {
+            Ok(count) if count >= 0 => Some(count as usize),
+            _ => {
+                return Err(Error::Message(
+                    "ERR value is out of range, must be positive".to_owned(),
+                ))
+            }
+        },
+        _ => return Err(CommandError::WrongArity(name).into()),
+    };
+    let key = args[0];
+    let mut keyspace = context.db.lock();
+    let Some(list) = keyspace.get_list_mut(key)? else {
+        return Ok(match count {
+            Some(_) => RedirsValue::Array(None),
+            None => RedirsValue::BulkString(None),
+        });
+    };
+    let reply = match count {
+        None => RedirsValue::from(end.pop(list)),
+        Some(count) => RedirsValue::Array(Some(
+            (0..count.min(list.len()))
+                .filter_map(|_| end.pop(list))
+                .map(RedirsValue::from)
+                .collect(),
+        )),
+    };
+    keyspace.remove_if_empty(key);
+    Ok(reply)
+}
+
+pub(crate) fn lpop(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    pop(context, args, End::Left, "lpop")
+}
+
+pub(crate) fn rpop(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    pop(context, args, End::Right, "rpop")
+}
+
+// the positions from `start` to `stop` both included of a list of `len`
+// elements, negative ones counting from the end. Out of range positions are
+// clamped and an empty range is none
+pub(crate) fn range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (start + len).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        stop + len
+    } else {
+        stop.min(len - 1)
+    };
+    (start <= stop && start < len).then_some((start as usize, stop as usize))
+}
+
+pub(crate) fn lrange(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (start, stop) = (parse_int(args[1])?, parse_int(args[2])?);
+    let mut keyspace = context.db.lock();
+    let elements = match keyspace.get_list(args[0])? {
+        Some(list) => match range(start, stop, list.len()) {
+            Some((start, stop)) => list
+                .range(start..=stop)
+                .map(|element| RedirsValue::from(element.clone()))
+                .collect(),
+            None => Vec::new(),
+        },
+        None => Vec::new(),
+    };
+    Ok(RedirsValue::Array(Some(elements)))
+}
+
+pub(crate) fn llen(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let len = context
+        .db
+        .lock()
+        .get_list(args[0])?
+        .map_or(0, VecDeque::len);
+    Ok(RedirsValue::Integer(len as i64))
+}
+
+// the new length, 0 for a missing key and -1 when the pivot is not there
+pub(crate) fn linsert(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, pivot, element) = (args[0], args[2], args[3]);
+    let after = match args[1] {
+        where_ if where_.eq_ignore_ascii_case(b"before") => false,
+        where_ if where_.eq_ignore_ascii_case(b"after") => true,
+        _ => return Err(CommandError::SyntaxError.into()),
+    };
+    let mut keyspace = context.db.lock();
+    let Some(list) = keyspace.get_list_mut(key)? else {
+        return Ok(RedirsValue::Integer(0));
+    };
+    let Some(at) = list.iter().position(|e| e == pivot) else {
+        return Ok(RedirsValue::Integer(-1));
+    };
+    list.insert(at + after as usize, element.to_vec());
+    Ok(RedirsValue::Integer(list.len() as i64))
+}
+
+pub(crate) fn lset(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, index, element) = (args[0], parse_int(args[1])?, args[2]);
+    let mut keyspace = context.db.lock();
+    let Some(list) = keyspace.get_list_mut(key)? else {
+        return Err(Error::Message("ERR no such key".to_owned()));
+    };
+    let len = list.len() as i64;
+    let index = if index < 0 { index + len } else { index };
+    match (0..len).contains(&index) {
+        true => list[index as usize] = element.to_vec(),
+        false => return Err(Error::Message("ERR index out of range".to_owned())),
+    }
+    Ok(ok())
+}
+
+// up to `count` matches from the head, from the tail when negative and all of
+// them for 0
+pub(crate) fn lrem(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, count, element) = (args[0], parse_int(args[1])?, args[2]);
+    let mut keyspace = context.db.lock();
+    let Some(list) = keyspace.get_list_mut(key)? else {
+        return Ok(RedirsValue::Integer(0));
+    };
+    let limit = match count {
+        0 => usize::MAX,
+        count => count.unsigned_abs() as usize,
+    };
+    let mut removed = 0;
+    let mut keep = |e: &Vec<u8>| {
+        let matched = removed < limit && e == element;
+        removed += matched as usize;
+        !matched
+    };
+    *list = match count < 0 {
+        true => {
+            let mut kept: VecDeque<_> = list.drain(..).rev().filter(&mut keep).collect();
+            kept.make_contiguous().reverse();
+            kept
+        }
+        false => list.drain(..).filter(&mut keep).collect(),
+    };
+    keyspace.remove_if_empty(key);
+    Ok(RedirsValue::Integer(removed as i64))
+}
+
+// keeps the range LRANGE would return, nothing of it removes the key
+pub(crate) fn ltrim(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, start, stop) = (args[0], parse_int(args[1])?, parse_int(args[2])?);
+    let mut keyspace = context.db.lock();
+    if let Some(list) = keyspace.get_list_mut(key)? {
+        match range(start, stop, list.len()) {
+            Some((start, stop)) => {
+                list.truncate(stop + 1);
+                list.drain(..start);
+            }
+            None => list.clear(),
+        }
+        keyspace.remove_if_empty(key);
+    }
+    Ok(ok())
+}
+
+const RANK_ZERO: &str = "ERR RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list";
+const RANK_MIN: &str =
+    "ERR value is out of range, value must between -9223372036854775807 and 9223372036854775807";
+
+// LPOS key element [RANK rank] [COUNT num-matches] [MAXLEN len]: the index of
+// the rank-th match, counting from the tail when negative, looking at no more
+// than maxlen elements. COUNT replies with an array of up to that many
+// matches, all of them for 0
+pub(crate) fn lpos(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, element) = (args[0], args[1]);
+    let (mut rank, mut count, mut maxlen) = (1, None, 0);
+    for pair in args[2..].chunks(2) {
+        let [opt, value] = pair else {
+            return Err(CommandError::SyntaxError.into());
+        };
+        let opt = opt.to_ascii_lowercase();
+        if !matches!(&opt[..], b"rank" | b"count" | b"maxlen") {
+            return Err(CommandError::SyntaxError.into());
+        }
+        let value = parse_int(value)?;
+        match (&opt[..], value) {
+            (b"rank", 0) => return Err(Error::Message(RANK_ZERO.to_owned())),
+            (b"rank", i64::MIN) => return Err(Error::Message(RANK_MIN.to_owned())),
+            (b"rank", value) => rank = value,
+            (b"count", ..0) => {
+                return Err(Error::Message("ERR COUNT can't be negative".to_owned()))
+            }
+            (b"count", value) => count = Some(value as usize),
+            (_, ..0) => return Err(Error::Message("ERR MAXLEN can't be negative".to_owned())),
+            (_, value) => maxlen = value as usize,
+        }
+    }
+    let mut keyspace = context.db.lock();
+    let list = keyspace.get_list(key)?;
+    let len = list.map_or(0, VecDeque::len);
+    let looked_at = match maxlen {
+        0 => len,
+        maxlen => maxlen.min(len),
+    };
+    let indices: Box<dyn Iterator<Item = usize>> = match rank > 0 {
+        true => Box::new(0..looked_at),
+        false => Box::new((len - looked_at..len).rev()),
+    };
+    let mut matches = indices
+        .filter(|&at| list.is_some_and(|list| list[at] == element))
+        .skip(rank.unsigned_abs() as usize - 1)
+        .take(match count {
+            Some(0) | None => usize::MAX,
+            Some(count) => count,
+        })
+        .map(|at| RedirsValue::Integer(at as i64));
+    Ok(match count {
+        Some(_) => RedirsValue::Array(Some(matches.collect())),
+        None => matches.next().unwrap_or(RedirsValue::BulkString(None)),
+    })
+}
+
+// the end of a wait of `timeout` seconds starting now, none for 0 waiting
+// forever. A finite timeout past what an `Instant` holds is an error
+pub(crate) fn deadline(timeout: &[u8]) -> Result<Option<Instant>, Error> {
+    let timeout = parse_float(timeout)
+        .map_err(|_| Error::Message("ERR timeout is not a float or out of range".to_owned()))?;
+    if timeout < 0.0 {
+        return Err(Error::Message("ERR timeout is negative".to_owned()));
+    }
+    if timeout == 0.0 {
+        return Ok(None);
+    }
+    Duration::try_from_secs_f64(timeout)
+        .ok()
+        .and_then(|timeout| Instant::now().checked_add(timeout))
+        .map(Some)
+        .ok_or_else(|| Error::Message("ERR timeout is out of range".to_owned()))
+}
+
+// BLPOP key [key ...] timeout: the first element of the first of the keys
+// holding a list, or a wait for one of them to get one for up to timeout
+// seconds, 0 waiting forever
+fn blocking_pop(context: &mut Context<'_>, args: &[&[u8]], end: End) -> Reply {
+    let (keys, timeout) = args.split_at(args.len() - 1);
+    let deadline = deadline(timeout[0])?;
+    let mut keyspace = context.db.lock();
+    for key in keys {
+        if let Some(list) = keyspace.get_list_mut(key)? {
+            let element = end.pop(list).expect("lists are never empty");
+            keyspace.remove_if_empty(key);
+            return Ok(RedirsValue::Array(Some(vec![
+                RedirsValue::from(key.to_vec()),
+                RedirsValue::from(element),
+            ])));
+        }
+    }
+    drop(keyspace);
+    context.block = Some(Block::new(context.db, keys, Pop::List(end), deadline));
+    Ok(RedirsValue::Null)
+}
+
+pub(crate) fn blpop(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    blocking_pop(context, args, End::Left)
+}
+
+pub(crate) fn brpop(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    blocking_pop(context, args, End::Right)
+}
@@ -0,0 +1,251 @@
+// runs the scripts of EVAL and EVALSHA in Lua 5.1 as redis does: a fresh
+// interpreter for each script, KEYS and ARGV holding its arguments and
+// redis.call and redis.pcall running commands through the table the way a
+// client's are run. Replies cross between RESP and Lua by the rules of the
+// redis docs, a script seeing what a RESP2 client would
+
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use mlua::{ChunkMode, HookTriggers, Lua, LuaOptions, MultiValue, StdLib, Table, Value, VmState};
+use protocol::{Lexer, ProcVersion, RedirsValue};
+
+use super::{spec, Context, Error, Outcome, Reply};
+use crate::scripting::{hex, sha1};
+
+// how often the time limit is checked, in VM instructions
+const HOOK_INSTRUCTIONS: u32 = 10_000;
+
+// tables nested deeper than this in a script's reply are an error, a table
+// holding itself would otherwise never end
+const MAX_DEPTH: usize = 128;
+
+// redis.call raises the error of an error reply, which redis.pcall returns
+const PRELUDE: &str = r#"
+redis.call = function(...)
+    local reply = redis.pcall(...)
+    if type(reply) == "table" and reply.err then
+        error(reply)
+    end
+    return reply
+end
+redis.error_reply = function(err) return { err = err } end
+redis.status_reply = function(ok) return { ok = ok } end
+"#;
+
+fn engine(e: mlua::Error) -> Error {
+    Error::Message(format!("ERR {e}"))
+}
+
+// the script's reply, the commands it ran having been run as the client's
+// while the keyspace is held for it alone. A script running past the busy
+// threshold is stopped with its writes so far kept, as redis's SCRIPT KILL
+// leaves them
+pub(super) fn run(
+    context: &mut Context<'_>,
+    script: &[u8],
+    keys: &[&[u8]],
+    args: &[&[u8]],
+) -> Reply {
+    let lua = Lua::new_with(
+        StdLib::TABLE | StdLib::STRING | StdLib::MATH,
+        LuaOptions::new(),
+    )
+    .map_err(engine)?;
+    let threshold = context.db.scripts().busy_threshold();
+    let timed_out = Arc::new(AtomicBool::new(false));
+    if !threshold.is_zero() {
+        let (start, timed_out) = (Instant::now(), timed_out.clone());
+        let triggers = HookTriggers::new().every_nth_instruction(HOOK_INSTRUCTIONS);
+        lua.set_hook(triggers, move |_, _| match start.elapsed() > threshold {
+            true => {
+                timed_out.store(true, Ordering::Relaxed);
+                Err(mlua::Error::runtime("script ran past the busy threshold"))
+            }
+            false => Ok(VmState::Continue),
+        });
+    }
+    // SELECT in a script changes the database of the script alone
+    let db = context.client.db;
+    let reply = lua
+        .scope(|scope| {
+            let globals = lua.globals();
+            // nothing is read from files
+            globals.raw_set("dofile", Value::Nil)?;
+            globals.raw_set("loadfile", Value::Nil)?;
+            globals.raw_set("KEYS", strings(&lua, keys)?)?;
+            globals.raw_set("ARGV", strings(&lua, args)?)?;
+            let redis = lua.create_table()?;
+            redis.raw_set(
+                "pcall",
+                scope.create_function_mut(|lua, args: MultiValue| {
+                    to_lua(lua, resp2(&call(context, &args)))
+                })?,
+            )?;
+            redis.raw_set(
+                "sha1hex",
+                lua.create_function(|_, text: mlua::String| Ok(hex(&sha1(&text.as_bytes()))))?,
+            )?;
+            globals.raw_set("redis", redis)?;
+            lua.load(PRELUDE).exec()?;
+            // compiled bytecode could crash the interpreter, only text is run
+            let function = match lua
+                .load(script)
+                .set_name("=user_script")
+                .set_mode(ChunkMode::Text)
+                .into_function()
+            {
+                Ok(function) => function,
+                Err(e) => {
+                    return Ok(Err(Error::Message(format!(
+                        "ERR Error compiling script (new function): {e}"
+                    ))))
+                }
+            };
+            let pcall: mlua::Function = globals.raw_get("pcall")?;
+            let (ok, value): (bool, Value) = pcall.call(function)?;
+            Ok(match ok {
+                true => from_lua(&value, 0),
+                false => Err(script_error(&value)),
+            })
+        })
+        .map_err(engine)
+        .and_then(|reply| reply);
+    context.client.db = db;
+    match timed_out.load(Ordering::Relaxed) {
+        true => Err(Error::Message(format!(
+            "BUSY the script ran for longer than the busy-reply-threshold of {} ms and was stopped",
+            threshold.as_millis()
+        ))),
+        false => reply,
+    }
+}
+
+// KEYS or ARGV
+fn strings(lua: &Lua, args: &[&[u8]]) -> mlua::Result<Table> {
+    lua.create_sequence_from(
+        args.iter()
+            .map(|arg| lua.create_string(arg))
+            .collect::<mlua::Result<Vec<_>>>()?,
+    )
+}
+
+// what the script raised: the error reply of a failed redis.call or one of
+// its own making, or any other Lua error
+fn script_error(value: &Value) -> Error {
+    if let Value::Table(table) = value {
+        if let Ok(Value::String(err)) = table.raw_get("err") {
+            return Error::Message(err.to_string_lossy());
+        }
+    }
+    match value {
+        Value::String(message) => Error::Message(format!("ERR {}", message.to_string_lossy())),
+        Value::Error(e) => Error::Message(format!("ERR {e}")),
+        _ => Error::Message("ERR unknown error raised by the script".to_owned()),
+    }
+}
+
+// runs a redis.call or redis.pcall, an error reply for one that cannot run
+// from a script or at all
+fn call(context: &mut Context<'_>, args: &MultiValue) -> RedirsValue {
+    let args = args
+        .iter()
+        .map(|arg| match arg {
+            Value::String(s) => Some(s.as_bytes().to_vec()),
+            Value::Integer(i) => Some(i.to_string().into_bytes()),
+            Value::Number(n) => Some(n.to_string().into_bytes()),
+            _ => None,
+        })
+        .collect::<Option<Vec<_>>>();
+    let error = |message: &str| RedirsValue::SimpleError(message.to_owned());
+    let Some(args) = args else {
+        return error("ERR Lua redis lib command arguments must be strings or integers");
+    };
+    let Some(name) = args.first() else {
+        return error("ERR Please specify at least one argument for this redis lib call");
+    };
+    match spec(name) {
+        None => error("ERR Unknown Redis command called from script"),
+        Some(command) if command.flags.contains(&"noscript") => {
+            error("ERR This Redis command is not allowed from script")
+        }
+        Some(_) => {
+            let request =
+                RedirsValue::Array(Some(args.into_iter().map(RedirsValue::from).collect()));
+            // blocking commands do not wait, as in a transaction
+            match super::run(&request, context.client, context.db) {
+                Outcome::Reply(reply) => reply,
+                Outcome::Replies(replies) => RedirsValue::Array(Some(replies)),
+                Outcome::Block(_) => RedirsValue::Array(None),
+            }
+        }
+    }
+}
+
+// the reply as a RESP2 client reads it, with the downgrades of the writer
+fn resp2(reply: &RedirsValue) -> RedirsValue {
+    let mut out = Vec::with_capacity(reply.encoded_len());
+    reply
+        .write_resp(&mut out, ProcVersion::V2)
+        .expect("writing to a Vec cannot fail");
+    Lexer::new(&out)
+        .lex()
+        .expect("the writer's RESP2 lexes back")
+}
+
+// a RESP2 reply as Lua has it: status replies are tables with an ok field and
+// errors tables with an err field, nil is false
+fn to_lua(lua: &Lua, reply: RedirsValue) -> mlua::Result<Value> {
+    let field = |name: &str, text: String| -> mlua::Result<Value> {
+        let table = lua.create_table()?;
+        table.raw_set(name, text)?;
+        Ok(Value::Table(table))
+    };
+    Ok(match reply {
+        RedirsValue::Integer(i) => Value::Integer(i),
+        RedirsValue::BulkString(Some(bytes)) => Value::String(lua.create_string(&bytes)?),
+        RedirsValue::Array(Some(values)) => {
+            let table = lua.create_table_with_capacity(values.len(), 0)?;
+            for (i, value) in values.into_iter().enumerate() {
+                table.raw_set(i + 1, to_lua(lua, value)?)?;
+            }
+            Value::Table(table)
+        }
+        RedirsValue::SimpleString(status) => field("ok", status)?,
+        RedirsValue::SimpleError(err) => field("err", err)?,
+        _ => Value::Boolean(false),
+    })
+}
+
+// what a script returned as a reply: numbers are integers cut towards zero,
+// true is 1 and false and nil are nil, tables with an err or ok field are
+// error and status replies and any other an array up to its first nil
+fn from_lua(value: &Value, depth: usize) -> Reply {
+    Ok(match value {
+        Value::Integer(i) => RedirsValue::Integer(*i),
+        Value::Number(n) => RedirsValue::Integer(*n as i64),
+        Value::String(s) => RedirsValue::from(s.as_bytes().to_vec()),
+        Value::Boolean(true) => RedirsValue::Integer(1),
+        Value::Table(_) if depth == MAX_DEPTH => {
+            return Err(Error::Message("ERR reached lua stack limit".to_owned()))
+        }
+        Value::Table(table) => match (table.raw_get("err"), table.raw_get("ok")) {
+            (Ok(Value::String(err)), _) => RedirsValue::SimpleError(err.to_string_lossy()),
+            (_, Ok(Value::String(status))) => RedirsValue::SimpleString(status.to_string_lossy()),
+            _ => RedirsValue::Array(Some(
+                (1..)
+                    .map_while(|i| match table.raw_get(i) {
+                        Ok(Value::Nil) | Err(_) => None,
+                        Ok(value) => Some(from_lua(&value, depth + 1)),
+                    })
+                    .collect::<Result<_, _>>()?,
+            )),
+        },
+        _ => RedirsValue::BulkString(None),
+    })
+}
@@ -0,0 +1,32 @@
+use protocol::{CommandError, RedirsValue};
+
+use super::{parse_int, Context, Error, Reply};
+use crate::memory::DEFAULT_SAMPLES;
+
+// MEMORY USAGE key [SAMPLES count]: the bytes the key and its value take, an
+// aggregate measured on its first `count` elements, all of them for 0
+pub(crate) fn memory(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let subcommand = args[0].to_ascii_lowercase();
+    match (subcommand.as_slice(), &args[1..]) {
+        (b"usage", [key, opts @ ..]) => {
+            let samples = match opts {
+                [] => DEFAULT_SAMPLES,
+                [samples, count] if samples.eq_ignore_ascii_case(b"samples") => {
+                    usize::try_from(parse_int(count)?).map_err(|_| CommandError::SyntaxError)?
+                }
+                _ => return Err(CommandError::SyntaxError.into()),
+            };
+            Ok(match context.db.lock().memory_usage(key, samples) {
+                Some(bytes) => RedirsValue::Integer(bytes as i64),
+                None => RedirsValue::Null,
+            })
+        }
+        (b"usage", _) => Err(Error::Message(
+            "ERR wrong number of arguments for 'memory|usage' command".to_owned(),
+        )),
+        _ => Err(Error::Message(format!(
+            "ERR unknown subcommand '{}'. Try MEMORY HELP.",
+            String::from_utf8_lossy(args[0])
+        ))),
+    }
+}
@@ -0,0 +1,1562 @@
+use protocol::{Action, Cmd, CommandError, HelloReply, RedirsValue, System};
+
+use crate::{
+    blocking::Block,
+    connection::Client,
+    db::{Value, WrongType},
+    eviction, notify,
+    tracking::Tracking,
+    Db,
+};
+
+mod acl;
+mod bitmaps;
+mod client;
+mod command;
+mod config;
+mod databases;
+mod expire;
+mod hashes;
+mod hyperloglog;
+mod info;
+mod keys;
+mod lists;
+#[cfg(feature = "lua")]
+mod lua;
+mod memory;
+mod object;
+mod pubsub;
+mod scripting;
+mod sets;
+mod sorted_sets;
+mod streams;
+mod strings;
+mod transactions;
+
+pub(crate) use transactions::{unwatch_keys, Transaction};
+
+// why a command failed, sent back to the client as an error reply
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Error {
+    Command(CommandError),
+    WrongType,
+    // the whole text of the reply, its ERR or other prefix included
+    Message(String),
+}
+
+impl From<CommandError> for Error {
+    fn from(e: CommandError) -> Self {
+        Error::Command(e)
+    }
+}
+
+impl From<WrongType> for Error {
+    fn from(_: WrongType) -> Self {
+        Error::WrongType
+    }
+}
+
+impl Error {
+    pub fn to_client_error(&self) -> RedirsValue {
+        match self {
+            Error::Command(e) => e.to_client_error(),
+            Error::WrongType => WrongType.to_client_error(),
+            Error::Message(message) => RedirsValue::SimpleError(message.clone()),
+        }
+    }
+}
+
+pub(crate) type Reply = Result<RedirsValue, Error>;
+
+// what a command runs against
+pub(crate) struct Context<'a> {
+    pub client: &'a mut Client,
+    pub db: &'a Db,
+    // set by a command that waits for its reply instead of replying now
+    pub block: Option<Block>,
+    // set by a command replying more than once, sent in place of its reply
+    pub replies: Option<Vec<RedirsValue>>,
+}
+
+// how a connection answers a request
+pub(crate) enum Outcome {
+    Reply(RedirsValue),
+    // the reply comes once the wait is over
+    Block(Block),
+    Replies(Vec<RedirsValue>),
+}
+
+// a command served here rather than parsed by `protocol::Cmd`
+pub(crate) struct Command {
+    // lowercase, as redis reports it
+    pub name: &'static str,
+    // the number of arguments including the name, at least -arity of them
+    // when negative
+    pub arity: i32,
+    // as redis has them, e.g. write, readonly, fast or denyoom
+    pub flags: &'static [&'static str],
+    // the ACL categories it is in besides those its flags put it in, see
+    // `acl::in_category`
+    pub categories: &'static [&'static str],
+    pub keys: Keys,
+    // gets the arguments after the name, their count already checked
+    pub run: fn(&mut Context<'_>, &[&[u8]]) -> Reply,
+}
+
+// which of the arguments of a command are keys, the name at 0
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Keys {
+    None,
+    // redis's first key, last key and step, a negative last key counting
+    // back from the end
+    Range(usize, i32, usize),
+    // as many as the count at this position says, right after it
+    Counted(usize),
+    // the first half of the arguments after STREAMS
+    Streams,
+}
+
+impl Keys {
+    // the keys among `args`, those a short or malformed request is missing
+    // left out
+    pub fn of<'a>(self, args: &[&'a [u8]]) -> Vec<&'a [u8]> {
+        let range = match self {
+            Keys::None => return Vec::new(),
+            Keys::Range(first, last, step) => {
+                let last = match last < 0 {
+                    true => args.len() as i32 + last,
+                    false => last,
+                };
+                (first..=last.max(0) as usize).step_by(step)
+            }
+            Keys::Counted(at) => {
+                let count = args.get(at).and_then(|count| parse_int(count).ok());
+                let count = count.filter(|count| *count > 0).unwrap_or(0) as usize;
+                (at + 1..=at + count).step_by(1)
+            }
+            Keys::Streams => {
+                let Some(streams) = args
+                    .iter()
+                    .position(|arg| arg.eq_ignore_ascii_case(b"streams"))
+                else {
+                    return Vec::new();
+                };
+                let count = (args.len() - streams - 1) / 2;
+                (streams + 1..=streams + count).step_by(1)
+            }
+        };
+        range.filter_map(|i| args.get(i).copied()).collect()
+    }
+}
+
+const COMMANDS: &[Command] = &[
+    Command {
+        name: "expire",
+        arity: -3,
+        flags: &["write", "fast"],
+        categories: &["keyspace"],
+        keys: Keys::Range(1, 1, 1),
+        run: expire::expire,
+    },
+    Command {
+        name: "pexpire",
+        arity: -3,
+        flags: &["write", "fast"],
+        categories: &["keyspace"],
+        keys: Keys::Range(1, 1, 1),
+        run: expire::pexpire,
+    },
+    Command {
+        name: "expireat",
+        arity: -3,
+        flags: &["write", "fast"],
+        categories: &["keyspace"],
+        keys: Keys::Range(1, 1, 1),
+        run: expire::expireat,
+    },
+    Command {
+        name: "pexpireat",
+        arity: -3,
+        flags: &["write", "fast"],
+        categories: &["keyspace"],
+        keys: Keys::Range(1, 1, 1),
+        run: expire::pexpireat,
+    },
+    Command {
+        name: "persist",
+        arity: 2,
+        flags: &["write", "fast"],
+        categories: &["keyspace"],
+        keys: Keys::Range(1, 1, 1),
+        run: expire::persist,
+    },
+    Command {
+        name: "ttl",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        categories: &["keyspace"],
+        keys: Keys::Range(1, 1, 1),
+        run: expire::ttl,
+    },
+    Command {
+        name: "pttl",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        categories: &["keyspace"],
+        keys: Keys::Range(1, 1, 1),
+        run: expire::pttl,
+    },
+    Command {
+        name: "keys",
+        arity: 2,
+        flags: &["readonly"],
+        categories: &["keyspace", "dangerous"],
+        keys: Keys::None,
+        run: keys::keys,
+    },
+    Command {
+        name: "scan",
+        arity: -2,
+        flags: &["readonly"],
+        categories: &["keyspace"],
+        keys: Keys::None,
+        run: keys::scan,
+    },
+    Command {
+        name: "type",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        categories: &["keyspace"],
+        keys: Keys::Range(1, 1, 1),
+        run: keys::type_,
+    },
+    Command {
+        name: "rename",
+        arity: 3,
+        flags: &["write"],
+        categories: &["keyspace"],
+        keys: Keys::Range(1, 2, 1),
+        run: keys::rename,
+    },
+    Command {
+        name: "renamenx",
+        arity: 3,
+        flags: &["write", "fast"],
+        categories: &["keyspace"],
+        keys: Keys::Range(1, 2, 1),
+        run: keys::renamenx,
+    },
+    Command {
+        name: "copy",
+        arity: -3,
+        flags: &["write"],
+        categories: &["keyspace"],
+        keys: Keys::Range(1, 2, 1),
+        run: keys::copy,
+    },
+    Command {
+        name: "randomkey",
+        arity: 1,
+        flags: &["readonly"],
+        categories: &["keyspace"],
+        keys: Keys::None,
+        run: keys::randomkey,
+    },
+    Command {
+        name: "lpush",
+        arity: -3,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["list"],
+        keys: Keys::Range(1, 1, 1),
+        run: lists::lpush,
+    },
+    Command {
+        name: "rpush",
+        arity: -3,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["list"],
+        keys: Keys::Range(1, 1, 1),
+        run: lists::rpush,
+    },
+    Command {
+        name: "lpop",
+        arity: -2,
+        flags: &["write", "fast"],
+        categories: &["list"],
+        keys: Keys::Range(1, 1, 1),
+        run: lists::lpop,
+    },
+    Command {
+        name: "rpop",
+        arity: -2,
+        flags: &["write", "fast"],
+        categories: &["list"],
+        keys: Keys::Range(1, 1, 1),
+        run: lists::rpop,
+    },
+    Command {
+        name: "lrange",
+        arity: 4,
+        flags: &["readonly"],
+        categories: &["list"],
+        keys: Keys::Range(1, 1, 1),
+        run: lists::lrange,
+    },
+    Command {
+        name: "llen",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        categories: &["list"],
+        keys: Keys::Range(1, 1, 1),
+        run: lists::llen,
+    },
+    Command {
+        name: "blpop",
+        arity: -3,
+        flags: &["write", "blocking"],
+        categories: &["list"],
+        keys: Keys::Range(1, -2, 1),
+        run: lists::blpop,
+    },
+    Command {
+        name: "brpop",
+        arity: -3,
+        flags: &["write", "blocking"],
+        categories: &["list"],
+        keys: Keys::Range(1, -2, 1),
+        run: lists::brpop,
+    },
+    Command {
+        name: "linsert",
+        arity: 5,
+        flags: &["write", "denyoom"],
+        categories: &["list"],
+        keys: Keys::Range(1, 1, 1),
+        run: lists::linsert,
+    },
+    Command {
+        name: "lset",
+        arity: 4,
+        flags: &["write", "denyoom"],
+        categories: &["list"],
+        keys: Keys::Range(1, 1, 1),
+        run: lists::lset,
+    },
+    Command {
+        name: "lrem",
+        arity: 4,
+        flags: &["write"],
+        categories: &["list"],
+        keys: Keys::Range(1, 1, 1),
+        run: lists::lrem,
+    },
+    Command {
+        name: "ltrim",
+        arity: 4,
+        flags: &["write"],
+        categories: &["list"],
+        keys: Keys::Range(1, 1, 1),
+        run: lists::ltrim,
+    },
+    Command {
+        name: "lpos",
+        arity: -3,
+        flags: &["readonly"],
+        categories: &["list"],
+        keys: Keys::Range(1, 1, 1),
+        run: lists::lpos,
+    },
+    Command {
+        name: "hset",
+        arity: -4,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["hash"],
+        keys: Keys::Range(1, 1, 1),
+        run: hashes::hset,
+    },
+    Command {
+        name: "hget",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        categories: &["hash"],
+        keys: Keys::Range(1, 1, 1),
+        run: hashes::hget,
+    },
+    Command {
+        name: "hmget",
+        arity: -3,
+        flags: &["readonly", "fast"],
+        categories: &["hash"],
+        keys: Keys::Range(1, 1, 1),
+        run: hashes::hmget,
+    },
+    Command {
+        name: "hdel",
+        arity: -3,
+        flags: &["write", "fast"],
+        categories: &["hash"],
+        keys: Keys::Range(1, 1, 1),
+        run: hashes::hdel,
+    },
+    Command {
+        name: "hgetall",
+        arity: 2,
+        flags: &["readonly"],
+        categories: &["hash"],
+        keys: Keys::Range(1, 1, 1),
+        run: hashes::hgetall,
+    },
+    Command {
+        name: "hexists",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        categories: &["hash"],
+        keys: Keys::Range(1, 1, 1),
+        run: hashes::hexists,
+    },
+    Command {
+        name: "hlen",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        categories: &["hash"],
+        keys: Keys::Range(1, 1, 1),
+        run: hashes::hlen,
+    },
+    Command {
+        name: "hincrby",
+        arity: 4,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["hash"],
+        keys: Keys::Range(1, 1, 1),
+        run: hashes::hincrby,
+    },
+    Command {
+        name: "hincrbyfloat",
+        arity: 4,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["hash"],
+        keys: Keys::Range(1, 1, 1),
+        run: hashes::hincrbyfloat,
+    },
+    Command {
+        name: "hrandfield",
+        arity: -2,
+        flags: &["readonly"],
+        categories: &["hash"],
+        keys: Keys::Range(1, 1, 1),
+        run: hashes::hrandfield,
+    },
+    Command {
+        name: "hscan",
+        arity: -3,
+        flags: &["readonly"],
+        categories: &["hash"],
+        keys: Keys::Range(1, 1, 1),
+        run: hashes::hscan,
+    },
+    Command {
+        name: "sadd",
+        arity: -3,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["set"],
+        keys: Keys::Range(1, 1, 1),
+        run: sets::sadd,
+    },
+    Command {
+        name: "srem",
+        arity: -3,
+        flags: &["write", "fast"],
+        categories: &["set"],
+        keys: Keys::Range(1, 1, 1),
+        run: sets::srem,
+    },
+    Command {
+        name: "smembers",
+        arity: 2,
+        flags: &["readonly"],
+        categories: &["set"],
+        keys: Keys::Range(1, 1, 1),
+        run: sets::smembers,
+    },
+    Command {
+        name: "sismember",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        categories: &["set"],
+        keys: Keys::Range(1, 1, 1),
+        run: sets::sismember,
+    },
+    Command {
+        name: "smismember",
+        arity: -3,
+        flags: &["readonly", "fast"],
+        categories: &["set"],
+        keys: Keys::Range(1, 1, 1),
+        run: sets::smismember,
+    },
+    Command {
+        name: "scard",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        categories: &["set"],
+        keys: Keys::Range(1, 1, 1),
+        run: sets::scard,
+    },
+    Command {
+        name: "sinter",
+        arity: -2,
+        flags: &["readonly"],
+        categories: &["set"],
+        keys: Keys::Range(1, -1, 1),
+        run: sets::sinter,
+    },
+    Command {
+        name: "sunion",
+        arity: -2,
+        flags: &["readonly"],
+        categories: &["set"],
+        keys: Keys::Range(1, -1, 1),
+        run: sets::sunion,
+    },
+    Command {
+        name: "sdiff",
+        arity: -2,
+        flags: &["readonly"],
+        categories: &["set"],
+        keys: Keys::Range(1, -1, 1),
+        run: sets::sdiff,
+    },
+    Command {
+        name: "sinterstore",
+        arity: -3,
+        flags: &["write", "denyoom"],
+        categories: &["set"],
+        keys: Keys::Range(1, -1, 1),
+        run: sets::sinterstore,
+    },
+    Command {
+        name: "sunionstore",
+        arity: -3,
+        flags: &["write", "denyoom"],
+        categories: &["set"],
+        keys: Keys::Range(1, -1, 1),
+        run: sets::sunionstore,
+    },
+    Command {
+        name: "sdiffstore",
+        arity: -3,
+        flags: &["write", "denyoom"],
+        categories: &["set"],
+        keys: Keys::Range(1, -1, 1),
+        run: sets::sdiffstore,
+    },
+    Command {
+        name: "sintercard",
+        arity: -3,
+        flags: &["readonly"],
+        categories: &["set"],
+        keys: Keys::Counted(1),
+        run: sets::sintercard,
+    },
+    Command {
+        name: "spop",
+        arity: -2,
+        flags: &["write", "fast"],
+        categories: &["set"],
+        keys: Keys::Range(1, 1, 1),
+        run: sets::spop,
+    },
+    Command {
+        name: "srandmember",
+        arity: -2,
+        flags: &["readonly"],
+        categories: &["set"],
+        keys: Keys::Range(1, 1, 1),
+        run: sets::srandmember,
+    },
+    Command {
+        name: "zadd",
+        arity: -4,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["sortedset"],
+        keys: Keys::Range(1, 1, 1),
+        run: sorted_sets::zadd,
+    },
+    Command {
+        name: "zscore",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        categories: &["sortedset"],
+        keys: Keys::Range(1, 1, 1),
+        run: sorted_sets::zscore,
+    },
+    Command {
+        name: "zcard",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        categories: &["sortedset"],
+        keys: Keys::Range(1, 1, 1),
+        run: sorted_sets::zcard,
+    },
+    Command {
+        name: "zrange",
+        arity: -4,
+        flags: &["readonly"],
+        categories: &["sortedset"],
+        keys: Keys::Range(1, 1, 1),
+        run: sorted_sets::zrange,
+    },
+    Command {
+        name: "zrangebyscore",
+        arity: -4,
+        flags: &["readonly"],
+        categories: &["sortedset"],
+        keys: Keys::Range(1, 1, 1),
+        run: sorted_sets::zrangebyscore,
+    },
+    Command {
+        name: "zincrby",
+        arity: 4,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["sortedset"],
+        keys: Keys::Range(1, 1, 1),
+        run: sorted_sets::zincrby,
+    },
+    Command {
+        name: "zrank",
+        arity: -3,
+        flags: &["readonly", "fast"],
+        categories: &["sortedset"],
+        keys: Keys::Range(1, 1, 1),
+        run: sorted_sets::zrank,
+    },
+    Command {
+        name: "zrevrank",
+        arity: -3,
+        flags: &["readonly", "fast"],
+        categories: &["sortedset"],
+        keys: Keys::Range(1, 1, 1),
+        run: sorted_sets::zrevrank,
+    },
+    Command {
+        name: "zrem",
+        arity: -3,
+        flags: &["write", "fast"],
+        categories: &["sortedset"],
+        keys: Keys::Range(1, 1, 1),
+        run: sorted_sets::zrem,
+    },
+    Command {
+        name: "zcount",
+        arity: 4,
+        flags: &["readonly", "fast"],
+        categories: &["sortedset"],
+        keys: Keys::Range(1, 1, 1),
+        run: sorted_sets::zcount,
+    },
+    Command {
+        name: "zremrangebyscore",
+        arity: 4,
+        flags: &["write"],
+        categories: &["sortedset"],
+        keys: Keys::Range(1, 1, 1),
+        run: sorted_sets::zremrangebyscore,
+    },
+    Command {
+        name: "zpopmin",
+        arity: -2,
+        flags: &["write", "fast"],
+        categories: &["sortedset"],
+        keys: Keys::Range(1, 1, 1),
+        run: sorted_sets::zpopmin,
+    },
+    Command {
+        name: "zpopmax",
+        arity: -2,
+        flags: &["write", "fast"],
+        categories: &["sortedset"],
+        keys: Keys::Range(1, 1, 1),
+        run: sorted_sets::zpopmax,
+    },
+    Command {
+        name: "bzpopmin",
+        arity: -3,
+        flags: &["write", "fast", "blocking"],
+        categories: &["sortedset"],
+        keys: Keys::Range(1, -2, 1),
+        run: sorted_sets::bzpopmin,
+    },
+    Command {
+        name: "bzpopmax",
+        arity: -3,
+        flags: &["write", "fast", "blocking"],
+        categories: &["sortedset"],
+        keys: Keys::Range(1, -2, 1),
+        run: sorted_sets::bzpopmax,
+    },
+    Command {
+        name: "setbit",
+        arity: 4,
+        flags: &["write", "denyoom"],
+        categories: &["bitmap"],
+        keys: Keys::Range(1, 1, 1),
+        run: bitmaps::setbit,
+    },
+    Command {
+        name: "getbit",
+        arity: 3,
+        flags: &["readonly", "fast"],
+        categories: &["bitmap"],
+        keys: Keys::Range(1, 1, 1),
+        run: bitmaps::getbit,
+    },
+    Command {
+        name: "bitcount",
+        arity: -2,
+        flags: &["readonly"],
+        categories: &["bitmap"],
+        keys: Keys::Range(1, 1, 1),
+        run: bitmaps::bitcount,
+    },
+    Command {
+        name: "bitpos",
+        arity: -3,
+        flags: &["readonly"],
+        categories: &["bitmap"],
+        keys: Keys::Range(1, 1, 1),
+        run: bitmaps::bitpos,
+    },
+    Command {
+        name: "bitop",
+        arity: -4,
+        flags: &["write", "denyoom"],
+        categories: &["bitmap"],
+        keys: Keys::Range(2, -1, 1),
+        run: bitmaps::bitop,
+    },
+    Command {
+        name: "pfadd",
+        arity: -2,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["hyperloglog"],
+        keys: Keys::Range(1, 1, 1),
+        run: hyperloglog::pfadd,
+    },
+    Command {
+        name: "pfcount",
+        arity: -2,
+        flags: &["readonly"],
+        categories: &["hyperloglog"],
+        keys: Keys::Range(1, -1, 1),
+        run: hyperloglog::pfcount,
+    },
+    Command {
+        name: "pfmerge",
+        arity: -2,
+        flags: &["write", "denyoom"],
+        categories: &["hyperloglog"],
+        keys: Keys::Range(1, -1, 1),
+        run: hyperloglog::pfmerge,
+    },
+    Command {
+        name: "xadd",
+        arity: -5,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["stream"],
+        keys: Keys::Range(1, 1, 1),
+        run: streams::xadd,
+    },
+    Command {
+        name: "xlen",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        categories: &["stream"],
+        keys: Keys::Range(1, 1, 1),
+        run: streams::xlen,
+    },
+    Command {
+        name: "xrange",
+        arity: -4,
+        flags: &["readonly"],
+        categories: &["stream"],
+        keys: Keys::Range(1, 1, 1),
+        run: streams::xrange,
+    },
+    Command {
+        name: "xrevrange",
+        arity: -4,
+        flags: &["readonly"],
+        categories: &["stream"],
+        keys: Keys::Range(1, 1, 1),
+        run: streams::xrevrange,
+    },
+    Command {
+        name: "xread",
+        arity: -4,
+        flags: &["readonly", "blocking"],
+        categories: &["stream"],
+        keys: Keys::Streams,
+        run: streams::xread,
+    },
+    Command {
+        name: "xgroup",
+        arity: -2,
+        flags: &["write"],
+        categories: &["stream"],
+        keys: Keys::Range(2, 2, 1),
+        run: streams::xgroup,
+    },
+    Command {
+        name: "xreadgroup",
+        arity: -7,
+        flags: &["write", "blocking"],
+        categories: &["stream"],
+        keys: Keys::Streams,
+        run: streams::xreadgroup,
+    },
+    Command {
+        name: "xack",
+        arity: -4,
+        flags: &["write", "fast"],
+        categories: &["stream"],
+        keys: Keys::Range(1, 1, 1),
+        run: streams::xack,
+    },
+    Command {
+        name: "xpending",
+        arity: -3,
+        flags: &["readonly"],
+        categories: &["stream"],
+        keys: Keys::Range(1, 1, 1),
+        run: streams::xpending,
+    },
+    Command {
+        name: "xclaim",
+        arity: -6,
+        flags: &["write", "fast"],
+        categories: &["stream"],
+        keys: Keys::Range(1, 1, 1),
+        run: streams::xclaim,
+    },
+    Command {
+        name: "subscribe",
+        arity: -2,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        categories: &[],
+        keys: Keys::None,
+        run: pubsub::subscribe,
+    },
+    Command {
+        name: "unsubscribe",
+        arity: -1,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        categories: &[],
+        keys: Keys::None,
+        run: pubsub::unsubscribe,
+    },
+    Command {
+        name: "psubscribe",
+        arity: -2,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        categories: &[],
+        keys: Keys::None,
+        run: pubsub::psubscribe,
+    },
+    Command {
+        name: "punsubscribe",
+        arity: -1,
+        flags: &["pubsub", "noscript", "loading", "stale"],
+        categories: &[],
+        keys: Keys::None,
+        run: pubsub::punsubscribe,
+    },
+    Command {
+        name: "pubsub",
+        arity: -2,
+        flags: &["pubsub", "loading", "stale"],
+        categories: &[],
+        keys: Keys::None,
+        run: pubsub::pubsub,
+    },
+    Command {
+        name: "publish",
+        arity: 3,
+        flags: &["pubsub", "loading", "stale", "fast"],
+        categories: &[],
+        keys: Keys::None,
+        run: pubsub::publish,
+    },
+    Command {
+        name: "quit",
+        arity: -1,
+        flags: &["noscript", "loading", "stale", "fast", "no_auth"],
+        categories: &["connection"],
+        keys: Keys::None,
+        run: quit,
+    },
+    Command {
+        name: "multi",
+        arity: 1,
+        flags: &["noscript", "loading", "stale", "fast"],
+        categories: &["transaction"],
+        keys: Keys::None,
+        run: transactions::multi,
+    },
+    Command {
+        name: "exec",
+        arity: 1,
+        flags: &["noscript", "loading", "stale"],
+        categories: &["transaction"],
+        keys: Keys::None,
+        run: transactions::exec,
+    },
+    Command {
+        name: "discard",
+        arity: 1,
+        flags: &["noscript", "loading", "stale", "fast"],
+        categories: &["transaction"],
+        keys: Keys::None,
+        run: transactions::discard,
+    },
+    Command {
+        name: "watch",
+        arity: -2,
+        flags: &["noscript", "loading", "stale", "fast"],
+        categories: &["transaction"],
+        keys: Keys::Range(1, -1, 1),
+        run: transactions::watch,
+    },
+    Command {
+        name: "unwatch",
+        arity: 1,
+        flags: &["noscript", "loading", "stale", "fast"],
+        categories: &["transaction"],
+        keys: Keys::None,
+        run: transactions::unwatch,
+    },
+    Command {
+        name: "eval",
+        arity: -3,
+        flags: &["noscript", "stale"],
+        categories: &["scripting"],
+        keys: Keys::Counted(2),
+        run: scripting::eval,
+    },
+    Command {
+        name: "evalsha",
+        arity: -3,
+        flags: &["noscript", "stale"],
+        categories: &["scripting"],
+        keys: Keys::Counted(2),
+        run: scripting::evalsha,
+    },
+    Command {
+        name: "script",
+        arity: -2,
+        flags: &["noscript"],
+        categories: &["scripting"],
+        keys: Keys::None,
+        run: scripting::script,
+    },
+    Command {
+        name: "auth",
+        arity: -2,
+        flags: &["noscript", "loading", "stale", "fast", "no_auth"],
+        categories: &["connection"],
+        keys: Keys::None,
+        run: acl::auth,
+    },
+    Command {
+        name: "acl",
+        arity: -2,
+        flags: &["noscript", "loading", "stale"],
+        categories: &[],
+        keys: Keys::None,
+        run: acl::acl,
+    },
+    Command {
+        name: "select",
+        arity: 2,
+        flags: &["loading", "stale", "fast"],
+        categories: &["connection"],
+        keys: Keys::None,
+        run: databases::select,
+    },
+    Command {
+        name: "swapdb",
+        arity: 3,
+        flags: &["write", "fast"],
+        categories: &["keyspace", "dangerous"],
+        keys: Keys::None,
+        run: databases::swapdb,
+    },
+    Command {
+        name: "move",
+        arity: 3,
+        flags: &["write", "fast"],
+        categories: &["keyspace"],
+        keys: Keys::Range(1, 1, 1),
+        run: databases::move_,
+    },
+    Command {
+        name: "flushdb",
+        arity: -1,
+        flags: &["write"],
+        categories: &["keyspace", "dangerous"],
+        keys: Keys::None,
+        run: databases::flushdb,
+    },
+    Command {
+        name: "flushall",
+        arity: -1,
+        flags: &["write"],
+        categories: &["keyspace", "dangerous"],
+        keys: Keys::None,
+        run: databases::flushall,
+    },
+    Command {
+        name: "dbsize",
+        arity: 1,
+        flags: &["readonly", "fast"],
+        categories: &["keyspace"],
+        keys: Keys::None,
+        run: databases::dbsize,
+    },
+    Command {
+        name: "memory",
+        arity: -2,
+        flags: &["readonly"],
+        categories: &[],
+        keys: Keys::Range(2, 2, 1),
+        run: memory::memory,
+    },
+    Command {
+        name: "object",
+        arity: -2,
+        flags: &["readonly"],
+        categories: &["keyspace"],
+        keys: Keys::Range(2, 2, 1),
+        run: object::object,
+    },
+    Command {
+        name: "info",
+        arity: -1,
+        flags: &["loading", "stale"],
+        categories: &["dangerous"],
+        keys: Keys::None,
+        run: info::info,
+    },
+    Command {
+        name: "command",
+        arity: -1,
+        flags: &["loading", "stale"],
+        categories: &["connection"],
+        keys: Keys::None,
+        run: command::command,
+    },
+    Command {
+        name: "client",
+        arity: -2,
+        flags: &["noscript", "loading", "stale"],
+        categories: &["connection"],
+        keys: Keys::None,
+        run: client::client,
+    },
+    Command {
+        name: "config",
+        arity: -2,
+        flags: &["admin", "noscript", "loading", "stale"],
+        categories: &[],
+        keys: Keys::None,
+        run: config::config,
+    },
+    Command {
+        name: "incr",
+        arity: 2,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["string"],
+        keys: Keys::Range(1, 1, 1),
+        run: strings::incr,
+    },
+    Command {
+        name: "decr",
+        arity: 2,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["string"],
+        keys: Keys::Range(1, 1, 1),
+        run: strings::decr,
+    },
+    Command {
+        name: "incrby",
+        arity: 3,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["string"],
+        keys: Keys::Range(1, 1, 1),
+        run: strings::incrby,
+    },
+    Command {
+        name: "decrby",
+        arity: 3,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["string"],
+        keys: Keys::Range(1, 1, 1),
+        run: strings::decrby,
+    },
+    Command {
+        name: "incrbyfloat",
+        arity: 3,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["string"],
+        keys: Keys::Range(1, 1, 1),
+        run: strings::incrbyfloat,
+    },
+    Command {
+        name: "getdel",
+        arity: 2,
+        flags: &["write", "fast"],
+        categories: &["string"],
+        keys: Keys::Range(1, 1, 1),
+        run: strings::getdel,
+    },
+    Command {
+        name: "getex",
+        arity: -2,
+        flags: &["write", "fast"],
+        categories: &["string"],
+        keys: Keys::Range(1, 1, 1),
+        run: strings::getex,
+    },
+    Command {
+        name: "append",
+        arity: 3,
+        flags: &["write", "denyoom", "fast"],
+        categories: &["string"],
+        keys: Keys::Range(1, 1, 1),
+        run: strings::append,
+    },
+    Command {
+        name: "strlen",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        categories: &["string"],
+        keys: Keys::Range(1, 1, 1),
+        run: strings::strlen,
+    },
+    Command {
+        name: "getrange",
+        arity: 4,
+        flags: &["readonly"],
+        categories: &["string"],
+        keys: Keys::Range(1, 1, 1),
+        run: strings::getrange,
+    },
+    Command {
+        name: "setrange",
+        arity: 4,
+        flags: &["write", "denyoom"],
+        categories: &["string"],
+        keys: Keys::Range(1, 1, 1),
+        run: strings::setrange,
+    },
+];
+
+// the commands `protocol::Cmd` parses, see `system_command` and
+// `action_command`, listed for what the table says about them
+const PARSED: &[Command] = &[
+    Command {
+        name: "get",
+        arity: 2,
+        flags: &["readonly", "fast"],
+        categories: &["string"],
+        keys: Keys::Range(1, 1, 1),
+        run: parsed,
+    },
+    Command {
+        name: "set",
+        arity: -3,
+        flags: &["write", "denyoom"],
+        categories: &["string"],
+        keys: Keys::Range(1, 1, 1),
+        run: parsed,
+    },
+    Command {
+        name: "del",
+        arity: -2,
+        flags: &["write"],
+        categories: &["keyspace"],
+        keys: Keys::Range(1, -1, 1),
+        run: parsed,
+    },
+    Command {
+        name: "unlink",
+        arity: -2,
+        flags: &["write", "fast"],
+        categories: &["keyspace"],
+        keys: Keys::Range(1, -1, 1),
+        run: parsed,
+    },
+    Command {
+        name: "exists",
+        arity: -2,
+        flags: &["readonly", "fast"],
+        categories: &["keyspace"],
+        keys: Keys::Range(1, -1, 1),
+        run: parsed,
+    },
+    Command {
+        name: "mget",
+        arity: -2,
+        flags: &["readonly", "fast"],
+        categories: &["string"],
+        keys: Keys::Range(1, -1, 1),
+        run: parsed,
+    },
+    Command {
+        name: "mset",
+        arity: -3,
+        flags: &["write", "denyoom"],
+        categories: &["string"],
+        keys: Keys::Range(1, -1, 2),
+        run: parsed,
+    },
+    Command {
+        name: "msetnx",
+        arity: -3,
+        flags: &["write", "denyoom"],
+        categories: &["string"],
+        keys: Keys::Range(1, -1, 2),
+        run: parsed,
+    },
+    Command {
+        name: "ping",
+        arity: -1,
+        flags: &["fast"],
+        categories: &["connection"],
+        keys: Keys::None,
+        run: parsed,
+    },
+    Command {
+        name: "echo",
+        arity: 2,
+        flags: &["fast"],
+        categories: &["connection"],
+        keys: Keys::None,
+        run: parsed,
+    },
+    Command {
+        name: "hello",
+        arity: -1,
+        flags: &["noscript", "loading", "stale", "fast", "no_auth"],
+        categories: &["connection"],
+        keys: Keys::None,
+        run: parsed,
+    },
+];
+
+// never called, `run` hands these to `protocol::Cmd`
+fn parsed(_: &mut Context<'_>, _: &[&[u8]]) -> Reply {
+    unreachable!("parsed by protocol::Cmd")
+}
+
+fn lookup(name: &[u8]) -> Option<&'static Command> {
+    COMMANDS
+        .iter()
+        .find(|command| name.eq_ignore_ascii_case(command.name.as_bytes()))
+}
+
+// every command the server knows, those `protocol::Cmd` parses included
+pub(crate) fn table() -> impl Iterator<Item = &'static Command> {
+    COMMANDS.iter().chain(PARSED)
+}
+
+// the table entry of a command, whichever way it is run
+pub(crate) fn spec(name: &[u8]) -> Option<&'static Command> {
+    table().find(|command| name.eq_ignore_ascii_case(command.name.as_bytes()))
+}
+
+pub(crate) fn ok() -> RedirsValue {
+    RedirsValue::SimpleString("OK".to_owned())
+}
+
+// an integer read the way redis does: an optional minus, no leading zeros,
+// nothing around the digits and no overflow
+pub(crate) fn parse_int(arg: &[u8]) -> Result<i64, Error> {
+    let digits = arg.strip_prefix(b"-").unwrap_or(arg);
+    let canonical = match digits {
+        [] => false,
+        [b'0'] => digits.len() == arg.len(),
+        [first, ..] => *first != b'0' && digits.iter().all(u8::is_ascii_digit),
+    };
+    canonical
+        .then(|| std::str::from_utf8(arg).ok()?.parse().ok())
+        .flatten()
+        .ok_or(Error::Command(CommandError::NotAnInteger))
+}
+
+// a float argument, a number that is neither infinite nor NaN
+pub(crate) fn parse_float(arg: &[u8]) -> Result<f64, Error> {
+    std::str::from_utf8(arg)
+        .ok()
+        .filter(|arg| !arg.starts_with(char::is_whitespace) && !arg.ends_with(char::is_whitespace))
+        .and_then(|arg| arg.parse::<f64>().ok())
+        .filter(|float| float.is_finite())
+        .ok_or_else(|| Error::Message("ERR value is not a valid float".to_owned()))
+}
+
+// the request as the arguments a client sent, the command name first
+fn args(request: &RedirsValue) -> Option<Vec<&[u8]>> {
+    let RedirsValue::Array(Some(arr)) = request else {
+        return None;
+    };
+    arr.iter()
+        .map(|arg| match arg {
+            RedirsValue::BulkString(Some(arg)) => Some(&arg[..]),
+            _ => None,
+        })
+        .collect()
+}
+
+// the commands running others with no command of another client in between
+const EXCLUSIVE: &[&str] = &["exec", "eval", "evalsha"];
+
+// the reply to a request, errors in the request are replies as well
+pub(crate) fn execute(request: &RedirsValue, client: &mut Client, db: &Db) -> Outcome {
+    if let Some(reply) = acl::unauthenticated(request, client) {
+        return Outcome::Reply(reply);
+    }
+    if let Some(reply) = subscribe_mode(request, client) {
+        return Outcome::Reply(reply);
+    }
+    if let Some(reply) = transactions::queue(request, client, db) {
+        return Outcome::Reply(reply);
+    }
+    // EXEC and scripts hold the keyspace for all the commands they run, any
+    // other command shares it
+    let exclusive = name(request).is_some_and(|name| {
+        EXCLUSIVE
+            .iter()
+            .any(|exclusive| name.eq_ignore_ascii_case(exclusive.as_bytes()))
+    });
+    let _shared = (!exclusive).then(|| db.shared());
+    let _exclusive = exclusive.then(|| db.exclusive());
+    run(request, client, db)
+}
+
+// the command name of a request
+pub(crate) fn name(request: &RedirsValue) -> Option<&[u8]> {
+    args(request)?.first().copied()
+}
+
+// checks what can be checked of a request without running it, the command
+// being known and its number of arguments, those `protocol::Cmd` parses being
+// parsed as well
+pub(crate) fn check(request: &RedirsValue) -> Result<(), Error> {
+    table_arity(request)?;
+    match name(request).and_then(lookup) {
+        Some(_) => Ok(()),
+        None => Cmd::try_from(request).map(|_| ()).map_err(Error::from),
+    }
+}
+
+// the number of arguments of a request checked against the arity in the
+// table, for every command it has
+fn table_arity(request: &RedirsValue) -> Result<(), Error> {
+    match args(request).and_then(|args| Some((spec(args.first()?)?, args))) {
+        Some((command, args)) => check_arity(command, &args),
+        None => Ok(()),
+    }
+}
+
+fn check_arity(command: &Command, args: &[&[u8]]) -> Result<(), Error> {
+    let arity = command.arity;
+    match arity >= 0 && args.len() as i32 == arity || arity < 0 && args.len() as i32 >= -arity {
+        true => Ok(()),
+        false => Err(CommandError::WrongArity(command.name).into()),
+    }
+}
+
+// runs a request, as `execute` does and EXEC does for each queued one
+fn run(request: &RedirsValue, client: &mut Client, db: &Db) -> Outcome {
+    db.stats().processed();
+    let db = db
+        .select(client.db)
+        .for_command(name(request).and_then(notify::command_event));
+    let mut context = Context {
+        client,
+        db: &db,
+        block: None,
+        replies: None,
+    };
+    let command = args(request).and_then(|args| Some((lookup(args.first()?)?, args)));
+    // checked again for EXEC, the permissions may have changed since the
+    // command was queued
+    let reply = acl::permitted(request, context.client, context.db)
+        .and_then(|()| out_of_memory(request, context.db))
+        .and_then(|()| match command {
+            Some((command, args)) => {
+                check_arity(command, &args).and_then(|()| (command.run)(&mut context, &args[1..]))
+            }
+            None => table_arity(request).and_then(|()| match Cmd::try_from(request) {
+                Ok(Cmd::System(system)) => system_command(system, &mut context),
+                Ok(Cmd::Action(action)) => action_command(action, context.db),
+                Err(e) => Err(e.into()),
+            }),
+        });
+    if reply.is_ok() {
+        track_reads(request, context.client, context.db);
+    }
+    match (reply, context.block, context.replies) {
+        (Ok(_), Some(block), _) => Outcome::Block(block),
+        (Ok(_), _, Some(replies)) => Outcome::Replies(replies),
+        (reply, _, _) => Outcome::Reply(reply.unwrap_or_else(|e| e.to_client_error())),
+    }
+}
+
+// the error for a command that may take more memory while the keys take more
+// than maxmemory and there is nothing left to evict, see `eviction`
+fn out_of_memory(request: &RedirsValue, db: &Db) -> Result<(), Error> {
+    let grows = args(request)
+        .and_then(|args| spec(args.first()?))
+        .is_some_and(|command| command.flags.contains(&"denyoom"));
+    match grows && !eviction::make_room(db) {
+        true => Err(Error::Message(
+            "OOM command not allowed when used memory > 'maxmemory'.".to_owned(),
+        )),
+        false => Ok(()),
+    }
+}
+
+// the keys a read only command read, for a connection tracking them in the
+// default mode
+fn track_reads(request: &RedirsValue, client: &Client, db: &Db) {
+    let default_mode = |tracking: &Tracking| tracking.prefixes.is_none();
+    if !client.tracking.as_ref().is_some_and(default_mode) {
+        return;
+    }
+    let Some(args) = args(request) else {
+        return;
+    };
+    match args.first().and_then(|name| spec(name)) {
+        Some(command) if command.flags.contains(&"readonly") => {
+            let keys = command.keys.of(&args);
+            db.tracker()
+                .read(client.id, &keys, db.clients(), db.pubsub());
+        }
+        _ => {}
+    }
+}
+
+// the commands a RESP2 connection subscribed to something is left with
+const SUBSCRIBE_MODE: &[&str] = &[
+    "subscribe",
+    "unsubscribe",
+    "psubscribe",
+    "punsubscribe",
+    "ping",
+    "quit",
+    "reset",
+];
+
+// the error for any other command in subscribe mode
+fn subscribe_mode(request: &RedirsValue, client: &Client) -> Option<RedirsValue> {
+    if !client.in_subscribe_mode() {
+        return None;
+    }
+    let name = args(request)?.first()?.to_ascii_lowercase();
+    if SUBSCRIBE_MODE
+        .iter()
+        .any(|allowed| allowed.as_bytes() == name)
+    {
+        return None;
+    }
+    Some(RedirsValue::SimpleError(format!(
+        "ERR Can't execute '{}': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context",
+        String::from_utf8_lossy(&name)
+    )))
+}
+
+// QUIT: OK, then the connection closes
+fn quit(context: &mut Context<'_>, _: &[&[u8]]) -> Reply {
+    context.client.quit = true;
+    Ok(ok())
+}
+
+fn system_command(system: System<'_>, context: &mut Context<'_>) -> Reply {
+    let client = &mut *context.client;
+    let reply = match system {
+        // in subscribe mode a PING is answered the way messages are
+        System::PING(message) if client.in_subscribe_mode() => RedirsValue::Array(Some(vec![
+            RedirsValue::from("pong"),
+            RedirsValue::from(message.unwrap_or_default()),
+        ])),
+        System::PING(None) => RedirsValue::SimpleString("PONG".to_owned()),
+        System::PING(Some(message)) | System::ECHO(message) => RedirsValue::from(message),
+        // the reply already speaks the negotiated protocol
+        // nothing changes unless the credentials are right
+        System::HELLO(hello) => {
+            if let Some((user, password)) = &hello.auth {
+                acl::login(client, context.db, user, password)?;
+            }
+            if let Some(name) = &hello.client_name {
+                self::client::check_name(name)?;
+            }
+            if let Some(proto) = hello.version {
+                client.proto = proto;
+            }
+            if let Some(name) = hello.client_name {
+                client.name = Some(name.into_owned());
+            }
+            HelloReply::new(client.proto, client.id).into_value()
+        }
+    };
+    Ok(reply)
+}
+
+fn action_command(action: Action<'_>, db: &Db) -> Reply {
+    match action {
+        Action::GET(key) => Ok(RedirsValue::from(db.lock().get_string(key)?.cloned())),
+        Action::SET((key, value, options)) => strings::set(db, key, value, options),
+        // nothing is freed in the background, UNLINK is DEL
+        Action::DEL(keys) | Action::UNLINK(keys) => {
+            let mut keyspace = db.lock();
+            let removed = keys
+                .iter()
+                .filter(|key| keyspace.remove(key).is_some())
+                .count();
+            Ok(RedirsValue::Integer(removed as i64))
+        }
+        // a key named twice counts twice
+        Action::EXISTS(keys) => {
+            let mut keyspace = db.lock();
+            let found = keys.iter().filter(|key| keyspace.exists(key)).count();
+            Ok(RedirsValue::Integer(found as i64))
+        }
+        // nil for the keys that are missing or hold something else
+        Action::MGET(keys) => {
+            let mut keyspace = db.lock();
+            let values = keys
+                .iter()
+                .map(|key| RedirsValue::from(keyspace.get_string(key).ok().flatten().cloned()))
+                .collect();
+            Ok(RedirsValue::Array(Some(values)))
+        }
+        Action::MSET(pairs) => {
+            let mut keyspace = db.lock();
+            for (key, value) in pairs {
+                keyspace.set(key.to_vec(), Value::String(value.to_vec()));
+            }
+            Ok(ok())
+        }
+        // all the keys or none of them
+        Action::MSETNX(pairs) => {
+            let mut keyspace = db.lock();
+            if pairs.iter().any(|(key, _)| keyspace.exists(key)) {
+                return Ok(RedirsValue::Integer(0));
+            }
+            for (key, value) in pairs {
+                keyspace.set(key.to_vec(), Value::String(value.to_vec()));
+            }
+            Ok(RedirsValue::Integer(1))
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use protocol::RedirsValue;
+
+use super::{Context, Error, Reply};
+
+// OBJECT ENCODING, REFCOUNT, IDLETIME and FREQ key, nil for a missing key.
+// Nothing is shared between keys so every value is referenced once, and access
+// frequencies are only shown under an LFU maxmemory-policy, as redis does
+pub(crate) fn object(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let subcommand = args[0].to_ascii_lowercase();
+    let lfu = context.db.eviction().policy().is_lfu();
+    let mut keyspace = context.db.lock();
+    match (subcommand.as_slice(), &args[1..]) {
+        (b"encoding", [key]) => Ok(keyspace.peek(key).map_or(RedirsValue::Null, |value| {
+            RedirsValue::from(value.encoding())
+        })),
+        (b"refcount", [key]) => Ok(keyspace
+            .peek(key)
+            .map_or(RedirsValue::Null, |_| RedirsValue::Integer(1))),
+        (b"idletime", [key]) => Ok(keyspace.idle_time(key).map_or(RedirsValue::Null, |idle| {
+            RedirsValue::Integer((idle / 1000) as i64)
+        })),
+        (b"freq", [key]) => match keyspace.frequency(key) {
+            None => Ok(RedirsValue::Null),
+            Some(frequency) if lfu => Ok(RedirsValue::Integer(frequency as i64)),
+            Some(_) => Err(Error::Message(
+                "ERR An LFU maxmemory policy is not selected, access frequency not tracked. \
+                 Please note that when switching between policies at runtime LRU and LFU data \
+                 will take some time to adjust."
+                    .to_owned(),
+            )),
+        },
+        (b"encoding" | b"refcount" | b"idletime" | b"freq", _) => Err(Error::Message(format!(
+            "ERR wrong number of arguments for 'object|{}' command",
+            String::from_utf8_lossy(&subcommand)
+        ))),
+        _ => Err(Error::Message(format!(
+            "ERR unknown subcommand '{}'. Try OBJECT HELP.",
+            String::from_utf8_lossy(args[0])
+        ))),
+    }
+}
@@ -0,0 +1,128 @@
+use protocol::{CommandError, RedirsValue};
+
+use super::{Context, Error, Reply};
+use crate::pubsub::Kind;
+
+// [kind, channel, count] as the subscribe commands confirm each channel or
+// pattern, the count being the subscriptions the connection is left with
+fn confirmation(kind: &str, name: Option<&[u8]>, count: usize) -> RedirsValue {
+    RedirsValue::Push(vec![
+        RedirsValue::from(kind),
+        RedirsValue::from(name.map(<[u8]>::to_vec)),
+        RedirsValue::Integer(count as i64),
+    ])
+}
+
+// a confirmation for each of `names`, the messages then come in between
+// replies
+fn subscribe_to(context: &mut Context<'_>, kind: Kind, names: &[&[u8]], reply: &str) -> Reply {
+    let client = &mut *context.client;
+    let replies = names
+        .iter()
+        .map(|name| {
+            if client.subscriptions.of(kind).insert(name.to_vec()) {
+                context
+                    .db
+                    .pubsub()
+                    .subscribe(kind, name, client.id, &client.mailbox);
+            }
+            confirmation(reply, Some(name), client.subscriptions.len())
+        })
+        .collect();
+    context.replies = Some(replies);
+    Ok(RedirsValue::Null)
+}
+
+// a confirmation for each of `names`, for every subscription of the kind
+// without any. With none of them a single one with a nil name
+fn unsubscribe_from(context: &mut Context<'_>, kind: Kind, names: &[&[u8]], reply: &str) -> Reply {
+    let client = &mut *context.client;
+    let names: Vec<Vec<u8>> = match names {
+        [] => client.subscriptions.of(kind).iter().cloned().collect(),
+        names => names.iter().map(|name| name.to_vec()).collect(),
+    };
+    let mut replies: Vec<_> = names
+        .into_iter()
+        .map(|name| {
+            if client.subscriptions.of(kind).remove(&name) {
+                context.db.pubsub().unsubscribe(kind, &name, client.id);
+            }
+            confirmation(reply, Some(&name), client.subscriptions.len())
+        })
+        .collect();
+    if replies.is_empty() {
+        replies.push(confirmation(reply, None, client.subscriptions.len()));
+    }
+    context.replies = Some(replies);
+    Ok(RedirsValue::Null)
+}
+
+// SUBSCRIBE channel [channel ...]
+pub(crate) fn subscribe(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    subscribe_to(context, Kind::Channel, args, "subscribe")
+}
+
+// UNSUBSCRIBE [channel [channel ...]]
+pub(crate) fn unsubscribe(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    unsubscribe_from(context, Kind::Channel, args, "unsubscribe")
+}
+
+// PSUBSCRIBE pattern [pattern ...]: the channels matching a pattern, the
+// messages on them coming as [pmessage, pattern, channel, payload]
+pub(crate) fn psubscribe(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    subscribe_to(context, Kind::Pattern, args, "psubscribe")
+}
+
+// PUNSUBSCRIBE [pattern [pattern ...]]
+pub(crate) fn punsubscribe(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    unsubscribe_from(context, Kind::Pattern, args, "punsubscribe")
+}
+
+// PUBLISH channel message: how many subscriptions it was handed to
+pub(crate) fn publish(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let receivers = context.db.pubsub().publish(args[0], args[1]);
+    Ok(RedirsValue::Integer(receivers as i64))
+}
+
+// PUBSUB CHANNELS [pattern], PUBSUB NUMSUB [channel ...] and PUBSUB NUMPAT.
+// There are no shard channels, SHARDCHANNELS and SHARDNUMSUB find none
+pub(crate) fn pubsub(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let pubsub = context.db.pubsub();
+    let subcommand = args[0].to_ascii_lowercase();
+    let rest = &args[1..];
+    let arity_error = |name| Err(CommandError::WrongArity(name).into());
+    match subcommand.as_slice() {
+        b"channels" if rest.len() > 1 => arity_error("pubsub|channels"),
+        b"shardchannels" if rest.len() > 1 => arity_error("pubsub|shardchannels"),
+        b"channels" => Ok(RedirsValue::Array(Some(
+            pubsub
+                .channels(rest.first().copied())
+                .into_iter()
+                .map(RedirsValue::from)
+                .collect(),
+        ))),
+        b"numsub" => Ok(RedirsValue::Array(Some(
+            rest.iter()
+                .zip(pubsub.numsub(rest))
+                .flat_map(|(channel, count)| {
+                    [
+                        RedirsValue::from(*channel),
+                        RedirsValue::Integer(count as i64),
+                    ]
+                })
+                .collect(),
+        ))),
+        b"numpat" if !rest.is_empty() => arity_error("pubsub|numpat"),
+        b"numpat" => Ok(RedirsValue::Integer(pubsub.numpat() as i64)),
+        b"shardchannels" => Ok(RedirsValue::Array(Some(Vec::new()))),
+        b"shardnumsub" => Ok(RedirsValue::Array(Some(
+            rest.iter()
+                .flat_map(|channel| [RedirsValue::from(*channel), RedirsValue::Integer(0)])
+                .collect(),
+        ))),
+        _ => Err(Error::Message(format!(
+            "ERR unknown subcommand '{}'. Try PUBSUB HELP.",
+            String::from_utf8_lossy(args[0])
+        ))),
+    }
+}
@@ -0,0 +1,87 @@
+use protocol::RedirsValue;
+
+use super::{ok, parse_int, Context, Error, Reply};
+
+// what EVAL and EVALSHA reply once the arguments check out in a build without
+// the `lua` feature, there being no interpreter to run the script with
+#[cfg(not(feature = "lua"))]
+fn run(_: &mut Context<'_>, _: &[u8], _: &[&[u8]], _: &[&[u8]]) -> Reply {
+    Err(Error::Message(
+        "ERR scripting is not available, this server was built without Lua".to_owned(),
+    ))
+}
+
+#[cfg(feature = "lua")]
+use super::lua::run;
+
+// numkeys key [key ...] arg [arg ...]: how many of them are keys
+fn numkeys(args: &[&[u8]]) -> Result<usize, Error> {
+    let numkeys = parse_int(args[0])?;
+    if numkeys < 0 {
+        return Err(Error::Message(
+            "ERR Number of keys can't be negative".to_owned(),
+        ));
+    }
+    if numkeys as usize > args.len() - 1 {
+        return Err(Error::Message(
+            "ERR Number of keys can't be greater than number of args".to_owned(),
+        ));
+    }
+    Ok(numkeys as usize)
+}
+
+// EVAL script numkeys [key ...] [arg ...]: the script is kept as SCRIPT LOAD
+// keeps it, then run with nothing else in between
+pub(crate) fn eval(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let numkeys = numkeys(&args[1..])?;
+    context.db.scripts().load(args[0]);
+    let (keys, rest) = args[2..].split_at(numkeys);
+    run(context, args[0], keys, rest)
+}
+
+// EVALSHA sha1 numkeys [key ...] [arg ...]
+pub(crate) fn evalsha(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let numkeys = numkeys(&args[1..])?;
+    let Some(script) = context.db.scripts().get(args[0]) else {
+        return Err(Error::Message(
+            "NOSCRIPT No matching script. Please use EVAL.".to_owned(),
+        ));
+    };
+    let (keys, rest) = args[2..].split_at(numkeys);
+    run(context, &script, keys, rest)
+}
+
+// SCRIPT LOAD script, SCRIPT EXISTS sha1 [sha1 ...] and SCRIPT FLUSH
+// [ASYNC|SYNC]
+pub(crate) fn script(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let scripts = context.db.scripts();
+    match (args[0].to_ascii_lowercase().as_slice(), &args[1..]) {
+        (b"load", [script]) => Ok(RedirsValue::from(scripts.load(script).into_bytes())),
+        (b"exists", shas) if !shas.is_empty() => Ok(RedirsValue::Array(Some(
+            shas.iter()
+                .map(|sha| RedirsValue::Integer(scripts.get(sha).is_some() as i64))
+                .collect(),
+        ))),
+        (b"flush", []) => {
+            scripts.flush();
+            Ok(ok())
+        }
+        (b"flush", [mode])
+            if mode.eq_ignore_ascii_case(b"async") || mode.eq_ignore_ascii_case(b"sync") =>
+        {
+            scripts.flush();
+            Ok(ok())
+        }
+        (b"flush", [_]) => Err(Error::Message(
+            "ERR SCRIPT FLUSH only support SYNC|ASYNC option".to_owned(),
+        )),
+        (b"load", _) | (b"exists", _) | (b"flush", _) => Err(Error::Message(format!(
+            "ERR wrong number of arguments for 'script|{}' command",
+            String::from_utf8_lossy(&args[0].to_ascii_lowercase())
+        ))),
+        _ => Err(Error::Message(format!(
+            "ERR unknown subcommand '{}'. Try SCRIPT HELP.",
+            String::from_utf8_lossy(args[0])
+        ))),
+    }
+}
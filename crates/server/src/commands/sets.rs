@@ -0,0 +1,275 @@
+use std::collections::HashSet;
+
+use indexmap::IndexSet;
+use rand::{seq::index, Rng};
+
+use protocol::{CommandError, RedirsValue};
+
+use super::{parse_int, Context, Error, Reply};
+use crate::db::Value;
+
+// the number of members that were not there before, a member given twice
+// counts once
+pub(crate) fn sadd(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let mut keyspace = context.db.lock();
+    let set = keyspace.get_or_create_set(args[0])?;
+    let added = args[1..]
+        .iter()
+        .filter(|member| set.insert(member.to_vec()))
+        .count();
+    Ok(RedirsValue::Integer(added as i64))
+}
+
+// removing the last member removes the key
+pub(crate) fn srem(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let key = args[0];
+    let mut keyspace = context.db.lock();
+    let Some(set) = keyspace.get_set_mut(key)? else {
+        return Ok(RedirsValue::Integer(0));
+    };
+    let removed = args[1..]
+        .iter()
+        .filter(|member| set.swap_remove(**member))
+        .count();
+    keyspace.remove_if_empty(key);
+    Ok(RedirsValue::Integer(removed as i64))
+}
+
+// a set, which RESP2 clients get as an array
+pub(crate) fn smembers(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let mut keyspace = context.db.lock();
+    let members = keyspace
+        .get_set(args[0])?
+        .into_iter()
+        .flatten()
+        .map(|member| RedirsValue::from(member.clone()))
+        .collect();
+    Ok(RedirsValue::Set(members))
+}
+
+pub(crate) fn sismember(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let mut keyspace = context.db.lock();
+    let found = keyspace
+        .get_set(args[0])?
+        .is_some_and(|set| set.contains(args[1]));
+    Ok(RedirsValue::Integer(found as i64))
+}
+
+// 0 or 1 for each member in the order given
+pub(crate) fn smismember(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let mut keyspace = context.db.lock();
+    let set = keyspace.get_set(args[0])?;
+    let found = args[1..]
+        .iter()
+        .map(|member| RedirsValue::Integer(set.is_some_and(|set| set.contains(*member)) as i64))
+        .collect();
+    Ok(RedirsValue::Array(Some(found)))
+}
+
+pub(crate) fn scard(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let len = context.db.lock().get_set(args[0])?.map_or(0, IndexSet::len);
+    Ok(RedirsValue::Integer(len as i64))
+}
+
+// how SINTER, SUNION and SDIFF combine their sets
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Operation {
+    Inter,
+    Union,
+    Diff,
+}
+
+type Members<'a> = HashSet<&'a Vec<u8>>;
+
+// the members of `sets` that are in all of them. The smallest is walked and
+// the others probed, up to `limit` members. A missing set is an empty one, so
+// the result is empty without looking at the members
+fn inter<'a>(sets: &[Option<&'a IndexSet<Vec<u8>>>], limit: usize) -> Members<'a> {
+    let Some(mut sets) = sets.iter().copied().collect::<Option<Vec<_>>>() else {
+        return HashSet::new();
+    };
+    sets.sort_by_key(|set| set.len());
+    let (smallest, others) = sets.split_first().expect("at least one key");
+    smallest
+        .iter()
+        .filter(|member| others.iter().all(|set| set.contains(*member)))
+        .take(limit)
+        .collect()
+}
+
+fn combine<'a>(operation: Operation, sets: &[Option<&'a IndexSet<Vec<u8>>>]) -> Members<'a> {
+    match (operation, sets) {
+        (Operation::Inter, _) => inter(sets, usize::MAX),
+        (Operation::Union, _) => sets.iter().flatten().flat_map(|set| set.iter()).collect(),
+        // the members of the first set in none of the others
+        (Operation::Diff, [Some(first), others @ ..]) => first
+            .iter()
+            .filter(|member| !others.iter().flatten().any(|set| set.contains(*member)))
+            .collect(),
+        (Operation::Diff, _) => HashSet::new(),
+    }
+}
+
+// the members as a set, which RESP2 clients get as an array
+fn algebra(context: &mut Context<'_>, keys: &[&[u8]], operation: Operation) -> Reply {
+    let mut keyspace = context.db.lock();
+    let sets = keyspace.get_sets(keys)?;
+    let members = combine(operation, &sets)
+        .into_iter()
+        .map(|member| RedirsValue::from(member.clone()))
+        .collect();
+    Ok(RedirsValue::Set(members))
+}
+
+pub(crate) fn sinter(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    algebra(context, args, Operation::Inter)
+}
+
+pub(crate) fn sunion(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    algebra(context, args, Operation::Union)
+}
+
+pub(crate) fn sdiff(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    algebra(context, args, Operation::Diff)
+}
+
+// the result replaces whatever the destination held, its deadline included,
+// and an empty one removes it. Replies with the size of the result
+fn algebra_store(context: &mut Context<'_>, args: &[&[u8]], operation: Operation) -> Reply {
+    let (destination, keys) = (args[0], &args[1..]);
+    let mut keyspace = context.db.lock();
+    let sets = keyspace.get_sets(keys)?;
+    let members: IndexSet<Vec<u8>> = combine(operation, &sets).into_iter().cloned().collect();
+    let len = members.len();
+    match len {
+        0 => {
+            keyspace.remove(destination);
+        }
+        _ => {
+            keyspace.set(destination.to_vec(), Value::Set(members));
+        }
+    }
+    Ok(RedirsValue::Integer(len as i64))
+}
+
+pub(crate) fn sinterstore(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    algebra_store(context, args, Operation::Inter)
+}
+
+pub(crate) fn sunionstore(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    algebra_store(context, args, Operation::Union)
+}
+
+pub(crate) fn sdiffstore(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    algebra_store(context, args, Operation::Diff)
+}
+
+// SINTERCARD numkeys key [key ...] [LIMIT limit]: the size of the
+// intersection, counting stops at limit unless it is 0
+pub(crate) fn sintercard(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let numkeys = parse_int(args[0])?;
+    if numkeys <= 0 {
+        return Err(Error::Message(
+            "ERR numkeys should be greater than 0".to_owned(),
+        ));
+    }
+    let Some((keys, opts)) = args[1..].split_at_checked(numkeys as usize) else {
+        return Err(Error::Message(
+            "ERR Number of keys can't be greater than number of args".to_owned(),
+        ));
+    };
+    let limit = match opts {
+        [] => 0,
+        [opt, limit] if opt.eq_ignore_ascii_case(b"limit") => match parse_int(limit)? {
+            ..0 => return Err(Error::Message("ERR LIMIT can't be negative".to_owned())),
+            limit => limit as usize,
+        },
+        _ => return Err(CommandError::SyntaxError.into()),
+    };
+    let mut keyspace = context.db.lock();
+    let sets = keyspace.get_sets(keys)?;
+    let limit = match limit {
+        0 => usize::MAX,
+        limit => limit,
+    };
+    Ok(RedirsValue::Integer(inter(&sets, limit).len() as i64))
+}
+
+// SPOP key [count]: one random member or nil without a count, a set of up to
+// count distinct ones with it. Each pick is an O(1) swap removal, a count past
+// the size takes the whole set at once
+pub(crate) fn spop(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let count = match args {
+        [_] => None,
+        [_, count] => match parse_int(count) {
+            Ok(count) if count >= 0 => Some(count as usize),
+            _ => {
+                return Err(Error::Message(
+                    "ERR value is out of range, must be positive".to_owned(),
+                ))
+            }
+        },
+        _ => return Err(CommandError::SyntaxError.into()),
+    };
+    let key = args[0];
+    let mut keyspace = context.db.lock();
+    let mut rng = rand::rng();
+    let Some(set) = keyspace.get_set_mut(key)? else {
+        return Ok(match count {
+            Some(_) => RedirsValue::Set(Default::default()),
+            None => RedirsValue::BulkString(None),
+        });
+    };
+    let reply = match count {
+        None => {
+            let member = set.swap_remove_index(rng.random_range(0..set.len()));
+            RedirsValue::from(member)
+        }
+        Some(count) if count >= set.len() => {
+            RedirsValue::Set(set.drain(..).map(RedirsValue::from).collect())
+        }
+        Some(count) => RedirsValue::Set(
+            (0..count)
+                .filter_map(|_| set.swap_remove_index(rng.random_range(0..set.len())))
+                .map(RedirsValue::from)
+                .collect(),
+        ),
+    };
+    keyspace.remove_if_empty(key);
+    Ok(reply)
+}
+
+// SRANDMEMBER key [count]: like SPOP without removing, except that a negative
+// count returns exactly -count members with repeats allowed
+pub(crate) fn srandmember(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let count = match args {
+        [_] => None,
+        [_, count] => match parse_int(count)? {
+            i64::MIN => return Err(Error::Message("ERR value is out of range".to_owned())),
+            count => Some(count),
+        },
+        _ => return Err(CommandError::SyntaxError.into()),
+    };
+    let mut keyspace = context.db.lock();
+    let set = keyspace.get_set(args[0])?;
+    let len = set.map_or(0, IndexSet::len);
+    let mut rng = rand::rng();
+    let pick = |at: usize| RedirsValue::from(set.expect("not empty")[at].clone());
+    Ok(match count {
+        None if len == 0 => RedirsValue::BulkString(None),
+        None => pick(rng.random_range(0..len)),
+        Some(_) if len == 0 => RedirsValue::Array(Some(Vec::new())),
+        Some(count) if count < 0 => RedirsValue::Array(Some(
+            (0..count.unsigned_abs())
+                .map(|_| pick(rng.random_range(0..len)))
+                .collect(),
+        )),
+        // distinct positions, sampled in O(count) for a small count
+        Some(count) => RedirsValue::Array(Some(
+            index::sample(&mut rng, len, (count as usize).min(len))
+                .into_iter()
+                .map(pick)
+                .collect(),
+        )),
+    })
+}
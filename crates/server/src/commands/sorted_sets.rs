@@ -0,0 +1,451 @@
+use protocol::{CommandError, ProcVersion, RedirsValue};
+
+use super::{lists, parse_int, Context, Error, Reply};
+use crate::{
+    blocking::{Block, End, Pop},
+    sorted_set::{parse_score, LexRange, ScoreRange, SortedSet},
+};
+
+fn not_a_float() -> Error {
+    Error::Message("ERR value is not a valid float".to_owned())
+}
+
+// ZADD key [NX|XX] [GT|LT] [CH] [INCR] score member [score member ...]: the
+// number of members added, or added and updated with CH. GT and LT update a
+// member only to a higher or lower score, new members are added either way.
+// INCR adds to the score of a single member and replies with the new score,
+// or nil when a condition stopped it
+pub(crate) fn zadd(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let key = args[0];
+    let (mut nx, mut xx, mut gt, mut lt, mut ch, mut incr) = Default::default();
+    let mut at = 1;
+    while let Some(flag) = args.get(at) {
+        let flag = match flag.to_ascii_lowercase().as_slice() {
+            b"nx" => &mut nx,
+            b"xx" => &mut xx,
+            b"gt" => &mut gt,
+            b"lt" => &mut lt,
+            b"ch" => &mut ch,
+            b"incr" => &mut incr,
+            _ => break,
+        };
+        *flag = true;
+        at += 1;
+    }
+    if nx && xx {
+        return Err(Error::Message(
+            "ERR XX and NX options at the same time are not compatible".to_owned(),
+        ));
+    }
+    if gt && lt || (gt || lt) && nx {
+        return Err(Error::Message(
+            "ERR GT, LT, and/or NX options at the same time are not compatible".to_owned(),
+        ));
+    }
+    let pairs = &args[at..];
+    if pairs.is_empty() || !pairs.len().is_multiple_of(2) {
+        return Err(CommandError::SyntaxError.into());
+    }
+    if incr && pairs.len() > 2 {
+        return Err(Error::Message(
+            "ERR INCR option supports a single increment-element pair".to_owned(),
+        ));
+    }
+    // nothing changes unless every score is one
+    let pairs = pairs
+        .chunks(2)
+        .map(|pair| Ok((parse_score(pair[0]).ok_or_else(not_a_float)?, pair[1])))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let mut keyspace = context.db.lock();
+    if xx && keyspace.get_sorted_set(key)?.is_none() {
+        return Ok(match incr {
+            true => RedirsValue::BulkString(None),
+            false => RedirsValue::Integer(0),
+        });
+    }
+    let zset = keyspace.get_or_create_sorted_set(key)?;
+    let (mut added, mut updated) = (0, 0);
+    let mut incremented = None;
+    for (score, member) in pairs {
+        let old = zset.score(member);
+        let score = match (incr, old) {
+            (true, Some(old)) => old + score,
+            _ => score,
+        };
+        if score.is_nan() {
+            return Err(Error::Message(
+                "ERR resulting score is not a number (NaN)".to_owned(),
+            ));
+        }
+        let allowed = match old {
+            None => !xx,
+            Some(old) => !(nx || gt && score <= old || lt && score >= old),
+        };
+        if !allowed {
+            continue;
+        }
+        match old {
+            None => added += 1,
+            Some(old) if old != score => updated += 1,
+            Some(_) => {}
+        }
+        zset.insert(member.to_vec(), score);
+        incremented = Some(score);
+    }
+    keyspace.serve_waiters(key);
+    Ok(match incr {
+        true => incremented.map_or(RedirsValue::BulkString(None), RedirsValue::Double),
+        false => RedirsValue::Integer(added + if ch { updated } else { 0 }),
+    })
+}
+
+pub(crate) fn zscore(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let mut keyspace = context.db.lock();
+    let score = keyspace
+        .get_sorted_set(args[0])?
+        .and_then(|zset| zset.score(args[1]));
+    Ok(score.map_or(RedirsValue::BulkString(None), RedirsValue::Double))
+}
+
+pub(crate) fn zcard(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let len = context
+        .db
+        .lock()
+        .get_sorted_set(args[0])?
+        .map_or(0, SortedSet::len);
+    Ok(RedirsValue::Integer(len as i64))
+}
+
+// ZINCRBY key increment member: the new score, a missing member starting
+// from 0
+pub(crate) fn zincrby(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, member) = (args[0], args[2]);
+    let increment = parse_score(args[1]).ok_or_else(not_a_float)?;
+    let mut keyspace = context.db.lock();
+    let zset = keyspace.get_or_create_sorted_set(key)?;
+    let score = zset.score(member).unwrap_or(0.0) + increment;
+    if score.is_nan() {
+        // only an infinity added to its opposite, so the member is there and
+        // the key is not left empty
+        return Err(Error::Message(
+            "ERR resulting score is not a number (NaN)".to_owned(),
+        ));
+    }
+    zset.insert(member.to_vec(), score);
+    keyspace.serve_waiters(key);
+    Ok(RedirsValue::Double(score))
+}
+
+// ZRANK key member [WITHSCORE]: the rank from the lowest score, or from the
+// highest one for ZREVRANK, followed by the score with WITHSCORE
+fn rank(context: &mut Context<'_>, args: &[&[u8]], rev: bool, name: &'static str) -> Reply {
+    let withscore = match args {
+        [_, _] => false,
+        [_, _, opt] if opt.eq_ignore_ascii_case(b"withscore") => true,
+        [_, _, _] => return Err(CommandError::SyntaxError.into()),
+        _ => return Err(CommandError::WrongArity(name).into()),
+    };
+    let (key, member) = (args[0], args[1]);
+    let mut keyspace = context.db.lock();
+    let found = keyspace.get_sorted_set(key)?.and_then(|zset| {
+        let rank = zset.rank(member)?;
+        let rank = match rev {
+            true => zset.len() - 1 - rank,
+            false => rank,
+        };
+        Some((rank, zset.score(member)?))
+    });
+    Ok(match (found, withscore) {
+        (Some((rank, score)), true) => RedirsValue::Array(Some(vec![
+            RedirsValue::Integer(rank as i64),
+            RedirsValue::Double(score),
+        ])),
+        (Some((rank, _)), false) => RedirsValue::Integer(rank as i64),
+        (None, true) => RedirsValue::Array(None),
+        (None, false) => RedirsValue::BulkString(None),
+    })
+}
+
+pub(crate) fn zrank(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    rank(context, args, false, "zrank")
+}
+
+pub(crate) fn zrevrank(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    rank(context, args, true, "zrevrank")
+}
+
+// ZREM key member [member ...]: how many of the members were there
+pub(crate) fn zrem(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let key = args[0];
+    let mut keyspace = context.db.lock();
+    let Some(zset) = keyspace.get_sorted_set_mut(key)? else {
+        return Ok(RedirsValue::Integer(0));
+    };
+    let removed = args[1..]
+        .iter()
+        .filter(|member| zset.remove(member).is_some())
+        .count();
+    keyspace.remove_if_empty(key);
+    Ok(RedirsValue::Integer(removed as i64))
+}
+
+// ZCOUNT key min max: how many members have a score in the range
+pub(crate) fn zcount(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let scores = score_range(args[1], args[2])?;
+    let mut keyspace = context.db.lock();
+    let count = keyspace.get_sorted_set(args[0])?.map_or(0, |zset| {
+        let (start, end) = zset.score_ranks(&scores);
+        end - start
+    });
+    Ok(RedirsValue::Integer(count as i64))
+}
+
+// ZREMRANGEBYSCORE key min max: how many members went
+pub(crate) fn zremrangebyscore(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let key = args[0];
+    let scores = score_range(args[1], args[2])?;
+    let mut keyspace = context.db.lock();
+    let Some(zset) = keyspace.get_sorted_set_mut(key)? else {
+        return Ok(RedirsValue::Integer(0));
+    };
+    let (start, end) = zset.score_ranks(&scores);
+    let removed = zset.remove_ranks(start, end);
+    keyspace.remove_if_empty(key);
+    Ok(RedirsValue::Integer(removed as i64))
+}
+
+// what a range selects members by
+enum By {
+    Rank(i64, i64),
+    Score(ScoreRange),
+    Lex(LexRange),
+}
+
+// a parsed ZRANGE, or ZRANGEBYSCORE read as one
+struct Range {
+    by: By,
+    rev: bool,
+    // how many of the selected members to skip, then how many to return at
+    // most, none for all of them
+    limit: Option<(i64, i64)>,
+    withscores: bool,
+}
+
+fn score_range(min: &[u8], max: &[u8]) -> Result<ScoreRange, Error> {
+    ScoreRange::parse(min, max)
+        .ok_or_else(|| Error::Message("ERR min or max is not a float".to_owned()))
+}
+
+fn lex_range(min: &[u8], max: &[u8]) -> Result<LexRange, Error> {
+    LexRange::parse(min, max)
+        .ok_or_else(|| Error::Message("ERR min or max not valid string range item".to_owned()))
+}
+
+// ZRANGE key start stop [BYSCORE|BYLEX] [REV] [LIMIT offset count]
+// [WITHSCORES]: start and stop are ranks, scores or members, the highest
+// first with REV. ZRANGEBYSCORE is read with `by_score` already set
+fn parse_range(args: &[&[u8]], mut by_score: bool) -> Result<Range, Error> {
+    let (mut by_lex, mut rev, mut limit, mut withscores) = (false, false, None, false);
+    let mut opts = &args[3..];
+    while let [opt, rest @ ..] = opts {
+        opts = match (opt.to_ascii_lowercase().as_slice(), rest) {
+            (b"byscore", rest) => {
+                by_score = true;
+                rest
+            }
+            (b"bylex", rest) => {
+                by_lex = true;
+                rest
+            }
+            (b"rev", rest) => {
+                rev = true;
+                rest
+            }
+            (b"withscores", rest) => {
+                withscores = true;
+                rest
+            }
+            (b"limit", [offset, count, rest @ ..]) => {
+                limit = Some((parse_int(offset)?, parse_int(count)?));
+                rest
+            }
+            _ => return Err(CommandError::SyntaxError.into()),
+        };
+    }
+    let (start, stop) = match rev {
+        true => (args[2], args[1]),
+        false => (args[1], args[2]),
+    };
+    let by = match (by_score, by_lex) {
+        (true, true) => return Err(CommandError::SyntaxError.into()),
+        (true, false) => By::Score(score_range(start, stop)?),
+        (false, true) => By::Lex(lex_range(start, stop)?),
+        (false, false) => By::Rank(parse_int(args[1])?, parse_int(args[2])?),
+    };
+    if limit.is_some() && matches!(by, By::Rank(..)) {
+        return Err(Error::Message(
+            "ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                .to_owned(),
+        ));
+    }
+    if withscores && matches!(by, By::Lex(_)) {
+        return Err(Error::Message(
+            "ERR syntax error, WITHSCORES not supported in combination with BYLEX".to_owned(),
+        ));
+    }
+    Ok(Range {
+        by,
+        rev,
+        limit,
+        withscores,
+    })
+}
+
+// the members the range selects with their scores, in reply order
+fn select<'a>(zset: &'a SortedSet, range: &Range) -> Vec<(&'a [u8], f64)> {
+    let len = zset.len();
+    // the selected ranks in ascending order, the end excluded
+    let (start, end) = match &range.by {
+        By::Rank(start, stop) => match lists::range(*start, *stop, len) {
+            Some((start, stop)) if range.rev => (len - 1 - stop, len - start),
+            Some((start, stop)) => (start, stop + 1),
+            None => (0, 0),
+        },
+        By::Score(scores) => zset.score_ranks(scores),
+        By::Lex(members) => zset.lex_ranks(members),
+    };
+    let (offset, count) = match range.limit {
+        Some((offset, _)) if offset < 0 => return Vec::new(),
+        Some((offset, count)) if count >= 0 => (offset as usize, count as usize),
+        Some((offset, _)) => (offset as usize, usize::MAX),
+        None => (0, usize::MAX),
+    };
+    let selected = (end - start).saturating_sub(offset).min(count);
+    match (selected, range.rev) {
+        (0, _) => Vec::new(),
+        (_, true) => zset
+            .iter_rev_from(end - 1 - offset)
+            .take(selected)
+            .collect(),
+        (_, false) => zset.iter_from(start + offset).take(selected).collect(),
+    }
+}
+
+// members alone, or each followed by its score: flat over RESP2 and as pairs
+// over RESP3
+fn members_reply(members: Vec<(&[u8], f64)>, withscores: bool, proto: ProcVersion) -> RedirsValue {
+    let items = members.into_iter().map(|(member, score)| {
+        (
+            RedirsValue::from(member.to_vec()),
+            RedirsValue::Double(score),
+        )
+    });
+    RedirsValue::Array(Some(match (withscores, proto) {
+        (false, _) => items.map(|(member, _)| member).collect(),
+        (true, ProcVersion::V3) => items
+            .map(|(member, score)| RedirsValue::Array(Some(vec![member, score])))
+            .collect(),
+        (true, _) => items.flat_map(|(member, score)| [member, score]).collect(),
+    }))
+}
+
+fn range_command(context: &mut Context<'_>, args: &[&[u8]], by_score: bool) -> Reply {
+    let range = parse_range(args, by_score)?;
+    let proto = context.client.proto;
+    let mut keyspace = context.db.lock();
+    let members = match keyspace.get_sorted_set(args[0])? {
+        Some(zset) => select(zset, &range),
+        None => Vec::new(),
+    };
+    Ok(members_reply(members, range.withscores, proto))
+}
+
+pub(crate) fn zrange(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    range_command(context, args, false)
+}
+
+// ZRANGEBYSCORE key min max [WITHSCORES] [LIMIT offset count]
+pub(crate) fn zrangebyscore(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let opts = &args[3..];
+    if opts.iter().any(|opt| {
+        ["byscore", "bylex", "rev"]
+            .iter()
+            .any(|name| opt.eq_ignore_ascii_case(name.as_bytes()))
+    }) {
+        return Err(CommandError::SyntaxError.into());
+    }
+    range_command(context, args, true)
+}
+
+// ZPOPMIN key [count]: the member with the lowest score and its score, or for
+// ZPOPMAX the highest. A count pops up to that many, as pairs over RESP3
+fn pop(context: &mut Context<'_>, args: &[&[u8]], end: End) -> Reply {
+    let count = match args {
+        [_] => None,
+        [_, count] => match parse_int(count) {
+            Ok(count) if count >= 0 => Some(count as usize),
+            _ => {
+                return Err(Error::Message(
+                    "ERR value is out of range, must be positive".to_owned(),
+                ))
+            }
+        },
+        _ => return Err(CommandError::SyntaxError.into()),
+    };
+    let key = args[0];
+    let proto = context.client.proto;
+    let mut keyspace = context.db.lock();
+    let popped: Vec<_> = match keyspace.get_sorted_set_mut(key)? {
+        Some(zset) => (0..count.unwrap_or(1))
+            .map_while(|_| end.pop_member(zset))
+            .collect(),
+        None => Vec::new(),
+    };
+    keyspace.remove_if_empty(key);
+    let members = popped
+        .iter()
+        .map(|(member, score)| (&member[..], *score))
+        .collect();
+    Ok(match count {
+        Some(_) => members_reply(members, true, proto),
+        None => members_reply(members, true, ProcVersion::V2),
+    })
+}
+
+pub(crate) fn zpopmin(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    pop(context, args, End::Left)
+}
+
+pub(crate) fn zpopmax(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    pop(context, args, End::Right)
+}
+
+// BZPOPMIN key [key ...] timeout: [key, member, score] for the first of the
+// keys holding a sorted set, or a wait for one of them to get a member
+fn blocking_pop(context: &mut Context<'_>, args: &[&[u8]], end: End) -> Reply {
+    let (keys, timeout) = args.split_at(args.len() - 1);
+    let deadline = lists::deadline(timeout[0])?;
+    let mut keyspace = context.db.lock();
+    for key in keys {
+        if let Some(zset) = keyspace.get_sorted_set_mut(key)? {
+            let (member, score) = end.pop_member(zset).expect("sorted sets are never empty");
+            keyspace.remove_if_empty(key);
+            return Ok(RedirsValue::Array(Some(vec![
+                RedirsValue::from(key.to_vec()),
+                RedirsValue::from(member),
+                RedirsValue::Double(score),
+            ])));
+        }
+    }
+    drop(keyspace);
+    context.block = Some(Block::new(context.db, keys, Pop::SortedSet(end), deadline));
+    Ok(RedirsValue::Null)
+}
+
+pub(crate) fn bzpopmin(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    blocking_pop(context, args, End::Left)
+}
+
+pub(crate) fn bzpopmax(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    blocking_pop(context, args, End::Right)
+}
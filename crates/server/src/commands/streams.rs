@@ -0,0 +1,640 @@
+use protocol::{CommandError, ProcVersion, RedirsValue};
+
+use super::{ok, parse_int, Context, Error, Reply};
+use crate::stream::{Claim, IdError, NewId, Stream, StreamEntry, StreamId, Trim, NODE_ENTRIES};
+
+fn invalid_id() -> Error {
+    Error::Message("ERR Invalid stream ID specified as stream command argument".to_owned())
+}
+
+fn parse_id(arg: &[u8], seq: u64) -> Result<StreamId, Error> {
+    StreamId::parse(arg, seq).ok_or_else(invalid_id)
+}
+
+// [id, [field, value, ...]]
+fn entry_reply(id: StreamId, entry: &StreamEntry) -> RedirsValue {
+    let fields = entry
+        .iter()
+        .flat_map(|(field, value)| [field, value])
+        .map(|item| RedirsValue::from(item.clone()))
+        .collect();
+    RedirsValue::Array(Some(vec![
+        RedirsValue::from(id.to_string().into_bytes()),
+        RedirsValue::Array(Some(fields)),
+    ]))
+}
+
+// how XADD trims the stream, an approximate trim removing no more than the
+// limit, 0 for none
+struct Trimming {
+    trim: Trim,
+    approximate: Option<usize>,
+}
+
+// XADD key [NOMKSTREAM] [MAXLEN|MINID [=|~] threshold [LIMIT count]] *|id
+// field value [field value ...]: the id of the entry added, nil when the key
+// is missing and NOMKSTREAM is given
+pub(crate) fn xadd(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let key = args[0];
+    let (mut nomkstream, mut trimming) = (false, None);
+    let mut at = 1;
+    while let Some(opt) = args.get(at) {
+        let opt = opt.to_ascii_lowercase();
+        let by_len = match opt.as_slice() {
+            b"nomkstream" => {
+                nomkstream = true;
+                at += 1;
+                continue;
+            }
+            b"maxlen" => true,
+            b"minid" => false,
+            _ => break,
+        };
+        let approximate = args.get(at + 1).copied() == Some(b"~");
+        if matches!(args.get(at + 1).copied(), Some(b"~" | b"=")) {
+            at += 1;
+        }
+        at += 1;
+        let threshold = *args.get(at).ok_or(CommandError::SyntaxError)?;
+        let trim = match by_len {
+            true => match parse_int(threshold)? {
+                len if len < 0 => {
+                    return Err(Error::Message(
+                        "ERR The MAXLEN argument must be >= 0.".to_owned(),
+                    ))
+                }
+                len => Trim::MaxLen(len as usize),
+            },
+            false => Trim::MinId(parse_id(threshold, 0)?),
+        };
+        at += 1;
+        let mut limit = 100 * NODE_ENTRIES;
+        if args
+            .get(at)
+            .is_some_and(|opt| opt.eq_ignore_ascii_case(b"limit"))
+        {
+            let count = parse_int(args.get(at + 1).ok_or(CommandError::SyntaxError)?)?;
+            if count < 0 {
+                return Err(Error::Message(
+                    "ERR The LIMIT argument must be >= 0.".to_owned(),
+                ));
+            }
+            if !approximate {
+                return Err(Error::Message(
+                    "ERR syntax error, LIMIT cannot be used without the special ~ option"
+                        .to_owned(),
+                ));
+            }
+            limit = count as usize;
+            at += 2;
+        }
+        trimming = Some(Trimming {
+            trim,
+            approximate: approximate.then_some(limit),
+        });
+    }
+    let id = NewId::parse(args.get(at).ok_or(CommandError::SyntaxError)?).ok_or_else(invalid_id)?;
+    let fields = &args[at + 1..];
+    if fields.is_empty() || !fields.len().is_multiple_of(2) {
+        return Err(CommandError::WrongArity("xadd").into());
+    }
+    let mut keyspace = context.db.lock();
+    let now = keyspace.now();
+    let id = match keyspace.get_stream(key)? {
+        Some(stream) => stream.next_id(id, now),
+        None if nomkstream => return Ok(RedirsValue::BulkString(None)),
+        // the id is checked before the key is created
+        None => Stream::new().next_id(id, now),
+    }
+    .map_err(id_error)?;
+    let stream = keyspace.get_or_create_stream(key)?;
+    let entry = fields
+        .chunks(2)
+        .map(|pair| (pair[0].to_vec(), pair[1].to_vec()))
+        .collect();
+    stream.add(id, entry);
+    if let Some(Trimming { trim, approximate }) = trimming {
+        stream.trim(trim, approximate);
+    }
+    Ok(RedirsValue::from(id.to_string().into_bytes()))
+}
+
+fn id_error(e: IdError) -> Error {
+    Error::Message(
+        match e {
+            IdError::Zero => "ERR The ID specified in XADD must be greater than 0-0",
+            IdError::NotGreater => {
+                "ERR The ID specified in XADD is equal or smaller than the target stream top item"
+            }
+            IdError::Exhausted => {
+                "ERR The stream has exhausted the last possible ID, unable to add more items"
+            }
+        }
+        .to_owned(),
+    )
+}
+
+pub(crate) fn xlen(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let len = context
+        .db
+        .lock()
+        .get_stream(args[0])?
+        .map_or(0, Stream::len);
+    Ok(RedirsValue::Integer(len as i64))
+}
+
+// the first id a range takes in: `-` for the lowest, a missing sequence read
+// as 0 and `(` excluding the id
+fn range_start(arg: &[u8]) -> Result<StreamId, Error> {
+    match arg {
+        b"-" => Ok(StreamId::MIN),
+        b"+" => Ok(StreamId::MAX),
+        [b'(', id @ ..] => parse_id(id, 0)?
+            .next()
+            .ok_or_else(|| Error::Message("ERR invalid start ID for the interval".to_owned())),
+        id => parse_id(id, 0),
+    }
+}
+
+// the last id a range takes in: `+` for the highest, a missing sequence read
+// as the highest and `(` excluding the id
+fn range_end(arg: &[u8]) -> Result<StreamId, Error> {
+    match arg {
+        b"-" => Ok(StreamId::MIN),
+        b"+" => Ok(StreamId::MAX),
+        [b'(', id @ ..] => parse_id(id, u64::MAX)?
+            .prev()
+            .ok_or_else(|| Error::Message("ERR invalid end ID for the interval".to_owned())),
+        id => parse_id(id, u64::MAX),
+    }
+}
+
+// XRANGE key start end [COUNT count], or XREVRANGE key end start with the
+// newest entries first
+fn range(context: &mut Context<'_>, args: &[&[u8]], rev: bool) -> Reply {
+    let (start, end) = match rev {
+        true => (args[2], args[1]),
+        false => (args[1], args[2]),
+    };
+    let (start, end) = (range_start(start)?, range_end(end)?);
+    let count = match &args[3..] {
+        [] => usize::MAX,
+        [opt, count] if opt.eq_ignore_ascii_case(b"count") => parse_int(count)?.max(0) as usize,
+        _ => return Err(CommandError::SyntaxError.into()),
+    };
+    let mut keyspace = context.db.lock();
+    let Some(stream) = keyspace.get_stream(args[0])? else {
+        return Ok(RedirsValue::Array(Some(Vec::new())));
+    };
+    let entries = stream.range(start..=end);
+    let entries: Vec<_> = match rev {
+        true => entries
+            .rev()
+            .take(count)
+            .map(|(id, entry)| entry_reply(id, entry))
+            .collect(),
+        false => entries
+            .take(count)
+            .map(|(id, entry)| entry_reply(id, entry))
+            .collect(),
+    };
+    Ok(RedirsValue::Array(Some(entries)))
+}
+
+pub(crate) fn xrange(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    range(context, args, false)
+}
+
+pub(crate) fn xrevrange(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    range(context, args, true)
+}
+
+// XREAD [COUNT count] STREAMS key [key ...] id [id ...]: for each stream up
+// to count entries after the id given for it, `$` standing for its last id.
+// Streams with none are left out and nil replies when all are
+pub(crate) fn xread(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let mut count = usize::MAX;
+    let mut opts = args;
+    let streams = loop {
+        opts = match opts {
+            [opt, streams @ ..] if opt.eq_ignore_ascii_case(b"streams") => break streams,
+            [opt, value, rest @ ..] if opt.eq_ignore_ascii_case(b"count") => {
+                count = match parse_int(value)? {
+                    count if count <= 0 => usize::MAX,
+                    count => count as usize,
+                };
+                rest
+            }
+            _ => return Err(CommandError::SyntaxError.into()),
+        };
+    };
+    let (keys, ids) = split_streams(streams, "xread")?;
+    let ids = ids
+        .iter()
+        .map(|id| match *id {
+            b"$" => Ok(None),
+            id => parse_id(id, 0).map(Some),
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let proto = context.client.proto;
+    let mut keyspace = context.db.lock();
+    let mut found = Vec::new();
+    for (key, after) in keys.iter().zip(ids) {
+        let Some(stream) = keyspace.get_stream(key)? else {
+            continue;
+        };
+        let after = after.unwrap_or(stream.last_id());
+        let Some(start) = after.next() else {
+            continue;
+        };
+        let entries: Vec<_> = stream
+            .range(start..=StreamId::MAX)
+            .take(count)
+            .map(|(id, entry)| entry_reply(id, entry))
+            .collect();
+        if !entries.is_empty() {
+            found.push((
+                RedirsValue::from(key.to_vec()),
+                RedirsValue::Array(Some(entries)),
+            ));
+        }
+    }
+    Ok(streams_reply(found, proto))
+}
+
+type Args<'a> = &'a [&'a [u8]];
+
+// the keys and ids after STREAMS, as many of each
+fn split_streams<'a>(streams: Args<'a>, name: &str) -> Result<(Args<'a>, Args<'a>), Error> {
+    if streams.is_empty() || !streams.len().is_multiple_of(2) {
+        return Err(Error::Message(format!(
+            "ERR Unbalanced '{name}' list of streams: for each stream key an ID or '$' must be specified."
+        )));
+    }
+    Ok(streams.split_at(streams.len() / 2))
+}
+
+// the entries read from each stream, as a map over RESP3 and as pairs over
+// RESP2, nil for none at all
+fn streams_reply(found: Vec<(RedirsValue, RedirsValue)>, proto: ProcVersion) -> RedirsValue {
+    match (found.is_empty(), proto) {
+        (true, _) => RedirsValue::Array(None),
+        (false, ProcVersion::V3) => RedirsValue::Map(found.into_iter().collect()),
+        (false, _) => RedirsValue::Array(Some(
+            found
+                .into_iter()
+                .map(|(key, entries)| RedirsValue::Array(Some(vec![key, entries])))
+                .collect(),
+        )),
+    }
+}
+
+fn text(bytes: &[u8]) -> std::borrow::Cow<'_, str> {
+    String::from_utf8_lossy(bytes)
+}
+
+// what the group commands other than XREADGROUP and XGROUP reply when the key
+// or the group is missing
+fn no_group(key: &[u8], group: &[u8]) -> Error {
+    Error::Message(format!(
+        "NOGROUP No such key '{}' or consumer group '{}'",
+        text(key),
+        text(group)
+    ))
+}
+
+const KEY_REQUIRED: &str = "ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.";
+
+// XGROUP CREATE key group id|$ [MKSTREAM], XGROUP DESTROY key group and
+// XGROUP CREATECONSUMER key group consumer
+pub(crate) fn xgroup(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let subcommand = args[0].to_ascii_lowercase();
+    let (name, arity) = match subcommand.as_slice() {
+        b"create" => ("xgroup|create", 4..=5),
+        b"destroy" => ("xgroup|destroy", 3..=3),
+        b"createconsumer" => ("xgroup|createconsumer", 4..=4),
+        _ => {
+            return Err(Error::Message(format!(
+                "ERR unknown subcommand '{}'. Try XGROUP HELP.",
+                text(args[0])
+            )))
+        }
+    };
+    if !arity.contains(&args.len()) {
+        return Err(CommandError::WrongArity(name).into());
+    }
+    let (key, group) = (args[1], args[2]);
+    let mut keyspace = context.db.lock();
+    let now = keyspace.now();
+    if subcommand == b"create" {
+        let mkstream = match args.get(4) {
+            None => false,
+            Some(opt) if opt.eq_ignore_ascii_case(b"mkstream") => true,
+            Some(_) => return Err(CommandError::SyntaxError.into()),
+        };
+        let id = match args[3] {
+            b"$" => None,
+            id => Some(parse_id(id, 0)?),
+        };
+        let stream = match keyspace.get_stream_mut(key)? {
+            Some(stream) => stream,
+            None if mkstream => keyspace.get_or_create_stream(key)?,
+            None => return Err(Error::Message(KEY_REQUIRED.to_owned())),
+        };
+        let id = id.unwrap_or(stream.last_id());
+        return match stream.create_group(group, id) {
+            true => Ok(ok()),
+            false => Err(Error::Message(
+                "BUSYGROUP Consumer Group name already exists".to_owned(),
+            )),
+        };
+    }
+    let Some(stream) = keyspace.get_stream_mut(key)? else {
+        return Err(Error::Message(KEY_REQUIRED.to_owned()));
+    };
+    if subcommand == b"destroy" {
+        return Ok(RedirsValue::Integer(stream.destroy_group(group) as i64));
+    }
+    let Some(group) = stream.group_mut(group) else {
+        return Err(Error::Message(format!(
+            "NOGROUP No such consumer group '{}' for key name '{}'",
+            text(group),
+            text(key)
+        )));
+    };
+    let created = !group.consumers.contains_key(args[3]);
+    group.consumer(args[3], now);
+    Ok(RedirsValue::Integer(created as i64))
+}
+
+// XREADGROUP GROUP group consumer [COUNT count] [NOACK] STREAMS key [key ...]
+// id [id ...]: `>` reads the entries never delivered to the group, which
+// become pending for the consumer unless NOACK is given, and any other id
+// reads again the entries pending for the consumer after it
+pub(crate) fn xreadgroup(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (mut group, mut count, mut noack) = (None, usize::MAX, false);
+    let mut opts = args;
+    let streams = loop {
+        opts = match opts {
+            [opt, streams @ ..] if opt.eq_ignore_ascii_case(b"streams") => break streams,
+            [opt, name, consumer, rest @ ..] if opt.eq_ignore_ascii_case(b"group") => {
+                group = Some((*name, *consumer));
+                rest
+            }
+            [opt, value, rest @ ..] if opt.eq_ignore_ascii_case(b"count") => {
+                count = match parse_int(value)? {
+                    count if count <= 0 => usize::MAX,
+                    count => count as usize,
+                };
+                rest
+            }
+            [opt, rest @ ..] if opt.eq_ignore_ascii_case(b"noack") => {
+                noack = true;
+                rest
+            }
+            _ => return Err(CommandError::SyntaxError.into()),
+        };
+    };
+    let Some((group, consumer)) = group else {
+        return Err(Error::Message(
+            "ERR Missing GROUP option for XREADGROUP".to_owned(),
+        ));
+    };
+    let (keys, ids) = split_streams(streams, "xreadgroup")?;
+    // none for `>`
+    let ids = ids
+        .iter()
+        .map(|id| match *id {
+            b">" => Ok(None),
+            b"$" => Err(Error::Message(
+                "ERR The $ ID is meaningless in the context of XREADGROUP".to_owned(),
+            )),
+            id => parse_id(id, 0).map(Some),
+        })
+        .collect::<Result<Vec<_>, Error>>()?;
+    let proto = context.client.proto;
+    let mut keyspace = context.db.lock();
+    let now = keyspace.now();
+    // nothing is read unless every group is there
+    for key in keys {
+        if keyspace
+            .get_stream(key)?
+            .and_then(|s| s.group(group))
+            .is_none()
+        {
+            return Err(Error::Message(format!(
+                "NOGROUP No such key '{}' or consumer group '{}' in XREADGROUP with GROUP option",
+                text(key),
+                text(group)
+            )));
+        }
+    }
+    let mut found = Vec::new();
+    for (key, after) in keys.iter().zip(ids) {
+        let stream = keyspace.get_stream_mut(key)?.expect("checked to be there");
+        let entries: Vec<_> = match after {
+            None => stream
+                .read_new(group, consumer, count, noack, now)
+                .expect("checked to be there")
+                .iter()
+                .map(|(id, entry)| entry_reply(*id, entry))
+                .collect(),
+            Some(after) => stream
+                .read_pending(group, consumer, after, count, now)
+                .expect("checked to be there")
+                .iter()
+                .map(|(id, entry)| match entry {
+                    Some(entry) => entry_reply(*id, entry),
+                    // acknowledged by no one but trimmed or deleted since
+                    None => RedirsValue::Array(Some(vec![
+                        RedirsValue::from(id.to_string().into_bytes()),
+                        RedirsValue::Array(None),
+                    ])),
+                })
+                .collect(),
+        };
+        // the pending entries of a stream are replied with even when none
+        if after.is_some() || !entries.is_empty() {
+            found.push((
+                RedirsValue::from(key.to_vec()),
+                RedirsValue::Array(Some(entries)),
+            ));
+        }
+    }
+    Ok(streams_reply(found, proto))
+}
+
+// XACK key group id [id ...]: how many of the ids were pending
+pub(crate) fn xack(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, group) = (args[0], args[1]);
+    let ids = args[2..]
+        .iter()
+        .map(|id| parse_id(id, 0))
+        .collect::<Result<Vec<_>, Error>>()?;
+    let mut keyspace = context.db.lock();
+    let Some(group) = keyspace
+        .get_stream_mut(key)?
+        .and_then(|stream| stream.group_mut(group))
+    else {
+        return Ok(RedirsValue::Integer(0));
+    };
+    let acked = ids.into_iter().filter(|id| group.ack(*id)).count();
+    Ok(RedirsValue::Integer(acked as i64))
+}
+
+// XPENDING key group: how many entries are pending, the lowest and highest
+// of their ids and how many each consumer has. XPENDING key group [IDLE
+// min-idle-time] start end count [consumer]: the pending entries in the range
+// with their consumer, idle time and deliveries
+pub(crate) fn xpending(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, group) = (args[0], args[1]);
+    let extended = match &args[2..] {
+        [] => None,
+        rest => {
+            let (min_idle, rest) = match rest {
+                [opt, idle, rest @ ..] if opt.eq_ignore_ascii_case(b"idle") => {
+                    (parse_int(idle)?.max(0) as u64, rest)
+                }
+                rest => (0, rest),
+            };
+            let (range, consumer) = match rest {
+                [start, end, count] => ([*start, *end, *count], None),
+                [start, end, count, consumer] => ([*start, *end, *count], Some(*consumer)),
+                _ => return Err(CommandError::SyntaxError.into()),
+            };
+            let [start, end, count] = range;
+            let count = parse_int(count)?.max(0) as usize;
+            Some((
+                min_idle,
+                range_start(start)?,
+                range_end(end)?,
+                count,
+                consumer,
+            ))
+        }
+    };
+    let mut keyspace = context.db.lock();
+    let now = keyspace.now();
+    let Some(group) = keyspace
+        .get_stream(key)?
+        .and_then(|stream| stream.group(group))
+    else {
+        return Err(no_group(key, group));
+    };
+    let id_reply = |id: &StreamId| RedirsValue::from(id.to_string().into_bytes());
+    let Some((min_idle, start, end, count, consumer)) = extended else {
+        let owners: Vec<_> = group
+            .consumers
+            .iter()
+            .filter(|(_, owner)| !owner.pending.is_empty())
+            .map(|(name, owner)| {
+                RedirsValue::Array(Some(vec![
+                    RedirsValue::from(name.clone()),
+                    RedirsValue::from(owner.pending.len().to_string().into_bytes()),
+                ]))
+            })
+            .collect();
+        let bound = |id: Option<(&StreamId, _)>| {
+            RedirsValue::from(id.map(|(id, _)| id.to_string().into_bytes()))
+        };
+        return Ok(RedirsValue::Array(Some(vec![
+            RedirsValue::Integer(group.pending.len() as i64),
+            bound(group.pending.first_key_value()),
+            bound(group.pending.last_key_value()),
+            RedirsValue::Array((!owners.is_empty()).then_some(owners)),
+        ])));
+    };
+    let entries = match start <= end {
+        true => group
+            .pending
+            .range(start..=end)
+            .filter(|(_, pending)| consumer.is_none_or(|consumer| pending.consumer == consumer))
+            .filter(|(_, pending)| now.saturating_sub(pending.delivered_at) >= min_idle)
+            .take(count)
+            .map(|(id, pending)| {
+                RedirsValue::Array(Some(vec![
+                    id_reply(id),
+                    RedirsValue::from(pending.consumer.clone()),
+                    RedirsValue::Integer(now.saturating_sub(pending.delivered_at) as i64),
+                    RedirsValue::Integer(pending.deliveries as i64),
+                ]))
+            })
+            .collect(),
+        false => Vec::new(),
+    };
+    Ok(RedirsValue::Array(Some(entries)))
+}
+
+// XCLAIM key group consumer min-idle-time id [id ...] [IDLE ms] [TIME
+// unix-time-milliseconds] [RETRYCOUNT count] [FORCE] [JUSTID] [LASTID id]:
+// the entries idle for at least min-idle-time that are now the consumer's,
+// only their ids with JUSTID
+pub(crate) fn xclaim(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, group, consumer) = (args[0], args[1], args[2]);
+    let min_idle = parse_int(args[3])
+        .map_err(|_| Error::Message("ERR Invalid min-idle-time argument for XCLAIM".to_owned()))?;
+    let mut claim = Claim {
+        min_idle: min_idle.max(0) as u64,
+        ..Claim::default()
+    };
+    // the ids run up to the first option
+    let ids: Vec<_> = args[4..]
+        .iter()
+        .map_while(|id| StreamId::parse(id, 0))
+        .collect();
+    if ids.is_empty() {
+        return Err(invalid_id());
+    }
+    let mut keyspace = context.db.lock();
+    let now = keyspace.now();
+    let mut opts = &args[4 + ids.len()..];
+    while let [opt, rest @ ..] = opts {
+        opts = match (opt.to_ascii_lowercase().as_slice(), rest) {
+            (b"force", rest) => {
+                claim.force = true;
+                rest
+            }
+            (b"justid", rest) => {
+                claim.just_id = true;
+                rest
+            }
+            (b"idle", [ms, rest @ ..]) => {
+                claim.delivered_at = Some(now.saturating_sub(parse_int(ms)?.max(0) as u64));
+                rest
+            }
+            (b"time", [at, rest @ ..]) => {
+                claim.delivered_at = Some((parse_int(at)?.max(0) as u64).min(now));
+                rest
+            }
+            (b"retrycount", [count, rest @ ..]) => {
+                claim.retry_count = Some(parse_int(count)?.max(0) as u64);
+                rest
+            }
+            (b"lastid", [id, rest @ ..]) => {
+                claim.last_id = Some(parse_id(id, 0)?);
+                rest
+            }
+            _ => {
+                return Err(Error::Message(format!(
+                    "ERR Unrecognized XCLAIM option '{}'",
+                    text(opt)
+                )))
+            }
+        };
+    }
+    let Some(claimed) = keyspace
+        .get_stream_mut(key)?
+        .and_then(|stream| stream.claim(group, consumer, &ids, &claim, now))
+    else {
+        return Err(no_group(key, group));
+    };
+    Ok(RedirsValue::Array(Some(
+        claimed
+            .iter()
+            .map(|(id, entry)| match claim.just_id {
+                true => RedirsValue::from(id.to_string().into_bytes()),
+                false => entry_reply(*id, entry),
+            })
+            .collect(),
+    )))
+}
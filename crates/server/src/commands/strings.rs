@@ -0,0 +1,286 @@
+use std::time::SystemTime;
+
+use protocol::{CommandError, Expiration, RedirsValue, SetCondition, SetOptions};
+
+use super::{ok, parse_float, parse_int, Context, Error, Reply};
+use crate::{db::Value, Db};
+
+// the longest string a key can hold, as redis limits it
+pub(crate) const MAX_STRING_LEN: usize = 512 * 1024 * 1024;
+
+fn too_long() -> Error {
+    Error::Message("ERR string exceeds maximum allowed size (proto-max-bulk-len)".to_owned())
+}
+
+// adds `by` to the integer a key holds, a missing key holding 0. The value is
+// changed in place, so a deadline stays
+fn incr_by(context: &mut Context<'_>, key: &[u8], by: i64) -> Reply {
+    let mut keyspace = context.db.lock();
+    let current = keyspace.get_string_mut(key)?;
+    let old = current
+        .as_ref()
+        .map(|v| parse_int(v))
+        .transpose()?
+        .unwrap_or(0);
+    let new = old
+        .checked_add(by)
+        .ok_or_else(|| Error::Message("ERR increment or decrement would overflow".to_owned()))?;
+    let digits = new.to_string().into_bytes();
+    match current {
+        Some(current) => *current = digits,
+        None => {
+            keyspace.set(key.to_vec(), Value::String(digits));
+        }
+    }
+    Ok(RedirsValue::Integer(new))
+}
+
+pub(crate) fn incr(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    incr_by(context, args[0], 1)
+}
+
+pub(crate) fn decr(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    incr_by(context, args[0], -1)
+}
+
+pub(crate) fn incrby(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let by = parse_int(args[1])?;
+    incr_by(context, args[0], by)
+}
+
+pub(crate) fn decrby(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let by = parse_int(args[1])?
+        .checked_neg()
+        .ok_or_else(|| Error::Message("ERR decrement would overflow".to_owned()))?;
+    incr_by(context, args[0], by)
+}
+
+// the shortest text that reads back as the same float, without an exponent
+// or trailing zeros: 3.0 is written 3 and 1e-5 is written 0.00001
+pub(crate) fn format_float(float: f64) -> Vec<u8> {
+    float.to_string().into_bytes()
+}
+
+pub(crate) fn incrbyfloat(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, by) = (args[0], parse_float(args[1])?);
+    let mut keyspace = context.db.lock();
+    let current = keyspace.get_string_mut(key)?;
+    let old = current
+        .as_deref()
+        .map(|v| parse_float(v))
+        .transpose()?
+        .unwrap_or(0.0);
+    let new = old + by;
+    if !new.is_finite() {
+        return Err(Error::Message(
+            "ERR increment would produce NaN or Infinity".to_owned(),
+        ));
+    }
+    let text = format_float(new);
+    match current {
+        Some(current) => *current = text.clone(),
+        None => {
+            keyspace.set(key.to_vec(), Value::String(text.clone()));
+        }
+    }
+    Ok(RedirsValue::from(text))
+}
+
+pub(crate) fn append(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, suffix) = (args[0], args[1]);
+    let mut keyspace = context.db.lock();
+    let len = match keyspace.get_string_mut(key)? {
+        Some(value) => {
+            if value.len() + suffix.len() > MAX_STRING_LEN {
+                return Err(too_long());
+            }
+            value.extend_from_slice(suffix);
+            value.len()
+        }
+        None => {
+            keyspace.set(key.to_vec(), Value::String(suffix.to_vec()));
+            suffix.len()
+        }
+    };
+    Ok(RedirsValue::Integer(len as i64))
+}
+
+pub(crate) fn strlen(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let mut keyspace = context.db.lock();
+    let len = keyspace.get_string(args[0])?.map_or(0, Vec::len);
+    Ok(RedirsValue::Integer(len as i64))
+}
+
+// the bytes from `start` to `end` both included, negative indexes count from
+// the end and the range is clamped to the string
+fn range(value: &[u8], start: i64, end: i64) -> &[u8] {
+    let len = value.len() as i64;
+    if start < 0 && end < 0 && start > end {
+        return b"";
+    }
+    let start = match start < 0 {
+        true => (len + start).max(0),
+        false => start,
+    };
+    let end = match end < 0 {
+        true => (len + end).max(0),
+        false => end.min(len - 1),
+    };
+    match start > end || len == 0 {
+        true => b"",
+        false => &value[start as usize..=end as usize],
+    }
+}
+
+pub(crate) fn getrange(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (start, end) = (parse_int(args[1])?, parse_int(args[2])?);
+    let mut keyspace = context.db.lock();
+    let value = keyspace.get_string(args[0])?.map_or(&[][..], Vec::as_slice);
+    Ok(RedirsValue::from(range(value, start, end)))
+}
+
+// writes over the string from `offset`, padding the gap past its end with
+// zero bytes
+pub(crate) fn setrange(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let (key, patch) = (args[0], args[2]);
+    let offset = parse_int(args[1])?;
+    if offset < 0 {
+        return Err(Error::Message("ERR offset is out of range".to_owned()));
+    }
+    let offset = offset as usize;
+    let mut keyspace = context.db.lock();
+    let value = match keyspace.get_string_mut(key)? {
+        Some(value) => value,
+        // nothing to write leaves a missing key missing
+        None if patch.is_empty() => return Ok(RedirsValue::Integer(0)),
+        None => keyspace.get_or_create_string(key)?,
+    };
+    if !patch.is_empty() {
+        let end = offset
+            .checked_add(patch.len())
+            .filter(|end| *end <= MAX_STRING_LEN);
+        let end = end.ok_or_else(too_long)?;
+        if value.len() < end {
+            value.resize(end, 0);
+        }
+        value[offset..end].copy_from_slice(patch);
+    }
+    Ok(RedirsValue::Integer(value.len() as i64))
+}
+
+// nil and no change for a missing key
+pub(crate) fn getdel(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let mut keyspace = context.db.lock();
+    let value = keyspace.get_string(args[0])?.cloned();
+    if value.is_some() {
+        keyspace.remove(args[0]);
+    }
+    Ok(RedirsValue::from(value))
+}
+
+// what GETEX does to the deadline of the key
+enum Ttl {
+    Keep,
+    Persist,
+    // a deadline in keyspace clock milliseconds, possibly past
+    At(i64),
+}
+
+// [EX seconds | PX milliseconds | EXAT unix-time-seconds |
+// PXAT unix-time-milliseconds | PERSIST], one of them at most
+fn parse_getex_options(args: &[&[u8]], now: u64) -> Result<Ttl, Error> {
+    let invalid = || Error::Command(CommandError::InvalidExpireTime("getex"));
+    let (opt, time) = match args {
+        [] => return Ok(Ttl::Keep),
+        [opt] if opt.eq_ignore_ascii_case(b"persist") => return Ok(Ttl::Persist),
+        [opt, time] => (opt.to_ascii_lowercase(), time),
+        _ => return Err(CommandError::SyntaxError.into()),
+    };
+    let (unit, base) = match opt.as_slice() {
+        b"ex" => (1000, now as i64),
+        b"px" => (1, now as i64),
+        b"exat" => (1000, 0),
+        b"pxat" => (1, 0),
+        _ => return Err(CommandError::SyntaxError.into()),
+    };
+    let time = parse_int(time)?;
+    if time <= 0 {
+        return Err(invalid());
+    }
+    time.checked_mul(unit)
+        .and_then(|millis| millis.checked_add(base))
+        .map(Ttl::At)
+        .ok_or_else(invalid)
+}
+
+// GET that changes the deadline as well, a deadline in the past deletes the
+// key after reading it
+pub(crate) fn getex(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    let key = args[0];
+    let mut keyspace = context.db.lock();
+    let ttl = parse_getex_options(&args[1..], keyspace.now())?;
+    let Some(value) = keyspace.get_string(key)?.cloned() else {
+        return Ok(RedirsValue::BulkString(None));
+    };
+    match ttl {
+        Ttl::Keep => {}
+        Ttl::Persist => {
+            keyspace.persist(key);
+        }
+        Ttl::At(deadline) => {
+            keyspace.expire_at(key, deadline.max(0) as u64);
+        }
+    }
+    Ok(RedirsValue::from(value))
+}
+
+// the deadline of a SET EX, PX, EXAT or PXAT, in the clock of the keyspace
+fn deadline(expire: &Expiration, now: u64) -> u64 {
+    match expire {
+        Expiration::In(time) => now.saturating_add(time.as_millis() as u64),
+        Expiration::At(time) => time
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as u64,
+    }
+}
+
+// the old value with GET, whether or not NX or XX let the value through.
+// Otherwise OK once set and nil when NX or XX stopped it
+pub(crate) fn set(db: &Db, key: Vec<u8>, value: RedirsValue, options: SetOptions) -> Reply {
+    // the parser hands values over as bulk strings
+    let value = value.as_bytes().map(<[u8]>::to_vec).unwrap_or_default();
+    let mut keyspace = db.lock();
+    // SET GET fails on a key that is not a string without writing to it
+    let old = match options.get {
+        true => keyspace.get_string(&key)?.cloned(),
+        false => None,
+    };
+    let set = match options.condition {
+        Some(SetCondition::NX) => !keyspace.exists(&key),
+        Some(SetCondition::XX) => keyspace.exists(&key),
+        None => true,
+    };
+    if set {
+        // the value and its deadline change under the same lock
+        let value = Value::String(value);
+        match (&options.expire, options.keep_ttl) {
+            (Some(expire), _) => {
+                let deadline = deadline(expire, keyspace.now());
+                keyspace.set(key.clone(), value);
+                keyspace.expire_at(&key, deadline);
+            }
+            (None, true) => {
+                keyspace.set_keep_ttl(key, value);
+            }
+            (None, false) => {
+                keyspace.set(key, value);
+            }
+        }
+    }
+    Ok(match (options.get, set) {
+        (true, _) => RedirsValue::from(old),
+        (false, true) => ok(),
+        (false, false) => RedirsValue::BulkString(None),
+    })
+}
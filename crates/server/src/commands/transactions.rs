@@ -0,0 +1,148 @@
+use protocol::RedirsValue;
+
+use super::{ok, Context, Error, Outcome, Reply};
+use crate::{connection::Client, Db};
+
+// the commands queued since MULTI
+#[derive(Debug, Default)]
+pub(crate) struct Transaction {
+    queued: Vec<RedirsValue>,
+    // a command could not be queued, EXEC then only discards the rest
+    aborted: bool,
+}
+
+impl Transaction {
+    // the commands queued so far
+    pub fn queued(&self) -> usize {
+        self.queued.len()
+    }
+}
+
+// the commands run right away in a transaction rather than queued
+const IMMEDIATE: &[&str] = &["exec", "discard", "multi", "watch", "quit", "reset"];
+
+// in a transaction, QUEUED for a request that looks like it can run, and an
+// error aborting the transaction for one that cannot or that the user of the
+// connection is not allowed to. None outside of one or for the commands that
+// run right away
+pub(super) fn queue(request: &RedirsValue, client: &mut Client, db: &Db) -> Option<RedirsValue> {
+    client.transaction.as_ref()?;
+    let permitted = super::acl::permitted(request, client, db);
+    let transaction = client.transaction.as_mut()?;
+    let name = super::name(request).map(<[u8]>::to_ascii_lowercase);
+    if name.is_some_and(|name| {
+        IMMEDIATE
+            .iter()
+            .any(|immediate| immediate.as_bytes() == name)
+    }) {
+        return None;
+    }
+    match permitted.and_then(|()| super::check(request)) {
+        Ok(()) => {
+            transaction.queued.push(request.clone());
+            Some(RedirsValue::SimpleString("QUEUED".to_owned()))
+        }
+        Err(e) => {
+            transaction.aborted = true;
+            Some(e.to_client_error())
+        }
+    }
+}
+
+// forgets the keys WATCH was given
+fn unwatch_all(context: &mut Context<'_>) {
+    unwatch_keys(context.client, context.db);
+}
+
+// the same for a connection going away
+pub(crate) fn unwatch_keys(client: &mut Client, db: &Db) {
+    for (index, key, _) in client.watched.drain(..) {
+        db.select(index).lock().unwatch(&key);
+    }
+}
+
+// MULTI: the commands that follow are queued until EXEC or DISCARD
+pub(crate) fn multi(context: &mut Context<'_>, _: &[&[u8]]) -> Reply {
+    if context.client.transaction.is_some() {
+        return Err(Error::Message(
+            "ERR MULTI calls can not be nested".to_owned(),
+        ));
+    }
+    context.client.transaction = Some(Transaction::default());
+    Ok(ok())
+}
+
+// EXEC: the replies of the queued commands, run with no other command in
+// between as `execute` holds the keyspace for it. A nil array when a watched key was written to since WATCH, the
+// commands are not run then. Blocking commands do not wait, as though they
+// timed out
+pub(crate) fn exec(context: &mut Context<'_>, _: &[&[u8]]) -> Reply {
+    let Some(transaction) = context.client.transaction.take() else {
+        return Err(Error::Message("ERR EXEC without MULTI".to_owned()));
+    };
+    let changed = context
+        .client
+        .watched
+        .iter()
+        .any(|(index, key, version)| context.db.select(*index).lock().changed(key, *version));
+    unwatch_all(context);
+    if transaction.aborted {
+        return Err(Error::Message(
+            "EXECABORT Transaction discarded because of previous errors.".to_owned(),
+        ));
+    }
+    if changed {
+        return Ok(RedirsValue::Array(None));
+    }
+    let replies = transaction
+        .queued
+        .iter()
+        .map(
+            |request| match super::run(request, context.client, context.db) {
+                Outcome::Reply(reply) => reply,
+                Outcome::Replies(replies) => RedirsValue::Array(Some(replies)),
+                Outcome::Block(_) => RedirsValue::Array(None),
+            },
+        )
+        .collect();
+    Ok(RedirsValue::Array(Some(replies)))
+}
+
+// DISCARD: drops the queued commands
+pub(crate) fn discard(context: &mut Context<'_>, _: &[&[u8]]) -> Reply {
+    if context.client.transaction.take().is_none() {
+        return Err(Error::Message("ERR DISCARD without MULTI".to_owned()));
+    }
+    unwatch_all(context);
+    Ok(ok())
+}
+
+// WATCH key [key ...]: the next EXEC runs nothing if any of the keys of the
+// selected database is written to before it
+pub(crate) fn watch(context: &mut Context<'_>, args: &[&[u8]]) -> Reply {
+    if context.client.transaction.is_some() {
+        return Err(Error::Message(
+            "ERR WATCH inside MULTI is not allowed".to_owned(),
+        ));
+    }
+    let index = context.db.index();
+    let mut keyspace = context.db.lock();
+    for key in args {
+        if context
+            .client
+            .watched
+            .iter()
+            .all(|(db, watched, _)| (*db, &watched[..]) != (index, *key))
+        {
+            let version = keyspace.watch(key);
+            context.client.watched.push((index, key.to_vec(), version));
+        }
+    }
+    Ok(ok())
+}
+
+// UNWATCH
+pub(crate) fn unwatch(context: &mut Context<'_>, _: &[&[u8]]) -> Reply {
+    unwatch_all(context);
+    Ok(ok())
+}
@@ -0,0 +1,284 @@
+// the parameters CONFIG GET shows and CONFIG SET changes at runtime. Each has a
+// type its values are parsed by, a check of its own on top for some, and is
+// read from and applied to wherever the server keeps it, so a change takes
+// effect on the connections already open
+
+use std::time::Duration;
+
+use crate::{
+    eviction::{Policy, POLICIES},
+    notify::KeyspaceEvents,
+    Db,
+};
+
+// what the values of a parameter are
+#[derive(Debug, Clone, Copy)]
+enum Type {
+    Integer { min: i64, max: i64 },
+    // bytes, with a k, kb, m, mb, g or gb suffix for units of them
+    Memory,
+    Enum(&'static [&'static str]),
+    Bool,
+    String,
+}
+
+// a value parsed for its parameter
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Setting {
+    Integer(i64),
+    Memory(u64),
+    Enum(&'static str),
+    Bool(bool),
+    String(Vec<u8>),
+}
+
+impl Setting {
+    // the way CONFIG GET shows it, memory as the plain bytes
+    pub fn show(&self) -> Vec<u8> {
+        match self {
+            Setting::Integer(value) => value.to_string().into_bytes(),
+            Setting::Memory(value) => value.to_string().into_bytes(),
+            Setting::Enum(value) => value.as_bytes().to_vec(),
+            Setting::Bool(true) => b"yes".to_vec(),
+            Setting::Bool(false) => b"no".to_vec(),
+            Setting::String(value) => value.clone(),
+        }
+    }
+    fn integer(&self) -> i64 {
+        match *self {
+            Setting::Integer(value) => value,
+            Setting::Memory(value) => value as i64,
+            _ => 0,
+        }
+    }
+}
+
+impl Type {
+    // the reason it is not one, as CONFIG SET reports it
+    fn parse(self, value: &[u8]) -> Result<Setting, String> {
+        let text = std::str::from_utf8(value).unwrap_or_default();
+        match self {
+            Type::Integer { min, max } => {
+                let value: i64 = text
+                    .parse()
+                    .map_err(|_| "argument couldn't be parsed into an integer".to_owned())?;
+                if !(min..=max).contains(&value) {
+                    return Err(format!(
+                        "argument must be between {min} and {max} inclusive"
+                    ));
+                }
+                Ok(Setting::Integer(value))
+            }
+            Type::Memory => memory(text)
+                .map(Setting::Memory)
+                .ok_or_else(|| "argument must be a memory value".to_owned()),
+            Type::Enum(names) => names
+                .iter()
+                .find(|name| text.eq_ignore_ascii_case(name))
+                .map(|name| Setting::Enum(name))
+                .ok_or_else(|| {
+                    format!(
+                        "argument(s) must be one of the following: {}",
+                        names.join(", ")
+                    )
+                }),
+            Type::Bool => match text.to_ascii_lowercase().as_str() {
+                "yes" => Ok(Setting::Bool(true)),
+                "no" => Ok(Setting::Bool(false)),
+                _ => Err("argument must be 'yes' or 'no'".to_owned()),
+            },
+            Type::String => Ok(Setting::String(value.to_vec())),
+        }
+    }
+}
+
+// bytes from e.g. 100, 1k or 2gb, k and the others units of 1000 bytes and kb
+// and the others of 1024
+fn memory(text: &str) -> Option<u64> {
+    let text = text.to_ascii_lowercase();
+    let digits = text
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(text.len());
+    let (number, unit) = text.split_at(digits);
+    let unit = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    number.parse::<u64>().ok()?.checked_mul(unit)
+}
+
+// what the type of a parameter does not catch, e.g. the letters of
+// notify-keyspace-events
+type Check = fn(&Setting) -> Result<(), String>;
+
+pub(crate) struct Parameter {
+    pub name: &'static str,
+    kind: Type,
+    validate: Option<Check>,
+    pub get: fn(&Db) -> Setting,
+    // the hook applying a change, none for the parameters fixed at startup
+    set: Option<fn(&Db, &Setting)>,
+}
+
+impl Parameter {
+    // the value to `apply`, or the reason it cannot be one
+    pub fn parse(&self, value: &[u8]) -> Result<Setting, String> {
+        if self.set.is_none() {
+            return Err("can't set immutable config".to_owned());
+        }
+        let setting = self.kind.parse(value)?;
+        if let Some(validate) = self.validate {
+            validate(&setting)?;
+        }
+        Ok(setting)
+    }
+    pub fn apply(&self, db: &Db, setting: &Setting) {
+        if let Some(set) = self.set {
+            set(db, setting);
+        }
+    }
+}
+
+fn keyspace_events(setting: &Setting) -> Option<KeyspaceEvents> {
+    match setting {
+        Setting::String(flags) => KeyspaceEvents::parse(flags),
+        _ => None,
+    }
+}
+
+const POLICY_NAMES: &[&str] = &{
+    let mut names = [""; POLICIES.len()];
+    let mut index = 0;
+    while index < POLICIES.len() {
+        names[index] = POLICIES[index].0;
+        index += 1;
+    }
+    names
+};
+
+// in the order CONFIG GET lists them
+pub(crate) const PARAMETERS: &[Parameter] = &[
+    Parameter {
+        name: "databases",
+        kind: Type::Integer {
+            min: 1,
+            max: i32::MAX as i64,
+        },
+        validate: None,
+        get: |db| Setting::Integer(db.databases() as i64),
+        set: None,
+    },
+    Parameter {
+        name: "port",
+        kind: Type::Integer {
+            min: 0,
+            max: u16::MAX as i64,
+        },
+        validate: None,
+        get: |db| Setting::Integer(db.stats().port() as i64),
+        set: None,
+    },
+    Parameter {
+        name: "cluster-enabled",
+        kind: Type::Bool,
+        validate: None,
+        get: |_| Setting::Bool(false),
+        set: None,
+    },
+    Parameter {
+        name: "maxmemory",
+        kind: Type::Memory,
+        validate: None,
+        get: |db| Setting::Memory(db.eviction().maxmemory()),
+        set: Some(|db, setting| db.eviction().set_maxmemory(setting.integer() as u64)),
+    },
+    Parameter {
+        name: "maxmemory-policy",
+        kind: Type::Enum(POLICY_NAMES),
+        validate: None,
+        get: |db| Setting::Enum(db.eviction().policy().name()),
+        set: Some(|db, setting| {
+            if let Setting::Enum(name) = setting {
+                db.eviction()
+                    .set_policy(Policy::parse(name).unwrap_or(Policy::NoEviction));
+            }
+        }),
+    },
+    Parameter {
+        name: "maxmemory-samples",
+        kind: Type::Integer { min: 1, max: 64 },
+        validate: None,
+        get: |db| Setting::Integer(db.eviction().samples() as i64),
+        set: Some(|db, setting| db.eviction().set_samples(setting.integer() as usize)),
+    },
+    Parameter {
+        name: "timeout",
+        kind: Type::Integer {
+            min: 0,
+            max: i32::MAX as i64,
+        },
+        validate: None,
+        get: |db| Setting::Integer(db.clients().timeout() as i64),
+        set: Some(|db, setting| db.clients().set_timeout(setting.integer() as u64)),
+    },
+    Parameter {
+        name: "requirepass",
+        kind: Type::String,
+        validate: None,
+        get: |db| Setting::String(db.acl().requirepass()),
+        // an empty password is none at all
+        set: Some(|db, setting| {
+            if let Setting::String(password) = setting {
+                db.set_requirepass(Some(password.clone()).filter(|p| !p.is_empty()));
+            }
+        }),
+    },
+    Parameter {
+        name: "notify-keyspace-events",
+        kind: Type::String,
+        validate: Some(|setting| match keyspace_events(setting) {
+            Some(_) => Ok(()),
+            None => Err("Invalid event class character. Use 'Ag$lshzxeKEtmdn'.".to_owned()),
+        }),
+        get: |db| Setting::String(db.keyspace_events().to_string().into_bytes()),
+        set: Some(|db, setting| {
+            db.set_keyspace_events(keyspace_events(setting).unwrap_or(KeyspaceEvents::NONE));
+        }),
+    },
+    Parameter {
+        name: "tracking-table-max-keys",
+        kind: Type::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+        validate: None,
+        get: |db| Setting::Integer(db.tracking_max_keys() as i64),
+        set: Some(|db, setting| db.set_tracking_max_keys(setting.integer() as usize)),
+    },
+    Parameter {
+        name: "busy-reply-threshold",
+        kind: Type::Integer {
+            min: 0,
+            max: i64::MAX,
+        },
+        validate: None,
+        get: |db| Setting::Integer(db.scripts().busy_threshold().as_millis() as i64),
+        set: Some(|db, setting| {
+            db.scripts()
+                .set_busy_threshold(Duration::from_millis(setting.integer() as u64))
+        }),
+    },
+];
+
+// the parameter going by `name`, in any case
+pub(crate) fn parameter(name: &[u8]) -> Option<&'static Parameter> {
+    PARAMETERS
+        .iter()
+        .find(|parameter| name.eq_ignore_ascii_case(parameter.name.as_bytes()))
+}
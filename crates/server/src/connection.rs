@@ -0,0 +1,264 @@
+use std::{
+    collections::VecDeque,
+    io,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use protocol::{ProcVersion, RedirsError, RedirsValue, RespReader};
+use tokio::{
+    io::{AsyncWriteExt, BufWriter},
+    net::{tcp::OwnedWriteHalf, TcpStream},
+};
+
+use crate::{
+    acl::DEFAULT_USER,
+    clients::{Info, Registration},
+    commands::{self, Outcome, Transaction},
+    pubsub::{self, Inbox, Kind, Mailbox, Subscriptions},
+    tracking::Tracking,
+    Db,
+};
+
+// handed out in accept order, the id HELLO reports
+static NEXT_ID: AtomicI64 = AtomicI64::new(1);
+
+// the state of a connection that its commands can change
+pub(crate) struct Client {
+    pub id: i64,
+    // the protocol replies are written in, RESP2 until a HELLO 3
+    pub proto: ProcVersion,
+    pub name: Option<Vec<u8>>,
+    // false until AUTH when the default user has a password
+    pub authenticated: bool,
+    // the user its commands are checked against, see `acl::Acl`
+    pub user: Vec<u8>,
+    // where what is published to its channels is queued
+    pub mailbox: Mailbox,
+    pub subscriptions: Subscriptions,
+    // set by QUIT, the connection closes once the reply is out
+    pub quit: bool,
+    // the commands queued since MULTI
+    pub transaction: Option<Transaction>,
+    // the database SELECT picked, 0 until then
+    pub db: usize,
+    // the keys WATCH was given, by database, with the version each had then
+    pub watched: Vec<(usize, Vec<u8>, u64)>,
+    // what CLIENT LIST shows of it, see `clients`
+    pub registration: Arc<Registration>,
+    // set by CLIENT TRACKING on
+    pub tracking: Option<Tracking>,
+}
+
+impl Client {
+    // whether a RESP2 connection is only left with the subscribe commands, a
+    // RESP3 one can go on with any other in between messages
+    pub fn in_subscribe_mode(&self) -> bool {
+        self.proto == ProcVersion::V2 && !self.subscriptions.is_empty()
+    }
+    // what CLIENT LIST shows brought up to date, as `cmd` is about to run or
+    // once it did
+    fn update(&self, cmd: Option<&[u8]>) {
+        self.registration.update(|info| {
+            info.name.clone_from(&self.name);
+            info.db = self.db;
+            info.subscriptions = self.subscriptions.channels.len();
+            info.patterns = self.subscriptions.patterns.len();
+            info.multi = self.transaction.as_ref().map(Transaction::queued);
+            if let Some(cmd) = cmd {
+                info.cmd = String::from_utf8_lossy(cmd).to_ascii_lowercase();
+                info.last = Instant::now();
+            }
+            info.user.clone_from(&self.user);
+            info.proto = self.proto;
+        });
+    }
+}
+
+pub(crate) async fn handle(stream: TcpStream, db: Db) {
+    let (Ok(addr), Ok(laddr)) = (stream.peer_addr(), stream.local_addr()) else {
+        return;
+    };
+    let (mailbox, inbox) = pubsub::mailbox();
+    let id = NEXT_ID.fetch_add(1, Ordering::Relaxed);
+    let now = Instant::now();
+    let registration = db.clients().register(
+        Info {
+            id,
+            addr,
+            laddr,
+            proto: ProcVersion::V2,
+            name: None,
+            connected: now,
+            last: now,
+            db: 0,
+            subscriptions: 0,
+            patterns: 0,
+            multi: None,
+            blocked: false,
+            cmd: "NULL".to_owned(),
+            user: DEFAULT_USER.to_vec(),
+        },
+        mailbox.clone(),
+    );
+    let mut client = Client {
+        id,
+        proto: ProcVersion::V2,
+        name: None,
+        authenticated: db.acl().open(),
+        user: DEFAULT_USER.to_vec(),
+        mailbox,
+        subscriptions: Subscriptions::default(),
+        quit: false,
+        transaction: None,
+        db: 0,
+        watched: Vec::new(),
+        registration,
+        tracking: None,
+    };
+    db.stats().connected();
+    // the only errors left are writes to a client that went away
+    let _ = serve(stream, &db, &mut client, inbox).await;
+    db.stats().disconnected();
+    db.clients().deregister(client.id);
+    if client.tracking.is_some() {
+        db.tracker().stop(client.id);
+    }
+    commands::unwatch_keys(&mut client, &db);
+    for kind in [Kind::Channel, Kind::Pattern] {
+        for name in client.subscriptions.of(kind).iter() {
+            db.pubsub().unsubscribe(kind, name, client.id);
+        }
+    }
+}
+
+async fn serve(
+    stream: TcpStream,
+    db: &Db,
+    client: &mut Client,
+    mut inbox: Inbox,
+) -> io::Result<()> {
+    let registration = client.registration.clone();
+    let (read, write) = stream.into_split();
+    let mut reader = RespReader::new(read);
+    let mut writer = BufWriter::new(write);
+    // requests read while blocked, served once the wait is over
+    let mut pending = VecDeque::new();
+    loop {
+        let next = match pending.pop_front() {
+            Some(request) => Ok(request),
+            // messages go out as they come in between requests
+            None => loop {
+                tokio::select! {
+                    next = reader.read_value() => break next,
+                    () = registration.killed() => return Ok(()),
+                    Some(message) = inbox.recv() => {
+                        if !forward(message, &mut writer, client.proto, &inbox).await? {
+                            return Ok(());
+                        }
+                    }
+                }
+            },
+        };
+        let request = match next {
+            Ok(request) => request,
+            // the client closed the connection, between commands or in the
+            // middle of one
+            Err(RedirsError::Eof | RedirsError::Io(_)) => return Ok(()),
+            // malformed input, redis replies with the error and hangs up
+            Err(e) => {
+                e.to_client_error()
+                    .write_resp_async(&mut writer, client.proto)
+                    .await?;
+                return writer.flush().await;
+            }
+        };
+        // what reached the connection before the request goes out before its
+        // reply, e.g. the invalidation of a key it is about to read again
+        if !drain(&mut inbox, &mut writer, client.proto).await? {
+            return Ok(());
+        }
+        client.update(commands::name(&request));
+        let replies = match commands::execute(&request, client, db) {
+            Outcome::Reply(reply) => vec![reply],
+            Outcome::Replies(replies) => replies,
+            Outcome::Block(block) => {
+                writer.flush().await?;
+                let _blocked = db.stats().blocked();
+                registration.update(|info| info.blocked = true);
+                let wait = block.wait();
+                tokio::pin!(wait);
+                // reading on notices the client leaving, dropping the block
+                // gives back whatever it was handed
+                let reply = loop {
+                    tokio::select! {
+                        reply = &mut wait => break reply,
+                        () = registration.killed() => return Ok(()),
+                        next = reader.read_value() => match next {
+                            Ok(request) => pending.push_back(request),
+                            Err(_) => return Ok(()),
+                        },
+                        Some(message) = inbox.recv() => {
+                            if !forward(message, &mut writer, client.proto, &inbox).await? {
+                                return Ok(());
+                            }
+                        }
+                    }
+                };
+                registration.update(|info| info.blocked = false);
+                vec![reply]
+            }
+        };
+        client.update(None);
+        if !drain(&mut inbox, &mut writer, client.proto).await? {
+            return Ok(());
+        }
+        for reply in replies {
+            reply.write_resp_async(&mut writer, client.proto).await?;
+        }
+        if client.quit {
+            return writer.flush().await;
+        }
+        // the replies to pipelined requests go out in one write
+        if pending.is_empty() && reader.buffered().is_empty() {
+            writer.flush().await?;
+        }
+    }
+}
+
+// writes out the messages queued, false when the connection fell too far
+// behind
+async fn drain(
+    inbox: &mut Inbox,
+    writer: &mut BufWriter<OwnedWriteHalf>,
+    proto: ProcVersion,
+) -> io::Result<bool> {
+    while let Some(message) = inbox.try_recv() {
+        if !forward(message, writer, proto, inbox).await? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+// writes a published message out, false when the connection fell too far
+// behind first
+async fn forward(
+    message: RedirsValue,
+    writer: &mut BufWriter<OwnedWriteHalf>,
+    proto: ProcVersion,
+    inbox: &Inbox,
+) -> io::Result<bool> {
+    let write = async {
+        message.write_resp_async(writer, proto).await?;
+        writer.flush().await
+    };
+    tokio::select! {
+        biased;
+        _ = inbox.overflowed() => Ok(false),
+        written = write => written.map(|_| true),
+    }
+}
@@ -0,0 +1,1059 @@
+use std::{
+    collections::{hash_map::RandomState, BTreeSet, HashMap, VecDeque},
+    error::Error,
+    fmt::Display,
+    hash::{BuildHasher, BuildHasherDefault, DefaultHasher},
+    mem::size_of,
+    ops::{Deref, DerefMut},
+    sync::{Arc, Mutex, MutexGuard, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use indexmap::{IndexMap, IndexSet};
+use protocol::RedirsValue;
+use rand::Rng;
+
+use crate::{
+    acl::Acl,
+    blocking::{Pop, Popped, Waiters},
+    clients::Clients,
+    clock::{Clock, SystemClock},
+    eviction::Eviction,
+    memory::{self, sampled, KEY_OVERHEAD},
+    notify::{Class, Journal, KeyspaceEvents},
+    pubsub::PubSub,
+    scripting::Scripts,
+    sorted_set::SortedSet,
+    stats::Stats,
+    stream::Stream,
+    tracking::Tracker,
+};
+
+// a command for one type used on a key holding another
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WrongType;
+
+impl Display for WrongType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("WRONGTYPE Operation against a key holding the wrong kind of value")
+    }
+}
+
+impl Error for WrongType {}
+
+impl WrongType {
+    pub fn to_client_error(&self) -> RedirsValue {
+        RedirsValue::SimpleError(self.to_string())
+    }
+}
+
+// what a key holds, every command works on one of these
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    String(Vec<u8>),
+    List(VecDeque<Vec<u8>>),
+    Hash(HashMap<Vec<u8>, Vec<u8>>),
+    // indexed so SPOP and SRANDMEMBER can pick members uniformly
+    Set(IndexSet<Vec<u8>>),
+    SortedSet(SortedSet),
+    Stream(Stream),
+}
+
+impl Value {
+    // the name TYPE replies with
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Hash(_) => "hash",
+            Value::Set(_) => "set",
+            Value::SortedSet(_) => "zset",
+            Value::Stream(_) => "stream",
+        }
+    }
+    // the name OBJECT ENCODING replies with, the one redis gives the
+    // representation each type has here. None of the compact ones redis
+    // switches to for small values (int, embstr, listpack, intset) is used, so
+    // a value keeps its encoding whatever its size
+    pub fn encoding(&self) -> &'static str {
+        match self {
+            Value::String(_) => "raw",
+            Value::List(_) => "linkedlist",
+            Value::Hash(_) | Value::Set(_) => "hashtable",
+            Value::SortedSet(_) => "skiplist",
+            Value::Stream(_) => "stream",
+        }
+    }
+    // what the writes to it are notified as
+    pub(crate) fn class(&self) -> Class {
+        match self {
+            Value::String(_) => Class::String,
+            Value::List(_) => Class::List,
+            Value::Hash(_) => Class::Hash,
+            Value::Set(_) => Class::Set,
+            Value::SortedSet(_) => Class::SortedSet,
+            Value::Stream(_) => Class::Stream,
+        }
+    }
+    // an estimate of the bytes it takes, see `memory`. An aggregate is
+    // measured on its first `samples` elements, all of them for 0
+    pub fn memory_usage(&self, samples: usize) -> usize {
+        let own = size_of::<Value>();
+        let slot = size_of::<Vec<u8>>();
+        match self {
+            Value::String(value) => own + value.capacity(),
+            Value::List(list) => {
+                own + list.capacity() * slot
+                    + sampled(list.iter().map(Vec::capacity), list.len(), samples)
+            }
+            // a control byte for each slot besides the field and the value
+            Value::Hash(hash) => {
+                let sizes = hash.iter().map(|(f, v)| f.capacity() + v.capacity());
+                own + hash.capacity() * (2 * slot + 1) + sampled(sizes, hash.len(), samples)
+            }
+            // the hash of each member and its index kept along
+            Value::Set(set) => {
+                let sizes = set.iter().map(Vec::capacity);
+                own + set.capacity() * (slot + 2 * size_of::<usize>())
+                    + sampled(sizes, set.len(), samples)
+            }
+            Value::SortedSet(zset) => own + zset.memory_usage(samples),
+            Value::Stream(stream) => own + stream.memory_usage(samples),
+        }
+    }
+    // an aggregate left with nothing in it, which redis never keeps around. A
+    // stream stays even when empty, as do strings
+    pub fn is_empty_aggregate(&self) -> bool {
+        match self {
+            Value::List(list) => list.is_empty(),
+            Value::Hash(hash) => hash.is_empty(),
+            Value::Set(set) => set.is_empty(),
+            Value::SortedSet(zset) => zset.is_empty(),
+            Value::String(_) | Value::Stream(_) => false,
+        }
+    }
+}
+
+// the counter a new key starts from, so it is not the first one evicted, and
+// how hard the counter gets to climb and how fast it goes back down, as redis
+// has them by default with lfu-log-factor and lfu-decay-time
+const LFU_INIT: u8 = 5;
+const LFU_LOG_FACTOR: f64 = 10.0;
+const LFU_DECAY: u64 = 60_000;
+
+// a value along with the last time, in `Clock` milliseconds, a command read or
+// wrote to its key, what OBJECT IDLETIME reports, and how often, what OBJECT
+// FREQ reports. The frequency is a logarithmic counter, one less for every
+// minute the key went unused
+#[derive(Debug)]
+struct Entry {
+    value: Value,
+    accessed: u64,
+    frequency: u8,
+}
+
+impl Entry {
+    fn access(&mut self, now: u64) -> &mut Value {
+        self.frequency = self.frequency(now);
+        let above = self.frequency.saturating_sub(LFU_INIT) as f64;
+        if self.frequency < u8::MAX && rand::random::<f64>() < 1.0 / (above * LFU_LOG_FACTOR + 1.0)
+        {
+            self.frequency += 1;
+        }
+        self.accessed = now;
+        &mut self.value
+    }
+    // the counter as of `now`, decayed since the last access
+    fn frequency(&self, now: u64) -> u8 {
+        let periods = now.saturating_sub(self.accessed) / LFU_DECAY;
+        self.frequency
+            .saturating_sub(periods.min(u8::MAX as u64) as u8)
+    }
+}
+
+// a key sampled for eviction, with what the eviction policies rank it by
+#[derive(Debug)]
+pub(crate) struct Candidate {
+    pub key: Vec<u8>,
+    pub idle: u64,
+    pub frequency: u8,
+    pub deadline: Option<u64>,
+}
+
+// the keys of a database, keys and values are binary safe. A key past its
+// deadline is removed by the first access to it, until then it still counts
+// towards `len`
+#[derive(Debug)]
+pub struct Keyspace {
+    // indexed so RANDOMKEY can pick one uniformly
+    entries: IndexMap<Vec<u8>, Entry>,
+    // the deadlines of the keys that have one, in `Clock` milliseconds,
+    // indexed so active expiry can pick random ones
+    expires: IndexMap<Vec<u8>, u64>,
+    clock: Arc<dyn Clock>,
+    // keys removed for reaching their deadline, lazily or by active expiry
+    expired_keys: u64,
+    // keys removed to get back under maxmemory
+    evicted_keys: u64,
+    // reads that found the key and those that did not
+    keyspace_hits: u64,
+    keyspace_misses: u64,
+    // every key by its hash, the order SCAN walks in. Hashes of the keys do not
+    // change as others come and go, so a cursor holding one stays valid
+    scan_order: BTreeSet<(u64, Vec<u8>)>,
+    hasher: RandomState,
+    // connections blocked on keys until they hold a list to pop from
+    waiters: Waiters,
+    // the writes to notify, see `notify`
+    journal: Journal,
+}
+
+// get_x and get_x_mut for the key holding an x, get_or_create_x creating an
+// empty one when the key is missing. All of them fail with `WrongType` when the
+// key holds something else, an expired key is missing. Handing out an x to
+// change journals a write to the key
+macro_rules! accessors {
+    ($($variant:ident($ty:ty) => $get:ident, $get_mut:ident, $get_or_create:ident, $class:ident;)*) => {
+        impl Keyspace {
+            $(
+                pub fn $get(&mut self, key: &[u8]) -> Result<Option<&$ty>, WrongType> {
+                    self.read(key);
+                    match self.entries.get(key).map(|entry| &entry.value) {
+                        None => Ok(None),
+                        Some(Value::$variant(value)) => Ok(Some(value)),
+                        Some(_) => Err(WrongType),
+                    }
+                }
+                pub fn $get_mut(&mut self, key: &[u8]) -> Result<Option<&mut $ty>, WrongType> {
+                    self.expire_if_due(key);
+                    let now = self.clock.now();
+                    match self.entries.get_mut(key).map(|entry| entry.access(now)) {
+                        None => Ok(None),
+                        Some(Value::$variant(value)) => {
+                            self.journal.write(Class::$class, key);
+                            Ok(Some(value))
+                        }
+                        Some(_) => Err(WrongType),
+                    }
+                }
+                pub fn $get_or_create(&mut self, key: &[u8]) -> Result<&mut $ty, WrongType> {
+                    self.expire_if_due(key);
+                    if !self.entries.contains_key(key) {
+                        self.insert(key.to_vec(), Value::$variant(Default::default()));
+                    }
+                    let now = self.clock.now();
+                    match self.entries.get_mut(key).map(|entry| entry.access(now)) {
+                        Some(Value::$variant(value)) => {
+                            self.journal.write(Class::$class, key);
+                            Ok(value)
+                        }
+                        _ => Err(WrongType),
+                    }
+                }
+            )*
+        }
+    };
+}
+
+accessors! {
+    String(Vec<u8>) => get_string, get_string_mut, get_or_create_string, String;
+    List(VecDeque<Vec<u8>>) => get_list, get_list_mut, get_or_create_list, List;
+    Hash(HashMap<Vec<u8>, Vec<u8>>) => get_hash, get_hash_mut, get_or_create_hash, Hash;
+    Set(IndexSet<Vec<u8>>) => get_set, get_set_mut, get_or_create_set, Set;
+    SortedSet(SortedSet) => get_sorted_set, get_sorted_set_mut, get_or_create_sorted_set, SortedSet;
+    Stream(Stream) => get_stream, get_stream_mut, get_or_create_stream, Stream;
+}
+
+impl Keyspace {
+    pub fn new(clock: Arc<dyn Clock>) -> Self {
+        Self {
+            entries: IndexMap::new(),
+            expires: IndexMap::new(),
+            clock,
+            expired_keys: 0,
+            evicted_keys: 0,
+            keyspace_hits: 0,
+            keyspace_misses: 0,
+            scan_order: BTreeSet::new(),
+            hasher: RandomState::new(),
+            waiters: Waiters::default(),
+            journal: Journal::default(),
+        }
+    }
+    // the current time of the clock deadlines are compared with
+    pub fn now(&self) -> u64 {
+        self.clock.now()
+    }
+    // a key is still there at its deadline and gone right after it
+    fn expire_if_due(&mut self, key: &[u8]) {
+        if let Some(&deadline) = self.expires.get(key) {
+            if deadline < self.clock.now() {
+                self.expire(key);
+            }
+        }
+    }
+    // both lazy and active expiry remove keys through here
+    fn expire(&mut self, key: &[u8]) {
+        self.delete(key);
+        self.expired_keys += 1;
+        self.journal.event(Class::Expired, "expired", key);
+    }
+    // one round of active expiry, checks up to `samples` random keys with a
+    // deadline and removes the expired ones. Returns (checked, removed)
+    pub fn expire_sample(&mut self, samples: usize) -> (usize, usize) {
+        let now = self.clock.now();
+        let mut rng = rand::rng();
+        let checked = samples.min(self.expires.len());
+        let mut removed = 0;
+        for _ in 0..checked {
+            let Some((key, &deadline)) = self
+                .expires
+                .get_index(rng.random_range(0..self.expires.len()))
+            else {
+                break;
+            };
+            if deadline < now {
+                let key = key.clone();
+                self.expire(&key);
+                removed += 1;
+            }
+        }
+        (checked, removed)
+    }
+    // a key picked uniformly, none when there are none left. An expired pick is
+    // removed and another one drawn
+    pub fn random_key(&mut self) -> Option<Vec<u8>> {
+        let mut rng = rand::rng();
+        loop {
+            let index = rng.random_range(0..self.entries.len().max(1));
+            let (key, _) = self.entries.get_index(index)?;
+            match self.expires.get(key) {
+                Some(&deadline) if deadline < self.clock.now() => {
+                    let key = key.clone();
+                    self.expire(&key);
+                }
+                _ => return Some(key.clone()),
+            }
+        }
+    }
+    // the number of keys with a deadline
+    pub fn expiring(&self) -> usize {
+        self.expires.len()
+    }
+    pub fn expired_keys(&self) -> u64 {
+        self.expired_keys
+    }
+    pub fn evicted_keys(&self) -> u64 {
+        self.evicted_keys
+    }
+    // up to `samples` keys picked at random for eviction to choose from, only
+    // those with a deadline when `volatile`
+    pub(crate) fn eviction_sample(&self, volatile: bool, samples: usize) -> Vec<Candidate> {
+        let now = self.clock.now();
+        let mut rng = rand::rng();
+        let pool = match volatile {
+            true => self.expires.len(),
+            false => self.entries.len(),
+        };
+        (0..samples.min(pool))
+            .filter_map(|_| {
+                let index = rng.random_range(0..pool);
+                let key = match volatile {
+                    true => self.expires.get_index(index)?.0,
+                    false => self.entries.get_index(index)?.0,
+                };
+                let entry = self.entries.get(key)?;
+                Some(Candidate {
+                    key: key.clone(),
+                    idle: now.saturating_sub(entry.accessed),
+                    frequency: entry.frequency(now),
+                    deadline: self.expires.get(key).copied(),
+                })
+            })
+            .collect()
+    }
+    // removes the key to free memory, notified as evicted. Returns what it
+    // took, see `memory_usage`, none for a missing key
+    pub(crate) fn evict(&mut self, key: &[u8]) -> Option<usize> {
+        let freed = self.memory_usage(key, memory::DEFAULT_SAMPLES)?;
+        self.delete(key);
+        self.evicted_keys += 1;
+        self.journal.event(Class::Evicted, "evicted", key);
+        Some(freed)
+    }
+    // (hits, misses) of the reads, see `read`
+    pub fn keyspace_hits(&self) -> (u64, u64) {
+        (self.keyspace_hits, self.keyspace_misses)
+    }
+    pub fn get(&mut self, key: &[u8]) -> Option<&Value> {
+        self.read(key);
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+    // what the key holds without counting as an access to it, for the
+    // commands looking at keys rather than using them
+    pub fn peek(&mut self, key: &[u8]) -> Option<&Value> {
+        self.expire_if_due(key);
+        self.entries.get(key).map(|entry| &entry.value)
+    }
+    // OBJECT IDLETIME: the milliseconds since the key was last accessed, none
+    // for a missing key
+    pub fn idle_time(&mut self, key: &[u8]) -> Option<u64> {
+        self.expire_if_due(key);
+        let accessed = self.entries.get(key)?.accessed;
+        Some(self.clock.now().saturating_sub(accessed))
+    }
+    // OBJECT FREQ: the access frequency counter of the key, none for a
+    // missing key
+    pub fn frequency(&mut self, key: &[u8]) -> Option<u8> {
+        self.expire_if_due(key);
+        let now = self.clock.now();
+        Some(self.entries.get(key)?.frequency(now))
+    }
+    // the key counts as accessed now
+    fn access(&mut self, key: &[u8]) {
+        self.expire_if_due(key);
+        let now = self.clock.now();
+        if let Some(entry) = self.entries.get_mut(key) {
+            entry.access(now);
+        }
+    }
+    // a command reading the key, an access that is a keyspace hit when the
+    // key is there and a miss when not
+    fn read(&mut self, key: &[u8]) {
+        self.access(key);
+        match self.entries.contains_key(key) {
+            true => self.keyspace_hits += 1,
+            false => self.keyspace_misses += 1,
+        }
+    }
+    // the `Value::type_name` of what the key holds, none for a missing key
+    pub fn key_type(&mut self, key: &[u8]) -> Option<&'static str> {
+        self.peek(key).map(Value::type_name)
+    }
+    // replaces whatever the key held, of any type, and drops its deadline
+    pub fn set(&mut self, key: Vec<u8>, value: Value) -> Option<Value> {
+        self.expires.swap_remove(&key);
+        self.set_keep_ttl(key, value)
+    }
+    // replaces whatever the key held, a deadline stays in place
+    pub fn set_keep_ttl(&mut self, key: Vec<u8>, value: Value) -> Option<Value> {
+        self.expire_if_due(&key);
+        self.journal.write(value.class(), &key);
+        self.insert(key, value)
+    }
+    fn insert(&mut self, key: Vec<u8>, value: Value) -> Option<Value> {
+        if !self.entries.contains_key(&key) {
+            self.scan_order
+                .insert((self.hasher.hash_one(&key), key.clone()));
+        }
+        let accessed = self.clock.now();
+        let entry = Entry {
+            value,
+            accessed,
+            frequency: LFU_INIT,
+        };
+        self.entries.insert(key, entry).map(|entry| entry.value)
+    }
+    // none for a missing key, an expired one included
+    pub fn remove(&mut self, key: &[u8]) -> Option<Value> {
+        self.expire_if_due(key);
+        let removed = self.delete(key)?;
+        self.journal.event(Class::Generic, "del", key);
+        Some(removed)
+    }
+    fn delete(&mut self, key: &[u8]) -> Option<Value> {
+        self.expires.swap_remove(key);
+        let value = self.entries.swap_remove(key)?.value;
+        self.scan_order
+            .remove(&(self.hasher.hash_one(key), key.to_vec()));
+        Some(value)
+    }
+    // the sets at `keys`, none for the missing ones. Fails when any of them
+    // holds something else
+    pub fn get_sets(
+        &mut self,
+        keys: &[&[u8]],
+    ) -> Result<Vec<Option<&IndexSet<Vec<u8>>>>, WrongType> {
+        for key in keys {
+            self.read(key);
+        }
+        keys.iter()
+            .map(
+                |key| match self.entries.get(*key).map(|entry| &entry.value) {
+                    None => Ok(None),
+                    Some(Value::Set(set)) => Ok(Some(set)),
+                    Some(_) => Err(WrongType),
+                },
+            )
+            .collect()
+    }
+    // the strings at `keys`, none for the missing ones. Fails when any of
+    // them holds something else
+    pub fn get_strings(&mut self, keys: &[&[u8]]) -> Result<Vec<Option<&[u8]>>, WrongType> {
+        for key in keys {
+            self.read(key);
+        }
+        keys.iter()
+            .map(
+                |key| match self.entries.get(*key).map(|entry| &entry.value) {
+                    None => Ok(None),
+                    Some(Value::String(value)) => Ok(Some(&value[..])),
+                    Some(_) => Err(WrongType),
+                },
+            )
+            .collect()
+    }
+    pub(crate) fn waiters_mut(&mut self) -> &mut Waiters {
+        &mut self.waiters
+    }
+    // hands the elements of the list or sorted set at `key` to the
+    // connections blocked on it popping that type, the longest waiting first,
+    // for as long as there are both
+    pub(crate) fn serve_waiters(&mut self, key: &[u8]) {
+        loop {
+            let list = matches!(self.peek(key), Some(Value::List(_)));
+            let Some(waiter) = self
+                .waiters
+                .first(key, |pop| matches!(pop, Pop::List(_)) == list)
+            else {
+                break;
+            };
+            let popped = match waiter.pop() {
+                Pop::List(end) => match self.get_list_mut(key) {
+                    Ok(Some(list)) => end.pop(list).map(Popped::Element),
+                    _ => None,
+                },
+                Pop::SortedSet(end) => match self.get_sorted_set_mut(key) {
+                    Ok(Some(zset)) => end
+                        .pop_member(zset)
+                        .map(|(member, score)| Popped::Member(member, score)),
+                    _ => None,
+                },
+            };
+            let Some(popped) = popped else {
+                break;
+            };
+            self.waiters.remove(&waiter);
+            waiter.deliver(key.to_vec(), popped);
+        }
+        self.remove_if_empty(key);
+    }
+    // what was handed to a connection that left before replying goes back
+    // where it came from
+    pub(crate) fn give_back(&mut self, key: &[u8], pop: Pop, popped: Popped) {
+        match popped {
+            Popped::Element(element) => {
+                let (Pop::List(end), Ok(list)) = (pop, self.get_or_create_list(key)) else {
+                    return;
+                };
+                end.push(list, element);
+            }
+            Popped::Member(member, score) => {
+                let Ok(zset) = self.get_or_create_sorted_set(key) else {
+                    return;
+                };
+                zset.insert(member, score);
+            }
+        }
+        self.serve_waiters(key);
+    }
+    // removes the key once a command took the last element out of it
+    pub fn remove_if_empty(&mut self, key: &[u8]) {
+        if self
+            .entries
+            .get(key)
+            .is_some_and(|entry| entry.value.is_empty_aggregate())
+        {
+            self.delete(key);
+            self.journal.event(Class::Generic, "del", key);
+        }
+    }
+    // MEMORY USAGE: what the key and its value take, none for a missing key
+    pub fn memory_usage(&mut self, key: &[u8], samples: usize) -> Option<usize> {
+        let value = self.peek(key)?.memory_usage(samples);
+        let deadline = match self.expires.contains_key(key) {
+            true => size_of::<Vec<u8>>() + key.len() + size_of::<u64>(),
+            false => 0,
+        };
+        Some(KEY_OVERHEAD + 2 * key.len() + value + deadline)
+    }
+    // the estimate of every key of the database, what INFO reports. It goes
+    // through all of them, expired ones included
+    pub fn used_memory(&self) -> usize {
+        let deadlines = self
+            .expires
+            .keys()
+            .map(|key| memory::bytes(key) + size_of::<u64>())
+            .sum::<usize>();
+        let keys = self
+            .entries
+            .iter()
+            .map(|(key, entry)| {
+                KEY_OVERHEAD
+                    + 2 * key.capacity()
+                    + entry.value.memory_usage(memory::DEFAULT_SAMPLES)
+            })
+            .sum::<usize>();
+        keys + deadlines
+    }
+    pub fn exists(&mut self, key: &[u8]) -> bool {
+        self.access(key);
+        self.entries.contains_key(key)
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    // the keys `filter` accepts, the expired ones are removed instead
+    pub fn keys(&mut self, filter: impl Fn(&[u8]) -> bool) -> Vec<Vec<u8>> {
+        let now = self.clock.now();
+        let mut expired = Vec::new();
+        let mut keys = Vec::new();
+        for key in self.entries.keys() {
+            match self.expires.get(key) {
+                Some(&deadline) if deadline < now => expired.push(key.clone()),
+                _ if filter(key) => keys.push(key.clone()),
+                _ => {}
+            }
+        }
+        for key in expired {
+            self.expire(&key);
+        }
+        keys
+    }
+    // one SCAN step, the keys from `cursor` on in hash order and the cursor to
+    // go on from, 0 once every key was seen. See `scan_step`, expired keys are
+    // removed instead of returned
+    pub fn scan(&mut self, cursor: u64, count: usize) -> (u64, Vec<Vec<u8>>) {
+        let now = self.clock.now();
+        let ordered = self
+            .scan_order
+            .range((cursor, Vec::new())..)
+            .map(|(hash, key)| (*hash, &key[..]));
+        let (next, found) = scan_step(ordered, count);
+        let (expired, keys): (Vec<_>, Vec<_>) =
+            found.into_iter().map(<[u8]>::to_vec).partition(|key| {
+                self.expires
+                    .get(key)
+                    .is_some_and(|&deadline| deadline < now)
+            });
+        for key in expired {
+            self.expire(&key);
+        }
+        (next, keys)
+    }
+    // moves the value and the deadline of `from` to `to`, replacing what `to`
+    // held. False when `from` is missing
+    pub fn rename(&mut self, from: &[u8], to: &[u8]) -> bool {
+        if !self.exists(from) {
+            return false;
+        }
+        if from != to {
+            let deadline = self.expires.get(from).copied();
+            let value = self.delete(from).expect("the key exists");
+            self.journal.event(Class::Generic, "rename_from", from);
+            self.set(to.to_vec(), value);
+            self.journal.event(Class::Generic, "rename_to", to);
+            if let Some(deadline) = deadline {
+                self.expires.insert(to.to_vec(), deadline);
+            }
+            self.serve_waiters(to);
+        }
+        true
+    }
+    // a deep copy of the value of `from` placed at `to` along with its
+    // deadline. False when `from` is missing or `to` exists and is not
+    // to be replaced
+    pub fn copy(&mut self, from: &[u8], to: &[u8], replace: bool) -> bool {
+        let Some(value) = self.get(from).cloned() else {
+            return false;
+        };
+        if !replace && self.exists(to) {
+            return false;
+        }
+        let deadline = self.expires.get(from).copied();
+        self.set(to.to_vec(), value);
+        self.journal.event(Class::Generic, "copy_to", to);
+        if let Some(deadline) = deadline {
+            self.expires.insert(to.to_vec(), deadline);
+        }
+        self.serve_waiters(to);
+        true
+    }
+    // the deadline of a key, none for a missing key or one without
+    pub fn deadline(&mut self, key: &[u8]) -> Option<u64> {
+        self.expire_if_due(key);
+        self.expires.get(key).copied()
+    }
+    // false for a missing key. A deadline that already passed removes the key
+    // right away, as redis does
+    pub fn expire_at(&mut self, key: &[u8], deadline: u64) -> bool {
+        if !self.exists(key) {
+            return false;
+        }
+        match deadline <= self.clock.now() {
+            true => {
+                self.delete(key);
+                self.journal.event(Class::Generic, "del", key);
+            }
+            false => {
+                self.expires.insert(key.to_vec(), deadline);
+                self.journal.event(Class::Generic, "expire", key);
+            }
+        }
+        true
+    }
+    // WATCH: the version of the key for one more connection watching it,
+    // changed by every write to the key while it is watched
+    pub(crate) fn watch(&mut self, key: &[u8]) -> u64 {
+        self.expire_if_due(key);
+        self.journal.watch(key)
+    }
+    pub(crate) fn unwatch(&mut self, key: &[u8]) {
+        self.journal.unwatch(key);
+    }
+    // whether the key was written to since it was watched at `version`, by
+    // expiring too
+    pub(crate) fn changed(&mut self, key: &[u8], version: u64) -> bool {
+        self.expire_if_due(key);
+        self.journal.version(key) != Some(version)
+    }
+    // the value and the deadline of a key for MOVE, out of this database
+    // and notified as `event`
+    pub(crate) fn take(&mut self, key: &[u8], event: &'static str) -> Option<(Value, Option<u64>)> {
+        self.expire_if_due(key);
+        let deadline = self.expires.get(key).copied();
+        let value = self.delete(key)?;
+        self.journal.event(Class::Generic, event, key);
+        Some((value, deadline))
+    }
+    // the other end of `take`, or of a COPY to another database, replacing
+    // what the key held
+    pub(crate) fn place(
+        &mut self,
+        key: &[u8],
+        value: Value,
+        deadline: Option<u64>,
+        event: &'static str,
+    ) {
+        self.set(key.to_vec(), value);
+        self.journal.event(Class::Generic, event, key);
+        if let Some(deadline) = deadline {
+            self.expires.insert(key.to_vec(), deadline);
+        }
+        self.serve_waiters(key);
+    }
+    // FLUSHDB: every key gone at once. The watched ones that were there count
+    // as written to but, as with redis, no event is notified. What the keys
+    // held is handed back for the caller to drop, which can take a while
+    pub(crate) fn flush(&mut self) -> Flushed {
+        for key in self.entries.keys() {
+            self.journal.touch(key);
+        }
+        self.journal.flushed();
+        Flushed {
+            _entries: std::mem::take(&mut self.entries),
+            _expires: std::mem::take(&mut self.expires),
+            _scan_order: std::mem::take(&mut self.scan_order),
+        }
+    }
+    // SWAPDB: the keys of the two databases change places. Their watched
+    // keys count as written to and their blocked connections are served
+    // from what they now hold, the connections stay where they are
+    pub(crate) fn swap_keys(&mut self, other: &mut Keyspace) {
+        std::mem::swap(&mut self.entries, &mut other.entries);
+        std::mem::swap(&mut self.expires, &mut other.expires);
+        std::mem::swap(&mut self.scan_order, &mut other.scan_order);
+        std::mem::swap(&mut self.hasher, &mut other.hasher);
+        for keyspace in [self, other] {
+            keyspace.journal.touch_all();
+            for key in keyspace.waiters.keys() {
+                keyspace.serve_waiters(&key);
+            }
+        }
+    }
+    // drops the deadline of a key, false when it had none
+    pub fn persist(&mut self, key: &[u8]) -> bool {
+        self.expire_if_due(key);
+        let persisted = self.expires.swap_remove(key).is_some();
+        if persisted {
+            self.journal.event(Class::Generic, "persist", key);
+        }
+        persisted
+    }
+}
+
+// the keys and values `Keyspace::flush` took out, freed when this is dropped
+pub(crate) struct Flushed {
+    _entries: IndexMap<Vec<u8>, Entry>,
+    _expires: IndexMap<Vec<u8>, u64>,
+    _scan_order: BTreeSet<(u64, Vec<u8>)>,
+}
+
+// the names from `ordered`, sorted by hash, making up one step of a scan. A
+// step stops after `count` names but never between names of the same hash, so
+// a name present for the whole scan is returned at least once whatever
+// happens to the others. The cursor is the hash to go on from, 0 once done
+pub(crate) fn scan_step<'a>(
+    ordered: impl Iterator<Item = (u64, &'a [u8])>,
+    count: usize,
+) -> (u64, Vec<&'a [u8]>) {
+    let mut names = Vec::new();
+    let mut last = None;
+    for (hash, name) in ordered {
+        if names.len() >= count && last != Some(hash) {
+            return (hash, names);
+        }
+        names.push(name);
+        last = Some(hash);
+    }
+    (0, names)
+}
+
+// one step of a scan over the members of a hash, set or sorted set, which
+// keep no order of their own. They are sorted by a fixed hash on every step,
+// so the cursor means the same from one step to the next
+pub(crate) fn scan_members<'a>(
+    members: impl Iterator<Item = &'a [u8]>,
+    cursor: u64,
+    count: usize,
+) -> (u64, Vec<&'a [u8]>) {
+    let hasher = BuildHasherDefault::<DefaultHasher>::default();
+    let mut ordered: Vec<_> = members
+        .map(|member| (hasher.hash_one(member), member))
+        .filter(|(hash, _)| *hash >= cursor)
+        .collect();
+    ordered.sort_unstable();
+    scan_step(ordered.into_iter(), count)
+}
+
+impl Default for Keyspace {
+    fn default() -> Self {
+        Self::new(Arc::new(SystemClock::new()))
+    }
+}
+
+// as many databases as redis has by default
+pub const DEFAULT_DATABASES: usize = 16;
+
+// the databases shared by every connection, each a keyspace of its own. A
+// handle works on one of them, see `select`. The locks are held for single
+// commands only, never across an `.await`
+#[derive(Debug, Clone)]
+pub struct Db {
+    keyspaces: Arc<[Mutex<Keyspace>]>,
+    // the database `lock` locks
+    index: usize,
+    // the channels connections subscribed to, with a lock of its own
+    pubsub: Arc<PubSub>,
+    // the scripts loaded, by their SHA1
+    scripts: Arc<Scripts>,
+    // the users connections authenticate as
+    acl: Arc<Acl>,
+    stats: Arc<Stats>,
+    // the live connections, see `clients`
+    clients: Arc<Clients>,
+    // the keys connections track, see `tracking`
+    tracker: Arc<Tracker>,
+    // maxmemory and what to do past it, see `eviction`
+    eviction: Arc<Eviction>,
+    // held shared by every command and exclusively by EXEC, which then runs
+    // a whole transaction with nothing in between
+    gate: Arc<RwLock<()>>,
+    // what the writes made through this handle are notified as, see
+    // `notify::command_event`
+    event: Option<&'static str>,
+}
+
+impl Default for Db {
+    fn default() -> Self {
+        Self::build(Arc::new(SystemClock::new()), DEFAULT_DATABASES)
+    }
+}
+
+impl Db {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    // deadlines measured with `clock` instead of the system one, e.g. a
+    // `ManualClock` in tests
+    pub fn with_clock(clock: impl Clock + 'static) -> Self {
+        Self::build(Arc::new(clock), DEFAULT_DATABASES)
+    }
+    // `count` databases instead of `DEFAULT_DATABASES`, one at least
+    pub fn with_databases(count: usize) -> Self {
+        Self::build(Arc::new(SystemClock::new()), count.max(1))
+    }
+    fn build(clock: Arc<dyn Clock>, count: usize) -> Self {
+        Self {
+            keyspaces: (0..count)
+                .map(|_| Mutex::new(Keyspace::new(clock.clone())))
+                .collect(),
+            index: 0,
+            pubsub: Arc::default(),
+            scripts: Arc::default(),
+            acl: Arc::default(),
+            stats: Arc::default(),
+            clients: Arc::default(),
+            tracker: Arc::default(),
+            eviction: Arc::default(),
+            gate: Arc::default(),
+            event: None,
+        }
+    }
+    pub fn databases(&self) -> usize {
+        self.keyspaces.len()
+    }
+    // the database this handle works on, 0 unless selected
+    pub fn index(&self) -> usize {
+        self.index
+    }
+    // a handle on database `index`, which must be below `databases`
+    pub fn select(&self, index: usize) -> Db {
+        assert!(index < self.databases(), "no database {index}");
+        Db {
+            index,
+            ..self.clone()
+        }
+    }
+    // a panic in one command must not take every other connection down with
+    // it, so poisoning is ignored. The writes made under the lock are
+    // notified as it is given back
+    pub fn lock(&self) -> Guard<'_> {
+        self.lock_index(self.index)
+    }
+    fn lock_index(&self, index: usize) -> Guard<'_> {
+        let mut keyspace = self.keyspaces[index]
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner());
+        keyspace.journal.tracking = self.tracker.active();
+        Guard {
+            keyspace,
+            index,
+            db: self,
+        }
+    }
+    // this database and database `other` locked together, in the order of
+    // their indexes so two commands doing the same never wait on each other.
+    // None when they are the same
+    pub(crate) fn lock_with(&self, other: usize) -> Option<(Guard<'_>, Guard<'_>)> {
+        match self.index.cmp(&other) {
+            std::cmp::Ordering::Equal => None,
+            std::cmp::Ordering::Less => {
+                let this = self.lock_index(self.index);
+                Some((this, self.lock_index(other)))
+            }
+            std::cmp::Ordering::Greater => {
+                let other = self.lock_index(other);
+                Some((self.lock_index(self.index), other))
+            }
+        }
+    }
+    pub(crate) fn pubsub(&self) -> &PubSub {
+        &self.pubsub
+    }
+    pub(crate) fn scripts(&self) -> &Scripts {
+        &self.scripts
+    }
+    pub(crate) fn acl(&self) -> &Acl {
+        &self.acl
+    }
+    pub(crate) fn stats(&self) -> &Stats {
+        &self.stats
+    }
+    pub(crate) fn clients(&self) -> &Clients {
+        &self.clients
+    }
+    pub(crate) fn tracker(&self) -> &Tracker {
+        &self.tracker
+    }
+    pub(crate) fn eviction(&self) -> &Eviction {
+        &self.eviction
+    }
+    // CLIENT TRACKING forgets the oldest readers past as many keys, none for
+    // no limit
+    pub fn set_tracking_max_keys(&self, max_keys: usize) {
+        self.tracker.set_max_keys(max_keys);
+    }
+    pub fn tracking_max_keys(&self) -> usize {
+        self.tracker.max_keys()
+    }
+    pub(crate) fn shared(&self) -> RwLockReadGuard<'_, ()> {
+        self.gate
+            .read()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    pub(crate) fn exclusive(&self) -> RwLockWriteGuard<'_, ()> {
+        self.gate
+            .write()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+    // the same database for a command, its writes notified as `event`
+    pub(crate) fn for_command(&self, event: Option<&'static str>) -> Db {
+        Db {
+            event,
+            ..self.clone()
+        }
+    }
+    // the keyspace notifications to publish, from every database, none
+    // unless set
+    pub fn set_keyspace_events(&self, events: KeyspaceEvents) {
+        for index in 0..self.databases() {
+            self.lock_index(index).journal.wanted = events;
+        }
+    }
+    // the estimated memory of the keys of every database, see
+    // `Keyspace::used_memory`
+    pub fn used_memory(&self) -> usize {
+        (0..self.databases())
+            .map(|index| self.lock_index(index).used_memory())
+            .sum()
+    }
+    pub fn keyspace_events(&self) -> KeyspaceEvents {
+        self.lock().journal.wanted
+    }
+    // the password of the default user, which connections made from now on
+    // then have to AUTH with. None for them to be authenticated from the
+    // start
+    pub fn set_requirepass(&self, password: Option<Vec<u8>>) {
+        self.acl.set_requirepass(password);
+    }
+}
+
+// the keyspace locked by `Db::lock`
+pub struct Guard<'a> {
+    keyspace: MutexGuard<'a, Keyspace>,
+    // the database it is, what its notifications name
+    index: usize,
+    db: &'a Db,
+}
+
+impl Deref for Guard<'_> {
+    type Target = Keyspace;
+
+    fn deref(&self) -> &Keyspace {
+        &self.keyspace
+    }
+}
+
+impl DerefMut for Guard<'_> {
+    fn deref_mut(&mut self) -> &mut Keyspace {
+        &mut self.keyspace
+    }
+}
+
+// published before the lock goes, so they come in the order of the writes
+impl Drop for Guard<'_> {
+    fn drop(&mut self) {
+        self.keyspace
+            .journal
+            .publish(&self.db.pubsub, self.index, self.db.event);
+        let (written, flushed) = self.keyspace.journal.written();
+        if !written.is_empty() || flushed {
+            let db = self.db;
+            db.tracker
+                .invalidate(written, flushed, &db.clients, &db.pubsub);
+        }
+    }
+}
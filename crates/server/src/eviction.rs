@@ -0,0 +1,168 @@
+// keeping the keys under maxmemory: before a command that may take more
+// memory, keys are evicted as maxmemory-policy says until the estimate of
+// what they take is back under it, or the command is refused when none can
+// go. Measuring every key is slow, so a measure is reused for a while and
+// lowered by what each eviction frees in between
+
+use std::{
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{db::Candidate, Db};
+
+// how long a measure of the keys is trusted for
+const MEASURE_EVERY: Duration = Duration::from_millis(100);
+
+// the keys eviction picks from, volatile ones only those with a deadline, and
+// what it picks them by
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Policy {
+    VolatileLru,
+    AllkeysLru,
+    VolatileLfu,
+    AllkeysLfu,
+    VolatileRandom,
+    AllkeysRandom,
+    VolatileTtl,
+    NoEviction,
+}
+
+// the names maxmemory-policy takes, in the order redis lists them
+pub(crate) const POLICIES: &[(&str, Policy)] = &[
+    ("volatile-lru", Policy::VolatileLru),
+    ("allkeys-lru", Policy::AllkeysLru),
+    ("volatile-lfu", Policy::VolatileLfu),
+    ("allkeys-lfu", Policy::AllkeysLfu),
+    ("volatile-random", Policy::VolatileRandom),
+    ("allkeys-random", Policy::AllkeysRandom),
+    ("volatile-ttl", Policy::VolatileTtl),
+    ("noeviction", Policy::NoEviction),
+];
+
+impl Policy {
+    pub fn parse(name: &str) -> Option<Policy> {
+        POLICIES
+            .iter()
+            .find(|(policy, _)| name.eq_ignore_ascii_case(policy))
+            .map(|(_, policy)| *policy)
+    }
+    pub fn name(self) -> &'static str {
+        let (name, _) = POLICIES
+            .iter()
+            .find(|(_, policy)| *policy == self)
+            .expect("every policy is named");
+        name
+    }
+    pub fn is_lfu(self) -> bool {
+        matches!(self, Policy::VolatileLfu | Policy::AllkeysLfu)
+    }
+    fn volatile(self) -> bool {
+        matches!(
+            self,
+            Policy::VolatileLru
+                | Policy::VolatileLfu
+                | Policy::VolatileRandom
+                | Policy::VolatileTtl
+        )
+    }
+    // how much better a pick `candidate` is, the largest going first
+    fn rank(self, candidate: &Candidate) -> u64 {
+        match self {
+            Policy::VolatileLru | Policy::AllkeysLru => candidate.idle,
+            Policy::VolatileLfu | Policy::AllkeysLfu => u8::MAX as u64 - candidate.frequency as u64,
+            Policy::VolatileTtl => u64::MAX - candidate.deadline.unwrap_or(u64::MAX),
+            Policy::VolatileRandom | Policy::AllkeysRandom | Policy::NoEviction => 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Eviction {
+    // the bytes the keys may take, 0 for no limit
+    maxmemory: AtomicU64,
+    policy: Mutex<Policy>,
+    // the keys sampled from each database per eviction
+    samples: AtomicUsize,
+    // the last measure of the keys and when it was taken
+    measured: Mutex<Option<(Instant, usize)>>,
+}
+
+impl Default for Eviction {
+    fn default() -> Self {
+        Self {
+            maxmemory: AtomicU64::new(0),
+            policy: Mutex::new(Policy::NoEviction),
+            samples: AtomicUsize::new(5),
+            measured: Mutex::new(None),
+        }
+    }
+}
+
+impl Eviction {
+    pub fn maxmemory(&self) -> u64 {
+        self.maxmemory.load(Ordering::Relaxed)
+    }
+    // a new limit is checked against a fresh measure
+    pub fn set_maxmemory(&self, bytes: u64) {
+        self.maxmemory.store(bytes, Ordering::Relaxed);
+        *self.measured.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+    pub fn policy(&self) -> Policy {
+        *self.policy.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    pub fn set_policy(&self, policy: Policy) {
+        *self.policy.lock().unwrap_or_else(|e| e.into_inner()) = policy;
+    }
+    pub fn samples(&self) -> usize {
+        self.samples.load(Ordering::Relaxed)
+    }
+    pub fn set_samples(&self, samples: usize) {
+        self.samples.store(samples, Ordering::Relaxed);
+    }
+}
+
+// false when the keys take more than maxmemory and the policy has none left
+// to evict, the command then refused with an OOM error
+pub(crate) fn make_room(db: &Db) -> bool {
+    let eviction = db.eviction();
+    let maxmemory = eviction.maxmemory() as usize;
+    if maxmemory == 0 {
+        return true;
+    }
+    // poisoning is ignored, a panic elsewhere must not fail every command
+    // that may take more memory
+    let mut measured = eviction.measured.lock().unwrap_or_else(|e| e.into_inner());
+    let (at, mut used) = match *measured {
+        Some((at, used)) if at.elapsed() < MEASURE_EVERY => (at, used),
+        _ => (Instant::now(), db.used_memory()),
+    };
+    let policy = eviction.policy();
+    let fits = loop {
+        if used <= maxmemory {
+            break true;
+        }
+        if policy == Policy::NoEviction {
+            break false;
+        }
+        let best = (0..db.databases())
+            .flat_map(|index| {
+                db.select(index)
+                    .lock()
+                    .eviction_sample(policy.volatile(), eviction.samples())
+                    .into_iter()
+                    .map(move |candidate| (index, candidate))
+            })
+            .max_by_key(|(_, candidate)| policy.rank(candidate));
+        let Some((index, candidate)) = best else {
+            break false;
+        };
+        let freed = db.select(index).lock().evict(&candidate.key).unwrap_or(0);
+        used = used.saturating_sub(freed);
+    };
+    *measured = Some((at, used));
+    fits
+}
@@ -0,0 +1,35 @@
+use std::time::{Duration, Instant};
+
+use tokio::time::MissedTickBehavior;
+
+use crate::Db;
+
+// as often and as many keys as redis checks by default
+pub const DEFAULT_EXPIRE_INTERVAL: Duration = Duration::from_millis(100);
+pub const DEFAULT_EXPIRE_SAMPLES: usize = 20;
+
+// removes the expired keys nobody reads. Every `interval` it samples keys with
+// a deadline in each database and goes for another round while more than a
+// quarter of them had expired, for at most a quarter of the interval in all.
+// The lock is taken per round, so commands run in between
+pub async fn active_expiry(db: Db, interval: Duration, samples: usize) {
+    let mut ticks = tokio::time::interval(interval);
+    ticks.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    loop {
+        ticks.tick().await;
+        let budget = Instant::now() + interval / 4;
+        for index in 0..db.databases() {
+            let db = db.select(index);
+            loop {
+                // not in the middle of a transaction
+                let shared = db.shared();
+                let (checked, removed) = db.lock().expire_sample(samples);
+                drop(shared);
+                if removed * 4 <= checked || Instant::now() >= budget {
+                    break;
+                }
+                tokio::task::yield_now().await;
+            }
+        }
+    }
+}
@@ -0,0 +1,105 @@
+// redis glob patterns over bytes, for KEYS, SCAN MATCH and PSUBSCRIBE:
+// `*` any run of bytes, `?` any one byte, `[abc]`, `[a-z]` and `[^a]` classes
+// and `\` escaping the byte after it
+
+pub fn matches(pattern: &[u8], input: &[u8]) -> bool {
+    matches_with(pattern, input, false)
+}
+
+// ASCII letters match either case, as CONFIG GET does
+pub fn matches_nocase(pattern: &[u8], input: &[u8]) -> bool {
+    matches_with(pattern, input, true)
+}
+
+fn eq(a: u8, b: u8, nocase: bool) -> bool {
+    match nocase {
+        true => a.eq_ignore_ascii_case(&b),
+        false => a == b,
+    }
+}
+
+// whether the class starting after the `[` at `at` matches `byte`, and the
+// position after the class. An unterminated class runs to the end of the
+// pattern and `[]` matches nothing
+fn class(pattern: &[u8], mut at: usize, byte: u8, nocase: bool) -> (bool, usize) {
+    let negated = pattern.get(at) == Some(&b'^');
+    if negated {
+        at += 1;
+    }
+    let mut matched = false;
+    while at < pattern.len() {
+        match pattern[at..] {
+            [b'\\', escaped, ..] => {
+                matched |= eq(escaped, byte, nocase);
+                at += 2;
+            }
+            [b']', ..] => {
+                at += 1;
+                break;
+            }
+            [start, b'-', end, ..] => {
+                let (mut start, mut end, mut byte) = (start.min(end), start.max(end), byte);
+                if nocase {
+                    start.make_ascii_lowercase();
+                    end.make_ascii_lowercase();
+                    byte.make_ascii_lowercase();
+                }
+                matched |= (start..=end).contains(&byte);
+                at += 3;
+            }
+            [other, ..] => {
+                matched |= eq(other, byte, nocase);
+                at += 1;
+            }
+            [] => unreachable!(),
+        }
+    }
+    (matched != negated, at)
+}
+
+// one pass over the input, a mismatch resumes after the last `*` with one more
+// byte swallowed by it. Earlier stars never need revisiting, so the work is
+// bounded by pattern length times input length whatever the pattern
+fn matches_with(pattern: &[u8], input: &[u8], nocase: bool) -> bool {
+    let (mut p, mut s) = (0, 0);
+    // the pattern position after the last star and the input it swallowed up to
+    let mut star: Option<(usize, usize)> = None;
+    while s < input.len() {
+        let step = match pattern.get(p) {
+            Some(b'*') => {
+                while pattern.get(p) == Some(&b'*') {
+                    p += 1;
+                }
+                if p == pattern.len() {
+                    return true;
+                }
+                star = Some((p, s));
+                continue;
+            }
+            Some(b'?') => Some(p + 1),
+            Some(b'[') => match class(pattern, p + 1, input[s], nocase) {
+                (true, next) => Some(next),
+                (false, _) => None,
+            },
+            // a trailing backslash stands for itself
+            Some(b'\\') if p + 1 < pattern.len() => {
+                eq(pattern[p + 1], input[s], nocase).then_some(p + 2)
+            }
+            Some(&literal) => eq(literal, input[s], nocase).then_some(p + 1),
+            None => None,
+        };
+        match (step, star) {
+            (Some(next), _) => {
+                p = next;
+                s += 1;
+            }
+            (None, Some((after, swallowed))) => {
+                p = after;
+                s = swallowed + 1;
+                star = Some((after, s));
+            }
+            (None, None) => return false,
+        }
+    }
+    pattern[p..].iter().all(|&byte| byte == b'*')
+}
@@ -0,0 +1,183 @@
+// the value of a HyperLogLog key: a string laid out as the dense encoding of
+// redis, a 16 byte header then 16384 registers of 6 bits each. The header is
+// "HYLL", the encoding, three unused bytes and the last count worked out,
+// little endian, its highest bit set once a change made it stale
+
+pub const REGISTERS: usize = 1 << P;
+// the bits of the hash that pick a register
+const P: u32 = 14;
+// the bits of the hash left to count a run of zeros in
+const Q: u32 = 64 - P;
+const BITS: usize = 6;
+const HEADER: usize = 16;
+pub const LEN: usize = HEADER + (REGISTERS * BITS).div_ceil(8);
+const MAGIC: &[u8] = b"HYLL";
+const DENSE: u8 = 0;
+const SEED: u64 = 0xadc8_3b19;
+
+// the registers unpacked, one byte each
+pub type Registers = [u8; REGISTERS];
+
+// an empty HyperLogLog, counting 0
+pub fn new() -> Vec<u8> {
+    let mut value = vec![0; LEN];
+    value[..4].copy_from_slice(MAGIC);
+    value[4] = DENSE;
+    value
+}
+
+// whether a string is a HyperLogLog this server can read
+pub fn is_valid(value: &[u8]) -> bool {
+    value.len() == LEN && value.starts_with(MAGIC) && value[4] == DENSE
+}
+
+fn get(value: &[u8], register: usize) -> u8 {
+    let (byte, shift) = (HEADER + register * BITS / 8, register * BITS % 8);
+    let next = value.get(byte + 1).copied().unwrap_or(0);
+    let pair = u16::from_le_bytes([value[byte], next]);
+    (pair >> shift) as u8 & 0x3f
+}
+
+fn set(value: &mut [u8], register: usize, count: u8) {
+    let (byte, shift) = (HEADER + register * BITS / 8, register * BITS % 8);
+    value[byte] &= !(0x3f << shift);
+    value[byte] |= count << shift;
+    if shift > 8 - BITS {
+        value[byte + 1] &= !(0x3f >> (8 - shift));
+        value[byte + 1] |= count >> (8 - shift);
+    }
+}
+
+fn invalidate(value: &mut [u8]) {
+    value[HEADER - 1] |= 0x80;
+}
+
+// the register an element lands in and the run of zeros it counts there, one
+// more than the trailing zeros of the rest of its hash
+fn position(element: &[u8]) -> (usize, u8) {
+    let hash = murmur64a(element, SEED);
+    let register = (hash & (REGISTERS as u64 - 1)) as usize;
+    let rest = (hash >> P) | 1 << Q;
+    (register, rest.trailing_zeros() as u8 + 1)
+}
+
+// counts an element in, whether a register changed
+pub fn add(value: &mut [u8], element: &[u8]) -> bool {
+    let (register, count) = position(element);
+    if get(value, register) >= count {
+        return false;
+    }
+    set(value, register, count);
+    invalidate(value);
+    true
+}
+
+pub fn registers(value: &[u8]) -> Registers {
+    let mut registers = [0; REGISTERS];
+    merge(&mut registers, value);
+    registers
+}
+
+// takes the highest of each register of `registers` and `value`
+pub fn merge(registers: &mut Registers, value: &[u8]) {
+    for (register, count) in registers.iter_mut().enumerate() {
+        *count = (*count).max(get(value, register));
+    }
+}
+
+// writes the registers over those of `value`
+pub fn store(value: &mut [u8], registers: &Registers) {
+    for (register, count) in registers.iter().enumerate() {
+        set(value, register, *count);
+    }
+    invalidate(value);
+}
+
+// the count of `value`, worked out again only when stale
+pub fn count(value: &mut [u8]) -> u64 {
+    let cached = &mut value[HEADER - 8..HEADER];
+    if cached[7] & 0x80 == 0 {
+        return u64::from_le_bytes(cached.try_into().expect("eight bytes"));
+    }
+    let count = estimate(&registers(value));
+    value[HEADER - 8..HEADER].copy_from_slice(&count.to_le_bytes());
+    count
+}
+
+// the estimator of Ertl's "New cardinality estimation algorithms for
+// HyperLogLog sketches", as redis has it, from how many registers hold each
+// count
+pub fn estimate(registers: &Registers) -> u64 {
+    let mut histogram = [0u32; Q as usize + 2];
+    for count in registers {
+        histogram[*count as usize] += 1;
+    }
+    let m = REGISTERS as f64;
+    let mut z = m * tau((m - histogram[Q as usize + 1] as f64) / m);
+    for count in histogram[1..=Q as usize].iter().rev() {
+        z += *count as f64;
+        z *= 0.5;
+    }
+    z += m * sigma(histogram[0] as f64 / m);
+    const ALPHA_INF: f64 = 0.721_347_520_444_481_7;
+    (ALPHA_INF * m * m / z).round() as u64
+}
+
+fn sigma(mut x: f64) -> f64 {
+    if x == 1.0 {
+        return f64::INFINITY;
+    }
+    let (mut y, mut z) = (1.0, x);
+    loop {
+        x *= x;
+        let before = z;
+        z += x * y;
+        y += y;
+        if z == before {
+            return z;
+        }
+    }
+}
+
+fn tau(mut x: f64) -> f64 {
+    if x == 0.0 || x == 1.0 {
+        return 0.0;
+    }
+    let (mut y, mut z) = (1.0, 1.0 - x);
+    loop {
+        x = x.sqrt();
+        let before = z;
+        y *= 0.5;
+        z -= (1.0 - x).powi(2) * y;
+        if z == before {
+            return z / 3.0;
+        }
+    }
+}
+
+// MurmurHash64A, the hash redis counts elements by
+fn murmur64a(key: &[u8], seed: u64) -> u64 {
+    const M: u64 = 0xc6a4_a793_5bd1_e995;
+    const R: u32 = 47;
+    let mut h = seed ^ (key.len() as u64).wrapping_mul(M);
+    let mut words = key.chunks_exact(8);
+    for word in words.by_ref() {
+        let mut k = u64::from_le_bytes(word.try_into().expect("eight bytes"));
+        k = k.wrapping_mul(M);
+        k ^= k >> R;
+        k = k.wrapping_mul(M);
+        h ^= k;
+        h = h.wrapping_mul(M);
+    }
+    let tail = words.remainder();
+    if !tail.is_empty() {
+        for (at, byte) in tail.iter().enumerate() {
+            h ^= (*byte as u64) << (8 * at);
+        }
+        h = h.wrapping_mul(M);
+    }
+    h ^= h >> R;
+    h = h.wrapping_mul(M);
+    h ^= h >> R;
+    h
+}
@@ -0,0 +1,91 @@
+use std::{io, net::SocketAddr, time::Duration};
+
+use tokio::net::TcpListener;
+
+mod acl;
+mod blocking;
+mod clients;
+mod clock;
+mod commands;
+mod config;
+mod connection;
+mod db;
+mod eviction;
+mod expiry;
+pub mod glob;
+pub mod hyperloglog;
+mod memory;
+mod notify;
+mod pubsub;
+mod scripting;
+pub mod sorted_set;
+mod stats;
+pub mod stream;
+mod tracking;
+
+pub use clients::reap_idle;
+pub use clock::{Clock, ManualClock, SystemClock};
+pub use db::{Db, Guard, Keyspace, Value, WrongType, DEFAULT_DATABASES};
+pub use expiry::{active_expiry, DEFAULT_EXPIRE_INTERVAL, DEFAULT_EXPIRE_SAMPLES};
+pub use notify::KeyspaceEvents;
+pub use sorted_set::SortedSet;
+pub use stats::sample_ops;
+pub use stream::{Stream, StreamEntry, StreamId};
+pub use tracking::DEFAULT_TRACKING_MAX_KEYS;
+
+pub const DEFAULT_ADDR: &str = "127.0.0.1:6379";
+
+#[derive(Debug, Clone)]
+pub struct Config {
+    // where the listener binds, `DEFAULT_ADDR` unless set
+    pub addr: SocketAddr,
+    // how often active expiry runs and the keys it checks per round
+    pub expire_interval: Duration,
+    pub expire_samples: usize,
+    // the keyspace notifications published, none by default
+    pub keyspace_events: KeyspaceEvents,
+    // the password AUTH takes, none for connections not to need one
+    pub requirepass: Option<Vec<u8>>,
+    // how many databases SELECT picks from
+    pub databases: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            addr: DEFAULT_ADDR.parse().expect("a valid socket address"),
+            expire_interval: DEFAULT_EXPIRE_INTERVAL,
+            expire_samples: DEFAULT_EXPIRE_SAMPLES,
+            keyspace_events: KeyspaceEvents::NONE,
+            requirepass: None,
+            databases: DEFAULT_DATABASES,
+        }
+    }
+}
+
+// binds `config.addr` and serves clients until the listener fails
+pub async fn serve(config: Config) -> io::Result<()> {
+    let listener = TcpListener::bind(config.addr).await?;
+    let db = Db::with_databases(config.databases);
+    db.set_keyspace_events(config.keyspace_events);
+    db.set_requirepass(config.requirepass);
+    tokio::spawn(active_expiry(
+        db.clone(),
+        config.expire_interval,
+        config.expire_samples,
+    ));
+    tokio::spawn(sample_ops(db.clone()));
+    tokio::spawn(reap_idle(db.clone()));
+    run(listener, db).await
+}
+
+// accepts clients on `listener`, each served by its own task against `db`,
+// e.g. a listener bound to port 0 in tests
+pub async fn run(listener: TcpListener, db: Db) -> io::Result<()> {
+    db.stats().set_port(listener.local_addr()?.port());
+    loop {
+        let (stream, _) = listener.accept().await?;
+        let db = db.clone();
+        tokio::spawn(connection::handle(stream, db));
+    }
+}
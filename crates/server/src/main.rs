@@ -0,0 +1,15 @@
+use std::{io, net::SocketAddr};
+
+use server::Config;
+
+// redirs [address], e.g. redirs 0.0.0.0:6379
+#[tokio::main]
+async fn main() -> io::Result<()> {
+    let mut config = Config::default();
+    if let Some(addr) = std::env::args().nth(1) {
+        config.addr = addr
+            .parse::<SocketAddr>()
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, format!("{addr}: {e}")))?;
+    }
+    server::serve(config).await
+}
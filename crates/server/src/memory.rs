@@ -0,0 +1,33 @@
+// estimates of the memory keys take, for MEMORY USAGE and INFO. They count
+// what the values hold and the containers holding it, not what the allocator
+// adds on top
+
+use std::mem::size_of;
+
+// as many elements of an aggregate as MEMORY USAGE measures unless told
+pub(crate) const DEFAULT_SAMPLES: usize = 5;
+
+// what a key takes besides its value: the key itself, in the keyspace and in
+// the SCAN order, the slots holding them and its last access time and
+// frequency, padded to a word
+pub(crate) const KEY_OVERHEAD: usize =
+    size_of::<Vec<u8>>() * 2 + 3 * size_of::<u64>() + size_of::<(Vec<u8>, crate::Value)>();
+
+// the bytes of `len` elements from the sizes of the first of them, `samples`
+// of them or all when it is 0, the rest taken to be as big on average
+pub(crate) fn sampled(sizes: impl Iterator<Item = usize>, len: usize, samples: usize) -> usize {
+    let samples = match samples {
+        0 => len,
+        samples => samples.min(len),
+    };
+    if samples == 0 {
+        return 0;
+    }
+    let measured: usize = sizes.take(samples).sum();
+    measured * len / samples
+}
+
+// the bytes of a vector of bytes, its buffer and itself
+pub(crate) fn bytes(bytes: &Vec<u8>) -> usize {
+    size_of::<Vec<u8>>() + bytes.capacity()
+}
@@ -0,0 +1,288 @@
+// keyspace notifications, the events redis publishes on writes to
+// `__keyspace@<db>__:<key>` with the event as the message and to
+// `__keyevent@<db>__:<event>` with the key as the message. The keyspace
+// journals its writes, see `Journal`, and they are published as its lock is
+// given back
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display},
+};
+
+use crate::pubsub::PubSub;
+
+// the kinds of write an event can be about, as notify-keyspace-events names
+// them
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Class {
+    // del, expire, rename_from and the other commands on keys of any type
+    Generic,
+    String,
+    List,
+    Set,
+    Hash,
+    SortedSet,
+    Stream,
+    Expired,
+    Evicted,
+}
+
+impl Class {
+    fn bit(self) -> u16 {
+        1 << self as u16
+    }
+}
+
+const KEYSPACE: u16 = 1 << 14;
+const KEYEVENT: u16 = 1 << 15;
+const ALL: u16 = (1 << (Class::Evicted as u16 + 1)) - 1;
+
+// the events to publish, parsed from and shown as the notify-keyspace-events
+// flags: K and E for the two kinds of channel and a letter for each class, A
+// standing for all of them. Nothing is published without K or E
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct KeyspaceEvents(u16);
+
+const LETTERS: &[(u8, Class)] = &[
+    (b'g', Class::Generic),
+    (b'$', Class::String),
+    (b'l', Class::List),
+    (b's', Class::Set),
+    (b'h', Class::Hash),
+    (b'z', Class::SortedSet),
+    (b't', Class::Stream),
+    (b'x', Class::Expired),
+    (b'e', Class::Evicted),
+];
+
+impl KeyspaceEvents {
+    pub const NONE: KeyspaceEvents = KeyspaceEvents(0);
+
+    // none for a letter that stands for nothing
+    pub fn parse(flags: &[u8]) -> Option<KeyspaceEvents> {
+        flags
+            .iter()
+            .try_fold(0, |bits, letter| {
+                let bit = match letter {
+                    b'K' => KEYSPACE,
+                    b'E' => KEYEVENT,
+                    b'A' => ALL,
+                    letter => LETTERS.iter().find(|(l, _)| l == letter)?.1.bit(),
+                };
+                Some(bits | bit)
+            })
+            .map(KeyspaceEvents)
+    }
+    pub(crate) fn wants(self, class: Class) -> bool {
+        self.0 & (KEYSPACE | KEYEVENT) != 0 && self.0 & class.bit() != 0
+    }
+}
+
+// the way redis shows them back, A when every class is in
+impl Display for KeyspaceEvents {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut flags = String::new();
+        match self.0 & ALL == ALL {
+            true => flags.push('A'),
+            false => flags.extend(
+                LETTERS
+                    .iter()
+                    .filter(|(_, class)| self.0 & class.bit() != 0)
+                    .map(|(letter, _)| *letter as char),
+            ),
+        }
+        if self.0 & KEYSPACE != 0 {
+            flags.push('K');
+        }
+        if self.0 & KEYEVENT != 0 {
+            flags.push('E');
+        }
+        f.write_str(&flags)
+    }
+}
+
+// a write to a key, named after the command that made it unless `name` says
+// otherwise
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Event {
+    class: Class,
+    name: Option<&'static str>,
+    key: Vec<u8>,
+}
+
+// a key watched by WATCH, with the connections watching it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Watched {
+    watchers: usize,
+    version: u64,
+}
+
+// the writes to the keyspace: those not published yet, only of the classes
+// wanted, and the versions of the keys watched, which every write changes
+#[derive(Debug, Default)]
+pub(crate) struct Journal {
+    pub wanted: KeyspaceEvents,
+    events: Vec<Event>,
+    watched: HashMap<Vec<u8>, Watched>,
+    // the last version handed out
+    version: u64,
+    // whether a connection tracks keys, see `tracking`, the keys written
+    // since the last publish collected for it then, and whether they all went
+    pub tracking: bool,
+    written: Vec<Vec<u8>>,
+    flushed: bool,
+}
+
+impl Journal {
+    // a write of the command running, several writes in a row to the same key
+    // making a single event
+    pub fn write(&mut self, class: Class, key: &[u8]) {
+        self.record(class, None, key);
+    }
+    // a write that is the same event whatever the command
+    pub fn event(&mut self, class: Class, name: &'static str, key: &[u8]) {
+        self.record(class, Some(name), key);
+    }
+    fn record(&mut self, class: Class, name: Option<&'static str>, key: &[u8]) {
+        self.touch(key);
+        if self.tracking && self.written.last().is_none_or(|last| last != key) {
+            self.written.push(key.to_vec());
+        }
+        if !self.wanted.wants(class) {
+            return;
+        }
+        let same = |last: &Event| last.class == class && last.name == name && last.key == key;
+        if !self.events.last().is_some_and(same) {
+            self.events.push(Event {
+                class,
+                name,
+                key: key.to_vec(),
+            });
+        }
+    }
+    // the version of the key for one more connection watching it
+    pub fn watch(&mut self, key: &[u8]) -> u64 {
+        let watched = self.watched.entry(key.to_vec()).or_insert(Watched {
+            watchers: 0,
+            version: self.version,
+        });
+        watched.watchers += 1;
+        watched.version
+    }
+    // for one connection less, the key forgotten once none is left
+    pub fn unwatch(&mut self, key: &[u8]) {
+        if let Some(watched) = self.watched.get_mut(key) {
+            watched.watchers -= 1;
+            if watched.watchers == 0 {
+                self.watched.remove(key);
+            }
+        }
+    }
+    // the key counts as written to, without an event
+    pub fn touch(&mut self, key: &[u8]) {
+        if let Some(watched) = self.watched.get_mut(key) {
+            self.version += 1;
+            watched.version = self.version;
+        }
+    }
+    // every key watched counts as written to, e.g. all of them going at once
+    pub fn touch_all(&mut self) {
+        for watched in self.watched.values_mut() {
+            self.version += 1;
+            watched.version = self.version;
+        }
+    }
+    // every key went at once, for the connections tracking keys
+    pub fn flushed(&mut self) {
+        self.flushed |= self.tracking;
+    }
+    // the keys written since the last time and whether all of them went, for
+    // the connections tracking them to be told
+    pub fn written(&mut self) -> (Vec<Vec<u8>>, bool) {
+        let mut written = std::mem::take(&mut self.written);
+        written.sort_unstable();
+        written.dedup();
+        (written, std::mem::take(&mut self.flushed))
+    }
+    pub fn version(&self, key: &[u8]) -> Option<u64> {
+        self.watched.get(key).map(|watched| watched.version)
+    }
+    // publishes the events journaled, `command` naming the writes of the
+    // command made against database `db`. Without one they are not published,
+    // the command only reads
+    pub fn publish(&mut self, pubsub: &PubSub, db: usize, command: Option<&'static str>) {
+        for event in self.events.drain(..) {
+            let Some(name) = event.name.or(command) else {
+                continue;
+            };
+            if self.wanted.0 & KEYSPACE != 0 {
+                let mut channel = format!("__keyspace@{db}__:").into_bytes();
+                channel.extend_from_slice(&event.key);
+                pubsub.publish(&channel, name.as_bytes());
+            }
+            if self.wanted.0 & KEYEVENT != 0 {
+                let channel = format!("__keyevent@{db}__:{name}");
+                pubsub.publish(channel.as_bytes(), &event.key);
+            }
+        }
+    }
+}
+
+// the commands that write, by the event their writes are named after. Those
+// not here, e.g. the ones only reading or keeping state aside from the keys,
+// publish only the events the keyspace names itself: del, expire, expired,
+// persist, rename_from, rename_to and copy_to
+const EVENTS: &[(&str, &str)] = &[
+    ("set", "set"),
+    ("mset", "set"),
+    ("msetnx", "set"),
+    ("setrange", "setrange"),
+    ("append", "append"),
+    ("incr", "incrby"),
+    ("decr", "incrby"),
+    ("incrby", "incrby"),
+    ("decrby", "incrby"),
+    ("incrbyfloat", "incrbyfloat"),
+    ("setbit", "setbit"),
+    ("bitop", "set"),
+    ("pfadd", "pfadd"),
+    ("pfmerge", "pfadd"),
+    ("lpush", "lpush"),
+    ("rpush", "rpush"),
+    ("lpop", "lpop"),
+    ("rpop", "rpop"),
+    ("blpop", "lpop"),
+    ("brpop", "rpop"),
+    ("linsert", "linsert"),
+    ("lset", "lset"),
+    ("lrem", "lrem"),
+    ("ltrim", "ltrim"),
+    ("hset", "hset"),
+    ("hdel", "hdel"),
+    ("hincrby", "hincrby"),
+    ("hincrbyfloat", "hincrbyfloat"),
+    ("sadd", "sadd"),
+    ("srem", "srem"),
+    ("spop", "spop"),
+    ("sinterstore", "sinterstore"),
+    ("sunionstore", "sunionstore"),
+    ("sdiffstore", "sdiffstore"),
+    ("zadd", "zadd"),
+    ("zincrby", "zincr"),
+    ("zrem", "zrem"),
+    ("zremrangebyscore", "zremrangebyscore"),
+    ("zpopmin", "zpopmin"),
+    ("zpopmax", "zpopmax"),
+    ("bzpopmin", "zpopmin"),
+    ("bzpopmax", "zpopmax"),
+    ("xadd", "xadd"),
+];
+
+// the event the writes of a command are named after, none for the commands
+// that do not write to the keys themselves
+pub(crate) fn command_event(name: &[u8]) -> Option<&'static str> {
+    EVENTS
+        .iter()
+        .find(|(command, _)| name.eq_ignore_ascii_case(command.as_bytes()))
+        .map(|(_, event)| *event)
+}
@@ -0,0 +1,215 @@
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{Arc, Mutex, MutexGuard},
+};
+
+use protocol::RedirsValue;
+use tokio::sync::{mpsc, Notify};
+
+use crate::glob;
+
+// how many messages a connection can fall behind by before it is dropped,
+// redis bounds the output buffer of its subscribers the same way
+pub(crate) const MAILBOX_LEN: usize = 1024;
+
+// where a connection is handed what is published to it, the publisher never
+// waits on it
+#[derive(Debug, Clone)]
+pub(crate) struct Mailbox {
+    queue: mpsc::Sender<RedirsValue>,
+    overflow: Arc<Notify>,
+}
+
+// the connection's end of its mailbox
+#[derive(Debug)]
+pub(crate) struct Inbox {
+    queue: mpsc::Receiver<RedirsValue>,
+    overflow: Arc<Notify>,
+}
+
+pub(crate) fn mailbox() -> (Mailbox, Inbox) {
+    let (sender, receiver) = mpsc::channel(MAILBOX_LEN);
+    let overflow = Arc::new(Notify::new());
+    let mailbox = Mailbox {
+        queue: sender,
+        overflow: overflow.clone(),
+    };
+    let inbox = Inbox {
+        queue: receiver,
+        overflow,
+    };
+    (mailbox, inbox)
+}
+
+impl Mailbox {
+    // whether the message was queued, a full mailbox tells its connection to
+    // hang up instead
+    pub fn post(&self, message: RedirsValue) -> bool {
+        match self.queue.try_send(message) {
+            Ok(()) => true,
+            Err(mpsc::error::TrySendError::Full(_)) => {
+                self.overflow.notify_one();
+                false
+            }
+            Err(mpsc::error::TrySendError::Closed(_)) => false,
+        }
+    }
+}
+
+impl Inbox {
+    pub async fn recv(&mut self) -> Option<RedirsValue> {
+        self.queue.recv().await
+    }
+    // a message already queued, without waiting for one
+    pub fn try_recv(&mut self) -> Option<RedirsValue> {
+        self.queue.try_recv().ok()
+    }
+    // once a message could not be queued for the connection, which then
+    // closes rather than hand out some of what was published and not the rest
+    pub async fn overflowed(&self) {
+        self.overflow.notified().await
+    }
+}
+
+// what a subscription is to, a channel by its name or every channel matching
+// a glob pattern
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Kind {
+    Channel,
+    Pattern,
+}
+
+// the channels and patterns a connection subscribed to
+#[derive(Debug, Default)]
+pub(crate) struct Subscriptions {
+    pub channels: BTreeSet<Vec<u8>>,
+    pub patterns: BTreeSet<Vec<u8>>,
+}
+
+impl Subscriptions {
+    pub fn of(&mut self, kind: Kind) -> &mut BTreeSet<Vec<u8>> {
+        match kind {
+            Kind::Channel => &mut self.channels,
+            Kind::Pattern => &mut self.patterns,
+        }
+    }
+    // what the confirmations to SUBSCRIBE and UNSUBSCRIBE count, channels and
+    // patterns alike
+    pub fn len(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// the connections subscribed to each channel or pattern, by id
+type Subscribers = HashMap<Vec<u8>, HashMap<i64, Mailbox>>;
+
+#[derive(Debug, Default)]
+struct Registry {
+    channels: Subscribers,
+    // connections subscribed to the same pattern share its entry, so a
+    // publish matches each pattern once however many subscribed to it
+    patterns: Subscribers,
+}
+
+impl Registry {
+    fn of(&mut self, kind: Kind) -> &mut Subscribers {
+        match kind {
+            Kind::Channel => &mut self.channels,
+            Kind::Pattern => &mut self.patterns,
+        }
+    }
+}
+
+// every subscription of every connection
+#[derive(Debug, Default)]
+pub(crate) struct PubSub {
+    registry: Mutex<Registry>,
+}
+
+impl PubSub {
+    fn registry(&self) -> MutexGuard<'_, Registry> {
+        self.registry.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    pub fn subscribe(&self, kind: Kind, name: &[u8], id: i64, mailbox: &Mailbox) {
+        self.registry()
+            .of(kind)
+            .entry(name.to_vec())
+            .or_default()
+            .insert(id, mailbox.clone());
+    }
+    pub fn unsubscribe(&self, kind: Kind, name: &[u8], id: i64) {
+        let mut registry = self.registry();
+        let subscribers = registry.of(kind);
+        if let Some(connections) = subscribers.get_mut(name) {
+            connections.remove(&id);
+            if connections.is_empty() {
+                subscribers.remove(name);
+            }
+        }
+    }
+    // the channels with a subscriber, those matching `pattern` when given
+    pub fn channels(&self, pattern: Option<&[u8]>) -> Vec<Vec<u8>> {
+        self.registry()
+            .channels
+            .keys()
+            .filter(|channel| pattern.is_none_or(|pattern| glob::matches(pattern, channel)))
+            .cloned()
+            .collect()
+    }
+    // whether connection `id` subscribed to the channel itself
+    pub fn subscribed(&self, channel: &[u8], id: i64) -> bool {
+        self.registry()
+            .channels
+            .get(channel)
+            .is_some_and(|connections| connections.contains_key(&id))
+    }
+    // the subscribers of each channel, patterns aside
+    pub fn numsub(&self, channels: &[&[u8]]) -> Vec<usize> {
+        let registry = self.registry();
+        channels
+            .iter()
+            .map(|channel| registry.channels.get(*channel).map_or(0, HashMap::len))
+            .collect()
+    }
+    // the patterns subscribed to, however many connections share each
+    pub fn numpat(&self) -> usize {
+        self.registry().patterns.len()
+    }
+    // how many subscriptions the message was queued for, a connection
+    // subscribed to the channel and to patterns matching it getting it once
+    // for each
+    pub fn publish(&self, channel: &[u8], message: &[u8]) -> usize {
+        let registry = self.registry();
+        let mut receivers = 0;
+        if let Some(connections) = registry.channels.get(channel) {
+            let push = RedirsValue::Push(vec![
+                RedirsValue::from("message"),
+                RedirsValue::from(channel),
+                RedirsValue::from(message),
+            ]);
+            receivers += post(connections, &push);
+        }
+        for (pattern, connections) in &registry.patterns {
+            if glob::matches(pattern, channel) {
+                let push = RedirsValue::Push(vec![
+                    RedirsValue::from("pmessage"),
+                    RedirsValue::from(&pattern[..]),
+                    RedirsValue::from(channel),
+                    RedirsValue::from(message),
+                ]);
+                receivers += post(connections, &push);
+            }
+        }
+        receivers
+    }
+}
+
+fn post(connections: &HashMap<i64, Mailbox>, push: &RedirsValue) -> usize {
+    connections
+        .values()
+        .filter(|mailbox| mailbox.post(push.clone()))
+        .count()
+}
@@ -0,0 +1,115 @@
+// the scripts EVAL and SCRIPT LOAD were given, by the SHA1 digest of their
+// text, and how long one may run. They run in Lua with the `lua` feature, see
+// `commands::lua`, without it EVAL and EVALSHA find their script and stop there
+
+use std::{
+    collections::HashMap,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex, MutexGuard,
+    },
+    time::Duration,
+};
+
+// redis's busy-reply-threshold, formerly lua-time-limit
+pub const DEFAULT_BUSY_THRESHOLD: Duration = Duration::from_secs(5);
+
+#[derive(Debug)]
+pub(crate) struct Scripts {
+    by_sha: Mutex<HashMap<String, Vec<u8>>>,
+    // in milliseconds, a script running longer is stopped with a BUSY error,
+    // none for 0 as redis has it
+    busy_threshold: AtomicU64,
+}
+
+impl Default for Scripts {
+    fn default() -> Self {
+        Self {
+            by_sha: Mutex::default(),
+            busy_threshold: AtomicU64::new(DEFAULT_BUSY_THRESHOLD.as_millis() as u64),
+        }
+    }
+}
+
+impl Scripts {
+    fn by_sha(&self) -> MutexGuard<'_, HashMap<String, Vec<u8>>> {
+        self.by_sha.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    // the digest the script goes by from now on
+    pub fn load(&self, script: &[u8]) -> String {
+        let sha = hex(&sha1(script));
+        self.by_sha().entry(sha.clone()).or_insert(script.to_vec());
+        sha
+    }
+    // `sha` in either case
+    pub fn get(&self, sha: &[u8]) -> Option<Vec<u8>> {
+        let sha = String::from_utf8_lossy(sha).to_ascii_lowercase();
+        self.by_sha().get(&sha).cloned()
+    }
+    pub fn flush(&self) {
+        self.by_sha().clear();
+    }
+    pub fn busy_threshold(&self) -> Duration {
+        Duration::from_millis(self.busy_threshold.load(Ordering::Relaxed))
+    }
+    pub fn set_busy_threshold(&self, threshold: Duration) {
+        self.busy_threshold
+            .store(threshold.as_millis() as u64, Ordering::Relaxed);
+    }
+}
+
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+// SHA1 as FIPS 180-4 has it, what redis names scripts by
+pub(crate) fn sha1(message: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [
+        0x6745_2301,
+        0xefcd_ab89,
+        0x98ba_dcfe,
+        0x1032_5476,
+        0xc3d2_e1f0,
+    ];
+    // the message, a one bit, zeros up to 8 bytes short of a whole block and
+    // its length in bits
+    let mut padded = message.to_vec();
+    padded.push(0x80);
+    while padded.len() % 64 != 56 {
+        padded.push(0);
+    }
+    padded.extend_from_slice(&(message.len() as u64 * 8).to_be_bytes());
+    for block in padded.chunks_exact(64) {
+        let mut w = [0u32; 80];
+        for (i, word) in block.chunks_exact(4).enumerate() {
+            w[i] = u32::from_be_bytes(word.try_into().expect("four bytes"));
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..20 => ((b & c) | (!b & d), 0x5a82_7999),
+                20..40 => (b ^ c ^ d, 0x6ed9_eba1),
+                40..60 => ((b & c) | (b & d) | (c & d), 0x8f1b_bcdc),
+                _ => (b ^ c ^ d, 0xca62_c1d6),
+            };
+            let t = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(*word);
+            (e, d, c, b, a) = (d, c, b.rotate_left(30), a, t);
+        }
+        for (h, x) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(x);
+        }
+    }
+    let mut digest = [0; 20];
+    for (bytes, h) in digest.chunks_exact_mut(4).zip(h) {
+        bytes.copy_from_slice(&h.to_be_bytes());
+    }
+    digest
+}
@@ -0,0 +1,488 @@
+// the value of a sorted set key: members with a score each, kept ordered by
+// score then member in a skiplist whose links count the nodes they pass over,
+// so finding a rank or the start of a range is O(log n)
+
+use std::{collections::HashMap, mem::size_of};
+
+use rand::Rng;
+
+use crate::memory::sampled;
+
+const MAX_LEVEL: usize = 32;
+// the node every level starts from, it holds no member
+const HEAD: usize = 0;
+// the end of a level
+const NIL: usize = usize::MAX;
+
+#[derive(Debug, Clone)]
+struct Link {
+    next: usize,
+    // how many ranks following the link moves forward, to the end of the list
+    // for the last link of a level
+    span: usize,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    member: Vec<u8>,
+    score: f64,
+    links: Vec<Link>,
+    // the node before on the lowest level, NIL for the first
+    back: usize,
+}
+
+impl Node {
+    // whether the node orders before (score, member), ties on the score broken
+    // by the member bytes
+    fn before(&self, score: f64, member: &[u8]) -> bool {
+        self.score < score || self.score == score && self.member[..] < *member
+    }
+}
+
+// nodes live in a vector and link by position, removed ones are reused
+#[derive(Debug, Clone)]
+struct SkipList {
+    nodes: Vec<Node>,
+    free: Vec<usize>,
+    // the number of levels in use
+    level: usize,
+    len: usize,
+    tail: usize,
+}
+
+impl SkipList {
+    fn new() -> Self {
+        let head = Node {
+            member: Vec::new(),
+            score: 0.0,
+            links: vec![Link { next: NIL, span: 0 }; MAX_LEVEL],
+            back: NIL,
+        };
+        Self {
+            nodes: vec![head],
+            free: Vec::new(),
+            level: 1,
+            len: 0,
+            tail: NIL,
+        }
+    }
+    // each level above the first holds a quarter of the nodes of the one below
+    fn random_level() -> usize {
+        let mut rng = rand::rng();
+        let mut level = 1;
+        while level < MAX_LEVEL && rng.random_ratio(1, 4) {
+            level += 1;
+        }
+        level
+    }
+    fn link(&self, at: usize, level: usize) -> &Link {
+        &self.nodes[at].links[level]
+    }
+    fn link_mut(&mut self, at: usize, level: usize) -> &mut Link {
+        &mut self.nodes[at].links[level]
+    }
+    // the last node on each level that orders before (score, member), and the
+    // rank of each of them counting the head as 0
+    fn predecessors(&self, score: f64, member: &[u8]) -> ([usize; MAX_LEVEL], [usize; MAX_LEVEL]) {
+        let (mut update, mut rank) = ([HEAD; MAX_LEVEL], [0; MAX_LEVEL]);
+        let mut at = HEAD;
+        for level in (0..self.level).rev() {
+            rank[level] = rank.get(level + 1).copied().unwrap_or(0);
+            loop {
+                let link = self.link(at, level);
+                if link.next == NIL || !self.nodes[link.next].before(score, member) {
+                    break;
+                }
+                rank[level] += link.span;
+                at = link.next;
+            }
+            update[level] = at;
+        }
+        (update, rank)
+    }
+    // the member must not be in the list already
+    fn insert(&mut self, score: f64, member: Vec<u8>) {
+        let (mut update, mut rank) = self.predecessors(score, &member);
+        let level = Self::random_level();
+        if level > self.level {
+            for new_level in self.level..level {
+                rank[new_level] = 0;
+                update[new_level] = HEAD;
+                self.link_mut(HEAD, new_level).span = self.len;
+            }
+            self.level = level;
+        }
+        let node = Node {
+            member,
+            score,
+            links: vec![Link { next: NIL, span: 0 }; level],
+            back: if update[0] == HEAD { NIL } else { update[0] },
+        };
+        let at = match self.free.pop() {
+            Some(at) => {
+                self.nodes[at] = node;
+                at
+            }
+            None => {
+                self.nodes.push(node);
+                self.nodes.len() - 1
+            }
+        };
+        for level in 0..level {
+            let before = self.link(update[level], level).clone();
+            let passed = rank[0] - rank[level];
+            *self.link_mut(at, level) = Link {
+                next: before.next,
+                span: before.span - passed,
+            };
+            *self.link_mut(update[level], level) = Link {
+                next: at,
+                span: passed + 1,
+            };
+        }
+        for (level, &before) in update.iter().enumerate().take(self.level).skip(level) {
+            self.link_mut(before, level).span += 1;
+        }
+        match self.link(at, 0).next {
+            NIL => self.tail = at,
+            next => self.nodes[next].back = at,
+        }
+        self.len += 1;
+    }
+    fn remove(&mut self, score: f64, member: &[u8]) -> bool {
+        let (update, _) = self.predecessors(score, member);
+        let at = self.link(update[0], 0).next;
+        if at == NIL || self.nodes[at].score != score || self.nodes[at].member != member {
+            return false;
+        }
+        for (level, &before) in update.iter().enumerate().take(self.level) {
+            let removed = self.nodes[at].links.get(level).cloned();
+            let before = self.link_mut(before, level);
+            match removed {
+                Some(removed) if before.next == at => {
+                    before.span = before.span + removed.span - 1;
+                    before.next = removed.next;
+                }
+                _ => before.span -= 1,
+            }
+        }
+        match self.link(at, 0).next {
+            NIL => self.tail = self.nodes[at].back,
+            next => self.nodes[next].back = self.nodes[at].back,
+        }
+        while self.level > 1 && self.link(HEAD, self.level - 1).next == NIL {
+            self.level -= 1;
+        }
+        self.nodes[at].member = Vec::new();
+        self.free.push(at);
+        self.len -= 1;
+        true
+    }
+    // how many nodes from the first on satisfy `pred`, which must hold for a
+    // prefix of the list and for none of the nodes after it
+    fn count_while(&self, pred: impl Fn(&Node) -> bool) -> usize {
+        let (mut at, mut rank) = (HEAD, 0);
+        for level in (0..self.level).rev() {
+            loop {
+                let link = self.link(at, level);
+                if link.next == NIL || !pred(&self.nodes[link.next]) {
+                    break;
+                }
+                rank += link.span;
+                at = link.next;
+            }
+        }
+        rank
+    }
+    // the node at a 0 based rank
+    fn at_rank(&self, rank: usize) -> usize {
+        if rank >= self.len {
+            return NIL;
+        }
+        let (mut at, mut passed) = (HEAD, 0);
+        for level in (0..self.level).rev() {
+            loop {
+                let link = self.link(at, level);
+                if link.next == NIL || passed + link.span > rank + 1 {
+                    break;
+                }
+                passed += link.span;
+                at = link.next;
+            }
+            if passed == rank + 1 {
+                return at;
+            }
+        }
+        NIL
+    }
+    fn first(&self) -> usize {
+        self.link(HEAD, 0).next
+    }
+}
+
+// the members and scores from a node on, towards higher ranks or lower ones
+pub struct Iter<'a> {
+    list: &'a SkipList,
+    at: usize,
+    rev: bool,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = (&'a [u8], f64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let node = self.list.nodes.get(self.at)?;
+        self.at = match self.rev {
+            true => node.back,
+            false => node.links[0].next,
+        };
+        Some((&node.member, node.score))
+    }
+}
+
+// members with their scores, ordered by score and then by member
+#[derive(Debug, Clone)]
+pub struct SortedSet {
+    scores: HashMap<Vec<u8>, f64>,
+    list: SkipList,
+}
+
+impl Default for SortedSet {
+    fn default() -> Self {
+        Self {
+            scores: HashMap::new(),
+            list: SkipList::new(),
+        }
+    }
+}
+
+impl PartialEq for SortedSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.scores == other.scores
+    }
+}
+
+impl SortedSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn len(&self) -> usize {
+        self.scores.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+    // an estimate of the bytes it takes, the members counted once in the map
+    // and once in the skiplist. Measured on the first `samples` nodes, all of
+    // them for 0
+    pub fn memory_usage(&self, samples: usize) -> usize {
+        let scores = self.scores.capacity() * (size_of::<Vec<u8>>() + size_of::<f64>() + 1);
+        let nodes = &self.list.nodes[1..];
+        let sizes = nodes
+            .iter()
+            .map(|node| 2 * node.member.capacity() + node.links.capacity() * size_of::<Link>());
+        size_of::<SortedSet>()
+            + scores
+            + self.list.nodes.capacity() * size_of::<Node>()
+            + MAX_LEVEL * size_of::<Link>()
+            + sampled(sizes, nodes.len(), samples)
+    }
+    pub fn score(&self, member: &[u8]) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+    // adds the member or moves it to its new score, returning the old one.
+    // Scores are never NaN
+    pub fn insert(&mut self, member: Vec<u8>, score: f64) -> Option<f64> {
+        let old = self.score(&member);
+        match old {
+            Some(old) if old == score => return Some(old),
+            Some(old) => {
+                self.list.remove(old, &member);
+            }
+            None => {}
+        }
+        self.list.insert(score, member.clone());
+        self.scores.insert(member, score);
+        old
+    }
+    pub fn remove(&mut self, member: &[u8]) -> Option<f64> {
+        let score = self.scores.remove(member)?;
+        self.list.remove(score, member);
+        Some(score)
+    }
+    // the 0 based position of the member, lowest score first
+    // removes the members from rank `start` to `end` excluded, returning how
+    // many went
+    pub fn remove_ranks(&mut self, start: usize, end: usize) -> usize {
+        let members: Vec<_> = match start < end {
+            true => self
+                .iter_from(start)
+                .take(end - start)
+                .map(|(member, _)| member.to_vec())
+                .collect(),
+            false => Vec::new(),
+        };
+        for member in &members {
+            self.remove(member);
+        }
+        members.len()
+    }
+    pub fn rank(&self, member: &[u8]) -> Option<usize> {
+        let score = self.score(member)?;
+        Some(self.list.count_while(|node| node.before(score, member)))
+    }
+    // the ranks of the members within `range`, from the first one up to but
+    // not including the end
+    pub fn score_ranks(&self, range: &ScoreRange) -> (usize, usize) {
+        let start = self.list.count_while(|node| range.below(node.score));
+        let end = self.list.count_while(|node| !range.above(node.score));
+        (start, end.max(start))
+    }
+    // the same by member for a sorted set whose scores are all the same, as
+    // the lexicographical commands expect
+    pub fn lex_ranks(&self, range: &LexRange) -> (usize, usize) {
+        let start = self.list.count_while(|node| range.below(&node.member));
+        let end = self.list.count_while(|node| !range.above(&node.member));
+        (start, end.max(start))
+    }
+    // from the lowest score to the highest
+    pub fn iter(&self) -> Iter<'_> {
+        self.iter_from(0)
+    }
+    // from the member at `rank` towards higher ones
+    pub fn iter_from(&self, rank: usize) -> Iter<'_> {
+        Iter {
+            list: &self.list,
+            at: match rank {
+                0 => self.list.first(),
+                rank => self.list.at_rank(rank),
+            },
+            rev: false,
+        }
+    }
+    // from the member at `rank` towards lower ones
+    pub fn iter_rev_from(&self, rank: usize) -> Iter<'_> {
+        Iter {
+            list: &self.list,
+            at: match rank + 1 == self.len() {
+                true => self.list.tail,
+                false => self.list.at_rank(rank),
+            },
+            rev: true,
+        }
+    }
+}
+
+// one end of a range of scores
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreBound {
+    pub score: f64,
+    pub exclusive: bool,
+}
+
+impl ScoreBound {
+    // a score, excluded when it follows a `(`. `-inf`, `+inf` and `inf` are
+    // the open ends
+    pub fn parse(arg: &[u8]) -> Option<Self> {
+        let (exclusive, score) = match arg.strip_prefix(b"(") {
+            Some(score) => (true, score),
+            None => (false, arg),
+        };
+        let score = parse_score(score)?;
+        Some(Self { score, exclusive })
+    }
+}
+
+// the scores ZRANGEBYSCORE, ZCOUNT and ZREMRANGEBYSCORE select
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ScoreRange {
+    pub min: ScoreBound,
+    pub max: ScoreBound,
+}
+
+impl ScoreRange {
+    // none when either end is not a score
+    pub fn parse(min: &[u8], max: &[u8]) -> Option<Self> {
+        Some(Self {
+            min: ScoreBound::parse(min)?,
+            max: ScoreBound::parse(max)?,
+        })
+    }
+    // whether a score is lower than the range
+    pub fn below(&self, score: f64) -> bool {
+        score < self.min.score || self.min.exclusive && score == self.min.score
+    }
+    // whether a score is higher than the range
+    pub fn above(&self, score: f64) -> bool {
+        score > self.max.score || self.max.exclusive && score == self.max.score
+    }
+    pub fn contains(&self, score: f64) -> bool {
+        !self.below(score) && !self.above(score)
+    }
+}
+
+// a score as redis reads one: a float, infinities included, but not NaN
+pub fn parse_score(arg: &[u8]) -> Option<f64> {
+    let text = std::str::from_utf8(arg).ok()?;
+    if text.starts_with(char::is_whitespace) || text.ends_with(char::is_whitespace) {
+        return None;
+    }
+    text.parse::<f64>().ok().filter(|score| !score.is_nan())
+}
+
+// one end of a range of members
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexBound {
+    // `-`, before every member
+    Min,
+    // `+`, after every member
+    Max,
+    // `[member`
+    Inclusive(Vec<u8>),
+    // `(member`
+    Exclusive(Vec<u8>),
+}
+
+impl LexBound {
+    pub fn parse(arg: &[u8]) -> Option<Self> {
+        match arg {
+            b"-" => Some(LexBound::Min),
+            b"+" => Some(LexBound::Max),
+            [b'[', member @ ..] => Some(LexBound::Inclusive(member.to_vec())),
+            [b'(', member @ ..] => Some(LexBound::Exclusive(member.to_vec())),
+            _ => None,
+        }
+    }
+}
+
+// the members ZRANGE BYLEX selects
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LexRange {
+    pub min: LexBound,
+    pub max: LexBound,
+}
+
+impl LexRange {
+    pub fn parse(min: &[u8], max: &[u8]) -> Option<Self> {
+        Some(Self {
+            min: LexBound::parse(min)?,
+            max: LexBound::parse(max)?,
+        })
+    }
+    pub fn below(&self, member: &[u8]) -> bool {
+        match &self.min {
+            LexBound::Min => false,
+            LexBound::Max => true,
+            LexBound::Inclusive(min) => member < &min[..],
+            LexBound::Exclusive(min) => member <= &min[..],
+        }
+    }
+    pub fn above(&self, member: &[u8]) -> bool {
+        match &self.max {
+            LexBound::Min => true,
+            LexBound::Max => false,
+            LexBound::Inclusive(max) => member > &max[..],
+            LexBound::Exclusive(max) => member >= &max[..],
+        }
+    }
+}
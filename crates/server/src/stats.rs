@@ -0,0 +1,115 @@
+// the runtime statistics INFO reports that are not about the keys, updated by
+// the connections as they come and go and by every command they run
+
+use std::{
+    collections::VecDeque,
+    sync::atomic::{AtomicU16, AtomicU64, AtomicUsize, Ordering},
+    time::{Duration, Instant},
+};
+
+use crate::Db;
+
+// as many one second samples as redis averages the rate of commands over
+const OPS_SAMPLES: usize = 16;
+
+#[derive(Debug)]
+pub(crate) struct Stats {
+    started: Instant,
+    // the port the listener is bound to, 0 until it is
+    port: AtomicU16,
+    connected_clients: AtomicUsize,
+    blocked_clients: AtomicUsize,
+    connections_received: AtomicU64,
+    commands_processed: AtomicU64,
+    // the commands per second over the last samples, see `sample_ops`
+    ops_per_sec: AtomicU64,
+}
+
+impl Default for Stats {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            port: AtomicU16::new(0),
+            connected_clients: AtomicUsize::new(0),
+            blocked_clients: AtomicUsize::new(0),
+            connections_received: AtomicU64::new(0),
+            commands_processed: AtomicU64::new(0),
+            ops_per_sec: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Stats {
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+    pub fn set_port(&self, port: u16) {
+        self.port.store(port, Ordering::Relaxed);
+    }
+    pub fn port(&self) -> u16 {
+        self.port.load(Ordering::Relaxed)
+    }
+    pub fn connected(&self) {
+        self.connections_received.fetch_add(1, Ordering::Relaxed);
+        self.connected_clients.fetch_add(1, Ordering::Relaxed);
+    }
+    pub fn disconnected(&self) {
+        self.connected_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+    pub fn connected_clients(&self) -> usize {
+        self.connected_clients.load(Ordering::Relaxed)
+    }
+    pub fn connections_received(&self) -> u64 {
+        self.connections_received.load(Ordering::Relaxed)
+    }
+    // a connection blocked until the returned guard is dropped, whether it
+    // was served or went away
+    pub fn blocked(&self) -> Blocked<'_> {
+        self.blocked_clients.fetch_add(1, Ordering::Relaxed);
+        Blocked(self)
+    }
+    pub fn blocked_clients(&self) -> usize {
+        self.blocked_clients.load(Ordering::Relaxed)
+    }
+    pub fn processed(&self) {
+        self.commands_processed.fetch_add(1, Ordering::Relaxed);
+    }
+    pub fn commands_processed(&self) -> u64 {
+        self.commands_processed.load(Ordering::Relaxed)
+    }
+    pub fn ops_per_sec(&self) -> u64 {
+        self.ops_per_sec.load(Ordering::Relaxed)
+    }
+}
+
+pub(crate) struct Blocked<'a>(&'a Stats);
+
+impl Drop for Blocked<'_> {
+    fn drop(&mut self) {
+        self.0.blocked_clients.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+// keeps the instantaneous_ops_per_sec of INFO up to date: once a second it
+// takes the commands processed since the last time and reports the average of
+// the last samples, as redis does
+pub async fn sample_ops(db: Db) {
+    let mut ticks = tokio::time::interval(Duration::from_secs(1));
+    let mut samples = VecDeque::with_capacity(OPS_SAMPLES);
+    let stats = db.stats();
+    let mut last = (Instant::now(), stats.commands_processed());
+    loop {
+        ticks.tick().await;
+        let now = (Instant::now(), stats.commands_processed());
+        let elapsed = now.0.duration_since(last.0).as_secs_f64();
+        if elapsed > 0.0 {
+            if samples.len() == OPS_SAMPLES {
+                samples.pop_front();
+            }
+            samples.push_back((now.1 - last.1) as f64 / elapsed);
+            let average = samples.iter().sum::<f64>() / samples.len() as f64;
+            stats.ops_per_sec.store(average as u64, Ordering::Relaxed);
+        }
+        last = now;
+    }
+}
@@ -0,0 +1,435 @@
+// the value of a stream key: entries of field value pairs under ids that only
+// ever grow, the last one given out kept even once its entry is trimmed away
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Display,
+    mem::size_of,
+    ops::RangeInclusive,
+};
+
+use crate::memory::{self, sampled};
+
+// a stream entry id, milliseconds then a sequence number within them
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: Self = Self::new(0, 0);
+    pub const MAX: Self = Self::new(u64::MAX, u64::MAX);
+
+    pub const fn new(ms: u64, seq: u64) -> Self {
+        Self { ms, seq }
+    }
+    // `ms-seq`, or `ms` alone with `seq` as the sequence
+    pub fn parse(arg: &[u8], seq: u64) -> Option<Self> {
+        let text = std::str::from_utf8(arg).ok()?;
+        let (ms, seq) = match text.split_once('-') {
+            Some((ms, seq)) => (ms, parse_part(seq)?),
+            None => (text, seq),
+        };
+        Some(Self::new(parse_part(ms)?, seq))
+    }
+    // the id right after, none after the last possible one
+    pub fn next(self) -> Option<Self> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(Self::new(self.ms, seq)),
+            None => Some(Self::new(self.ms.checked_add(1)?, 0)),
+        }
+    }
+    // the id right before, none before 0-0
+    pub fn prev(self) -> Option<Self> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(Self::new(self.ms, seq)),
+            None => Some(Self::new(self.ms.checked_sub(1)?, u64::MAX)),
+        }
+    }
+}
+
+// digits only, so no sign or spaces pass as part of an id
+fn parse_part(text: &str) -> Option<u64> {
+    match !text.is_empty() && text.bytes().all(|b| b.is_ascii_digit()) {
+        true => text.parse().ok(),
+        false => None,
+    }
+}
+
+impl Display for StreamId {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+// the id XADD is asked to add an entry under
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NewId {
+    // `*`, from the clock
+    Auto,
+    // `ms-*`, the next sequence within the milliseconds given
+    AutoSeq(u64),
+    Explicit(StreamId),
+}
+
+impl NewId {
+    pub fn parse(arg: &[u8]) -> Option<Self> {
+        match arg {
+            b"*" => Some(NewId::Auto),
+            [ms @ .., b'-', b'*'] => {
+                Some(NewId::AutoSeq(parse_part(std::str::from_utf8(ms).ok()?)?))
+            }
+            id => Some(NewId::Explicit(StreamId::parse(id, 0)?)),
+        }
+    }
+}
+
+// why an entry cannot be added under the id asked for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdError {
+    Zero,
+    NotGreater,
+    Exhausted,
+}
+
+// what XADD trims the stream to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trim {
+    // the newest that many entries
+    MaxLen(usize),
+    // the entries from that id on
+    MinId(StreamId),
+}
+
+// the entries redis keeps in one node of its radix tree. An approximate trim
+// only removes whole nodes, so it is done as if the entries were in them
+pub const NODE_ENTRIES: usize = 100;
+
+// how XCLAIM claims: the idle time below which entries stay with their
+// consumer, what the delivery time and count are set to and whether entries
+// not pending are claimed too
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Claim {
+    pub min_idle: u64,
+    // none for now
+    pub delivered_at: Option<u64>,
+    // none for one more than before, or as it was with `just_id`
+    pub retry_count: Option<u64>,
+    pub force: bool,
+    pub just_id: bool,
+    // moves the last delivered id of the group up to it
+    pub last_id: Option<StreamId>,
+}
+
+// the field value pairs of one stream entry
+pub type StreamEntry = Vec<(Vec<u8>, Vec<u8>)>;
+
+// an entry delivered to a consumer of a group and not acknowledged yet
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Pending {
+    pub consumer: Vec<u8>,
+    // when it was last delivered, in the milliseconds of the keyspace clock
+    pub delivered_at: u64,
+    pub deliveries: u64,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Consumer {
+    // the last time it read or claimed anything, and the last time it was
+    // given an entry
+    pub seen_at: u64,
+    pub active_at: Option<u64>,
+    // the ids it has pending, each also in the pending entries of the group
+    pub pending: BTreeSet<StreamId>,
+}
+
+// a consumer group: where delivery of new entries is up to, and what was
+// delivered to whom without being acknowledged
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Group {
+    pub last_delivered: StreamId,
+    pub pending: BTreeMap<StreamId, Pending>,
+    pub consumers: BTreeMap<Vec<u8>, Consumer>,
+}
+
+impl Group {
+    pub fn new(last_delivered: StreamId) -> Self {
+        Self {
+            last_delivered,
+            ..Self::default()
+        }
+    }
+    // the consumer `name`, made at `now` when it is not there yet
+    pub fn consumer(&mut self, name: &[u8], now: u64) -> &mut Consumer {
+        self.consumers
+            .entry(name.to_vec())
+            .or_insert_with(|| Consumer {
+                seen_at: now,
+                ..Consumer::default()
+            })
+    }
+    // records a delivery of `id` to `consumer`, moving it over from whoever had
+    // it pending. The consumer is expected to be there already
+    pub fn deliver(&mut self, id: StreamId, consumer: &[u8], delivered_at: u64, deliveries: u64) {
+        let previous = self.pending.insert(
+            id,
+            Pending {
+                consumer: consumer.to_vec(),
+                delivered_at,
+                deliveries,
+            },
+        );
+        if let Some(previous) = previous.filter(|previous| previous.consumer != consumer) {
+            if let Some(owner) = self.consumers.get_mut(&previous.consumer) {
+                owner.pending.remove(&id);
+            }
+        }
+        if let Some(owner) = self.consumers.get_mut(consumer) {
+            owner.pending.insert(id);
+        }
+    }
+    // acknowledges `id`, whether it was pending
+    pub fn ack(&mut self, id: StreamId) -> bool {
+        let Some(pending) = self.pending.remove(&id) else {
+            return false;
+        };
+        if let Some(owner) = self.consumers.get_mut(&pending.consumer) {
+            owner.pending.remove(&id);
+        }
+        true
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Stream {
+    entries: BTreeMap<StreamId, StreamEntry>,
+    last_id: StreamId,
+    groups: BTreeMap<Vec<u8>, Group>,
+}
+
+impl Stream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+    // an estimate of the bytes it takes, the entries measured on the first
+    // `samples` of them, all of them for 0, and the groups in full
+    pub fn memory_usage(&self, samples: usize) -> usize {
+        let entry = size_of::<StreamId>() + size_of::<StreamEntry>();
+        let entries = self.entries.values().map(|fields| {
+            entry
+                + fields.capacity() * size_of::<(Vec<u8>, Vec<u8>)>()
+                + fields
+                    .iter()
+                    .map(|(field, value)| field.capacity() + value.capacity())
+                    .sum::<usize>()
+        });
+        let groups = self.groups.iter().map(|(name, group)| {
+            let pending = group
+                .pending
+                .values()
+                .map(|pending| {
+                    size_of::<StreamId>() + size_of::<Pending>() + pending.consumer.capacity()
+                })
+                .sum::<usize>();
+            let consumers = group
+                .consumers
+                .iter()
+                .map(|(name, consumer)| {
+                    memory::bytes(name)
+                        + size_of::<Consumer>()
+                        + consumer.pending.len() * size_of::<StreamId>()
+                })
+                .sum::<usize>();
+            memory::bytes(name) + size_of::<Group>() + pending + consumers
+        });
+        size_of::<Stream>() + sampled(entries, self.entries.len(), samples) + groups.sum::<usize>()
+    }
+    // the last id given out, 0-0 before any
+    pub fn last_id(&self) -> StreamId {
+        self.last_id
+    }
+    // the id an entry added at `now` gets, always greater than the last one
+    pub fn next_id(&self, id: NewId, now: u64) -> Result<StreamId, IdError> {
+        let last = self.last_id;
+        let id = match id {
+            NewId::Explicit(StreamId::MIN) => return Err(IdError::Zero),
+            NewId::Explicit(id) => id,
+            NewId::AutoSeq(ms) if ms == last.ms => last.next().ok_or(IdError::NotGreater)?,
+            NewId::AutoSeq(ms) => StreamId::new(ms, 0),
+            NewId::Auto if now > last.ms => StreamId::new(now, 0),
+            // a clock behind the last id keeps counting within its milliseconds
+            NewId::Auto => last.next().ok_or(IdError::Exhausted)?,
+        };
+        match id > last {
+            true => Ok(id),
+            false => Err(IdError::NotGreater),
+        }
+    }
+    // adds an entry under an id `next_id` gave
+    pub fn add(&mut self, id: StreamId, entry: StreamEntry) {
+        debug_assert!(id > self.last_id);
+        self.entries.insert(id, entry);
+        self.last_id = id;
+    }
+    pub fn get(&self, id: StreamId) -> Option<&StreamEntry> {
+        self.entries.get(&id)
+    }
+    // the entries from `ids.start()` to `ids.end()` both included, in order
+    pub fn range(
+        &self,
+        ids: RangeInclusive<StreamId>,
+    ) -> impl DoubleEndedIterator<Item = (StreamId, &StreamEntry)> {
+        // BTreeMap panics on a range that ends before it starts
+        (ids.start() <= ids.end())
+            .then(|| self.entries.range(ids))
+            .into_iter()
+            .flatten()
+            .map(|(id, entry)| (*id, entry))
+    }
+    pub fn group(&self, name: &[u8]) -> Option<&Group> {
+        self.groups.get(name)
+    }
+    pub fn group_mut(&mut self, name: &[u8]) -> Option<&mut Group> {
+        self.groups.get_mut(name)
+    }
+    // adds a group delivering the entries after `last_delivered`, false when
+    // there is one by that name already
+    pub fn create_group(&mut self, name: &[u8], last_delivered: StreamId) -> bool {
+        if self.groups.contains_key(name) {
+            return false;
+        }
+        self.groups
+            .insert(name.to_vec(), Group::new(last_delivered));
+        true
+    }
+    pub fn destroy_group(&mut self, name: &[u8]) -> bool {
+        self.groups.remove(name).is_some()
+    }
+    // XREADGROUP with `>`: up to `count` entries never delivered to the group,
+    // now pending for the consumer unless `noack`. None without the group
+    pub fn read_new(
+        &mut self,
+        group: &[u8],
+        consumer: &[u8],
+        count: usize,
+        noack: bool,
+        now: u64,
+    ) -> Option<Vec<(StreamId, StreamEntry)>> {
+        let group = self.groups.get_mut(group)?;
+        group.consumer(consumer, now).seen_at = now;
+        let entries: Vec<_> = match group.last_delivered.next() {
+            Some(start) => self
+                .entries
+                .range(start..)
+                .take(count)
+                .map(|(id, entry)| (*id, entry.clone()))
+                .collect(),
+            None => Vec::new(),
+        };
+        if let Some((last, _)) = entries.last() {
+            group.last_delivered = *last;
+            group.consumer(consumer, now).active_at = Some(now);
+        }
+        if !noack {
+            for (id, _) in &entries {
+                group.deliver(*id, consumer, now, 1);
+            }
+        }
+        Some(entries)
+    }
+    // XREADGROUP with an id: up to `count` of the entries pending for the
+    // consumer after `after`, delivered again. Pending entries no longer in
+    // the stream come without their fields. None without the group
+    pub fn read_pending(
+        &mut self,
+        group: &[u8],
+        consumer: &[u8],
+        after: StreamId,
+        count: usize,
+        now: u64,
+    ) -> Option<Vec<(StreamId, Option<StreamEntry>)>> {
+        let group = self.groups.get_mut(group)?;
+        let owner = group.consumer(consumer, now);
+        owner.seen_at = now;
+        let ids: Vec<_> = match after.next() {
+            Some(start) => owner.pending.range(start..).take(count).copied().collect(),
+            None => Vec::new(),
+        };
+        let mut entries = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(pending) = group.pending.get_mut(&id) {
+                pending.delivered_at = now;
+                pending.deliveries += 1;
+            }
+            entries.push((id, self.entries.get(&id).cloned()));
+        }
+        Some(entries)
+    }
+    // XCLAIM: hands the entries pending long enough over to the consumer,
+    // replying with those that are still in the stream. Pending entries that
+    // are not are dropped. None without the group
+    pub fn claim(
+        &mut self,
+        group: &[u8],
+        consumer: &[u8],
+        ids: &[StreamId],
+        claim: &Claim,
+        now: u64,
+    ) -> Option<Vec<(StreamId, StreamEntry)>> {
+        let group = self.groups.get_mut(group)?;
+        if let Some(last) = claim.last_id.filter(|last| *last > group.last_delivered) {
+            group.last_delivered = last;
+        }
+        group.consumer(consumer, now).seen_at = now;
+        let mut claimed = Vec::new();
+        for &id in ids {
+            let deliveries = match group.pending.get(&id) {
+                Some(pending) if now.saturating_sub(pending.delivered_at) < claim.min_idle => {
+                    continue
+                }
+                Some(pending) => pending.deliveries,
+                None if claim.force && self.entries.contains_key(&id) => 1,
+                None => continue,
+            };
+            let Some(entry) = self.entries.get(&id) else {
+                group.ack(id);
+                continue;
+            };
+            let deliveries = match (claim.retry_count, claim.just_id) {
+                (Some(count), _) => count,
+                (None, true) => deliveries,
+                (None, false) => deliveries + 1,
+            };
+            let delivered_at = claim.delivered_at.unwrap_or(now);
+            group.consumer(consumer, now).active_at = Some(now);
+            group.deliver(id, consumer, delivered_at, deliveries);
+            claimed.push((id, entry.clone()));
+        }
+        Some(claimed)
+    }
+    // removes the oldest entries `trim` leaves out, returning how many went.
+    // An approximate trim removes only whole nodes, and of those no more
+    // entries than `limit` when it is not 0
+    pub fn trim(&mut self, trim: Trim, approximate: Option<usize>) -> usize {
+        let excess = match trim {
+            Trim::MaxLen(len) => self.len().saturating_sub(len),
+            Trim::MinId(id) => self.entries.range(..id).count(),
+        };
+        let removed = match approximate {
+            None => excess,
+            Some(0) => excess - excess % NODE_ENTRIES,
+            Some(limit) => (excess - excess % NODE_ENTRIES).min(limit - limit % NODE_ENTRIES),
+        };
+        for _ in 0..removed {
+            self.entries.pop_first();
+        }
+        removed
+    }
+}
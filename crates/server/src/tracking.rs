@@ -0,0 +1,211 @@
+// server-assisted client side caching, CLIENT TRACKING. A connection tracking
+// keys is told when they change: by default those it read, again only once it
+// reads them after that, or in BCAST mode every key starting with one of its
+// prefixes. It is told with an invalidate push in RESP3, or the message a
+// RESP2 connection subscribed to __redis__:invalidate gets when it is the one
+// tracking connections redirect to
+
+use std::{
+    collections::{BTreeSet, HashMap},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Mutex, MutexGuard,
+    },
+};
+
+use indexmap::IndexMap;
+use protocol::{ProcVersion, RedirsValue};
+use rand::Rng;
+
+use crate::{clients::Clients, pubsub::PubSub};
+
+// as many keys as redis remembers the readers of before forgetting some
+pub const DEFAULT_TRACKING_MAX_KEYS: usize = 1_000_000;
+
+// the channel RESP2 connections are told on
+pub(crate) const INVALIDATE_CHANNEL: &[u8] = b"__redis__:invalidate";
+
+// how a connection tracks keys
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct Tracking {
+    // the connection told instead of this one
+    pub redirect: Option<i64>,
+    // BCAST mode and the prefixes of the keys it is told about, any key for
+    // none; the keys it reads are not remembered then
+    pub prefixes: Option<Vec<Vec<u8>>>,
+}
+
+#[derive(Debug)]
+struct State {
+    connections: HashMap<i64, Tracking>,
+    // the connections that read each key since it last changed
+    keys: IndexMap<Vec<u8>, BTreeSet<i64>>,
+    max_keys: usize,
+}
+
+#[derive(Debug)]
+pub(crate) struct Tracker {
+    state: Mutex<State>,
+    // whether any connection tracks, the keys written are only collected then
+    active: AtomicBool,
+}
+
+impl Default for Tracker {
+    fn default() -> Self {
+        Self {
+            state: Mutex::new(State {
+                connections: HashMap::new(),
+                keys: IndexMap::new(),
+                max_keys: DEFAULT_TRACKING_MAX_KEYS,
+            }),
+            active: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Tracker {
+    fn state(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+    pub fn active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+    pub fn start(&self, id: i64, tracking: Tracking) {
+        let mut state = self.state();
+        state.connections.insert(id, tracking);
+        self.active.store(true, Ordering::Relaxed);
+    }
+    // the keys it read stay in the table until they change or are evicted,
+    // it is not told about them anymore
+    pub fn stop(&self, id: i64) {
+        let mut state = self.state();
+        state.connections.remove(&id);
+        self.active
+            .store(!state.connections.is_empty(), Ordering::Relaxed);
+    }
+    // the keys a connection tracking in the default mode read, the oldest
+    // ones evicted, and their readers told, once there are too many
+    pub fn read(&self, id: i64, keys: &[&[u8]], clients: &Clients, pubsub: &PubSub) {
+        let mut state = self.state();
+        for key in keys {
+            match state.keys.get_mut(*key) {
+                Some(readers) => {
+                    readers.insert(id);
+                }
+                None => {
+                    state.keys.insert(key.to_vec(), BTreeSet::from([id]));
+                }
+            }
+        }
+        let mut evicted = Vec::new();
+        let mut rng = rand::rng();
+        while state.max_keys > 0 && state.keys.len() > state.max_keys {
+            let index = rng.random_range(0..state.keys.len());
+            evicted.extend(state.keys.swap_remove_index(index));
+        }
+        let told = tell(&state, evicted);
+        drop(state);
+        send(told, clients, pubsub);
+    }
+    pub fn set_max_keys(&self, max_keys: usize) {
+        self.state().max_keys = max_keys;
+    }
+    pub fn max_keys(&self) -> usize {
+        self.state().max_keys
+    }
+    // the keys changed: their readers and the broadcast connections whose
+    // prefixes they match are told, `flushed` telling every connection that
+    // all of them may have
+    pub fn invalidate(
+        &self,
+        written: Vec<Vec<u8>>,
+        flushed: bool,
+        clients: &Clients,
+        pubsub: &PubSub,
+    ) {
+        let mut state = self.state();
+        let mut told = match flushed {
+            true => {
+                state.keys.clear();
+                state
+                    .connections
+                    .iter()
+                    .map(|(id, tracking)| (tracking.redirect.unwrap_or(*id), None))
+                    .collect()
+            }
+            false => {
+                let read = written
+                    .iter()
+                    .filter_map(|key| Some((key.clone(), state.keys.swap_remove(key)?)))
+                    .collect();
+                tell(&state, read)
+            }
+        };
+        for (id, tracking) in &state.connections {
+            let Some(prefixes) = &tracking.prefixes else {
+                continue;
+            };
+            let matching = written.iter().filter(|key| {
+                prefixes.is_empty() || prefixes.iter().any(|prefix| key.starts_with(prefix))
+            });
+            let target = tracking.redirect.unwrap_or(*id);
+            if let Some(Some(keys)) = told.get_mut(&target) {
+                keys.extend(matching.cloned());
+            } else if !flushed {
+                let keys: Vec<_> = matching.cloned().collect();
+                if !keys.is_empty() {
+                    told.insert(target, Some(keys));
+                }
+            }
+        }
+        drop(state);
+        send(told, clients, pubsub);
+    }
+}
+
+// the connections to tell about `keys`, given with the readers of each, by
+// where they are told. None for all the keys
+type Told = HashMap<i64, Option<Vec<Vec<u8>>>>;
+
+fn tell(state: &State, keys: Vec<(Vec<u8>, BTreeSet<i64>)>) -> Told {
+    let mut told = Told::new();
+    for (key, readers) in keys {
+        for reader in readers {
+            // those that stopped tracking since are not told
+            let Some(tracking) = state.connections.get(&reader) else {
+                continue;
+            };
+            let target = tracking.redirect.unwrap_or(reader);
+            if let Some(keys) = told.entry(target).or_insert_with(|| Some(Vec::new())) {
+                keys.push(key.clone());
+            }
+        }
+    }
+    told
+}
+
+// a push to a RESP3 connection, a message to a RESP2 one subscribed to
+// __redis__:invalidate, nothing to any other
+fn send(told: Told, clients: &Clients, pubsub: &PubSub) {
+    for (id, keys) in told {
+        let Some(registration) = clients.get(id) else {
+            continue;
+        };
+        let keys = match keys {
+            Some(keys) => {
+                RedirsValue::Array(Some(keys.into_iter().map(RedirsValue::from).collect()))
+            }
+            None => RedirsValue::Null,
+        };
+        let message = match registration.info().proto {
+            ProcVersion::V3 => vec![RedirsValue::from("invalidate"), keys],
+            _ if pubsub.subscribed(INVALIDATE_CHANNEL, id) => vec![
+                RedirsValue::from("message"),
+                RedirsValue::from(INVALIDATE_CHANNEL),
+                keys,
+            ],
+            _ => continue,
+        };
+        registration.post(RedirsValue::Push(message));
+    }
+}
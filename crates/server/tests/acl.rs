@@ -0,0 +1,235 @@
+mod common;
+
+use common::{bulk, error, int, nil, simple, start, Client};
+use protocol::RedirsValue;
+
+fn strings(items: &[&str]) -> RedirsValue {
+    RedirsValue::Array(Some(items.iter().map(|item| bulk(item)).collect()))
+}
+
+#[tokio::test]
+async fn a_read_only_user_can_get_but_not_set() {
+    let addr = start().await;
+    let mut admin = Client::connect(addr).await;
+    assert_eq!(
+        admin
+            .run(&[
+                "ACL",
+                "SETUSER",
+                "reader",
+                "on",
+                ">ro-secret",
+                "~*",
+                "+@read"
+            ])
+            .await,
+        simple("OK")
+    );
+    assert_eq!(admin.run(&["SET", "k", "v"]).await, simple("OK"));
+    let mut reader = Client::connect(addr).await;
+    assert_eq!(
+        reader.run(&["AUTH", "reader", "ro-secret"]).await,
+        simple("OK")
+    );
+    assert_eq!(
+        reader.run(&["ACL", "WHOAMI"]).await,
+        error("NOPERM User reader has no permissions to run the 'acl' command")
+    );
+    assert_eq!(reader.run(&["GET", "k"]).await, bulk("v"));
+    assert_eq!(reader.run(&["HGET", "h", "f"]).await, nil());
+    assert_eq!(
+        reader.run(&["SET", "k", "w"]).await,
+        error("NOPERM User reader has no permissions to run the 'set' command")
+    );
+    assert_eq!(
+        reader.run(&["LPUSH", "l", "x"]).await,
+        error("NOPERM User reader has no permissions to run the 'lpush' command")
+    );
+    // a forbidden command aborts a transaction as a malformed one does
+    assert_eq!(
+        admin
+            .run(&["ACL", "SETUSER", "reader", "+multi", "+exec"])
+            .await,
+        simple("OK")
+    );
+    assert_eq!(reader.run(&["MULTI"]).await, simple("OK"));
+    assert_eq!(
+        reader.run(&["SET", "k", "w"]).await,
+        error("NOPERM User reader has no permissions to run the 'set' command")
+    );
+    assert_eq!(
+        reader.run(&["EXEC"]).await,
+        error("EXECABORT Transaction discarded because of previous errors.")
+    );
+    assert_eq!(admin.run(&["GET", "k"]).await, bulk("v"));
+}
+
+#[tokio::test]
+async fn a_pattern_restricted_user_only_touches_its_keys() {
+    let addr = start().await;
+    let mut admin = Client::connect(addr).await;
+    assert_eq!(
+        admin
+            .run(&["ACL", "SETUSER", "app1", "on", ">pw", "~app1:*", "+@all"])
+            .await,
+        simple("OK")
+    );
+    let mut app = Client::connect(addr).await;
+    assert_eq!(app.run(&["AUTH", "app1", "pw"]).await, simple("OK"));
+    assert_eq!(app.run(&["SET", "app1:a", "1"]).await, simple("OK"));
+    assert_eq!(app.run(&["GET", "app1:a"]).await, bulk("1"));
+    assert_eq!(
+        app.run(&["GET", "app2:a"]).await,
+        error("NOPERM User app1 has no permissions to access the 'app2:a' key")
+    );
+    // every key of a command is checked, not only the first
+    assert_eq!(
+        app.run(&["MSET", "app1:b", "2", "other", "3"]).await,
+        error("NOPERM User app1 has no permissions to access the 'other' key")
+    );
+    assert_eq!(
+        app.run(&["RENAME", "app1:a", "elsewhere"]).await,
+        error("NOPERM User app1 has no permissions to access the 'elsewhere' key")
+    );
+    assert_eq!(
+        app.run(&["XREAD", "STREAMS", "app1:s", "secret", "0", "0"])
+            .await,
+        error("NOPERM User app1 has no permissions to access the 'secret' key")
+    );
+    assert_eq!(
+        app.run(&["EVAL", "return 1", "1", "nope"]).await,
+        error("NOPERM User app1 has no permissions to access the 'nope' key")
+    );
+    assert_eq!(admin.run(&["EXISTS", "app1:b", "other"]).await, int(0));
+    // the values after each key are not keys
+    assert_eq!(app.run(&["MSET", "app1:b", "other"]).await, simple("OK"));
+    assert_eq!(app.run(&["PING"]).await, simple("PONG"));
+}
+
+#[tokio::test]
+async fn setuser_rules_are_shown_back() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        client.run(&["ACL", "LIST"]).await,
+        strings(&["user default on nopass ~* +@all"])
+    );
+    assert_eq!(
+        client
+            .run(&[
+                "ACL", "SETUSER", "alice", "on", ">ro-secret", "~cache:*", "-@all", "+@read", "-keys",
+                "+slowlog|get",
+            ])
+            .await,
+        error("ERR Error in ACL SETUSER modifier '+slowlog|get': Unknown command or category name in ACL")
+    );
+    // nothing is applied when a rule is wrong
+    assert_eq!(client.run(&["ACL", "GETUSER", "alice"]).await, nil());
+    assert_eq!(
+        client
+            .run(&[
+                "ACL",
+                "SETUSER",
+                "alice",
+                "on",
+                ">ro-secret",
+                "~cache:*",
+                "+@read",
+                "-keys"
+            ])
+            .await,
+        simple("OK")
+    );
+    assert_eq!(
+        client.run(&["ACL", "GETUSER", "alice"]).await,
+        RedirsValue::Array(Some(vec![
+            bulk("flags"),
+            strings(&["on"]),
+            bulk("passwords"),
+            strings(&["61188f06130ab13fc430b8ba1e0e13e10ea01beb25ca9e8965623bfc0c971ba8"]),
+            bulk("commands"),
+            bulk("-@all +@read -keys"),
+            bulk("keys"),
+            bulk("~cache:*"),
+        ]))
+    );
+    assert_eq!(
+        client.run(&["ACL", "USERS"]).await,
+        strings(&["alice", "default"])
+    );
+    assert_eq!(
+        client.run(&["ACL", "SETUSER", "alice", "bogus"]).await,
+        error("ERR Error in ACL SETUSER modifier 'bogus': Syntax error")
+    );
+    assert_eq!(
+        client.run(&["ACL", "SETUSER", "alice", "#abc"]).await,
+        error("ERR Error in ACL SETUSER modifier '#abc': The password hash must be exactly 64 characters and contain only lowercase hexadecimal characters")
+    );
+    assert_eq!(
+        client.run(&["ACL", "DELUSER", "default"]).await,
+        error("ERR The 'default' user cannot be removed")
+    );
+    assert_eq!(
+        client.run(&["ACL", "DELUSER", "alice", "bob"]).await,
+        int(1)
+    );
+    assert_eq!(client.run(&["ACL", "WHOAMI"]).await, bulk("default"));
+}
+
+#[tokio::test]
+async fn auth_resolves_against_the_users() {
+    let addr = start().await;
+    let mut admin = Client::connect(addr).await;
+    admin
+        .run(&[
+            "ACL",
+            "SETUSER",
+            "bob",
+            "#e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855",
+            "+@all",
+            "~*",
+        ])
+        .await;
+    let mut client = Client::connect(addr).await;
+    // bob is off until turned on
+    assert_eq!(
+        client.run(&["AUTH", "bob", ""]).await,
+        error("WRONGPASS invalid username-password pair")
+    );
+    admin.run(&["ACL", "SETUSER", "bob", "on"]).await;
+    let reply = client.run(&["HELLO", "3", "AUTH", "bob", ""]).await;
+    assert!(matches!(reply, RedirsValue::Map(_)), "{reply:?}");
+    assert_eq!(client.run(&["ACL", "WHOAMI"]).await, bulk("bob"));
+    // the permissions are looked up for every command
+    admin.run(&["ACL", "SETUSER", "bob", "-get"]).await;
+    assert_eq!(
+        client.run(&["GET", "x"]).await,
+        error("NOPERM User bob has no permissions to run the 'get' command")
+    );
+    admin.run(&["ACL", "DELUSER", "bob"]).await;
+    assert_eq!(
+        client.run(&["PING"]).await,
+        error("NOAUTH Authentication required.")
+    );
+}
+
+#[tokio::test]
+async fn acl_cat_lists_categories_and_their_commands() {
+    let mut client = Client::connect(start().await).await;
+    let RedirsValue::Array(Some(categories)) = client.run(&["ACL", "CAT"]).await else {
+        panic!("expected an array");
+    };
+    assert!(categories.contains(&bulk("read")));
+    assert!(categories.contains(&bulk("sortedset")));
+    let RedirsValue::Array(Some(commands)) = client.run(&["ACL", "CAT", "hyperloglog"]).await
+    else {
+        panic!("expected an array");
+    };
+    assert_eq!(
+        commands,
+        vec![bulk("pfadd"), bulk("pfcount"), bulk("pfmerge")]
+    );
+    assert_eq!(
+        client.run(&["ACL", "CAT", "nope"]).await,
+        error("ERR Unknown category 'nope'")
+    );
+}
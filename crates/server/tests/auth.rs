@@ -0,0 +1,91 @@
+mod common;
+
+use std::net::SocketAddr;
+
+use common::{error, nil, simple, start, start_with, Client};
+use protocol::RedirsValue;
+use server::Db;
+
+const NOAUTH: &str = "NOAUTH Authentication required.";
+const WRONGPASS: &str = "WRONGPASS invalid username-password pair";
+
+async fn start_protected() -> SocketAddr {
+    let db = Db::new();
+    db.set_requirepass(Some(b"s3cret".to_vec()));
+    start_with(db).await
+}
+
+#[tokio::test]
+async fn commands_need_auth_once_a_password_is_required() {
+    let mut client = Client::connect(start_protected().await).await;
+    assert_eq!(client.run(&["GET", "x"]).await, error(NOAUTH));
+    assert_eq!(client.run(&["PING"]).await, error(NOAUTH));
+    assert_eq!(client.run(&["HELLO", "3"]).await, error(NOAUTH));
+    assert_eq!(client.run(&["MULTI"]).await, error(NOAUTH));
+    assert_eq!(client.run(&["AUTH", "nope"]).await, error(WRONGPASS));
+    assert_eq!(client.run(&["GET", "x"]).await, error(NOAUTH));
+    assert_eq!(client.run(&["AUTH", "s3cret"]).await, simple("OK"));
+    assert_eq!(client.run(&["GET", "x"]).await, nil());
+}
+
+#[tokio::test]
+async fn auth_takes_the_default_user_and_its_password() {
+    let mut client = Client::connect(start_protected().await).await;
+    assert_eq!(
+        client.run(&["AUTH", "alice", "s3cret"]).await,
+        error(WRONGPASS)
+    );
+    assert_eq!(
+        client.run(&["AUTH", "default", "s3cre"]).await,
+        error(WRONGPASS)
+    );
+    assert_eq!(
+        client.run(&["AUTH", "default", "s3cret", "x"]).await,
+        error("ERR syntax error")
+    );
+    assert_eq!(
+        client.run(&["AUTH", "default", "s3cret"]).await,
+        simple("OK")
+    );
+    assert_eq!(client.run(&["PING"]).await, simple("PONG"));
+}
+
+#[tokio::test]
+async fn auth_is_an_error_without_a_password() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        client.run(&["AUTH", "s3cret"]).await,
+        error("ERR Client sent AUTH, but no password is set")
+    );
+    assert_eq!(client.run(&["PING"]).await, simple("PONG"));
+}
+
+#[tokio::test]
+async fn hello_authenticates_and_switches_protocol_together() {
+    let mut client = Client::connect(start_protected().await).await;
+    assert_eq!(
+        client
+            .run(&["HELLO", "3", "AUTH", "default", "wrong"])
+            .await,
+        error(WRONGPASS)
+    );
+    // a failed handshake leaves the protocol as it was
+    assert_eq!(client.run(&["GET", "x"]).await, error(NOAUTH));
+    let reply = client
+        .run(&["HELLO", "3", "AUTH", "default", "s3cret", "SETNAME", "app"])
+        .await;
+    let RedirsValue::Map(map) = reply else {
+        panic!("expected a map, got {reply:?}");
+    };
+    assert_eq!(
+        map.get(&RedirsValue::from("proto")),
+        Some(&RedirsValue::Integer(3))
+    );
+    assert_eq!(client.run(&["GET", "x"]).await, RedirsValue::Null);
+}
+
+#[tokio::test]
+async fn quit_needs_no_auth() {
+    let mut client = Client::connect(start_protected().await).await;
+    assert_eq!(client.run(&["QUIT"]).await, simple("OK"));
+}
@@ -0,0 +1,219 @@
+mod common;
+
+use common::{bulk, error, int, start, Client};
+use protocol::RedirsValue;
+
+#[tokio::test]
+async fn setbit_and_getbit() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(client.run(&["SETBIT", "k", "7", "1"]).await, int(0));
+    assert_eq!(client.run(&["SETBIT", "k", "7", "1"]).await, int(1));
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("\u{1}"));
+    assert_eq!(client.run(&["GETBIT", "k", "7"]).await, int(1));
+    assert_eq!(client.run(&["GETBIT", "k", "0"]).await, int(0));
+    assert_eq!(client.run(&["GETBIT", "k", "100"]).await, int(0));
+    assert_eq!(client.run(&["GETBIT", "missing", "3"]).await, int(0));
+    // the string grows with zero bytes up to the one holding the bit
+    assert_eq!(client.run(&["SETBIT", "k", "25", "1"]).await, int(0));
+    assert_eq!(client.run(&["STRLEN", "k"]).await, int(4));
+    assert_eq!(
+        client.run(&["GET", "k"]).await,
+        RedirsValue::from(&b"\x01\x00\x00\x40"[..])
+    );
+    assert_eq!(client.run(&["SETBIT", "k", "7", "0"]).await, int(1));
+    assert_eq!(client.run(&["GETBIT", "k", "7"]).await, int(0));
+    // clearing a bit past the end still grows the string
+    assert_eq!(client.run(&["SETBIT", "other", "9", "0"]).await, int(0));
+    assert_eq!(client.run(&["STRLEN", "other"]).await, int(2));
+}
+
+#[tokio::test]
+async fn bit_offsets_and_values_are_checked() {
+    let mut client = Client::connect(start().await).await;
+    let offset = error("ERR bit offset is not an integer or out of range");
+    for bad in ["-1", "4294967296", "x", "1.5"] {
+        assert_eq!(
+            client.run(&["SETBIT", "k", bad, "1"]).await,
+            offset,
+            "{bad}"
+        );
+        assert_eq!(client.run(&["GETBIT", "k", bad]).await, offset, "{bad}");
+    }
+    // the last bit of the longest string is fine
+    assert_eq!(client.run(&["GETBIT", "k", "4294967295"]).await, int(0));
+    for bad in ["2", "-1", "x", ""] {
+        assert_eq!(
+            client.run(&["SETBIT", "k", "0", bad]).await,
+            error("ERR bit is not an integer or out of range"),
+            "{bad}"
+        );
+    }
+    assert_eq!(client.run(&["EXISTS", "k"]).await, int(0));
+    client.run(&["RPUSH", "list", "a"]).await;
+    let wrongtype = error("WRONGTYPE Operation against a key holding the wrong kind of value");
+    for args in [
+        &["SETBIT", "list", "0", "1"][..],
+        &["GETBIT", "list", "0"],
+        &["BITCOUNT", "list"],
+        &["BITPOS", "list", "1"],
+        &["BITOP", "OR", "dest", "list"],
+    ] {
+        assert_eq!(client.run(args).await, wrongtype, "{args:?}");
+    }
+}
+
+#[tokio::test]
+async fn bitcount_over_byte_and_bit_ranges() {
+    let mut client = Client::connect(start().await).await;
+    // the examples of the redis docs
+    client.run(&["SET", "k", "foobar"]).await;
+    let cases: &[(&[&str], i64)] = &[
+        (&[], 26),
+        (&["0", "0"], 4),
+        (&["1", "1"], 6),
+        (&["1", "1", "BYTE"], 6),
+        (&["5", "30", "BIT"], 17),
+        (&["0", "-1"], 26),
+        (&["-2", "-1"], 7),
+        (&["-1", "-2"], 0),
+        (&["2", "1"], 0),
+        (&["0", "100"], 26),
+        (&["-100", "0"], 4),
+        (&["0", "7", "bit"], 4),
+        (&["-8", "-1", "BIT"], 4),
+        (&["3", "3", "BIT"], 0),
+        (&["1", "1", "BIT"], 1),
+        (&["9", "12", "BIT"], 3),
+    ];
+    for (range, expected) in cases {
+        let mut args = vec!["BITCOUNT", "k"];
+        args.extend(*range);
+        assert_eq!(client.run(&args).await, int(*expected), "{range:?}");
+    }
+    // longer than a word, so the counting goes eight bytes at a time
+    let ones = vec![0xff; 21];
+    client.run_bytes(&[b"SET", b"long", &ones]).await;
+    assert_eq!(client.run(&["BITCOUNT", "long"]).await, int(168));
+    assert_eq!(
+        client.run(&["BITCOUNT", "long", "3", "-3", "BIT"]).await,
+        int(163)
+    );
+    assert_eq!(client.run(&["BITCOUNT", "missing"]).await, int(0));
+    assert_eq!(
+        client.run(&["BITCOUNT", "k", "0"]).await,
+        error("ERR syntax error")
+    );
+    assert_eq!(
+        client.run(&["BITCOUNT", "k", "0", "1", "WORD"]).await,
+        error("ERR syntax error")
+    );
+    assert_eq!(
+        client.run(&["BITCOUNT", "k", "a", "1"]).await,
+        error("ERR value is not an integer or out of range")
+    );
+}
+
+#[tokio::test]
+async fn bitpos_cases_of_the_redis_docs() {
+    let mut client = Client::connect(start().await).await;
+    let cases: &[(&[u8], &[&str], i64)] = &[
+        (b"\xff\xf0\x00", &["0"], 12),
+        (b"\x00\xff\xf0", &["1", "0"], 8),
+        (b"\x00\xff\xf0", &["1", "2"], 16),
+        (b"\x00\xff\xf0", &["1", "2", "-1", "BYTE"], 16),
+        (b"\x00\xff\xf0", &["1", "7", "15", "BIT"], 8),
+        (b"\x00\x00\x00", &["1"], -1),
+        (b"\x00\x00\x00", &["1", "7", "-3", "BIT"], -1),
+        // looking for a clear bit without an end sees zeros past the string
+        (b"\xff\xff\xff", &["0"], 24),
+        (b"\xff\xff\xff", &["0", "1"], 24),
+        // but not with an end
+        (b"\xff\xff\xff", &["0", "0", "-1"], -1),
+        (b"\xff\xff\xff", &["0", "0", "-1", "BIT"], -1),
+        (b"\xff\xff\x7f", &["0", "0", "-1"], 16),
+        (b"\x0f", &["1", "-1"], 4),
+        (b"\x0f\x00", &["1", "2", "3", "BIT"], -1),
+        (b"\x0f\x00", &["0", "2", "5", "BIT"], 2),
+        (b"\x00\x01", &["1", "-1", "-1"], 15),
+        (b"\x01", &["1", "3", "2"], -1),
+    ];
+    for (value, args, expected) in cases {
+        client.run_bytes(&[b"SET", b"k", value]).await;
+        let mut request = vec!["BITPOS", "k"];
+        request.extend(*args);
+        assert_eq!(
+            client.run(&request).await,
+            int(*expected),
+            "{value:?} {args:?}"
+        );
+    }
+    assert_eq!(client.run(&["BITPOS", "missing", "0"]).await, int(0));
+    assert_eq!(client.run(&["BITPOS", "missing", "1"]).await, int(-1));
+    assert_eq!(
+        client.run(&["BITPOS", "k", "2"]).await,
+        error("ERR The bit argument must be 1 or 0.")
+    );
+    assert_eq!(
+        client
+            .run(&["BITPOS", "k", "1", "0", "1", "BIT", "extra"])
+            .await,
+        error("ERR syntax error")
+    );
+}
+
+#[tokio::test]
+async fn bitop_writes_the_destination() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "a", "foobar"]).await;
+    client.run(&["SET", "b", "abcdef"]).await;
+    assert_eq!(
+        client.run(&["BITOP", "AND", "dest", "a", "b"]).await,
+        int(6)
+    );
+    assert_eq!(client.run(&["GET", "dest"]).await, bulk("`bc`ab"));
+    assert_eq!(client.run(&["BITOP", "or", "dest", "a", "b"]).await, int(6));
+    assert_eq!(client.run(&["GET", "dest"]).await, bulk("goofev"));
+    client.run_bytes(&[b"SET", b"x", b"\x0f\xf0"]).await;
+    client.run_bytes(&[b"SET", b"y", b"\xff"]).await;
+    // shorter sources count as zero bytes
+    assert_eq!(
+        client.run(&["BITOP", "XOR", "dest", "x", "y"]).await,
+        int(2)
+    );
+    assert_eq!(
+        client.run(&["GET", "dest"]).await,
+        RedirsValue::from(&b"\xf0\xf0"[..])
+    );
+    assert_eq!(
+        client.run(&["BITOP", "AND", "dest", "x", "missing"]).await,
+        int(2)
+    );
+    assert_eq!(
+        client.run(&["GET", "dest"]).await,
+        RedirsValue::from(&b"\x00\x00"[..])
+    );
+    assert_eq!(client.run(&["BITOP", "NOT", "dest", "x"]).await, int(2));
+    assert_eq!(
+        client.run(&["GET", "dest"]).await,
+        RedirsValue::from(&b"\xf0\x0f"[..])
+    );
+    // an empty result removes the destination, whatever it held, and the
+    // stored value carries no deadline
+    client.run(&["EXPIRE", "dest", "100"]).await;
+    assert_eq!(client.run(&["BITOP", "OR", "dest", "x"]).await, int(2));
+    assert_eq!(client.run(&["TTL", "dest"]).await, int(-1));
+    client.run(&["RPUSH", "list", "a"]).await;
+    assert_eq!(
+        client.run(&["BITOP", "OR", "list", "missing"]).await,
+        int(0)
+    );
+    assert_eq!(client.run(&["EXISTS", "list"]).await, int(0));
+    assert_eq!(
+        client.run(&["BITOP", "NOT", "dest", "x", "y"]).await,
+        error("ERR BITOP NOT must be called with a single source key.")
+    );
+    assert_eq!(
+        client.run(&["BITOP", "NAND", "dest", "x"]).await,
+        error("ERR syntax error")
+    );
+}
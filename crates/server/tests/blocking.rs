@@ -0,0 +1,323 @@
+mod common;
+
+use std::{
+    collections::HashSet,
+    time::{Duration, Instant},
+};
+
+use common::{array, bulk, error, int, simple, start, Client};
+use protocol::RedirsValue;
+use tokio::time::sleep;
+
+// long enough for a request sent before it to have blocked
+const SETTLE: Duration = Duration::from_millis(50);
+
+#[tokio::test]
+async fn a_list_already_there_is_popped_right_away() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["RPUSH", "b", "1", "2", "3"]).await;
+    assert_eq!(
+        client.run(&["BLPOP", "a", "b", "0"]).await,
+        array(&["b", "1"])
+    );
+    assert_eq!(
+        client.run(&["BRPOP", "a", "b", "0"]).await,
+        array(&["b", "3"])
+    );
+    assert_eq!(client.run(&["BLPOP", "b", "0"]).await, array(&["b", "2"]));
+    assert_eq!(client.run(&["EXISTS", "b"]).await, int(0));
+}
+
+#[tokio::test]
+async fn the_timeout_replies_with_a_nil_array() {
+    let mut client = Client::connect(start().await).await;
+    let started = Instant::now();
+    assert_eq!(
+        client.run(&["BLPOP", "empty", "0.1"]).await,
+        RedirsValue::Array(None)
+    );
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    assert_eq!(client.run(&["PING"]).await, simple("PONG"));
+}
+
+#[tokio::test]
+async fn a_push_wakes_the_blocked_connection() {
+    let addr = start().await;
+    let mut blocked = Client::connect(addr).await;
+    let mut pusher = Client::connect(addr).await;
+    let waiting = tokio::spawn(async move {
+        let reply = blocked.run(&["BLPOP", "other", "queue", "0"]).await;
+        (reply, blocked)
+    });
+    sleep(SETTLE).await;
+    assert_eq!(pusher.run(&["RPUSH", "queue", "job"]).await, int(1));
+    let (reply, _) = waiting.await.unwrap();
+    assert_eq!(reply, array(&["queue", "job"]));
+    assert_eq!(pusher.run(&["EXISTS", "queue"]).await, int(0));
+}
+
+#[tokio::test]
+async fn waiters_are_served_in_the_order_they_blocked() {
+    let addr = start().await;
+    let mut waiting = Vec::new();
+    for _ in 0..3 {
+        let mut client = Client::connect(addr).await;
+        waiting.push(tokio::spawn(async move {
+            client.run(&["BRPOP", "queue", "0"]).await
+        }));
+        sleep(SETTLE).await;
+    }
+    let mut pusher = Client::connect(addr).await;
+    // BRPOP takes from the tail, the first waiter gets the last element
+    assert_eq!(
+        pusher
+            .run(&["LPUSH", "queue", "c", "b", "a", "extra"])
+            .await,
+        int(4)
+    );
+    let mut replies = Vec::new();
+    for task in waiting {
+        replies.push(task.await.unwrap());
+    }
+    assert_eq!(
+        replies,
+        [
+            array(&["queue", "c"]),
+            array(&["queue", "b"]),
+            array(&["queue", "a"])
+        ]
+    );
+    assert_eq!(
+        pusher.run(&["LRANGE", "queue", "0", "-1"]).await,
+        array(&["extra"])
+    );
+}
+
+#[tokio::test]
+async fn requests_sent_while_blocked_are_answered_after() {
+    let addr = start().await;
+    let mut blocked = Client::connect(addr).await;
+    blocked
+        .send(b"*3\r\n$5\r\nBLPOP\r\n$1\r\nk\r\n$1\r\n0\r\n*1\r\n$4\r\nPING\r\n")
+        .await;
+    sleep(SETTLE).await;
+    let mut pusher = Client::connect(addr).await;
+    pusher.run(&["RPUSH", "k", "v"]).await;
+    assert_eq!(blocked.reply().await, array(&["k", "v"]));
+    assert_eq!(blocked.reply().await, simple("PONG"));
+}
+
+#[tokio::test]
+async fn a_connection_closed_while_blocked_takes_nothing() {
+    let addr = start().await;
+    let mut leaving = Client::connect(addr).await;
+    leaving
+        .send(b"*3\r\n$5\r\nBLPOP\r\n$1\r\nk\r\n$1\r\n0\r\n")
+        .await;
+    sleep(SETTLE).await;
+    drop(leaving);
+    sleep(SETTLE).await;
+    let mut pusher = Client::connect(addr).await;
+    pusher.run(&["RPUSH", "k", "v"]).await;
+    assert_eq!(pusher.run(&["LLEN", "k"]).await, int(1));
+}
+
+#[tokio::test]
+async fn racing_waiters_and_pushers_get_each_element_once() {
+    let addr = start().await;
+    let n = 32;
+    let waiters: Vec<_> = (0..n)
+        .map(|_| {
+            tokio::spawn(async move {
+                let mut client = Client::connect(addr).await;
+                client.run(&["BLPOP", "queue", "5"]).await
+            })
+        })
+        .collect();
+    let pushers: Vec<_> = (0..n)
+        .map(|i| {
+            tokio::spawn(async move {
+                let mut client = Client::connect(addr).await;
+                client.run(&["RPUSH", "queue", &i.to_string()]).await;
+            })
+        })
+        .collect();
+    for pusher in pushers {
+        pusher.await.unwrap();
+    }
+    let mut received = HashSet::new();
+    for waiter in waiters {
+        let RedirsValue::Array(Some(reply)) = waiter.await.unwrap() else {
+            panic!("a waiter timed out");
+        };
+        let RedirsValue::BulkString(Some(element)) = &reply[1] else {
+            panic!("{reply:?}");
+        };
+        assert!(received.insert(element.clone()), "delivered twice");
+    }
+    assert_eq!(received.len(), n);
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.run(&["EXISTS", "queue"]).await, int(0));
+}
+
+#[tokio::test]
+async fn blocking_pop_errors() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        client.run(&["BLPOP", "k", "-1"]).await,
+        error("ERR timeout is negative")
+    );
+    assert_eq!(
+        client.run(&["BRPOP", "k", "soon"]).await,
+        error("ERR timeout is not a float or out of range")
+    );
+    assert_eq!(
+        client.run(&["BLPOP", "k"]).await,
+        error("ERR wrong number of arguments for 'blpop' command")
+    );
+    client.run(&["SET", "string", "v"]).await;
+    assert_eq!(
+        client.run(&["BLPOP", "string", "0"]).await,
+        error("WRONGTYPE Operation against a key holding the wrong kind of value")
+    );
+}
+
+#[tokio::test]
+async fn timeouts_past_what_a_clock_holds_are_errors() {
+    let mut client = Client::connect(start().await).await;
+    for timeout in ["1e20", "1.7976931348623157e308"] {
+        assert_eq!(
+            client.run(&["BLPOP", "k", timeout]).await,
+            error("ERR timeout is out of range")
+        );
+    }
+    // the timeout is checked before anything is popped, and the connection
+    // goes on serving
+    assert_eq!(client.run(&["RPUSH", "k", "v"]).await, int(1));
+    assert_eq!(
+        client.run(&["BRPOP", "k", "1e20"]).await,
+        error("ERR timeout is out of range")
+    );
+    assert_eq!(client.run(&["BRPOP", "k", "1"]).await, array(&["k", "v"]));
+    assert_eq!(client.run(&["PING"]).await, simple("PONG"));
+}
+
+#[tokio::test]
+async fn a_sorted_set_already_there_is_popped_right_away() {
+    let mut client = Client::connect(start().await).await;
+    client
+        .run(&["ZADD", "b", "1", "low", "2", "mid", "3", "high"])
+        .await;
+    client.run(&["ZADD", "c", "0", "other"]).await;
+    assert_eq!(
+        client.run(&["BZPOPMIN", "a", "b", "c", "0"]).await,
+        array(&["b", "low", "1"])
+    );
+    assert_eq!(
+        client.run(&["BZPOPMAX", "a", "b", "c", "0"]).await,
+        array(&["b", "high", "3"])
+    );
+    assert_eq!(
+        client.run(&["BZPOPMAX", "b", "0"]).await,
+        array(&["b", "mid", "2"])
+    );
+    assert_eq!(client.run(&["EXISTS", "b"]).await, int(0));
+    let started = Instant::now();
+    assert_eq!(
+        client.run(&["BZPOPMIN", "b", "0.1"]).await,
+        RedirsValue::Array(None)
+    );
+    assert!(started.elapsed() >= Duration::from_millis(100));
+}
+
+#[tokio::test]
+async fn a_zadd_wakes_the_blocked_connection() {
+    let addr = start().await;
+    let mut blocked = Client::connect(addr).await;
+    let mut adder = Client::connect(addr).await;
+    let waiting = tokio::spawn(async move {
+        let reply = blocked.run(&["BZPOPMAX", "other", "jobs", "0"]).await;
+        (reply, blocked)
+    });
+    sleep(SETTLE).await;
+    assert_eq!(
+        adder
+            .run(&["ZADD", "jobs", "1", "later", "9", "urgent"])
+            .await,
+        int(2)
+    );
+    let (reply, mut blocked) = waiting.await.unwrap();
+    assert_eq!(reply, array(&["jobs", "urgent", "9"]));
+    assert_eq!(adder.run(&["ZCARD", "jobs"]).await, int(1));
+    // over RESP3 the score is a double
+    blocked.run(&["HELLO", "3"]).await;
+    let waiting = tokio::spawn(async move { blocked.run(&["BZPOPMIN", "next", "0"]).await });
+    sleep(SETTLE).await;
+    adder.run(&["ZINCRBY", "next", "2.5", "job"]).await;
+    assert_eq!(
+        waiting.await.unwrap(),
+        RedirsValue::Array(Some(vec![
+            bulk("next"),
+            bulk("job"),
+            RedirsValue::Double(2.5),
+        ]))
+    );
+    assert_eq!(adder.run(&["EXISTS", "next"]).await, int(0));
+}
+
+#[tokio::test]
+async fn list_and_sorted_set_waiters_on_a_key_take_only_their_type() {
+    let addr = start().await;
+    let mut list_waiter = Client::connect(addr).await;
+    let list_waiting = tokio::spawn(async move { list_waiter.run(&["BLPOP", "k", "0"]).await });
+    sleep(SETTLE).await;
+    let mut zset_waiter = Client::connect(addr).await;
+    let zset_waiting = tokio::spawn(async move { zset_waiter.run(&["BZPOPMIN", "k", "0"]).await });
+    sleep(SETTLE).await;
+    let mut client = Client::connect(addr).await;
+    client.run(&["ZADD", "k", "1", "m"]).await;
+    assert_eq!(zset_waiting.await.unwrap(), array(&["k", "m", "1"]));
+    client.run(&["RPUSH", "k", "v"]).await;
+    assert_eq!(list_waiting.await.unwrap(), array(&["k", "v"]));
+}
+
+#[tokio::test]
+async fn a_connection_closed_while_blocked_on_a_sorted_set_takes_nothing() {
+    let addr = start().await;
+    let mut leaving = Client::connect(addr).await;
+    leaving
+        .send(b"*3\r\n$8\r\nBZPOPMIN\r\n$1\r\nk\r\n$1\r\n0\r\n")
+        .await;
+    sleep(SETTLE).await;
+    drop(leaving);
+    sleep(SETTLE).await;
+    let mut client = Client::connect(addr).await;
+    client.run(&["ZADD", "k", "1", "m"]).await;
+    assert_eq!(client.run(&["ZCARD", "k"]).await, int(1));
+    assert_eq!(
+        client.run(&["BZPOPMIN", "k", "-1"]).await,
+        error("ERR timeout is negative")
+    );
+    client.run(&["SET", "string", "v"]).await;
+    assert_eq!(
+        client.run(&["BZPOPMAX", "string", "0"]).await,
+        error("WRONGTYPE Operation against a key holding the wrong kind of value")
+    );
+}
+
+#[tokio::test]
+async fn sorted_set_timeouts_past_what_a_clock_holds_are_errors() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["ZADD", "k", "1", "m"]).await;
+    for command in ["BZPOPMIN", "BZPOPMAX"] {
+        assert_eq!(
+            client.run(&[command, "k", "1e20"]).await,
+            error("ERR timeout is out of range")
+        );
+    }
+    assert_eq!(client.run(&["ZCARD", "k"]).await, int(1));
+    assert_eq!(
+        client.run(&["BZPOPMIN", "k", "1"]).await,
+        array(&["k", "m", "1"])
+    );
+}
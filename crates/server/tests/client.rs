@@ -0,0 +1,199 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{bulk, error, int, nil, simple, start, Client};
+use protocol::RedirsValue;
+
+async fn id(client: &mut Client) -> i64 {
+    match client.run(&["CLIENT", "ID"]).await {
+        RedirsValue::Integer(id) => id,
+        other => panic!("expected an id, got {other:?}"),
+    }
+}
+
+// the lines of CLIENT LIST, each as its fields
+async fn list(client: &mut Client) -> Vec<Vec<(String, String)>> {
+    let RedirsValue::BulkString(Some(lines)) = client.run(&["CLIENT", "LIST"]).await else {
+        panic!("expected the client list");
+    };
+    String::from_utf8(lines.to_vec())
+        .unwrap()
+        .lines()
+        .map(|line| {
+            line.split(' ')
+                .map(|field| {
+                    let (name, value) = field.split_once('=').unwrap();
+                    (name.to_owned(), value.to_owned())
+                })
+                .collect()
+        })
+        .collect()
+}
+
+fn field<'a>(line: &'a [(String, String)], name: &str) -> &'a str {
+    &line.iter().find(|(field, _)| field == name).unwrap().1
+}
+
+async fn line_of(client: &mut Client, id: i64) -> Option<Vec<(String, String)>> {
+    list(client)
+        .await
+        .into_iter()
+        .find(|line| field(line, "id") == id.to_string())
+}
+
+// the connection was closed on the server side
+async fn closed(client: &mut Client) {
+    let read = tokio::time::timeout(Duration::from_secs(1), client.stream.read_value()).await;
+    assert!(read.expect("the connection to close promptly").is_err());
+}
+
+#[tokio::test]
+async fn ids_and_names() {
+    let addr = start().await;
+    let mut client = Client::connect(addr).await;
+    let mut other = Client::connect(addr).await;
+    let (first, second) = (id(&mut client).await, id(&mut other).await);
+    assert!(second > first);
+    assert_eq!(client.run(&["CLIENT", "GETNAME"]).await, nil());
+    assert_eq!(
+        client.run(&["CLIENT", "SETNAME", "worker-1"]).await,
+        simple("OK")
+    );
+    assert_eq!(client.run(&["CLIENT", "GETNAME"]).await, bulk("worker-1"));
+    for name in ["with space", "new\nline"] {
+        assert_eq!(
+            client.run(&["CLIENT", "SETNAME", name]).await,
+            error("ERR Client names cannot contain spaces, newlines or special characters.")
+        );
+    }
+    assert_eq!(
+        client.run(&["HELLO", "2", "SETNAME", "a b"]).await,
+        error("ERR Client names cannot contain spaces, newlines or special characters.")
+    );
+    assert_eq!(client.run(&["CLIENT", "GETNAME"]).await, bulk("worker-1"));
+    // an empty name takes it away
+    assert_eq!(client.run(&["CLIENT", "SETNAME", ""]).await, simple("OK"));
+    assert_eq!(client.run(&["CLIENT", "GETNAME"]).await, nil());
+}
+
+#[tokio::test]
+async fn list_shows_every_connection() {
+    let addr = start().await;
+    let mut client = Client::connect(addr).await;
+    let mut other = Client::connect(addr).await;
+    other.run(&["CLIENT", "SETNAME", "other"]).await;
+    other.run(&["SELECT", "2"]).await;
+    let (mine, theirs) = (id(&mut client).await, id(&mut other).await);
+    let me = line_of(&mut client, mine).await.unwrap();
+    assert_eq!(field(&me, "name"), "");
+    assert_eq!(field(&me, "cmd"), "client");
+    assert_eq!(field(&me, "db"), "0");
+    assert_eq!(field(&me, "multi"), "-1");
+    let them = line_of(&mut client, theirs).await.unwrap();
+    assert_eq!(field(&them, "name"), "other");
+    assert_eq!(field(&them, "db"), "2");
+    assert_eq!(field(&them, "user"), "default");
+    assert!(field(&them, "addr").parse::<std::net::SocketAddr>().is_ok());
+    assert_eq!(field(&them, "laddr"), addr.to_string());
+    drop(other);
+    for _ in 0..100 {
+        if line_of(&mut client, theirs).await.is_none() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("the closed connection is still listed");
+}
+
+#[tokio::test]
+async fn kill_by_id() {
+    let addr = start().await;
+    let mut killer = Client::connect(addr).await;
+    let mut victim = Client::connect(addr).await;
+    let target = id(&mut victim).await;
+    // the victim sits idle in a read
+    assert_eq!(
+        killer
+            .run(&["CLIENT", "KILL", "ID", &target.to_string()])
+            .await,
+        int(1)
+    );
+    closed(&mut victim).await;
+    assert_eq!(
+        killer
+            .run(&["CLIENT", "KILL", "ID", &target.to_string()])
+            .await,
+        int(0)
+    );
+    assert_eq!(killer.run(&["PING"]).await, simple("PONG"));
+}
+
+#[tokio::test]
+async fn kill_by_addr() {
+    let addr = start().await;
+    let mut killer = Client::connect(addr).await;
+    let mut victim = Client::connect(addr).await;
+    let mut blocked = Client::connect(addr).await;
+    let target = id(&mut victim).await;
+    let victim_addr = field(&line_of(&mut killer, target).await.unwrap(), "addr").to_owned();
+    assert_eq!(
+        killer.run(&["CLIENT", "KILL", &victim_addr]).await,
+        simple("OK")
+    );
+    closed(&mut victim).await;
+    assert_eq!(
+        killer.run(&["CLIENT", "KILL", &victim_addr]).await,
+        error("ERR No such client")
+    );
+    // a connection blocked on a list goes as well
+    let target = id(&mut blocked).await;
+    let blocked_addr = field(&line_of(&mut killer, target).await.unwrap(), "addr").to_owned();
+    blocked
+        .send(b"*3\r\n$5\r\nBLPOP\r\n$1\r\nl\r\n$1\r\n0\r\n")
+        .await;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(
+        killer.run(&["CLIENT", "KILL", "ADDR", &blocked_addr]).await,
+        int(1)
+    );
+    closed(&mut blocked).await;
+}
+
+#[tokio::test]
+async fn kill_skips_the_caller() {
+    let mut client = Client::connect(start().await).await;
+    let me = id(&mut client).await.to_string();
+    assert_eq!(client.run(&["CLIENT", "KILL", "ID", &me]).await, int(0));
+    assert_eq!(
+        client
+            .run(&["CLIENT", "KILL", "ID", &me, "SKIPME", "no"])
+            .await,
+        int(1)
+    );
+    closed(&mut client).await;
+}
+
+#[tokio::test]
+async fn client_errors() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        client.run(&["CLIENT", "KILL", "ID", "0"]).await,
+        error("ERR client-id should be greater than 0")
+    );
+    for args in [
+        &["CLIENT", "KILL", "ID", "1", "SKIPME"][..],
+        &["CLIENT", "KILL", "FLAVOR", "x"],
+        &["CLIENT", "KILL", "SKIPME", "maybe"],
+    ] {
+        assert_eq!(client.run(args).await, error("ERR syntax error"));
+    }
+    assert_eq!(
+        client.run(&["CLIENT", "SETNAME"]).await,
+        error("ERR wrong number of arguments for 'client|setname' command")
+    );
+    assert_eq!(
+        client.run(&["CLIENT", "PAUSE", "10"]).await,
+        error("ERR unknown subcommand 'PAUSE'. Try CLIENT HELP.")
+    );
+}
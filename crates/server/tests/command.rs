@@ -0,0 +1,124 @@
+mod common;
+
+use common::{array, error, int, nil, start, Client};
+use protocol::RedirsValue;
+
+fn entry(reply: &RedirsValue) -> &[RedirsValue] {
+    match reply {
+        RedirsValue::Array(Some(entry)) => entry,
+        other => panic!("expected a command entry, got {other:?}"),
+    }
+}
+
+fn statuses(items: &[&str]) -> RedirsValue {
+    RedirsValue::Array(Some(
+        items
+            .iter()
+            .map(|item| RedirsValue::SimpleString(item.to_string()))
+            .collect(),
+    ))
+}
+
+#[tokio::test]
+async fn info_describes_the_table() {
+    let mut client = Client::connect(start().await).await;
+    let reply = client
+        .run(&["COMMAND", "INFO", "get", "MSET", "nosuch"])
+        .await;
+    let reply = entry(&reply);
+    assert_eq!(reply.len(), 3);
+    let get = entry(&reply[0]);
+    assert_eq!(get[0], RedirsValue::from("get"));
+    assert_eq!(get[1], int(2));
+    assert_eq!(get[2], statuses(&["readonly", "fast"]));
+    assert_eq!(&get[3..6], &[int(1), int(1), int(1)]);
+    assert_eq!(get[6], statuses(&["@read", "@string", "@fast"]));
+    let mset = entry(&reply[1]);
+    assert_eq!(mset[1], int(-3));
+    assert_eq!(&mset[3..6], &[int(1), int(-1), int(2)]);
+    assert_eq!(reply[2], nil());
+    // the keys found by a count are not a range
+    let eval = client.run(&["COMMAND", "INFO", "eval"]).await;
+    let eval = entry(&entry(&eval)[0]).to_vec();
+    assert!(entry(&eval[2]).contains(&RedirsValue::SimpleString("movablekeys".to_owned())));
+    assert_eq!(&eval[3..6], &[int(0), int(0), int(0)]);
+}
+
+#[tokio::test]
+async fn listing_and_count() {
+    let mut client = Client::connect(start().await).await;
+    let RedirsValue::Integer(count) = client.run(&["COMMAND", "COUNT"]).await else {
+        panic!("expected a count");
+    };
+    let all = client.run(&["COMMAND"]).await;
+    assert_eq!(entry(&all).len() as i64, count);
+    let names: Vec<_> = entry(&all)
+        .iter()
+        .map(|command| entry(command)[0].clone())
+        .collect();
+    for name in ["get", "set", "command", "zadd", "xread"] {
+        assert!(names.contains(&RedirsValue::from(name)), "{name}");
+    }
+    assert_eq!(
+        entry(&client.run(&["COMMAND", "INFO"]).await).len() as i64,
+        count
+    );
+}
+
+#[tokio::test]
+async fn getkeys() {
+    let mut client = Client::connect(start().await).await;
+    let cases: &[(&[&str], &[&str])] = &[
+        (&["MSET", "a", "1", "b", "2", "c", "3"], &["a", "b", "c"]),
+        (&["GETRANGE", "k", "0", "-1"], &["k"]),
+        (&["DEL", "x", "y"], &["x", "y"]),
+        (&["EVAL", "return 1", "2", "k1", "k2", "arg"], &["k1", "k2"]),
+        (
+            &["XREAD", "COUNT", "2", "STREAMS", "s1", "s2", "0", "0"],
+            &["s1", "s2"],
+        ),
+        (&["MEMORY", "USAGE", "k"], &["k"]),
+    ];
+    for (invocation, keys) in cases {
+        let mut command = vec!["COMMAND", "GETKEYS"];
+        command.extend_from_slice(invocation);
+        assert_eq!(client.run(&command).await, array(keys), "{invocation:?}");
+    }
+    assert_eq!(
+        client.run(&["COMMAND", "GETKEYS", "nosuch", "k"]).await,
+        error("ERR Invalid command specified")
+    );
+    assert_eq!(
+        client.run(&["COMMAND", "GETKEYS", "GETRANGE", "k"]).await,
+        error("ERR Invalid number of arguments specified for command")
+    );
+    assert_eq!(
+        client.run(&["COMMAND", "GETKEYS", "PING"]).await,
+        error("ERR The command has no key arguments")
+    );
+    assert_eq!(
+        client.run(&["COMMAND", "GETKEYS"]).await,
+        error("ERR wrong number of arguments for 'command|getkeys' command")
+    );
+    assert_eq!(
+        client.run(&["COMMAND", "NOPE"]).await,
+        error("ERR unknown subcommand 'nope'. Try COMMAND HELP.")
+    );
+}
+
+#[tokio::test]
+async fn arity_comes_from_the_table() {
+    let mut client = Client::connect(start().await).await;
+    for (command, name) in [
+        (&["GET"][..], "get"),
+        (&["MSET", "a"], "mset"),
+        (&["ECHO"], "echo"),
+    ] {
+        assert_eq!(
+            client.run(command).await,
+            error(&format!(
+                "ERR wrong number of arguments for '{name}' command"
+            ))
+        );
+    }
+}
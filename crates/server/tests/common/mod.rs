@@ -0,0 +1,85 @@
+// helpers shared by the integration tests, each test binary uses a part of them
+#![allow(dead_code)]
+
+use std::net::SocketAddr;
+
+use protocol::{RedirsValue, RespReader};
+use server::Db;
+use tokio::{
+    io::AsyncWriteExt,
+    net::{TcpListener, TcpStream},
+};
+
+// a server on an ephemeral port, serving until the test ends
+pub async fn start() -> SocketAddr {
+    start_with(Db::new()).await
+}
+
+// the same against `db`, which the test can keep a clone of
+pub async fn start_with(db: Db) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(server::run(listener, db));
+    addr
+}
+
+pub struct Client {
+    pub stream: RespReader<TcpStream>,
+}
+
+impl Client {
+    pub async fn connect(addr: SocketAddr) -> Self {
+        Self {
+            stream: RespReader::new(TcpStream::connect(addr).await.unwrap()),
+        }
+    }
+    pub async fn send(&mut self, raw: &[u8]) {
+        self.stream.get_mut().write_all(raw).await.unwrap();
+    }
+    pub async fn reply(&mut self) -> RedirsValue {
+        self.stream.read_value().await.unwrap()
+    }
+    pub async fn request(&mut self, raw: &[u8]) -> RedirsValue {
+        self.send(raw).await;
+        self.reply().await
+    }
+    // sends the arguments as an array of bulk strings
+    pub async fn run(&mut self, args: &[&str]) -> RedirsValue {
+        let args: Vec<_> = args.iter().map(|arg| arg.as_bytes()).collect();
+        self.run_bytes(&args).await
+    }
+    // the same for arguments that are not text
+    pub async fn run_bytes(&mut self, args: &[&[u8]]) -> RedirsValue {
+        let request = RedirsValue::Array(Some(
+            args.iter()
+                .map(|arg| RedirsValue::from(arg.to_vec()))
+                .collect(),
+        ));
+        self.request(&request.to_resp_bytes()).await
+    }
+}
+
+pub fn simple(s: &str) -> RedirsValue {
+    RedirsValue::SimpleString(s.to_owned())
+}
+
+pub fn error(s: &str) -> RedirsValue {
+    RedirsValue::SimpleError(s.to_owned())
+}
+
+pub fn int(n: i64) -> RedirsValue {
+    RedirsValue::Integer(n)
+}
+
+pub fn bulk(s: &str) -> RedirsValue {
+    RedirsValue::from(s)
+}
+
+pub fn nil() -> RedirsValue {
+    RedirsValue::BulkString(None)
+}
+
+// an array of bulk strings
+pub fn array(items: &[&str]) -> RedirsValue {
+    RedirsValue::Array(Some(items.iter().map(|item| bulk(item)).collect()))
+}
@@ -0,0 +1,278 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{array, bulk, error, int, nil, simple, start, start_with, Client};
+use protocol::RedirsValue;
+use server::{reap_idle, Db};
+
+// the connection was closed on the server side, within `within`
+async fn closed(client: &mut Client, within: Duration) {
+    let read = tokio::time::timeout(within, client.stream.read_value()).await;
+    assert!(read.expect("the connection to close in time").is_err());
+}
+
+#[tokio::test]
+async fn get_matches_patterns() {
+    let addr = start().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(
+        client.run(&["CONFIG", "GET", "maxmemory"]).await,
+        array(&["maxmemory", "0"])
+    );
+    assert_eq!(
+        client.run(&["CONFIG", "GET", "MAXMEMORY-*"]).await,
+        array(&["maxmemory-policy", "noeviction", "maxmemory-samples", "5"])
+    );
+    // a parameter two patterns match is there once
+    assert_eq!(
+        client
+            .run(&["CONFIG", "GET", "timeout", "time*", "nothing"])
+            .await,
+        array(&["timeout", "0"])
+    );
+    assert_eq!(client.run(&["CONFIG", "GET", "nothing"]).await, array(&[]));
+    client.run(&["HELLO", "3"]).await;
+    assert_eq!(
+        client.run(&["CONFIG", "GET", "databases"]).await,
+        RedirsValue::Map(vec![(bulk("databases"), bulk("16"))].into())
+    );
+}
+
+#[tokio::test]
+async fn set_checks_every_value_first() {
+    let addr = start().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(
+        client.run(&["CONFIG", "SET", "nothing", "1"]).await,
+        error("ERR Unknown option or number of arguments for CONFIG SET - 'nothing'")
+    );
+    assert_eq!(
+        client
+            .run(&["CONFIG", "SET", "timeout", "10", "maxmemory", "1zb"])
+            .await,
+        error(
+            "ERR CONFIG SET failed (possibly related to argument 'maxmemory') - argument must be a memory value"
+        )
+    );
+    assert_eq!(
+        client.run(&["CONFIG", "GET", "timeout"]).await,
+        array(&["timeout", "0"])
+    );
+    assert_eq!(
+        client.run(&["CONFIG", "SET", "maxmemory-samples", "65"]).await,
+        error(
+            "ERR CONFIG SET failed (possibly related to argument 'maxmemory-samples') - argument must be between 1 and 64 inclusive"
+        )
+    );
+    assert_eq!(
+        client.run(&["CONFIG", "SET", "maxmemory-policy", "oldest"]).await,
+        error(
+            "ERR CONFIG SET failed (possibly related to argument 'maxmemory-policy') - argument(s) must be one of the following: volatile-lru, allkeys-lru, volatile-lfu, allkeys-lfu, volatile-random, allkeys-random, volatile-ttl, noeviction"
+        )
+    );
+    assert_eq!(
+        client.run(&["CONFIG", "SET", "databases", "4"]).await,
+        error(
+            "ERR CONFIG SET failed (possibly related to argument 'databases') - can't set immutable config"
+        )
+    );
+    assert_eq!(
+        client
+            .run(&["CONFIG", "SET", "timeout", "1", "TIMEOUT", "2"])
+            .await,
+        error(
+            "ERR CONFIG SET failed (possibly related to argument 'TIMEOUT') - duplicate parameter"
+        )
+    );
+    assert_eq!(
+        client.run(&["CONFIG", "SET", "timeout"]).await,
+        error("ERR wrong number of arguments for 'config|set' command")
+    );
+    assert_eq!(
+        client
+            .run(&[
+                "CONFIG",
+                "SET",
+                "maxmemory",
+                "2mb",
+                "maxmemory-policy",
+                "ALLKEYS-LRU"
+            ])
+            .await,
+        simple("OK")
+    );
+    assert_eq!(
+        client.run(&["CONFIG", "GET", "maxmemory*"]).await,
+        array(&[
+            "maxmemory",
+            "2097152",
+            "maxmemory-policy",
+            "allkeys-lru",
+            "maxmemory-samples",
+            "5"
+        ])
+    );
+    client.run(&["CONFIG", "SET", "maxmemory", "3k"]).await;
+    assert_eq!(
+        client.run(&["CONFIG", "GET", "maxmemory"]).await,
+        array(&["maxmemory", "3000"])
+    );
+}
+
+#[tokio::test]
+async fn requirepass_applies_to_new_connections() {
+    let addr = start().await;
+    let mut admin = Client::connect(addr).await;
+    assert_eq!(
+        admin.run(&["CONFIG", "SET", "requirepass", "secret"]).await,
+        simple("OK")
+    );
+    assert_eq!(
+        admin.run(&["CONFIG", "GET", "requirepass"]).await,
+        array(&["requirepass", "secret"])
+    );
+    // already authenticated
+    assert_eq!(admin.run(&["PING"]).await, simple("PONG"));
+    let mut other = Client::connect(addr).await;
+    assert_eq!(
+        other.run(&["GET", "foo"]).await,
+        error("NOAUTH Authentication required.")
+    );
+    assert_eq!(other.run(&["AUTH", "secret"]).await, simple("OK"));
+    assert_eq!(other.run(&["GET", "foo"]).await, nil());
+    admin.run(&["CONFIG", "SET", "requirepass", ""]).await;
+    let mut open = Client::connect(addr).await;
+    assert_eq!(open.run(&["GET", "foo"]).await, nil());
+}
+
+#[tokio::test]
+async fn keyspace_events_start_on_a_subscribed_connection() {
+    let addr = start().await;
+    let mut subscriber = Client::connect(addr).await;
+    let mut writer = Client::connect(addr).await;
+    subscriber.run(&["SUBSCRIBE", "__keyevent@0__:set"]).await;
+    writer.run(&["SET", "before", "1"]).await;
+    assert_eq!(
+        writer
+            .run(&["CONFIG", "SET", "notify-keyspace-events", "Eq"])
+            .await,
+        error(
+            "ERR CONFIG SET failed (possibly related to argument 'notify-keyspace-events') - Invalid event class character. Use 'Ag$lshzxeKEtmdn'."
+        )
+    );
+    writer
+        .run(&["CONFIG", "SET", "notify-keyspace-events", "E$"])
+        .await;
+    assert_eq!(
+        writer
+            .run(&["CONFIG", "GET", "notify-keyspace-events"])
+            .await,
+        array(&["notify-keyspace-events", "$E"])
+    );
+    writer.run(&["SET", "after", "1"]).await;
+    assert_eq!(
+        subscriber.reply().await,
+        array(&["message", "__keyevent@0__:set", "after"])
+    );
+}
+
+#[tokio::test]
+async fn maxmemory_refuses_or_evicts() {
+    let db = Db::new();
+    let addr = start_with(db.clone()).await;
+    let mut client = Client::connect(addr).await;
+    let value = "x".repeat(1000);
+    for index in 0..10 {
+        client.run(&["SET", &format!("key:{index}"), &value]).await;
+    }
+    let used = db.used_memory();
+    client
+        .run(&["CONFIG", "SET", "maxmemory", &(used - 100).to_string()])
+        .await;
+    assert_eq!(
+        client.run(&["SET", "more", &value]).await,
+        error("OOM command not allowed when used memory > 'maxmemory'.")
+    );
+    // reads and deletes still go through
+    assert_eq!(client.run(&["GET", "key:0"]).await, bulk(&value));
+    assert_eq!(client.run(&["DBSIZE"]).await, int(10));
+    client
+        .run(&["CONFIG", "SET", "maxmemory-policy", "allkeys-random"])
+        .await;
+    assert_eq!(client.run(&["SET", "more", &value]).await, simple("OK"));
+    let RedirsValue::Integer(keys) = client.run(&["DBSIZE"]).await else {
+        panic!("DBSIZE is an integer");
+    };
+    assert!(keys < 11, "nothing was evicted");
+    let info = client.run(&["INFO", "stats"]).await;
+    let RedirsValue::BulkString(Some(text)) = info else {
+        panic!("INFO is a bulk string, got {info:?}");
+    };
+    assert!(!String::from_utf8_lossy(&text).contains("evicted_keys:0\r\n"));
+}
+
+#[tokio::test]
+async fn volatile_policies_only_evict_keys_with_a_deadline() {
+    let db = Db::new();
+    let addr = start_with(db.clone()).await;
+    let mut client = Client::connect(addr).await;
+    let value = "x".repeat(1000);
+    client.run(&["SET", "kept", &value]).await;
+    client.run(&["SET", "expiring", &value, "EX", "100"]).await;
+    let used = db.used_memory();
+    client
+        .run(&[
+            "CONFIG",
+            "SET",
+            "maxmemory",
+            &(used - 100).to_string(),
+            "maxmemory-policy",
+            "volatile-ttl",
+        ])
+        .await;
+    assert_eq!(client.run(&["SET", "more", "1"]).await, simple("OK"));
+    assert_eq!(client.run(&["EXISTS", "expiring"]).await, int(0));
+    // the one left has no deadline to go by
+    client.run(&["CONFIG", "SET", "maxmemory", "1"]).await;
+    assert_eq!(
+        client.run(&["SET", "more", "2"]).await,
+        error("OOM command not allowed when used memory > 'maxmemory'.")
+    );
+    assert_eq!(client.run(&["GET", "kept"]).await, bulk(&value));
+}
+
+#[tokio::test]
+async fn lfu_policy_shows_access_frequencies() {
+    let addr = start().await;
+    let mut client = Client::connect(addr).await;
+    client.run(&["SET", "foo", "bar"]).await;
+    client
+        .run(&["CONFIG", "SET", "maxmemory-policy", "allkeys-lfu"])
+        .await;
+    // a new key starts at 5, the first access always counts
+    assert_eq!(client.run(&["OBJECT", "FREQ", "foo"]).await, int(5));
+    client.run(&["GET", "foo"]).await;
+    assert_eq!(client.run(&["OBJECT", "FREQ", "foo"]).await, int(6));
+    assert_eq!(client.run(&["OBJECT", "FREQ", "missing"]).await, nil());
+}
+
+#[tokio::test]
+async fn timeout_closes_idle_connections() {
+    let db = Db::new();
+    let addr = start_with(db.clone()).await;
+    let reaper = tokio::spawn(reap_idle(db));
+    let mut admin = Client::connect(addr).await;
+    let mut idle = Client::connect(addr).await;
+    let mut subscriber = Client::connect(addr).await;
+    idle.run(&["PING"]).await;
+    subscriber.run(&["SUBSCRIBE", "news"]).await;
+    assert_eq!(
+        admin.run(&["CONFIG", "SET", "timeout", "1"]).await,
+        simple("OK")
+    );
+    closed(&mut idle, Duration::from_secs(3)).await;
+    // subscribers wait for messages, not commands
+    assert_eq!(subscriber.run(&["PING"]).await, array(&["pong", ""]));
+    reaper.abort();
+}
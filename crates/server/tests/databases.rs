@@ -0,0 +1,362 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{array, bulk, error, int, nil, simple, start, start_with, Client};
+use protocol::RedirsValue;
+use server::{active_expiry, Db, KeyspaceEvents, ManualClock, Value};
+
+#[tokio::test]
+async fn databases_are_isolated() {
+    let addr = start().await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.run(&["SELECT", "1"]).await, simple("OK"));
+    assert_eq!(client.run(&["SET", "k", "one"]).await, simple("OK"));
+    assert_eq!(client.run(&["SELECT", "0"]).await, simple("OK"));
+    assert_eq!(client.run(&["GET", "k"]).await, nil());
+    assert_eq!(client.run(&["SET", "k", "zero"]).await, simple("OK"));
+    // another connection starts in database 0
+    let mut other = Client::connect(addr).await;
+    assert_eq!(other.run(&["GET", "k"]).await, bulk("zero"));
+    assert_eq!(other.run(&["SELECT", "1"]).await, simple("OK"));
+    assert_eq!(other.run(&["GET", "k"]).await, bulk("one"));
+    assert_eq!(other.run(&["KEYS", "*"]).await, array(&["k"]));
+}
+
+#[tokio::test]
+async fn select_checks_the_index() {
+    let mut client = Client::connect(start_with(Db::with_databases(4)).await).await;
+    assert_eq!(client.run(&["SELECT", "3"]).await, simple("OK"));
+    for index in ["4", "-1"] {
+        assert_eq!(
+            client.run(&["SELECT", index]).await,
+            error("ERR DB index is out of range")
+        );
+    }
+    assert_eq!(
+        client.run(&["SELECT", "x"]).await,
+        error("ERR value is not an integer or out of range")
+    );
+    // a failed SELECT leaves the database as it was
+    client.run(&["SET", "k", "v"]).await;
+    assert_eq!(client.run(&["SELECT", "3"]).await, simple("OK"));
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("v"));
+}
+
+#[tokio::test]
+async fn select_works_with_resp3_and_in_transactions() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["HELLO", "3"]).await;
+    assert_eq!(client.run(&["SELECT", "2"]).await, simple("OK"));
+    client.run(&["SET", "k", "two"]).await;
+    assert_eq!(client.run(&["MULTI"]).await, simple("OK"));
+    for request in [
+        &["SELECT", "5"][..],
+        &["SET", "k", "five"],
+        &["SELECT", "2"],
+        &["GET", "k"],
+    ] {
+        assert_eq!(client.run(request).await, simple("QUEUED"));
+    }
+    assert_eq!(
+        client.run(&["EXEC"]).await,
+        RedirsValue::Array(Some(vec![
+            simple("OK"),
+            simple("OK"),
+            simple("OK"),
+            bulk("two")
+        ]))
+    );
+    client.run(&["SELECT", "5"]).await;
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("five"));
+}
+
+#[tokio::test]
+async fn swapdb_exchanges_what_two_databases_hold() {
+    let addr = start().await;
+    let mut zero = Client::connect(addr).await;
+    let mut one = Client::connect(addr).await;
+    one.run(&["SELECT", "1"]).await;
+    zero.run(&["SET", "a", "0"]).await;
+    zero.run(&["PEXPIRE", "a", "100000"]).await;
+    one.run(&["SET", "b", "1"]).await;
+    zero.run(&["WATCH", "a"]).await;
+    assert_eq!(one.run(&["SWAPDB", "0", "1"]).await, simple("OK"));
+    assert_eq!(zero.run(&["GET", "a"]).await, nil());
+    assert_eq!(zero.run(&["GET", "b"]).await, bulk("1"));
+    assert_eq!(one.run(&["GET", "a"]).await, bulk("0"));
+    assert!(matches!(one.run(&["PTTL", "a"]).await, RedirsValue::Integer(ttl) if ttl > 0));
+    // the watched key went with its database
+    zero.run(&["MULTI"]).await;
+    zero.run(&["GET", "a"]).await;
+    assert_eq!(zero.run(&["EXEC"]).await, RedirsValue::Array(None));
+    assert_eq!(one.run(&["SWAPDB", "1", "1"]).await, simple("OK"));
+    assert_eq!(
+        one.run(&["SWAPDB", "x", "1"]).await,
+        error("ERR invalid first DB index")
+    );
+    assert_eq!(
+        one.run(&["SWAPDB", "1", "x"]).await,
+        error("ERR invalid second DB index")
+    );
+    assert_eq!(
+        one.run(&["SWAPDB", "0", "16"]).await,
+        error("ERR DB index is out of range")
+    );
+}
+
+#[tokio::test]
+async fn swapdb_serves_blocked_clients_from_the_new_contents() {
+    let addr = start().await;
+    let mut blocked = Client::connect(addr).await;
+    let request = ["BLPOP", "list", "0"];
+    let mut client = Client::connect(addr).await;
+    client.run(&["SELECT", "1"]).await;
+    client.run(&["RPUSH", "list", "x"]).await;
+    let waiting = tokio::spawn(async move {
+        let reply = blocked.run(&request).await;
+        (blocked, reply)
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    client.run(&["SWAPDB", "0", "1"]).await;
+    let (_, reply) = tokio::time::timeout(Duration::from_secs(5), waiting)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(reply, array(&["list", "x"]));
+}
+
+#[tokio::test]
+async fn move_takes_a_key_to_another_database() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "k", "v"]).await;
+    client.run(&["EXPIRE", "k", "100"]).await;
+    assert_eq!(client.run(&["MOVE", "k", "3"]).await, int(1));
+    assert_eq!(client.run(&["EXISTS", "k"]).await, int(0));
+    assert_eq!(client.run(&["MOVE", "k", "3"]).await, int(0));
+    client.run(&["SET", "k", "again"]).await;
+    // not over a key the other database has
+    assert_eq!(client.run(&["MOVE", "k", "3"]).await, int(0));
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("again"));
+    assert_eq!(
+        client.run(&["MOVE", "k", "0"]).await,
+        error("ERR source and destination objects are the same")
+    );
+    assert_eq!(
+        client.run(&["MOVE", "k", "99"]).await,
+        error("ERR DB index is out of range")
+    );
+    client.run(&["SELECT", "3"]).await;
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("v"));
+    assert!(matches!(client.run(&["TTL", "k"]).await, RedirsValue::Integer(ttl) if ttl > 0));
+}
+
+#[tokio::test]
+async fn copy_takes_a_db_option() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["RPUSH", "l", "a", "b"]).await;
+    assert_eq!(client.run(&["COPY", "l", "l", "DB", "2"]).await, int(1));
+    assert_eq!(client.run(&["COPY", "l", "l", "DB", "2"]).await, int(0));
+    assert_eq!(
+        client.run(&["COPY", "l", "l", "DB", "2", "REPLACE"]).await,
+        int(1)
+    );
+    assert_eq!(
+        client.run(&["COPY", "l", "l"]).await,
+        error("ERR source and destination objects are the same")
+    );
+    client.run(&["SELECT", "2"]).await;
+    assert_eq!(
+        client.run(&["LRANGE", "l", "0", "-1"]).await,
+        array(&["a", "b"])
+    );
+}
+
+#[tokio::test]
+async fn notifications_name_the_database() {
+    let db = Db::new();
+    db.set_keyspace_events(KeyspaceEvents::parse(b"KEA").unwrap());
+    let addr = start_with(db).await;
+    let mut subscriber = Client::connect(addr).await;
+    subscriber.run(&["PSUBSCRIBE", "__key*@*__:*"]).await;
+    let mut client = Client::connect(addr).await;
+    client.run(&["SELECT", "7"]).await;
+    client.run(&["SET", "k", "v"]).await;
+    let pmessage =
+        |channel: &str, message: &str| array(&["pmessage", "__key*@*__:*", channel, message]);
+    assert_eq!(
+        subscriber.reply().await,
+        pmessage("__keyspace@7__:k", "set")
+    );
+    assert_eq!(
+        subscriber.reply().await,
+        pmessage("__keyevent@7__:set", "k")
+    );
+    client.run(&["MOVE", "k", "2"]).await;
+    assert_eq!(
+        subscriber.reply().await,
+        pmessage("__keyspace@7__:k", "move_from")
+    );
+    assert_eq!(
+        subscriber.reply().await,
+        pmessage("__keyevent@7__:move_from", "k")
+    );
+    assert_eq!(
+        subscriber.reply().await,
+        pmessage("__keyspace@2__:k", "move_to")
+    );
+    assert_eq!(
+        subscriber.reply().await,
+        pmessage("__keyevent@2__:move_to", "k")
+    );
+}
+
+#[tokio::test]
+async fn active_expiry_covers_every_database() {
+    let clock = ManualClock::new(1000);
+    let db = Db::with_clock(clock.clone());
+    let other = db.select(9);
+    {
+        let mut keyspace = other.lock();
+        keyspace.set(b"k".to_vec(), Value::String(b"v".to_vec()));
+        keyspace.expire_at(b"k", 1001);
+    }
+    let task = tokio::spawn(active_expiry(db.clone(), Duration::from_millis(5), 20));
+    clock.advance(Duration::from_millis(2));
+    let emptied = tokio::time::timeout(Duration::from_secs(5), async {
+        while !other.lock().is_empty() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await;
+    task.abort();
+    assert!(emptied.is_ok());
+    assert_eq!(other.lock().expired_keys(), 1);
+}
+
+#[tokio::test]
+async fn flushdb_empties_the_selected_database_only() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "zero", "0"]).await;
+    client.run(&["SELECT", "1"]).await;
+    client.run(&["SET", "one", "1"]).await;
+    client.run(&["EXPIRE", "one", "100"]).await;
+    assert_eq!(client.run(&["FLUSHDB"]).await, simple("OK"));
+    assert_eq!(client.run(&["KEYS", "*"]).await, array(&[]));
+    client.run(&["SELECT", "0"]).await;
+    assert_eq!(client.run(&["GET", "zero"]).await, bulk("0"));
+    for mode in ["SYNC", "async"] {
+        client.run(&["SET", "zero", "0"]).await;
+        assert_eq!(client.run(&["FLUSHDB", mode]).await, simple("OK"));
+        assert_eq!(client.run(&["EXISTS", "zero"]).await, int(0));
+    }
+    assert_eq!(
+        client.run(&["FLUSHDB", "LATER"]).await,
+        error("ERR syntax error")
+    );
+    assert_eq!(
+        client.run(&["FLUSHALL", "SYNC", "ASYNC"]).await,
+        error("ERR syntax error")
+    );
+}
+
+#[tokio::test]
+async fn flushall_empties_every_database() {
+    let mut client = Client::connect(start().await).await;
+    for index in ["0", "3", "15"] {
+        client.run(&["SELECT", index]).await;
+        client.run(&["SET", "k", index]).await;
+    }
+    assert_eq!(client.run(&["FLUSHALL", "ASYNC"]).await, simple("OK"));
+    for index in ["0", "3", "15"] {
+        client.run(&["SELECT", index]).await;
+        assert_eq!(client.run(&["GET", "k"]).await, nil());
+    }
+}
+
+#[tokio::test]
+async fn flushing_a_watched_key_aborts_the_transaction() {
+    let addr = start().await;
+    let mut client = Client::connect(addr).await;
+    let mut other = Client::connect(addr).await;
+    client.run(&["SET", "k", "v"]).await;
+    client.run(&["WATCH", "k"]).await;
+    other.run(&["FLUSHALL"]).await;
+    client.run(&["MULTI"]).await;
+    client.run(&["SET", "k", "w"]).await;
+    assert_eq!(client.run(&["EXEC"]).await, RedirsValue::Array(None));
+    // a key that was not there is not written to by a flush
+    client.run(&["WATCH", "missing"]).await;
+    other.run(&["FLUSHDB"]).await;
+    client.run(&["MULTI"]).await;
+    client.run(&["SET", "k", "w"]).await;
+    assert_eq!(
+        client.run(&["EXEC"]).await,
+        RedirsValue::Array(Some(vec![simple("OK")]))
+    );
+}
+
+#[tokio::test]
+async fn flushes_notify_no_key_events() {
+    let db = Db::new();
+    db.set_keyspace_events(KeyspaceEvents::parse(b"KEA").unwrap());
+    let addr = start_with(db).await;
+    let mut subscriber = Client::connect(addr).await;
+    subscriber
+        .run(&["SUBSCRIBE", "__keyevent@0__:set", "__keyevent@0__:del"])
+        .await;
+    subscriber.reply().await;
+    let mut client = Client::connect(addr).await;
+    client.run(&["SET", "a", "1"]).await;
+    assert_eq!(
+        subscriber.reply().await,
+        array(&["message", "__keyevent@0__:set", "a"])
+    );
+    client.run(&["FLUSHDB"]).await;
+    client.run(&["SET", "b", "1"]).await;
+    assert_eq!(
+        subscriber.reply().await,
+        array(&["message", "__keyevent@0__:set", "b"])
+    );
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn an_async_flush_does_not_stall_other_clients() {
+    let db = Db::new();
+    {
+        let mut keyspace = db.lock();
+        for i in 0..500_000 {
+            let key = format!("key:{i}").into_bytes();
+            keyspace.set(key, Value::List((0..4).map(|n| vec![n; 16]).collect()));
+        }
+    }
+    let addr = start_with(db.clone()).await;
+    let mut flusher = Client::connect(addr).await;
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.run(&["PING"]).await, simple("PONG"));
+    let started = std::time::Instant::now();
+    assert_eq!(flusher.run(&["FLUSHALL", "ASYNC"]).await, simple("OK"));
+    assert_eq!(client.run(&["SET", "fresh", "v"]).await, simple("OK"));
+    assert_eq!(client.run(&["GET", "fresh"]).await, bulk("v"));
+    let elapsed = started.elapsed();
+    assert_eq!(db.lock().len(), 1);
+    // dropping half a million lists takes a good deal longer than this
+    assert!(elapsed < Duration::from_millis(50), "took {elapsed:?}");
+}
+
+#[tokio::test]
+async fn dbsize_counts_the_selected_database() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(client.run(&["DBSIZE"]).await, int(0));
+    client.run(&["MSET", "a", "1", "b", "2"]).await;
+    client.run(&["RPUSH", "l", "x"]).await;
+    assert_eq!(client.run(&["DBSIZE"]).await, int(3));
+    assert_eq!(client.run(&["SELECT", "1"]).await, simple("OK"));
+    assert_eq!(client.run(&["DBSIZE"]).await, int(0));
+    client.run(&["SET", "c", "3"]).await;
+    assert_eq!(client.run(&["DBSIZE"]).await, int(1));
+    assert_eq!(
+        client.run(&["DBSIZE", "x"]).await,
+        error("ERR wrong number of arguments for 'dbsize' command")
+    );
+}
@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+
+use protocol::RedirsValue;
+use server::{Db, Value, WrongType};
+
+fn list(items: &[&[u8]]) -> Value {
+    Value::List(items.iter().map(|item| item.to_vec()).collect())
+}
+
+#[test]
+fn typed_accessors_reject_other_types() {
+    let db = Db::new();
+    let mut keyspace = db.lock();
+    keyspace.set(b"list".to_vec(), list(&[b"a"]));
+    keyspace.set(b"string".to_vec(), Value::String(b"v".to_vec()));
+
+    assert_eq!(keyspace.get_string(b"list"), Err(WrongType));
+    assert_eq!(keyspace.get_string_mut(b"list"), Err(WrongType));
+    assert_eq!(keyspace.get_or_create_string(b"list"), Err(WrongType));
+    assert_eq!(keyspace.get_list(b"string"), Err(WrongType));
+    assert_eq!(keyspace.get_or_create_list(b"string"), Err(WrongType));
+    assert_eq!(keyspace.get_hash(b"list"), Err(WrongType));
+    assert_eq!(keyspace.get_or_create_set(b"string"), Err(WrongType));
+    assert_eq!(keyspace.get_sorted_set(b"list"), Err(WrongType));
+    assert_eq!(keyspace.get_or_create_stream(b"list"), Err(WrongType));
+
+    // a failed accessor leaves the key as it was
+    assert_eq!(keyspace.get(b"list"), Some(&list(&[b"a"])));
+    assert_eq!(keyspace.get_string(b"string"), Ok(Some(&b"v".to_vec())));
+    assert_eq!(keyspace.len(), 2);
+}
+
+#[test]
+fn wrong_type_reply() {
+    assert_eq!(
+        WrongType.to_client_error(),
+        RedirsValue::SimpleError(
+            "WRONGTYPE Operation against a key holding the wrong kind of value".to_owned()
+        )
+    );
+}
+
+#[test]
+fn missing_keys() {
+    let db = Db::new();
+    let mut keyspace = db.lock();
+    assert_eq!(keyspace.get_string(b"k"), Ok(None));
+    assert_eq!(keyspace.get_list_mut(b"k"), Ok(None));
+    assert!(!keyspace.exists(b"k"));
+    assert!(keyspace.is_empty());
+
+    keyspace
+        .get_or_create_list(b"k")
+        .unwrap()
+        .push_back(b"x".to_vec());
+    assert!(keyspace.exists(b"k"));
+    assert_eq!(
+        keyspace.get_list(b"k"),
+        Ok(Some(&VecDeque::from([b"x".to_vec()])))
+    );
+    assert_eq!(keyspace.remove(b"k"), Some(list(&[b"x"])));
+    assert_eq!(keyspace.remove(b"k"), None);
+    assert_eq!(keyspace.len(), 0);
+}
+
+#[test]
+fn set_replaces_any_type() {
+    let db = Db::new();
+    let mut keyspace = db.lock();
+    keyspace.set(b"k".to_vec(), list(&[b"a"]));
+    let old = keyspace.set(b"k".to_vec(), Value::String(b"v".to_vec()));
+    assert_eq!(old, Some(list(&[b"a"])));
+    assert_eq!(keyspace.get_list(b"k"), Err(WrongType));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn concurrent_tasks_share_the_keyspace() {
+    let db = Db::new();
+    let tasks: Vec<_> = (0..16)
+        .map(|task| {
+            let db = db.clone();
+            tokio::spawn(async move {
+                for i in 0..500 {
+                    {
+                        let mut keyspace = db.lock();
+                        keyspace
+                            .get_or_create_list(b"shared")
+                            .unwrap()
+                            .push_back(format!("{task}:{i}").into_bytes());
+                        keyspace.set(
+                            format!("own:{task}").into_bytes(),
+                            Value::String(i.to_string().into_bytes()),
+                        );
+                    }
+                    tokio::task::yield_now().await;
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    let mut keyspace = db.lock();
+    assert_eq!(
+        keyspace.get_list(b"shared").unwrap().unwrap().len(),
+        16 * 500
+    );
+    assert_eq!(keyspace.len(), 17);
+    for task in 0..16 {
+        let key = format!("own:{task}");
+        assert_eq!(
+            keyspace.get_string(key.as_bytes()),
+            Ok(Some(&b"499".to_vec()))
+        );
+    }
+}
@@ -0,0 +1,177 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{bulk, error, int, nil, simple, start_with, Client};
+use server::{Db, ManualClock};
+
+// unix time 1_700_000_000, in milliseconds
+const START: u64 = 1_700_000_000_000;
+
+async fn connect() -> (Client, ManualClock) {
+    let clock = ManualClock::new(START);
+    let addr = start_with(Db::with_clock(clock.clone())).await;
+    (Client::connect(addr).await, clock)
+}
+
+#[tokio::test]
+async fn ttl_of_missing_and_persistent_keys() {
+    let (mut client, _) = connect().await;
+    assert_eq!(client.run(&["TTL", "k"]).await, int(-2));
+    assert_eq!(client.run(&["PTTL", "k"]).await, int(-2));
+    client.run(&["SET", "k", "v"]).await;
+    assert_eq!(client.run(&["TTL", "k"]).await, int(-1));
+    assert_eq!(client.run(&["PTTL", "k"]).await, int(-1));
+}
+
+#[tokio::test]
+async fn expired_keys_behave_as_missing() {
+    let (mut client, clock) = connect().await;
+    client.run(&["SET", "k", "v", "PX", "1500"]).await;
+    assert_eq!(client.run(&["PTTL", "k"]).await, int(1500));
+    assert_eq!(client.run(&["TTL", "k"]).await, int(2));
+    clock.advance(Duration::from_millis(1500));
+    // still there at its deadline
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("v"));
+    assert_eq!(client.run(&["PTTL", "k"]).await, int(0));
+    clock.advance(Duration::from_millis(1));
+    assert_eq!(client.run(&["GET", "k"]).await, nil());
+    assert_eq!(client.run(&["TTL", "k"]).await, int(-2));
+    assert_eq!(client.run(&["DEL", "k"]).await, int(0));
+}
+
+#[tokio::test]
+async fn set_options_set_the_deadline() {
+    let (mut client, _) = connect().await;
+    client.run(&["SET", "ex", "v", "EX", "10"]).await;
+    assert_eq!(client.run(&["PTTL", "ex"]).await, int(10_000));
+    let at = (START / 1000 + 20).to_string();
+    client.run(&["SET", "exat", "v", "EXAT", &at]).await;
+    assert_eq!(client.run(&["TTL", "exat"]).await, int(20));
+    let at = (START + 30).to_string();
+    client.run(&["SET", "pxat", "v", "PXAT", &at]).await;
+    assert_eq!(client.run(&["PTTL", "pxat"]).await, int(30));
+    // a deadline in the past leaves nothing behind
+    client.run(&["SET", "past", "v", "PXAT", "1"]).await;
+    assert_eq!(client.run(&["GET", "past"]).await, nil());
+}
+
+#[tokio::test]
+async fn plain_set_clears_the_ttl_and_keepttl_keeps_it() {
+    let (mut client, _) = connect().await;
+    client.run(&["SET", "k", "v", "EX", "10"]).await;
+    client.run(&["SET", "k", "w", "KEEPTTL"]).await;
+    assert_eq!(client.run(&["TTL", "k"]).await, int(10));
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("w"));
+    client.run(&["SET", "k", "x"]).await;
+    assert_eq!(client.run(&["TTL", "k"]).await, int(-1));
+}
+
+#[tokio::test]
+async fn expire_and_pexpire() {
+    let (mut client, clock) = connect().await;
+    assert_eq!(client.run(&["EXPIRE", "k", "10"]).await, int(0));
+    client.run(&["SET", "k", "v"]).await;
+    assert_eq!(client.run(&["EXPIRE", "k", "10"]).await, int(1));
+    assert_eq!(client.run(&["PTTL", "k"]).await, int(10_000));
+    assert_eq!(client.run(&["PEXPIRE", "k", "250"]).await, int(1));
+    assert_eq!(client.run(&["PTTL", "k"]).await, int(250));
+    clock.advance(Duration::from_millis(251));
+    assert_eq!(client.run(&["GET", "k"]).await, nil());
+    assert_eq!(client.run(&["EXPIRE", "k", "10"]).await, int(0));
+}
+
+#[tokio::test]
+async fn expire_in_the_past_deletes() {
+    let (mut client, _) = connect().await;
+    client.run(&["SET", "k", "v"]).await;
+    assert_eq!(client.run(&["EXPIRE", "k", "-1"]).await, int(1));
+    assert_eq!(client.run(&["TTL", "k"]).await, int(-2));
+    client.run(&["SET", "k", "v"]).await;
+    assert_eq!(client.run(&["PEXPIRE", "k", "0"]).await, int(1));
+    assert_eq!(client.run(&["GET", "k"]).await, nil());
+}
+
+#[tokio::test]
+async fn expireat_and_pexpireat() {
+    let (mut client, _) = connect().await;
+    client.run(&["SET", "k", "v"]).await;
+    let at = (START / 1000 + 100).to_string();
+    assert_eq!(client.run(&["EXPIREAT", "k", &at]).await, int(1));
+    assert_eq!(client.run(&["TTL", "k"]).await, int(100));
+    let at = (START + 1234).to_string();
+    assert_eq!(client.run(&["PEXPIREAT", "k", &at]).await, int(1));
+    assert_eq!(client.run(&["PTTL", "k"]).await, int(1234));
+    assert_eq!(client.run(&["EXPIREAT", "k", "1"]).await, int(1));
+    assert_eq!(client.run(&["GET", "k"]).await, nil());
+}
+
+#[tokio::test]
+async fn persist() {
+    let (mut client, clock) = connect().await;
+    assert_eq!(client.run(&["PERSIST", "k"]).await, int(0));
+    client.run(&["SET", "k", "v", "EX", "1"]).await;
+    assert_eq!(client.run(&["PERSIST", "k"]).await, int(1));
+    assert_eq!(client.run(&["PERSIST", "k"]).await, int(0));
+    assert_eq!(client.run(&["TTL", "k"]).await, int(-1));
+    clock.advance(Duration::from_secs(60));
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("v"));
+}
+
+#[tokio::test]
+async fn expire_conditions() {
+    let (mut client, _) = connect().await;
+    client.run(&["SET", "k", "v"]).await;
+    assert_eq!(client.run(&["EXPIRE", "k", "10", "XX"]).await, int(0));
+    assert_eq!(client.run(&["EXPIRE", "k", "10", "GT"]).await, int(0));
+    assert_eq!(client.run(&["EXPIRE", "k", "10", "NX"]).await, int(1));
+    assert_eq!(client.run(&["EXPIRE", "k", "20", "NX"]).await, int(0));
+    assert_eq!(client.run(&["EXPIRE", "k", "5", "GT"]).await, int(0));
+    assert_eq!(client.run(&["EXPIRE", "k", "20", "GT"]).await, int(1));
+    assert_eq!(client.run(&["EXPIRE", "k", "30", "LT"]).await, int(0));
+    assert_eq!(client.run(&["EXPIRE", "k", "15", "lt", "xx"]).await, int(1));
+    assert_eq!(client.run(&["TTL", "k"]).await, int(15));
+    client.run(&["SET", "p", "v"]).await;
+    assert_eq!(client.run(&["EXPIRE", "p", "15", "LT"]).await, int(1));
+}
+
+#[tokio::test]
+async fn expire_errors() {
+    let (mut client, _) = connect().await;
+    client.run(&["SET", "k", "v"]).await;
+    assert_eq!(
+        client.run(&["EXPIRE", "k", "ten"]).await,
+        error("ERR value is not an integer or out of range")
+    );
+    assert_eq!(
+        client.run(&["EXPIRE", "k", "9223372036854775807"]).await,
+        error("ERR invalid expire time in 'expire' command")
+    );
+    assert_eq!(
+        client.run(&["EXPIRE", "k", "10", "NX", "XX"]).await,
+        error("ERR NX and XX, GT or LT options at the same time are not compatible")
+    );
+    assert_eq!(
+        client.run(&["EXPIRE", "k", "10", "GT", "LT"]).await,
+        error("ERR GT and LT options at the same time are not compatible")
+    );
+    assert_eq!(
+        client.run(&["EXPIRE", "k", "10", "SOON"]).await,
+        error("ERR Unsupported option SOON")
+    );
+    assert_eq!(
+        client.run(&["TTL"]).await,
+        error("ERR wrong number of arguments for 'ttl' command")
+    );
+    assert_eq!(
+        client.run(&["PERSIST", "k", "k"]).await,
+        error("ERR wrong number of arguments for 'persist' command")
+    );
+    assert_eq!(client.run(&["TTL", "k"]).await, int(-1));
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("v"));
+    assert_eq!(
+        client.run(&["SET", "k", "v", "EX", "0"]).await,
+        error("ERR invalid expire time in 'set' command")
+    );
+    assert_eq!(client.run(&["PING"]).await, simple("PONG"));
+}
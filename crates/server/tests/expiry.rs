@@ -0,0 +1,76 @@
+use std::time::Duration;
+
+use server::{active_expiry, Db, ManualClock, Value};
+
+const START: u64 = 1_700_000_000_000;
+
+fn fill(db: &Db, prefix: &str, count: usize, deadline: Option<u64>) {
+    let mut keyspace = db.lock();
+    for i in 0..count {
+        let key = format!("{prefix}:{i}").into_bytes();
+        keyspace.set(key.clone(), Value::String(b"v".to_vec()));
+        if let Some(deadline) = deadline {
+            keyspace.expire_at(&key, deadline);
+        }
+    }
+}
+
+#[test]
+fn a_sample_removes_only_expired_keys() {
+    let clock = ManualClock::new(START);
+    let db = Db::with_clock(clock.clone());
+    fill(&db, "short", 50, Some(START + 10));
+    fill(&db, "long", 50, Some(START + 10_000));
+    fill(&db, "forever", 50, None);
+    assert_eq!(db.lock().expire_sample(20), (20, 0));
+
+    clock.advance(Duration::from_millis(11));
+    let mut keyspace = db.lock();
+    while keyspace.expiring() > 50 {
+        keyspace.expire_sample(20);
+    }
+    assert_eq!(keyspace.len(), 100);
+    assert_eq!(keyspace.expired_keys(), 50);
+    for i in 0..50 {
+        assert!(keyspace.exists(format!("long:{i}").as_bytes()));
+        assert!(keyspace.exists(format!("forever:{i}").as_bytes()));
+    }
+}
+
+#[test]
+fn lazy_and_active_expiry_count_the_same_way() {
+    let clock = ManualClock::new(START);
+    let db = Db::with_clock(clock.clone());
+    fill(&db, "k", 2, Some(START + 1));
+    clock.advance(Duration::from_millis(2));
+    let mut keyspace = db.lock();
+    assert_eq!(keyspace.get_string(b"k:0"), Ok(None));
+    assert_eq!(keyspace.expire_sample(20), (1, 1));
+    assert_eq!(keyspace.expired_keys(), 2);
+    assert!(keyspace.is_empty());
+}
+
+#[tokio::test]
+async fn unread_expired_keys_are_removed_in_the_background() {
+    let clock = ManualClock::new(START);
+    let db = Db::with_clock(clock.clone());
+    fill(&db, "dead", 20_000, Some(START + 100));
+    fill(&db, "alive", 100, None);
+    let task = tokio::spawn(active_expiry(db.clone(), Duration::from_millis(5), 20));
+    clock.advance(Duration::from_millis(101));
+
+    let shrunk = tokio::time::timeout(Duration::from_secs(10), async {
+        // stops short of the last few keys, a round that finds a quarter or less
+        // of its sample expired waits for the next interval
+        while db.lock().len() > 1_000 {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    })
+    .await;
+    task.abort();
+    assert!(shrunk.is_ok(), "{} keys left", db.lock().len());
+    let mut keyspace = db.lock();
+    for i in 0..100 {
+        assert!(keyspace.exists(format!("alive:{i}").as_bytes()));
+    }
+}
@@ -0,0 +1,107 @@
+use std::time::{Duration, Instant};
+
+use server::glob::{matches, matches_nocase};
+
+// (pattern, input, matches), mostly from the cases redis tests stringmatchlen with
+const CASES: &[(&str, &str, bool)] = &[
+    ("", "", true),
+    ("", "a", false),
+    ("a", "", false),
+    ("*", "", true),
+    ("*", "anything", true),
+    ("**", "anything", true),
+    ("hello", "hello", true),
+    ("hello", "hell", false),
+    ("hello", "helloo", false),
+    ("h?llo", "hello", true),
+    ("h?llo", "hallo", true),
+    ("h?llo", "hllo", false),
+    ("h*llo", "hllo", true),
+    ("h*llo", "heeeello", true),
+    ("h*llo", "heeeelloo", false),
+    ("h[ae]llo", "hello", true),
+    ("h[ae]llo", "hallo", true),
+    ("h[ae]llo", "hillo", false),
+    ("h[^e]llo", "hallo", true),
+    ("h[^e]llo", "hello", false),
+    ("h[a-b]llo", "hallo", true),
+    ("h[a-b]llo", "hbllo", true),
+    ("h[a-b]llo", "hcllo", false),
+    // reversed ranges are swapped
+    ("h[b-a]llo", "hallo", true),
+    ("[a-z]*", "user", true),
+    ("[a-z]*", "User", false),
+    ("[0-9][0-9]", "42", true),
+    ("[^0-9]", "7", false),
+    ("user:*", "user:1000", true),
+    ("user:*", "users:1000", false),
+    ("*:*:*", "a:b:c", true),
+    ("*:*:*", "a:b", false),
+    ("*a", "ba", true),
+    ("*a", "ab", false),
+    ("a*", "ab", true),
+    ("*ab*cd*", "xxabyycdzz", true),
+    ("*ab*cd*", "xxcdyyabzz", false),
+    // escapes
+    ("h\\*llo", "h*llo", true),
+    ("h\\*llo", "hello", false),
+    ("h\\?llo", "h?llo", true),
+    ("\\[a]", "[a]", true),
+    ("[\\]]", "]", true),
+    ("[\\-]", "-", true),
+    ("[a\\-z]", "b", false),
+    ("a\\", "a\\", true),
+    ("\\a", "a", true),
+    // classes that never close run to the end of the pattern
+    ("[abc", "b", true),
+    ("[abc", "d", false),
+    ("[]", "a", false),
+    ("[]a", "a", false),
+    ("[^]", "a", true),
+    ("[*]", "*", true),
+    ("[*]", "a", false),
+    ("[?]", "?", true),
+    ("[?]", "a", false),
+];
+
+#[test]
+fn pattern_table() {
+    for &(pattern, input, expected) in CASES {
+        assert_eq!(
+            matches(pattern.as_bytes(), input.as_bytes()),
+            expected,
+            "{pattern:?} against {input:?}"
+        );
+    }
+}
+
+#[test]
+fn matching_is_on_bytes() {
+    assert!(matches(b"?", &[0xff]));
+    assert!(matches(b"\xc3?", "é".as_bytes()));
+    assert!(!matches(b"?", "é".as_bytes()));
+    assert!(matches(b"[\x00-\x10]", &[0x05]));
+    assert!(matches(b"*\0*", b"a\0b"));
+}
+
+#[test]
+fn nocase() {
+    assert!(matches_nocase(b"MAXMEMORY*", b"maxmemory-policy"));
+    assert!(matches_nocase(b"[A-C]x", b"bX"));
+    assert!(matches_nocase(b"[a-c]x", b"BX"));
+    assert!(!matches(b"MAXMEMORY*", b"maxmemory-policy"));
+}
+
+#[test]
+fn pathological_patterns_finish_quickly() {
+    let start = Instant::now();
+    let stars = "a*".repeat(30) + "b";
+    let input = "a".repeat(10_000);
+    assert!(!matches(stars.as_bytes(), input.as_bytes()));
+    let stars = "*a".repeat(30) + "*b";
+    assert!(!matches(stars.as_bytes(), input.as_bytes()));
+    assert!(matches(stars.as_bytes(), (input.clone() + "b").as_bytes()));
+    let classes = "[*[*".repeat(100);
+    assert!(!matches(classes.as_bytes(), input.as_bytes()));
+    assert!(start.elapsed() < Duration::from_secs(2));
+}
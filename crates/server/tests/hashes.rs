@@ -0,0 +1,309 @@
+mod common;
+
+use common::{array, bulk, error, int, nil, simple, start, Client};
+use protocol::RedirsValue;
+
+#[tokio::test]
+async fn hset_counts_new_fields_and_hget_reads_them() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        client
+            .run(&["HSET", "user", "name", "ada", "age", "36"])
+            .await,
+        int(2)
+    );
+    // an overwritten field is not new
+    assert_eq!(
+        client
+            .run(&["HSET", "user", "age", "37", "city", "london"])
+            .await,
+        int(1)
+    );
+    assert_eq!(client.run(&["HGET", "user", "age"]).await, bulk("37"));
+    assert_eq!(client.run(&["HGET", "user", "missing"]).await, nil());
+    assert_eq!(client.run(&["HGET", "missing", "name"]).await, nil());
+    assert_eq!(client.run(&["HLEN", "user"]).await, int(3));
+    assert_eq!(client.run(&["HLEN", "missing"]).await, int(0));
+    assert_eq!(client.run(&["HEXISTS", "user", "city"]).await, int(1));
+    assert_eq!(client.run(&["HEXISTS", "user", "zip"]).await, int(0));
+    assert_eq!(client.run(&["TYPE", "user"]).await, simple("hash"));
+    assert_eq!(
+        client.run(&["HSET", "user", "name"]).await,
+        error("ERR wrong number of arguments for 'hset' command")
+    );
+    assert_eq!(
+        client.run(&["HSET", "user", "a", "1", "b"]).await,
+        error("ERR wrong number of arguments for 'hset' command")
+    );
+}
+
+#[tokio::test]
+async fn hmget_keeps_the_argument_order() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["HSET", "h", "a", "1", "b", "2"]).await;
+    assert_eq!(
+        client.run(&["HMGET", "h", "b", "nope", "a", "b"]).await,
+        RedirsValue::Array(Some(vec![bulk("2"), nil(), bulk("1"), bulk("2")]))
+    );
+    assert_eq!(
+        client.run(&["HMGET", "missing", "a", "b"]).await,
+        RedirsValue::Array(Some(vec![nil(), nil()]))
+    );
+}
+
+#[tokio::test]
+async fn hdel_removes_the_key_with_its_last_field() {
+    let mut client = Client::connect(start().await).await;
+    client
+        .run(&["HSET", "h", "a", "1", "b", "2", "c", "3"])
+        .await;
+    assert_eq!(client.run(&["HDEL", "h", "a", "a", "nope"]).await, int(1));
+    assert_eq!(client.run(&["HDEL", "h", "b", "c"]).await, int(2));
+    assert_eq!(client.run(&["EXISTS", "h"]).await, int(0));
+    assert_eq!(client.run(&["HDEL", "h", "a"]).await, int(0));
+}
+
+// the fields and values of a flat HGETALL reply, sorted by field
+fn flat_pairs(reply: RedirsValue) -> Vec<(RedirsValue, RedirsValue)> {
+    let RedirsValue::Array(Some(items)) = reply else {
+        panic!("expected an array, got {reply:?}");
+    };
+    let mut pairs: Vec<_> = items
+        .chunks(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect();
+    pairs.sort();
+    pairs
+}
+
+#[tokio::test]
+async fn hgetall_is_a_flat_array_over_resp2() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["HSET", "h", "a", "1", "b", "2"]).await;
+    assert_eq!(
+        flat_pairs(client.run(&["HGETALL", "h"]).await),
+        vec![(bulk("a"), bulk("1")), (bulk("b"), bulk("2"))]
+    );
+    assert_eq!(client.run(&["HGETALL", "missing"]).await, array(&[]));
+}
+
+#[tokio::test]
+async fn hgetall_is_a_map_over_resp3() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["HELLO", "3"]).await;
+    client.run(&["HSET", "h", "a", "1", "b", "2"]).await;
+    let RedirsValue::Map(map) = client.run(&["HGETALL", "h"]).await else {
+        panic!("expected a map");
+    };
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get(&bulk("a")), Some(&bulk("1")));
+    assert_eq!(map.get(&bulk("b")), Some(&bulk("2")));
+    let RedirsValue::Map(empty) = client.run(&["HGETALL", "missing"]).await else {
+        panic!("expected a map");
+    };
+    assert_eq!(empty.len(), 0);
+}
+
+#[tokio::test]
+async fn hash_commands_on_a_string_are_wrongtype() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "string", "v"]).await;
+    let wrongtype = error("WRONGTYPE Operation against a key holding the wrong kind of value");
+    for args in [
+        &["HSET", "string", "f", "v"][..],
+        &["HGET", "string", "f"],
+        &["HMGET", "string", "f"],
+        &["HDEL", "string", "f"],
+        &["HGETALL", "string"],
+        &["HEXISTS", "string", "f"],
+        &["HLEN", "string"],
+    ] {
+        assert_eq!(client.run(args).await, wrongtype, "{args:?}");
+    }
+}
+
+#[tokio::test]
+async fn hincrby_and_hincrbyfloat() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(client.run(&["HINCRBY", "h", "n", "5"]).await, int(5));
+    assert_eq!(client.run(&["HINCRBY", "h", "n", "-7"]).await, int(-2));
+    assert_eq!(client.run(&["HGET", "h", "n"]).await, bulk("-2"));
+    client
+        .run(&["HSET", "h", "max", &i64::MAX.to_string(), "text", "abc"])
+        .await;
+    assert_eq!(
+        client.run(&["HINCRBY", "h", "max", "1"]).await,
+        error("ERR increment or decrement would overflow")
+    );
+    assert_eq!(
+        client.run(&["HINCRBY", "h", "text", "1"]).await,
+        error("ERR hash value is not an integer")
+    );
+    assert_eq!(
+        client.run(&["HINCRBY", "h", "n", "x"]).await,
+        error("ERR value is not an integer or out of range")
+    );
+    assert_eq!(
+        client.run(&["HINCRBYFLOAT", "h", "f", "10.5"]).await,
+        bulk("10.5")
+    );
+    assert_eq!(
+        client.run(&["HINCRBYFLOAT", "h", "f", "0.1"]).await,
+        bulk("10.6")
+    );
+    assert_eq!(
+        client.run(&["HINCRBYFLOAT", "h", "n", "2"]).await,
+        bulk("0")
+    );
+    assert_eq!(
+        client.run(&["HINCRBYFLOAT", "h", "text", "1"]).await,
+        error("ERR hash value is not a float")
+    );
+    assert_eq!(
+        client.run(&["HINCRBYFLOAT", "h", "f", "inf"]).await,
+        error("ERR value is not a valid float")
+    );
+    client.run(&["HSET", "h", "big", "1.7e308"]).await;
+    assert_eq!(
+        client.run(&["HINCRBYFLOAT", "h", "big", "1.7e308"]).await,
+        error("ERR increment would produce NaN or Infinity")
+    );
+}
+
+fn items(reply: RedirsValue) -> Vec<RedirsValue> {
+    let RedirsValue::Array(Some(items)) = reply else {
+        panic!("expected an array, got {reply:?}");
+    };
+    items
+}
+
+#[tokio::test]
+async fn hrandfield_counts() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(client.run(&["HRANDFIELD", "h"]).await, nil());
+    assert_eq!(client.run(&["HRANDFIELD", "h", "3"]).await, array(&[]));
+    assert_eq!(client.run(&["HRANDFIELD", "h", "-3"]).await, array(&[]));
+    client
+        .run(&["HSET", "h", "a", "1", "b", "2", "c", "3"])
+        .await;
+    let fields = [bulk("a"), bulk("b"), bulk("c")];
+    assert!(fields.contains(&client.run(&["HRANDFIELD", "h"]).await));
+    assert_eq!(client.run(&["HRANDFIELD", "h", "0"]).await, array(&[]));
+    // a positive count gives distinct fields, no more than there are
+    let mut distinct = items(client.run(&["HRANDFIELD", "h", "2"]).await);
+    assert_eq!(distinct.len(), 2);
+    distinct.dedup();
+    assert_eq!(distinct.len(), 2);
+    let mut all = items(client.run(&["HRANDFIELD", "h", "10"]).await);
+    all.sort();
+    assert_eq!(all, fields);
+    // a negative count gives exactly that many, repeats included
+    let repeated = items(client.run(&["HRANDFIELD", "h", "-30"]).await);
+    assert_eq!(repeated.len(), 30);
+    assert!(repeated.iter().all(|field| fields.contains(field)));
+    let mut unique = repeated.clone();
+    unique.sort();
+    unique.dedup();
+    assert!(unique.len() < repeated.len());
+    client.run(&["HSET", "one", "only", "v"]).await;
+    assert_eq!(
+        client.run(&["HRANDFIELD", "one", "-4"]).await,
+        array(&["only", "only", "only", "only"])
+    );
+}
+
+#[tokio::test]
+async fn hrandfield_withvalues() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["HSET", "h", "a", "1", "b", "2"]).await;
+    let flat = items(client.run(&["HRANDFIELD", "h", "-6", "WITHVALUES"]).await);
+    assert_eq!(flat.len(), 12);
+    for pair in flat.chunks(2) {
+        assert!(
+            [[bulk("a"), bulk("1")], [bulk("b"), bulk("2")]]
+                .contains(&[pair[0].clone(), pair[1].clone()]),
+            "{pair:?}"
+        );
+    }
+    client.run(&["HELLO", "3"]).await;
+    let pairs = items(client.run(&["HRANDFIELD", "h", "2", "WITHVALUES"]).await);
+    let mut pairs: Vec<_> = pairs.into_iter().map(items).collect();
+    pairs.sort();
+    assert_eq!(
+        pairs,
+        vec![vec![bulk("a"), bulk("1")], vec![bulk("b"), bulk("2")]]
+    );
+    assert_eq!(
+        client.run(&["HRANDFIELD", "h", "1", "WITH"]).await,
+        error("ERR syntax error")
+    );
+    assert_eq!(
+        client
+            .run(&["HRANDFIELD", "h", "-9223372036854775807", "WITHVALUES"])
+            .await,
+        error("ERR value is out of range")
+    );
+}
+
+// every field and value a full HSCAN returns
+async fn hscan_all(client: &mut Client, key: &str, opts: &[&str]) -> Vec<Vec<RedirsValue>> {
+    let mut cursor = "0".to_owned();
+    let mut found = Vec::new();
+    loop {
+        let mut args = vec!["HSCAN", key, &cursor];
+        args.extend(opts);
+        let reply = items(client.run(&args).await);
+        let RedirsValue::BulkString(Some(next)) = &reply[0] else {
+            panic!("{reply:?}");
+        };
+        found.push(items(reply[1].clone()));
+        cursor = String::from_utf8(next.to_vec()).unwrap();
+        if cursor == "0" {
+            return found;
+        }
+    }
+}
+
+#[tokio::test]
+async fn hscan_walks_every_field_once() {
+    let mut client = Client::connect(start().await).await;
+    let mut args = vec!["HSET".to_owned(), "h".to_owned()];
+    for i in 0..100 {
+        args.extend([format!("field:{i}"), i.to_string()]);
+    }
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    client.run(&args).await;
+    let steps = hscan_all(&mut client, "h", &["COUNT", "7"]).await;
+    assert!(steps.len() > 1);
+    let mut pairs: Vec<_> = steps
+        .concat()
+        .chunks(2)
+        .map(|pair| (pair[0].clone(), pair[1].clone()))
+        .collect();
+    pairs.sort();
+    pairs.dedup();
+    assert_eq!(pairs.len(), 100);
+    assert!(pairs.contains(&(bulk("field:42"), bulk("42"))));
+    let fields = hscan_all(&mut client, "h", &["MATCH", "field:1?", "NOVALUES"]).await;
+    let mut fields = fields.concat();
+    fields.sort();
+    let expected: Vec<_> = (10..20).map(|i| bulk(&format!("field:{i}"))).collect();
+    assert_eq!(fields, expected);
+    assert_eq!(
+        client.run(&["HSCAN", "missing", "0"]).await,
+        RedirsValue::Array(Some(vec![bulk("0"), array(&[])]))
+    );
+    assert_eq!(
+        client.run(&["HSCAN", "h", "0", "TYPE", "hash"]).await,
+        error("ERR syntax error")
+    );
+    assert_eq!(
+        client.run(&["HSCAN", "h", "x"]).await,
+        error("ERR invalid cursor")
+    );
+    client.run(&["SET", "string", "v"]).await;
+    assert_eq!(
+        client.run(&["HSCAN", "string", "0"]).await,
+        error("WRONGTYPE Operation against a key holding the wrong kind of value")
+    );
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use common::{error, int, simple, start, Client};
+use protocol::RedirsValue;
+use server::hyperloglog;
+
+#[tokio::test]
+async fn pfadd_reports_register_changes() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        client
+            .run(&["PFADD", "hll", "a", "b", "c", "d", "e", "f", "g"])
+            .await,
+        int(1)
+    );
+    assert_eq!(client.run(&["PFCOUNT", "hll"]).await, int(7));
+    assert_eq!(client.run(&["PFADD", "hll", "a", "c", "g"]).await, int(0));
+    assert_eq!(client.run(&["PFADD", "hll", "h"]).await, int(1));
+    assert_eq!(client.run(&["PFCOUNT", "hll"]).await, int(8));
+    // creating the key counts as a change, even with nothing to add
+    assert_eq!(client.run(&["PFADD", "empty"]).await, int(1));
+    assert_eq!(client.run(&["PFADD", "empty"]).await, int(0));
+    assert_eq!(client.run(&["PFCOUNT", "empty"]).await, int(0));
+    assert_eq!(client.run(&["PFCOUNT", "missing"]).await, int(0));
+    assert_eq!(client.run(&["TYPE", "hll"]).await, simple("string"));
+}
+
+#[tokio::test]
+async fn pfcount_and_pfmerge_take_the_union() {
+    let mut client = Client::connect(start().await).await;
+    // the example of the redis docs
+    client
+        .run(&["PFADD", "hll1", "foo", "bar", "zap", "a"])
+        .await;
+    client.run(&["PFADD", "hll2", "a", "b", "c", "foo"]).await;
+    assert_eq!(
+        client.run(&["PFCOUNT", "hll1", "hll2", "missing"]).await,
+        int(6)
+    );
+    // counting several keys changes none of them
+    assert_eq!(client.run(&["PFCOUNT", "hll1"]).await, int(4));
+    assert_eq!(
+        client.run(&["PFMERGE", "hll3", "hll1", "hll2"]).await,
+        simple("OK")
+    );
+    assert_eq!(client.run(&["PFCOUNT", "hll3"]).await, int(6));
+    // the destination joins in the union and keeps its deadline
+    client.run(&["PFADD", "dest", "x"]).await;
+    client.run(&["EXPIRE", "dest", "100"]).await;
+    client.run(&["PFMERGE", "dest", "hll1"]).await;
+    assert_eq!(client.run(&["PFCOUNT", "dest"]).await, int(5));
+    assert_eq!(client.run(&["TTL", "dest"]).await, int(100));
+    assert_eq!(client.run(&["PFMERGE", "new"]).await, simple("OK"));
+    assert_eq!(client.run(&["PFCOUNT", "new"]).await, int(0));
+}
+
+#[tokio::test]
+async fn a_hyperloglog_round_trips_through_get_and_set() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["PFADD", "hll", "a", "b", "c"]).await;
+    let RedirsValue::BulkString(Some(value)) = client.run(&["GET", "hll"]).await else {
+        panic!("not a string");
+    };
+    assert_eq!(value.len(), hyperloglog::LEN);
+    assert!(value.starts_with(b"HYLL"));
+    client.run_bytes(&[b"SET", b"copy", &value]).await;
+    assert_eq!(client.run(&["PFCOUNT", "copy"]).await, int(3));
+    assert_eq!(client.run(&["PFADD", "copy", "d"]).await, int(1));
+    assert_eq!(client.run(&["PFCOUNT", "copy", "hll"]).await, int(4));
+}
+
+#[tokio::test]
+async fn other_values_are_not_hyperloglogs() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "string", "HYLL not really"]).await;
+    client.run(&["PFADD", "hll", "a"]).await;
+    let invalid = error("WRONGTYPE Key is not a valid HyperLogLog string value.");
+    for args in [
+        &["PFADD", "string", "a"][..],
+        &["PFCOUNT", "string"],
+        &["PFCOUNT", "hll", "string"],
+        &["PFMERGE", "hll", "string"],
+        &["PFMERGE", "string", "hll"],
+    ] {
+        assert_eq!(client.run(args).await, invalid, "{args:?}");
+    }
+    client.run(&["RPUSH", "list", "a"]).await;
+    let wrongtype = error("WRONGTYPE Operation against a key holding the wrong kind of value");
+    for args in [
+        &["PFADD", "list", "a"][..],
+        &["PFCOUNT", "list"],
+        &["PFCOUNT", "hll", "list"],
+        &["PFMERGE", "hll", "list"],
+    ] {
+        assert_eq!(client.run(args).await, wrongtype, "{args:?}");
+    }
+}
+
+#[tokio::test]
+async fn the_estimate_of_100k_elements_is_within_the_standard_error() {
+    let mut client = Client::connect(start().await).await;
+    let n = 100_000;
+    for batch in (0..n).collect::<Vec<_>>().chunks(1000) {
+        let elements: Vec<_> = batch.iter().map(|i| format!("visitor:{i}")).collect();
+        let mut args = vec!["PFADD", "visitors"];
+        args.extend(elements.iter().map(String::as_str));
+        client.run(&args).await;
+    }
+    let RedirsValue::Integer(estimate) = client.run(&["PFCOUNT", "visitors"]).await else {
+        panic!("not an integer");
+    };
+    let error = (estimate - n).abs() as f64 / n as f64;
+    assert!(error < 0.0081, "{estimate} is off by {:.2}%", error * 100.0);
+}
+
+// the registers and estimator directly, on sets of several sizes, with
+// merging two halves counting as much as adding everything to one
+#[test]
+fn merged_halves_count_as_the_whole() {
+    for n in [10u64, 1_000, 50_000] {
+        let (mut whole, mut even, mut odd) =
+            (hyperloglog::new(), hyperloglog::new(), hyperloglog::new());
+        for i in 0..n {
+            let element = i.to_string();
+            hyperloglog::add(&mut whole, element.as_bytes());
+            let half = if i % 2 == 0 { &mut even } else { &mut odd };
+            hyperloglog::add(half, element.as_bytes());
+        }
+        let mut registers = hyperloglog::registers(&even);
+        hyperloglog::merge(&mut registers, &odd);
+        assert_eq!(registers, hyperloglog::registers(&whole));
+        let estimate = hyperloglog::count(&mut whole);
+        assert_eq!(hyperloglog::estimate(&registers), estimate);
+        let error = estimate.abs_diff(n) as f64 / n as f64;
+        assert!(error < 0.02, "{n}: {estimate}");
+    }
+}
@@ -0,0 +1,153 @@
+mod common;
+
+use std::{collections::HashMap, time::Duration};
+
+use common::{start, start_with, Client};
+use protocol::RedirsValue;
+use server::Db;
+
+// the text INFO replies with
+async fn payload(client: &mut Client, sections: &[&str]) -> String {
+    let mut command = vec!["INFO"];
+    command.extend_from_slice(sections);
+    match client.run(&command).await {
+        RedirsValue::BulkString(Some(payload)) => String::from_utf8(payload.to_vec()).unwrap(),
+        other => panic!("expected the INFO text, got {other:?}"),
+    }
+}
+
+// the fields of the sections, by name
+async fn info(client: &mut Client, sections: &[&str]) -> HashMap<String, String> {
+    payload(client, sections)
+        .await
+        .split("\r\n")
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(|line| {
+            let (field, value) = line.split_once(':').unwrap();
+            (field.to_owned(), value.to_owned())
+        })
+        .collect()
+}
+
+fn headers(payload: &str) -> Vec<&str> {
+    payload
+        .split("\r\n")
+        .filter(|line| line.starts_with("# "))
+        .collect()
+}
+
+#[tokio::test]
+async fn sections() {
+    let mut client = Client::connect(start().await).await;
+    let every = [
+        "# Server",
+        "# Clients",
+        "# Memory",
+        "# Stats",
+        "# Replication",
+        "# Keyspace",
+    ];
+    for sections in [&[][..], &["default"], &["everything"], &["ALL"]] {
+        assert_eq!(headers(&payload(&mut client, sections).await), every);
+    }
+    let some = payload(&mut client, &["keyspace", "Server"]).await;
+    assert_eq!(headers(&some), ["# Server", "# Keyspace"]);
+    assert!(some.starts_with("# Server\r\nredis_version:"));
+    assert!(some.contains("\r\n\r\n# Keyspace\r\n"));
+    assert_eq!(payload(&mut client, &["nosuchsection"]).await, "");
+}
+
+#[tokio::test]
+async fn server_and_clients() {
+    let addr = start().await;
+    let mut client = Client::connect(addr).await;
+    let server = info(&mut client, &["server"]).await;
+    assert_eq!(server["redis_mode"], "standalone");
+    assert_eq!(server["tcp_port"], addr.port().to_string());
+    assert!(server["uptime_in_seconds"].parse::<u64>().is_ok());
+    assert_eq!(info(&mut client, &["replication"]).await["role"], "master");
+    assert_eq!(
+        info(&mut client, &["clients"]).await["connected_clients"],
+        "1"
+    );
+    let mut other = Client::connect(addr).await;
+    other
+        .send(b"*3\r\n$5\r\nBLPOP\r\n$1\r\nl\r\n$1\r\n0\r\n")
+        .await;
+    let mut clients = HashMap::new();
+    for _ in 0..100 {
+        clients = info(&mut client, &["clients"]).await;
+        if clients["blocked_clients"] == "1" {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(clients["connected_clients"], "2");
+    assert_eq!(clients["blocked_clients"], "1");
+    client.run(&["RPUSH", "l", "x"]).await;
+    other.reply().await;
+    assert_eq!(
+        info(&mut client, &["clients"]).await["blocked_clients"],
+        "0"
+    );
+}
+
+#[tokio::test]
+async fn hits_and_misses() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "k", "v"]).await;
+    let before = info(&mut client, &["stats"]).await;
+    client.run(&["GET", "k"]).await;
+    client.run(&["GET", "missing"]).await;
+    let after = info(&mut client, &["stats"]).await;
+    let count = |stats: &HashMap<String, String>, field: &str| stats[field].parse::<u64>().unwrap();
+    assert_eq!(
+        count(&after, "keyspace_hits"),
+        count(&before, "keyspace_hits") + 1
+    );
+    assert_eq!(
+        count(&after, "keyspace_misses"),
+        count(&before, "keyspace_misses") + 1
+    );
+    // the two GETs and the INFO before
+    assert_eq!(
+        count(&after, "total_commands_processed"),
+        count(&before, "total_commands_processed") + 3
+    );
+    assert!(count(&after, "total_connections_received") >= 1);
+}
+
+#[tokio::test]
+async fn keyspace_and_memory() {
+    let mut client = Client::connect(start().await).await;
+    assert!(!payload(&mut client, &["keyspace"]).await.contains("db0"));
+    let empty = info(&mut client, &["memory"]).await["used_memory"]
+        .parse::<usize>()
+        .unwrap();
+    client.run(&["SET", "a", &"x".repeat(4096)]).await;
+    client.run(&["SET", "b", "2", "EX", "100"]).await;
+    client.run(&["SELECT", "3"]).await;
+    client.run(&["SADD", "s", "m"]).await;
+    let keyspace = info(&mut client, &["keyspace"]).await;
+    assert_eq!(keyspace["db0"], "keys=2,expires=1");
+    assert_eq!(keyspace["db3"], "keys=1,expires=0");
+    assert_eq!(keyspace.len(), 2);
+    let memory = info(&mut client, &["memory"]).await;
+    assert!(memory["used_memory"].parse::<usize>().unwrap() >= empty + 4096);
+    assert!(memory["used_memory_human"].ends_with('K'));
+}
+
+#[tokio::test]
+async fn instantaneous_ops() {
+    let db = Db::new();
+    tokio::spawn(server::sample_ops(db.clone()));
+    let mut client = Client::connect(start_with(db).await).await;
+    for _ in 0..100 {
+        client.run(&["PING"]).await;
+    }
+    tokio::time::sleep(Duration::from_millis(1100)).await;
+    let ops = info(&mut client, &["stats"]).await["instantaneous_ops_per_sec"]
+        .parse::<u64>()
+        .unwrap();
+    assert!(ops > 0);
+}
@@ -0,0 +1,405 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{array, bulk, error, int, nil, simple, start, start_with, Client};
+use protocol::RedirsValue;
+use server::{Db, ManualClock};
+
+#[tokio::test]
+async fn del_and_unlink_count_the_removed_keys() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["MSET", "a", "1", "b", "2", "c", "3"]).await;
+    assert_eq!(client.run(&["DEL", "a", "b", "missing", "a"]).await, int(2));
+    assert_eq!(client.run(&["UNLINK", "c", "c"]).await, int(1));
+    assert_eq!(client.run(&["UNLINK", "c"]).await, int(0));
+    assert_eq!(
+        client.run(&["DEL"]).await,
+        error("ERR wrong number of arguments for 'del' command")
+    );
+    assert_eq!(
+        client.run(&["UNLINK"]).await,
+        error("ERR wrong number of arguments for 'unlink' command")
+    );
+}
+
+#[tokio::test]
+async fn exists_counts_duplicates() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "a", "1"]).await;
+    assert_eq!(client.run(&["EXISTS", "a"]).await, int(1));
+    assert_eq!(client.run(&["EXISTS", "a", "a", "a"]).await, int(3));
+    assert_eq!(client.run(&["EXISTS", "a", "missing", "a"]).await, int(2));
+    assert_eq!(client.run(&["EXISTS", "missing"]).await, int(0));
+    assert_eq!(
+        client.run(&["EXISTS"]).await,
+        error("ERR wrong number of arguments for 'exists' command")
+    );
+}
+
+fn sorted(reply: RedirsValue) -> Vec<RedirsValue> {
+    let RedirsValue::Array(Some(mut keys)) = reply else {
+        panic!("expected an array, got {reply:?}");
+    };
+    keys.sort_by_key(|key| key.as_bytes().map(<[u8]>::to_vec));
+    keys
+}
+
+#[tokio::test]
+async fn keys_matches_a_pattern() {
+    let mut client = Client::connect(start().await).await;
+    client
+        .run(&[
+            "MSET", "user:1", "a", "user:2", "b", "users", "c", "other", "d",
+        ])
+        .await;
+    assert_eq!(
+        sorted(client.run(&["KEYS", "user:*"]).await),
+        vec![bulk("user:1"), bulk("user:2")]
+    );
+    assert_eq!(sorted(client.run(&["KEYS", "*"]).await).len(), 4);
+    assert_eq!(
+        sorted(client.run(&["KEYS", "user?"]).await),
+        vec![bulk("users")]
+    );
+    assert_eq!(sorted(client.run(&["KEYS", "nothing*"]).await), vec![]);
+}
+
+#[tokio::test]
+async fn keys_skips_and_removes_expired_keys() {
+    let clock = ManualClock::new(1_000_000);
+    let db = Db::with_clock(clock.clone());
+    let mut client = Client::connect(start_with(db.clone()).await).await;
+    client.run(&["SET", "gone", "v", "PX", "10"]).await;
+    client.run(&["SET", "kept", "v"]).await;
+    clock.advance(Duration::from_millis(11));
+    assert_eq!(db.lock().len(), 2);
+    assert_eq!(sorted(client.run(&["KEYS", "*"]).await), vec![bulk("kept")]);
+    assert_eq!(db.lock().len(), 1);
+}
+
+// every key a full SCAN returns, following the cursor until it comes back 0
+async fn scan_all(client: &mut Client, opts: &[&str]) -> Vec<Vec<u8>> {
+    let mut cursor = "0".to_owned();
+    let mut keys = Vec::new();
+    loop {
+        let mut args = vec!["SCAN", &cursor];
+        args.extend(opts);
+        let RedirsValue::Array(Some(reply)) = client.run(&args).await else {
+            panic!("expected an array");
+        };
+        let [next, RedirsValue::Array(Some(page))] = &reply[..] else {
+            panic!("unexpected reply {reply:?}");
+        };
+        keys.extend(page.iter().map(|key| key.as_bytes().unwrap().to_vec()));
+        cursor = String::from_utf8(next.as_bytes().unwrap().to_vec()).unwrap();
+        if cursor == "0" {
+            return keys;
+        }
+    }
+}
+
+#[tokio::test]
+async fn scan_returns_every_key_once_when_nothing_changes() {
+    let mut client = Client::connect(start().await).await;
+    for i in 0..500 {
+        client.run(&["SET", &format!("key:{i}"), "v"]).await;
+    }
+    for count in ["1", "7", "10", "1000"] {
+        let mut keys = scan_all(&mut client, &["COUNT", count]).await;
+        keys.sort();
+        let before = keys.len();
+        keys.dedup();
+        assert_eq!((before, keys.len()), (500, 500), "COUNT {count}");
+    }
+    assert!(scan_all(&mut client, &[]).await.len() == 500);
+}
+
+#[tokio::test]
+async fn scan_match_and_type() {
+    let db = Db::new();
+    db.lock()
+        .get_or_create_list(b"user:list")
+        .unwrap()
+        .push_back(b"a".to_vec());
+    db.lock().get_or_create_hash(b"user:hash").unwrap();
+    let mut client = Client::connect(start_with(db).await).await;
+    client
+        .run(&["MSET", "user:1", "a", "user:2", "b", "other", "c"])
+        .await;
+    let mut keys = scan_all(&mut client, &["MATCH", "user:*", "COUNT", "2"]).await;
+    keys.sort();
+    assert_eq!(
+        keys,
+        [&b"user:1"[..], b"user:2", b"user:hash", b"user:list"]
+    );
+    let mut keys = scan_all(&mut client, &["TYPE", "string"]).await;
+    keys.sort();
+    assert_eq!(keys, [&b"other"[..], b"user:1", b"user:2"]);
+    let keys = scan_all(&mut client, &["type", "LIST", "match", "*"]).await;
+    assert_eq!(keys, [b"user:list"]);
+    assert!(scan_all(&mut client, &["TYPE", "zset"]).await.is_empty());
+}
+
+#[tokio::test]
+async fn scan_errors() {
+    let mut client = Client::connect(start().await).await;
+    let syntax = error("ERR syntax error");
+    assert_eq!(
+        client.run(&["SCAN", "x"]).await,
+        error("ERR invalid cursor")
+    );
+    assert_eq!(
+        client.run(&["SCAN", "-1"]).await,
+        error("ERR invalid cursor")
+    );
+    assert_eq!(client.run(&["SCAN", "0", "COUNT", "0"]).await, syntax);
+    assert_eq!(client.run(&["SCAN", "0", "COUNT"]).await, syntax);
+    assert_eq!(client.run(&["SCAN", "0", "LIMIT", "1"]).await, syntax);
+    assert_eq!(
+        client.run(&["SCAN", "0", "COUNT", "x"]).await,
+        error("ERR value is not an integer or out of range")
+    );
+    assert_eq!(
+        client.run(&["SCAN", "0", "TYPE", "tree"]).await,
+        error("ERR unknown type name 'tree'")
+    );
+}
+
+#[tokio::test]
+async fn scan_skips_expired_keys() {
+    let clock = ManualClock::new(1_000_000);
+    let db = Db::with_clock(clock.clone());
+    let mut client = Client::connect(start_with(db.clone()).await).await;
+    for i in 0..20 {
+        client
+            .run(&["SET", &format!("gone:{i}"), "v", "PX", "5"])
+            .await;
+    }
+    client.run(&["SET", "kept", "v"]).await;
+    clock.advance(Duration::from_millis(6));
+    assert_eq!(scan_all(&mut client, &["COUNT", "3"]).await, [b"kept"]);
+    assert_eq!(db.lock().len(), 1);
+}
+
+#[tokio::test]
+async fn keys_present_for_a_whole_scan_are_returned_while_others_change() {
+    let addr = start().await;
+    let mut client = Client::connect(addr).await;
+    for i in 0..2000 {
+        client.run(&["SET", &format!("stable:{i}"), "v"]).await;
+    }
+    let churn = tokio::spawn(async move {
+        let mut client = Client::connect(addr).await;
+        for round in 0.. {
+            for i in 0..200 {
+                let key = format!("volatile:{round}:{i}");
+                client.run(&["SET", &key, "v"]).await;
+            }
+            for i in 0..200 {
+                let key = format!("volatile:{round}:{i}");
+                client.run(&["DEL", &key]).await;
+            }
+        }
+    });
+    for _ in 0..5 {
+        let keys = scan_all(&mut client, &["COUNT", "25"]).await;
+        let mut stable: Vec<_> = keys
+            .into_iter()
+            .filter(|key| key.starts_with(b"stable:"))
+            .collect();
+        stable.sort();
+        stable.dedup();
+        assert_eq!(stable.len(), 2000);
+    }
+    churn.abort();
+}
+
+#[tokio::test]
+async fn type_of_every_kind() {
+    let clock = ManualClock::new(1_000_000);
+    let db = Db::with_clock(clock.clone());
+    {
+        let mut keyspace = db.lock();
+        keyspace.get_or_create_list(b"list").unwrap();
+        keyspace.get_or_create_hash(b"hash").unwrap();
+        keyspace.get_or_create_set(b"set").unwrap();
+        keyspace.get_or_create_sorted_set(b"zset").unwrap();
+        keyspace.get_or_create_stream(b"stream").unwrap();
+    }
+    let mut client = Client::connect(start_with(db.clone()).await).await;
+    client.run(&["SET", "string", "v"]).await;
+    for kind in ["string", "list", "hash", "set", "zset", "stream"] {
+        assert_eq!(client.run(&["TYPE", kind]).await, simple(kind));
+        assert_eq!(db.lock().key_type(kind.as_bytes()), Some(kind));
+    }
+    assert_eq!(client.run(&["TYPE", "missing"]).await, simple("none"));
+    client.run(&["SET", "expiring", "v", "PX", "10"]).await;
+    assert_eq!(client.run(&["TYPE", "expiring"]).await, simple("string"));
+    clock.advance(Duration::from_millis(11));
+    assert_eq!(client.run(&["TYPE", "expiring"]).await, simple("none"));
+    assert_eq!(db.lock().key_type(b"expiring"), None);
+}
+
+#[tokio::test]
+async fn rename_moves_the_value_and_its_ttl() {
+    let clock = ManualClock::new(1_000_000);
+    let mut client = Client::connect(start_with(Db::with_clock(clock.clone())).await).await;
+    client.run(&["SET", "src", "v", "PX", "5000"]).await;
+    client.run(&["SET", "dst", "old"]).await;
+    assert_eq!(client.run(&["RENAME", "src", "dst"]).await, simple("OK"));
+    assert_eq!(client.run(&["GET", "dst"]).await, bulk("v"));
+    assert_eq!(client.run(&["PTTL", "dst"]).await, int(5000));
+    assert_eq!(client.run(&["EXISTS", "src"]).await, int(0));
+    assert_eq!(client.run(&["PTTL", "src"]).await, int(-2));
+    // a destination with a ttl loses it to a persistent source
+    client.run(&["SET", "persistent", "p"]).await;
+    assert_eq!(
+        client.run(&["RENAME", "persistent", "dst"]).await,
+        simple("OK")
+    );
+    assert_eq!(client.run(&["PTTL", "dst"]).await, int(-1));
+    // onto itself is a no-op
+    client.run(&["PEXPIRE", "dst", "300"]).await;
+    assert_eq!(client.run(&["RENAME", "dst", "dst"]).await, simple("OK"));
+    assert_eq!(client.run(&["GET", "dst"]).await, bulk("p"));
+    assert_eq!(client.run(&["PTTL", "dst"]).await, int(300));
+    assert_eq!(
+        client.run(&["RENAME", "missing", "dst"]).await,
+        error("ERR no such key")
+    );
+    clock.advance(Duration::from_millis(301));
+    assert_eq!(
+        client.run(&["RENAME", "dst", "other"]).await,
+        error("ERR no such key")
+    );
+}
+
+#[tokio::test]
+async fn renamenx_refuses_a_taken_name() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["MSET", "a", "1", "b", "2"]).await;
+    assert_eq!(client.run(&["RENAMENX", "a", "b"]).await, int(0));
+    assert_eq!(client.run(&["MGET", "a", "b"]).await, array(&["1", "2"]));
+    assert_eq!(client.run(&["RENAMENX", "a", "a"]).await, int(0));
+    assert_eq!(client.run(&["RENAMENX", "a", "c"]).await, int(1));
+    assert_eq!(client.run(&["GET", "c"]).await, bulk("1"));
+    assert_eq!(client.run(&["EXISTS", "a"]).await, int(0));
+    assert_eq!(
+        client.run(&["RENAMENX", "a", "d"]).await,
+        error("ERR no such key")
+    );
+}
+
+#[tokio::test]
+async fn copy_duplicates_the_value_and_its_ttl() {
+    let clock = ManualClock::new(1_000_000);
+    let db = Db::with_clock(clock.clone());
+    db.lock()
+        .get_or_create_list(b"list")
+        .unwrap()
+        .push_back(b"a".to_vec());
+    let mut client = Client::connect(start_with(db.clone()).await).await;
+    client.run(&["SET", "src", "v", "PX", "5000"]).await;
+    assert_eq!(client.run(&["COPY", "src", "dst"]).await, int(1));
+    assert_eq!(client.run(&["GET", "dst"]).await, bulk("v"));
+    assert_eq!(client.run(&["PTTL", "dst"]).await, int(5000));
+    assert_eq!(client.run(&["PTTL", "src"]).await, int(5000));
+    // the copy is left alone without REPLACE
+    client.run(&["SET", "src", "new"]).await;
+    assert_eq!(client.run(&["COPY", "src", "dst"]).await, int(0));
+    assert_eq!(client.run(&["GET", "dst"]).await, bulk("v"));
+    assert_eq!(client.run(&["COPY", "src", "dst", "REPLACE"]).await, int(1));
+    assert_eq!(client.run(&["GET", "dst"]).await, bulk("new"));
+    assert_eq!(client.run(&["PTTL", "dst"]).await, int(-1));
+    assert_eq!(client.run(&["COPY", "missing", "dst"]).await, int(0));
+    // aggregates are copied deep
+    assert_eq!(
+        client.run(&["COPY", "list", "copy", "DB", "0"]).await,
+        int(1)
+    );
+    db.lock()
+        .get_list_mut(b"list")
+        .unwrap()
+        .unwrap()
+        .push_back(b"b".to_vec());
+    assert_eq!(db.lock().get_list(b"copy").unwrap().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn copy_errors() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "a", "1"]).await;
+    assert_eq!(
+        client.run(&["COPY", "a", "a"]).await,
+        error("ERR source and destination objects are the same")
+    );
+    assert_eq!(
+        client.run(&["COPY", "a", "b", "DB", "16"]).await,
+        error("ERR DB index is out of range")
+    );
+    assert_eq!(
+        client.run(&["COPY", "a", "b", "DB", "x"]).await,
+        error("ERR value is not an integer or out of range")
+    );
+    assert_eq!(
+        client.run(&["COPY", "a", "b", "DB"]).await,
+        error("ERR syntax error")
+    );
+    assert_eq!(
+        client.run(&["COPY", "a", "b", "NOPE"]).await,
+        error("ERR syntax error")
+    );
+    assert_eq!(
+        client.run(&["RENAME", "a"]).await,
+        error("ERR wrong number of arguments for 'rename' command")
+    );
+}
+
+#[tokio::test]
+async fn randomkey_is_roughly_uniform() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(client.run(&["RANDOMKEY"]).await, nil());
+    let keys = ["a", "b", "c", "d", "e"];
+    for key in keys {
+        client.run(&["SET", key, "v"]).await;
+    }
+    let draws = 5000;
+    let mut counts = std::collections::HashMap::new();
+    for _ in 0..draws {
+        let RedirsValue::BulkString(Some(key)) = client.run(&["RANDOMKEY"]).await else {
+            panic!("expected a key");
+        };
+        *counts.entry(key).or_insert(0.0) += 1.0;
+    }
+    assert_eq!(counts.len(), keys.len());
+    // 4 degrees of freedom, well past the 0.001 critical value of 18.5
+    let expected = draws as f64 / keys.len() as f64;
+    let chi_squared: f64 = counts
+        .values()
+        .map(|observed| (observed - expected).powi(2) / expected)
+        .sum();
+    assert!(chi_squared < 30.0, "{counts:?}");
+}
+
+#[tokio::test]
+async fn randomkey_never_returns_an_expired_key() {
+    let clock = ManualClock::new(1_000_000);
+    let db = Db::with_clock(clock.clone());
+    let mut client = Client::connect(start_with(db.clone()).await).await;
+    for i in 0..50 {
+        client
+            .run(&["SET", &format!("gone{i}"), "v", "PX", "10"])
+            .await;
+    }
+    client.run(&["SET", "kept", "v"]).await;
+    clock.advance(Duration::from_millis(11));
+    for _ in 0..20 {
+        assert_eq!(client.run(&["RANDOMKEY"]).await, bulk("kept"));
+    }
+    client.run(&["DEL", "kept"]).await;
+    client.run(&["SET", "last", "v", "PX", "10"]).await;
+    clock.advance(Duration::from_millis(11));
+    assert_eq!(client.run(&["RANDOMKEY"]).await, nil());
+    assert!(db.lock().is_empty());
+}
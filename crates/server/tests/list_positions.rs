@@ -0,0 +1,250 @@
+// the examples of the redis docs for LINSERT, LSET, LREM, LTRIM and LPOS,
+// then the edges around them
+mod common;
+
+use common::{array, error, int, nil, simple, start, Client};
+use protocol::RedirsValue;
+
+fn ints(items: &[i64]) -> RedirsValue {
+    RedirsValue::Array(Some(items.iter().map(|&item| int(item)).collect()))
+}
+
+#[tokio::test]
+async fn linsert() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["RPUSH", "mylist", "Hello", "World"]).await;
+    assert_eq!(
+        client
+            .run(&["LINSERT", "mylist", "BEFORE", "World", "There"])
+            .await,
+        int(3)
+    );
+    assert_eq!(
+        client.run(&["LRANGE", "mylist", "0", "-1"]).await,
+        array(&["Hello", "There", "World"])
+    );
+    assert_eq!(
+        client
+            .run(&["LINSERT", "mylist", "after", "World", "!"])
+            .await,
+        int(4)
+    );
+    assert_eq!(
+        client.run(&["LRANGE", "mylist", "-2", "-1"]).await,
+        array(&["World", "!"])
+    );
+    // only the first match of the pivot counts
+    client.run(&["RPUSH", "mylist", "Hello"]).await;
+    client
+        .run(&["LINSERT", "mylist", "AFTER", "Hello", "1"])
+        .await;
+    assert_eq!(
+        client.run(&["LRANGE", "mylist", "0", "1"]).await,
+        array(&["Hello", "1"])
+    );
+    assert_eq!(
+        client
+            .run(&["LINSERT", "mylist", "BEFORE", "nope", "x"])
+            .await,
+        int(-1)
+    );
+    assert_eq!(
+        client
+            .run(&["LINSERT", "missing", "BEFORE", "a", "x"])
+            .await,
+        int(0)
+    );
+    assert_eq!(client.run(&["EXISTS", "missing"]).await, int(0));
+    assert_eq!(
+        client.run(&["LINSERT", "mylist", "BESIDE", "a", "x"]).await,
+        error("ERR syntax error")
+    );
+}
+
+#[tokio::test]
+async fn lset() {
+    let mut client = Client::connect(start().await).await;
+    client
+        .run(&["RPUSH", "mylist", "one", "two", "three"])
+        .await;
+    assert_eq!(
+        client.run(&["LSET", "mylist", "0", "four"]).await,
+        simple("OK")
+    );
+    assert_eq!(
+        client.run(&["LSET", "mylist", "-2", "five"]).await,
+        simple("OK")
+    );
+    assert_eq!(
+        client.run(&["LRANGE", "mylist", "0", "-1"]).await,
+        array(&["four", "five", "three"])
+    );
+    for index in ["3", "-4"] {
+        assert_eq!(
+            client.run(&["LSET", "mylist", index, "x"]).await,
+            error("ERR index out of range")
+        );
+    }
+    assert_eq!(
+        client.run(&["LSET", "missing", "0", "x"]).await,
+        error("ERR no such key")
+    );
+}
+
+#[tokio::test]
+async fn lrem() {
+    let mut client = Client::connect(start().await).await;
+    client
+        .run(&["RPUSH", "mylist", "hello", "hello", "foo", "hello"])
+        .await;
+    assert_eq!(client.run(&["LREM", "mylist", "-2", "hello"]).await, int(2));
+    assert_eq!(
+        client.run(&["LRANGE", "mylist", "0", "-1"]).await,
+        array(&["hello", "foo"])
+    );
+    client.run(&["DEL", "mylist"]).await;
+    client
+        .run(&["RPUSH", "mylist", "a", "b", "a", "c", "a"])
+        .await;
+    assert_eq!(client.run(&["LREM", "mylist", "1", "a"]).await, int(1));
+    assert_eq!(
+        client.run(&["LRANGE", "mylist", "0", "-1"]).await,
+        array(&["b", "a", "c", "a"])
+    );
+    assert_eq!(client.run(&["LREM", "mylist", "-1", "a"]).await, int(1));
+    assert_eq!(
+        client.run(&["LRANGE", "mylist", "0", "-1"]).await,
+        array(&["b", "a", "c"])
+    );
+    assert_eq!(client.run(&["LREM", "mylist", "5", "z"]).await, int(0));
+    client.run(&["RPUSH", "mylist", "a"]).await;
+    assert_eq!(client.run(&["LREM", "mylist", "0", "a"]).await, int(2));
+    assert_eq!(
+        client.run(&["LRANGE", "mylist", "0", "-1"]).await,
+        array(&["b", "c"])
+    );
+    assert_eq!(client.run(&["LREM", "missing", "0", "a"]).await, int(0));
+    client.run(&["RPUSH", "same", "x", "x"]).await;
+    assert_eq!(client.run(&["LREM", "same", "0", "x"]).await, int(2));
+    assert_eq!(client.run(&["EXISTS", "same"]).await, int(0));
+}
+
+#[tokio::test]
+async fn ltrim() {
+    let mut client = Client::connect(start().await).await;
+    client
+        .run(&["RPUSH", "mylist", "one", "two", "three"])
+        .await;
+    assert_eq!(
+        client.run(&["LTRIM", "mylist", "1", "-1"]).await,
+        simple("OK")
+    );
+    assert_eq!(
+        client.run(&["LRANGE", "mylist", "0", "-1"]).await,
+        array(&["two", "three"])
+    );
+    client.run(&["RPUSH", "mylist", "four", "five"]).await;
+    assert_eq!(
+        client.run(&["LTRIM", "mylist", "-100", "2"]).await,
+        simple("OK")
+    );
+    assert_eq!(
+        client.run(&["LRANGE", "mylist", "0", "-1"]).await,
+        array(&["two", "three", "four"])
+    );
+    assert_eq!(
+        client.run(&["LTRIM", "mylist", "1", "1"]).await,
+        simple("OK")
+    );
+    assert_eq!(
+        client.run(&["LRANGE", "mylist", "0", "-1"]).await,
+        array(&["three"])
+    );
+    // an empty range removes the key
+    assert_eq!(
+        client.run(&["LTRIM", "mylist", "5", "10"]).await,
+        simple("OK")
+    );
+    assert_eq!(client.run(&["EXISTS", "mylist"]).await, int(0));
+    assert_eq!(
+        client.run(&["LTRIM", "missing", "0", "1"]).await,
+        simple("OK")
+    );
+    assert_eq!(client.run(&["EXISTS", "missing"]).await, int(0));
+}
+
+#[tokio::test]
+async fn lpos() {
+    let mut client = Client::connect(start().await).await;
+    client
+        .run(&[
+            "RPUSH", "mylist", "a", "b", "c", "d", "1", "2", "3", "4", "3", "3", "3",
+        ])
+        .await;
+    assert_eq!(client.run(&["LPOS", "mylist", "3"]).await, int(6));
+    assert_eq!(
+        client
+            .run(&["LPOS", "mylist", "3", "COUNT", "0", "RANK", "2"])
+            .await,
+        ints(&[8, 9, 10])
+    );
+    client.run(&["DEL", "mylist"]).await;
+    client
+        .run(&["RPUSH", "mylist", "a", "b", "c", "1", "2", "3", "c", "c"])
+        .await;
+    let cases: &[(&[&str], RedirsValue)] = &[
+        (&[], int(2)),
+        (&["RANK", "2"], int(6)),
+        (&["RANK", "-1"], int(7)),
+        (&["RANK", "-3"], int(2)),
+        (&["RANK", "4"], nil()),
+        (&["COUNT", "2"], ints(&[2, 6])),
+        (&["RANK", "-1", "COUNT", "2"], ints(&[7, 6])),
+        (&["COUNT", "0"], ints(&[2, 6, 7])),
+        (&["COUNT", "9"], ints(&[2, 6, 7])),
+        (&["COUNT", "0", "RANK", "2"], ints(&[6, 7])),
+        (&["COUNT", "0", "MAXLEN", "3"], ints(&[2])),
+        (&["MAXLEN", "2"], nil()),
+        (&["MAXLEN", "0"], int(2)),
+        (&["RANK", "-1", "MAXLEN", "1"], int(7)),
+        (&["RANK", "-1", "COUNT", "0", "MAXLEN", "3"], ints(&[7, 6])),
+        (&["RANK", "5", "COUNT", "0"], ints(&[])),
+    ];
+    for (opts, expected) in cases {
+        let args: Vec<&str> = ["LPOS", "mylist", "c"]
+            .iter()
+            .chain(*opts)
+            .copied()
+            .collect();
+        assert_eq!(&client.run(&args).await, expected, "{opts:?}");
+    }
+    assert_eq!(client.run(&["LPOS", "mylist", "z"]).await, nil());
+    assert_eq!(client.run(&["LPOS", "missing", "c"]).await, nil());
+    assert_eq!(
+        client.run(&["LPOS", "missing", "c", "COUNT", "1"]).await,
+        ints(&[])
+    );
+}
+
+#[tokio::test]
+async fn lpos_errors() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["RPUSH", "mylist", "a"]).await;
+    let cases: &[(&[&str], &str)] = &[
+        (&["RANK", "0"], "ERR RANK can't be zero: use 1 to start from the first match, 2 from the second ... or use negative to start from the end of the list"),
+        (&["RANK", "-9223372036854775808"], "ERR value is out of range, value must between -9223372036854775807 and 9223372036854775807"),
+        (&["COUNT", "-1"], "ERR COUNT can't be negative"),
+        (&["MAXLEN", "-1"], "ERR MAXLEN can't be negative"),
+        (&["RANK", "x"], "ERR value is not an integer or out of range"),
+        (&["RANK"], "ERR syntax error"),
+        (&["NOPE", "x"], "ERR syntax error"),
+    ];
+    for (opts, expected) in cases {
+        let args: Vec<&str> = ["LPOS", "mylist", "a"]
+            .iter()
+            .chain(*opts)
+            .copied()
+            .collect();
+        assert_eq!(client.run(&args).await, error(expected), "{opts:?}");
+    }
+}
@@ -0,0 +1,127 @@
+mod common;
+
+use common::{array, bulk, error, int, nil, simple, start, Client};
+use protocol::RedirsValue;
+
+#[tokio::test]
+async fn pushes_return_the_new_length() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(client.run(&["RPUSH", "list", "b", "c"]).await, int(2));
+    assert_eq!(client.run(&["LPUSH", "list", "a", "z"]).await, int(4));
+    assert_eq!(
+        client.run(&["LRANGE", "list", "0", "-1"]).await,
+        array(&["z", "a", "b", "c"])
+    );
+    assert_eq!(client.run(&["LLEN", "list"]).await, int(4));
+    assert_eq!(client.run(&["LLEN", "missing"]).await, int(0));
+    assert_eq!(client.run(&["TYPE", "list"]).await, simple("list"));
+}
+
+#[tokio::test]
+async fn pops_with_and_without_a_count() {
+    let mut client = Client::connect(start().await).await;
+    client
+        .run(&["RPUSH", "list", "a", "b", "c", "d", "e"])
+        .await;
+    assert_eq!(client.run(&["LPOP", "list"]).await, bulk("a"));
+    assert_eq!(client.run(&["RPOP", "list"]).await, bulk("e"));
+    assert_eq!(client.run(&["LPOP", "list", "2"]).await, array(&["b", "c"]));
+    assert_eq!(client.run(&["LPOP", "list", "0"]).await, array(&[]));
+    assert_eq!(client.run(&["RPOP", "list", "5"]).await, array(&["d"]));
+    assert_eq!(client.run(&["LPOP", "list"]).await, nil());
+    assert_eq!(
+        client.run(&["RPOP", "list", "1"]).await,
+        RedirsValue::Array(None)
+    );
+    assert_eq!(
+        client.run(&["LPOP", "list", "-1"]).await,
+        error("ERR value is out of range, must be positive")
+    );
+    assert_eq!(
+        client.run(&["LPOP", "list", "1", "2"]).await,
+        error("ERR wrong number of arguments for 'lpop' command")
+    );
+}
+
+#[tokio::test]
+async fn popping_the_last_element_deletes_the_key() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["RPUSH", "one", "a"]).await;
+    assert_eq!(client.run(&["RPOP", "one"]).await, bulk("a"));
+    assert_eq!(client.run(&["EXISTS", "one"]).await, int(0));
+    assert_eq!(client.run(&["TYPE", "one"]).await, simple("none"));
+    client.run(&["RPUSH", "many", "a", "b"]).await;
+    assert_eq!(
+        client.run(&["LPOP", "many", "10"]).await,
+        array(&["a", "b"])
+    );
+    assert_eq!(client.run(&["EXISTS", "many"]).await, int(0));
+    // the key starts over as a list when pushed again
+    assert_eq!(client.run(&["LPUSH", "many", "c"]).await, int(1));
+}
+
+#[tokio::test]
+async fn lrange_index_matrix() {
+    let mut client = Client::connect(start().await).await;
+    client
+        .run(&["RPUSH", "list", "a", "b", "c", "d", "e"])
+        .await;
+    let cases: &[(&str, &str, &[&str])] = &[
+        ("0", "-1", &["a", "b", "c", "d", "e"]),
+        ("0", "0", &["a"]),
+        ("1", "3", &["b", "c", "d"]),
+        ("-1", "-1", &["e"]),
+        ("-2", "-1", &["d", "e"]),
+        ("-3", "3", &["c", "d"]),
+        ("1", "-2", &["b", "c", "d"]),
+        ("-100", "1", &["a", "b"]),
+        ("-100", "-100", &[]),
+        ("-100", "100", &["a", "b", "c", "d", "e"]),
+        ("2", "100", &["c", "d", "e"]),
+        ("4", "4", &["e"]),
+        ("5", "10", &[]),
+        ("3", "1", &[]),
+        ("-1", "-2", &[]),
+        ("0", "-6", &[]),
+    ];
+    for (start, stop, expected) in cases {
+        assert_eq!(
+            client.run(&["LRANGE", "list", start, stop]).await,
+            array(expected),
+            "LRANGE {start} {stop}"
+        );
+    }
+    assert_eq!(
+        client.run(&["LRANGE", "missing", "0", "-1"]).await,
+        array(&[])
+    );
+    assert_eq!(
+        client.run(&["LRANGE", "list", "a", "1"]).await,
+        error("ERR value is not an integer or out of range")
+    );
+}
+
+#[tokio::test]
+async fn list_commands_on_a_string_are_wrongtype() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "string", "v"]).await;
+    let wrongtype = error("WRONGTYPE Operation against a key holding the wrong kind of value");
+    for args in [
+        &["LPUSH", "string", "a"][..],
+        &["RPUSH", "string", "a"],
+        &["LPOP", "string"],
+        &["RPOP", "string", "2"],
+        &["LRANGE", "string", "0", "-1"],
+        &["LLEN", "string"],
+        &["LINSERT", "string", "BEFORE", "a", "b"],
+        &["LSET", "string", "0", "a"],
+        &["LREM", "string", "0", "a"],
+        &["LTRIM", "string", "0", "1"],
+        &["LPOS", "string", "a"],
+    ] {
+        assert_eq!(client.run(args).await, wrongtype, "{args:?}");
+    }
+    assert_eq!(client.run(&["GET", "string"]).await, bulk("v"));
+    client.run(&["RPUSH", "list", "a"]).await;
+    assert_eq!(client.run(&["GET", "list"]).await, wrongtype);
+}
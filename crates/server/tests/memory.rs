@@ -0,0 +1,102 @@
+mod common;
+
+use common::{error, nil, start, Client};
+use protocol::RedirsValue;
+
+async fn usage(client: &mut Client, args: &[&str]) -> i64 {
+    let mut command = vec!["MEMORY", "USAGE"];
+    command.extend_from_slice(args);
+    match client.run(&command).await {
+        RedirsValue::Integer(bytes) => bytes,
+        other => panic!("expected a size, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn usage_grows_with_the_value() {
+    let mut client = Client::connect(start().await).await;
+    let long = "x".repeat(1000);
+    client.run(&["SET", "a", "x"]).await;
+    client.run(&["SET", "b", &long]).await;
+    let short = usage(&mut client, &["a"]).await;
+    assert!(short > 0);
+    assert!(usage(&mut client, &["b"]).await >= short + 999);
+    // the key itself counts too
+    client.run(&["SET", &"k".repeat(500), "x"]).await;
+    assert!(usage(&mut client, &[&"k".repeat(500)]).await >= short + 500);
+    assert_eq!(client.run(&["MEMORY", "USAGE", "missing"]).await, nil());
+}
+
+#[tokio::test]
+async fn usage_of_every_type() {
+    let mut client = Client::connect(start().await).await;
+    let writes: &[(&str, &[&str])] = &[
+        ("list", &["RPUSH", "list"]),
+        ("hash", &["HSET", "hash"]),
+        ("set", &["SADD", "set"]),
+        ("zset", &["ZADD", "zset"]),
+    ];
+    for (key, write) in writes {
+        let mut small = write.to_vec();
+        small.extend(["1", "a"]);
+        client.run(&small).await;
+        let before = usage(&mut client, &[key]).await;
+        let mut big = write.to_vec();
+        let items: Vec<String> = (0..100).map(|i| format!("{i:0>20}")).collect();
+        for item in &items {
+            big.extend([item.as_str(), item.as_str()]);
+        }
+        client.run(&big).await;
+        let after = usage(&mut client, &[key]).await;
+        assert!(after > before + 2000, "{key}: {before} then {after}");
+    }
+    client.run(&["XADD", "stream", "*", "f", "v"]).await;
+    let before = usage(&mut client, &["stream"]).await;
+    for _ in 0..100 {
+        client
+            .run(&["XADD", "stream", "*", "field", &"v".repeat(20)])
+            .await;
+    }
+    assert!(usage(&mut client, &["stream"]).await > before + 2000);
+}
+
+#[tokio::test]
+async fn usage_samples() {
+    let mut client = Client::connect(start().await).await;
+    // the first elements small, the rest far bigger
+    client.run(&["RPUSH", "l", "a", "b", "c", "d", "e"]).await;
+    let big = "x".repeat(1000);
+    for _ in 0..20 {
+        client.run(&["RPUSH", "l", &big]).await;
+    }
+    let sampled = usage(&mut client, &["l"]).await;
+    assert_eq!(usage(&mut client, &["l", "SAMPLES", "5"]).await, sampled);
+    let all = usage(&mut client, &["l", "SAMPLES", "0"]).await;
+    assert!(all > sampled + 10_000, "{sampled} then {all}");
+    assert_eq!(usage(&mut client, &["l", "samples", "1000"]).await, all);
+}
+
+#[tokio::test]
+async fn usage_errors() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "k", "v"]).await;
+    assert_eq!(
+        client.run(&["MEMORY", "USAGE", "k", "SAMPLES", "x"]).await,
+        error("ERR value is not an integer or out of range")
+    );
+    for args in [
+        &["MEMORY", "USAGE", "k", "SAMPLES", "-1"][..],
+        &["MEMORY", "USAGE", "k", "SAMPLES"],
+        &["MEMORY", "USAGE", "k", "COUNT", "1"],
+    ] {
+        assert_eq!(client.run(args).await, error("ERR syntax error"));
+    }
+    assert_eq!(
+        client.run(&["MEMORY", "USAGE"]).await,
+        error("ERR wrong number of arguments for 'memory|usage' command")
+    );
+    assert_eq!(
+        client.run(&["MEMORY", "DOCTOR"]).await,
+        error("ERR unknown subcommand 'DOCTOR'. Try MEMORY HELP.")
+    );
+}
@@ -0,0 +1,198 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{array, int, start_with, Client};
+use protocol::RedirsValue;
+use server::{active_expiry, Db, KeyspaceEvents, ManualClock};
+
+// a server publishing the events `flags` asks for, with a client subscribed to
+// `channels` and another one to write with
+async fn serve(flags: &str, channels: &[&str]) -> (Client, Client, ManualClock, Db) {
+    let clock = ManualClock::new(1000);
+    let db = Db::with_clock(clock.clone());
+    db.set_keyspace_events(KeyspaceEvents::parse(flags.as_bytes()).unwrap());
+    let addr = start_with(db.clone()).await;
+    let mut subscriber = Client::connect(addr).await;
+    let mut subscribe = vec!["PSUBSCRIBE"];
+    subscribe.extend(channels);
+    subscriber.run(&subscribe).await;
+    for _ in 1..channels.len() {
+        subscriber.reply().await;
+    }
+    (subscriber, Client::connect(addr).await, clock, db)
+}
+
+fn pmessage(pattern: &str, channel: &str, message: &str) -> RedirsValue {
+    array(&["pmessage", pattern, channel, message])
+}
+
+// the key of each keyevent message, with the event
+async fn keyevents(subscriber: &mut Client, count: usize) -> Vec<(String, String)> {
+    let mut events = Vec::new();
+    for _ in 0..count {
+        let RedirsValue::Array(Some(message)) = subscriber.reply().await else {
+            panic!("not a message");
+        };
+        let text = |value: &RedirsValue| match value {
+            RedirsValue::BulkString(Some(bytes)) => String::from_utf8(bytes.to_vec()).unwrap(),
+            _ => panic!("not a bulk string"),
+        };
+        let event = text(&message[2])
+            .trim_start_matches("__keyevent@0__:")
+            .to_owned();
+        events.push((event, text(&message[3])));
+    }
+    events
+}
+
+fn pairs(events: &[(&str, &str)]) -> Vec<(String, String)> {
+    events
+        .iter()
+        .map(|(event, key)| (event.to_string(), key.to_string()))
+        .collect()
+}
+
+#[test]
+fn flags_parse_and_show_the_way_redis_does() {
+    for (flags, shown) in [
+        ("", ""),
+        ("KEA", "AKE"),
+        ("Kg$lshzxet", "AK"),
+        ("E$l", "$lE"),
+        ("xKg", "gxK"),
+    ] {
+        let events = KeyspaceEvents::parse(flags.as_bytes()).unwrap();
+        assert_eq!(events.to_string(), shown, "{flags}");
+    }
+    assert_eq!(KeyspaceEvents::parse(b"KEq"), None);
+    assert_eq!(KeyspaceEvents::parse(b""), Some(KeyspaceEvents::NONE));
+}
+
+#[tokio::test]
+async fn both_channels_carry_the_event() {
+    let (mut subscriber, mut client, _, _) = serve("KEA", &["__key*__:*"]).await;
+    client.run(&["SET", "foo", "bar"]).await;
+    assert_eq!(
+        subscriber.reply().await,
+        pmessage("__key*__:*", "__keyspace@0__:foo", "set")
+    );
+    assert_eq!(
+        subscriber.reply().await,
+        pmessage("__key*__:*", "__keyevent@0__:set", "foo")
+    );
+}
+
+#[tokio::test]
+async fn writes_are_named_after_their_commands() {
+    let (mut subscriber, mut client, _, _) = serve("EA", &["__keyevent@0__:*"]).await;
+    for args in [
+        &["SET", "s", "1"][..],
+        &["INCRBY", "s", "2"],
+        &["APPEND", "s", "x"],
+        &["MSET", "a", "1", "b", "2"],
+        &["RPUSH", "l", "x", "y"],
+        &["LPOP", "l", "2"],
+        &["HSET", "h", "f", "v"],
+        &["SADD", "set", "m"],
+        &["ZADD", "z", "1", "m"],
+        &["ZINCRBY", "z", "1", "m"],
+        &["XADD", "x", "1-0", "f", "v"],
+        &["EXPIRE", "a", "100"],
+        &["PERSIST", "a"],
+        &["RENAME", "a", "c"],
+        &["DEL", "b", "c", "missing"],
+    ] {
+        client.run(args).await;
+    }
+    assert_eq!(
+        keyevents(&mut subscriber, 19).await,
+        pairs(&[
+            ("set", "s"),
+            ("incrby", "s"),
+            ("append", "s"),
+            ("set", "a"),
+            ("set", "b"),
+            ("rpush", "l"),
+            // the list goes once it is empty
+            ("lpop", "l"),
+            ("del", "l"),
+            ("hset", "h"),
+            ("sadd", "set"),
+            ("zadd", "z"),
+            ("zincr", "z"),
+            ("xadd", "x"),
+            ("expire", "a"),
+            ("persist", "a"),
+            ("rename_from", "a"),
+            ("rename_to", "c"),
+            ("del", "b"),
+            ("del", "c"),
+        ])
+    );
+}
+
+#[tokio::test]
+async fn reads_and_other_classes_publish_nothing() {
+    // generic events only
+    let (mut subscriber, mut client, _, _) = serve("Eg", &["__keyevent@0__:*"]).await;
+    for args in [
+        &["SET", "s", "1"][..],
+        &["GET", "s"],
+        &["PFADD", "hll", "a"],
+        &["PFCOUNT", "hll"],
+        &["RPUSH", "l", "x"],
+        &["LRANGE", "l", "0", "-1"],
+        &["XADD", "x", "1-0", "f", "v"],
+        &["XGROUP", "CREATE", "x", "g", "0"],
+        &["XREADGROUP", "GROUP", "g", "c", "STREAMS", "x", ">"],
+        &["DEL", "s"],
+    ] {
+        client.run(args).await;
+    }
+    assert_eq!(keyevents(&mut subscriber, 1).await, pairs(&[("del", "s")]));
+}
+
+#[tokio::test]
+async fn nothing_is_published_without_k_or_e() {
+    let (mut subscriber, mut client, _, db) = serve("A", &["__key*"]).await;
+    client.run(&["SET", "k", "v"]).await;
+    db.set_keyspace_events(KeyspaceEvents::parse(b"Ex").unwrap());
+    assert_eq!(db.keyspace_events().to_string(), "xE");
+    client.run(&["SET", "k", "v", "PX", "1"]).await;
+    client.run(&["PEXPIRE", "k", "0"]).await;
+    let mut publisher = client;
+    publisher.run(&["PUBLISH", "__keyplain", "done"]).await;
+    assert_eq!(
+        subscriber.reply().await,
+        pmessage("__key*", "__keyplain", "done")
+    );
+}
+
+#[tokio::test]
+async fn expired_keys_are_notified_when_read() {
+    let (mut subscriber, mut client, clock, _) = serve("Ex", &["__keyevent@0__:expired"]).await;
+    client.run(&["SET", "short", "v", "PX", "50"]).await;
+    clock.advance(Duration::from_millis(51));
+    assert_eq!(client.run(&["GET", "short"]).await, common::nil());
+    assert_eq!(
+        subscriber.reply().await,
+        pmessage("__keyevent@0__:expired", "__keyevent@0__:expired", "short")
+    );
+}
+
+#[tokio::test]
+async fn expired_keys_are_notified_by_active_expiry() {
+    let (mut subscriber, mut client, clock, db) = serve("Ex", &["__keyevent@0__:expired"]).await;
+    client.run(&["SET", "short", "v", "PX", "50"]).await;
+    assert_eq!(client.run(&["PTTL", "short"]).await, int(50));
+    clock.advance(Duration::from_millis(51));
+    tokio::spawn(active_expiry(db, Duration::from_millis(5), 20));
+    let message = tokio::time::timeout(Duration::from_secs(5), subscriber.reply())
+        .await
+        .expect("the key expired");
+    assert_eq!(
+        message,
+        pmessage("__keyevent@0__:expired", "__keyevent@0__:expired", "short")
+    );
+}
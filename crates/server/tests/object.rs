@@ -0,0 +1,104 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{bulk, error, int, nil, start, start_with, Client};
+use server::{Db, ManualClock};
+
+#[tokio::test]
+async fn encoding_of_every_type() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "string", "1"]).await;
+    client.run(&["RPUSH", "list", "a"]).await;
+    client.run(&["HSET", "hash", "f", "v"]).await;
+    client.run(&["SADD", "set", "1"]).await;
+    client.run(&["ZADD", "zset", "1", "a"]).await;
+    client.run(&["XADD", "stream", "*", "f", "v"]).await;
+    for (key, encoding) in [
+        ("string", "raw"),
+        ("list", "linkedlist"),
+        ("hash", "hashtable"),
+        ("set", "hashtable"),
+        ("zset", "skiplist"),
+        ("stream", "stream"),
+    ] {
+        assert_eq!(
+            client.run(&["OBJECT", "ENCODING", key]).await,
+            bulk(encoding),
+            "{key}"
+        );
+    }
+    assert_eq!(client.run(&["OBJECT", "ENCODING", "missing"]).await, nil());
+}
+
+#[tokio::test]
+async fn encoding_stays_as_values_grow() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["HSET", "hash", "f", "v"]).await;
+    let small = client.run(&["OBJECT", "ENCODING", "hash"]).await;
+    for i in 0..200 {
+        let field = format!("field{i}");
+        client
+            .run(&["HSET", "hash", &field, &"v".repeat(100)])
+            .await;
+    }
+    assert_eq!(client.run(&["OBJECT", "ENCODING", "hash"]).await, small);
+}
+
+#[tokio::test]
+async fn idletime_follows_accesses() {
+    let clock = ManualClock::new(1_700_000_000_000);
+    let mut client = Client::connect(start_with(Db::with_clock(clock.clone())).await).await;
+    client.run(&["SET", "k", "v"]).await;
+    client.run(&["RPUSH", "l", "a"]).await;
+    assert_eq!(client.run(&["OBJECT", "IDLETIME", "k"]).await, int(0));
+    clock.advance(Duration::from_secs(10));
+    assert_eq!(client.run(&["OBJECT", "IDLETIME", "k"]).await, int(10));
+    // looking at a key is not an access to it
+    client.run(&["TYPE", "k"]).await;
+    client.run(&["MEMORY", "USAGE", "k"]).await;
+    assert_eq!(client.run(&["OBJECT", "IDLETIME", "k"]).await, int(10));
+    // reads and writes are
+    client.run(&["GET", "k"]).await;
+    client.run(&["LPUSH", "l", "b"]).await;
+    clock.advance(Duration::from_secs(3));
+    assert_eq!(client.run(&["OBJECT", "IDLETIME", "k"]).await, int(3));
+    assert_eq!(client.run(&["OBJECT", "IDLETIME", "l"]).await, int(3));
+    client.run(&["EXISTS", "k"]).await;
+    assert_eq!(client.run(&["OBJECT", "IDLETIME", "k"]).await, int(0));
+    assert_eq!(client.run(&["OBJECT", "IDLETIME", "missing"]).await, nil());
+}
+
+#[tokio::test]
+async fn refcount_and_freq() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "k", "v"]).await;
+    assert_eq!(client.run(&["OBJECT", "REFCOUNT", "k"]).await, int(1));
+    assert_eq!(client.run(&["OBJECT", "REFCOUNT", "missing"]).await, nil());
+    assert_eq!(
+        client.run(&["OBJECT", "FREQ", "k"]).await,
+        error(
+            "ERR An LFU maxmemory policy is not selected, access frequency not tracked. \
+             Please note that when switching between policies at runtime LRU and LFU data \
+             will take some time to adjust."
+        )
+    );
+    assert_eq!(client.run(&["OBJECT", "FREQ", "missing"]).await, nil());
+}
+
+#[tokio::test]
+async fn object_errors() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        client.run(&["OBJECT", "ENCODING"]).await,
+        error("ERR wrong number of arguments for 'object|encoding' command")
+    );
+    assert_eq!(
+        client.run(&["OBJECT", "IDLETIME", "a", "b"]).await,
+        error("ERR wrong number of arguments for 'object|idletime' command")
+    );
+    assert_eq!(
+        client.run(&["OBJECT", "LRU", "k"]).await,
+        error("ERR unknown subcommand 'LRU'. Try OBJECT HELP.")
+    );
+}
@@ -0,0 +1,339 @@
+mod common;
+
+use std::time::Duration;
+
+use common::{array, bulk, error, int, nil, simple, start, Client};
+use protocol::RedirsValue;
+
+// [kind, channel, count] or [message, channel, payload], a push under RESP3
+// and a plain array under RESP2
+fn event(kind: &str, channel: &str, last: RedirsValue) -> RedirsValue {
+    RedirsValue::Array(Some(vec![bulk(kind), bulk(channel), last]))
+}
+
+fn push(kind: &str, channel: &str, last: RedirsValue) -> RedirsValue {
+    RedirsValue::Push(vec![bulk(kind), bulk(channel), last])
+}
+
+#[tokio::test]
+async fn messages_reach_every_subscriber() {
+    let addr = start().await;
+    let mut publisher = Client::connect(addr).await;
+    let mut first = Client::connect(addr).await;
+    let mut second = Client::connect(addr).await;
+    assert_eq!(
+        first.run(&["SUBSCRIBE", "news", "sport"]).await,
+        event("subscribe", "news", int(1))
+    );
+    assert_eq!(first.reply().await, event("subscribe", "sport", int(2)));
+    assert_eq!(
+        second.run(&["SUBSCRIBE", "news"]).await,
+        event("subscribe", "news", int(1))
+    );
+    // subscribing again changes nothing
+    assert_eq!(
+        second.run(&["SUBSCRIBE", "news"]).await,
+        event("subscribe", "news", int(1))
+    );
+
+    assert_eq!(publisher.run(&["PUBLISH", "news", "hello"]).await, int(2));
+    assert_eq!(publisher.run(&["PUBLISH", "sport", "goal"]).await, int(1));
+    assert_eq!(publisher.run(&["PUBLISH", "weather", "rain"]).await, int(0));
+    assert_eq!(first.reply().await, event("message", "news", bulk("hello")));
+    assert_eq!(first.reply().await, event("message", "sport", bulk("goal")));
+    assert_eq!(
+        second.reply().await,
+        event("message", "news", bulk("hello"))
+    );
+
+    assert_eq!(
+        first.run(&["UNSUBSCRIBE", "news"]).await,
+        event("unsubscribe", "news", int(1))
+    );
+    assert_eq!(publisher.run(&["PUBLISH", "news", "again"]).await, int(1));
+    assert_eq!(
+        second.reply().await,
+        event("message", "news", bulk("again"))
+    );
+    // a connection going away leaves its channels
+    drop(second);
+    let mut receivers = int(1);
+    for _ in 0..100 {
+        receivers = publisher.run(&["PUBLISH", "news", "bye"]).await;
+        if receivers == int(0) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(receivers, int(0));
+}
+
+#[tokio::test]
+async fn resp2_subscribers_are_limited_to_the_subscribe_commands() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SUBSCRIBE", "a", "b"]).await;
+    client.reply().await;
+    assert_eq!(
+        client.run(&["GET", "k"]).await,
+        error("ERR Can't execute 'get': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context")
+    );
+    assert_eq!(
+        client.run(&["xadd", "s", "*", "f", "v"]).await,
+        error("ERR Can't execute 'xadd': only (P|S)SUBSCRIBE / (P|S)UNSUBSCRIBE / PING / QUIT / RESET are allowed in this context")
+    );
+    assert_eq!(client.run(&["PING"]).await, array(&["pong", ""]));
+    assert_eq!(client.run(&["PING", "hi"]).await, array(&["pong", "hi"]));
+    // without channels every subscription goes
+    assert_eq!(
+        client.run(&["UNSUBSCRIBE"]).await,
+        event("unsubscribe", "a", int(1))
+    );
+    assert_eq!(client.reply().await, event("unsubscribe", "b", int(0)));
+    assert_eq!(
+        client.run(&["UNSUBSCRIBE"]).await,
+        RedirsValue::Array(Some(vec![bulk("unsubscribe"), nil(), int(0)]))
+    );
+    assert_eq!(client.run(&["GET", "k"]).await, nil());
+    assert_eq!(client.run(&["PING"]).await, simple("PONG"));
+}
+
+#[tokio::test]
+async fn resp3_subscribers_get_pushes_and_keep_issuing_commands() {
+    let addr = start().await;
+    let mut publisher = Client::connect(addr).await;
+    let mut subscriber = Client::connect(addr).await;
+    subscriber.run(&["HELLO", "3"]).await;
+    assert_eq!(
+        subscriber.run(&["SUBSCRIBE", "news"]).await,
+        push("subscribe", "news", int(1))
+    );
+    assert_eq!(subscriber.run(&["SET", "k", "v"]).await, simple("OK"));
+    assert_eq!(publisher.run(&["PUBLISH", "news", "hello"]).await, int(1));
+    assert_eq!(
+        subscriber.reply().await,
+        push("message", "news", bulk("hello"))
+    );
+    assert_eq!(subscriber.run(&["GET", "k"]).await, bulk("v"));
+    assert_eq!(subscriber.run(&["PING"]).await, simple("PONG"));
+    assert_eq!(
+        subscriber.run(&["UNSUBSCRIBE", "news", "other"]).await,
+        push("unsubscribe", "news", int(0))
+    );
+    assert_eq!(
+        subscriber.reply().await,
+        push("unsubscribe", "other", int(0))
+    );
+}
+
+#[tokio::test]
+async fn a_subscriber_that_stops_reading_does_not_hold_up_publishers() {
+    let addr = start().await;
+    let mut publisher = Client::connect(addr).await;
+    let mut idle = Client::connect(addr).await;
+    let mut reading = Client::connect(addr).await;
+    idle.run(&["SUBSCRIBE", "firehose"]).await;
+    reading.run(&["SUBSCRIBE", "firehose"]).await;
+    let payload = "x".repeat(16 * 1024);
+    let reader = tokio::spawn(async move {
+        for _ in 0..3000 {
+            reading.reply().await;
+        }
+        reading
+    });
+    // far more than the idle subscriber's socket and mailbox take in
+    let publishing = async {
+        for _ in 0..3000 {
+            publisher.run(&["PUBLISH", "firehose", &payload]).await;
+        }
+    };
+    tokio::time::timeout(Duration::from_secs(10), publishing)
+        .await
+        .expect("publishing went on");
+    let _reading = tokio::time::timeout(Duration::from_secs(10), reader)
+        .await
+        .expect("the reading subscriber got everything")
+        .unwrap();
+    // the idle one was hung up on past what it had been sent
+    let drained = async { while idle.stream.read_value().await.is_ok() {} };
+    tokio::time::timeout(Duration::from_secs(10), drained)
+        .await
+        .expect("the idle subscriber was disconnected");
+    assert_eq!(publisher.run(&["PUBLISH", "firehose", "x"]).await, int(1));
+}
+
+#[tokio::test]
+async fn quit_closes_the_connection() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(client.run(&["QUIT"]).await, simple("OK"));
+    assert!(client.stream.read_value().await.is_err());
+}
+
+fn pmessage(pattern: &str, channel: &str, payload: &str) -> RedirsValue {
+    array(&["pmessage", pattern, channel, payload])
+}
+
+#[tokio::test]
+async fn pattern_subscribers_get_the_channels_matching() {
+    let addr = start().await;
+    let mut publisher = Client::connect(addr).await;
+    let mut both = Client::connect(addr).await;
+    let mut other = Client::connect(addr).await;
+    assert_eq!(
+        both.run(&["PSUBSCRIBE", "news.*"]).await,
+        event("psubscribe", "news.*", int(1))
+    );
+    // the count takes in channels and patterns
+    assert_eq!(
+        both.run(&["SUBSCRIBE", "news.tech"]).await,
+        event("subscribe", "news.tech", int(2))
+    );
+    assert_eq!(
+        other.run(&["PSUBSCRIBE", "news.*", "*.tech"]).await,
+        event("psubscribe", "news.*", int(1))
+    );
+    assert_eq!(other.reply().await, event("psubscribe", "*.tech", int(2)));
+
+    // once for each subscription matching
+    assert_eq!(
+        publisher.run(&["PUBLISH", "news.tech", "rust"]).await,
+        int(4)
+    );
+    assert_eq!(
+        both.reply().await,
+        event("message", "news.tech", bulk("rust"))
+    );
+    assert_eq!(both.reply().await, pmessage("news.*", "news.tech", "rust"));
+    let mut received = vec![other.reply().await, other.reply().await];
+    received.sort();
+    let mut expected = vec![
+        pmessage("news.*", "news.tech", "rust"),
+        pmessage("*.tech", "news.tech", "rust"),
+    ];
+    expected.sort();
+    assert_eq!(received, expected);
+
+    assert_eq!(
+        publisher.run(&["PUBLISH", "news.art", "paint"]).await,
+        int(2)
+    );
+    assert_eq!(both.reply().await, pmessage("news.*", "news.art", "paint"));
+    assert_eq!(other.reply().await, pmessage("news.*", "news.art", "paint"));
+    assert_eq!(publisher.run(&["PUBLISH", "sport", "goal"]).await, int(0));
+
+    assert_eq!(
+        other.run(&["PUNSUBSCRIBE"]).await,
+        event("punsubscribe", "*.tech", int(1))
+    );
+    assert_eq!(other.reply().await, event("punsubscribe", "news.*", int(0)));
+    // unsubscribing from patterns leaves the channels alone
+    assert_eq!(
+        both.run(&["PUNSUBSCRIBE", "news.*", "missing"]).await,
+        event("punsubscribe", "news.*", int(1))
+    );
+    assert_eq!(both.reply().await, event("punsubscribe", "missing", int(1)));
+    assert_eq!(
+        both.run(&["PUNSUBSCRIBE"]).await,
+        RedirsValue::Array(Some(vec![bulk("punsubscribe"), nil(), int(1)]))
+    );
+    assert_eq!(
+        publisher.run(&["PUBLISH", "news.tech", "again"]).await,
+        int(1)
+    );
+    assert_eq!(
+        both.reply().await,
+        event("message", "news.tech", bulk("again"))
+    );
+}
+
+#[tokio::test]
+async fn resp3_pattern_messages_are_pushes() {
+    let addr = start().await;
+    let mut publisher = Client::connect(addr).await;
+    let mut subscriber = Client::connect(addr).await;
+    subscriber.run(&["HELLO", "3"]).await;
+    assert_eq!(
+        subscriber.run(&["PSUBSCRIBE", "h?llo"]).await,
+        push("psubscribe", "h?llo", int(1))
+    );
+    assert_eq!(publisher.run(&["PUBLISH", "hello", "world"]).await, int(1));
+    assert_eq!(
+        subscriber.reply().await,
+        RedirsValue::Push(vec![
+            bulk("pmessage"),
+            bulk("h?llo"),
+            bulk("hello"),
+            bulk("world")
+        ])
+    );
+}
+
+#[tokio::test]
+async fn pubsub_reports_the_subscriptions() {
+    let addr = start().await;
+    let mut admin = Client::connect(addr).await;
+    let mut first = Client::connect(addr).await;
+    let mut second = Client::connect(addr).await;
+    let mut patterns = Client::connect(addr).await;
+    assert_eq!(admin.run(&["PUBSUB", "CHANNELS"]).await, array(&[]));
+    assert_eq!(admin.run(&["PUBSUB", "NUMPAT"]).await, int(0));
+    first.run(&["SUBSCRIBE", "news.tech", "sport"]).await;
+    first.reply().await;
+    second.run(&["SUBSCRIBE", "news.tech"]).await;
+    // the same pattern from two connections counts once
+    first.run(&["PSUBSCRIBE", "news.*"]).await;
+    patterns.run(&["PSUBSCRIBE", "news.*", "*"]).await;
+    patterns.reply().await;
+
+    let mut channels = admin.run(&["PUBSUB", "CHANNELS"]).await;
+    if let RedirsValue::Array(Some(names)) = &mut channels {
+        names.sort();
+    }
+    assert_eq!(channels, array(&["news.tech", "sport"]));
+    assert_eq!(
+        admin.run(&["PUBSUB", "CHANNELS", "news.*"]).await,
+        array(&["news.tech"])
+    );
+    assert_eq!(admin.run(&["PUBSUB", "CHANNELS", "x*"]).await, array(&[]));
+    assert_eq!(
+        admin
+            .run(&["PUBSUB", "NUMSUB", "sport", "news.tech", "missing", "sport"])
+            .await,
+        RedirsValue::Array(Some(vec![
+            bulk("sport"),
+            int(1),
+            bulk("news.tech"),
+            int(2),
+            bulk("missing"),
+            int(0),
+            bulk("sport"),
+            int(1),
+        ]))
+    );
+    assert_eq!(admin.run(&["PUBSUB", "NUMSUB"]).await, array(&[]));
+    assert_eq!(admin.run(&["PUBSUB", "numpat"]).await, int(2));
+
+    // gone with the last subscriber
+    second.run(&["UNSUBSCRIBE"]).await;
+    first.run(&["UNSUBSCRIBE", "news.tech"]).await;
+    assert_eq!(admin.run(&["PUBSUB", "CHANNELS"]).await, array(&["sport"]));
+    patterns.run(&["PUNSUBSCRIBE", "*"]).await;
+    assert_eq!(admin.run(&["PUBSUB", "NUMPAT"]).await, int(1));
+
+    assert_eq!(admin.run(&["PUBSUB", "SHARDCHANNELS"]).await, array(&[]));
+    assert_eq!(
+        admin.run(&["PUBSUB", "SHARDNUMSUB", "sport"]).await,
+        RedirsValue::Array(Some(vec![bulk("sport"), int(0)]))
+    );
+    assert_eq!(
+        admin.run(&["PUBSUB", "NUMPAT", "x"]).await,
+        error("ERR wrong number of arguments for 'pubsub|numpat' command")
+    );
+    assert_eq!(
+        admin.run(&["PUBSUB", "CHANNELS", "a", "b"]).await,
+        error("ERR wrong number of arguments for 'pubsub|channels' command")
+    );
+    assert_eq!(
+        admin.run(&["PUBSUB", "NOPE"]).await,
+        error("ERR unknown subcommand 'NOPE'. Try PUBSUB HELP.")
+    );
+}
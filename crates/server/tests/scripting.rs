@@ -0,0 +1,397 @@
+mod common;
+
+use common::{array, bulk, error, int, nil, simple, start, Client};
+use protocol::RedirsValue;
+
+// runs `script` with no keys or arguments
+async fn eval(client: &mut Client, script: &str) -> RedirsValue {
+    client.run(&["EVAL", script, "0"]).await
+}
+
+#[tokio::test]
+async fn scripts_are_named_by_their_sha1() {
+    let mut client = Client::connect(start().await).await;
+    let a = "a".repeat(1000);
+    for (script, sha) in [
+        ("return 1", "e0e1f9fabfc9d4800c877a703b823ac0578ff8db"),
+        ("", "da39a3ee5e6b4b0d3255bfef95601890afd80709"),
+        ("abc", "a9993e364706816aba3e25717850c26c9cd0d89d"),
+        (
+            "return redis.call('GET', KEYS[1])",
+            "d3c21d0c2b9ca22f82737626a27bcaf5d288f99f",
+        ),
+        // longer than a block
+        (&a, "291e9a6c66994949b57ba5e650361e98fc36b1ba"),
+    ] {
+        assert_eq!(client.run(&["SCRIPT", "LOAD", script]).await, bulk(sha));
+    }
+    assert_eq!(
+        client
+            .run(&[
+                "SCRIPT",
+                "EXISTS",
+                "e0e1f9fabfc9d4800c877a703b823ac0578ff8db",
+                "E0E1F9FABFC9D4800C877A703B823AC0578FF8DB",
+                "0000000000000000000000000000000000000000",
+            ])
+            .await,
+        RedirsValue::Array(Some(vec![int(1), int(1), int(0)]))
+    );
+    assert_eq!(
+        client.run(&["SCRIPT", "FLUSH", "ASYNC"]).await,
+        simple("OK")
+    );
+    assert_eq!(
+        client
+            .run(&[
+                "SCRIPT",
+                "EXISTS",
+                "e0e1f9fabfc9d4800c877a703b823ac0578ff8db"
+            ])
+            .await,
+        RedirsValue::Array(Some(vec![int(0)]))
+    );
+}
+
+#[tokio::test]
+async fn eval_checks_its_arguments_and_caches_the_script() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        client
+            .run(&["EVALSHA", "e0e1f9fabfc9d4800c877a703b823ac0578ff8db", "0"])
+            .await,
+        error("NOSCRIPT No matching script. Please use EVAL.")
+    );
+    assert_eq!(
+        client.run(&["EVAL", "return 1", "x"]).await,
+        error("ERR value is not an integer or out of range")
+    );
+    assert_eq!(
+        client.run(&["EVAL", "return 1", "-1"]).await,
+        error("ERR Number of keys can't be negative")
+    );
+    assert_eq!(
+        client.run(&["EVAL", "return 1", "2", "k"]).await,
+        error("ERR Number of keys can't be greater than number of args")
+    );
+    assert_eq!(
+        client.run(&["EVAL", "return 1", "1", "k", "arg"]).await,
+        int(1)
+    );
+    assert_eq!(
+        client
+            .run(&["EVALSHA", "e0e1f9fabfc9d4800c877a703b823ac0578ff8db", "0"])
+            .await,
+        int(1)
+    );
+}
+
+#[tokio::test]
+async fn script_subcommands_are_checked() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        client.run(&["SCRIPT", "LOAD"]).await,
+        error("ERR wrong number of arguments for 'script|load' command")
+    );
+    assert_eq!(
+        client.run(&["SCRIPT", "EXISTS"]).await,
+        error("ERR wrong number of arguments for 'script|exists' command")
+    );
+    assert_eq!(
+        client.run(&["SCRIPT", "FLUSH", "LATER"]).await,
+        error("ERR SCRIPT FLUSH only support SYNC|ASYNC option")
+    );
+    assert_eq!(
+        client.run(&["SCRIPT", "NOPE"]).await,
+        error("ERR unknown subcommand 'NOPE'. Try SCRIPT HELP.")
+    );
+}
+
+#[tokio::test]
+async fn lua_values_become_replies() {
+    let mut client = Client::connect(start().await).await;
+    for (script, reply) in [
+        ("return 1", int(1)),
+        ("return -7", int(-7)),
+        // numbers are cut towards zero
+        ("return 3.99", int(3)),
+        ("return -3.99", int(-3)),
+        ("return 'text'", bulk("text")),
+        ("return true", int(1)),
+        ("return false", nil()),
+        ("return nil", nil()),
+        ("return", nil()),
+        ("return {ok = 'FINE'}", simple("FINE")),
+        ("return {err = 'ERR broken'}", error("ERR broken")),
+        ("return redis.status_reply('FINE')", simple("FINE")),
+        ("return redis.error_reply('MY own')", error("MY own")),
+        ("return {}", array(&[])),
+        // arrays end at their first nil
+        ("return {'a', 'b', nil, 'c'}", array(&["a", "b"])),
+        (
+            "return {1, 'two', {3, {ok = 'four'}}, false, 5}",
+            RedirsValue::Array(Some(vec![
+                int(1),
+                bulk("two"),
+                RedirsValue::Array(Some(vec![int(3), simple("four")])),
+                nil(),
+                int(5),
+            ])),
+        ),
+        (
+            "local t = {} t[1] = t return t",
+            error("ERR reached lua stack limit"),
+        ),
+    ] {
+        assert_eq!(eval(&mut client, script).await, reply, "{script}");
+    }
+}
+
+#[tokio::test]
+async fn replies_become_lua_values() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["RPUSH", "list", "a", "b"]).await;
+    for (script, reply) in [
+        ("return type(redis.call('SET', 'k', '1'))", bulk("table")),
+        ("return redis.call('SET', 'k', '1').ok", bulk("OK")),
+        ("return redis.call('SET', 'k', '1')", simple("OK")),
+        ("return redis.call('INCR', 'k')", int(2)),
+        ("return type(redis.call('INCR', 'k'))", bulk("number")),
+        ("return redis.call('GET', 'k')", bulk("3")),
+        // nil is false
+        ("return type(redis.call('GET', 'missing'))", bulk("boolean")),
+        ("return redis.call('GET', 'missing') == false", int(1)),
+        (
+            "return redis.call('LRANGE', 'list', 0, -1)",
+            array(&["a", "b"]),
+        ),
+        ("return #redis.call('LRANGE', 'list', 0, -1)", int(2)),
+        // RESP3 only replies are seen as a RESP2 client reads them
+        ("return redis.call('HGETALL', 'missing')", array(&[])),
+        (
+            "return redis.pcall('INCR', 'list').err",
+            bulk("WRONGTYPE Operation against a key holding the wrong kind of value"),
+        ),
+        (
+            "return redis.pcall('INCR', 'list')",
+            error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+        ),
+        // redis.call raises the error reply and the script ends with it
+        (
+            "redis.call('INCR', 'list') return 1",
+            error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+        ),
+    ] {
+        assert_eq!(eval(&mut client, script).await, reply, "{script}");
+    }
+}
+
+#[tokio::test]
+async fn scripts_see_their_keys_and_arguments() {
+    let mut client = Client::connect(start().await).await;
+    let script = "return {#KEYS, #ARGV, KEYS[1], KEYS[2], ARGV[1]}";
+    assert_eq!(
+        client
+            .run(&["EVAL", script, "2", "k1", "k2", "a1", "a2"])
+            .await,
+        RedirsValue::Array(Some(vec![
+            int(2),
+            int(2),
+            bulk("k1"),
+            bulk("k2"),
+            bulk("a1"),
+        ]))
+    );
+    let script = "return redis.call('SET', KEYS[1], ARGV[1])";
+    let sha = match client.run(&["SCRIPT", "LOAD", script]).await {
+        RedirsValue::BulkString(Some(sha)) => String::from_utf8(sha.to_vec()).unwrap(),
+        reply => panic!("{reply:?}"),
+    };
+    assert_eq!(
+        client.run(&["EVALSHA", &sha, "1", "k", "v"]).await,
+        simple("OK")
+    );
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("v"));
+    assert_eq!(
+        eval(&mut client, "return redis.sha1hex('')").await,
+        bulk("da39a3ee5e6b4b0d3255bfef95601890afd80709")
+    );
+    // numbers are passed as their text
+    assert_eq!(
+        eval(&mut client, "return redis.call('SET', 'n', 12)").await,
+        simple("OK")
+    );
+    assert_eq!(client.run(&["GET", "n"]).await, bulk("12"));
+}
+
+#[tokio::test]
+async fn script_errors_are_replies() {
+    let mut client = Client::connect(start().await).await;
+    for (script, reply) in [
+        (
+            "return redis.call('SUBSCRIBE', 'c')",
+            "ERR This Redis command is not allowed from script",
+        ),
+        (
+            "return redis.call('EVAL', 'return 1', '0')",
+            "ERR This Redis command is not allowed from script",
+        ),
+        (
+            "return redis.call('NOPE')",
+            "ERR Unknown Redis command called from script",
+        ),
+        (
+            "return redis.call()",
+            "ERR Please specify at least one argument for this redis lib call",
+        ),
+        (
+            "return redis.call('GET', {})",
+            "ERR Lua redis lib command arguments must be strings or integers",
+        ),
+        (
+            "return redis.call('GET')",
+            "ERR wrong number of arguments for 'get' command",
+        ),
+        ("error('boom')", "ERR user_script:1: boom"),
+        // nothing is read from files
+        (
+            "return loadfile('/etc/hosts')",
+            "ERR user_script:1: attempt to call global 'loadfile' (a nil value)",
+        ),
+    ] {
+        assert_eq!(eval(&mut client, script).await, error(reply), "{script}");
+    }
+    match eval(&mut client, "return (").await {
+        RedirsValue::SimpleError(e) => {
+            assert!(e.starts_with("ERR Error compiling script"), "{e}")
+        }
+        reply => panic!("{reply:?}"),
+    }
+}
+
+#[tokio::test]
+async fn select_in_a_script_stays_in_it() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        eval(
+            &mut client,
+            "redis.call('SELECT', 1) return redis.call('SET', 'k', 'x')"
+        )
+        .await,
+        simple("OK")
+    );
+    assert_eq!(client.run(&["GET", "k"]).await, nil());
+    assert_eq!(client.run(&["SELECT", "1"]).await, simple("OK"));
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("x"));
+}
+
+#[tokio::test]
+async fn scripts_past_the_busy_threshold_are_stopped() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        client
+            .run(&["CONFIG", "SET", "busy-reply-threshold", "100"])
+            .await,
+        simple("OK")
+    );
+    assert_eq!(
+        client.run(&["CONFIG", "GET", "busy-reply-threshold"]).await,
+        array(&["busy-reply-threshold", "100"])
+    );
+    assert_eq!(
+        eval(&mut client, "redis.call('SET', 'k', 'x') while true do end").await,
+        error(
+            "BUSY the script ran for longer than the busy-reply-threshold of 100 ms and was stopped"
+        )
+    );
+    // what it wrote before is kept, and the server goes on
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("x"));
+    assert_eq!(eval(&mut client, "return 1").await, int(1));
+}
+
+// the example of the redis docs: the key is deleted only while it still holds
+// the value the caller set, no other client running a command in between
+const COMPARE_AND_DELETE: &str = "if redis.call('GET', KEYS[1]) == ARGV[1] then \
+    return redis.call('DEL', KEYS[1]) \
+else \
+    return 0 \
+end";
+
+#[tokio::test]
+async fn compare_and_delete() {
+    let addr = start().await;
+    let mut client = Client::connect(addr).await;
+    client.run(&["SET", "lock", "mine"]).await;
+    assert_eq!(
+        client
+            .run(&["EVAL", COMPARE_AND_DELETE, "1", "lock", "theirs"])
+            .await,
+        int(0)
+    );
+    assert_eq!(client.run(&["GET", "lock"]).await, bulk("mine"));
+    assert_eq!(
+        client
+            .run(&["EVAL", COMPARE_AND_DELETE, "1", "lock", "mine"])
+            .await,
+        int(1)
+    );
+    assert_eq!(client.run(&["EXISTS", "lock"]).await, int(0));
+    assert_eq!(
+        client
+            .run(&["EVAL", COMPARE_AND_DELETE, "1", "lock", "mine"])
+            .await,
+        int(0)
+    );
+
+    // of many clients racing to take the lock and free it again, each sees
+    // its own value and deletes it exactly once
+    let racers = (0..8).map(|i| {
+        tokio::spawn(async move {
+            let mut client = Client::connect(addr).await;
+            let me = format!("client-{i}");
+            let mut freed = 0;
+            for _ in 0..50 {
+                if client.run(&["SET", "lock", &me, "NX"]).await == simple("OK") {
+                    let reply = client
+                        .run(&["EVAL", COMPARE_AND_DELETE, "1", "lock", &me])
+                        .await;
+                    assert_eq!(reply, int(1), "{me}");
+                    freed += 1;
+                }
+            }
+            freed
+        })
+    });
+    let mut freed = 0;
+    for racer in racers.collect::<Vec<_>>() {
+        freed += racer.await.unwrap();
+    }
+    assert!(freed > 0);
+    assert_eq!(client.run(&["EXISTS", "lock"]).await, int(0));
+}
+
+#[tokio::test]
+async fn scripts_run_with_no_command_in_between() {
+    let addr = start().await;
+    let mut writer = Client::connect(addr).await;
+    let mut reader = Client::connect(addr).await;
+    let script = "for i = 1, 1000 do redis.call('INCR', KEYS[1]) end return 1";
+    let writing = tokio::spawn(async move {
+        for _ in 0..5 {
+            assert_eq!(writer.run(&["EVAL", script, "1", "n"]).await, int(1));
+        }
+    });
+    // the counter is only ever seen between two scripts
+    while !writing.is_finished() {
+        match reader.run(&["GET", "n"]).await {
+            RedirsValue::BulkString(None) => {}
+            RedirsValue::BulkString(Some(n)) => {
+                let n: u64 = std::str::from_utf8(&n).unwrap().parse().unwrap();
+                assert_eq!(n % 1000, 0, "{n}");
+            }
+            reply => panic!("{reply:?}"),
+        }
+    }
+    writing.await.unwrap();
+    assert_eq!(reader.run(&["GET", "n"]).await, bulk("5000"));
+}
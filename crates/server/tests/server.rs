@@ -0,0 +1,192 @@
+mod common;
+
+use common::{error, simple, start, start_with, Client};
+use protocol::RedirsValue;
+use server::Db;
+use tokio::io::AsyncWriteExt;
+
+#[tokio::test]
+async fn set_then_get_returns_the_value() {
+    let mut client = Client::connect(start().await).await;
+    let reply = client
+        .request(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$3\r\nbar\r\n")
+        .await;
+    assert_eq!(reply, simple("OK"));
+    let reply = client.request(b"*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n").await;
+    assert_eq!(reply, RedirsValue::from("bar"));
+}
+
+#[tokio::test]
+async fn get_of_a_missing_key_is_nil() {
+    let mut client = Client::connect(start().await).await;
+    client.send(b"*2\r\n$3\r\nGET\r\n$7\r\nmissing\r\n").await;
+    let mut raw = [0; 5];
+    tokio::io::AsyncReadExt::read_exact(client.stream.get_mut(), &mut raw)
+        .await
+        .unwrap();
+    assert_eq!(&raw, b"$-1\r\n");
+}
+
+#[tokio::test]
+async fn del_counts_the_removed_key() {
+    let mut client = Client::connect(start().await).await;
+    client
+        .request(b"*3\r\n$3\r\nSET\r\n$1\r\nk\r\n$1\r\nv\r\n")
+        .await;
+    let del = b"*2\r\n$3\r\nDEL\r\n$1\r\nk\r\n";
+    assert_eq!(client.request(del).await, RedirsValue::Integer(1));
+    assert_eq!(client.request(del).await, RedirsValue::Integer(0));
+}
+
+#[tokio::test]
+async fn ping_and_echo() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        client.request(b"*1\r\n$4\r\nPING\r\n").await,
+        simple("PONG")
+    );
+    assert_eq!(
+        client.request(b"*2\r\n$4\r\nPING\r\n$2\r\nhi\r\n").await,
+        RedirsValue::from("hi")
+    );
+    // an empty message is echoed like any other
+    assert_eq!(client.run(&["PING", ""]).await, RedirsValue::from(""));
+    assert_eq!(
+        client.request(b"*2\r\n$4\r\nECHO\r\n$3\r\na\nb\r\n").await,
+        RedirsValue::from("a\nb")
+    );
+}
+
+#[tokio::test]
+async fn unknown_commands_are_errors_and_the_connection_stays_open() {
+    let mut client = Client::connect(start().await).await;
+    let reply = client.request(b"*2\r\n$4\r\nNOPE\r\n$1\r\nx\r\n").await;
+    assert_eq!(
+        reply,
+        RedirsValue::SimpleError(
+            "ERR unknown command 'NOPE', with args beginning with: 'x' ".to_owned()
+        )
+    );
+    assert_eq!(
+        client.request(b"*1\r\n$4\r\nPING\r\n").await,
+        simple("PONG")
+    );
+}
+
+#[tokio::test]
+async fn pipelined_requests_are_answered_in_order() {
+    let mut client = Client::connect(start().await).await;
+    client
+        .send(b"*3\r\n$3\r\nSET\r\n$1\r\na\r\n$1\r\n1\r\n*2\r\n$3\r\nGET\r\n$1\r\na\r\n*1\r\n$4\r\nPING\r\n")
+        .await;
+    assert_eq!(client.reply().await, simple("OK"));
+    assert_eq!(client.reply().await, RedirsValue::from("1"));
+    assert_eq!(client.reply().await, simple("PONG"));
+}
+
+#[tokio::test]
+async fn hello_3_switches_the_replies_to_resp3() {
+    let mut client = Client::connect(start().await).await;
+    let reply = client.request(b"*2\r\n$5\r\nHELLO\r\n$1\r\n3\r\n").await;
+    let RedirsValue::Map(map) = reply else {
+        panic!("expected a map, got {reply:?}");
+    };
+    assert_eq!(
+        map.get(&RedirsValue::from("proto")),
+        Some(&RedirsValue::Integer(3))
+    );
+    assert_eq!(
+        client.request(b"*2\r\n$3\r\nGET\r\n$1\r\nx\r\n").await,
+        RedirsValue::Null
+    );
+}
+
+#[tokio::test]
+async fn a_client_leaving_mid_command_does_not_affect_others() {
+    let addr = start().await;
+    let mut leaving = Client::connect(addr).await;
+    leaving
+        .send(b"*3\r\n$3\r\nSET\r\n$3\r\nfoo\r\n$10\r\nbar")
+        .await;
+    drop(leaving);
+    let mut client = Client::connect(addr).await;
+    assert_eq!(
+        client.request(b"*1\r\n$4\r\nPING\r\n").await,
+        simple("PONG")
+    );
+    assert_eq!(
+        client.request(b"*2\r\n$3\r\nGET\r\n$3\r\nfoo\r\n").await,
+        RedirsValue::BulkString(None)
+    );
+}
+
+#[tokio::test]
+async fn protocol_errors_are_replied_before_closing() {
+    let mut client = Client::connect(start().await).await;
+    let reply = client.request(b"*1\r\n?PING\r\n").await;
+    assert!(matches!(reply, RedirsValue::SimpleError(_)), "{reply:?}");
+    assert!(client.stream.read_value().await.is_err());
+}
+
+#[tokio::test]
+async fn endless_lines_are_cut_off() {
+    let mut client = Client::connect(start().await).await;
+    client.send(b"*1\r\n$").await;
+    let digits = vec![b'9'; 128 * 1024];
+    // the server may hang up before it has read all of it
+    let _ = client.stream.get_mut().write_all(&digits).await;
+    assert_eq!(
+        client.reply().await,
+        error("ERR Protocol error: too big inline request")
+    );
+    assert!(client.stream.read_value().await.is_err());
+}
+
+#[tokio::test]
+async fn many_clients_at_once() {
+    let addr = start().await;
+    let tasks: Vec<_> = (0..64)
+        .map(|i| {
+            tokio::spawn(async move {
+                let mut client = Client::connect(addr).await;
+                let key = format!("key:{i}");
+                let set = format!(
+                    "*3\r\n$3\r\nSET\r\n${}\r\n{key}\r\n$1\r\n{}\r\n",
+                    key.len(),
+                    i % 10
+                );
+                assert_eq!(client.request(set.as_bytes()).await, simple("OK"));
+                let get = format!("*2\r\n$3\r\nGET\r\n${}\r\n{key}\r\n", key.len());
+                assert_eq!(
+                    client.request(get.as_bytes()).await,
+                    RedirsValue::from((i % 10).to_string())
+                );
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+}
+
+#[tokio::test]
+async fn string_commands_on_other_types_are_wrongtype() {
+    let db = Db::new();
+    db.lock()
+        .get_or_create_list(b"list")
+        .unwrap()
+        .push_back(b"a".to_vec());
+    let addr = start_with(db).await;
+    let mut client = Client::connect(addr).await;
+    let wrongtype = RedirsValue::SimpleError(
+        "WRONGTYPE Operation against a key holding the wrong kind of value".to_owned(),
+    );
+    let get = b"*2\r\n$3\r\nGET\r\n$4\r\nlist\r\n";
+    assert_eq!(client.request(get).await, wrongtype);
+    let set_get = b"*4\r\n$3\r\nSET\r\n$4\r\nlist\r\n$1\r\nv\r\n$3\r\nGET\r\n";
+    assert_eq!(client.request(set_get).await, wrongtype);
+    // a plain SET overwrites whatever the key held
+    let set = b"*3\r\n$3\r\nSET\r\n$4\r\nlist\r\n$1\r\nv\r\n";
+    assert_eq!(client.request(set).await, simple("OK"));
+    assert_eq!(client.request(get).await, RedirsValue::from("v"));
+}
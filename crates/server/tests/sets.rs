@@ -0,0 +1,340 @@
+mod common;
+
+use common::{array, bulk, error, int, nil, simple, start, Client};
+use protocol::RedirsValue;
+
+// the members of an array or set reply, sorted
+fn members(reply: RedirsValue) -> Vec<RedirsValue> {
+    let mut members: Vec<_> = match reply {
+        RedirsValue::Array(Some(items)) => items,
+        RedirsValue::Set(set) => set.into_iter().collect(),
+        reply => panic!("expected members, got {reply:?}"),
+    };
+    members.sort();
+    members
+}
+
+#[tokio::test]
+async fn sadd_counts_only_new_members() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(client.run(&["SADD", "s", "a", "b", "a", "a"]).await, int(2));
+    assert_eq!(client.run(&["SADD", "s", "b", "c"]).await, int(1));
+    assert_eq!(client.run(&["SCARD", "s"]).await, int(3));
+    assert_eq!(client.run(&["SCARD", "missing"]).await, int(0));
+    assert_eq!(client.run(&["TYPE", "s"]).await, simple("set"));
+    assert_eq!(
+        members(client.run(&["SMEMBERS", "s"]).await),
+        vec![bulk("a"), bulk("b"), bulk("c")]
+    );
+}
+
+#[tokio::test]
+async fn srem_removes_the_key_with_its_last_member() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SADD", "s", "a", "b", "c"]).await;
+    assert_eq!(client.run(&["SREM", "s", "a", "a", "nope"]).await, int(1));
+    assert_eq!(client.run(&["SREM", "s", "b", "c"]).await, int(2));
+    assert_eq!(client.run(&["EXISTS", "s"]).await, int(0));
+    assert_eq!(client.run(&["SREM", "s", "a"]).await, int(0));
+}
+
+#[tokio::test]
+async fn membership() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SADD", "s", "a", "b"]).await;
+    assert_eq!(client.run(&["SISMEMBER", "s", "a"]).await, int(1));
+    assert_eq!(client.run(&["SISMEMBER", "s", "z"]).await, int(0));
+    assert_eq!(client.run(&["SISMEMBER", "missing", "a"]).await, int(0));
+    assert_eq!(
+        client.run(&["SMISMEMBER", "s", "b", "z", "a", "b"]).await,
+        RedirsValue::Array(Some(vec![int(1), int(0), int(1), int(1)]))
+    );
+    assert_eq!(
+        client.run(&["SMISMEMBER", "missing", "a", "b"]).await,
+        RedirsValue::Array(Some(vec![int(0), int(0)]))
+    );
+}
+
+#[tokio::test]
+async fn smembers_is_an_array_over_resp2_and_a_set_over_resp3() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SADD", "s", "a", "b"]).await;
+    let reply = client.run(&["SMEMBERS", "s"]).await;
+    assert!(matches!(reply, RedirsValue::Array(_)), "{reply:?}");
+    assert_eq!(members(reply), vec![bulk("a"), bulk("b")]);
+    assert_eq!(client.run(&["SMEMBERS", "missing"]).await, array(&[]));
+    client.run(&["HELLO", "3"]).await;
+    let reply = client.run(&["SMEMBERS", "s"]).await;
+    assert!(matches!(reply, RedirsValue::Set(_)), "{reply:?}");
+    assert_eq!(members(reply), vec![bulk("a"), bulk("b")]);
+    let RedirsValue::Set(empty) = client.run(&["SMEMBERS", "missing"]).await else {
+        panic!("expected a set");
+    };
+    assert!(empty.is_empty());
+}
+
+#[tokio::test]
+async fn set_commands_on_a_string_are_wrongtype() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "string", "v"]).await;
+    let wrongtype = error("WRONGTYPE Operation against a key holding the wrong kind of value");
+    for args in [
+        &["SADD", "string", "a"][..],
+        &["SREM", "string", "a"],
+        &["SMEMBERS", "string"],
+        &["SISMEMBER", "string", "a"],
+        &["SMISMEMBER", "string", "a"],
+        &["SCARD", "string"],
+    ] {
+        assert_eq!(client.run(args).await, wrongtype, "{args:?}");
+    }
+    assert_eq!(client.run(&["GET", "string"]).await, bulk("v"));
+}
+
+fn bulks(items: &[&str]) -> Vec<RedirsValue> {
+    items.iter().map(|item| bulk(item)).collect()
+}
+
+#[tokio::test]
+async fn sinter_sunion_sdiff() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SADD", "key1", "a", "b", "c", "d"]).await;
+    client.run(&["SADD", "key2", "c"]).await;
+    client.run(&["SADD", "key3", "a", "c", "e"]).await;
+    assert_eq!(
+        members(client.run(&["SINTER", "key1", "key2", "key3"]).await),
+        bulks(&["c"])
+    );
+    assert_eq!(
+        members(client.run(&["SINTER", "key1", "key3"]).await),
+        bulks(&["a", "c"])
+    );
+    assert_eq!(
+        members(client.run(&["SUNION", "key1", "key2", "key3"]).await),
+        bulks(&["a", "b", "c", "d", "e"])
+    );
+    assert_eq!(
+        members(client.run(&["SDIFF", "key1", "key2", "key3"]).await),
+        bulks(&["b", "d"])
+    );
+    assert_eq!(
+        members(client.run(&["SDIFF", "key3", "key1"]).await),
+        bulks(&["e"])
+    );
+    // a single key is its own result
+    assert_eq!(
+        members(client.run(&["SINTER", "key1"]).await),
+        bulks(&["a", "b", "c", "d"])
+    );
+}
+
+#[tokio::test]
+async fn missing_keys_are_empty_sets() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SADD", "key1", "a", "b"]).await;
+    // one empty operand empties the intersection
+    assert_eq!(
+        members(client.run(&["SINTER", "key1", "missing"]).await),
+        vec![]
+    );
+    assert_eq!(
+        members(client.run(&["SUNION", "missing", "key1"]).await),
+        bulks(&["a", "b"])
+    );
+    assert_eq!(
+        members(client.run(&["SDIFF", "key1", "missing"]).await),
+        bulks(&["a", "b"])
+    );
+    assert_eq!(
+        members(client.run(&["SDIFF", "missing", "key1"]).await),
+        vec![]
+    );
+    // the types of every key are checked, even after an empty one
+    client.run(&["SET", "string", "v"]).await;
+    assert_eq!(
+        client.run(&["SINTER", "missing", "string"]).await,
+        error("WRONGTYPE Operation against a key holding the wrong kind of value")
+    );
+}
+
+#[tokio::test]
+async fn store_variants_overwrite_the_destination() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SADD", "key1", "a", "b", "c"]).await;
+    client.run(&["SADD", "key2", "c", "d"]).await;
+    client.run(&["SET", "dest", "string", "EX", "100"]).await;
+    assert_eq!(
+        client.run(&["SUNIONSTORE", "dest", "key1", "key2"]).await,
+        int(4)
+    );
+    assert_eq!(client.run(&["TYPE", "dest"]).await, simple("set"));
+    assert_eq!(client.run(&["TTL", "dest"]).await, int(-1));
+    assert_eq!(
+        members(client.run(&["SMEMBERS", "dest"]).await),
+        bulks(&["a", "b", "c", "d"])
+    );
+    assert_eq!(
+        client.run(&["SINTERSTORE", "dest", "key1", "key2"]).await,
+        int(1)
+    );
+    assert_eq!(
+        members(client.run(&["SMEMBERS", "dest"]).await),
+        bulks(&["c"])
+    );
+    assert_eq!(
+        client.run(&["SDIFFSTORE", "key1", "key1", "key2"]).await,
+        int(2)
+    );
+    assert_eq!(
+        members(client.run(&["SMEMBERS", "key1"]).await),
+        bulks(&["a", "b"])
+    );
+    // an empty result removes the destination
+    assert_eq!(
+        client
+            .run(&["SINTERSTORE", "dest", "key1", "missing"])
+            .await,
+        int(0)
+    );
+    assert_eq!(client.run(&["EXISTS", "dest"]).await, int(0));
+    assert_eq!(
+        client.run(&["SDIFFSTORE", "key2", "key2", "key2"]).await,
+        int(0)
+    );
+    assert_eq!(client.run(&["EXISTS", "key2"]).await, int(0));
+}
+
+#[tokio::test]
+async fn sintercard_with_a_limit() {
+    let mut client = Client::connect(start().await).await;
+    let mut args = vec!["SADD", "big"];
+    let members: Vec<String> = (0..1000).map(|i| i.to_string()).collect();
+    args.extend(members.iter().map(String::as_str));
+    client.run(&args).await;
+    args[1] = "other";
+    client.run(&args[..502]).await;
+    assert_eq!(
+        client.run(&["SINTERCARD", "2", "big", "other"]).await,
+        int(500)
+    );
+    assert_eq!(
+        client
+            .run(&["SINTERCARD", "2", "big", "other", "LIMIT", "10"])
+            .await,
+        int(10)
+    );
+    assert_eq!(
+        client
+            .run(&["SINTERCARD", "2", "big", "other", "LIMIT", "0"])
+            .await,
+        int(500)
+    );
+    assert_eq!(
+        client
+            .run(&["SINTERCARD", "2", "big", "other", "LIMIT", "9999"])
+            .await,
+        int(500)
+    );
+    assert_eq!(client.run(&["SINTERCARD", "1", "missing"]).await, int(0));
+    assert_eq!(
+        client.run(&["SINTERCARD", "0", "big"]).await,
+        error("ERR numkeys should be greater than 0")
+    );
+    assert_eq!(
+        client.run(&["SINTERCARD", "3", "big", "other"]).await,
+        error("ERR Number of keys can't be greater than number of args")
+    );
+    assert_eq!(
+        client.run(&["SINTERCARD", "1", "big", "LIMIT", "-1"]).await,
+        error("ERR LIMIT can't be negative")
+    );
+    assert_eq!(
+        client.run(&["SINTERCARD", "1", "big", "extra"]).await,
+        error("ERR syntax error")
+    );
+}
+
+#[tokio::test]
+async fn spop_removes_what_it_returns() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SADD", "s", "a", "b", "c", "d", "e"]).await;
+    let popped = client.run(&["SPOP", "s"]).await;
+    assert!(bulks(&["a", "b", "c", "d", "e"]).contains(&popped));
+    assert_eq!(client.run(&["SCARD", "s"]).await, int(4));
+    let RedirsValue::BulkString(Some(popped)) = popped else {
+        unreachable!();
+    };
+    let popped = String::from_utf8(popped.to_vec()).unwrap();
+    assert_eq!(client.run(&["SISMEMBER", "s", &popped]).await, int(0));
+    let partial = members(client.run(&["SPOP", "s", "3"]).await);
+    assert_eq!(partial.len(), 3);
+    assert_eq!(client.run(&["SCARD", "s"]).await, int(1));
+    let mut left = members(client.run(&["SMEMBERS", "s"]).await);
+    left.extend(partial);
+    left.sort();
+    left.dedup();
+    assert_eq!(left.len(), 4);
+    assert_eq!(client.run(&["SPOP", "s", "0"]).await, array(&[]));
+    // more than there are takes them all and removes the key
+    assert_eq!(members(client.run(&["SPOP", "s", "10"]).await).len(), 1);
+    assert_eq!(client.run(&["EXISTS", "s"]).await, int(0));
+    assert_eq!(client.run(&["SPOP", "s"]).await, nil());
+    assert_eq!(client.run(&["SPOP", "s", "2"]).await, array(&[]));
+    assert_eq!(
+        client.run(&["SPOP", "s", "-1"]).await,
+        error("ERR value is out of range, must be positive")
+    );
+    assert_eq!(
+        client.run(&["SPOP", "s", "1", "2"]).await,
+        error("ERR syntax error")
+    );
+}
+
+#[tokio::test]
+async fn spop_of_the_whole_set_removes_the_key() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SADD", "s", "a", "b", "c"]).await;
+    assert_eq!(
+        members(client.run(&["SPOP", "s", "3"]).await),
+        bulks(&["a", "b", "c"])
+    );
+    assert_eq!(client.run(&["EXISTS", "s"]).await, int(0));
+}
+
+#[tokio::test]
+async fn srandmember_leaves_the_set_alone() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(client.run(&["SRANDMEMBER", "s"]).await, nil());
+    assert_eq!(client.run(&["SRANDMEMBER", "s", "-3"]).await, array(&[]));
+    client.run(&["SADD", "s", "a", "b", "c"]).await;
+    let all = bulks(&["a", "b", "c"]);
+    assert!(all.contains(&client.run(&["SRANDMEMBER", "s"]).await));
+    let mut distinct = members(client.run(&["SRANDMEMBER", "s", "2"]).await);
+    distinct.dedup();
+    assert_eq!(distinct.len(), 2);
+    assert_eq!(members(client.run(&["SRANDMEMBER", "s", "10"]).await), all);
+    assert_eq!(client.run(&["SRANDMEMBER", "s", "0"]).await, array(&[]));
+    assert_eq!(client.run(&["SCARD", "s"]).await, int(3));
+}
+
+#[tokio::test]
+async fn srandmember_with_a_negative_count_repeats() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SADD", "s", "a", "b", "c"]).await;
+    let RedirsValue::Array(Some(picked)) = client.run(&["SRANDMEMBER", "s", "-50"]).await else {
+        panic!("expected an array");
+    };
+    assert_eq!(picked.len(), 50);
+    let all = bulks(&["a", "b", "c"]);
+    assert!(picked.iter().all(|member| all.contains(member)));
+    let mut distinct = picked.clone();
+    distinct.sort();
+    distinct.dedup();
+    assert!(distinct.len() <= 3);
+    client.run(&["SADD", "one", "only"]).await;
+    assert_eq!(
+        client.run(&["SRANDMEMBER", "one", "-3"]).await,
+        array(&["only", "only", "only"])
+    );
+    assert_eq!(client.run(&["SCARD", "s"]).await, int(3));
+}
@@ -0,0 +1,691 @@
+mod common;
+
+use std::collections::BTreeSet;
+
+use common::{array, bulk, error, int, nil, simple, start, Client};
+use protocol::RedirsValue;
+use rand::Rng;
+use server::sorted_set::{ScoreBound, ScoreRange, SortedSet};
+
+// a client with the leaderboard of the redis docs' examples
+async fn leaderboard() -> Client {
+    let mut client = Client::connect(start().await).await;
+    client
+        .run(&[
+            "ZADD", "z", "1", "one", "2", "two", "3", "three", "4", "four",
+        ])
+        .await;
+    client
+}
+
+#[tokio::test]
+async fn zadd_zscore_and_zcard() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        client
+            .run(&["ZADD", "z", "1", "a", "2", "b", "1", "a"])
+            .await,
+        int(2)
+    );
+    assert_eq!(
+        client.run(&["ZADD", "z", "1.5", "a", "3", "c"]).await,
+        int(1)
+    );
+    assert_eq!(client.run(&["ZSCORE", "z", "a"]).await, bulk("1.5"));
+    assert_eq!(client.run(&["ZSCORE", "z", "nope"]).await, nil());
+    assert_eq!(client.run(&["ZSCORE", "missing", "a"]).await, nil());
+    assert_eq!(client.run(&["ZCARD", "z"]).await, int(3));
+    assert_eq!(client.run(&["ZCARD", "missing"]).await, int(0));
+    assert_eq!(client.run(&["TYPE", "z"]).await, simple("zset"));
+    client
+        .run(&["ZADD", "z", "-inf", "low", "+inf", "high"])
+        .await;
+    assert_eq!(client.run(&["ZSCORE", "z", "low"]).await, bulk("-inf"));
+    assert_eq!(client.run(&["ZSCORE", "z", "high"]).await, bulk("inf"));
+    for args in [
+        &["ZADD", "z", "x", "a"][..],
+        &["ZADD", "z", "nan", "a"],
+        &["ZADD", "z", "1", "a", "two", "b"],
+    ] {
+        assert_eq!(
+            client.run(args).await,
+            error("ERR value is not a valid float"),
+            "{args:?}"
+        );
+    }
+    // a bad pair leaves the others unapplied
+    assert_eq!(client.run(&["ZSCORE", "z", "a"]).await, bulk("1.5"));
+    assert_eq!(
+        client.run(&["ZADD", "z", "1", "a", "2"]).await,
+        error("ERR syntax error")
+    );
+    assert_eq!(
+        client.run(&["ZADD", "z", "NX", "1"]).await,
+        error("ERR syntax error")
+    );
+}
+
+#[tokio::test]
+async fn zadd_flags() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["ZADD", "z", "10", "a"]).await;
+    assert_eq!(
+        client.run(&["ZADD", "z", "NX", "20", "a", "1", "b"]).await,
+        int(1)
+    );
+    assert_eq!(client.run(&["ZSCORE", "z", "a"]).await, bulk("10"));
+    assert_eq!(
+        client.run(&["ZADD", "z", "XX", "20", "a", "1", "c"]).await,
+        int(0)
+    );
+    assert_eq!(client.run(&["ZSCORE", "z", "a"]).await, bulk("20"));
+    assert_eq!(client.run(&["ZSCORE", "z", "c"]).await, nil());
+    assert_eq!(
+        client
+            .run(&["ZADD", "z", "CH", "21", "a", "1", "b", "5", "d"])
+            .await,
+        int(2)
+    );
+    assert_eq!(
+        client.run(&["ZADD", "missing", "XX", "1", "a"]).await,
+        int(0)
+    );
+    assert_eq!(client.run(&["EXISTS", "missing"]).await, int(0));
+    let conflicts: &[(&[&str], &str)] = &[
+        (
+            &["NX", "XX"],
+            "ERR XX and NX options at the same time are not compatible",
+        ),
+        (
+            &["GT", "LT"],
+            "ERR GT, LT, and/or NX options at the same time are not compatible",
+        ),
+        (
+            &["NX", "GT"],
+            "ERR GT, LT, and/or NX options at the same time are not compatible",
+        ),
+    ];
+    for (flags, expected) in conflicts {
+        let mut args = vec!["ZADD", "z"];
+        args.extend(*flags);
+        args.extend(["1", "a"]);
+        assert_eq!(client.run(&args).await, error(expected), "{flags:?}");
+    }
+}
+
+#[tokio::test]
+async fn zadd_gt_and_lt_only_move_one_way() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["ZADD", "z", "10", "a", "10", "b"]).await;
+    assert_eq!(
+        client
+            .run(&["ZADD", "z", "GT", "CH", "5", "a", "15", "b"])
+            .await,
+        int(1)
+    );
+    assert_eq!(client.run(&["ZSCORE", "z", "a"]).await, bulk("10"));
+    assert_eq!(client.run(&["ZSCORE", "z", "b"]).await, bulk("15"));
+    assert_eq!(
+        client
+            .run(&["ZADD", "z", "LT", "CH", "5", "a", "20", "b"])
+            .await,
+        int(1)
+    );
+    assert_eq!(client.run(&["ZSCORE", "z", "a"]).await, bulk("5"));
+    assert_eq!(client.run(&["ZSCORE", "z", "b"]).await, bulk("15"));
+    // new members are added whatever the score
+    assert_eq!(client.run(&["ZADD", "z", "GT", "-100", "c"]).await, int(1));
+    assert_eq!(
+        client
+            .run(&["ZADD", "z", "XX", "LT", "1", "c", "1", "d"])
+            .await,
+        int(0)
+    );
+    assert_eq!(client.run(&["ZSCORE", "z", "c"]).await, bulk("-100"));
+    assert_eq!(client.run(&["ZSCORE", "z", "d"]).await, nil());
+}
+
+#[tokio::test]
+async fn zadd_incr() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        client.run(&["ZADD", "z", "INCR", "2.5", "a"]).await,
+        bulk("2.5")
+    );
+    assert_eq!(
+        client.run(&["ZADD", "z", "INCR", "-1", "a"]).await,
+        bulk("1.5")
+    );
+    assert_eq!(
+        client.run(&["ZADD", "z", "INCR", "NX", "1", "a"]).await,
+        nil()
+    );
+    assert_eq!(
+        client.run(&["ZADD", "z", "INCR", "XX", "1", "b"]).await,
+        nil()
+    );
+    assert_eq!(
+        client.run(&["ZADD", "z", "INCR", "GT", "-1", "a"]).await,
+        nil()
+    );
+    assert_eq!(
+        client.run(&["ZADD", "z", "INCR", "GT", "1", "a"]).await,
+        bulk("2.5")
+    );
+    assert_eq!(
+        client.run(&["ZADD", "z", "INCR", "1", "a", "1", "b"]).await,
+        error("ERR INCR option supports a single increment-element pair")
+    );
+    client.run(&["ZADD", "z", "+inf", "top"]).await;
+    assert_eq!(
+        client.run(&["ZADD", "z", "INCR", "-inf", "top"]).await,
+        error("ERR resulting score is not a number (NaN)")
+    );
+}
+
+#[tokio::test]
+async fn score_ties_order_by_member() {
+    let mut client = Client::connect(start().await).await;
+    client
+        .run(&[
+            "ZADD", "z", "1", "c", "1", "a", "0", "z", "1", "b", "2", "aa",
+        ])
+        .await;
+    assert_eq!(
+        client.run(&["ZRANGE", "z", "0", "-1"]).await,
+        array(&["z", "a", "b", "c", "aa"])
+    );
+    assert_eq!(
+        client.run(&["ZRANGE", "z", "0", "-1", "REV"]).await,
+        array(&["aa", "c", "b", "a", "z"])
+    );
+}
+
+#[tokio::test]
+async fn zrange_by_rank() {
+    let mut client = leaderboard().await;
+    let cases: &[(&str, &str, &[&str])] = &[
+        ("0", "-1", &["one", "two", "three", "four"]),
+        ("2", "3", &["three", "four"]),
+        ("-2", "-1", &["three", "four"]),
+        ("1", "100", &["two", "three", "four"]),
+        ("3", "1", &[]),
+        ("5", "10", &[]),
+    ];
+    for (start, stop, expected) in cases {
+        assert_eq!(
+            client.run(&["ZRANGE", "z", start, stop]).await,
+            array(expected),
+            "{start} {stop}"
+        );
+    }
+    assert_eq!(
+        client.run(&["ZRANGE", "z", "0", "1", "REV"]).await,
+        array(&["four", "three"])
+    );
+    assert_eq!(
+        client.run(&["ZRANGE", "z", "-1", "-1", "REV"]).await,
+        array(&["one"])
+    );
+    assert_eq!(
+        client.run(&["ZRANGE", "missing", "0", "-1"]).await,
+        array(&[])
+    );
+    assert_eq!(
+        client
+            .run(&["ZRANGE", "z", "0", "-1", "LIMIT", "0", "1"])
+            .await,
+        error(
+            "ERR syntax error, LIMIT is only supported in combination with either BYSCORE or BYLEX"
+        )
+    );
+}
+
+#[tokio::test]
+async fn zrange_by_score_with_exclusive_and_infinite_bounds() {
+    let mut client = leaderboard().await;
+    let cases: &[(&str, &str, &[&str])] = &[
+        ("-inf", "+inf", &["one", "two", "three", "four"]),
+        ("2", "3", &["two", "three"]),
+        ("(2", "3", &["three"]),
+        ("2", "(3", &["two"]),
+        ("(2", "(3", &[]),
+        ("(1", "+inf", &["two", "three", "four"]),
+        ("-inf", "(2", &["one"]),
+        ("1.5", "3.5", &["two", "three"]),
+        ("3", "2", &[]),
+        ("5", "inf", &[]),
+    ];
+    for (min, max, expected) in cases {
+        assert_eq!(
+            client.run(&["ZRANGEBYSCORE", "z", min, max]).await,
+            array(expected),
+            "ZRANGEBYSCORE {min} {max}"
+        );
+        assert_eq!(
+            client.run(&["ZRANGE", "z", min, max, "BYSCORE"]).await,
+            array(expected),
+            "ZRANGE {min} {max} BYSCORE"
+        );
+    }
+    // REV takes the bounds highest first
+    assert_eq!(
+        client
+            .run(&["ZRANGE", "z", "(4", "2", "BYSCORE", "REV"])
+            .await,
+        array(&["three", "two"])
+    );
+    assert_eq!(
+        client
+            .run(&["ZRANGEBYSCORE", "z", "-inf", "+inf", "LIMIT", "1", "2"])
+            .await,
+        array(&["two", "three"])
+    );
+    assert_eq!(
+        client
+            .run(&["ZRANGE", "z", "+inf", "-inf", "BYSCORE", "REV", "LIMIT", "1", "-1"])
+            .await,
+        array(&["three", "two", "one"])
+    );
+    assert_eq!(
+        client
+            .run(&["ZRANGEBYSCORE", "z", "-inf", "+inf", "LIMIT", "-1", "2"])
+            .await,
+        array(&[])
+    );
+    assert_eq!(
+        client
+            .run(&["ZRANGEBYSCORE", "z", "-inf", "+inf", "LIMIT", "10", "2"])
+            .await,
+        array(&[])
+    );
+    for (min, max) in [("x", "1"), ("1", "(x"), ("((1", "2"), ("nan", "1")] {
+        assert_eq!(
+            client.run(&["ZRANGEBYSCORE", "z", min, max]).await,
+            error("ERR min or max is not a float"),
+            "{min} {max}"
+        );
+    }
+    assert_eq!(
+        client.run(&["ZRANGEBYSCORE", "z", "0", "1", "REV"]).await,
+        error("ERR syntax error")
+    );
+}
+
+#[tokio::test]
+async fn zrange_by_lex() {
+    let mut client = Client::connect(start().await).await;
+    client
+        .run(&[
+            "ZADD", "z", "0", "a", "0", "b", "0", "c", "0", "d", "0", "e",
+        ])
+        .await;
+    let cases: &[(&str, &str, &[&str])] = &[
+        ("-", "+", &["a", "b", "c", "d", "e"]),
+        ("-", "[c", &["a", "b", "c"]),
+        ("-", "(c", &["a", "b"]),
+        ("[aaa", "(d", &["b", "c"]),
+        ("(b", "[d", &["c", "d"]),
+        ("+", "-", &[]),
+        ("[e", "[a", &[]),
+    ];
+    for (min, max, expected) in cases {
+        assert_eq!(
+            client.run(&["ZRANGE", "z", min, max, "BYLEX"]).await,
+            array(expected),
+            "{min} {max}"
+        );
+    }
+    assert_eq!(
+        client
+            .run(&["ZRANGE", "z", "[d", "-", "BYLEX", "REV", "LIMIT", "1", "2"])
+            .await,
+        array(&["c", "b"])
+    );
+    assert_eq!(
+        client.run(&["ZRANGE", "z", "a", "[c", "BYLEX"]).await,
+        error("ERR min or max not valid string range item")
+    );
+    assert_eq!(
+        client
+            .run(&["ZRANGE", "z", "-", "+", "BYLEX", "WITHSCORES"])
+            .await,
+        error("ERR syntax error, WITHSCORES not supported in combination with BYLEX")
+    );
+}
+
+#[tokio::test]
+async fn withscores_is_flat_over_resp2_and_pairs_over_resp3() {
+    let mut client = leaderboard().await;
+    client.run(&["ZADD", "z", "2.5", "half"]).await;
+    assert_eq!(
+        client.run(&["ZRANGE", "z", "1", "2", "WITHSCORES"]).await,
+        array(&["two", "2", "half", "2.5"])
+    );
+    assert_eq!(
+        client
+            .run(&["ZRANGEBYSCORE", "z", "(3", "+inf", "WITHSCORES"])
+            .await,
+        array(&["four", "4"])
+    );
+    client.run(&["HELLO", "3"]).await;
+    assert_eq!(
+        client.run(&["ZRANGE", "z", "1", "2", "WITHSCORES"]).await,
+        RedirsValue::Array(Some(vec![
+            RedirsValue::Array(Some(vec![bulk("two"), RedirsValue::Double(2.0)])),
+            RedirsValue::Array(Some(vec![bulk("half"), RedirsValue::Double(2.5)])),
+        ]))
+    );
+    assert_eq!(
+        client.run(&["ZSCORE", "z", "half"]).await,
+        RedirsValue::Double(2.5)
+    );
+}
+
+#[tokio::test]
+async fn zincrby() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(client.run(&["ZINCRBY", "z", "2", "a"]).await, bulk("2"));
+    assert_eq!(
+        client.run(&["ZINCRBY", "z", "-0.5", "a"]).await,
+        bulk("1.5")
+    );
+    assert_eq!(
+        client.run(&["ZINCRBY", "z", "+inf", "a"]).await,
+        bulk("inf")
+    );
+    assert_eq!(
+        client.run(&["ZINCRBY", "z", "-inf", "a"]).await,
+        error("ERR resulting score is not a number (NaN)")
+    );
+    assert_eq!(
+        client.run(&["ZINCRBY", "z", "one", "a"]).await,
+        error("ERR value is not a valid float")
+    );
+    assert_eq!(client.run(&["ZSCORE", "z", "a"]).await, bulk("inf"));
+    assert_eq!(
+        client.run(&["ZINCRBY", "other", "x", "a"]).await,
+        error("ERR value is not a valid float")
+    );
+    assert_eq!(client.run(&["EXISTS", "other"]).await, int(0));
+    client.run(&["HELLO", "3"]).await;
+    assert_eq!(
+        client.run(&["ZINCRBY", "z", "1", "b"]).await,
+        RedirsValue::Double(1.0)
+    );
+}
+
+#[tokio::test]
+async fn zrank_and_zrevrank() {
+    let mut client = leaderboard().await;
+    for (member, rank) in [("one", 0), ("two", 1), ("three", 2), ("four", 3)] {
+        assert_eq!(client.run(&["ZRANK", "z", member]).await, int(rank));
+        assert_eq!(client.run(&["ZREVRANK", "z", member]).await, int(3 - rank));
+    }
+    assert_eq!(client.run(&["ZRANK", "z", "five"]).await, nil());
+    assert_eq!(client.run(&["ZREVRANK", "missing", "one"]).await, nil());
+    assert_eq!(
+        client.run(&["ZRANK", "z", "three", "WITHSCORE"]).await,
+        RedirsValue::Array(Some(vec![int(2), bulk("3")]))
+    );
+    assert_eq!(
+        client.run(&["ZREVRANK", "z", "three", "withscore"]).await,
+        RedirsValue::Array(Some(vec![int(1), bulk("3")]))
+    );
+    assert_eq!(
+        client.run(&["ZRANK", "z", "five", "WITHSCORE"]).await,
+        RedirsValue::Array(None)
+    );
+    assert_eq!(
+        client.run(&["ZRANK", "z", "one", "WITHSCORES"]).await,
+        error("ERR syntax error")
+    );
+    assert_eq!(
+        client.run(&["ZRANK", "z", "one", "WITHSCORE", "x"]).await,
+        error("ERR wrong number of arguments for 'zrank' command")
+    );
+}
+
+#[tokio::test]
+async fn ranks_follow_interleaved_inserts_and_removals() {
+    let mut client = Client::connect(start().await).await;
+    client
+        .run(&["ZADD", "z", "10", "a", "20", "b", "30", "c"])
+        .await;
+    assert_eq!(client.run(&["ZRANK", "z", "c"]).await, int(2));
+    client.run(&["ZADD", "z", "5", "d", "15", "e"]).await;
+    // d a e b c
+    assert_eq!(client.run(&["ZRANK", "z", "c"]).await, int(4));
+    assert_eq!(client.run(&["ZRANK", "z", "e"]).await, int(2));
+    assert_eq!(client.run(&["ZREM", "z", "a", "d", "nope"]).await, int(2));
+    // e b c
+    assert_eq!(client.run(&["ZRANK", "z", "e"]).await, int(0));
+    assert_eq!(client.run(&["ZREVRANK", "z", "e"]).await, int(2));
+    // moving a member changes its rank and those it passes
+    client.run(&["ZINCRBY", "z", "100", "e"]).await;
+    assert_eq!(client.run(&["ZRANK", "z", "e"]).await, int(2));
+    assert_eq!(client.run(&["ZRANK", "z", "b"]).await, int(0));
+    client.run(&["ZADD", "z", "20", "a"]).await;
+    // a and b tie on 20 and order by member
+    assert_eq!(client.run(&["ZRANK", "z", "a"]).await, int(0));
+    assert_eq!(client.run(&["ZRANK", "z", "b"]).await, int(1));
+    assert_eq!(
+        client.run(&["ZRANGE", "z", "0", "-1"]).await,
+        array(&["a", "b", "c", "e"])
+    );
+}
+
+#[tokio::test]
+async fn zrem_removes_the_emptied_key() {
+    let mut client = leaderboard().await;
+    assert_eq!(client.run(&["ZREM", "z", "one", "one"]).await, int(1));
+    assert_eq!(client.run(&["ZREM", "missing", "one"]).await, int(0));
+    assert_eq!(
+        client.run(&["ZREM", "z", "two", "three", "four"]).await,
+        int(3)
+    );
+    assert_eq!(client.run(&["EXISTS", "z"]).await, int(0));
+}
+
+#[tokio::test]
+async fn zcount_and_zremrangebyscore() {
+    let mut client = leaderboard().await;
+    let cases: &[(&str, &str, i64)] = &[
+        ("-inf", "+inf", 4),
+        ("(1", "3", 2),
+        ("1", "(3", 2),
+        ("(1", "(2", 0),
+        ("4", "1", 0),
+    ];
+    for (min, max, count) in cases {
+        assert_eq!(
+            client.run(&["ZCOUNT", "z", min, max]).await,
+            int(*count),
+            "{min} {max}"
+        );
+    }
+    assert_eq!(
+        client.run(&["ZCOUNT", "missing", "-inf", "+inf"]).await,
+        int(0)
+    );
+    assert_eq!(
+        client.run(&["ZCOUNT", "z", "one", "3"]).await,
+        error("ERR min or max is not a float")
+    );
+    assert_eq!(
+        client.run(&["ZREMRANGEBYSCORE", "z", "(1", "3"]).await,
+        int(2)
+    );
+    assert_eq!(
+        client.run(&["ZRANGE", "z", "0", "-1"]).await,
+        array(&["one", "four"])
+    );
+    assert_eq!(client.run(&["ZRANK", "z", "four"]).await, int(1));
+    assert_eq!(
+        client.run(&["ZREMRANGEBYSCORE", "z", "5", "+inf"]).await,
+        int(0)
+    );
+    assert_eq!(
+        client.run(&["ZREMRANGEBYSCORE", "z", "-inf", "+inf"]).await,
+        int(2)
+    );
+    assert_eq!(client.run(&["EXISTS", "z"]).await, int(0));
+    assert_eq!(
+        client.run(&["ZREMRANGEBYSCORE", "z", "-inf", "x"]).await,
+        error("ERR min or max is not a float")
+    );
+}
+
+// the parser ZRANGEBYSCORE, ZCOUNT and ZREMRANGEBYSCORE share
+#[test]
+fn score_ranges_parse_exclusive_and_infinite_bounds() {
+    let bound = |score, exclusive| ScoreBound { score, exclusive };
+    let range = |min, max| Some(ScoreRange { min, max });
+    let cases: &[(&str, &str, Option<ScoreRange>)] = &[
+        ("1", "2", range(bound(1.0, false), bound(2.0, false))),
+        ("(1", "(2.5", range(bound(1.0, true), bound(2.5, true))),
+        (
+            "-inf",
+            "+inf",
+            range(bound(f64::NEG_INFINITY, false), bound(f64::INFINITY, false)),
+        ),
+        (
+            "(-inf",
+            "inf",
+            range(bound(f64::NEG_INFINITY, true), bound(f64::INFINITY, false)),
+        ),
+        ("1e2", "-3", range(bound(100.0, false), bound(-3.0, false))),
+        ("x", "1", None),
+        ("1", "((1", None),
+        ("[1", "2", None),
+        ("nan", "1", None),
+        (" 1", "2", None),
+        ("", "2", None),
+        ("(", "2", None),
+    ];
+    for (min, max, expected) in cases {
+        let parsed = ScoreRange::parse(min.as_bytes(), max.as_bytes());
+        assert_eq!(parsed, *expected, "{min} {max}");
+    }
+    let range = ScoreRange::parse(b"(1", b"2").unwrap();
+    assert!(range.below(1.0) && !range.below(1.5));
+    assert!(range.contains(2.0) && !range.contains(1.0));
+    assert!(range.above(2.5) && !range.above(2.0));
+}
+
+#[tokio::test]
+async fn zpopmin_and_zpopmax() {
+    let mut client = leaderboard().await;
+    assert_eq!(client.run(&["ZPOPMIN", "z"]).await, array(&["one", "1"]));
+    assert_eq!(client.run(&["ZPOPMAX", "z"]).await, array(&["four", "4"]));
+    assert_eq!(client.run(&["ZPOPMIN", "z", "0"]).await, array(&[]));
+    assert_eq!(
+        client.run(&["ZPOPMIN", "z", "-1"]).await,
+        error("ERR value is out of range, must be positive")
+    );
+    assert_eq!(
+        client.run(&["ZPOPMIN", "z", "1", "2"]).await,
+        error("ERR syntax error")
+    );
+    client.run(&["ZADD", "z", "5", "five"]).await;
+    assert_eq!(
+        client.run(&["ZPOPMAX", "z", "2"]).await,
+        array(&["five", "5", "three", "3"])
+    );
+    assert_eq!(
+        client.run(&["ZPOPMIN", "z", "10"]).await,
+        array(&["two", "2"])
+    );
+    assert_eq!(client.run(&["EXISTS", "z"]).await, int(0));
+    assert_eq!(client.run(&["ZPOPMIN", "z"]).await, array(&[]));
+    assert_eq!(client.run(&["ZPOPMAX", "z", "3"]).await, array(&[]));
+    // a count gets pairs over RESP3, a single pop stays flat
+    client
+        .run(&["ZADD", "z", "1", "a", "2", "b", "3", "c"])
+        .await;
+    client.run(&["HELLO", "3"]).await;
+    assert_eq!(
+        client.run(&["ZPOPMIN", "z"]).await,
+        RedirsValue::Array(Some(vec![bulk("a"), RedirsValue::Double(1.0)]))
+    );
+    assert_eq!(
+        client.run(&["ZPOPMAX", "z", "2"]).await,
+        RedirsValue::Array(Some(vec![
+            RedirsValue::Array(Some(vec![bulk("c"), RedirsValue::Double(3.0)])),
+            RedirsValue::Array(Some(vec![bulk("b"), RedirsValue::Double(2.0)])),
+        ]))
+    );
+}
+
+#[tokio::test]
+async fn sorted_set_commands_on_a_string_are_wrongtype() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "string", "v"]).await;
+    let wrongtype = error("WRONGTYPE Operation against a key holding the wrong kind of value");
+    for args in [
+        &["ZADD", "string", "1", "a"][..],
+        &["ZADD", "string", "XX", "1", "a"],
+        &["ZSCORE", "string", "a"],
+        &["ZCARD", "string"],
+        &["ZRANGE", "string", "0", "-1"],
+        &["ZRANGEBYSCORE", "string", "-inf", "+inf"],
+        &["ZINCRBY", "string", "1", "a"],
+        &["ZRANK", "string", "a"],
+        &["ZREVRANK", "string", "a"],
+        &["ZREM", "string", "a"],
+        &["ZCOUNT", "string", "-inf", "+inf"],
+        &["ZREMRANGEBYSCORE", "string", "-inf", "+inf"],
+        &["ZPOPMIN", "string"],
+        &["ZPOPMAX", "string", "1"],
+    ] {
+        assert_eq!(client.run(args).await, wrongtype, "{args:?}");
+    }
+}
+
+// the skiplist against a BTreeSet of (score, member), through random inserts,
+// moves and removals
+#[test]
+fn sorted_set_matches_an_ordered_model() {
+    let mut rng = rand::rng();
+    let mut zset = SortedSet::new();
+    let mut model = BTreeSet::<(i64, Vec<u8>)>::new();
+    for _ in 0..5000 {
+        let member = format!("m{}", rng.random_range(0..300)).into_bytes();
+        let score = rng.random_range(-20..20);
+        let old = model.iter().find(|(_, m)| *m == member).cloned();
+        if rng.random_ratio(1, 3) {
+            assert_eq!(
+                zset.remove(&member).map(|s| s as i64),
+                old.as_ref().map(|(s, _)| *s)
+            );
+            if let Some(old) = old {
+                model.remove(&old);
+            }
+        } else {
+            zset.insert(member.clone(), score as f64);
+            if let Some(old) = old {
+                model.remove(&old);
+            }
+            model.insert((score, member));
+        }
+    }
+    assert_eq!(zset.len(), model.len());
+    let ordered: Vec<_> = zset.iter().map(|(m, s)| (s as i64, m.to_vec())).collect();
+    assert_eq!(ordered, model.iter().cloned().collect::<Vec<_>>());
+    for (rank, (_, member)) in model.iter().enumerate() {
+        assert_eq!(zset.rank(member), Some(rank));
+        assert_eq!(
+            zset.iter_from(rank).next().map(|(m, _)| m),
+            Some(&member[..])
+        );
+        assert_eq!(
+            zset.iter_rev_from(rank).next().map(|(m, _)| m),
+            Some(&member[..])
+        );
+    }
+    let backwards: Vec<_> = zset
+        .iter_rev_from(zset.len() - 1)
+        .map(|(m, s)| (s as i64, m.to_vec()))
+        .collect();
+    assert_eq!(backwards, model.iter().rev().cloned().collect::<Vec<_>>());
+}
@@ -0,0 +1,811 @@
+mod common;
+
+use common::{array, bulk, error, int, nil, simple, start, start_with, Client};
+use std::time::Duration;
+
+use protocol::RedirsValue;
+use server::{
+    stream::{NewId, Stream, Trim},
+    Db, ManualClock, StreamId,
+};
+
+// a clock at 1000 ms, so auto ids are known ahead
+async fn connect() -> (Client, ManualClock) {
+    let clock = ManualClock::new(1000);
+    let addr = start_with(Db::with_clock(clock.clone())).await;
+    (Client::connect(addr).await, clock)
+}
+
+// [id, [field, value, ...]]
+fn entry(id: &str, fields: &[&str]) -> RedirsValue {
+    RedirsValue::Array(Some(vec![bulk(id), array(fields)]))
+}
+
+fn entries(items: &[RedirsValue]) -> RedirsValue {
+    RedirsValue::Array(Some(items.to_vec()))
+}
+
+#[test]
+fn stream_ids_parse_and_format() {
+    let cases: &[(&str, u64, Option<StreamId>)] = &[
+        ("1-2", 0, Some(StreamId::new(1, 2))),
+        (
+            "1526919030474-55",
+            0,
+            Some(StreamId::new(1526919030474, 55)),
+        ),
+        ("5", 0, Some(StreamId::new(5, 0))),
+        ("5", u64::MAX, Some(StreamId::new(5, u64::MAX))),
+        ("0-0", 0, Some(StreamId::MIN)),
+        (
+            "18446744073709551615-18446744073709551615",
+            0,
+            Some(StreamId::MAX),
+        ),
+        ("18446744073709551616-0", 0, None),
+        ("1-", 0, None),
+        ("-1", 0, None),
+        ("1-2-3", 0, None),
+        ("+1-2", 0, None),
+        (" 1-2", 0, None),
+        ("a-b", 0, None),
+        ("", 0, None),
+    ];
+    for (text, seq, expected) in cases {
+        assert_eq!(StreamId::parse(text.as_bytes(), *seq), *expected, "{text}");
+    }
+    assert_eq!(
+        StreamId::new(1526919030474, 55).to_string(),
+        "1526919030474-55"
+    );
+    assert_eq!(StreamId::MIN.to_string(), "0-0");
+    assert_eq!(StreamId::new(1, u64::MAX).next(), Some(StreamId::new(2, 0)));
+    assert_eq!(StreamId::new(2, 0).prev(), Some(StreamId::new(1, u64::MAX)));
+    assert_eq!(StreamId::MAX.next(), None);
+    assert_eq!(StreamId::MIN.prev(), None);
+    assert_eq!(NewId::parse(b"*"), Some(NewId::Auto));
+    assert_eq!(NewId::parse(b"7-*"), Some(NewId::AutoSeq(7)));
+    assert_eq!(
+        NewId::parse(b"7-1"),
+        Some(NewId::Explicit(StreamId::new(7, 1)))
+    );
+    assert_eq!(NewId::parse(b"x-*"), None);
+}
+
+#[test]
+fn approximate_trims_remove_whole_nodes() {
+    let mut stream = Stream::new();
+    for i in 1..=250 {
+        let id = stream.next_id(NewId::Auto, i).unwrap();
+        stream.add(id, Vec::new());
+    }
+    assert_eq!(stream.trim(Trim::MaxLen(100), Some(0)), 100);
+    assert_eq!(stream.len(), 150);
+    assert_eq!(stream.trim(Trim::MaxLen(100), Some(0)), 0);
+    assert_eq!(stream.trim(Trim::MinId(StreamId::new(200, 0)), None), 99);
+    assert_eq!(stream.len(), 51);
+    assert_eq!(stream.last_id(), StreamId::new(250, 0));
+}
+
+#[tokio::test]
+async fn xadd_gives_out_increasing_ids() {
+    let (mut client, clock) = connect().await;
+    assert_eq!(
+        client.run(&["XADD", "s", "*", "f", "v"]).await,
+        bulk("1000-0")
+    );
+    assert_eq!(
+        client.run(&["XADD", "s", "*", "f", "v"]).await,
+        bulk("1000-1")
+    );
+    clock.set(1005);
+    assert_eq!(
+        client.run(&["XADD", "s", "*", "f", "v"]).await,
+        bulk("1005-0")
+    );
+    // a clock going back does not make ids go back
+    clock.set(900);
+    assert_eq!(
+        client.run(&["XADD", "s", "*", "f", "v"]).await,
+        bulk("1005-1")
+    );
+    assert_eq!(
+        client.run(&["XADD", "s", "2000-*", "f", "v"]).await,
+        bulk("2000-0")
+    );
+    assert_eq!(
+        client.run(&["XADD", "s", "2000-*", "f", "v"]).await,
+        bulk("2000-1")
+    );
+    assert_eq!(
+        client.run(&["XADD", "s", "2000-5", "f", "v"]).await,
+        bulk("2000-5")
+    );
+    assert_eq!(
+        client.run(&["XADD", "s", "3000", "f", "v"]).await,
+        bulk("3000-0")
+    );
+    assert_eq!(client.run(&["XLEN", "s"]).await, int(8));
+    assert_eq!(client.run(&["XLEN", "missing"]).await, int(0));
+    assert_eq!(client.run(&["TYPE", "s"]).await, simple("stream"));
+    let smaller =
+        error("ERR The ID specified in XADD is equal or smaller than the target stream top item");
+    assert_eq!(
+        client.run(&["XADD", "s", "3000-0", "f", "v"]).await,
+        smaller
+    );
+    assert_eq!(
+        client.run(&["XADD", "s", "2999-*", "f", "v"]).await,
+        smaller
+    );
+    assert_eq!(
+        client.run(&["XADD", "new", "0-0", "f", "v"]).await,
+        error("ERR The ID specified in XADD must be greater than 0-0")
+    );
+    // 0-* starts the sequence at 1
+    assert_eq!(
+        client.run(&["XADD", "new", "0-*", "f", "v"]).await,
+        bulk("0-1")
+    );
+    client
+        .run(&[
+            "XADD",
+            "full",
+            "18446744073709551615-18446744073709551615",
+            "f",
+            "v",
+        ])
+        .await;
+    assert_eq!(
+        client.run(&["XADD", "full", "*", "f", "v"]).await,
+        error("ERR The stream has exhausted the last possible ID, unable to add more items")
+    );
+}
+
+#[tokio::test]
+async fn xadd_arguments_are_checked() {
+    let (mut client, _) = connect().await;
+    let cases: &[(&[&str], &str)] = &[
+        (
+            &["s", "1-x", "f", "v"],
+            "ERR Invalid stream ID specified as stream command argument",
+        ),
+        (
+            &["s", "*", "f"],
+            "ERR wrong number of arguments for 'xadd' command",
+        ),
+        (
+            &["s", "*", "f", "v", "g"],
+            "ERR wrong number of arguments for 'xadd' command",
+        ),
+        (
+            &["s", "MAXLEN", "-1", "*", "f", "v"],
+            "ERR The MAXLEN argument must be >= 0.",
+        ),
+        (
+            &["s", "MAXLEN", "x", "*", "f", "v"],
+            "ERR value is not an integer or out of range",
+        ),
+        (
+            &["s", "MINID", "x", "*", "f", "v"],
+            "ERR Invalid stream ID specified as stream command argument",
+        ),
+        (
+            &["s", "MAXLEN", "1", "LIMIT", "10", "*", "f", "v"],
+            "ERR syntax error, LIMIT cannot be used without the special ~ option",
+        ),
+        (
+            &["s", "MAXLEN", "~", "1", "LIMIT", "-1", "*", "f", "v"],
+            "ERR The LIMIT argument must be >= 0.",
+        ),
+    ];
+    for (args, expected) in cases {
+        let mut request = vec!["XADD"];
+        request.extend(*args);
+        assert_eq!(client.run(&request).await, error(expected), "{args:?}");
+    }
+    assert_eq!(client.run(&["EXISTS", "s"]).await, int(0));
+    assert_eq!(
+        client
+            .run(&["XADD", "s", "NOMKSTREAM", "*", "f", "v"])
+            .await,
+        nil()
+    );
+    assert_eq!(client.run(&["EXISTS", "s"]).await, int(0));
+    client.run(&["XADD", "s", "*", "f", "v"]).await;
+    assert_eq!(
+        client
+            .run(&["XADD", "s", "NOMKSTREAM", "*", "f", "v"])
+            .await,
+        bulk("1000-1")
+    );
+}
+
+#[tokio::test]
+async fn xadd_trims_by_length_and_id() {
+    let mut client = Client::connect(start().await).await;
+    for i in 1..=5 {
+        client
+            .run(&[
+                "XADD",
+                "s",
+                "MAXLEN",
+                "3",
+                &format!("{i}-0"),
+                "n",
+                &i.to_string(),
+            ])
+            .await;
+    }
+    assert_eq!(client.run(&["XLEN", "s"]).await, int(3));
+    assert_eq!(
+        client.run(&["XRANGE", "s", "-", "+"]).await,
+        entries(&[
+            entry("3-0", &["n", "3"]),
+            entry("4-0", &["n", "4"]),
+            entry("5-0", &["n", "5"]),
+        ])
+    );
+    client
+        .run(&["XADD", "s", "MINID", "=", "5", "6-0", "n", "6"])
+        .await;
+    assert_eq!(client.run(&["XLEN", "s"]).await, int(2));
+    // MAXLEN 0 leaves an empty stream, which stays
+    client
+        .run(&["XADD", "s", "MAXLEN", "0", "7-0", "n", "7"])
+        .await;
+    assert_eq!(client.run(&["XLEN", "s"]).await, int(0));
+    assert_eq!(client.run(&["EXISTS", "s"]).await, int(1));
+    // the last id stays even with no entries left
+    assert_eq!(
+        client.run(&["XADD", "s", "7-0", "n", "7"]).await,
+        error("ERR The ID specified in XADD is equal or smaller than the target stream top item")
+    );
+    // approximately trimming only removes whole nodes of 100 entries
+    for i in 1..=250 {
+        client.run(&["XADD", "big", "*", "n", &i.to_string()]).await;
+    }
+    client
+        .run(&["XADD", "big", "MAXLEN", "~", "100", "*", "n", "251"])
+        .await;
+    assert_eq!(client.run(&["XLEN", "big"]).await, int(151));
+    client
+        .run(&["XADD", "big", "MAXLEN", "~", "100", "*", "n", "252"])
+        .await;
+    assert_eq!(client.run(&["XLEN", "big"]).await, int(152));
+    client
+        .run(&[
+            "XADD", "big", "MAXLEN", "~", "10", "LIMIT", "50", "*", "n", "253",
+        ])
+        .await;
+    assert_eq!(client.run(&["XLEN", "big"]).await, int(153));
+    client
+        .run(&[
+            "XADD", "big", "MAXLEN", "~", "10", "LIMIT", "100", "*", "n", "254",
+        ])
+        .await;
+    assert_eq!(client.run(&["XLEN", "big"]).await, int(54));
+}
+
+#[tokio::test]
+async fn xrange_and_xrevrange() {
+    let mut client = Client::connect(start().await).await;
+    for id in ["1-0", "1-1", "2-0", "3-5"] {
+        client.run(&["XADD", "s", id, "id", id]).await;
+    }
+    let e = |id| entry(id, &["id", id]);
+    let cases: &[(&str, &str, &[&str])] = &[
+        ("-", "+", &["1-0", "1-1", "2-0", "3-5"]),
+        ("1-1", "2-0", &["1-1", "2-0"]),
+        // a missing sequence takes in the whole millisecond
+        ("1", "1", &["1-0", "1-1"]),
+        ("2", "+", &["2-0", "3-5"]),
+        ("(1-0", "(3-5", &["1-1", "2-0"]),
+        ("(1", "+", &["1-1", "2-0", "3-5"]),
+        ("-", "(2-0", &["1-0", "1-1"]),
+        // a missing end sequence is the highest, so excluding it keeps 2-0
+        ("-", "(2", &["1-0", "1-1", "2-0"]),
+        ("3-6", "+", &[]),
+        ("2-0", "1-0", &[]),
+    ];
+    for (start, end, expected) in cases {
+        let expected: Vec<_> = expected.iter().map(|id| e(id)).collect();
+        assert_eq!(
+            client.run(&["XRANGE", "s", start, end]).await,
+            entries(&expected),
+            "XRANGE {start} {end}"
+        );
+        let reversed: Vec<_> = expected.into_iter().rev().collect();
+        assert_eq!(
+            client.run(&["XREVRANGE", "s", end, start]).await,
+            entries(&reversed),
+            "XREVRANGE {end} {start}"
+        );
+    }
+    assert_eq!(
+        client.run(&["XRANGE", "s", "-", "+", "COUNT", "2"]).await,
+        entries(&[e("1-0"), e("1-1")])
+    );
+    assert_eq!(
+        client
+            .run(&["XREVRANGE", "s", "+", "-", "count", "1"])
+            .await,
+        entries(&[e("3-5")])
+    );
+    assert_eq!(
+        client.run(&["XRANGE", "s", "-", "+", "COUNT", "0"]).await,
+        entries(&[])
+    );
+    assert_eq!(
+        client.run(&["XRANGE", "missing", "-", "+"]).await,
+        entries(&[])
+    );
+    assert_eq!(
+        client.run(&["XRANGE", "s", "x", "+"]).await,
+        error("ERR Invalid stream ID specified as stream command argument")
+    );
+    assert_eq!(
+        client
+            .run(&[
+                "XRANGE",
+                "s",
+                "(18446744073709551615-18446744073709551615",
+                "+"
+            ])
+            .await,
+        error("ERR invalid start ID for the interval")
+    );
+    assert_eq!(
+        client.run(&["XRANGE", "s", "-", "(0-0"]).await,
+        error("ERR invalid end ID for the interval")
+    );
+    assert_eq!(
+        client.run(&["XRANGE", "s", "-", "+", "COUNT"]).await,
+        error("ERR syntax error")
+    );
+}
+
+#[tokio::test]
+async fn entries_keep_their_fields_in_order() {
+    let mut client = Client::connect(start().await).await;
+    client
+        .run(&["XADD", "s", "1-0", "z", "1", "a", "2", "z", "3"])
+        .await;
+    assert_eq!(
+        client.run(&["XRANGE", "s", "-", "+"]).await,
+        entries(&[entry("1-0", &["z", "1", "a", "2", "z", "3"])])
+    );
+}
+
+#[tokio::test]
+async fn xread_reads_after_the_ids_given() {
+    let mut client = Client::connect(start().await).await;
+    for id in ["1-0", "2-0", "3-0"] {
+        client.run(&["XADD", "a", id, "f", "a"]).await;
+    }
+    client.run(&["XADD", "b", "5-0", "f", "b"]).await;
+    let stream =
+        |key, items: &[RedirsValue]| RedirsValue::Array(Some(vec![bulk(key), entries(items)]));
+    assert_eq!(
+        client
+            .run(&["XREAD", "STREAMS", "a", "b", "1-0", "0"])
+            .await,
+        entries(&[
+            stream("a", &[entry("2-0", &["f", "a"]), entry("3-0", &["f", "a"])]),
+            stream("b", &[entry("5-0", &["f", "b"])]),
+        ])
+    );
+    assert_eq!(
+        client
+            .run(&["XREAD", "COUNT", "1", "STREAMS", "a", "b", "0", "0"])
+            .await,
+        entries(&[
+            stream("a", &[entry("1-0", &["f", "a"])]),
+            stream("b", &[entry("5-0", &["f", "b"])]),
+        ])
+    );
+    // streams with nothing new are left out, nil when all are
+    assert_eq!(
+        client
+            .run(&["XREAD", "STREAMS", "a", "b", "missing", "2", "$", "0"])
+            .await,
+        entries(&[stream("a", &[entry("3-0", &["f", "a"])])])
+    );
+    assert_eq!(
+        client.run(&["XREAD", "STREAMS", "a", "b", "$", "$"]).await,
+        RedirsValue::Array(None)
+    );
+    client.run(&["HELLO", "3"]).await;
+    assert_eq!(
+        client
+            .run(&["XREAD", "STREAMS", "a", "b", "2-0", "4"])
+            .await,
+        RedirsValue::Map(
+            [
+                (bulk("a"), entries(&[entry("3-0", &["f", "a"])])),
+                (bulk("b"), entries(&[entry("5-0", &["f", "b"])])),
+            ]
+            .into_iter()
+            .collect()
+        )
+    );
+}
+
+#[tokio::test]
+async fn xread_arguments_are_checked() {
+    let mut client = Client::connect(start().await).await;
+    let unbalanced = error(
+        "ERR Unbalanced 'xread' list of streams: for each stream key an ID or '$' must be specified.",
+    );
+    assert_eq!(
+        client.run(&["XREAD", "STREAMS", "a", "b", "0"]).await,
+        unbalanced
+    );
+    assert_eq!(
+        client.run(&["XREAD", "COUNT", "1", "a", "0"]).await,
+        error("ERR syntax error")
+    );
+    assert_eq!(
+        client.run(&["XREAD", "STREAMS", "a", "x"]).await,
+        error("ERR Invalid stream ID specified as stream command argument")
+    );
+    client.run(&["SET", "string", "v"]).await;
+    let wrongtype = error("WRONGTYPE Operation against a key holding the wrong kind of value");
+    for args in [
+        &["XADD", "string", "*", "f", "v"][..],
+        &["XLEN", "string"],
+        &["XRANGE", "string", "-", "+"],
+        &["XREVRANGE", "string", "+", "-"],
+        &["XREAD", "STREAMS", "string", "0"],
+    ] {
+        assert_eq!(client.run(args).await, wrongtype, "{args:?}");
+    }
+}
+
+// [[key, entries]] as XREADGROUP replies over RESP2 for a single stream
+fn read(key: &str, items: &[RedirsValue]) -> RedirsValue {
+    entries(&[RedirsValue::Array(Some(vec![bulk(key), entries(items)]))])
+}
+
+#[tokio::test]
+async fn xgroup_creates_and_destroys_groups() {
+    let (mut client, _) = connect().await;
+    assert_eq!(
+        client.run(&["XGROUP", "CREATE", "s", "g", "$"]).await,
+        error("ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.")
+    );
+    assert_eq!(
+        client
+            .run(&["XGROUP", "CREATE", "s", "g", "$", "MKSTREAM"])
+            .await,
+        simple("OK")
+    );
+    assert_eq!(client.run(&["XLEN", "s"]).await, int(0));
+    assert_eq!(
+        client.run(&["XGROUP", "CREATE", "s", "g", "0"]).await,
+        error("BUSYGROUP Consumer Group name already exists")
+    );
+    assert_eq!(
+        client
+            .run(&["XGROUP", "CREATECONSUMER", "s", "g", "alice"])
+            .await,
+        int(1)
+    );
+    assert_eq!(
+        client
+            .run(&["XGROUP", "CREATECONSUMER", "s", "g", "alice"])
+            .await,
+        int(0)
+    );
+    assert_eq!(
+        client
+            .run(&["XGROUP", "CREATECONSUMER", "s", "other", "alice"])
+            .await,
+        error("NOGROUP No such consumer group 'other' for key name 's'")
+    );
+    assert_eq!(client.run(&["XGROUP", "DESTROY", "s", "g"]).await, int(1));
+    assert_eq!(client.run(&["XGROUP", "DESTROY", "s", "g"]).await, int(0));
+    assert_eq!(
+        client.run(&["XGROUP", "NOPE", "s"]).await,
+        error("ERR unknown subcommand 'NOPE'. Try XGROUP HELP.")
+    );
+    assert_eq!(
+        client.run(&["XGROUP", "CREATE", "s"]).await,
+        error("ERR wrong number of arguments for 'xgroup|create' command")
+    );
+    // a group made at `$` only sees what comes after
+    client.run(&["XADD", "s", "1-0", "f", "old"]).await;
+    client.run(&["XGROUP", "CREATE", "s", "late", "$"]).await;
+    client.run(&["XADD", "s", "2-0", "f", "new"]).await;
+    assert_eq!(
+        client
+            .run(&["XREADGROUP", "GROUP", "late", "c", "STREAMS", "s", ">"])
+            .await,
+        read("s", &[entry("2-0", &["f", "new"])])
+    );
+}
+
+#[tokio::test]
+async fn consumers_share_a_stream_and_recover_what_others_left() {
+    let (mut client, clock) = connect().await;
+    for id in ["1-0", "2-0", "3-0", "4-0"] {
+        client.run(&["XADD", "jobs", id, "job", id]).await;
+    }
+    client
+        .run(&["XGROUP", "CREATE", "jobs", "workers", "0"])
+        .await;
+    let job = |id: &str| entry(id, &["job", id]);
+
+    // each new entry goes to a single consumer
+    assert_eq!(
+        client
+            .run(&[
+                "XREADGROUP",
+                "GROUP",
+                "workers",
+                "alice",
+                "COUNT",
+                "2",
+                "STREAMS",
+                "jobs",
+                ">"
+            ])
+            .await,
+        read("jobs", &[job("1-0"), job("2-0")])
+    );
+    assert_eq!(
+        client
+            .run(&[
+                "XREADGROUP",
+                "GROUP",
+                "workers",
+                "bob",
+                "STREAMS",
+                "jobs",
+                ">"
+            ])
+            .await,
+        read("jobs", &[job("3-0"), job("4-0")])
+    );
+    assert_eq!(
+        client
+            .run(&[
+                "XREADGROUP",
+                "GROUP",
+                "workers",
+                "bob",
+                "STREAMS",
+                "jobs",
+                ">"
+            ])
+            .await,
+        RedirsValue::Array(None)
+    );
+
+    // bob gets through his, alice crashes with hers unacknowledged
+    assert_eq!(
+        client
+            .run(&["XACK", "jobs", "workers", "3-0", "4-0", "4-0", "9-0"])
+            .await,
+        int(2)
+    );
+    clock.advance(Duration::from_millis(5000));
+    assert_eq!(
+        client.run(&["XPENDING", "jobs", "workers"]).await,
+        entries(&[
+            int(2),
+            bulk("1-0"),
+            bulk("2-0"),
+            entries(&[array(&["alice", "2"])]),
+        ])
+    );
+    let pending = |id: &str, consumer: &str, idle: i64, deliveries: i64| {
+        entries(&[bulk(id), bulk(consumer), int(idle), int(deliveries)])
+    };
+    assert_eq!(
+        client
+            .run(&["XPENDING", "jobs", "workers", "-", "+", "10"])
+            .await,
+        entries(&[
+            pending("1-0", "alice", 5000, 1),
+            pending("2-0", "alice", 5000, 1),
+        ])
+    );
+    assert_eq!(
+        client
+            .run(&["XPENDING", "jobs", "workers", "IDLE", "6000", "-", "+", "10"])
+            .await,
+        entries(&[])
+    );
+
+    // alice's history is there for her when she comes back ...
+    assert_eq!(
+        client
+            .run(&[
+                "XREADGROUP",
+                "GROUP",
+                "workers",
+                "alice",
+                "STREAMS",
+                "jobs",
+                "0"
+            ])
+            .await,
+        read("jobs", &[job("1-0"), job("2-0")])
+    );
+    assert_eq!(
+        client
+            .run(&[
+                "XREADGROUP",
+                "GROUP",
+                "workers",
+                "bob",
+                "STREAMS",
+                "jobs",
+                "0"
+            ])
+            .await,
+        read("jobs", &[])
+    );
+
+    // ... but bob claims what has been idle long enough
+    clock.advance(Duration::from_millis(1000));
+    assert_eq!(
+        client
+            .run(&["XCLAIM", "jobs", "workers", "bob", "2000", "1-0", "2-0"])
+            .await,
+        entries(&[])
+    );
+    clock.advance(Duration::from_millis(2000));
+    assert_eq!(
+        client
+            .run(&["XCLAIM", "jobs", "workers", "bob", "2000", "1-0", "JUSTID"])
+            .await,
+        array(&["1-0"])
+    );
+    assert_eq!(
+        client
+            .run(&["XCLAIM", "jobs", "workers", "bob", "2000", "2-0"])
+            .await,
+        entries(&[job("2-0")])
+    );
+    assert_eq!(
+        client
+            .run(&["XPENDING", "jobs", "workers", "-", "+", "10", "bob"])
+            .await,
+        entries(&[pending("1-0", "bob", 0, 2), pending("2-0", "bob", 0, 3)])
+    );
+    assert_eq!(
+        client
+            .run(&["XPENDING", "jobs", "workers", "-", "+", "10", "alice"])
+            .await,
+        entries(&[])
+    );
+
+    // acknowledging empties the pending entries
+    assert_eq!(
+        client.run(&["XACK", "jobs", "workers", "1-0", "2-0"]).await,
+        int(2)
+    );
+    assert_eq!(
+        client.run(&["XPENDING", "jobs", "workers"]).await,
+        entries(&[int(0), nil(), nil(), RedirsValue::Array(None)])
+    );
+}
+
+#[tokio::test]
+async fn reading_a_group_handles_noack_and_deleted_entries() {
+    let (mut client, _) = connect().await;
+    client.run(&["XADD", "s", "1-0", "f", "v"]).await;
+    client.run(&["XADD", "s", "2-0", "f", "v"]).await;
+    client.run(&["XGROUP", "CREATE", "s", "g", "0"]).await;
+    client
+        .run(&[
+            "XREADGROUP",
+            "GROUP",
+            "g",
+            "c",
+            "COUNT",
+            "1",
+            "NOACK",
+            "STREAMS",
+            "s",
+            ">",
+        ])
+        .await;
+    client
+        .run(&["XREADGROUP", "GROUP", "g", "c", "STREAMS", "s", ">"])
+        .await;
+    // only the entry read without NOACK is pending, and once trimmed away
+    // it comes back without its fields
+    client
+        .run(&["XADD", "s", "MAXLEN", "1", "3-0", "f", "v"])
+        .await;
+    assert_eq!(
+        client
+            .run(&["XREADGROUP", "GROUP", "g", "c", "STREAMS", "s", "0"])
+            .await,
+        read(
+            "s",
+            &[RedirsValue::Array(Some(vec![
+                bulk("2-0"),
+                RedirsValue::Array(None)
+            ]))]
+        )
+    );
+    // claiming it drops it from the pending entries
+    assert_eq!(
+        client.run(&["XCLAIM", "s", "g", "c", "0", "2-0"]).await,
+        entries(&[])
+    );
+    assert_eq!(
+        client.run(&["XPENDING", "s", "g"]).await,
+        entries(&[int(0), nil(), nil(), RedirsValue::Array(None)])
+    );
+
+    client.run(&["HELLO", "3"]).await;
+    assert_eq!(
+        client
+            .run(&["XREADGROUP", "GROUP", "g", "c", "STREAMS", "s", ">"])
+            .await,
+        RedirsValue::Map(
+            [(bulk("s"), entries(&[entry("3-0", &["f", "v"])]))]
+                .into_iter()
+                .collect()
+        )
+    );
+}
+
+#[tokio::test]
+async fn group_arguments_are_checked() {
+    let (mut client, _) = connect().await;
+    client.run(&["XADD", "s", "1-0", "f", "v"]).await;
+    client.run(&["XGROUP", "CREATE", "s", "g", "0"]).await;
+    assert_eq!(
+        client
+            .run(&["XREADGROUP", "GROUP", "missing", "c", "STREAMS", "s", ">"])
+            .await,
+        error(
+            "NOGROUP No such key 's' or consumer group 'missing' in XREADGROUP with GROUP option"
+        )
+    );
+    assert_eq!(
+        client
+            .run(&["XREADGROUP", "COUNT", "1", "STREAMS", "s", ">", "x"])
+            .await,
+        error("ERR Missing GROUP option for XREADGROUP")
+    );
+    assert_eq!(
+        client
+            .run(&["XREADGROUP", "GROUP", "g", "c", "STREAMS", "s", "$"])
+            .await,
+        error("ERR The $ ID is meaningless in the context of XREADGROUP")
+    );
+    assert_eq!(
+        client
+            .run(&["XREADGROUP", "GROUP", "g", "c", "STREAMS", "s", "t", ">"])
+            .await,
+        error("ERR Unbalanced 'xreadgroup' list of streams: for each stream key an ID or '$' must be specified.")
+    );
+    assert_eq!(client.run(&["XACK", "missing", "g", "1-0"]).await, int(0));
+    assert_eq!(
+        client.run(&["XPENDING", "s", "missing"]).await,
+        error("NOGROUP No such key 's' or consumer group 'missing'")
+    );
+    assert_eq!(
+        client.run(&["XCLAIM", "s", "g", "c", "x", "1-0"]).await,
+        error("ERR Invalid min-idle-time argument for XCLAIM")
+    );
+    assert_eq!(
+        client
+            .run(&["XCLAIM", "s", "g", "c", "0", "1-0", "NOPE"])
+            .await,
+        error("ERR Unrecognized XCLAIM option 'NOPE'")
+    );
+    assert_eq!(
+        client
+            .run(&["XCLAIM", "s", "missing", "c", "0", "1-0"])
+            .await,
+        error("NOGROUP No such key 's' or consumer group 'missing'")
+    );
+}
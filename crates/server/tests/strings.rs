@@ -0,0 +1,502 @@
+mod common;
+
+use common::{bulk, error, int, start, Client};
+use protocol::RedirsValue;
+use server::{Db, ManualClock};
+
+const NOT_AN_INTEGER: &str = "ERR value is not an integer or out of range";
+
+#[tokio::test]
+async fn incr_and_decr() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(client.run(&["INCR", "n"]).await, int(1));
+    assert_eq!(client.run(&["INCR", "n"]).await, int(2));
+    assert_eq!(client.run(&["DECR", "n"]).await, int(1));
+    assert_eq!(client.run(&["DECR", "m"]).await, int(-1));
+    assert_eq!(client.run(&["INCRBY", "n", "41"]).await, int(42));
+    assert_eq!(client.run(&["DECRBY", "n", "-8"]).await, int(50));
+    assert_eq!(client.run(&["GET", "n"]).await, bulk("50"));
+    client.run(&["SET", "s", "-7"]).await;
+    assert_eq!(client.run(&["INCRBY", "s", "10"]).await, int(3));
+}
+
+#[tokio::test]
+async fn incr_rejects_values_that_are_not_integers() {
+    let mut client = Client::connect(start().await).await;
+    for value in [
+        "abc",
+        "",
+        " 1",
+        "1 ",
+        "+1",
+        "01",
+        "-0",
+        "1.5",
+        "99999999999999999999",
+    ] {
+        client.run(&["SET", "k", value]).await;
+        assert_eq!(
+            client.run(&["INCR", "k"]).await,
+            error(NOT_AN_INTEGER),
+            "{value:?}"
+        );
+        assert_eq!(client.run(&["GET", "k"]).await, bulk(value));
+    }
+    assert_eq!(
+        client.run(&["INCRBY", "n", "x"]).await,
+        error(NOT_AN_INTEGER)
+    );
+}
+
+#[tokio::test]
+async fn incr_detects_overflow() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "k", "9223372036854775807"]).await;
+    assert_eq!(
+        client.run(&["INCR", "k"]).await,
+        error("ERR increment or decrement would overflow")
+    );
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("9223372036854775807"));
+    client.run(&["SET", "k", "-9223372036854775808"]).await;
+    assert_eq!(
+        client.run(&["DECR", "k"]).await,
+        error("ERR increment or decrement would overflow")
+    );
+    assert_eq!(
+        client.run(&["DECRBY", "k", "-9223372036854775808"]).await,
+        error("ERR decrement would overflow")
+    );
+}
+
+#[tokio::test]
+async fn incrbyfloat() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        client.run(&["INCRBYFLOAT", "f", "10.5"]).await,
+        bulk("10.5")
+    );
+    assert_eq!(client.run(&["INCRBYFLOAT", "f", "0.1"]).await, bulk("10.6"));
+    assert_eq!(client.run(&["INCRBYFLOAT", "f", "-5.6"]).await, bulk("5"));
+    assert_eq!(
+        client.run(&["INCRBYFLOAT", "f", "5.0e3"]).await,
+        bulk("5005")
+    );
+    assert_eq!(client.run(&["GET", "f"]).await, bulk("5005"));
+    // integers are floats as well, and INCR reads the result back
+    assert_eq!(client.run(&["INCR", "f"]).await, int(5006));
+    assert_eq!(
+        client.run(&["INCRBYFLOAT", "g", "0.00001"]).await,
+        bulk("0.00001")
+    );
+}
+
+#[tokio::test]
+async fn incrbyfloat_errors() {
+    let mut client = Client::connect(start().await).await;
+    let invalid = error("ERR value is not a valid float");
+    assert_eq!(client.run(&["INCRBYFLOAT", "f", "abc"]).await, invalid);
+    assert_eq!(client.run(&["INCRBYFLOAT", "f", "inf"]).await, invalid);
+    assert_eq!(client.run(&["INCRBYFLOAT", "f", " 1"]).await, invalid);
+    client.run(&["SET", "s", "1.5x"]).await;
+    assert_eq!(client.run(&["INCRBYFLOAT", "s", "1"]).await, invalid);
+    client.run(&["SET", "f", "1.7e308"]).await;
+    assert_eq!(
+        client.run(&["INCRBYFLOAT", "f", "1.7e308"]).await,
+        error("ERR increment would produce NaN or Infinity")
+    );
+}
+
+#[tokio::test]
+async fn counters_keep_their_deadline() {
+    let db = Db::with_clock(ManualClock::new(1_000_000));
+    let mut client = Client::connect(common::start_with(db).await).await;
+    client.run(&["SET", "n", "1", "PX", "5000"]).await;
+    client.run(&["INCR", "n"]).await;
+    client.run(&["INCRBYFLOAT", "n", "1.5"]).await;
+    assert_eq!(client.run(&["PTTL", "n"]).await, int(5000));
+}
+
+#[tokio::test]
+async fn concurrent_increments_are_never_lost() {
+    let addr = start().await;
+    let tasks: Vec<_> = (0..16)
+        .map(|_| {
+            tokio::spawn(async move {
+                let mut client = Client::connect(addr).await;
+                for _ in 0..200 {
+                    client.run(&["INCR", "counter"]).await;
+                }
+            })
+        })
+        .collect();
+    for task in tasks {
+        task.await.unwrap();
+    }
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.run(&["GET", "counter"]).await, bulk("3200"));
+}
+
+#[tokio::test]
+async fn counters_on_other_types_are_wrongtype() {
+    let db = Db::new();
+    db.lock().get_or_create_list(b"l").unwrap();
+    let mut client = Client::connect(common::start_with(db).await).await;
+    let wrongtype = error("WRONGTYPE Operation against a key holding the wrong kind of value");
+    assert_eq!(client.run(&["INCR", "l"]).await, wrongtype);
+    assert_eq!(client.run(&["INCRBYFLOAT", "l", "1"]).await, wrongtype);
+}
+
+#[tokio::test]
+async fn append_and_strlen() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(client.run(&["STRLEN", "k"]).await, int(0));
+    assert_eq!(client.run(&["APPEND", "k", "Hello"]).await, int(5));
+    assert_eq!(client.run(&["APPEND", "k", " World"]).await, int(11));
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("Hello World"));
+    assert_eq!(client.run(&["STRLEN", "k"]).await, int(11));
+    client.run(&["SET", "n", "41"]).await;
+    client.run(&["APPEND", "n", "0"]).await;
+    assert_eq!(client.run(&["INCR", "n"]).await, int(411));
+}
+
+#[tokio::test]
+async fn getrange() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "k", "This is a string"]).await;
+    let cases = [
+        ("0", "3", "This"),
+        ("-3", "-1", "ing"),
+        ("0", "-1", "This is a string"),
+        ("10", "100", "string"),
+        ("-100", "3", "This"),
+        // both ends clamp to the first byte, as in redis
+        ("-100", "-50", "T"),
+        ("5", "2", ""),
+        ("-1", "-5", ""),
+        ("20", "30", ""),
+        ("15", "15", "g"),
+    ];
+    for (start, end, expected) in cases {
+        assert_eq!(
+            client.run(&["GETRANGE", "k", start, end]).await,
+            bulk(expected),
+            "{start} {end}"
+        );
+    }
+    assert_eq!(
+        client.run(&["GETRANGE", "missing", "0", "-1"]).await,
+        bulk("")
+    );
+    client.run(&["SET", "empty", ""]).await;
+    assert_eq!(
+        client.run(&["GETRANGE", "empty", "0", "-1"]).await,
+        bulk("")
+    );
+    assert_eq!(
+        client.run(&["GETRANGE", "k", "a", "1"]).await,
+        error(NOT_AN_INTEGER)
+    );
+}
+
+#[tokio::test]
+async fn setrange() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["SET", "k", "Hello World"]).await;
+    assert_eq!(client.run(&["SETRANGE", "k", "6", "Redis"]).await, int(11));
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("Hello Redis"));
+    assert_eq!(
+        client.run(&["SETRANGE", "k", "6", "Redirs!"]).await,
+        int(13)
+    );
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("Hello Redirs!"));
+    // the gap before the offset is zero padded
+    assert_eq!(client.run(&["SETRANGE", "pad", "3", "ab"]).await, int(5));
+    assert_eq!(client.run(&["GET", "pad"]).await, bulk("\0\0\0ab"));
+    assert_eq!(client.run(&["SETRANGE", "pad", "7", "c"]).await, int(8));
+    assert_eq!(client.run(&["GET", "pad"]).await, bulk("\0\0\0ab\0\0c"));
+    // an empty value writes nothing, not even a missing key
+    assert_eq!(client.run(&["SETRANGE", "none", "10", ""]).await, int(0));
+    assert_eq!(client.run(&["GET", "none"]).await, common::nil());
+    assert_eq!(client.run(&["SETRANGE", "k", "100", ""]).await, int(13));
+}
+
+#[tokio::test]
+async fn setrange_limits() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(
+        client.run(&["SETRANGE", "k", "-1", "a"]).await,
+        error("ERR offset is out of range")
+    );
+    let too_long = error("ERR string exceeds maximum allowed size (proto-max-bulk-len)");
+    assert_eq!(
+        client.run(&["SETRANGE", "k", "536870912", "a"]).await,
+        too_long
+    );
+    assert_eq!(
+        client
+            .run(&["SETRANGE", "k", "9223372036854775807", "a"])
+            .await,
+        too_long
+    );
+    assert_eq!(client.run(&["STRLEN", "k"]).await, int(0));
+}
+
+#[tokio::test]
+async fn byte_commands_on_other_types_are_wrongtype() {
+    let db = Db::new();
+    db.lock().get_or_create_set(b"s").unwrap();
+    let mut client = Client::connect(common::start_with(db).await).await;
+    let wrongtype = error("WRONGTYPE Operation against a key holding the wrong kind of value");
+    assert_eq!(client.run(&["APPEND", "s", "x"]).await, wrongtype);
+    assert_eq!(client.run(&["STRLEN", "s"]).await, wrongtype);
+    assert_eq!(client.run(&["GETRANGE", "s", "0", "1"]).await, wrongtype);
+    assert_eq!(client.run(&["SETRANGE", "s", "0", "x"]).await, wrongtype);
+}
+
+#[tokio::test]
+async fn mset_and_mget() {
+    let db = Db::new();
+    db.lock().get_or_create_list(b"list").unwrap();
+    let mut client = Client::connect(common::start_with(db).await).await;
+    assert_eq!(
+        client.run(&["MSET", "a", "1", "b", "2", "a", "3"]).await,
+        common::simple("OK")
+    );
+    assert_eq!(
+        client.run(&["MGET", "a", "list", "b", "missing"]).await,
+        RedirsValue::Array(Some(vec![
+            bulk("3"),
+            common::nil(),
+            bulk("2"),
+            common::nil()
+        ]))
+    );
+    // MSET overwrites keys of any type
+    client.run(&["MSET", "list", "x"]).await;
+    assert_eq!(client.run(&["GET", "list"]).await, bulk("x"));
+    for args in [&["MSET", "a"][..], &["MSET", "a", "1", "b"], &["MSET"]] {
+        assert_eq!(
+            client.run(args).await,
+            error("ERR wrong number of arguments for 'mset' command")
+        );
+    }
+    assert_eq!(
+        client.run(&["MGET"]).await,
+        error("ERR wrong number of arguments for 'mget' command")
+    );
+}
+
+#[tokio::test]
+async fn msetnx_sets_all_or_nothing() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(client.run(&["MSETNX", "a", "1", "b", "2"]).await, int(1));
+    assert_eq!(client.run(&["MSETNX", "c", "3", "a", "4"]).await, int(0));
+    assert_eq!(
+        client.run(&["MGET", "a", "b", "c"]).await,
+        RedirsValue::Array(Some(vec![bulk("1"), bulk("2"), common::nil()]))
+    );
+    assert_eq!(
+        client.run(&["MSETNX", "c", "3", "d"]).await,
+        error("ERR wrong number of arguments for 'msetnx' command")
+    );
+}
+
+const START: u64 = 1_700_000_000_000;
+
+async fn with_clock() -> (Client, ManualClock) {
+    let clock = ManualClock::new(START);
+    let addr = common::start_with(Db::with_clock(clock.clone())).await;
+    (Client::connect(addr).await, clock)
+}
+
+#[tokio::test]
+async fn getdel() {
+    let db = Db::new();
+    db.lock().get_or_create_hash(b"h").unwrap();
+    let mut client = Client::connect(common::start_with(db).await).await;
+    client.run(&["SET", "k", "v"]).await;
+    assert_eq!(client.run(&["GETDEL", "k"]).await, bulk("v"));
+    assert_eq!(client.run(&["EXISTS", "k"]).await, int(0));
+    assert_eq!(client.run(&["GETDEL", "k"]).await, common::nil());
+    assert_eq!(
+        client.run(&["GETDEL", "h"]).await,
+        error("WRONGTYPE Operation against a key holding the wrong kind of value")
+    );
+    assert_eq!(client.run(&["EXISTS", "h"]).await, int(1));
+}
+
+#[tokio::test]
+async fn getex_sets_the_deadline() {
+    let (mut client, _) = with_clock().await;
+    client.run(&["SET", "k", "v"]).await;
+    let (exat, pxat) = ((START / 1000 + 20).to_string(), (START + 5).to_string());
+    let cases = [
+        (vec!["EX", "10"], 10_000),
+        (vec!["PX", "1234"], 1234),
+        (vec!["ex", "3"], 3000),
+        (vec!["EXAT", &exat], 20_000),
+        (vec!["PXAT", &pxat], 5),
+    ];
+    for (opts, pttl) in cases {
+        let mut args = vec!["GETEX", "k"];
+        args.extend(opts.iter().copied());
+        assert_eq!(client.run(&args).await, bulk("v"), "{opts:?}");
+        assert_eq!(client.run(&["PTTL", "k"]).await, int(pttl), "{opts:?}");
+    }
+}
+
+#[tokio::test]
+async fn getex_persist_and_no_options() {
+    let (mut client, clock) = with_clock().await;
+    client.run(&["SET", "k", "v", "PX", "100"]).await;
+    // without options the deadline stays as it was, like GET
+    assert_eq!(client.run(&["GETEX", "k"]).await, bulk("v"));
+    assert_eq!(client.run(&["PTTL", "k"]).await, int(100));
+    assert_eq!(client.run(&["GETEX", "k", "PERSIST"]).await, bulk("v"));
+    assert_eq!(client.run(&["PTTL", "k"]).await, int(-1));
+    // PERSIST on a key without a deadline changes nothing
+    assert_eq!(client.run(&["GETEX", "k", "persist"]).await, bulk("v"));
+    assert_eq!(client.run(&["PTTL", "k"]).await, int(-1));
+    clock.advance(std::time::Duration::from_secs(1));
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("v"));
+}
+
+#[tokio::test]
+async fn getex_in_the_past_deletes_after_reading() {
+    let (mut client, _) = with_clock().await;
+    client.run(&["SET", "k", "v"]).await;
+    assert_eq!(client.run(&["GETEX", "k", "PXAT", "1"]).await, bulk("v"));
+    assert_eq!(client.run(&["PTTL", "k"]).await, int(-2));
+    assert_eq!(client.run(&["GETEX", "k", "EX", "10"]).await, common::nil());
+    assert_eq!(client.run(&["EXISTS", "k"]).await, int(0));
+}
+
+#[tokio::test]
+async fn getex_errors_leave_the_deadline() {
+    let (mut client, _) = with_clock().await;
+    client.run(&["SET", "k", "v", "EX", "50"]).await;
+    let syntax = error("ERR syntax error");
+    let invalid = error("ERR invalid expire time in 'getex' command");
+    let cases = [
+        (&["EX", "10", "PX", "10"][..], &syntax),
+        (&["EX", "10", "PERSIST"], &syntax),
+        (&["PERSIST", "EX", "10"], &syntax),
+        (&["PERSIST", "PERSIST"], &syntax),
+        (&["EX"], &syntax),
+        (&["KEEPTTL"], &syntax),
+        (&["SOON", "10"], &syntax),
+        (&["EX", "0"], &invalid),
+        (&["PX", "-5"], &invalid),
+        (&["EX", "9223372036854775807"], &invalid),
+    ];
+    for (opts, expected) in cases {
+        let mut args = vec!["GETEX", "k"];
+        args.extend(opts);
+        assert_eq!(&client.run(&args).await, expected, "{opts:?}");
+        assert_eq!(client.run(&["TTL", "k"]).await, int(50), "{opts:?}");
+    }
+    assert_eq!(
+        client.run(&["GETEX", "k", "EX", "ten"]).await,
+        error(NOT_AN_INTEGER)
+    );
+}
+
+// the state a key is in before a SET
+#[derive(Debug, Clone, Copy)]
+enum Before {
+    Absent,
+    WithTtl,
+    WithoutTtl,
+}
+
+#[tokio::test]
+async fn set_option_matrix() {
+    let (mut client, _) = with_clock().await;
+    let states = [Before::Absent, Before::WithTtl, Before::WithoutTtl];
+    for (i, before) in states.into_iter().enumerate() {
+        for condition in [Some("NX"), Some("XX"), None] {
+            for get in [true, false] {
+                for keep_ttl in [true, false] {
+                    let key = format!("k:{i}:{condition:?}:{get}:{keep_ttl}");
+                    match before {
+                        Before::Absent => {}
+                        Before::WithTtl => {
+                            client.run(&["SET", &key, "old", "PX", "5000"]).await;
+                        }
+                        Before::WithoutTtl => {
+                            client.run(&["SET", &key, "old"]).await;
+                        }
+                    }
+                    let mut args = vec!["SET", &key, "new"];
+                    args.extend(condition);
+                    args.extend(get.then_some("GET"));
+                    args.extend(keep_ttl.then_some("KEEPTTL"));
+                    let reply = client.run(&args).await;
+
+                    let present = !matches!(before, Before::Absent);
+                    let applied = match condition {
+                        Some("NX") => !present,
+                        Some("XX") => present,
+                        _ => true,
+                    };
+                    let expected = match (get, applied, present) {
+                        (true, _, true) => bulk("old"),
+                        (true, _, false) => common::nil(),
+                        (false, true, _) => common::simple("OK"),
+                        (false, false, _) => common::nil(),
+                    };
+                    assert_eq!(reply, expected, "{args:?} on {before:?}");
+
+                    let value = match (applied, present) {
+                        (true, _) => bulk("new"),
+                        (false, true) => bulk("old"),
+                        (false, false) => common::nil(),
+                    };
+                    assert_eq!(client.run(&["GET", &key]).await, value, "{args:?}");
+                    let pttl = match (before, applied, keep_ttl) {
+                        (Before::Absent, false, _) => -2,
+                        (Before::WithTtl, false, _) | (Before::WithTtl, true, true) => 5000,
+                        _ => -1,
+                    };
+                    assert_eq!(client.run(&["PTTL", &key]).await, int(pttl), "{args:?}");
+                }
+            }
+        }
+    }
+}
+
+#[tokio::test]
+async fn set_get_on_other_types_is_wrongtype_with_any_condition() {
+    let db = Db::new();
+    db.lock().get_or_create_list(b"l").unwrap();
+    let mut client = Client::connect(common::start_with(db).await).await;
+    let wrongtype = error("WRONGTYPE Operation against a key holding the wrong kind of value");
+    for condition in [&["NX"][..], &["XX"], &[]] {
+        let mut args = vec!["SET", "l", "v", "GET"];
+        args.extend(condition);
+        assert_eq!(client.run(&args).await, wrongtype, "{args:?}");
+        assert_eq!(client.run(&["GET", "l"]).await, wrongtype);
+    }
+    // without GET the type of the old value does not matter
+    assert_eq!(client.run(&["SET", "l", "v", "NX"]).await, common::nil());
+    assert_eq!(
+        client.run(&["SET", "l", "v", "XX"]).await,
+        common::simple("OK")
+    );
+    assert_eq!(client.run(&["GET", "l"]).await, bulk("v"));
+}
+
+#[tokio::test]
+async fn set_xx_on_an_expired_key_is_a_missing_key() {
+    let (mut client, clock) = with_clock().await;
+    client.run(&["SET", "k", "old", "PX", "10"]).await;
+    clock.advance(std::time::Duration::from_millis(11));
+    assert_eq!(
+        client.run(&["SET", "k", "v", "XX", "GET"]).await,
+        common::nil()
+    );
+    assert_eq!(
+        client.run(&["SET", "k", "v", "NX"]).await,
+        common::simple("OK")
+    );
+}
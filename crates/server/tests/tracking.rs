@@ -0,0 +1,209 @@
+mod common;
+
+use common::{array, bulk, error, simple, start, start_with, Client};
+use protocol::RedirsValue;
+use server::Db;
+
+async fn resp3(addr: std::net::SocketAddr) -> Client {
+    let mut client = Client::connect(addr).await;
+    client.run(&["HELLO", "3"]).await;
+    client
+}
+
+async fn id(client: &mut Client) -> String {
+    match client.run(&["CLIENT", "ID"]).await {
+        RedirsValue::Integer(id) => id.to_string(),
+        other => panic!("expected an id, got {other:?}"),
+    }
+}
+
+fn invalidate(keys: &[&str]) -> RedirsValue {
+    RedirsValue::Push(vec![RedirsValue::from("invalidate"), array(keys)])
+}
+
+#[tokio::test]
+async fn reads_are_invalidated_once() {
+    let addr = start().await;
+    let mut reader = resp3(addr).await;
+    let mut writer = Client::connect(addr).await;
+    writer.run(&["SET", "foo", "1"]).await;
+    assert_eq!(
+        reader.run(&["CLIENT", "TRACKING", "ON"]).await,
+        simple("OK")
+    );
+    assert_eq!(reader.run(&["GET", "foo"]).await, bulk("1"));
+    writer.run(&["SET", "foo", "2"]).await;
+    // the push comes before the reply to what the reader sends next
+    reader.send(b"*1\r\n$4\r\nPING\r\n").await;
+    assert_eq!(reader.reply().await, invalidate(&["foo"]));
+    assert_eq!(reader.reply().await, simple("PONG"));
+    // not read again since, so not tracked anymore
+    writer.run(&["SET", "foo", "3"]).await;
+    assert_eq!(reader.run(&["PING"]).await, simple("PONG"));
+    // missing keys are tracked too, as are those read by any read command
+    reader.run(&["GET", "missing"]).await;
+    reader.run(&["STRLEN", "foo"]).await;
+    writer.run(&["MSET", "missing", "x", "foo", "4"]).await;
+    assert_eq!(reader.reply().await, invalidate(&["foo", "missing"]));
+}
+
+#[tokio::test]
+async fn expiry_and_deletes_invalidate() {
+    let addr = start().await;
+    let mut reader = resp3(addr).await;
+    let mut writer = Client::connect(addr).await;
+    writer.run(&["SET", "k", "v"]).await;
+    reader.run(&["CLIENT", "TRACKING", "ON"]).await;
+    reader.run(&["GET", "k"]).await;
+    writer.run(&["DEL", "k"]).await;
+    assert_eq!(reader.reply().await, invalidate(&["k"]));
+    writer.run(&["RPUSH", "l", "a"]).await;
+    reader.run(&["LRANGE", "l", "0", "-1"]).await;
+    writer.run(&["LPOP", "l"]).await;
+    assert_eq!(reader.reply().await, invalidate(&["l"]));
+}
+
+#[tokio::test]
+async fn bcast_prefixes() {
+    let addr = start().await;
+    let mut reader = resp3(addr).await;
+    let mut writer = Client::connect(addr).await;
+    assert_eq!(
+        reader
+            .run(&["CLIENT", "TRACKING", "ON", "BCAST", "PREFIX", "user:", "PREFIX", "job:"])
+            .await,
+        simple("OK")
+    );
+    // nothing needs to be read first
+    writer.run(&["SET", "other", "1"]).await;
+    writer.run(&["SET", "user:1", "1"]).await;
+    assert_eq!(reader.reply().await, invalidate(&["user:1"]));
+    writer
+        .run(&["MSET", "job:2", "1", "x", "1", "user:3", "1"])
+        .await;
+    assert_eq!(reader.reply().await, invalidate(&["job:2", "user:3"]));
+    // every key without a prefix
+    let mut all = resp3(addr).await;
+    all.run(&["CLIENT", "TRACKING", "ON", "BCAST"]).await;
+    writer.run(&["SET", "anything", "1"]).await;
+    assert_eq!(all.reply().await, invalidate(&["anything"]));
+}
+
+#[tokio::test]
+async fn redirect_to_a_resp2_subscriber() {
+    let addr = start().await;
+    let mut listener = Client::connect(addr).await;
+    let mut reader = Client::connect(addr).await;
+    let mut writer = Client::connect(addr).await;
+    let target = id(&mut listener).await;
+    listener.run(&["SUBSCRIBE", "__redis__:invalidate"]).await;
+    assert_eq!(
+        reader
+            .run(&["CLIENT", "TRACKING", "ON", "REDIRECT", &target])
+            .await,
+        simple("OK")
+    );
+    reader.run(&["GET", "k"]).await;
+    writer.run(&["SET", "k", "v"]).await;
+    assert_eq!(
+        listener.reply().await,
+        RedirsValue::Array(Some(vec![
+            bulk("message"),
+            bulk("__redis__:invalidate"),
+            array(&["k"]),
+        ]))
+    );
+    // the reader itself is told nothing
+    assert_eq!(reader.run(&["PING"]).await, simple("PONG"));
+}
+
+#[tokio::test]
+async fn flushes_invalidate_everything() {
+    let addr = start().await;
+    let mut reader = resp3(addr).await;
+    let mut writer = Client::connect(addr).await;
+    writer.run(&["SET", "k", "v"]).await;
+    reader.run(&["CLIENT", "TRACKING", "ON"]).await;
+    reader.run(&["GET", "k"]).await;
+    writer.run(&["FLUSHALL"]).await;
+    assert_eq!(
+        reader.reply().await,
+        RedirsValue::Push(vec![RedirsValue::from("invalidate"), RedirsValue::Null])
+    );
+}
+
+#[tokio::test]
+async fn the_table_is_bounded() {
+    let db = Db::new();
+    db.set_tracking_max_keys(2);
+    let addr = start_with(db).await;
+    let mut reader = resp3(addr).await;
+    reader.run(&["CLIENT", "TRACKING", "ON"]).await;
+    reader.run(&["GET", "a"]).await;
+    reader.run(&["GET", "b"]).await;
+    // a third key makes one of those go, its reader told as if it changed
+    reader.send(b"*2\r\n$3\r\nGET\r\n$1\r\nc\r\n").await;
+    let RedirsValue::Push(push) = reader.reply().await else {
+        panic!("expected an invalidation");
+    };
+    assert_eq!(push[0], RedirsValue::from("invalidate"));
+    let RedirsValue::Array(Some(keys)) = &push[1] else {
+        panic!("expected the evicted key");
+    };
+    assert_eq!(keys.len(), 1);
+    assert!(["a", "b", "c"]
+        .iter()
+        .any(|key| keys[0] == RedirsValue::from(*key)));
+    assert_eq!(reader.reply().await, RedirsValue::Null);
+}
+
+#[tokio::test]
+async fn off_stops_the_pushes() {
+    let addr = start().await;
+    let mut reader = resp3(addr).await;
+    let mut writer = Client::connect(addr).await;
+    reader.run(&["CLIENT", "TRACKING", "ON"]).await;
+    reader.run(&["GET", "k"]).await;
+    assert_eq!(
+        reader.run(&["CLIENT", "TRACKING", "OFF"]).await,
+        simple("OK")
+    );
+    writer.run(&["SET", "k", "v"]).await;
+    assert_eq!(reader.run(&["PING"]).await, simple("PONG"));
+}
+
+#[tokio::test]
+async fn tracking_errors() {
+    let mut client = resp3(start().await).await;
+    assert_eq!(
+        client
+            .run(&["CLIENT", "TRACKING", "ON", "PREFIX", "a"])
+            .await,
+        error("ERR PREFIX option requires BCAST mode to be enabled")
+    );
+    assert_eq!(
+        client
+            .run(&["CLIENT", "TRACKING", "ON", "REDIRECT", "999999"])
+            .await,
+        error("ERR The client ID you want redirect to does not exist")
+    );
+    for args in [
+        &["CLIENT", "TRACKING", "MAYBE"][..],
+        &["CLIENT", "TRACKING", "ON", "PREFIX"],
+        &["CLIENT", "TRACKING", "ON", "NOSUCH"],
+    ] {
+        assert_eq!(client.run(args).await, error("ERR syntax error"));
+    }
+    client.run(&["CLIENT", "TRACKING", "ON"]).await;
+    assert_eq!(
+        client.run(&["CLIENT", "TRACKING", "ON", "BCAST"]).await,
+        error(
+            "ERR You can't switch BCAST mode on/off before disabling tracking for this client, \
+             and then re-enabling it with a different mode."
+        )
+    );
+    assert_eq!(
+        client.run(&["CLIENT", "TRACKING"]).await,
+        error("ERR wrong number of arguments for 'client|tracking' command")
+    );
+}
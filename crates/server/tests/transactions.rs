@@ -0,0 +1,196 @@
+mod common;
+
+use common::{array, bulk, error, int, nil, simple, start, Client};
+use protocol::RedirsValue;
+
+fn queued() -> RedirsValue {
+    simple("QUEUED")
+}
+
+#[tokio::test]
+async fn exec_runs_the_queued_commands() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(client.run(&["MULTI"]).await, simple("OK"));
+    assert_eq!(client.run(&["SET", "k", "1"]).await, queued());
+    assert_eq!(client.run(&["INCR", "k"]).await, queued());
+    assert_eq!(client.run(&["LPUSH", "k", "x"]).await, queued());
+    assert_eq!(client.run(&["GET", "k"]).await, queued());
+    // errors while running are replies like any other
+    assert_eq!(
+        client.run(&["EXEC"]).await,
+        RedirsValue::Array(Some(vec![
+            simple("OK"),
+            int(2),
+            error("WRONGTYPE Operation against a key holding the wrong kind of value"),
+            bulk("2"),
+        ]))
+    );
+    assert_eq!(client.run(&["MULTI"]).await, simple("OK"));
+    assert_eq!(client.run(&["EXEC"]).await, array(&[]));
+    assert_eq!(client.run(&["GET", "k"]).await, bulk("2"));
+}
+
+#[tokio::test]
+async fn multi_exec_and_discard_are_checked() {
+    let mut client = Client::connect(start().await).await;
+    assert_eq!(client.run(&["EXEC"]).await, error("ERR EXEC without MULTI"));
+    assert_eq!(
+        client.run(&["DISCARD"]).await,
+        error("ERR DISCARD without MULTI")
+    );
+    client.run(&["MULTI"]).await;
+    assert_eq!(
+        client.run(&["MULTI"]).await,
+        error("ERR MULTI calls can not be nested")
+    );
+    assert_eq!(
+        client.run(&["WATCH", "k"]).await,
+        error("ERR WATCH inside MULTI is not allowed")
+    );
+    assert_eq!(client.run(&["SET", "k", "v"]).await, queued());
+    assert_eq!(client.run(&["DISCARD"]).await, simple("OK"));
+    assert_eq!(client.run(&["GET", "k"]).await, nil());
+}
+
+#[tokio::test]
+async fn a_command_that_cannot_queue_aborts_the_transaction() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["MULTI"]).await;
+    assert_eq!(client.run(&["SET", "k", "v"]).await, queued());
+    assert_eq!(
+        client.run(&["LPUSH", "l"]).await,
+        error("ERR wrong number of arguments for 'lpush' command")
+    );
+    assert_eq!(
+        client.run(&["NOPE"]).await,
+        error("ERR unknown command 'NOPE', with args beginning with: ")
+    );
+    assert_eq!(
+        client.run(&["EXEC"]).await,
+        error("EXECABORT Transaction discarded because of previous errors.")
+    );
+    assert_eq!(client.run(&["GET", "k"]).await, nil());
+    // the connection is out of the transaction
+    assert_eq!(client.run(&["SET", "k", "v"]).await, simple("OK"));
+}
+
+#[tokio::test]
+async fn blocking_commands_in_a_transaction_do_not_wait() {
+    let mut client = Client::connect(start().await).await;
+    client.run(&["MULTI"]).await;
+    client.run(&["BLPOP", "missing", "0"]).await;
+    client.run(&["RPUSH", "l", "x"]).await;
+    client.run(&["BLPOP", "l", "0"]).await;
+    assert_eq!(
+        client.run(&["EXEC"]).await,
+        RedirsValue::Array(Some(vec![
+            RedirsValue::Array(None),
+            int(1),
+            array(&["l", "x"]),
+        ]))
+    );
+}
+
+#[tokio::test]
+async fn a_write_to_a_watched_key_fails_exec() {
+    let addr = start().await;
+    let mut first = Client::connect(addr).await;
+    let mut second = Client::connect(addr).await;
+    first.run(&["SET", "k", "1"]).await;
+    assert_eq!(first.run(&["WATCH", "k", "other"]).await, simple("OK"));
+    assert_eq!(second.run(&["WATCH", "k"]).await, simple("OK"));
+    first.run(&["MULTI"]).await;
+    first.run(&["INCR", "k"]).await;
+    second.run(&["MULTI"]).await;
+    second.run(&["INCR", "k"]).await;
+    // the first EXEC writes the key, failing the second
+    assert_eq!(
+        first.run(&["EXEC"]).await,
+        RedirsValue::Array(Some(vec![int(2)]))
+    );
+    assert_eq!(second.run(&["EXEC"]).await, RedirsValue::Array(None));
+    assert_eq!(first.run(&["GET", "k"]).await, bulk("2"));
+
+    // EXEC forgets the watched keys
+    first.run(&["MULTI"]).await;
+    first.run(&["INCR", "k"]).await;
+    second.run(&["SET", "k", "10"]).await;
+    assert_eq!(
+        first.run(&["EXEC"]).await,
+        RedirsValue::Array(Some(vec![int(11)]))
+    );
+}
+
+#[tokio::test]
+async fn unwatch_and_discard_forget_the_watched_keys() {
+    let addr = start().await;
+    let mut client = Client::connect(addr).await;
+    let mut other = Client::connect(addr).await;
+    client.run(&["WATCH", "k"]).await;
+    assert_eq!(client.run(&["UNWATCH"]).await, simple("OK"));
+    other.run(&["SET", "k", "v"]).await;
+    client.run(&["MULTI"]).await;
+    client.run(&["GET", "k"]).await;
+    assert_eq!(
+        client.run(&["EXEC"]).await,
+        RedirsValue::Array(Some(vec![bulk("v")]))
+    );
+
+    client.run(&["WATCH", "k"]).await;
+    client.run(&["MULTI"]).await;
+    client.run(&["DISCARD"]).await;
+    other.run(&["SET", "k", "w"]).await;
+    client.run(&["MULTI"]).await;
+    client.run(&["GET", "k"]).await;
+    assert_eq!(
+        client.run(&["EXEC"]).await,
+        RedirsValue::Array(Some(vec![bulk("w")]))
+    );
+
+    // deleting and expiring are writes, reading is not
+    client.run(&["WATCH", "k"]).await;
+    other.run(&["GET", "k"]).await;
+    other.run(&["DEL", "k"]).await;
+    client.run(&["MULTI"]).await;
+    assert_eq!(client.run(&["EXEC"]).await, RedirsValue::Array(None));
+    client.run(&["WATCH", "k"]).await;
+    other.run(&["GET", "k"]).await;
+    client.run(&["MULTI"]).await;
+    assert_eq!(client.run(&["EXEC"]).await, array(&[]));
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+async fn racing_transactions_on_a_watched_key_lose_no_update() {
+    let addr = start().await;
+    let mut tasks = Vec::new();
+    for _ in 0..8 {
+        tasks.push(tokio::spawn(async move {
+            let mut client = Client::connect(addr).await;
+            let mut committed = 0;
+            // read, then write only if nobody wrote in between, retrying
+            // until it goes through
+            while committed < 25 {
+                client.run(&["WATCH", "counter"]).await;
+                let value = match client.run(&["GET", "counter"]).await {
+                    RedirsValue::BulkString(Some(value)) => String::from_utf8(value.to_vec())
+                        .unwrap()
+                        .parse::<i64>()
+                        .unwrap(),
+                    _ => 0,
+                };
+                client.run(&["MULTI"]).await;
+                client
+                    .run(&["SET", "counter", &(value + 1).to_string()])
+                    .await;
+                if client.run(&["EXEC"]).await != RedirsValue::Array(None) {
+                    committed += 1;
+                }
+            }
+        }));
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    let mut client = Client::connect(addr).await;
+    assert_eq!(client.run(&["GET", "counter"]).await, bulk("200"));
+}